@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS reconciliation_batch_jobs;
+DROP TABLE IF EXISTS reconciliation_batches;
@@ -0,0 +1,29 @@
+-- Batches group reconciliation jobs that are run and reported on together,
+-- e.g. one job per bank account for a month-end close
+CREATE TABLE reconciliation_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    execution_mode VARCHAR(20) NOT NULL DEFAULT 'sequential'
+        CHECK (execution_mode IN ('sequential', 'parallel')),
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    created_by UUID NOT NULL REFERENCES users(id),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_reconciliation_batches_project_id ON reconciliation_batches(project_id);
+
+CREATE TABLE reconciliation_batch_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES reconciliation_batches(id) ON DELETE CASCADE,
+    job_id UUID NOT NULL REFERENCES reconciliation_jobs(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, job_id)
+);
+
+CREATE INDEX idx_reconciliation_batch_jobs_batch_id ON reconciliation_batch_jobs(batch_id, position);
@@ -0,0 +1,26 @@
+-- Durable queue of reconciliation job runs, shared by every backend replica.
+-- Workers claim rows with SELECT ... FOR UPDATE SKIP LOCKED and hold them
+-- under a lease renewed by heartbeats; an expired lease can be claimed again.
+CREATE TABLE reconciliation_job_queue (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_id UUID NOT NULL UNIQUE REFERENCES reconciliation_jobs(id) ON DELETE CASCADE,
+    state VARCHAR(20) NOT NULL DEFAULT 'queued'
+        CHECK (state IN ('queued', 'leased', 'completed', 'cancelled', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    -- Earliest time the entry may be claimed; pushed back between retries
+    available_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    leased_by VARCHAR(255),
+    lease_expires_at TIMESTAMPTZ,
+    heartbeat_at TIMESTAMPTZ,
+    last_error TEXT,
+    -- Progress of the last run, used to resume after a restart or retry
+    checkpoint JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_reconciliation_job_queue_available
+    ON reconciliation_job_queue(available_at) WHERE state = 'queued';
+CREATE INDEX idx_reconciliation_job_queue_lease
+    ON reconciliation_job_queue(lease_expires_at) WHERE state = 'leased';
@@ -0,0 +1,24 @@
+-- Opening and closing balances of the bank statements read into a data
+-- source. One file can hold several statements (accounts or days), and the
+-- rows are replaced whenever the data source is ingested again.
+CREATE TABLE data_source_statements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    data_source_id UUID NOT NULL REFERENCES data_sources(id) ON DELETE CASCADE,
+    ingestion_job_id UUID NOT NULL REFERENCES ingestion_jobs(id) ON DELETE CASCADE,
+    -- Position of the statement in the file
+    sequence INTEGER NOT NULL,
+    format VARCHAR(20) NOT NULL,
+    statement_id VARCHAR(255),
+    account VARCHAR(255),
+    currency VARCHAR(3),
+    opening_balance NUMERIC(20, 4),
+    opening_date DATE,
+    closing_balance NUMERIC(20, 4),
+    closing_date DATE,
+    entry_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_data_source_statements_data_source
+    ON data_source_statements(data_source_id, sequence);
+CREATE INDEX idx_data_source_statements_job ON data_source_statements(ingestion_job_id);
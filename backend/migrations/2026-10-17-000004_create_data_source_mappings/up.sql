@@ -0,0 +1,12 @@
+-- Saved transformation specs of a data source. Saving a mapping adds a new
+-- version rather than changing the old one, so a job can be re-run with the
+-- mapping it was ingested with.
+CREATE TABLE data_source_mappings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    data_source_id UUID NOT NULL REFERENCES data_sources(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    spec JSONB NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (data_source_id, version)
+);
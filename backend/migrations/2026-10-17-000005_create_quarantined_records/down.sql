@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_reconciliation_records_dedup_key;
+DROP TABLE IF EXISTS quarantined_records;
@@ -0,0 +1,28 @@
+-- Records held back from ingestion as duplicates, until someone releases them
+-- into reconciliation_records or discards them. The record's columns are kept
+-- as they would have been inserted.
+CREATE TABLE quarantined_records (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    ingestion_job_id UUID NOT NULL REFERENCES ingestion_jobs(id) ON DELETE CASCADE,
+    -- File row the record was read from
+    record_index INTEGER,
+    dedup_key VARCHAR(64) NOT NULL,
+    -- 'in_file' for a repeat of an earlier row of the same file,
+    -- 'existing_record' for a repeat of an already ingested record
+    reason VARCHAR(50) NOT NULL,
+    duplicate_of_record_id UUID REFERENCES reconciliation_records(id) ON DELETE SET NULL,
+    duplicate_of_row INTEGER,
+    record_data JSONB NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_quarantined_records_job ON quarantined_records (ingestion_job_id, status);
+
+-- Duplicate keys of ingested records are kept in their audit trail
+CREATE INDEX idx_reconciliation_records_dedup_key
+    ON reconciliation_records (project_id, (audit_trail->>'dedup_key'))
+    WHERE audit_trail ? 'dedup_key';
@@ -0,0 +1,35 @@
+-- Outgoing email, written before delivery so that a crash or an SMTP outage
+-- doesn't lose it. Workers claim rows with SELECT ... FOR UPDATE SKIP LOCKED;
+-- failed deliveries are retried with backoff until max_attempts, and addresses
+-- that bounced are not mailed again.
+CREATE TABLE email_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Lowercased, so bounced addresses match case-insensitively
+    recipient VARCHAR(255) NOT NULL,
+    subject TEXT NOT NULL,
+    html_body TEXT,
+    text_body TEXT NOT NULL,
+    -- Template and locale the body was rendered from, if any
+    template VARCHAR(100),
+    locale VARCHAR(20),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'sending', 'sent', 'failed', 'bounced', 'suppressed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 6,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- A 'sending' row whose lock ran out is claimed again
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    -- Message-ID header, for matching bounce reports
+    message_id VARCHAR(255),
+    correlation_id VARCHAR(255),
+    sent_at TIMESTAMPTZ,
+    bounced_at TIMESTAMPTZ,
+    bounce_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_outbox_due ON email_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_email_outbox_recipient ON email_outbox(recipient) WHERE status = 'bounced';
+CREATE UNIQUE INDEX idx_email_outbox_message_id ON email_outbox(message_id) WHERE message_id IS NOT NULL;
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_audit_logs_resource;
+DROP INDEX IF EXISTS idx_audit_logs_sequence;
+ALTER TABLE audit_logs
+    DROP COLUMN IF EXISTS entry_hash,
+    DROP COLUMN IF EXISTS prev_hash,
+    DROP COLUMN IF EXISTS correlation_id,
+    DROP COLUMN IF EXISTS actor_id,
+    DROP COLUMN IF EXISTS sequence;
@@ -0,0 +1,16 @@
+-- Hash chain over audit_logs. Each entry stores the hash of the previous
+-- entry and a SHA-256 over its own fields and that hash, in sequence order,
+-- so that editing, deleting or reordering entries breaks the chain from that
+-- point on. Entries written before this migration have no sequence and are
+-- outside the chain.
+ALTER TABLE audit_logs
+    ADD COLUMN sequence BIGINT,
+    -- Actor as recorded; unlike user_id it has no foreign key, so deleting a
+    -- user doesn't rewrite (and so break) the chained entry
+    ADD COLUMN actor_id UUID,
+    ADD COLUMN correlation_id VARCHAR(255),
+    ADD COLUMN prev_hash VARCHAR(64),
+    ADD COLUMN entry_hash VARCHAR(64);
+
+CREATE UNIQUE INDEX idx_audit_logs_sequence ON audit_logs(sequence) WHERE sequence IS NOT NULL;
+CREATE INDEX idx_audit_logs_resource ON audit_logs(resource_type, resource_id, created_at);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_team_members_user;
+DROP INDEX IF EXISTS idx_project_members_project_user;
+DROP TABLE IF EXISTS project_teams;
@@ -0,0 +1,20 @@
+-- Teams granted a role in a project; every active member of an active team
+-- holds the team's role in the project, alongside any direct membership in
+-- project_members. Roles: owner, approver, reviewer, preparer, viewer.
+CREATE TABLE project_teams (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL
+        CHECK (role IN ('owner', 'approver', 'reviewer', 'preparer', 'viewer')),
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (project_id, team_id)
+);
+
+CREATE INDEX idx_project_teams_team ON project_teams(team_id);
+
+-- Role lookups on every request filter members by project and user
+CREATE INDEX IF NOT EXISTS idx_project_members_project_user ON project_members(project_id, user_id);
+CREATE INDEX IF NOT EXISTS idx_team_members_user ON team_members(user_id);
@@ -0,0 +1,35 @@
+-- Four-eyes approval of match decisions. When a project's approval policy
+-- (projects.settings.approval_policy) requires it, approving a match leaves
+-- the result in 'pending_approval' and records the proposed change here
+-- until a second user, other than the preparer, approves or rejects it.
+CREATE TABLE match_approvals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    result_id UUID NOT NULL REFERENCES reconciliation_results(id) ON DELETE CASCADE,
+    job_id UUID NOT NULL REFERENCES reconciliation_jobs(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected')),
+    -- Policy conditions that required approval: 'all_matches', 'manual_match', 'variance'
+    reasons JSONB NOT NULL DEFAULT '[]',
+    -- Change applied to the result when approved: status, record_b_id, match_type,
+    -- confidence_score, notes
+    proposed JSONB NOT NULL,
+    -- Result status restored when rejected
+    previous_status VARCHAR(50),
+    -- Absolute difference between the matched records' amounts
+    variance DOUBLE PRECISION,
+    prepared_by UUID NOT NULL REFERENCES users(id),
+    prepared_notes TEXT,
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMPTZ,
+    -- Approver's notes; the reason when rejected
+    decision_notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT match_approvals_separate_approver CHECK (decided_by IS NULL OR decided_by <> prepared_by),
+    CONSTRAINT match_approvals_rejection_reason CHECK (status <> 'rejected' OR decision_notes IS NOT NULL)
+);
+
+-- At most one open request per result
+CREATE UNIQUE INDEX idx_match_approvals_open ON match_approvals (result_id) WHERE status = 'pending';
+CREATE INDEX idx_match_approvals_queue ON match_approvals (project_id, status, created_at);
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_api_keys_user;
+DROP INDEX IF EXISTS idx_api_keys_key_hash;
+ALTER TABLE api_keys
+    DROP COLUMN IF EXISTS revoked_by,
+    DROP COLUMN IF EXISTS revoked_at,
+    DROP COLUMN IF EXISTS last_used_ip,
+    DROP COLUMN IF EXISTS created_by,
+    DROP COLUMN IF EXISTS project_ids,
+    DROP COLUMN IF EXISTS kind;
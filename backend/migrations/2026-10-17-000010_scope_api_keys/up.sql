@@ -0,0 +1,16 @@
+-- Personal and service API keys. A key acts as its user (a service account
+-- for service keys) within its scopes, which are kept in `permissions` as a
+-- JSON array such as ["ingestion:write", "reconciliation:read"], and, when
+-- project_ids is set, only in those projects. Only a SHA-256 of the key is
+-- stored; key_prefix identifies it in listings.
+ALTER TABLE api_keys
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'personal'
+        CHECK (kind IN ('personal', 'service')),
+    ADD COLUMN project_ids JSONB,
+    ADD COLUMN created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN last_used_ip VARCHAR(45),
+    ADD COLUMN revoked_at TIMESTAMPTZ,
+    ADD COLUMN revoked_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_api_keys_key_hash ON api_keys(key_hash);
+CREATE INDEX IF NOT EXISTS idx_api_keys_user ON api_keys(user_id, created_at);
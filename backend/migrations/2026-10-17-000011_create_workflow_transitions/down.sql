@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS workflow_transitions;
+DROP INDEX IF EXISTS idx_workflow_instances_waiting_for;
+ALTER TABLE workflow_instances DROP COLUMN IF EXISTS waiting_for;
@@ -0,0 +1,24 @@
+-- Workflow execution. Every change of an instance's step or status is
+-- recorded in workflow_transitions. An instance paused on something outside
+-- the engine (a reconciliation job, an approval) names it in waiting_for,
+-- e.g. 'job:<uuid>' or 'approval:<step>'.
+ALTER TABLE workflow_instances ADD COLUMN waiting_for VARCHAR(255);
+
+CREATE INDEX IF NOT EXISTS idx_workflow_instances_waiting_for
+    ON workflow_instances(waiting_for) WHERE waiting_for IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS workflow_transitions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    instance_id UUID NOT NULL REFERENCES workflow_instances(id) ON DELETE CASCADE,
+    from_step VARCHAR(255),
+    to_step VARCHAR(255),
+    from_status VARCHAR(50) NOT NULL,
+    to_status VARCHAR(50) NOT NULL,
+    reason VARCHAR(100) NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_workflow_transitions_instance
+    ON workflow_transitions(instance_id, created_at);
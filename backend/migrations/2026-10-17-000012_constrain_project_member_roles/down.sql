@@ -0,0 +1,2 @@
+-- Roles mapped by up.sql are not restored; only the constraint is dropped
+ALTER TABLE project_members DROP CONSTRAINT IF EXISTS project_members_role_check;
@@ -0,0 +1,23 @@
+-- Direct project memberships may still hold roles from before project roles
+-- were introduced. Map them onto the current roles, so no member loses
+-- access when unknown names stop being accepted, then constrain the column
+-- like project_teams.role. Anything unrecognised falls back to viewer.
+UPDATE project_members
+SET role = CASE lower(trim(role))
+        WHEN 'owner' THEN 'owner'
+        WHEN 'admin' THEN 'owner'
+        WHEN 'approver' THEN 'approver'
+        WHEN 'manager' THEN 'approver'
+        WHEN 'reviewer' THEN 'reviewer'
+        WHEN 'preparer' THEN 'preparer'
+        WHEN 'editor' THEN 'preparer'
+        WHEN 'member' THEN 'preparer'
+        WHEN 'user' THEN 'preparer'
+        ELSE 'viewer'
+    END
+WHERE role IS NULL
+   OR role NOT IN ('owner', 'approver', 'reviewer', 'preparer', 'viewer');
+
+ALTER TABLE project_members
+    ADD CONSTRAINT project_members_role_check
+        CHECK (role IN ('owner', 'approver', 'reviewer', 'preparer', 'viewer'));
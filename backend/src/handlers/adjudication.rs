@@ -16,8 +16,10 @@ use crate::handlers::types::{
         CreateDecisionRequest, UpdateDecisionRequest, AppealDecisionRequest,
     },
 };
+use crate::services::auth::ProjectAction;
 use crate::services::cache::MultiLevelCache;
 use crate::services::adjudication::AdjudicationService;
+use crate::utils::{check_admin_permission, check_project_action, check_project_permission};
 use crate::models::{NewAdjudicationCase, NewAdjudicationDecision, NewAdjudicationWorkflow, UpdateAdjudicationCase, UpdateAdjudicationDecision, UpdateAdjudicationWorkflow};
 use std::sync::Arc;
 
@@ -98,14 +100,13 @@ pub struct Decision {
 )]
 pub async fn list_cases(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
-    // project_id would come from query params if needed
-    let project_id = None;
+    let project_id = listed_project(data.get_ref(), &http_req, &query)?;
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
     let (cases, total) = adjudication_service.list_cases(project_id, page, per_page).await?;
@@ -148,6 +149,7 @@ pub async fn create_case(
     let project_id = req
         .project_id
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let case_number = format!("CASE-{}", Uuid::new_v4());
     
     let new_case = NewAdjudicationCase {
@@ -192,12 +194,14 @@ pub async fn create_case(
 )]
 pub async fn get_case(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let case_id = path.into_inner();
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
     let case = adjudication_service.get_case(case_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, case.project_id)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -211,7 +215,7 @@ pub async fn get_case(
 pub async fn update_case(
     path: web::Path<Uuid>,
     req: web::Json<UpdateCaseRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let case_id = path.into_inner();
@@ -225,6 +229,9 @@ pub async fn update_case(
     };
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let case = adjudication_service.update_case(case_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -238,11 +245,14 @@ pub async fn update_case(
 /// Delete case
 pub async fn delete_case(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let case_id = path.into_inner();
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     adjudication_service.delete_case(case_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -251,7 +261,7 @@ pub async fn delete_case(
 pub async fn assign_case(
     path: web::Path<Uuid>,
     req: web::Json<AssignCaseRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
@@ -259,6 +269,9 @@ pub async fn assign_case(
     let assigned_to = req.user_id;
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let case = adjudication_service.assign_case(case_id, assigned_to).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -281,6 +294,8 @@ pub async fn resolve_case(
     let notes = req.notes.clone();
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
     let case = adjudication_service.resolve_case(case_id, user_id, notes).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -294,13 +309,13 @@ pub async fn resolve_case(
 /// List workflows
 pub async fn list_workflows(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
-    let project_id = None; // Could come from query params
+    let project_id = listed_project(data.get_ref(), &http_req, &query)?;
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
     let (workflows, total) = adjudication_service.list_workflows(project_id, page, per_page).await?;
@@ -326,6 +341,7 @@ pub async fn create_workflow(
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
     let user_id = extract_user_id(&http_req)?;
+    check_workflow_action(data.get_ref(), user_id, req.project_id, ProjectAction::Manage)?;
     let new_workflow = NewAdjudicationWorkflow {
         project_id: req.project_id,
         name: req.name.clone(),
@@ -349,12 +365,14 @@ pub async fn create_workflow(
 /// Get workflow
 pub async fn get_workflow(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
     let workflow = adjudication_service.get_workflow(workflow_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    check_workflow_action(data.get_ref(), user_id, workflow.project_id, ProjectAction::View)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -368,7 +386,7 @@ pub async fn get_workflow(
 pub async fn update_workflow(
     path: web::Path<Uuid>,
     req: web::Json<UpdateAdjudicationWorkflowRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
@@ -380,6 +398,9 @@ pub async fn update_workflow(
     };
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = adjudication_service.get_workflow(workflow_id).await?.project_id;
+    check_workflow_action(data.get_ref(), user_id, project_id, ProjectAction::Manage)?;
     let workflow = adjudication_service.update_workflow(workflow_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -393,11 +414,14 @@ pub async fn update_workflow(
 /// Delete workflow
 pub async fn delete_workflow(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = adjudication_service.get_workflow(workflow_id).await?.project_id;
+    check_workflow_action(data.get_ref(), user_id, project_id, ProjectAction::Manage)?;
     adjudication_service.delete_workflow(workflow_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -405,16 +429,25 @@ pub async fn delete_workflow(
 /// List decisions
 pub async fn list_decisions(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    filter: web::Query<DecisionFilter>,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
-    // case_id would come from query params if needed
-    let case_id = None;
+    let case_id = filter.case_id;
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    // Decisions across all cases are for admins; others list one case's
+    match case_id {
+        Some(case_id) => {
+            let project_id = adjudication_service.get_case(case_id).await?.project_id;
+            check_project_permission(data.get_ref(), user_id, project_id)?;
+        }
+        None => check_admin_permission(data.get_ref(), user_id)?,
+    }
     let (decisions, total) = adjudication_service.list_decisions(case_id, page, per_page).await?;
     
     let total_pages = (total as f64 / per_page as f64).ceil() as i32;
@@ -450,6 +483,8 @@ pub async fn create_decision(
     };
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let project_id = adjudication_service.get_case(req.case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
     let decision = adjudication_service.create_decision(new_decision).await?;
     
     Ok(HttpResponse::Created().json(ApiResponse {
@@ -463,11 +498,15 @@ pub async fn create_decision(
 /// Get decision
 pub async fn get_decision(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let decision_id = path.into_inner();
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let case_id = adjudication_service.get_decision(decision_id).await?.case_id;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
     let decision = adjudication_service.get_decision(decision_id).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -482,7 +521,7 @@ pub async fn get_decision(
 pub async fn update_decision(
     path: web::Path<Uuid>,
     req: web::Json<UpdateDecisionRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let decision_id = path.into_inner();
@@ -499,6 +538,10 @@ pub async fn update_decision(
     };
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let case_id = adjudication_service.get_decision(decision_id).await?.case_id;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
     let decision = adjudication_service.update_decision(decision_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -513,7 +556,7 @@ pub async fn update_decision(
 pub async fn appeal_decision(
     path: web::Path<Uuid>,
     req: web::Json<AppealDecisionRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
@@ -521,6 +564,10 @@ pub async fn appeal_decision(
     let reason = req.reason.clone();
     
     let adjudication_service = AdjudicationService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let case_id = adjudication_service.get_decision(decision_id).await?.case_id;
+    let project_id = adjudication_service.get_case(case_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let decision = adjudication_service.appeal_decision(decision_id, reason).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -531,6 +578,50 @@ pub async fn appeal_decision(
     }))
 }
 
+/// Filter for listing decisions
+#[derive(Debug, Deserialize)]
+pub struct DecisionFilter {
+    pub case_id: Option<Uuid>,
+}
+
+/// Project a case or workflow listing is limited to
+///
+/// Admins may list across all projects; everyone else must name a project
+/// they can view.
+fn listed_project(
+    db: &Database,
+    http_req: &HttpRequest,
+    query: &SearchQueryParams,
+) -> Result<Option<Uuid>, AppError> {
+    let user_id = extract_user_id(http_req)?;
+    let project_id = query
+        .project_id
+        .as_deref()
+        .map(|s| Uuid::parse_str(s).map_err(|_| AppError::Validation("Invalid project_id".to_string())))
+        .transpose()?;
+    match project_id {
+        Some(project_id) => check_project_permission(db, user_id, project_id)?,
+        None if crate::utils::accessible_project_ids(db, user_id)?.is_none() => {}
+        None => return Err(AppError::Validation("project_id is required".to_string())),
+    }
+    Ok(project_id)
+}
+
+/// Check `action` on a workflow; workflows without a project are shared by
+/// all projects, so anyone may view them but only admins may change them
+fn check_workflow_action(
+    db: &Database,
+    user_id: Uuid,
+    project_id: Option<Uuid>,
+    action: ProjectAction,
+) -> Result<(), AppError> {
+    match project_id {
+        Some(project_id) => check_project_action(db, user_id, project_id, action),
+        None if action == ProjectAction::View => Ok(()),
+        None => check_admin_permission(db, user_id),
+    }
+}
+
 fn build_metadata_with_rationale(
     metadata: Option<serde_json::Value>,
     rationale: Option<String>,
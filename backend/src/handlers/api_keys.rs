@@ -0,0 +1,175 @@
+//! API key handlers
+//!
+//! Issue, list and revoke personal and service API keys. Keys can't manage
+//! keys: these routes are outside every key's scopes.
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::handlers::helpers::extract_user_id;
+use crate::handlers::types::ApiResponse;
+use crate::services::audit::{mark_audited, AuditContext};
+use crate::services::auth::{ApiKeyService, CreateApiKey};
+use crate::utils::check_admin_permission;
+
+/// Configure API key routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(list_api_keys))
+        .route("", web::post().to(create_api_key))
+        .route("/{key_id}", web::get().to(get_api_key))
+        .route("/{key_id}", web::delete().to(revoke_api_key));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyListQuery {
+    /// Keys of this user; admins only. Defaults to the caller's keys.
+    pub user_id: Option<Uuid>,
+    /// Every key; admins only
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// List API keys the caller owns or created
+#[utoipa::path(
+    get,
+    path = "/api/v1/api-keys",
+    tag = "API Keys",
+    params(
+        ("user_id" = Option<Uuid>, Query, description = "Keys of this user (admins only)"),
+        ("all" = Option<bool>, Query, description = "Every key (admins only)")
+    ),
+    responses(
+        (status = 200, description = "API keys, newest first", body = ApiResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_api_keys(
+    query: web::Query<ApiKeyListQuery>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let owner = match (query.all, query.user_id) {
+        (false, None) => Some(user_id),
+        (false, Some(owner)) if owner == user_id => Some(user_id),
+        (all, owner) => {
+            check_admin_permission(data.get_ref(), user_id)?;
+            if all {
+                None
+            } else {
+                owner
+            }
+        }
+    };
+
+    let keys = ApiKeyService::new(data.get_ref().clone()).list(owner)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(keys),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Issue an API key
+///
+/// The key is in the response only this once.
+#[utoipa::path(
+    post,
+    path = "/api/v1/api-keys",
+    tag = "API Keys",
+    responses(
+        (status = 201, description = "Key issued", body = ApiResponse),
+        (status = 400, description = "Invalid scopes, projects or expiry", body = ErrorResponse),
+        (status = 403, description = "Service keys require admin access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_api_key(
+    req: web::Json<CreateApiKey>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let ctx = AuditContext::from_request(&http_req).with_actor(user_id);
+
+    let created =
+        ApiKeyService::new(data.get_ref().clone()).create(&ctx, user_id, req.into_inner())?;
+    mark_audited(&http_req);
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(created),
+        message: Some("Store this key now; it can't be shown again".to_string()),
+        error: None,
+    }))
+}
+
+/// Get an API key
+#[utoipa::path(
+    get,
+    path = "/api/v1/api-keys/{key_id}",
+    tag = "API Keys",
+    params(("key_id" = Uuid, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "API key", body = ApiResponse),
+        (status = 404, description = "API key not found", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_api_key(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let key = ApiKeyService::new(data.get_ref().clone()).get(path.into_inner())?;
+    if key.user_id != user_id && key.created_by != Some(user_id) {
+        check_admin_permission(data.get_ref(), user_id)?;
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(key),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/api/v1/api-keys/{key_id}",
+    tag = "API Keys",
+    params(("key_id" = Uuid, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "Key revoked", body = ApiResponse),
+        (status = 403, description = "Not the key's user, creator or an admin", body = ErrorResponse),
+        (status = 409, description = "Key already revoked", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_api_key(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let ctx = AuditContext::from_request(&http_req).with_actor(user_id);
+
+    let key =
+        ApiKeyService::new(data.get_ref().clone()).revoke(&ctx, path.into_inner(), user_id)?;
+    mark_audited(&http_req);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(key),
+        message: Some("API key revoked".to_string()),
+        error: None,
+    }))
+}
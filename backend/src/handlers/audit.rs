@@ -0,0 +1,99 @@
+//! Audit trail handlers
+//!
+//! Admin-only access to the hash-chained audit trail.
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::handlers::helpers::extract_user_id;
+use crate::handlers::types::ApiResponse;
+use crate::services::audit::{AuditQuery, AuditTrail};
+use crate::utils::check_admin_permission;
+
+/// Configure audit routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(list_audit_entries))
+        .route("/verify", web::get().to(verify_audit_chain));
+}
+
+/// List audit entries
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit",
+    tag = "Audit",
+    params(
+        ("actor_id" = Option<Uuid>, Query, description = "Filter by actor"),
+        ("action" = Option<String>, Query, description = "Filter by action"),
+        ("resource_type" = Option<String>, Query, description = "Filter by entity type"),
+        ("resource_id" = Option<Uuid>, Query, description = "Filter by entity ID"),
+        ("correlation_id" = Option<String>, Query, description = "Filter by correlation ID"),
+        ("from" = Option<String>, Query, description = "Entries at or after this time (RFC 3339)"),
+        ("to" = Option<String>, Query, description = "Entries before this time (RFC 3339)"),
+        ("page" = Option<i64>, Query, description = "Page number (1-based)"),
+        ("per_page" = Option<i64>, Query, description = "Items per page (max 500)")
+    ),
+    responses(
+        (status = 200, description = "Audit entries, newest first", body = ApiResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_audit_entries(
+    query: web::Query<AuditQuery>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    check_admin_permission(data.get_ref(), user_id)?;
+
+    let entries = AuditTrail::new(Arc::new(data.get_ref().clone())).list(&query)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "entries": entries,
+            "count": entries.len(),
+        })),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Verify the audit hash chain
+///
+/// Recomputes every chained entry's hash and reports the first entry that was
+/// modified, removed or reordered, along with the current head hash.
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/verify",
+    tag = "Audit",
+    responses(
+        (status = 200, description = "Verification result", body = ApiResponse),
+        (status = 403, description = "Admin access required", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn verify_audit_chain(
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    check_admin_permission(data.get_ref(), user_id)?;
+
+    let verification = AuditTrail::new(Arc::new(data.get_ref().clone())).verify()?;
+    if !verification.valid {
+        log::error!(
+            "Audit chain verification failed: {:?}",
+            verification.first_break
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::to_value(&verification)?),
+        message: None,
+        error: None,
+    }))
+}
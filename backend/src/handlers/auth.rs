@@ -11,6 +11,7 @@ use crate::handlers::helpers::{get_client_ip, get_user_agent, mask_email};
 use crate::services::auth::{
     AuthService, ChangeInitialPasswordRequest, ChangePasswordRequest, GoogleOAuthRequest, LoginRequest, RegisterRequest,
 };
+use crate::services::email::EmailService;
 use crate::services::security_monitor::{
     SecurityEvent, SecurityEventType, SecurityMonitor, SecuritySeverity,
 };
@@ -569,17 +570,37 @@ pub async fn request_password_reset(
     data: web::Data<crate::database::Database>,
     auth_service: web::Data<Arc<AuthService>>,
     config: web::Data<crate::config::Config>,
+    email_service: web::Data<Arc<EmailService>>,
 ) -> Result<HttpResponse, AppError> {
     let enhanced_auth = crate::services::auth::EnhancedAuthService::new(
         config.jwt_secret.clone(),
         auth_service.as_ref().get_expiration(),
     );
 
-    let _reset_token = enhanced_auth
+    let reset_token = enhanced_auth
         .generate_password_reset_token(&req.email, &data)
         .await?;
 
-    // In production, the reset token is sent via email only and never returned in API responses
+    // The reset token is sent via email only and never returned in API responses
+    let (user_id, first_name) = {
+        use crate::models::schema::users;
+        use diesel::prelude::*;
+        let mut conn = data.get_connection()?;
+        users::table
+            .filter(users::email.eq(&req.email))
+            .select((users::id, users::first_name))
+            .first::<(Uuid, Option<String>)>(&mut conn)
+            .map_err(AppError::Database)?
+    };
+    let locale = user_locale(data.get_ref(), user_id).await;
+    email_service
+        .send_password_reset(
+            &req.email,
+            &reset_token,
+            first_name.as_deref().unwrap_or("there"),
+            locale.as_deref(),
+        )
+        .await?;
 
     Ok(
         HttpResponse::Ok().json(crate::handlers::types::ApiResponse::<serde_json::Value> {
@@ -673,6 +694,7 @@ pub async fn resend_verification(
     user_service: web::Data<Arc<UserService>>,
     auth_service: web::Data<Arc<AuthService>>,
     config: web::Data<crate::config::Config>,
+    email_service: web::Data<Arc<EmailService>>,
 ) -> Result<HttpResponse, AppError> {
     let enhanced_auth = crate::services::auth::EnhancedAuthService::new(
         config.jwt_secret.clone(),
@@ -687,19 +709,38 @@ pub async fn resend_verification(
         .generate_email_verification_token(user.id, &user.email, &data)
         .await?;
 
+    // The token is sent via email only
+    let locale = user_locale(data.get_ref(), user.id).await;
+    email_service
+        .send_email_verification(
+            &user.email,
+            &token,
+            user.first_name.as_deref().unwrap_or("there"),
+            locale.as_deref(),
+        )
+        .await?;
+
     Ok(
-        HttpResponse::Ok().json(crate::handlers::types::ApiResponse {
+        HttpResponse::Ok().json(crate::handlers::types::ApiResponse::<()> {
             success: true,
-            data: Some(serde_json::json!({
-                "message": "Verification token generated",
-                "token": token // Remove this in production - send via email
-            })),
+            data: None,
             message: Some("Verification email sent".to_string()),
             error: None,
         }),
     )
 }
 
+/// A user's preferred language for email, if they chose one
+async fn user_locale(db: &crate::database::Database, user_id: Uuid) -> Option<String> {
+    use crate::services::user::{PreferencesService, PreferencesServiceTrait};
+    PreferencesService::new(Arc::new(db.clone()))
+        .get_settings(user_id)
+        .await
+        .ok()
+        .map(|settings| settings.preferences.language)
+        .filter(|language| !language.is_empty())
+}
+
 /// Google OAuth endpoint
 pub async fn google_oauth(
     req: web::Json<GoogleOAuthRequest>,
@@ -17,8 +17,10 @@ use crate::handlers::types::{
         CreateDiscrepancyRequest, UpdateDiscrepancyRequest, ResolveDiscrepancyRequest,
     },
 };
+use crate::services::auth::ProjectAction;
 use crate::services::cache::MultiLevelCache;
 use crate::services::cashflow::CashflowService;
+use crate::utils::{check_project_action, check_project_permission};
 use crate::models::{CashflowCategory, NewCashflowCategory, NewCashflowTransaction, NewCashflowDiscrepancy, UpdateCashflowCategory, UpdateCashflowTransaction, UpdateCashflowDiscrepancy};
 use bigdecimal::{BigDecimal, FromPrimitive};
 use std::env;
@@ -83,9 +85,12 @@ pub struct Discrepancy {
 /// Get cashflow analysis
 pub async fn get_analysis(
     path: web::Path<uuid::Uuid>,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
@@ -123,10 +128,12 @@ pub async fn list_categories(
 /// Create category
 pub async fn create_category(
     req: web::Json<CreateCashflowCategoryRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_action(data.get_ref(), user_id, req.project_id, ProjectAction::Prepare)?;
     
     let new_category = NewCashflowCategory {
         project_id: req.project_id,
@@ -153,7 +160,7 @@ pub async fn create_category(
 /// Get category
 pub async fn get_category(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let category_id = path.into_inner();
@@ -161,6 +168,8 @@ pub async fn get_category(
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
     let category = cashflow_service.get_category(category_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, category.project_id)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -174,7 +183,7 @@ pub async fn get_category(
 pub async fn update_category(
     path: web::Path<Uuid>,
     req: web::Json<UpdateCashflowCategoryRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let category_id = path.into_inner();
@@ -187,6 +196,9 @@ pub async fn update_category(
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = cashflow_service.get_category(category_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let category = cashflow_service.update_category(category_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -200,13 +212,16 @@ pub async fn update_category(
 /// Delete category
 pub async fn delete_category(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let category_id = path.into_inner();
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = cashflow_service.get_category(category_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     cashflow_service.delete_category(category_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -214,13 +229,15 @@ pub async fn delete_category(
 /// List transactions
 pub async fn list_transactions(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let project_id = query.project_id.as_ref()
         .and_then(|s| Uuid::parse_str(s).ok())
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
     
@@ -245,10 +262,12 @@ pub async fn list_transactions(
 /// Create transaction
 pub async fn create_transaction(
     req: web::Json<CreateTransactionRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_action(data.get_ref(), user_id, req.project_id, ProjectAction::Prepare)?;
     
     let amount = BigDecimal::from_f64(req.amount)
         .ok_or_else(|| AppError::Validation("Invalid amount".to_string()))?;
@@ -262,7 +281,7 @@ pub async fn create_transaction(
         description: req.description.clone(),
         reference_number: req.reference_number.clone(),
         metadata: req.metadata.clone().unwrap_or_else(|| serde_json::json!({})),
-        created_by: None,
+        created_by: Some(user_id),
     };
     
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
@@ -281,7 +300,7 @@ pub async fn create_transaction(
 /// Get transaction
 pub async fn get_transaction(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let transaction_id = path.into_inner();
@@ -289,6 +308,8 @@ pub async fn get_transaction(
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
     let transaction = cashflow_service.get_transaction(transaction_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, transaction.project_id)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -302,7 +323,7 @@ pub async fn get_transaction(
 pub async fn update_transaction(
     path: web::Path<Uuid>,
     req: web::Json<UpdateTransactionRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let transaction_id = path.into_inner();
@@ -316,6 +337,9 @@ pub async fn update_transaction(
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = cashflow_service.get_transaction(transaction_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     let transaction = cashflow_service.update_transaction(transaction_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -329,13 +353,16 @@ pub async fn update_transaction(
 /// Delete transaction
 pub async fn delete_transaction(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let transaction_id = path.into_inner();
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = cashflow_service.get_transaction(transaction_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
     cashflow_service.delete_transaction(transaction_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -343,13 +370,15 @@ pub async fn delete_transaction(
 /// List discrepancies
 pub async fn list_discrepancies(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let project_id = query.project_id.as_ref()
         .and_then(|s| Uuid::parse_str(s).ok())
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
     
@@ -374,10 +403,12 @@ pub async fn list_discrepancies(
 /// Create discrepancy
 pub async fn create_discrepancy(
     req: web::Json<CreateDiscrepancyRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_action(data.get_ref(), user_id, req.project_id, ProjectAction::Prepare)?;
     
     let amount_diff = BigDecimal::from_f64(
         req.expected_amount.unwrap_or(0.0) - req.actual_amount.unwrap_or(0.0)
@@ -409,7 +440,7 @@ pub async fn create_discrepancy(
 /// Get discrepancy
 pub async fn get_discrepancy(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let discrepancy_id = path.into_inner();
@@ -417,6 +448,8 @@ pub async fn get_discrepancy(
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
     let discrepancy = cashflow_service.get_discrepancy(discrepancy_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, discrepancy.project_id)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -430,7 +463,7 @@ pub async fn get_discrepancy(
 pub async fn update_discrepancy(
     path: web::Path<Uuid>,
     req: web::Json<UpdateDiscrepancyRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let discrepancy_id = path.into_inner();
@@ -444,6 +477,9 @@ pub async fn update_discrepancy(
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = cashflow_service.get_discrepancy(discrepancy_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
     let discrepancy = cashflow_service.update_discrepancy(discrepancy_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -468,6 +504,8 @@ pub async fn resolve_discrepancy(
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
     })?;
+    let project_id = cashflow_service.get_discrepancy(discrepancy_id).await?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
     let discrepancy = cashflow_service.resolve_discrepancy(discrepancy_id, user_id, notes).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -481,12 +519,14 @@ pub async fn resolve_discrepancy(
 /// Get cashflow metrics
 pub async fn get_metrics(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let project_id = query.project_id.as_ref()
         .and_then(|s| Uuid::parse_str(s).ok())
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
+    let user_id = extract_user_id(&http_req)?;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
     
     let cashflow_service = create_cashflow_service(&data).map_err(|e| {
         AppError::InternalServerError(format!("Failed to create cashflow service: {}", e))
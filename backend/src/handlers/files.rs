@@ -9,7 +9,8 @@ use crate::errors::AppError;
 use crate::handlers::helpers::extract_user_id;
 use crate::handlers::types::ApiResponse;
 use crate::services::cache::MultiLevelCache;
-use crate::utils::check_project_permission;
+use crate::services::auth::ProjectAction;
+use crate::utils::{check_project_action, check_project_permission};
 use futures_util::StreamExt;
 
 /// Configure file management routes
@@ -67,7 +68,7 @@ pub async fn init_resumable_upload(
     config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     let user_id = extract_user_id(&http_req)?;
-    check_project_permission(data.get_ref(), user_id, req.project_id)?;
+    check_project_action(data.get_ref(), user_id, req.project_id, ProjectAction::Prepare)?;
 
     let file_service =
         crate::services::file::FileService::new(data.get_ref().clone(), config.upload_path.clone());
@@ -156,7 +157,7 @@ pub async fn complete_resumable_upload(
     config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     let user_id = extract_user_id(&http_req)?;
-    check_project_permission(data.get_ref(), user_id, req.project_id)?;
+    check_project_action(data.get_ref(), user_id, req.project_id, ProjectAction::Prepare)?;
 
     let file_service =
         crate::services::file::FileService::new(data.get_ref().clone(), config.upload_path.clone());
@@ -354,7 +355,7 @@ pub async fn delete_file(
 
     // ✅ SECURITY FIX: Check authorization before deleting file
     let user_id = extract_user_id(&http_req)?;
-    check_project_permission(data.get_ref(), user_id, file_info.project_id)?;
+    check_project_action(data.get_ref(), user_id, file_info.project_id, ProjectAction::Prepare)?;
 
     file_service.delete_file(file_id_val).await?;
 
@@ -393,7 +394,7 @@ pub async fn process_file(
 
     // ✅ SECURITY FIX: Check authorization before processing file
     let user_id = extract_user_id(&http_req)?;
-    check_project_permission(data.get_ref(), user_id, file_info.project_id)?;
+    check_project_action(data.get_ref(), user_id, file_info.project_id, ProjectAction::Prepare)?;
 
     let processing_result = file_service.process_file(file_id_val).await?;
 
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::config::Config;
 use crate::database::Database;
 use crate::errors::AppError;
 use crate::handlers::helpers::extract_user_id;
@@ -13,10 +14,18 @@ use crate::handlers::types::{
     ApiResponse, PaginatedResponse, SearchQueryParams,
     ingestion::{
         UploadDataRequest, ProcessDataRequest, ValidateDataRequest, TransformDataRequest,
+        QuarantineAction, QuarantineQuery, ResolveQuarantineRequest,
     },
 };
-use crate::utils::check_project_permission;
-use crate::services::ingestion::IngestionService;
+use crate::services::auth::ProjectAction;
+use crate::utils::{check_project_action, check_project_permission};
+use crate::models::ingestion::IngestionJob as IngestionJobRecord;
+use crate::services::data_source::DataSourceService;
+use crate::services::file::FileService;
+use crate::services::ingestion::{IngestionService, STALE_PROCESSING_MINUTES};
+use crate::services::parsing::TransformSpec;
+use crate::services::validation::business_rules::{RecordRuleSpec, Severity};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// Configure ingestion routes
@@ -25,9 +34,12 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .route("/process", web::post().to(process_data))
         .route("/validate", web::post().to(validate_data))
         .route("/transform", web::post().to(transform_data))
+        .route("/mappings/{data_source_id}", web::get().to(get_mappings))
+        .route("/quarantine/{id}/resolve", web::post().to(resolve_quarantined_record))
         .route("/{id}/status", web::get().to(get_status))
         .route("/{id}/results", web::get().to(get_results))
         .route("/{id}/errors", web::get().to(get_errors))
+        .route("/{id}/quarantine", web::get().to(get_quarantine))
         .route("/{id}/download", web::get().to(download_data));
 }
 
@@ -86,7 +98,7 @@ pub async fn upload_data(
     let filename = req.filename.clone();
     
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, project_id)?;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
 
     use crate::models::schema::ingestion_jobs;
     use crate::models::ingestion::NewIngestionJob;
@@ -142,7 +154,7 @@ pub async fn upload_data(
 
 /// Process uploaded data
 /// 
-/// Starts processing an uploaded ingestion job.
+/// Starts processing an uploaded ingestion job, unless a run already is.
 #[utoipa::path(
     post,
     path = "/api/v1/ingestion/process",
@@ -151,6 +163,7 @@ pub async fn upload_data(
     responses(
         (status = 200, description = "Processing started", body = ApiResponse),
         (status = 404, description = "Job not found", body = ErrorResponse),
+        (status = 409, description = "Job is already being processed", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
         (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
     ),
@@ -160,6 +173,7 @@ pub async fn process_data(
     req: web::Json<ProcessDataRequest>,
     http_req: HttpRequest,
     data: web::Data<Database>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
     let user_id = extract_user_id(&http_req)?;
@@ -181,23 +195,68 @@ pub async fn process_data(
         .ok_or_else(|| AppError::NotFound("Ingestion job not found".to_string()))?;
 
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, job.project_id)?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::Prepare)?;
+
+    // Resolve the uploaded file this job ingests
+    let (file_id, path) = resolve_job_file(data.get_ref(), &config, &job, req.file_id).await?;
+
+    // Remember the file and data source on the job so a rerun finds them
+    let mut source_config = job.source_config.clone();
+    if let Some(object) = source_config.as_object_mut() {
+        object.insert("file_id".to_string(), serde_json::json!(file_id));
+        if let Some(data_source_id) = req.data_source_id {
+            object.insert("data_source_id".to_string(), serde_json::json!(data_source_id));
+        }
+    }
+    // Claim the job, unless another run holds it; a run that stopped writing
+    // batches long ago crashed and leaves the job free to claim again
+    let now = chrono::Utc::now();
+    let stale_before = now - chrono::Duration::minutes(STALE_PROCESSING_MINUTES);
+    let claimed = diesel::update(
+        ingestion_jobs::table.find(job_id).filter(
+            ingestion_jobs::status
+                .ne("processing")
+                .or(ingestion_jobs::updated_at.lt(stale_before)),
+        ),
+    )
+    .set((
+        ingestion_jobs::source_config.eq(source_config),
+        ingestion_jobs::status.eq("processing"),
+        ingestion_jobs::started_at.eq(Some(now)),
+        ingestion_jobs::updated_at.eq(now),
+    ))
+    .returning(ingestion_jobs::id)
+    .get_result::<Uuid>(&mut conn)
+    .optional()
+    .map_err(AppError::Database)?;
+    if claimed.is_none() {
+        return Err(AppError::Conflict(format!(
+            "Ingestion job {} is already being processed",
+            job_id
+        )));
+    }
+
+    // Parse in the background; progress is visible through the status endpoint
+    let ingestion_service = IngestionService::new(Arc::new(data.get_ref().clone()));
+    tokio::spawn(async move {
+        match ingestion_service.ingest_file(job_id, path).await {
+            Ok(summary) => log::info!(
+                "Ingestion job {} imported {} records ({} errors)",
+                job_id,
+                summary.imported_records,
+                summary.error_count
+            ),
+            Err(e) => log::error!("Ingestion job {} failed: {}", job_id, e),
+        }
+    });
 
-    // Update job status to processing
-    diesel::update(ingestion_jobs::table.find(job_id))
-        .set((
-            ingestion_jobs::status.eq("processing"),
-            ingestion_jobs::started_at.eq(Some(chrono::Utc::now())),
-        ))
-        .execute(&mut conn)
-        .map_err(AppError::Database)?;
-    
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(serde_json::json!({
             "status": "processing",
             "message": "Processing started",
             "job_id": job_id,
+            "file_id": file_id,
         })),
         message: Some("Processing started".to_string()),
         error: None,
@@ -206,7 +265,8 @@ pub async fn process_data(
 
 /// Validate data
 /// 
-/// Validates data in an ingestion job.
+/// Runs record rules over the records of a completed ingestion job and
+/// returns the failures grouped by rule, errors apart from warnings.
 #[utoipa::path(
     post,
     path = "/api/v1/ingestion/validate",
@@ -245,40 +305,240 @@ pub async fn validate_data(
         .ok_or_else(|| AppError::NotFound("Ingestion job not found".to_string()))?;
 
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, job.project_id)?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::Prepare)?;
+    if job.status != "completed" {
+        return Err(AppError::Validation(format!(
+            "Ingestion job is {}; records can be validated once it has completed",
+            job.status
+        )));
+    }
+
+    // Rules from the request are remembered on the job so a rerun uses them
+    let specs: Vec<RecordRuleSpec> = match req.rules.as_ref().or_else(|| job.source_config.get("validation_rules")) {
+        Some(rules) => serde_json::from_value(rules.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid validation rules: {}", e)))?,
+        None => RecordRuleSpec::defaults(),
+    };
+    if let Some(rules) = &req.rules {
+        let mut source_config = job.source_config.clone();
+        if let Some(object) = source_config.as_object_mut() {
+            object.insert("validation_rules".to_string(), rules.clone());
+        }
+        diesel::update(ingestion_jobs::table.find(job_id))
+            .set(ingestion_jobs::source_config.eq(source_config))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+    }
+
+    let ingestion_service = IngestionService::new(Arc::new(data.get_ref().clone()));
+    let validation = ingestion_service.validate_job(&job, &specs).await?;
+
+    // Each rule's findings are paged separately, errors and warnings apart
+    let page = req.page.unwrap_or(1);
+    let per_page = req.per_page.unwrap_or(20);
+    let offset = usize::try_from((page - 1) * per_page).unwrap_or(usize::MAX);
+    let group = |severity: Severity| {
+        validation
+            .rules
+            .iter()
+            .filter(|rule| rule.severity == severity)
+            .map(|rule| {
+                serde_json::json!({
+                    "rule": rule.rule,
+                    "total": rule.total,
+                    "page": page,
+                    "per_page": per_page,
+                    "total_pages": (rule.total as f64 / per_page as f64).ceil() as i64,
+                    "items": rule.findings.iter().skip(offset).take(per_page as usize).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
 
-    // For now, return basic validation result
-    // In production, this would perform actual validation
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(serde_json::json!({
-            "valid": true,
-            "errors": [],
-            "warnings": [],
+            "valid": validation.valid,
             "job_id": job_id,
+            "records_checked": validation.records_checked,
+            "error_count": validation.error_count,
+            "warning_count": validation.warning_count,
+            "errors": group(Severity::Error),
+            "warnings": group(Severity::Warning),
         })),
         message: Some("Validation completed".to_string()),
         error: None,
     }))
 }
 
+/// The uploaded file a job reads: `file_id`, else the job's `source_config.file_id`
+async fn resolve_job_file(
+    db: &Database,
+    config: &Config,
+    job: &IngestionJobRecord,
+    file_id: Option<Uuid>,
+) -> Result<(Uuid, PathBuf), AppError> {
+    let file_id = file_id
+        .or_else(|| {
+            job.source_config
+                .get("file_id")
+                .and_then(|v| v.as_str())
+                .and_then(|s| Uuid::parse_str(s).ok())
+        })
+        .ok_or_else(|| AppError::Validation("No uploaded file linked to this ingestion job".to_string()))?;
+    let file_service = FileService::new(db.clone(), config.upload_path.clone());
+    let (path, uploaded_file) = file_service.get_file_for_download(file_id).await?;
+    if uploaded_file.project_id != job.project_id {
+        return Err(AppError::Validation(
+            "File belongs to a different project than the ingestion job".to_string(),
+        ));
+    }
+    Ok((file_id, path))
+}
+
 /// Transform data
+///
+/// Runs a transformation spec over the first rows of the job's file and
+/// returns each row as read, as transformed and as it would be imported.
+/// With `save` the spec becomes the data source's next mapping version,
+/// which later ingestions into that data source apply.
+#[utoipa::path(
+    post,
+    path = "/api/v1/ingestion/transform",
+    tag = "Ingestion",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "Transformation previewed", body = ApiResponse),
+        (status = 400, description = "Invalid transformation spec", body = ErrorResponse),
+        (status = 404, description = "Job or data source not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn transform_data(
     req: web::Json<TransformDataRequest>,
-    _http_req: HttpRequest,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
-    let _job_id = req.job_id;
-    let _transformation_rules = req.transformation_rules.clone();
-    
+    let user_id = extract_user_id(&http_req)?;
+
+    use crate::models::schema::ingestion_jobs;
+    let mut conn = data.get_connection()?;
+    let job = ingestion_jobs::table
+        .find(req.job_id)
+        .first::<IngestionJobRecord>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound("Ingestion job not found".to_string()))?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::Prepare)?;
+
+    let data_source_id = req.data_source_id.or_else(|| {
+        job.source_config
+            .get("data_source_id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+    });
+    let data_source_service = DataSourceService::new(data.get_ref().clone());
+    if let Some(data_source_id) = data_source_id {
+        let data_source = data_source_service
+            .get_data_source(data_source_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Data source not found".to_string()))?;
+        if data_source.project_id != job.project_id {
+            return Err(AppError::Validation(
+                "Data source belongs to a different project than the ingestion job".to_string(),
+            ));
+        }
+    }
+
+    let ingestion_service = IngestionService::new(Arc::new(data.get_ref().clone()));
+    let (spec, mut version) = match &req.transformation_rules {
+        Some(rules) => {
+            let spec: TransformSpec = serde_json::from_value(rules.clone())
+                .map_err(|e| AppError::Validation(format!("Invalid transformation spec: {}", e)))?;
+            (spec, None)
+        }
+        None => ingestion_service.resolve_spec(&job, data_source_id).await?,
+    };
+
+    if req.save {
+        let data_source_id = data_source_id
+            .ok_or_else(|| AppError::Validation("A data source is needed to save a mapping".to_string()))?;
+        let mapping = data_source_service
+            .save_mapping(data_source_id, &spec, Some(user_id))
+            .await?;
+        version = Some(mapping.version);
+    }
+
+    let (file_id, path) = resolve_job_file(data.get_ref(), &config, &job, None).await?;
+    let preview = ingestion_service
+        .preview_file(&job, path, &spec, req.preview_rows)
+        .await?;
+    let transformed_records = preview.rows.len() - preview.filtered_rows - preview.error_count;
+
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(serde_json::json!({
-            "status": "transformed",
-            "transformed_records": 0
+            "status": "previewed",
+            "job_id": job.id,
+            "file_id": file_id,
+            "data_source_id": data_source_id,
+            "version": version,
+            "saved": req.save,
+            "transformed_records": transformed_records,
+            "preview": preview,
         })),
-        message: Some("Transformation completed".to_string()),
+        message: Some(if req.save {
+            "Mapping saved".to_string()
+        } else {
+            "Transformation previewed".to_string()
+        }),
+        error: None,
+    }))
+}
+
+/// Get mapping versions
+///
+/// Lists the saved transformation specs of a data source, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ingestion/mappings/{data_source_id}",
+    tag = "Ingestion",
+    params(
+        ("data_source_id" = Uuid, Path, description = "Data source ID")
+    ),
+    responses(
+        (status = 200, description = "Mappings retrieved successfully", body = ApiResponse),
+        (status = 404, description = "Data source not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_mappings(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let data_source_id = path.into_inner();
+
+    let data_source_service = DataSourceService::new(data.get_ref().clone());
+    let data_source = data_source_service
+        .get_data_source(data_source_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Data source not found".to_string()))?;
+    check_project_permission(data.get_ref(), user_id, data_source.project_id)?;
+
+    let mappings = data_source_service.get_mappings(data_source_id).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!(mappings)),
+        message: None,
         error: None,
     }))
 }
@@ -450,6 +710,106 @@ pub async fn get_errors(
     Ok(HttpResponse::Ok().json(paginated))
 }
 
+/// Get quarantined duplicates
+///
+/// Lists the records of an ingestion job that were held back as duplicates
+/// of an earlier row or an already ingested record.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ingestion/{id}/quarantine",
+    tag = "Ingestion",
+    params(
+        ("id" = Uuid, Path, description = "Ingestion job ID"),
+        ("status" = Option<String>, Query, description = "pending, released or discarded"),
+        ("page" = Option<i64>, Query, description = "Page number (1-based)"),
+        ("per_page" = Option<i64>, Query, description = "Items per page (max 100)")
+    ),
+    responses(
+        (status = 200, description = "Quarantined records retrieved successfully", body = PaginatedResponse),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_quarantine(
+    path: web::Path<Uuid>,
+    query: web::Query<QuarantineQuery>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    query
+        .validate()
+        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))?;
+    let user_id = extract_user_id(&http_req)?;
+    let job_id = path.into_inner();
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+
+    let ingestion_service = IngestionService::new(Arc::new(data.get_ref().clone()));
+    let job = ingestion_service.get_job(job_id).await?;
+    check_project_permission(data.get_ref(), user_id, job.project_id)?;
+
+    let (records, total) = ingestion_service
+        .get_quarantine(job_id, query.status.as_deref(), page, per_page)
+        .await?;
+
+    let total_pages = (total as f64 / per_page as f64).ceil() as i32;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse {
+        items: records,
+        total,
+        page: page as i32,
+        per_page: per_page as i32,
+        total_pages,
+    }))
+}
+
+/// Resolve a quarantined duplicate
+///
+/// Releases a quarantined record into its job's records, or discards it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/ingestion/quarantine/{id}/resolve",
+    tag = "Ingestion",
+    params(
+        ("id" = Uuid, Path, description = "Quarantined record ID")
+    ),
+    request_body = ResolveQuarantineRequest,
+    responses(
+        (status = 200, description = "Quarantined record resolved", body = ApiResponse),
+        (status = 404, description = "Quarantined record not found", body = ErrorResponse),
+        (status = 409, description = "Quarantined record already resolved", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resolve_quarantined_record(
+    path: web::Path<Uuid>,
+    req: web::Json<ResolveQuarantineRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let id = path.into_inner();
+
+    let ingestion_service = IngestionService::new(Arc::new(data.get_ref().clone()));
+    let quarantined = ingestion_service.get_quarantined_record(id).await?;
+    check_project_action(data.get_ref(), user_id, quarantined.project_id, ProjectAction::Prepare)?;
+
+    let resolved = ingestion_service
+        .resolve_quarantined_record(id, req.action == QuarantineAction::Release, user_id)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!(resolved)),
+        message: Some(format!("Quarantined record {}", resolved.status)),
+        error: None,
+    }))
+}
+
 /// Download processed data
 /// 
 /// Downloads the processed data from an ingestion job.
@@ -42,6 +42,8 @@ pub mod onboarding;
 pub mod logs;
 
 // Security handlers
+pub mod api_keys;
+pub mod audit;
 pub mod compliance;
 pub mod security;
 pub mod security_events;
@@ -130,6 +132,10 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .service(web::scope("/security").configure(security::configure_routes))
             // Security events routes
             .service(web::scope("/security").configure(security_events::configure_routes))
+            // Audit trail routes
+            .service(web::scope("/audit").configure(audit::configure_routes))
+            // API key routes
+            .service(web::scope("/api-keys").configure(api_keys::configure_routes))
             // Compliance routes
             .service(web::scope("/compliance").configure(compliance::configure_routes))
             // Health check routes
@@ -196,6 +202,8 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                 .configure(security::configure_routes)
                 .configure(security_events::configure_routes),
         )
+        // API key routes
+        .service(web::scope("/api/api-keys").configure(api_keys::configure_routes))
         // Compliance routes
         .service(web::scope("/api/compliance").configure(compliance::configure_routes))
         // Health check routes (from existing health.rs)
@@ -1,7 +1,12 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use crate::database::Database;
 use crate::errors::AppError;
+use crate::handlers::helpers::extract_user_id;
+use crate::handlers::types::ApiResponse;
 use crate::models::Project;
+use crate::services::auth::{ProjectAction, ProjectRole};
+use crate::services::project_permissions::ProjectPermissionService;
+use crate::services::reconciliation::approvals::{self, ApprovalPolicy};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -109,6 +114,218 @@ pub async fn get_project_analytics(
     })))
 }
 
+#[derive(Deserialize)]
+pub struct AddProjectMemberRequest {
+    pub user_id: Uuid,
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct ProjectRoleRequest {
+    pub role: String,
+}
+
+fn permission_service(data: &web::Data<Database>) -> ProjectPermissionService {
+    ProjectPermissionService::new(data.get_ref().clone())
+}
+
+/// List a project's members and the teams granted a role in it
+pub async fn list_project_members(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let service = permission_service(&data);
+    service.check_project_permission(user_id, project_id)?;
+
+    let members = service.list_members(project_id)?;
+    let teams = service.list_team_grants(project_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "members": members,
+            "teams": teams,
+        })),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Roles the current user holds in a project
+pub async fn get_my_project_roles(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let service = permission_service(&data);
+    service.check_project_permission(user_id, project_id)?;
+
+    let roles = service.project_roles(user_id, project_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({ "roles": roles })),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Add a member to a project, or change the role of an existing one
+pub async fn add_project_member(
+    path: web::Path<Uuid>,
+    req: web::Json<AddProjectMemberRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let role: ProjectRole = req.role.parse()?;
+    let service = permission_service(&data);
+    service.check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    let member = service.set_member_role(project_id, req.user_id, role, user_id)?;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(member),
+        message: Some(format!("Member added as {}", role)),
+        error: None,
+    }))
+}
+
+/// Change a member's role in a project
+pub async fn update_project_member(
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<ProjectRoleRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, member_id) = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let role: ProjectRole = req.role.parse()?;
+    let service = permission_service(&data);
+    service.check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    let member = service.set_member_role(project_id, member_id, role, user_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(member),
+        message: Some(format!("Member role changed to {}", role)),
+        error: None,
+    }))
+}
+
+/// Remove a member from a project
+pub async fn remove_project_member(
+    path: web::Path<(Uuid, Uuid)>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, member_id) = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let service = permission_service(&data);
+    service.check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    service.remove_member(project_id, member_id)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Grant a team a role in a project, replacing any role it already had
+pub async fn grant_project_team(
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<ProjectRoleRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, team_id) = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let role: ProjectRole = req.role.parse()?;
+    let service = permission_service(&data);
+    service.check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    let grant = service.grant_team(project_id, team_id, role, user_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(grant),
+        message: Some(format!("Team granted {}", role)),
+        error: None,
+    }))
+}
+
+/// Withdraw a team's role in a project
+pub async fn revoke_project_team(
+    path: web::Path<(Uuid, Uuid)>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, team_id) = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let service = permission_service(&data);
+    service.check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    service.revoke_team(project_id, team_id)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Get a project's match approval policy
+pub async fn get_approval_policy(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    permission_service(&data).check_project_permission(user_id, project_id)?;
+
+    let mut conn = data.get_connection()?;
+    let policy = approvals::project_policy(&mut conn, project_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(policy),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Set which match decisions in a project need a second approver
+pub async fn update_approval_policy(
+    path: web::Path<Uuid>,
+    req: web::Json<ApprovalPolicy>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    permission_service(&data).check_project_action(user_id, project_id, ProjectAction::Manage)?;
+
+    let policy = req.into_inner();
+    let mut conn = data.get_connection()?;
+    approvals::set_project_policy(&mut conn, project_id, &policy)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(policy),
+        message: Some("Approval policy updated".to_string()),
+        error: None,
+    }))
+}
+
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    // Configure routes here
+    cfg.route("/{project_id}/members", web::get().to(list_project_members))
+        .route("/{project_id}/members", web::post().to(add_project_member))
+        .route("/{project_id}/members/{user_id}", web::put().to(update_project_member))
+        .route("/{project_id}/members/{user_id}", web::delete().to(remove_project_member))
+        .route("/{project_id}/teams/{team_id}", web::put().to(grant_project_team))
+        .route("/{project_id}/teams/{team_id}", web::delete().to(revoke_project_team))
+        .route("/{project_id}/roles", web::get().to(get_my_project_roles))
+        .route("/{project_id}/approval-policy", web::get().to(get_approval_policy))
+        .route("/{project_id}/approval-policy", web::put().to(update_approval_policy));
 }
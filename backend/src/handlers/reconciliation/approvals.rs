@@ -0,0 +1,137 @@
+//! Match approval handlers
+//!
+//! The queue of match decisions awaiting a second approver, and approving or
+//! rejecting them. Deciding needs the approve role in the match's project
+//! and is refused to the decision's preparer.
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::handlers::helpers::extract_user_id;
+use crate::handlers::types::{ApiResponse, PaginatedResponse};
+use crate::services::audit::{self, AuditContext};
+use crate::services::auth::ProjectAction;
+use crate::services::reconciliation::approvals::{self, ApprovalDecision, ApprovalQuery};
+use crate::utils::{accessible_project_ids, check_project_action, check_project_permission};
+
+#[derive(Debug, Deserialize)]
+pub struct ApproveMatchRequest {
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectMatchRequest {
+    pub reason: String,
+}
+
+/// List match approval requests
+///
+/// Pending requests by default, oldest first, in the projects the user has a
+/// role in. `actionable=true` leaves out the user's own requests.
+pub async fn list_approvals(
+    query: web::Query<ApprovalQuery>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    if let Some(project_id) = query.project_id {
+        check_project_permission(data.get_ref(), user_id, project_id)?;
+    }
+    let projects = accessible_project_ids(data.get_ref(), user_id)?;
+
+    let mut conn = data.get_connection()?;
+    let (items, total) = approvals::list(&mut conn, &query, projects.as_deref(), user_id)?;
+
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+    Ok(HttpResponse::Ok().json(PaginatedResponse {
+        items,
+        total,
+        page: query.page.unwrap_or(1).max(1) as i32,
+        per_page: per_page as i32,
+        total_pages: (total as f64 / per_page as f64).ceil() as i32,
+    }))
+}
+
+/// Get a match approval request
+pub async fn get_approval(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let mut conn = data.get_connection()?;
+    let approval = approvals::get(&mut conn, path.into_inner())?;
+    check_project_permission(data.get_ref(), user_id, approval.project_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(approval),
+        message: None,
+        error: None,
+    }))
+}
+
+/// Approve a match decision, applying it
+pub async fn approve_match(
+    path: web::Path<Uuid>,
+    req: web::Json<ApproveMatchRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let decision = ApprovalDecision::Approve {
+        notes: req.into_inner().notes,
+    };
+    decide(
+        path.into_inner(),
+        decision,
+        &http_req,
+        &data,
+        "Match decision approved",
+    )
+}
+
+/// Reject a match decision with a reason, restoring the match's status
+pub async fn reject_match(
+    path: web::Path<Uuid>,
+    req: web::Json<RejectMatchRequest>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let decision = ApprovalDecision::Reject {
+        reason: req.into_inner().reason,
+    };
+    decide(
+        path.into_inner(),
+        decision,
+        &http_req,
+        &data,
+        "Match decision rejected",
+    )
+}
+
+fn decide(
+    approval_id: Uuid,
+    decision: ApprovalDecision,
+    http_req: &HttpRequest,
+    data: &web::Data<Database>,
+    message: &str,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(http_req)?;
+    let mut conn = data.get_connection()?;
+    let project_id = approvals::get(&mut conn, approval_id)?.project_id;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Approve)?;
+
+    let ctx = AuditContext::from_request(http_req).with_actor(user_id);
+    let approval = approvals::decide(&mut conn, &ctx, approval_id, user_id, decision)?;
+    audit::mark_audited(http_req);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(approval),
+        message: Some(message.to_string()),
+        error: None,
+    }))
+}
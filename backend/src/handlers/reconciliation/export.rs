@@ -14,7 +14,7 @@ use crate::services::cache::MultiLevelCache;
 
 #[derive(serde::Deserialize)]
 pub struct StartExportRequest {
-    pub format: Option<String>, // csv|json
+    pub format: Option<String>, // csv|json|xlsx|parquet
 }
 
 /// Start export job (async): generates a file and caches its path
@@ -33,6 +33,14 @@ pub async fn start_export_job(
 
     // Prepare export path
     let format = req.format.clone().unwrap_or_else(|| "csv".to_string());
+    // The format names the file, so only known ones get this far
+    if !crate::services::reconciliation::EXPORT_FORMATS.contains(&format.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Unsupported export format: {}; expected one of {}",
+            format,
+            crate::services::reconciliation::EXPORT_FORMATS.join(", ")
+        )));
+    }
     let mut export_dir = PathBuf::from(&config.upload_path);
     export_dir.push("exports");
     export_dir.push(job_id_val.to_string());
@@ -48,7 +56,7 @@ pub async fn start_export_job(
     let mut export_path = export_dir.clone();
     export_path.push(&filename);
 
-    // Spawn async export task (simple CSV/JSON dump)
+    // Spawn async export task
     let db_clone = data.get_ref().clone();
     let cache_clone = cache.clone();
     let path_for_task = export_path.clone();
@@ -103,12 +111,14 @@ pub async fn start_export_job(
 /// Get export status and link if ready
 pub async fn get_export_status(
     job_id: web::Path<Uuid>,
-    _http_req: HttpRequest,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
     cache: web::Data<MultiLevelCache>,
     _config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
+    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
     if let Some(info) = cache
         .get::<serde_json::Value>(&format!("export:{}", job_id_val))
         .await?
@@ -135,12 +145,14 @@ pub async fn get_export_status(
 /// Download export file (serves from disk)
 pub async fn download_export_file(
     job_id: web::Path<Uuid>,
-    _http_req: HttpRequest,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
     config: web::Data<Config>,
 ) -> Result<NamedFile, AppError> {
+    let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
+    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
     let export_dir = PathBuf::from(&config.upload_path)
         .join("exports")
         .join(job_id_val.to_string());
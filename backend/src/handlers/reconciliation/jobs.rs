@@ -8,6 +8,8 @@ use crate::database::Database;
 use crate::errors::AppError;
 use crate::handlers::helpers::extract_user_id;
 use crate::handlers::types::{ApiResponse, UpdateReconciliationJobRequest};
+use crate::services::audit::{self, AuditContext};
+use crate::services::auth::ProjectAction;
 use crate::services::cache::MultiLevelCache;
 use crate::websocket::WsServer;
 use actix::Addr;
@@ -68,7 +70,7 @@ pub async fn create_reconciliation_job(
     let user_id = extract_user_id(&http_req)?;
 
     // Security check
-    crate::utils::check_project_permission(data.get_ref(), user_id, req.project_id)?;
+    crate::utils::check_job_permission(data.get_ref(), user_id, req.project_id)?;
 
     let matching_rules = if let Some(settings) = &req.settings {
         if let Some(rules) = settings.get("matching_rules") {
@@ -82,6 +84,34 @@ pub async fn create_reconciliation_job(
         vec![]
     };
 
+    let blocking = match req.settings.as_ref().and_then(|s| s.get("blocking")) {
+        Some(blocking) => serde_json::from_value(blocking.clone()).map_err(|e| {
+            AppError::Validation(format!("Invalid blocking format: {}", e))
+        })?,
+        None => Default::default(),
+    };
+
+    let assignment = match req.settings.as_ref().and_then(|s| s.get("assignment")) {
+        Some(assignment) => serde_json::from_value(assignment.clone()).map_err(|e| {
+            AppError::Validation(format!("Invalid assignment mode: {}", e))
+        })?,
+        None => Default::default(),
+    };
+
+    let aggregation = match req.settings.as_ref().and_then(|s| s.get("aggregation")) {
+        Some(aggregation) => serde_json::from_value(aggregation.clone()).map_err(|e| {
+            AppError::Validation(format!("Invalid aggregation format: {}", e))
+        })?,
+        None => None,
+    };
+
+    let parallelism = match req.settings.as_ref().and_then(|s| s.get("parallelism")) {
+        Some(parallelism) => serde_json::from_value(parallelism.clone()).map_err(|e| {
+            AppError::Validation(format!("Invalid parallelism: {}", e))
+        })?,
+        None => None,
+    };
+
     let request = crate::services::reconciliation::CreateReconciliationJobRequest {
         project_id: req.project_id,
         name: req.name.clone(),
@@ -90,11 +120,17 @@ pub async fn create_reconciliation_job(
         source_b_id: req.target_data_source_id,
         confidence_threshold: req.confidence_threshold,
         matching_rules,
+        blocking,
+        assignment,
+        aggregation,
+        parallelism,
     };
 
+    let audit_ctx = AuditContext::from_request(&http_req).with_actor(user_id);
     let new_job = reconciliation_service
-        .create_reconciliation_job(user_id, request)
+        .create_reconciliation_job_audited(&audit_ctx, user_id, request)
         .await?;
+    audit::mark_audited(&http_req);
 
     cache
         .delete(&format!("jobs:project:{}", req.project_id))
@@ -167,7 +203,7 @@ pub async fn update_reconciliation_job(
     let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
 
-    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
+    crate::utils::check_job_action(data.get_ref(), user_id, job_id_val, ProjectAction::Prepare)?;
 
     let reconciliation_service =
         crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
@@ -216,16 +252,18 @@ pub async fn delete_reconciliation_job(
     let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
 
-    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
+    crate::utils::check_job_action(data.get_ref(), user_id, job_id_val, ProjectAction::Prepare)?;
 
     let project_id =
         crate::utils::authorization::get_project_id_from_job(data.get_ref(), job_id_val).ok();
     let reconciliation_service =
         crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
 
+    let audit_ctx = AuditContext::from_request(&http_req).with_actor(user_id);
     reconciliation_service
-        .delete_reconciliation_job(job_id_val)
+        .delete_reconciliation_job_audited(&audit_ctx, job_id_val)
         .await?;
+    audit::mark_audited(&http_req);
 
     if let Some(pid) = project_id {
         let _ = cache.invalidate_job_cache(job_id_val, pid).await;
@@ -246,7 +284,7 @@ pub async fn start_reconciliation_job(
     let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
 
-    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
+    crate::utils::check_job_action(data.get_ref(), user_id, job_id_val, ProjectAction::Prepare)?;
 
     let project_id =
         crate::utils::authorization::get_project_id_from_job(data.get_ref(), job_id_val).ok();
@@ -283,7 +321,7 @@ pub async fn stop_reconciliation_job(
     let user_id = extract_user_id(&http_req)?;
     let job_id_val = job_id.into_inner();
 
-    crate::utils::check_job_access(data.get_ref(), user_id, job_id_val)?;
+    crate::utils::check_job_action(data.get_ref(), user_id, job_id_val, ProjectAction::Prepare)?;
 
     let project_id =
         crate::utils::authorization::get_project_id_from_job(data.get_ref(), job_id_val).ok();
@@ -341,11 +379,14 @@ pub async fn get_reconciliation_progress(
 
 /// Get active reconciliation jobs
 pub async fn get_active_reconciliation_jobs(
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
     let reconciliation_service =
         crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
     let active_jobs = reconciliation_service.get_active_jobs().await?;
+    let active_jobs = visible_jobs(data.get_ref(), user_id, active_jobs)?;
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -359,11 +400,14 @@ pub async fn get_active_reconciliation_jobs(
 
 /// Get queued reconciliation jobs
 pub async fn get_queued_reconciliation_jobs(
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
     let reconciliation_service =
         crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
     let queued_jobs = reconciliation_service.get_queued_jobs().await?;
+    let queued_jobs = visible_jobs(data.get_ref(), user_id, queued_jobs)?;
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -375,3 +419,19 @@ pub async fn get_queued_reconciliation_jobs(
     }))
 }
 
+/// The jobs among `job_ids` in projects the user can access
+fn visible_jobs(db: &Database, user_id: Uuid, job_ids: Vec<Uuid>) -> Result<Vec<Uuid>, AppError> {
+    use crate::models::schema::reconciliation_jobs;
+    use diesel::prelude::*;
+
+    let Some(project_ids) = crate::utils::accessible_project_ids(db, user_id)? else {
+        return Ok(job_ids);
+    };
+    let mut conn = db.get_connection()?;
+    reconciliation_jobs::table
+        .filter(reconciliation_jobs::id.eq_any(&job_ids))
+        .filter(reconciliation_jobs::project_id.eq_any(&project_ids))
+        .select(reconciliation_jobs::id)
+        .load::<Uuid>(&mut conn)
+        .map_err(AppError::Database)
+}
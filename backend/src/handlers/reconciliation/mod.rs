@@ -3,9 +3,11 @@
 //! This module provides HTTP handlers for reconciliation operations split into:
 //! - `jobs`: Job CRUD and control operations
 //! - `results`: Results retrieval and match operations
+//! - `approvals`: Queue and decisions of match approvals
 //! - `export`: Export operations
 //! - `sample`: Sample onboarding
 
+pub mod approvals;
 pub mod jobs;
 pub mod results;
 pub mod export;
@@ -47,6 +49,16 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             "/matches/{match_id}",
             web::put().to(results::update_reconciliation_match),
         )
+        .route("/approvals", web::get().to(approvals::list_approvals))
+        .route("/approvals/{approval_id}", web::get().to(approvals::get_approval))
+        .route(
+            "/approvals/{approval_id}/approve",
+            web::post().to(approvals::approve_match),
+        )
+        .route(
+            "/approvals/{approval_id}/reject",
+            web::post().to(approvals::reject_match),
+        )
         .route(
             "/jobs/{job_id}/export/download",
             web::get().to(export::download_export_file),
@@ -89,7 +101,9 @@ use crate::handlers::types::{ApiResponse, PaginatedResponse, SearchQueryParams};
 use crate::handlers::helpers::extract_user_id;
 use crate::database::Database;
 use crate::errors::AppError;
-use crate::utils::check_project_permission;
+use crate::services::audit::{self, AuditContext, AuditEvent};
+use crate::services::auth::ProjectAction;
+use crate::utils::{accessible_project_ids, check_project_action, check_project_permission};
 use actix_web::{web, HttpRequest, HttpResponse, Result};
 use diesel::prelude::*;
 use uuid::Uuid;
@@ -119,22 +133,24 @@ pub async fn get_records(
     http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let _user_id = extract_user_id(&http_req)?;
+    let user_id = extract_user_id(&http_req)?;
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
     let offset = (page - 1) * per_page;
 
     use crate::models::schema::reconciliation_records;
+    let projects = accessible_project_ids(data.get_ref(), user_id)?;
     let mut conn = data.get_connection()?;
 
-    // Build query with filters
-    let query_builder = reconciliation_records::table.into_boxed();
-    
-    // Apply filters if provided in query string
-    // Note: For now, we'll get all records. In production, add proper filtering
-    
-    // Get total count - rebuild query for count
-    let total: i64 = reconciliation_records::table
+    // Only records in projects the user has a role in
+    let mut query_builder = reconciliation_records::table.into_boxed();
+    let mut count_query = reconciliation_records::table.into_boxed();
+    if let Some(ref project_ids) = projects {
+        query_builder = query_builder.filter(reconciliation_records::project_id.eq_any(project_ids));
+        count_query = count_query.filter(reconciliation_records::project_id.eq_any(project_ids));
+    }
+
+    let total: i64 = count_query
         .count()
         .get_result(&mut conn)
         .map_err(AppError::Database)?;
@@ -211,7 +227,7 @@ pub async fn create_record(
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
     
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, project_id)?;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
 
     use crate::models::schema::reconciliation_records;
     let mut conn = data.get_connection()?;
@@ -368,7 +384,7 @@ pub async fn update_record(
     let existing = existing.ok_or_else(|| AppError::NotFound("Record not found".to_string()))?;
 
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, existing.project_id)?;
+    check_project_action(data.get_ref(), user_id, existing.project_id, ProjectAction::Prepare)?;
 
     // Update fields
     diesel::update(reconciliation_records::table.find(record_id))
@@ -456,7 +472,7 @@ pub async fn delete_record(
 
     if let Some(r) = record {
         // Check authorization
-        check_project_permission(data.get_ref(), user_id, r.project_id)?;
+        check_project_action(data.get_ref(), user_id, r.project_id, ProjectAction::Prepare)?;
 
         // Delete record
         diesel::delete(reconciliation_records::table.find(record_id))
@@ -523,7 +539,7 @@ pub async fn bulk_update_records(
 
         if let Some(r) = record {
             // Check authorization
-            if check_project_permission(data.get_ref(), user_id, r.project_id).is_ok() {
+            if check_project_action(data.get_ref(), user_id, r.project_id, ProjectAction::Prepare).is_ok() {
                 // Build update tuple with all fields
                 // Get existing values or use updates
                 let status_value = updates.get("status")
@@ -625,7 +641,7 @@ pub async fn bulk_delete_records(
 
         if let Some(r) = record {
             // Check authorization
-            if check_project_permission(data.get_ref(), user_id, r.project_id).is_ok() {
+            if check_project_action(data.get_ref(), user_id, r.project_id, ProjectAction::Prepare).is_ok() {
                 match diesel::delete(reconciliation_records::table.find(record_id))
                     .execute(&mut conn)
                 {
@@ -704,16 +720,21 @@ pub async fn list_rules(
     http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let _user_id = extract_user_id(&http_req)?;
+    let user_id = extract_user_id(&http_req)?;
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
     let offset = (page - 1) * per_page;
 
     use crate::models::schema::reconciliation_jobs;
+    let projects = accessible_project_ids(data.get_ref(), user_id)?;
     let mut conn = data.get_connection()?;
 
-    // Get jobs with matching rules in settings
-    let jobs = reconciliation_jobs::table
+    // Get jobs with matching rules in settings, in projects the user has a role in
+    let mut jobs_query = reconciliation_jobs::table.into_boxed();
+    if let Some(ref project_ids) = projects {
+        jobs_query = jobs_query.filter(reconciliation_jobs::project_id.eq_any(project_ids));
+    }
+    let jobs = jobs_query
         .select((
             reconciliation_jobs::id,
             reconciliation_jobs::project_id,
@@ -742,6 +763,8 @@ pub async fn list_rules(
                             "rule_type": rule.get("rule_type"),
                             "weight": rule.get("weight"),
                             "threshold": rule.get("threshold"),
+                            "required": rule.get("required"),
+                            "options": rule.get("options"),
                         }));
                     }
                 }
@@ -794,7 +817,7 @@ pub async fn create_rule(
         .ok_or_else(|| AppError::Validation("project_id is required".to_string()))?;
     
     // Check authorization
-    check_project_permission(data.get_ref(), user_id, project_id)?;
+    check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Prepare)?;
 
     // Validate rule structure
     let field = req
@@ -817,50 +840,76 @@ pub async fn create_rule(
         .and_then(|v| v.as_f64())
         .unwrap_or(0.8);
 
+    let required = req
+        .get("required")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Rule-type specific options (amount tolerance, date window)
+    let options = match req.get("options") {
+        Some(options) => {
+            serde_json::from_value::<crate::services::reconciliation::RuleOptions>(options.clone())
+                .map_err(|e| AppError::Validation(format!("Invalid rule options: {}", e)))?;
+            options.clone()
+        }
+        None => serde_json::json!({}),
+    };
+
     // Store rule in project settings (for now, as a simple approach)
     // In production, consider creating a dedicated matching_rules table
     use crate::models::schema::projects;
     let mut conn = data.get_connection()?;
-    
-    let project = projects::table
-        .find(project_id)
-        .first::<crate::models::Project>(&mut conn)
-        .map_err(|e| {
-            if e == diesel::NotFound {
-                AppError::NotFound("Project not found".to_string())
-            } else {
-                AppError::Database(e)
-            }
-        })?;
 
-    // Get existing rules from project settings
-    let mut settings = project.settings.clone();
-    
-    // Ensure matching_rules exists
-    if !settings.get("matching_rules").is_some() {
-        settings["matching_rules"] = serde_json::json!([]);
-    }
-    
-    let rules_array = settings
-        .get_mut("matching_rules")
-        .and_then(|v| v.as_array_mut())
-        .ok_or_else(|| AppError::Internal("Failed to access rules array".to_string()))?;
-
-    // Add new rule
+    let rule_id = Uuid::new_v4();
     let new_rule = serde_json::json!({
-        "id": Uuid::new_v4(),
+        "id": rule_id,
         "field": field,
         "rule_type": rule_type,
         "weight": weight,
         "threshold": threshold,
+        "required": required,
+        "options": options,
     });
-    rules_array.push(new_rule.clone());
 
-    // Update project settings
-    diesel::update(projects::table.find(project_id))
-        .set(projects::settings.eq(settings))
-        .execute(&mut conn)
-        .map_err(AppError::Database)?;
+    // Read, update and audit the settings in one transaction, holding the
+    // project row so a concurrent settings change (e.g. the approval policy)
+    // isn't overwritten
+    let ctx = AuditContext::from_request(&http_req).with_actor(user_id);
+    conn.transaction::<_, AppError, _>(|tx| {
+        let mut settings = projects::table
+            .find(project_id)
+            .select(projects::settings)
+            .for_update()
+            .first::<serde_json::Value>(tx)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+        if !settings.is_object() {
+            settings = serde_json::json!({});
+        }
+
+        // Ensure matching_rules exists
+        if !settings.get("matching_rules").is_some_and(|rules| rules.is_array()) {
+            settings["matching_rules"] = serde_json::json!([]);
+        }
+        settings
+            .get_mut("matching_rules")
+            .and_then(|v| v.as_array_mut())
+            .ok_or_else(|| AppError::Internal("Failed to access rules array".to_string()))?
+            .push(new_rule.clone());
+
+        diesel::update(projects::table.find(project_id))
+            .set(projects::settings.eq(settings))
+            .execute(tx)?;
+        audit::append(
+            tx,
+            &ctx,
+            AuditEvent::new("rule.create", "matching_rule", Some(rule_id))
+                .with_change(None, Some(new_rule.clone()))
+                .with_details(serde_json::json!({ "project_id": project_id })),
+        )?;
+        Ok(())
+    })?;
+    audit::mark_audited(&http_req);
 
     Ok(HttpResponse::Created().json(ApiResponse {
         success: true,
@@ -893,7 +942,7 @@ pub async fn get_rule(
     http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let _user_id = extract_user_id(&http_req)?;
+    let user_id = extract_user_id(&http_req)?;
     let rule_id = path.into_inner();
 
     // Try to parse as UUID first (for future dedicated rules table)
@@ -902,17 +951,7 @@ pub async fn get_rule(
         return Err(AppError::NotFound("Rule not found".to_string()));
     }
 
-    // Parse job_id-index format
-    let parts: Vec<&str> = rule_id.split('-').collect();
-    if parts.len() < 2 {
-        return Err(AppError::Validation("Invalid rule ID format".to_string()));
-    }
-
-    let job_id = Uuid::parse_str(parts[0])
-        .map_err(|_| AppError::Validation("Invalid job ID in rule ID".to_string()))?;
-    let _index: usize = parts[1]
-        .parse()
-        .map_err(|_| AppError::Validation("Invalid rule index".to_string()))?;
+    let (job_id, index) = parse_job_rule_id(&rule_id)?;
 
     use crate::models::schema::reconciliation_jobs;
     let mut conn = data.get_connection()?;
@@ -924,12 +963,13 @@ pub async fn get_rule(
         .map_err(AppError::Database)?;
 
     let job = job.ok_or_else(|| AppError::NotFound("Job not found".to_string()))?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::View)?;
 
     // Extract rule from job settings
     if let Some(settings) = job.settings {
         if let Some(matching_rules) = settings.get("matching_rules") {
             if let Some(rules_array) = matching_rules.as_array() {
-                if let Some(rule) = rules_array.get(_index) {
+                if let Some(rule) = rules_array.get(index) {
                     return Ok(HttpResponse::Ok().json(ApiResponse {
                         success: true,
                         data: Some(serde_json::json!({
@@ -975,17 +1015,7 @@ pub async fn update_rule(
     let user_id = extract_user_id(&http_req)?;
     let rule_id = path.into_inner();
 
-    // Parse job_id-index format
-    let parts: Vec<&str> = rule_id.split('-').collect();
-    if parts.len() < 2 {
-        return Err(AppError::Validation("Invalid rule ID format".to_string()));
-    }
-
-    let job_id = Uuid::parse_str(parts[0])
-        .map_err(|_| AppError::Validation("Invalid job ID in rule ID".to_string()))?;
-    let index: usize = parts[1]
-        .parse()
-        .map_err(|_| AppError::Validation("Invalid rule index".to_string()))?;
+    let (job_id, index) = parse_job_rule_id(&rule_id)?;
 
     use crate::models::schema::reconciliation_jobs;
     let mut conn = data.get_connection()?;
@@ -999,56 +1029,72 @@ pub async fn update_rule(
     let job = job.ok_or_else(|| AppError::NotFound("Job not found".to_string()))?;
 
     // Check authorization via project
-    check_project_permission(data.get_ref(), user_id, job.project_id)?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::Prepare)?;
 
-    // Update rule in job settings
-    let mut settings = job.settings.unwrap_or_else(|| serde_json::json!({}));
-    
-    // Update rule if it exists - collect rule data first
-    let updated_rule = if let Some(matching_rules) = settings.get_mut("matching_rules") {
-        if let Some(rules_array) = matching_rules.as_array_mut() {
-            if let Some(rule) = rules_array.get_mut(index) {
-                // Update rule fields
-                if let Some(field) = req.get("field").and_then(|v| v.as_str()) {
-                    rule["field"] = serde_json::json!(field);
-                }
-                if let Some(rule_type) = req.get("rule_type").and_then(|v| v.as_str()) {
-                    rule["rule_type"] = serde_json::json!(rule_type);
-                }
-                if let Some(weight) = req.get("weight").and_then(|v| v.as_f64()) {
-                    rule["weight"] = serde_json::json!(weight);
-                }
-                if let Some(threshold) = req.get("threshold").and_then(|v| v.as_f64()) {
-                    rule["threshold"] = serde_json::json!(threshold);
-                }
-                // Clone rule before mutable borrow ends
-                Some(rule.clone())
-            } else {
-                None
-            }
-        } else {
-            None
+    // Rule-type specific options are checked as on creation
+    if let Some(options) = req.get("options") {
+        serde_json::from_value::<crate::services::reconciliation::RuleOptions>(options.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid rule options: {}", e)))?;
+    }
+
+    // Read, update and audit the settings in one transaction, holding the
+    // job row so a concurrent change to its rules isn't overwritten
+    let ctx = AuditContext::from_request(&http_req).with_actor(user_id);
+    let rule = conn.transaction::<_, AppError, _>(|tx| {
+        let mut settings = reconciliation_jobs::table
+            .find(job_id)
+            .select(reconciliation_jobs::settings)
+            .for_update()
+            .first::<Option<serde_json::Value>>(tx)?
+            .unwrap_or_else(|| serde_json::json!({}));
+        let rule = settings
+            .get_mut("matching_rules")
+            .and_then(|rules| rules.as_array_mut())
+            .and_then(|rules| rules.get_mut(index))
+            .ok_or_else(|| AppError::NotFound("Rule not found".to_string()))?;
+        let before = rule.clone();
+        let entity_id = rule_entity_id(rule);
+
+        if let Some(field) = req.get("field").and_then(|v| v.as_str()) {
+            rule["field"] = serde_json::json!(field);
         }
-    } else {
-        None
-    };
-    
-    // Update job settings after mutable borrow is complete
-    if let Some(rule) = updated_rule {
+        if let Some(rule_type) = req.get("rule_type").and_then(|v| v.as_str()) {
+            rule["rule_type"] = serde_json::json!(rule_type);
+        }
+        if let Some(weight) = req.get("weight").and_then(|v| v.as_f64()) {
+            rule["weight"] = serde_json::json!(weight);
+        }
+        if let Some(threshold) = req.get("threshold").and_then(|v| v.as_f64()) {
+            rule["threshold"] = serde_json::json!(threshold);
+        }
+        if let Some(required) = req.get("required").and_then(|v| v.as_bool()) {
+            rule["required"] = serde_json::json!(required);
+        }
+        if let Some(options) = req.get("options") {
+            rule["options"] = options.clone();
+        }
+        let rule = rule.clone();
+
         diesel::update(reconciliation_jobs::table.find(job_id))
             .set(reconciliation_jobs::settings.eq(Some(settings)))
-            .execute(&mut conn)
-            .map_err(AppError::Database)?;
+            .execute(tx)?;
+        audit::append(
+            tx,
+            &ctx,
+            AuditEvent::new("rule.update", "matching_rule", Some(entity_id))
+                .with_change(Some(before), Some(rule.clone()))
+                .with_details(serde_json::json!({ "rule_id": rule_id, "job_id": job_id })),
+        )?;
+        Ok(rule)
+    })?;
+    audit::mark_audited(&http_req);
 
-        Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            message: Some("Rule updated successfully".to_string()),
-            error: None,
-        }))
-    } else {
-        Err(AppError::NotFound("Rule not found".to_string()))
-    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(rule),
+        message: Some("Rule updated successfully".to_string()),
+        error: None,
+    }))
 }
 
 /// Delete matching rule
@@ -1076,17 +1122,7 @@ pub async fn delete_rule(
     let user_id = extract_user_id(&http_req)?;
     let rule_id = path.into_inner();
 
-    // Parse job_id-index format
-    let parts: Vec<&str> = rule_id.split('-').collect();
-    if parts.len() < 2 {
-        return Err(AppError::Validation("Invalid rule ID format".to_string()));
-    }
-
-    let job_id = Uuid::parse_str(parts[0])
-        .map_err(|_| AppError::Validation("Invalid job ID in rule ID".to_string()))?;
-    let index: usize = parts[1]
-        .parse()
-        .map_err(|_| AppError::Validation("Invalid rule index".to_string()))?;
+    let (job_id, index) = parse_job_rule_id(&rule_id)?;
 
     use crate::models::schema::reconciliation_jobs;
     let mut conn = data.get_connection()?;
@@ -1100,98 +1136,296 @@ pub async fn delete_rule(
     let job = job.ok_or_else(|| AppError::NotFound("Job not found".to_string()))?;
 
     // Check authorization via project
-    check_project_permission(data.get_ref(), user_id, job.project_id)?;
-
-    // Remove rule from job settings
-    let mut settings = job.settings.unwrap_or_else(|| serde_json::json!({}));
-    if let Some(matching_rules) = settings.get_mut("matching_rules") {
-        if let Some(rules_array) = matching_rules.as_array_mut() {
-            if index < rules_array.len() {
-                rules_array.remove(index);
-
-                // Update job settings
-                diesel::update(reconciliation_jobs::table.find(job_id))
-                    .set(reconciliation_jobs::settings.eq(Some(settings)))
-                    .execute(&mut conn)
-                    .map_err(AppError::Database)?;
+    check_project_action(data.get_ref(), user_id, job.project_id, ProjectAction::Prepare)?;
+
+    // Remove the rule and record the change together, holding the job row
+    // so a concurrent change to its rules isn't overwritten
+    let ctx = AuditContext::from_request(&http_req).with_actor(user_id);
+    conn.transaction::<_, AppError, _>(|tx| {
+        let mut settings = reconciliation_jobs::table
+            .find(job_id)
+            .select(reconciliation_jobs::settings)
+            .for_update()
+            .first::<Option<serde_json::Value>>(tx)?
+            .unwrap_or_else(|| serde_json::json!({}));
+        let mut removed = settings
+            .get_mut("matching_rules")
+            .and_then(|rules| rules.as_array_mut())
+            .filter(|rules| index < rules.len())
+            .map(|rules| rules.remove(index))
+            .ok_or_else(|| AppError::NotFound("Rule not found".to_string()))?;
+        let entity_id = rule_entity_id(&mut removed);
 
-                return Ok(HttpResponse::NoContent().finish());
-            }
-        }
-    }
+        diesel::update(reconciliation_jobs::table.find(job_id))
+            .set(reconciliation_jobs::settings.eq(Some(settings)))
+            .execute(tx)?;
+        audit::append(
+            tx,
+            &ctx,
+            AuditEvent::new("rule.delete", "matching_rule", Some(entity_id))
+                .with_change(Some(removed), None)
+                .with_details(serde_json::json!({ "rule_id": rule_id, "job_id": job_id })),
+        )?;
+        Ok(())
+    })?;
+    audit::mark_audited(&http_req);
 
-    Err(AppError::NotFound("Rule not found".to_string()))
+    Ok(HttpResponse::NoContent().finish())
 }
 
-/// Test matching rule
+/// Job and index of a rule ID in the "{job_id}-{index}" format; the job ID
+/// has hyphens of its own, so the index is what follows the last one
+fn parse_job_rule_id(rule_id: &str) -> Result<(Uuid, usize), AppError> {
+    let (job_id, index) = rule_id
+        .rsplit_once('-')
+        .ok_or_else(|| AppError::Validation("Invalid rule ID format".to_string()))?;
+    let job_id = Uuid::parse_str(job_id)
+        .map_err(|_| AppError::Validation("Invalid job ID in rule ID".to_string()))?;
+    let index = index
+        .parse()
+        .map_err(|_| AppError::Validation("Invalid rule index".to_string()))?;
+    Ok((job_id, index))
+}
+
+/// ID a rule's changes are audited under, as on creation; a rule without
+/// one (set on its job rather than created) is given one to keep
+fn rule_entity_id(rule: &mut serde_json::Value) -> Uuid {
+    if let Some(id) = rule
+        .get("id")
+        .and_then(|id| id.as_str())
+        .and_then(|id| Uuid::parse_str(id).ok())
+    {
+        return id;
+    }
+    let id = Uuid::new_v4();
+    if let Some(rule) = rule.as_object_mut() {
+        rule.insert("id".to_string(), serde_json::json!(id));
+    }
+    id
+}
+
+/// Test matching rules
+///
+/// Scores two sample records, or a sample of source A against source B, with
+/// the real blocking, scoring and assignment stages. Nothing is persisted.
+#[utoipa::path(
+    post,
+    path = "/api/v1/reconciliation/rules/test",
+    tag = "Reconciliation",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "Rule test completed", body = ApiResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse),
+        (status = 404, description = "Data source not found", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn test_rule(
-    _req: web::Json<serde_json::Value>,
-    _data: web::Data<Database>,
+    req: web::Json<serde_json::Value>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
+    use crate::services::reconciliation::dry_run;
+
+    let user_id = extract_user_id(&http_req)?;
+    let request: dry_run::RuleTestRequest = serde_json::from_value(req.into_inner())
+        .map_err(|e| AppError::Validation(format!("Invalid rule test request: {}", e)))?;
+
+    // Sample data may only come from projects the user can access
+    for data_source_id in [request.source_a_id, request.source_b_id].into_iter().flatten() {
+        let data_source =
+            crate::services::reconciliation::load_data_source(data.get_ref(), data_source_id)?;
+        check_project_action(data.get_ref(), user_id, data_source.project_id, ProjectAction::Prepare)?;
+    }
+
+    let report = dry_run::test_rules(data.get_ref(), &request).await?;
+
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
-        data: Some(serde_json::json!({
-            "matches": false,
-            "confidence_score": 0.0
-        })),
+        data: Some(serde_json::to_value(&report).map_err(AppError::Serialization)?),
         message: Some("Rule test completed".to_string()),
         error: None,
     }))
 }
 
 /// List batches
+///
+/// Lists the reconciliation batches of a project with their combined summary.
+#[utoipa::path(
+    get,
+    path = "/api/v1/reconciliation/batches",
+    tag = "Reconciliation",
+    params(
+        ("project_id" = Uuid, Query, description = "Project whose batches are listed"),
+        ("page" = Option<i32>, Query, description = "Page number (1-based)"),
+        ("per_page" = Option<i32>, Query, description = "Items per page (max 100)")
+    ),
+    responses(
+        (status = 200, description = "Batches retrieved successfully", body = PaginatedResponse),
+        (status = 400, description = "Missing or invalid project_id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_batches(
     query: web::Query<SearchQueryParams>,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let batches: Vec<serde_json::Value> = vec![];
-    let total_pages = 0;
-    
+    let user_id = extract_user_id(&http_req)?;
+    let project_id = query
+        .project_id
+        .as_deref()
+        .ok_or_else(|| AppError::Validation("project_id is required".to_string()))
+        .and_then(|id| {
+            Uuid::parse_str(id)
+                .map_err(|e| AppError::Validation(format!("Invalid project_id: {}", e)))
+        })?;
+    check_project_permission(data.get_ref(), user_id, project_id)?;
+
+    let page = query.page.unwrap_or(1).max(1) as i64;
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100) as i64;
+
+    let reconciliation_service =
+        crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
+    let (batches, total) = reconciliation_service.list_batches(project_id, page, per_page)?;
+    let items = batches
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::Serialization)?;
+
+    let total_pages = (total as f64 / per_page as f64).ceil() as i32;
     let paginated = PaginatedResponse {
-        items: batches,
-        total: 0,
-        page: query.page.unwrap_or(1),
-        per_page: query.per_page.unwrap_or(20),
+        items,
+        total,
+        page: page as i32,
+        per_page: per_page as i32,
         total_pages,
     };
-    
+
     Ok(HttpResponse::Ok().json(paginated))
 }
 
 /// Create batch
+///
+/// Groups existing jobs of one project into a batch that is processed and
+/// reported on together.
+#[utoipa::path(
+    post,
+    path = "/api/v1/reconciliation/batches",
+    tag = "Reconciliation",
+    request_body = serde_json::Value,
+    responses(
+        (status = 201, description = "Batch created successfully", body = ApiResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse),
+        (status = 404, description = "Job not found in project", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create_batch(
-    _req: web::Json<serde_json::Value>,
-    _data: web::Data<Database>,
+    req: web::Json<serde_json::Value>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
+    use crate::services::reconciliation::CreateBatchRequest;
+
+    let user_id = extract_user_id(&http_req)?;
+    let request: CreateBatchRequest = serde_json::from_value(req.into_inner())
+        .map_err(|e| AppError::Validation(format!("Invalid batch request: {}", e)))?;
+    check_project_action(data.get_ref(), user_id, request.project_id, ProjectAction::Prepare)?;
+
+    let reconciliation_service =
+        crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
+    let batch = reconciliation_service.create_batch(user_id, request).await?;
+
     Ok(HttpResponse::Created().json(ApiResponse {
         success: true,
-        data: Some(serde_json::json!({
-            "id": Uuid::new_v4(),
-            "status": "queued"
-        })),
+        data: Some(serde_json::to_value(&batch).map_err(AppError::Serialization)?),
         message: Some("Batch created successfully".to_string()),
         error: None,
     }))
 }
 
 /// Get batch
+///
+/// Returns a batch with its status, per-job progress and combined summary.
+#[utoipa::path(
+    get,
+    path = "/api/v1/reconciliation/batches/{id}",
+    tag = "Reconciliation",
+    params(
+        ("id" = Uuid, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 200, description = "Batch retrieved successfully", body = ApiResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse),
+        (status = 404, description = "Batch not found", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_batch(
     path: web::Path<Uuid>,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let _batch_id = path.into_inner();
-    return Err(AppError::NotFound("Batch not found".to_string()));
+    let user_id = extract_user_id(&http_req)?;
+    let reconciliation_service =
+        crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
+    let batch = reconciliation_service.get_batch(path.into_inner())?;
+    check_project_permission(data.get_ref(), user_id, batch.batch.project_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::to_value(&batch).map_err(AppError::Serialization)?),
+        message: None,
+        error: None,
+    }))
 }
 
 /// Process batch
+///
+/// Starts every job of the batch, in order or in parallel according to the
+/// batch's execution mode. Progress is reported through `get_batch`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/reconciliation/batches/{id}/process",
+    tag = "Reconciliation",
+    params(
+        ("id" = Uuid, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 202, description = "Batch processing started", body = ApiResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - no project access", body = ErrorResponse),
+        (status = 404, description = "Batch not found", body = ErrorResponse),
+        (status = 409, description = "Batch or one of its jobs is already running", body = ErrorResponse)
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn process_batch(
     path: web::Path<Uuid>,
-    _data: web::Data<Database>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
-    let _batch_id = path.into_inner();
-    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+    let user_id = extract_user_id(&http_req)?;
+    let batch_id = path.into_inner();
+    let reconciliation_service =
+        crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
+    let batch = reconciliation_service.get_batch(batch_id)?;
+    check_project_action(data.get_ref(), user_id, batch.batch.project_id, ProjectAction::Prepare)?;
+
+    let batch = reconciliation_service.process_batch(batch_id).await?;
+
+    Ok(HttpResponse::Accepted().json(ApiResponse {
         success: true,
-        data: None,
+        data: Some(serde_json::to_value(&batch).map_err(AppError::Serialization)?),
         message: Some("Batch processing started".to_string()),
         error: None,
     }))
@@ -7,7 +7,9 @@ use crate::database::Database;
 use crate::errors::AppError;
 use crate::handlers::helpers::extract_user_id;
 use crate::handlers::types::{ApiResponse, ReconciliationResultsQuery};
-use crate::services::reconciliation::service::MatchResolve;
+use crate::services::auth::ProjectAction;
+use crate::services::reconciliation::service::{MatchResolve, MatchUpdate};
+use crate::services::reconciliation::MatchStatus;
 
 #[derive(serde::Deserialize)]
 pub struct BatchResolveRequest {
@@ -23,12 +25,29 @@ pub async fn batch_resolve_conflicts(
     _config: web::Data<crate::config::Config>,
 ) -> Result<HttpResponse, AppError> {
     let user_id = extract_user_id(&http_req)?;
+
+    // Every match must be in a project where the user may review
+    let mut checked = std::collections::HashSet::new();
+    for resolve in &req.resolves {
+        let project_id = crate::utils::get_project_id_from_match(data.get_ref(), resolve.match_id)?;
+        if checked.insert(project_id) {
+            crate::utils::check_project_action(
+                data.get_ref(),
+                user_id,
+                project_id,
+                ProjectAction::Review,
+            )?;
+        }
+    }
+
     let reconciliation_service =
         crate::services::reconciliation::ReconciliationService::new(data.get_ref().clone());
 
+    let audit = crate::services::audit::AuditContext::from_request(&http_req).with_actor(user_id);
     let result = reconciliation_service
-        .batch_approve_matches(user_id, req.resolves.clone())
+        .batch_approve_matches(&audit, req.resolves.clone())
         .await?;
+    crate::services::audit::mark_audited(&http_req);
 
     // Best-effort cache invalidation for affected jobs/projects if present in result
     // (Service can include affected ids in the future)
@@ -38,11 +57,13 @@ pub async fn batch_resolve_conflicts(
         data: Some(serde_json::json!({
             "approved": result.approved,
             "rejected": result.rejected,
+            "pending_approval": result.pending_approval,
             "errors": result.errors,
         })),
         message: Some(format!(
-            "Resolved {} matches",
-            result.approved + result.rejected
+            "Resolved {} matches, {} awaiting approval",
+            result.approved + result.rejected,
+            result.pending_approval
         )),
         error: None,
     }))
@@ -123,27 +144,66 @@ pub async fn update_reconciliation_match(
     match_id: web::Path<Uuid>,
     req: web::Json<serde_json::Value>,
     http_req: HttpRequest,
-    _data: web::Data<Database>,
+    data: web::Data<Database>,
     reconciliation_service: web::Data<crate::services::reconciliation::ReconciliationService>,
 ) -> Result<HttpResponse, AppError> {
-    // Note: _data parameter is intentionally unused - database access via reconciliation_service
     let user_id = extract_user_id(&http_req)?;
     let match_id_val = match_id.into_inner();
 
+    let project_id = crate::utils::get_project_id_from_match(data.get_ref(), match_id_val)?;
+    crate::utils::check_project_action(data.get_ref(), user_id, project_id, ProjectAction::Review)?;
+
     // Extract update data
-    let status = req.get("status").and_then(|s| s.as_str());
+    let status = req
+        .get("status")
+        .and_then(|s| s.as_str())
+        .map(str::parse::<MatchStatus>)
+        .transpose()?;
     let confidence_score = req.get("confidence_score").and_then(|c| c.as_f64());
-    let reviewed_by = req.get("reviewed_by").and_then(|r| r.as_str());
+    let record_b_id = match req.get("record_b_id").and_then(|r| r.as_str()) {
+        Some(id) => Some(
+            Uuid::parse_str(id)
+                .map_err(|_| AppError::Validation("Invalid record_b_id".to_string()))?,
+        ),
+        None => None,
+    };
+    let notes = req.get("notes").and_then(|n| n.as_str()).map(str::to_string);
+    // The reviewer is whoever makes the change; it can't be attributed to someone else
+    if let Some(reviewed_by) = req.get("reviewed_by").and_then(|r| r.as_str()) {
+        if Uuid::parse_str(reviewed_by).ok() != Some(user_id) {
+            return Err(AppError::Validation(
+                "reviewed_by must be the current user".to_string(),
+            ));
+        }
+    }
 
     // Update the match
+    let audit = crate::services::audit::AuditContext::from_request(&http_req).with_actor(user_id);
     let updated_match = reconciliation_service
-        .update_match(user_id, match_id_val, status, confidence_score, reviewed_by)
+        .update_match(
+            &audit,
+            user_id,
+            match_id_val,
+            MatchUpdate {
+                status,
+                confidence_score,
+                record_b_id,
+                notes,
+            },
+        )
         .await?;
+    crate::services::audit::mark_audited(&http_req);
+
+    let message = if updated_match.approval_id.is_some() {
+        "Match change submitted for approval"
+    } else {
+        "Match updated successfully"
+    };
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(updated_match),
-        message: Some("Match updated successfully".to_string()),
+        message: Some(message.to_string()),
         error: None,
     }))
 }
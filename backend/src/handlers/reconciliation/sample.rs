@@ -28,7 +28,13 @@ pub async fn start_sample_onboarding(
     ws_server: web::Data<Addr<WsServer>>,
 ) -> Result<HttpResponse, AppError> {
     let user_id = extract_user_id(&http_req)?;
-    crate::utils::check_project_permission(data.get_ref(), user_id, req.project_id)?;
+    // Creates data sources and a job, so it takes more than read access
+    crate::utils::check_project_action(
+        data.get_ref(),
+        user_id,
+        req.project_id,
+        crate::services::auth::ProjectAction::Prepare,
+    )?;
 
     // Create two sample data sources pointing to bundled files
     let ds_service = crate::services::data_source::DataSourceService::new(data.get_ref().clone());
@@ -85,6 +91,10 @@ pub async fn start_sample_onboarding(
         source_b_id: ds_b.id,
         confidence_threshold: req.confidence_threshold.unwrap_or(0.8),
         matching_rules: vec![],
+        blocking: Default::default(),
+        assignment: Default::default(),
+        aggregation: None,
+        parallelism: None,
     };
     let job_status = recon_service
         .create_reconciliation_job(user_id, job_req)
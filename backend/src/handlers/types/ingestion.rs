@@ -17,18 +17,77 @@ pub struct UploadDataRequest {
 #[derive(Debug, Deserialize, Validate, utoipa::ToSchema)]
 pub struct ProcessDataRequest {
     pub job_id: Uuid,
+    /// Uploaded file to ingest; defaults to the job's `source_config.file_id`
+    #[serde(default)]
+    pub file_id: Option<Uuid>,
+    /// Data source to update with the record count and statement balances
+    #[serde(default)]
+    pub data_source_id: Option<Uuid>,
 }
 
 /// Validate data request
 #[derive(Debug, Deserialize, Validate, utoipa::ToSchema)]
 pub struct ValidateDataRequest {
     pub job_id: Uuid,
+    /// Record rules to run; they are kept on the job for later runs. Defaults
+    /// to the job's `source_config.validation_rules`, else the built-in rules.
+    #[serde(default)]
+    pub rules: Option<serde_json::Value>,
+    /// Page of each rule's findings (1-based)
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: Option<i64>,
 }
 
 /// Transform data request
+///
+/// Previews a transformation spec against the first rows of the job's file
+/// and, with `save`, stores it as the data source's next mapping version.
 #[derive(Debug, Deserialize, Validate, utoipa::ToSchema)]
 pub struct TransformDataRequest {
     pub job_id: Uuid,
+    /// Spec to preview; defaults to the one the job would run
     pub transformation_rules: Option<serde_json::Value>,
+    /// Data source to save the spec to; defaults to the job's `source_config.data_source_id`
+    #[serde(default)]
+    pub data_source_id: Option<Uuid>,
+    #[serde(default)]
+    pub save: bool,
+    #[serde(default = "default_preview_rows")]
+    #[validate(range(min = 1, max = 500))]
+    pub preview_rows: usize,
 }
 
+fn default_preview_rows() -> usize {
+    20
+}
+
+/// Quarantined duplicates query
+#[derive(Debug, Deserialize, Validate, utoipa::ToSchema)]
+pub struct QuarantineQuery {
+    /// pending, released or discarded; all of them when omitted
+    pub status: Option<String>,
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: Option<i64>,
+}
+
+/// What to do with a quarantined duplicate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineAction {
+    /// Insert it into the job's records after all
+    Release,
+    /// Keep it out of the records
+    Discard,
+}
+
+/// Resolve quarantined record request
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct ResolveQuarantineRequest {
+    pub action: QuarantineAction,
+}
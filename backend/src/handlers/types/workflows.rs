@@ -11,13 +11,10 @@ pub struct CreateInstanceRequest {
     pub data: serde_json::Value,
 }
 
-/// Update workflow instance request
-#[derive(Debug, Deserialize, utoipa::ToSchema)]
-pub struct UpdateInstanceRequest {
-    pub status: Option<String>,
-    pub current_step: Option<String>,
-    pub state: Option<serde_json::Value>,
-    pub error_message: Option<String>,
+/// Approve or reject a workflow approval step
+#[derive(Debug, Default, Deserialize, utoipa::ToSchema)]
+pub struct DecideStepRequest {
+    pub notes: Option<String>,
 }
 
 /// Create workflow rule request
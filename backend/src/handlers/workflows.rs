@@ -11,18 +11,18 @@ use crate::handlers::helpers::extract_user_id;
 use crate::handlers::types::{
     ApiResponse, PaginatedResponse, SearchQueryParams,
     workflows::{
-        CreateInstanceRequest, UpdateInstanceRequest,
+        CreateInstanceRequest, DecideStepRequest,
         CreateRuleRequest, UpdateRuleRequest, TestRuleRequest,
     },
 };
+use crate::services::auth::ProjectAction;
 use crate::services::cache::MultiLevelCache;
-use crate::services::workflow::WorkflowService;
+use crate::services::workflow::{Condition, RuleAction, WorkflowDefinition, WorkflowEngine, WorkflowService};
+use crate::utils::{accessible_project_ids, check_admin_permission, check_project_action};
 use crate::models::{
     NewWorkflow,
-    NewWorkflowInstance,
     NewWorkflowRule,
     UpdateWorkflow,
-    UpdateWorkflowInstance,
     UpdateWorkflowRule,
     WorkflowInstance,
     WorkflowRule,
@@ -41,8 +41,11 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .route("/instances", web::get().to(list_instances))
         .route("/instances", web::post().to(create_instance))
         .route("/instances/{id}", web::get().to(get_instance))
-        .route("/instances/{id}", web::put().to(update_instance))
         .route("/instances/{id}/cancel", web::post().to(cancel_instance))
+        .route("/instances/{id}/retry", web::post().to(retry_instance))
+        .route("/instances/{id}/transitions", web::get().to(list_transitions))
+        .route("/instances/{id}/steps/{step}/approve", web::post().to(approve_step))
+        .route("/instances/{id}/steps/{step}/reject", web::post().to(reject_step))
         // Workflow rules
         .route("/rules", web::get().to(list_rules))
         .route("/rules", web::post().to(create_rule))
@@ -67,6 +70,9 @@ pub struct Workflow {
 pub struct CreateWorkflowRequest {
     pub name: String,
     pub description: Option<String>,
+    /// Scopes triggers to the project's events; managing it then takes the
+    /// project's Manage role, and without one it takes an admin
+    pub project_id: Option<Uuid>,
     pub definition: serde_json::Value,
 }
 
@@ -75,20 +81,44 @@ pub struct UpdateWorkflowRequest {
     pub name: Option<String>,
     pub description: Option<String>,
     pub definition: Option<serde_json::Value>,
+    /// `draft`, `active` or `archived`; only active workflows run
+    pub status: Option<String>,
+}
+
+/// Statuses a workflow can be set to
+const WORKFLOW_STATUSES: &[&str] = &["draft", "active", "archived"];
+
+/// Check a user may manage or act on a workflow: by the workflow's project
+/// role, or as an admin for workflows outside a project
+fn check_workflow_action(
+    db: &Database,
+    user_id: Uuid,
+    project_id: Option<Uuid>,
+    action: ProjectAction,
+) -> Result<(), AppError> {
+    match project_id {
+        Some(project_id) => check_project_action(db, user_id, project_id, action),
+        None => check_admin_permission(db, user_id),
+    }
 }
 
 /// List workflows
 pub async fn list_workflows(
     query: web::Query<SearchQueryParams>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
     _cache: web::Data<MultiLevelCache>,
 ) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1) as i64;
     let per_page = query.per_page.unwrap_or(20).min(100) as i64;
+    let user_id = extract_user_id(&http_req)?;
+    // Admins see every workflow; others those of the projects they can see
+    let project_ids = accessible_project_ids(data.get_ref(), user_id)?;
     
     let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let (workflows, total) = workflow_service.list_workflows(page, per_page).await?;
+    let (workflows, total) = workflow_service
+        .list_workflows(project_ids.as_deref(), page, per_page)
+        .await?;
     
     let total_pages = (total as f64 / per_page as f64).ceil() as i32;
     
@@ -110,11 +140,12 @@ pub async fn create_workflow(
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let user_id = extract_user_id(&http_req)?;
+    check_workflow_action(data.get_ref(), user_id, req.project_id, ProjectAction::Manage)?;
     
     let new_workflow = NewWorkflow {
         name: req.name.clone(),
         description: req.description.clone(),
-        project_id: None,
+        project_id: req.project_id,
         definition: req.definition.clone(),
         status: "draft".to_string(),
         created_by: user_id,
@@ -134,12 +165,14 @@ pub async fn create_workflow(
 /// Get workflow
 pub async fn get_workflow(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
     let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
     let workflow = workflow_service.get_workflow(workflow_id).await?;
+    check_workflow_action(data.get_ref(), user_id, workflow.project_id, ProjectAction::View)?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -153,18 +186,36 @@ pub async fn get_workflow(
 pub async fn update_workflow(
     path: web::Path<Uuid>,
     req: web::Json<UpdateWorkflowRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
+    let current = workflow_service.get_workflow(workflow_id).await?;
+    check_workflow_action(data.get_ref(), user_id, current.project_id, ProjectAction::Manage)?;
+
+    if let Some(status) = &req.status {
+        if !WORKFLOW_STATUSES.contains(&status.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Invalid workflow status '{}'; expected one of {}",
+                status,
+                WORKFLOW_STATUSES.join(", ")
+            )));
+        }
+    }
+    // A workflow can only be active with a definition the engine can run
+    if req.status.as_deref().unwrap_or(&current.status) == "active" {
+        WorkflowDefinition::parse(req.definition.as_ref().unwrap_or(&current.definition))?;
+    }
+
     let update = UpdateWorkflow {
         name: req.name.clone(),
         description: req.description.clone(),
         definition: req.definition.clone(),
-        status: None,
+        status: req.status.clone(),
     };
     
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
     let workflow = workflow_service.update_workflow(workflow_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -178,11 +229,14 @@ pub async fn update_workflow(
 /// Delete workflow
 pub async fn delete_workflow(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let workflow_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
     let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
+    let workflow = workflow_service.get_workflow(workflow_id).await?;
+    check_workflow_action(data.get_ref(), user_id, workflow.project_id, ProjectAction::Manage)?;
     workflow_service.delete_workflow(workflow_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -218,23 +272,19 @@ pub async fn create_instance(
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
     let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    let workflow = WorkflowService::new(Arc::clone(&db)).get_workflow(req.workflow_id).await?;
+    check_workflow_action(data.get_ref(), user_id, workflow.project_id, ProjectAction::Prepare)?;
     
-    let new_instance = NewWorkflowInstance {
-        workflow_id: req.workflow_id,
-        status: "pending".to_string(),
-        current_step: None,
-        state: req.data.clone(),
-        started_by: Some(user_id),
-        started_at: Some(chrono::Utc::now()),
-    };
-    
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let instance = workflow_service.create_instance(new_instance).await?;
+    // Runs until the instance waits on a job or an approval, or ends
+    let instance = WorkflowEngine::new(db)
+        .start(req.workflow_id, Some(user_id), req.data.clone())
+        .await?;
     
     Ok(HttpResponse::Created().json(ApiResponse {
         success: true,
         data: Some(instance),
-        message: Some("Workflow instance created successfully".to_string()),
+        message: Some("Workflow instance started".to_string()),
         error: None,
     }))
 }
@@ -242,12 +292,14 @@ pub async fn create_instance(
 /// Get workflow instance
 pub async fn get_instance(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let instance_id = path.into_inner();
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let instance = workflow_service.get_instance(instance_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_instance_action(&db, user_id, instance_id, ProjectAction::View).await?;
+    let instance = WorkflowService::new(db).get_instance(instance_id).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -257,53 +309,129 @@ pub async fn get_instance(
     }))
 }
 
-/// Update workflow instance
-pub async fn update_instance(
+/// Check a user may act on an instance; whoever started it may also
+/// cancel, retry and view it while they can still see its project, but not
+/// approve its steps
+async fn check_instance_action(
+    db: &Arc<Database>,
+    user_id: Uuid,
+    instance_id: Uuid,
+    action: ProjectAction,
+) -> Result<(), AppError> {
+    let workflow_service = WorkflowService::new(Arc::clone(db));
+    let instance = workflow_service.get_instance(instance_id).await?;
+    let workflow = workflow_service.get_workflow(instance.workflow_id).await?;
+    let action = if action != ProjectAction::Approve && instance.started_by == Some(user_id) {
+        ProjectAction::View
+    } else {
+        action
+    };
+    check_workflow_action(db, user_id, workflow.project_id, action)
+}
+
+/// Cancel workflow instance
+pub async fn cancel_instance(
     path: web::Path<Uuid>,
-    req: web::Json<UpdateInstanceRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let instance_id = path.into_inner();
-    let update = UpdateWorkflowInstance {
-        status: req.status.clone(),
-        current_step: req.current_step.clone(),
-        state: req.state.clone(),
-        completed_at: None,
-        error_message: req.error_message.clone(),
-    };
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_instance_action(&db, user_id, instance_id, ProjectAction::Manage).await?;
     
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let instance = workflow_service.update_instance(instance_id, update).await?;
+    WorkflowEngine::new(db).cancel(instance_id, Some(user_id))?;
+    
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        data: None,
+        message: Some("Workflow instance cancelled".to_string()),
+        error: None,
+    }))
+}
+
+/// Retry a failed workflow instance from the step that failed
+pub async fn retry_instance(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let instance_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_instance_action(&db, user_id, instance_id, ProjectAction::Manage).await?;
+    
+    let instance = WorkflowEngine::new(db).retry(instance_id, user_id).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(instance),
-        message: Some("Workflow instance updated successfully".to_string()),
+        message: Some("Workflow instance retried".to_string()),
         error: None,
     }))
 }
 
-/// Cancel workflow instance
-pub async fn cancel_instance(
+/// Approve the approval step a workflow instance is waiting on
+pub async fn approve_step(
+    path: web::Path<(Uuid, String)>,
+    req: Option<web::Json<DecideStepRequest>>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    decide_step(path.into_inner(), req, http_req, data, true).await
+}
+
+/// Reject the approval step a workflow instance is waiting on
+pub async fn reject_step(
+    path: web::Path<(Uuid, String)>,
+    req: Option<web::Json<DecideStepRequest>>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    decide_step(path.into_inner(), req, http_req, data, false).await
+}
+
+async fn decide_step(
+    (instance_id, step): (Uuid, String),
+    req: Option<web::Json<DecideStepRequest>>,
+    http_req: HttpRequest,
+    data: web::Data<Database>,
+    approved: bool,
+) -> Result<HttpResponse, AppError> {
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_instance_action(&db, user_id, instance_id, ProjectAction::Approve).await?;
+    
+    let notes = req.and_then(|req| req.into_inner().notes);
+    let instance = WorkflowEngine::new(db)
+        .decide(instance_id, &step, user_id, approved, notes)
+        .await?;
+    
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(instance),
+        message: Some(if approved { "Step approved" } else { "Step rejected" }.to_string()),
+        error: None,
+    }))
+}
+
+/// List a workflow instance's transitions, oldest first
+pub async fn list_transitions(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let instance_id = path.into_inner();
-    let update = UpdateWorkflowInstance {
-        status: Some("cancelled".to_string()),
-        completed_at: Some(chrono::Utc::now()),
-        ..Default::default()
-    };
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_instance_action(&db, user_id, instance_id, ProjectAction::View).await?;
     
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    workflow_service.update_instance(instance_id, update).await?;
+    let transitions = WorkflowEngine::new(db).transitions(instance_id)?;
     
-    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+    Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
-        data: None,
-        message: Some("Workflow instance cancelled".to_string()),
+        data: Some(transitions),
+        message: None,
         error: None,
     }))
 }
@@ -326,13 +454,29 @@ pub async fn list_rules(
     Ok(HttpResponse::Ok().json(paginated))
 }
 
+/// Check a user may take `action` on a rule's workflow
+async fn check_rule_action(
+    db: &Arc<Database>,
+    user_id: Uuid,
+    workflow_id: Uuid,
+    action: ProjectAction,
+) -> Result<(), AppError> {
+    let workflow = WorkflowService::new(Arc::clone(db)).get_workflow(workflow_id).await?;
+    check_workflow_action(db, user_id, workflow.project_id, action)
+}
+
 /// Create workflow rule
 pub async fn create_rule(
     req: web::Json<CreateRuleRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    check_rule_action(&db, user_id, req.workflow_id, ProjectAction::Manage).await?;
+    Condition::parse(&req.condition)?;
+    RuleAction::parse(&req.action)?;
     
     let new_rule = NewWorkflowRule {
         workflow_id: req.workflow_id,
@@ -343,8 +487,7 @@ pub async fn create_rule(
         is_active: req.active.unwrap_or(true),
     };
     
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let rule = workflow_service.create_rule(new_rule).await?;
+    let rule = WorkflowService::new(db).create_rule(new_rule).await?;
     
     Ok(HttpResponse::Created().json(ApiResponse {
         success: true,
@@ -357,12 +500,14 @@ pub async fn create_rule(
 /// Get workflow rule
 pub async fn get_rule(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let rule_id = path.into_inner();
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
-    let rule = workflow_service.get_rule(rule_id).await?;
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    let rule = WorkflowService::new(Arc::clone(&db)).get_rule(rule_id).await?;
+    check_rule_action(&db, user_id, rule.workflow_id, ProjectAction::View).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -376,10 +521,21 @@ pub async fn get_rule(
 pub async fn update_rule(
     path: web::Path<Uuid>,
     req: web::Json<UpdateRuleRequest>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let rule_id = path.into_inner();
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    let workflow_service = WorkflowService::new(Arc::clone(&db));
+    let current = workflow_service.get_rule(rule_id).await?;
+    check_rule_action(&db, user_id, current.workflow_id, ProjectAction::Manage).await?;
+    if let Some(condition) = &req.condition {
+        Condition::parse(condition)?;
+    }
+    if let Some(action) = &req.action {
+        RuleAction::parse(action)?;
+    }
     let update = UpdateWorkflowRule {
         name: req.name.clone(),
         condition: req.condition.clone(),
@@ -388,7 +544,6 @@ pub async fn update_rule(
         is_active: req.active,
     };
     
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
     let rule = workflow_service.update_rule(rule_id, update).await?;
     
     Ok(HttpResponse::Ok().json(ApiResponse {
@@ -402,11 +557,15 @@ pub async fn update_rule(
 /// Delete workflow rule
 pub async fn delete_rule(
     path: web::Path<Uuid>,
-    _http_req: HttpRequest,
+    http_req: HttpRequest,
     data: web::Data<Database>,
 ) -> Result<HttpResponse, AppError> {
     let rule_id = path.into_inner();
-    let workflow_service = WorkflowService::new(Arc::new(data.get_ref().clone()));
+    let user_id = extract_user_id(&http_req)?;
+    let db = Arc::new(data.get_ref().clone());
+    let workflow_service = WorkflowService::new(Arc::clone(&db));
+    let rule = workflow_service.get_rule(rule_id).await?;
+    check_rule_action(&db, user_id, rule.workflow_id, ProjectAction::Manage).await?;
     workflow_service.delete_rule(rule_id).await?;
     Ok(HttpResponse::NoContent().finish())
 }
@@ -419,12 +578,16 @@ pub async fn test_rule(
 ) -> Result<HttpResponse, AppError> {
     req.validate().map_err(|e| AppError::Validation(format!("Validation error: {:?}", e)))?;
     
-    // Basic rule testing - in production this would evaluate the rule against test_data
+    // `rule` is a rule (`condition` and `action`) or just a condition
+    let condition = Condition::parse(req.rule.get("condition").unwrap_or(&req.rule))?;
+    let action = req.rule.get("action").map(RuleAction::parse).transpose()?;
+    let matches = condition.evaluate(&req.test_data);
+    
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
         data: Some(serde_json::json!({
-            "matches": false,
-            "result": null
+            "matches": matches,
+            "result": if matches { action } else { None }
         })),
         message: Some("Rule test completed".to_string()),
         error: None,
@@ -11,6 +11,7 @@ use reconciliation_backend::{
     handlers,
     middleware::{
         api_versioning::{ApiVersioningConfig, ApiVersioningMiddleware},
+        audit::AuditMiddleware,
         correlation_id::CorrelationIdMiddleware,
         error_handler::ErrorHandlerMiddleware,
         rate_limit::PerEndpointRateLimitMiddleware,
@@ -446,6 +447,42 @@ async fn async_main() -> std::io::Result<()> {
     let metrics_service = Arc::new(MetricsService::new());
     log::info!("Metrics service initialized");
 
+    // Email goes through the database outbox; every replica runs an outbox
+    // worker and they share the outbox table
+    let email_service = {
+        use reconciliation_backend::services::email::EmailService;
+        let email_service = Arc::new(
+            EmailService::new_with_resilience(resilience.clone()).with_outbox(Arc::new(database.clone())),
+        );
+        if let Some(worker) = email_service.outbox_worker() {
+            worker.spawn();
+        }
+        log::info!("Email service initialized: {:?}", email_service);
+        email_service
+    };
+
+    // Start the reconciliation queue worker; every replica runs one and they
+    // share the durable queue
+    {
+        use reconciliation_backend::services::reconciliation::{shared_job_processor, QueueWorker};
+        let processor = shared_job_processor();
+        let worker_concurrency = processor.max_concurrent_jobs;
+        QueueWorker::new(database.clone(), processor)
+            .with_email(email_service.clone())
+            .spawn();
+        log::info!(
+            "Reconciliation queue worker started ({} concurrent jobs)",
+            worker_concurrency
+        );
+    }
+
+    // Workflow steps that failed with attempts left run again from here
+    {
+        use reconciliation_backend::services::workflow::WorkflowEngine;
+        WorkflowEngine::spawn_retry_worker(Arc::new(database.clone()));
+        log::info!("Workflow retry worker started");
+    }
+
     // Clone config for use in HttpServer closure
     let config_clone = config.clone();
 
@@ -472,6 +509,7 @@ async fn async_main() -> std::io::Result<()> {
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+        // API keys are authenticated whatever this says; it only covers JWTs
         require_identity_verification: is_production_env, // Disable in development
         enforce_least_privilege: is_production_env,       // Disable in development
         network_segmentation: is_production_env,
@@ -536,6 +574,9 @@ async fn async_main() -> std::io::Result<()> {
         let auth_rate_limit_config = reconciliation_backend::middleware::AuthRateLimitConfig::default();
 
         actix_web::App::new()
+            // Record audit entries for mutating requests (innermost, so it sees
+            // the handler's response and the identity set by outer middleware)
+            .wrap(AuditMiddleware)
             // Add correlation ID middleware (must be first to propagate IDs)
             .wrap(CorrelationIdMiddleware)
             // Add error handler middleware (ensures correlation IDs in error responses)
@@ -583,6 +624,7 @@ async fn async_main() -> std::io::Result<()> {
             ))
             // Add metrics service (required by metrics handlers)
             .app_data(web::Data::new(metrics_service.clone()))
+            .app_data(web::Data::new(email_service.clone()))
             // Add WebSocket server (required by WebSocket handlers)
             // Note: ws_server is created in this closure to ensure it runs in Actix runtime context
             .app_data(web::Data::new(ws_server))
@@ -0,0 +1,167 @@
+//! Audit Middleware
+//!
+//! Records an audit entry for every successful mutating request (POST, PUT,
+//! PATCH, DELETE) whose handler didn't record a more specific one itself
+//! (see `services::audit::mark_audited`). The entry names the route pattern
+//! and the first UUID in the path as the entity; the body is not recorded.
+//! Appending waits on the chain lock, so it runs on the blocking pool rather
+//! than holding up the worker.
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    web, Error, HttpMessage,
+};
+use futures::future::{ok, Ready};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::services::audit::{self, AuditContext, AuditEvent, Audited};
+
+/// Audit middleware
+pub struct AuditMiddleware;
+
+impl<S> Transform<S, ServiceRequest> for AuditMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuditService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuditService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+/// Audit service
+pub struct AuditService<S> {
+    service: Rc<S>,
+}
+
+impl<S> Service<ServiceRequest> for AuditService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let mutating = matches!(
+            *req.method(),
+            Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+        );
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            if !mutating
+                || !res.status().is_success()
+                || res.request().extensions().get::<Audited>().is_some()
+            {
+                return Ok(res);
+            }
+
+            let request = res.request();
+            let Some(db) = request.app_data::<web::Data<Database>>() else {
+                return Ok(res);
+            };
+            let path = request.path();
+            let pattern = request.match_pattern().unwrap_or_else(|| path.to_string());
+            let event = AuditEvent::new(
+                format!("{} {}", request.method(), pattern),
+                resource_type(&pattern),
+                first_uuid(path),
+            )
+            .with_details(serde_json::json!({ "path": path, "status": res.status().as_u16() }));
+            let ctx = AuditContext::from_request(request);
+
+            let correlation_id = ctx.correlation_id.clone();
+            let db = db.clone();
+            let appended = web::block(move || {
+                db.get_connection()
+                    .and_then(|mut conn| audit::append(&mut conn, &ctx, event))
+            })
+            .await;
+
+            // The request already succeeded; a failure here is logged, not returned
+            let error = match appended {
+                Ok(Ok(_)) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(e) = error {
+                log::error!(
+                    "[{}] Failed to record audit entry for {} {}: {}",
+                    correlation_id.as_deref().unwrap_or("unknown"),
+                    request.method(),
+                    path,
+                    e
+                );
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// Entity type of a route: its first segment after `/api` and the version,
+/// for example `reconciliation` for `/api/v1/reconciliation/jobs/{job_id}`
+fn resource_type(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != "api")
+        .find(|segment| !is_version(segment))
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+fn is_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// First path segment that is a UUID, taken as the entity the request changed
+fn first_uuid(path: &str) -> Option<Uuid> {
+    path.split('/')
+        .find_map(|segment| Uuid::parse_str(segment).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_entity_from_path() {
+        assert_eq!(
+            resource_type("/api/v1/reconciliation/jobs/{job_id}"),
+            "reconciliation"
+        );
+        assert_eq!(resource_type("/api/projects"), "projects");
+        assert_eq!(resource_type("/"), "unknown");
+
+        let id = Uuid::new_v4();
+        assert_eq!(
+            first_uuid(&format!("/api/v1/projects/{}/jobs", id)),
+            Some(id)
+        );
+        assert_eq!(first_uuid("/api/v1/projects"), None);
+    }
+}
@@ -13,7 +13,7 @@ use std::sync::Arc;
 
 use crate::errors::{AppError, AppResult};
 use crate::monitoring::SecurityMetrics;
-use crate::services::auth::{AuthService, Claims};
+use crate::services::auth::{api_keys, AuthService, Claims, ProjectGrant};
 use crate::services::security_monitor::{
     SecurityEvent, SecurityEventType, SecurityMonitor, SecuritySeverity,
 };
@@ -355,8 +355,9 @@ where
             // Add claims to request extensions
             req.extensions_mut().insert(claims);
 
-            // Call the next service
-            service.call(req).await
+            // Call the next service; a JWT carries no project restriction
+            // beyond the user's roles
+            api_keys::with_grant(ProjectGrant::Unrestricted, service.call(req)).await
         })
     }
 }
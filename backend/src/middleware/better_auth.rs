@@ -5,6 +5,7 @@
 
 use crate::errors::{AppError, AppResult};
 use crate::services::auth::types::Claims;
+use crate::services::auth::ProjectGrant;
 use actix_web::dev::ServiceRequest;
 use actix_web::HttpMessage;
 use serde::{Deserialize, Serialize};
@@ -209,6 +210,7 @@ pub async fn verify_identity_better_auth(
 
     // Store claims in request extensions for use in handlers
     req.extensions_mut().insert(internal_claims);
+    req.extensions_mut().insert(ProjectGrant::Unrestricted);
 
     // Also store user ID as UUID if possible
     if let Ok(user_id) = uuid::Uuid::parse_str(&claims.sub) {
@@ -256,6 +258,7 @@ pub async fn verify_identity_dual(
             };
 
             req.extensions_mut().insert(internal_claims);
+            req.extensions_mut().insert(ProjectGrant::Unrestricted);
 
             if let Ok(user_id) = uuid::Uuid::parse_str(&claims.sub) {
                 req.extensions_mut().insert(user_id);
@@ -281,6 +284,7 @@ pub async fn verify_identity_dual(
         }
 
         req.extensions_mut().insert(claims.clone());
+        req.extensions_mut().insert(ProjectGrant::Unrestricted);
 
         if let Ok(user_id) = uuid::Uuid::parse_str(&claims.sub) {
             req.extensions_mut().insert(user_id);
@@ -16,9 +16,11 @@ use crate::middleware::security::headers::{
     add_security_headers_to_response, CspNonce, SecurityHeadersConfig,
 };
 use crate::middleware::zero_trust::{
-    check_network_segmentation, enforce_least_privilege, verify_identity, verify_mtls,
-    ZeroTrustConfig,
+    check_network_segmentation, enforce_least_privilege, verify_identity,
+    verify_presented_api_key, verify_mtls, ZeroTrustConfig,
 };
+use crate::errors::AppError;
+use crate::services::auth::api_keys::{self, ProjectGrant};
 use crate::services::auth::AuthService;
 use redis::Client as RedisClient;
 use crate::config::Config;
@@ -151,15 +153,35 @@ where
                 || path.starts_with("/api/auth/password-reset");
 
             if !should_skip {
-                // Verify identity
-                if zero_trust_config.require_identity_verification {
-                    if let Err(e) = verify_identity(&req, auth_service.as_ref(), redis_client.as_ref()).await {
+                // Verify identity; API keys are authenticated in every
+                // environment, so a key's scopes and projects always apply
+                let identity = if zero_trust_config.require_identity_verification {
+                    verify_identity(&req, auth_service.as_ref(), redis_client.as_ref()).await
+                } else {
+                    // Without a key the request is a user's, whose project
+                    // roles alone decide what they can reach
+                    verify_presented_api_key(&req).map(|presented| {
+                        if !presented {
+                            req.extensions_mut().insert(ProjectGrant::Unrestricted);
+                        }
+                    })
+                };
+                match identity {
+                    Ok(()) => {}
+                    // An API key whose scopes don't cover the request
+                    Err(AppError::Forbidden(msg)) => {
+                        log::warn!("API key scope check failed: {}", msg);
+                        return Err(actix_web::error::ErrorForbidden(msg));
+                    }
+                    Err(e) => {
                         log::warn!("Identity verification failed: {}", e);
                         return Err(actix_web::error::ErrorUnauthorized(
                             "Identity verification failed",
                         ));
                     }
+                }
 
+                if zero_trust_config.require_identity_verification {
                     // RBAC: Extract user claims and check permissions
                     if zero_trust_config.enforce_least_privilege {
                         let auth_service_clone = auth_service.clone();
@@ -231,8 +253,12 @@ where
                 }
             }
 
-            // 3. Call Service
-            let mut res = service.call(req).await?;
+            // 3. Call Service, under the grant authentication settled on
+            let grant = req.extensions().get::<ProjectGrant>().cloned();
+            let mut res = match grant {
+                Some(grant) => api_keys::with_grant(grant, service.call(req)).await?,
+                None => service.call(req).await?,
+            };
 
             // 4. Security Headers Logic (Response side)
             add_security_headers_to_response(&mut res, &headers_config, csp_nonce.as_deref());
@@ -16,7 +16,7 @@ use crate::config::better_auth::BetterAuthConfig;
 use crate::errors::{AppError, AppResult};
 use crate::middleware::better_auth::BetterAuthValidator;
 use crate::monitoring::SecurityMetrics;
-use crate::services::auth::{AuthService, Claims};
+use crate::services::auth::{api_keys, AuthService, Claims, ProjectGrant};
 use crate::services::structured_logging::{LogLevel, StructuredLogging};
 
 /// Dual authentication middleware configuration
@@ -263,8 +263,9 @@ where
             // Add claims to request extensions
             req.extensions_mut().insert(claims);
 
-            // Call the next service
-            service.call(req).await
+            // Call the next service; a JWT carries no project restriction
+            // beyond the user's roles
+            api_keys::with_grant(ProjectGrant::Unrestricted, service.call(req)).await
         })
     }
 }
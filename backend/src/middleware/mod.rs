@@ -71,3 +71,7 @@ pub use rate_limit::{
 // API versioning middleware
 pub mod api_versioning;
 pub use api_versioning::{ApiVersioningConfig, ApiVersioningMiddleware};
+
+// Audit trail middleware
+pub mod audit;
+pub use audit::AuditMiddleware;
@@ -1,26 +1,34 @@
 //! API Key Authentication Middleware
 //!
 //! Provides API key authentication for external service-to-service communication.
-//! Validates API keys from Authorization header or X-API-Key header.
+//! Validates keys issued by `ApiKeyService` from the X-API-Key header or the
+//! Authorization header, and checks the key's scopes cover the request.
+//!
+//! Routes behind the zero-trust identity check accept keys as well as JWTs;
+//! this middleware is for routes that accept keys only.
 
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error,
+    web, Error, HttpMessage,
 };
 use futures_util::future::LocalBoxFuture;
 use std::{
     future::{ready, Ready},
     rc::Rc,
 };
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::services::auth::api_keys::{self, ApiKeyGrant, ApiKeyService, ProjectGrant};
+use crate::services::auth::Claims;
 
-use crate::errors::AppError;
-use crate::services::secrets::SecretsService;
+/// How long the claims made for a key-authenticated request are valid
+const KEY_CLAIMS_TTL_SECS: usize = 3600;
 
 /// API Key authentication configuration
 #[derive(Clone)]
 pub struct ApiKeyConfig {
-    /// Required API key (from environment)
-    pub api_key: String,
     /// Header name to check (default: "X-API-Key")
     pub header_name: String,
     /// Whether to also check Authorization header
@@ -30,14 +38,75 @@ pub struct ApiKeyConfig {
 impl Default for ApiKeyConfig {
     fn default() -> Self {
         Self {
-            api_key: SecretsService::get_api_key()
-                .unwrap_or_else(|_| "".to_string()),
             header_name: "X-API-Key".to_string(),
             check_authorization: true,
         }
     }
 }
 
+/// API key presented with a request, if any
+///
+/// Read from `header_name`, or from `Authorization` as `ApiKey <key>` or as
+/// a bearer token that is a key rather than a JWT.
+pub fn presented_api_key(req: &ServiceRequest, config: &ApiKeyConfig) -> Option<String> {
+    if let Some(key) = req
+        .headers()
+        .get(&config.header_name)
+        .and_then(|h| h.to_str().ok())
+    {
+        return Some(key.trim().to_string());
+    }
+    if !config.check_authorization {
+        return None;
+    }
+    let auth = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())?;
+    if let Some(key) = auth.strip_prefix("ApiKey ") {
+        return Some(key.trim().to_string());
+    }
+    auth.strip_prefix("Bearer ")
+        .filter(|token| api_keys::is_api_key(token))
+        .map(|token| token.trim().to_string())
+}
+
+/// Authenticate a request by an API key and check its scopes cover it
+///
+/// Stores the key's user in the request extensions as a JWT would (claims and
+/// user ID), along with the key's grant, which the request must then be
+/// handled under (see `api_keys::with_grant`), as a [`ProjectGrant::Key`].
+pub fn authenticate_api_key(req: &ServiceRequest, key: &str) -> AppResult<ApiKeyGrant> {
+    let db = req
+        .app_data::<web::Data<Database>>()
+        .ok_or_else(|| AppError::Internal("Database not configured".to_string()))?;
+    let ip = crate::handlers::helpers::get_client_ip(req.request());
+    let (grant, user) = ApiKeyService::new(db.get_ref().clone()).authenticate(key, Some(&ip))?;
+    grant.check_request(req.path(), req.method().as_str())?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as usize;
+    let claims = Claims {
+        sub: user.id.to_string(),
+        email: user.email,
+        // Role stored in status field
+        role: user.status,
+        exp: now + KEY_CLAIMS_TTL_SECS,
+        iat: now,
+        iss: None,
+        aud: None,
+    };
+
+    let mut extensions = req.extensions_mut();
+    extensions.insert(claims);
+    extensions.insert::<Uuid>(grant.user_id);
+    extensions.insert(grant.clone());
+    extensions.insert(ProjectGrant::Key(grant.clone()));
+    Ok(grant)
+}
+
 /// API Key authentication middleware
 pub struct ApiKeyMiddleware {
     config: ApiKeyConfig,
@@ -95,52 +164,22 @@ where
         let config = self.config.clone();
 
         Box::pin(async move {
-            // Skip API key check if no API key is configured
-            if config.api_key.is_empty() {
-                return service.call(req).await;
-            }
-
-            // Extract API key from headers
-            let provided_key = req.headers()
-                .get(&config.header_name)
-                .and_then(|h| h.to_str().ok())
-                .or_else(|| {
-                    if config.check_authorization {
-                        req.headers()
-                            .get("Authorization")
-                            .and_then(|h| h.to_str().ok())
-                            .and_then(|auth| {
-                                // Support "Bearer <key>" or "ApiKey <key>" format
-                                if auth.starts_with("Bearer ") {
-                                    Some(&auth[7..])
-                                } else if auth.starts_with("ApiKey ") {
-                                    Some(&auth[7..])
-                                } else {
-                                    Some(auth)
-                                }
-                            })
-                    } else {
-                        None
-                    }
-                });
-
-            match provided_key {
-                Some(key) if key == config.api_key => {
-                    // Valid API key - proceed with request
-                    service.call(req).await
+            let Some(key) = presented_api_key(&req, &config) else {
+                // No API key provided
+                let error = AppError::Authentication("API key required".to_string());
+                return Err(actix_web::error::ErrorUnauthorized(error));
+            };
+
+            match authenticate_api_key(&req, &key) {
+                Ok(grant) => {
+                    api_keys::with_grant(ProjectGrant::Key(grant), service.call(req)).await
                 }
-                Some(_) => {
-                    // Invalid API key
-                    let error = AppError::Authentication("Invalid API key".to_string());
-                    Err(actix_web::error::ErrorUnauthorized(error))
-                }
-                None => {
-                    // No API key provided
-                    let error = AppError::Authentication("API key required".to_string());
+                Err(error @ AppError::Forbidden(_)) => Err(actix_web::error::ErrorForbidden(error)),
+                Err(error) => {
+                    log::warn!("API key authentication failed: {}", error);
                     Err(actix_web::error::ErrorUnauthorized(error))
                 }
             }
         })
     }
 }
-
@@ -413,7 +413,7 @@ async fn validate_update_user_request(
 
     // Validate role if provided
     if let Some(role) = body.get("role").and_then(|v| v.as_str()) {
-        let valid_roles = ["admin", "user", "analyst", "viewer"];
+        let valid_roles = ["admin", "user", "analyst", "viewer", "service"];
         if !valid_roles.contains(&role) {
             return Err(AppError::Validation(format!(
                 "Invalid role: {}. Valid roles are: {}",
@@ -2,7 +2,8 @@
 
 use crate::errors::{AppError, AppResult};
 use crate::middleware::dual_auth::DualAuthMiddleware;
-use crate::services::auth::{AuthService, Claims};
+use crate::middleware::security::api_key::{authenticate_api_key, presented_api_key, ApiKeyConfig};
+use crate::services::auth::{AuthService, Claims, ProjectGrant};
 use actix_web::dev::ServiceRequest;
 use actix_web::HttpMessage;
 use redis::Client as RedisClient;
@@ -14,6 +15,9 @@ use uuid::Uuid;
 /// Validates JWT token signature, expiration, and checks revocation list.
 /// Stores claims and user ID in request extensions for downstream use.
 ///
+/// A request presenting an API key is authenticated by the key instead, and
+/// fails if the key's scopes don't cover it.
+///
 /// Supports both legacy and Better Auth tokens when dual_auth_middleware is provided.
 pub async fn verify_identity(
     req: &ServiceRequest,
@@ -23,6 +27,18 @@ pub async fn verify_identity(
     verify_identity_with_dual_auth(req, auth_service, redis_client, None).await
 }
 
+/// Authenticate the API key a request presents, if any; whether it had one
+///
+/// Keys are checked whether or not identity verification is required, so a
+/// request presenting one is never handled without the key's scopes and
+/// project restriction.
+pub fn verify_presented_api_key(req: &ServiceRequest) -> AppResult<bool> {
+    match presented_api_key(req, &ApiKeyConfig::default()) {
+        Some(key) => authenticate_api_key(req, &key).map(|_| true),
+        None => Ok(false),
+    }
+}
+
 /// Verify identity with dual auth support
 ///
 /// This function supports both legacy and Better Auth tokens.
@@ -33,6 +49,12 @@ pub async fn verify_identity_with_dual_auth(
     redis_client: Option<&Arc<RedisClient>>,
     dual_auth_middleware: Option<&Arc<DualAuthMiddleware>>,
 ) -> AppResult<()> {
+    // API keys are accepted wherever tokens are; they're checked against the
+    // stored keys rather than as JWTs, and revoked by marking them there
+    if verify_presented_api_key(req)? {
+        return Ok(());
+    }
+
     // Check for authentication token
     let auth_header = req.headers().get("Authorization")
         .and_then(|h| h.to_str().ok())
@@ -89,9 +111,11 @@ pub async fn verify_identity_with_dual_auth(
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|e| AppError::Unauthorized(format!("Invalid user ID in token: {}", e)))?;
 
-    // Store claims in request extensions for use in other middleware/handlers
+    // Store claims in request extensions for use in other middleware/handlers;
+    // a JWT carries no project restriction beyond the user's roles
     req.extensions_mut().insert(claims);
     req.extensions_mut().insert(user_id);
+    req.extensions_mut().insert(ProjectGrant::Unrestricted);
 
     // Check token revocation list (Redis or database lookup)
     if let Err(e) = check_token_revocation(token, redis_client).await {
@@ -9,15 +9,17 @@ mod network;
 mod privilege;
 
 pub use config::ZeroTrustConfig;
-pub use identity::{verify_identity, extract_token_from_request};
+pub use identity::{verify_identity, verify_presented_api_key, extract_token_from_request};
 pub use mtls::verify_mtls;
 pub use network::{check_network_segmentation, is_ip_in_ranges};
 pub use privilege::{enforce_least_privilege, extract_resource_from_path, extract_action_from_method};
 
+use crate::errors::AppError;
+use crate::services::auth::api_keys::{self, ProjectGrant};
 use crate::services::auth::AuthService;
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error,
+    Error, HttpMessage,
 };
 use futures::future::{ready, Ready};
 use redis::Client as RedisClient;
@@ -146,9 +148,26 @@ where
                 return service.call(req).await;
             }
 
-            // Verify identity
-            if config.require_identity_verification {
-                if let Err(e) = verify_identity(&req, auth_service_clone.as_ref(), redis_client_clone.as_ref()).await {
+            // Verify identity; API keys are authenticated in every environment
+            let identity = if config.require_identity_verification {
+                verify_identity(&req, auth_service_clone.as_ref(), redis_client_clone.as_ref()).await
+            } else {
+                // Without a key the request is a user's, whose project roles
+                // alone decide what they can reach
+                verify_presented_api_key(&req).map(|presented| {
+                    if !presented {
+                        req.extensions_mut().insert(ProjectGrant::Unrestricted);
+                    }
+                })
+            };
+            match identity {
+                Ok(()) => {}
+                // An API key whose scopes don't cover the request
+                Err(AppError::Forbidden(msg)) => {
+                    log::warn!("API key scope check failed: {}", msg);
+                    return Err(actix_web::error::ErrorForbidden(msg));
+                }
+                Err(e) => {
                     log::warn!("Identity verification failed: {}", e);
                     return Err(actix_web::error::ErrorUnauthorized("Identity verification failed"));
                 }
@@ -178,7 +197,11 @@ where
                 }
             }
 
-            service.call(req).await
+            let grant = req.extensions().get::<ProjectGrant>().cloned();
+            match grant {
+                Some(grant) => api_keys::with_grant(grant, service.call(req)).await,
+                None => service.call(req).await,
+            }
         })
     }
 }
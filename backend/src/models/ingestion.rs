@@ -5,7 +5,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::schema::{ingestion_errors, ingestion_jobs, ingestion_results};
+use crate::models::schema::{ingestion_errors, ingestion_jobs, ingestion_results, quarantined_records};
 
 /// Ingestion job model
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
@@ -110,3 +110,35 @@ pub struct NewIngestionError {
     pub stack_trace: Option<String>,
 }
 
+/// A record held back from ingestion as a duplicate
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = quarantined_records)]
+pub struct QuarantinedRecord {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub ingestion_job_id: Uuid,
+    pub record_index: Option<i32>,
+    pub dedup_key: String,
+    pub reason: String,
+    pub duplicate_of_record_id: Option<Uuid>,
+    pub duplicate_of_row: Option<i32>,
+    pub record_data: serde_json::Value,
+    pub status: String,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New quarantined record (for inserts)
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = quarantined_records)]
+pub struct NewQuarantinedRecord {
+    pub project_id: Uuid,
+    pub ingestion_job_id: Uuid,
+    pub record_index: Option<i32>,
+    pub dedup_key: String,
+    pub reason: String,
+    pub duplicate_of_record_id: Option<Uuid>,
+    pub duplicate_of_row: Option<i32>,
+    pub record_data: serde_json::Value,
+}
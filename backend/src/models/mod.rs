@@ -282,7 +282,7 @@ pub struct ReconciliationRecord {
 }
 
 /// New reconciliation record model for inserts
-#[derive(Insertable, Deserialize)]
+#[derive(Insertable, Serialize, Deserialize)]
 #[diesel(table_name = crate::models::schema::reconciliation_records)]
 pub struct NewReconciliationRecord {
     pub project_id: Uuid,
@@ -359,6 +359,83 @@ pub struct UpdateReconciliationJob {
     pub unmatched_records: Option<i32>,
 }
 
+/// Reconciliation batch model
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::reconciliation_batches)]
+pub struct ReconciliationBatch {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub execution_mode: String,
+    pub status: String,
+    pub created_by: Uuid,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New reconciliation batch model for inserts
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = crate::models::schema::reconciliation_batches)]
+pub struct NewReconciliationBatch {
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub execution_mode: String,
+    pub status: String,
+    pub created_by: Uuid,
+}
+
+/// Job membership of a reconciliation batch
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::reconciliation_batch_jobs)]
+pub struct ReconciliationBatchJob {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub job_id: Uuid,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New batch membership model for inserts
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = crate::models::schema::reconciliation_batch_jobs)]
+pub struct NewReconciliationBatchJob {
+    pub batch_id: Uuid,
+    pub job_id: Uuid,
+    pub position: i32,
+}
+
+/// Entry of the durable reconciliation job queue
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::reconciliation_job_queue)]
+pub struct ReconciliationQueueEntry {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub state: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub available_at: DateTime<Utc>,
+    pub leased_by: Option<String>,
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub checkpoint: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New job queue entry for inserts
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = crate::models::schema::reconciliation_job_queue)]
+pub struct NewReconciliationQueueEntry {
+    pub job_id: Uuid,
+    pub state: String,
+    pub max_attempts: i32,
+}
+
 /// Data source model
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::models::schema::data_sources)]
@@ -420,6 +497,66 @@ pub struct UpdateDataSource {
     pub is_active: Option<bool>,
 }
 
+/// Balances of one bank statement read into a data source
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::data_source_statements)]
+pub struct DataSourceStatement {
+    pub id: Uuid,
+    pub data_source_id: Uuid,
+    pub ingestion_job_id: Uuid,
+    pub sequence: i32,
+    pub format: String,
+    pub statement_id: Option<String>,
+    pub account: Option<String>,
+    pub currency: Option<String>,
+    pub opening_balance: Option<BigDecimal>,
+    pub opening_date: Option<chrono::NaiveDate>,
+    pub closing_balance: Option<BigDecimal>,
+    pub closing_date: Option<chrono::NaiveDate>,
+    pub entry_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New data source statement for inserts
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = crate::models::schema::data_source_statements)]
+pub struct NewDataSourceStatement {
+    pub data_source_id: Uuid,
+    pub ingestion_job_id: Uuid,
+    pub sequence: i32,
+    pub format: String,
+    pub statement_id: Option<String>,
+    pub account: Option<String>,
+    pub currency: Option<String>,
+    pub opening_balance: Option<BigDecimal>,
+    pub opening_date: Option<chrono::NaiveDate>,
+    pub closing_balance: Option<BigDecimal>,
+    pub closing_date: Option<chrono::NaiveDate>,
+    pub entry_count: i32,
+}
+
+/// One saved version of a data source's transformation spec
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::data_source_mappings)]
+pub struct DataSourceMapping {
+    pub id: Uuid,
+    pub data_source_id: Uuid,
+    pub version: i32,
+    pub spec: serde_json::Value,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New data source mapping for inserts
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = crate::models::schema::data_source_mappings)]
+pub struct NewDataSourceMapping {
+    pub data_source_id: Uuid,
+    pub version: i32,
+    pub spec: serde_json::Value,
+    pub created_by: Option<Uuid>,
+}
+
 /// Reconciliation result model
 #[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::models::schema::reconciliation_results)]
@@ -453,8 +590,50 @@ pub struct NewReconciliationResult {
     pub reviewed_by: Option<Uuid>,
 }
 
+/// Request for a second user to approve a match decision
+///
+/// See `services::reconciliation::approvals`.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::match_approvals)]
+pub struct MatchApproval {
+    pub id: Uuid,
+    pub result_id: Uuid,
+    pub job_id: Uuid,
+    pub project_id: Uuid,
+    pub status: String,
+    pub reasons: serde_json::Value,
+    pub proposed: serde_json::Value,
+    pub previous_status: Option<String>,
+    pub variance: Option<f64>,
+    pub prepared_by: Uuid,
+    pub prepared_notes: Option<String>,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New match approval request for inserts
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::match_approvals)]
+pub struct NewMatchApproval {
+    pub result_id: Uuid,
+    pub job_id: Uuid,
+    pub project_id: Uuid,
+    pub reasons: serde_json::Value,
+    pub proposed: serde_json::Value,
+    pub previous_status: Option<String>,
+    pub variance: Option<f64>,
+    pub prepared_by: Uuid,
+    pub prepared_notes: Option<String>,
+}
+
 /// Audit log model
-#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+///
+/// Entries with a `sequence` are part of the hash chain written by
+/// `services::audit`; `entry_hash` covers every field but `user_id`.
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[diesel(table_name = crate::models::schema::audit_logs)]
 pub struct AuditLog {
     pub id: Uuid,
@@ -462,11 +641,17 @@ pub struct AuditLog {
     pub action: String,
     pub resource_type: String,
     pub resource_id: Option<Uuid>,
+    pub details: Option<serde_json::Value>,
     pub old_values: Option<serde_json::Value>,
     pub new_values: Option<serde_json::Value>,
-    pub ip_address: Option<String>,
+    pub ip_address: Option<ipnetwork::IpNetwork>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub sequence: Option<i64>,
+    pub actor_id: Option<Uuid>,
+    pub correlation_id: Option<String>,
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
 }
 
 /// New audit log model for inserts
@@ -617,6 +802,49 @@ pub struct UpdatePasswordResetToken {
     pub used_at: Option<DateTime<Utc>>,
 }
 
+/// API key model
+///
+/// `permissions` holds the key's scopes; `project_ids`, when set, the only
+/// projects it can act in.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::models::schema::api_keys)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub key_prefix: String,
+    pub permissions: serde_json::Value,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub kind: String,
+    pub project_ids: Option<serde_json::Value>,
+    pub created_by: Option<Uuid>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+}
+
+/// New API key for inserts
+#[derive(Insertable)]
+#[diesel(table_name = crate::models::schema::api_keys)]
+pub struct NewApiKey {
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_hash: String,
+    pub key_prefix: String,
+    pub permissions: serde_json::Value,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub kind: String,
+    pub project_ids: Option<serde_json::Value>,
+    pub created_by: Option<Uuid>,
+}
+
 /// Email verification token model
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = crate::models::schema::email_verification_tokens)]
@@ -754,17 +982,21 @@ impl From<Project> for ProjectResponse {
 
 // Re-export notification types
 pub use notification::{
-    NewNotification, NewNotificationPreferences, Notification, NotificationPreferences,
-    UpdateNotificationPreferences,
+    EmailOutboxEntry, NewEmailOutboxEntry, NewNotification, NewNotificationPreferences, Notification,
+    NotificationPreferences, UpdateNotificationPreferences,
 };
 
 // Re-export team types
-pub use team::{NewTeam, NewTeamMember, Team, TeamMember, UpdateTeam, UpdateTeamMember};
+pub use team::{
+    NewProjectTeam, NewTeam, NewTeamMember, ProjectTeam, Team, TeamMember, UpdateTeam,
+    UpdateTeamMember,
+};
 
 // Re-export workflow types
 pub use workflow::{
-    NewWorkflow, NewWorkflowInstance, NewWorkflowRule, UpdateWorkflow, UpdateWorkflowInstance,
-    UpdateWorkflowRule, Workflow, WorkflowInstance, WorkflowRule,
+    NewWorkflow, NewWorkflowInstance, NewWorkflowRule, NewWorkflowTransition, UpdateWorkflow,
+    UpdateWorkflowInstance, UpdateWorkflowRule, Workflow, WorkflowInstance, WorkflowRule,
+    WorkflowTransition,
 };
 
 // Re-export cashflow types
@@ -784,7 +1016,7 @@ pub use adjudication::{
 // Re-export ingestion types
 pub use ingestion::{
     IngestionError, IngestionJob, IngestionResult, NewIngestionError, NewIngestionJob,
-    NewIngestionResult, UpdateIngestionJob,
+    NewIngestionResult, NewQuarantinedRecord, QuarantinedRecord, UpdateIngestionJob,
 };
 
 // Re-export visualization types
@@ -5,7 +5,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::schema::{notifications, notification_preferences};
+use crate::models::schema::{email_outbox, notifications, notification_preferences};
 
 /// Notification model
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
@@ -74,3 +74,43 @@ pub struct UpdateNotificationPreferences {
     pub project_updated: Option<bool>,
 }
 
+/// Email in the outbox, waiting for delivery or delivered
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = email_outbox)]
+pub struct EmailOutboxEntry {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: Option<String>,
+    pub text_body: String,
+    pub template: Option<String>,
+    pub locale: Option<String>,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub message_id: Option<String>,
+    pub correlation_id: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub bounced_at: Option<DateTime<Utc>>,
+    pub bounce_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New outbox email (for inserts)
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = email_outbox)]
+pub struct NewEmailOutboxEntry {
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: Option<String>,
+    pub text_body: String,
+    pub template: Option<String>,
+    pub locale: Option<String>,
+    pub status: String,
+    pub max_attempts: i32,
+    pub correlation_id: Option<String>,
+}
@@ -19,6 +19,14 @@ diesel::table! {
         is_active -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        #[max_length = 20]
+        kind -> Varchar,
+        project_ids -> Nullable<Jsonb>,
+        created_by -> Nullable<Uuid>,
+        #[max_length = 45]
+        last_used_ip -> Nullable<Varchar>,
+        revoked_at -> Nullable<Timestamptz>,
+        revoked_by -> Nullable<Uuid>,
     }
 }
 
@@ -84,6 +92,14 @@ diesel::table! {
         ip_address -> Nullable<Inet>,
         user_agent -> Nullable<Text>,
         created_at -> Timestamptz,
+        sequence -> Nullable<Int8>,
+        actor_id -> Nullable<Uuid>,
+        #[max_length = 255]
+        correlation_id -> Nullable<Varchar>,
+        #[max_length = 64]
+        prev_hash -> Nullable<Varchar>,
+        #[max_length = 64]
+        entry_hash -> Nullable<Varchar>,
     }
 }
 
@@ -53,11 +53,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    quarantined_records (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        ingestion_job_id -> Uuid,
+        record_index -> Nullable<Int4>,
+        #[max_length = 64]
+        dedup_key -> Varchar,
+        #[max_length = 50]
+        reason -> Varchar,
+        duplicate_of_record_id -> Nullable<Uuid>,
+        duplicate_of_row -> Nullable<Int4>,
+        record_data -> Jsonb,
+        #[max_length = 50]
+        status -> Varchar,
+        reviewed_by -> Nullable<Uuid>,
+        reviewed_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(ingestion_jobs -> projects (project_id));
 diesel::joinable!(ingestion_jobs -> users (created_by));
 diesel::joinable!(ingestion_results -> ingestion_jobs (job_id));
 diesel::joinable!(ingestion_errors -> ingestion_jobs (job_id));
+diesel::joinable!(quarantined_records -> ingestion_jobs (ingestion_job_id));
 
 diesel::allow_tables_to_appear_in_same_query!(ingestion_jobs, projects);
 diesel::allow_tables_to_appear_in_same_query!(ingestion_results, ingestion_jobs);
+diesel::allow_tables_to_appear_in_same_query!(quarantined_records, ingestion_jobs);
 
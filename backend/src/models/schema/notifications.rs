@@ -32,6 +32,37 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    email_outbox (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        recipient -> Varchar,
+        subject -> Text,
+        html_body -> Nullable<Text>,
+        text_body -> Text,
+        #[max_length = 100]
+        template -> Nullable<Varchar>,
+        #[max_length = 20]
+        locale -> Nullable<Varchar>,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        #[max_length = 255]
+        message_id -> Nullable<Varchar>,
+        #[max_length = 255]
+        correlation_id -> Nullable<Varchar>,
+        sent_at -> Nullable<Timestamptz>,
+        bounced_at -> Nullable<Timestamptz>,
+        bounce_reason -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 // Joinable relationships
 diesel::joinable!(notifications -> users (user_id));
 diesel::joinable!(notification_preferences -> users (user_id));
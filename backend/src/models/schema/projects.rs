@@ -126,6 +126,40 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    data_source_mappings (id) {
+        id -> Uuid,
+        data_source_id -> Uuid,
+        version -> Int4,
+        spec -> Jsonb,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    data_source_statements (id) {
+        id -> Uuid,
+        data_source_id -> Uuid,
+        ingestion_job_id -> Uuid,
+        sequence -> Int4,
+        #[max_length = 20]
+        format -> Varchar,
+        #[max_length = 255]
+        statement_id -> Nullable<Varchar>,
+        #[max_length = 255]
+        account -> Nullable<Varchar>,
+        #[max_length = 3]
+        currency -> Nullable<Varchar>,
+        opening_balance -> Nullable<Numeric>,
+        opening_date -> Nullable<Date>,
+        closing_balance -> Nullable<Numeric>,
+        closing_date -> Nullable<Date>,
+        entry_count -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     reconciliation_jobs (id) {
         id -> Uuid,
@@ -151,6 +185,55 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    reconciliation_batches (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        description -> Nullable<Text>,
+        #[max_length = 20]
+        execution_mode -> Varchar,
+        #[max_length = 50]
+        status -> Varchar,
+        created_by -> Uuid,
+        started_at -> Nullable<Timestamptz>,
+        completed_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reconciliation_batch_jobs (id) {
+        id -> Uuid,
+        batch_id -> Uuid,
+        job_id -> Uuid,
+        position -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reconciliation_job_queue (id) {
+        id -> Uuid,
+        job_id -> Uuid,
+        #[max_length = 20]
+        state -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        available_at -> Timestamptz,
+        #[max_length = 255]
+        leased_by -> Nullable<Varchar>,
+        lease_expires_at -> Nullable<Timestamptz>,
+        heartbeat_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        checkpoint -> Nullable<Jsonb>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     reconciliation_records (id) {
         id -> Uuid,
@@ -191,6 +274,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    match_approvals (id) {
+        id -> Uuid,
+        result_id -> Uuid,
+        job_id -> Uuid,
+        project_id -> Uuid,
+        #[max_length = 20]
+        status -> Varchar,
+        reasons -> Jsonb,
+        proposed -> Jsonb,
+        #[max_length = 50]
+        previous_status -> Nullable<Varchar>,
+        variance -> Nullable<Float8>,
+        prepared_by -> Uuid,
+        prepared_notes -> Nullable<Text>,
+        decided_by -> Nullable<Uuid>,
+        decided_at -> Nullable<Timestamptz>,
+        decision_notes -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     uploaded_files (id) {
         id -> Uuid,
@@ -220,9 +326,18 @@ diesel::joinable!(project_members -> projects (project_id));
 diesel::joinable!(project_members -> users (user_id));
 diesel::joinable!(reconciliation_jobs -> projects (project_id));
 diesel::joinable!(reconciliation_jobs -> users (created_by));
+diesel::joinable!(reconciliation_batches -> projects (project_id));
+diesel::joinable!(data_source_mappings -> data_sources (data_source_id));
+diesel::joinable!(data_source_statements -> data_sources (data_source_id));
+diesel::joinable!(reconciliation_batch_jobs -> reconciliation_batches (batch_id));
+diesel::joinable!(reconciliation_batch_jobs -> reconciliation_jobs (job_id));
+diesel::joinable!(reconciliation_job_queue -> reconciliation_jobs (job_id));
 diesel::joinable!(reconciliation_records -> projects (project_id));
 diesel::joinable!(reconciliation_records -> reconciliation_jobs (ingestion_job_id));
 diesel::joinable!(reconciliation_results -> reconciliation_jobs (job_id));
+diesel::joinable!(match_approvals -> reconciliation_results (result_id));
+diesel::joinable!(match_approvals -> reconciliation_jobs (job_id));
+diesel::joinable!(match_approvals -> projects (project_id));
 diesel::joinable!(uploaded_files -> projects (project_id));
 diesel::joinable!(uploaded_files -> users (uploaded_by));
 
@@ -237,5 +352,13 @@ diesel::allow_tables_to_appear_in_same_query!(users, project_members);
 diesel::allow_tables_to_appear_in_same_query!(users, reconciliation_jobs);
 diesel::allow_tables_to_appear_in_same_query!(users, uploaded_files);
 diesel::allow_tables_to_appear_in_same_query!(reconciliation_results, reconciliation_jobs);
+diesel::allow_tables_to_appear_in_same_query!(match_approvals, reconciliation_results);
+diesel::allow_tables_to_appear_in_same_query!(match_approvals, reconciliation_jobs);
+diesel::allow_tables_to_appear_in_same_query!(match_approvals, projects);
+diesel::allow_tables_to_appear_in_same_query!(reconciliation_batches, reconciliation_batch_jobs);
+diesel::allow_tables_to_appear_in_same_query!(reconciliation_job_queue, reconciliation_jobs);
+diesel::allow_tables_to_appear_in_same_query!(reconciliation_batch_jobs, reconciliation_jobs);
 diesel::allow_tables_to_appear_in_same_query!(audit_logs, users);
-diesel::allow_tables_to_appear_in_same_query!(data_sources, projects);
\ No newline at end of file
+diesel::allow_tables_to_appear_in_same_query!(data_sources, projects);
+diesel::allow_tables_to_appear_in_same_query!(data_source_statements, data_sources);
+diesel::allow_tables_to_appear_in_same_query!(data_source_mappings, data_sources);
\ No newline at end of file
@@ -30,11 +30,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    project_teams (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        team_id -> Uuid,
+        #[max_length = 50]
+        role -> Varchar,
+        granted_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(teams -> users (owner_id));
 diesel::joinable!(team_members -> teams (team_id));
 diesel::joinable!(team_members -> users (user_id));
+diesel::joinable!(project_teams -> projects (project_id));
+diesel::joinable!(project_teams -> teams (team_id));
 
 diesel::allow_tables_to_appear_in_same_query!(teams, users);
 diesel::allow_tables_to_appear_in_same_query!(team_members, teams);
 diesel::allow_tables_to_appear_in_same_query!(team_members, users);
+diesel::allow_tables_to_appear_in_same_query!(project_teams, teams);
+diesel::allow_tables_to_appear_in_same_query!(project_teams, projects);
+diesel::allow_tables_to_appear_in_same_query!(project_teams, team_members);
 
@@ -31,6 +31,8 @@ diesel::table! {
         error_message -> Nullable<Text>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        #[max_length = 255]
+        waiting_for -> Nullable<Varchar>,
     }
 }
 
@@ -49,11 +51,33 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    workflow_transitions (id) {
+        id -> Uuid,
+        instance_id -> Uuid,
+        #[max_length = 255]
+        from_step -> Nullable<Varchar>,
+        #[max_length = 255]
+        to_step -> Nullable<Varchar>,
+        #[max_length = 50]
+        from_status -> Varchar,
+        #[max_length = 50]
+        to_status -> Varchar,
+        #[max_length = 100]
+        reason -> Varchar,
+        details -> Jsonb,
+        actor_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(workflows -> projects (project_id));
 diesel::joinable!(workflows -> users (created_by));
 diesel::joinable!(workflow_instances -> workflows (workflow_id));
 diesel::joinable!(workflow_instances -> users (started_by));
 diesel::joinable!(workflow_rules -> workflows (workflow_id));
+diesel::joinable!(workflow_transitions -> workflow_instances (instance_id));
+diesel::joinable!(workflow_transitions -> users (actor_id));
 
 diesel::allow_tables_to_appear_in_same_query!(workflows, projects);
 diesel::allow_tables_to_appear_in_same_query!(workflow_instances, workflows);
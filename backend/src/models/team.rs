@@ -5,7 +5,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::schema::{teams, team_members};
+use crate::models::schema::{project_teams, teams, team_members};
 
 /// Team model
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
@@ -79,3 +79,25 @@ pub struct UpdateTeamMember {
     pub is_active: Option<bool>,
 }
 
+/// Role granted to a team's members in a project
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = project_teams)]
+pub struct ProjectTeam {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub team_id: Uuid,
+    pub role: String,
+    pub granted_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New project team grant (for inserts)
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = project_teams)]
+pub struct NewProjectTeam {
+    pub project_id: Uuid,
+    pub team_id: Uuid,
+    pub role: String,
+    pub granted_by: Option<Uuid>,
+}
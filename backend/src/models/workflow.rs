@@ -5,7 +5,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::schema::{workflows, workflow_instances, workflow_rules};
+use crate::models::schema::{workflows, workflow_instances, workflow_rules, workflow_transitions};
 
 /// Workflow model
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
@@ -59,6 +59,8 @@ pub struct WorkflowInstance {
     pub error_message: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// What a waiting instance is paused on, e.g. `job:<id>`
+    pub waiting_for: Option<String>,
 }
 
 /// New workflow instance (for inserts)
@@ -81,7 +83,8 @@ pub struct UpdateWorkflowInstance {
     pub current_step: Option<String>,
     pub state: Option<serde_json::Value>,
     pub completed_at: Option<DateTime<Utc>>,
-    pub error_message: Option<String>,
+    pub error_message: Option<Option<String>>,
+    pub waiting_for: Option<Option<String>>,
 }
 
 /// Workflow rule model
@@ -122,3 +125,33 @@ pub struct UpdateWorkflowRule {
     pub is_active: Option<bool>,
 }
 
+
+/// Recorded change of a workflow instance's step or status
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = workflow_transitions)]
+pub struct WorkflowTransition {
+    pub id: Uuid,
+    pub instance_id: Uuid,
+    pub from_step: Option<String>,
+    pub to_step: Option<String>,
+    pub from_status: String,
+    pub to_status: String,
+    pub reason: String,
+    pub details: serde_json::Value,
+    pub actor_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New workflow transition (for inserts)
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = workflow_transitions)]
+pub struct NewWorkflowTransition {
+    pub instance_id: Uuid,
+    pub from_step: Option<String>,
+    pub to_step: Option<String>,
+    pub from_status: String,
+    pub to_status: String,
+    pub reason: String,
+    pub details: serde_json::Value,
+    pub actor_id: Option<Uuid>,
+}
@@ -0,0 +1,596 @@
+//! Tamper-evident audit trail
+//!
+//! Every entry records the actor, action, entity, a before/after diff, the
+//! client IP and the request's correlation ID. Entries are chained: each has
+//! a `sequence`, the `prev_hash` of the entry before it, and an `entry_hash`,
+//! the SHA-256 of its canonical JSON (object keys sorted) including that
+//! previous hash. Editing, deleting or reordering an entry therefore breaks
+//! the chain from that entry on, which `AuditTrail::verify` reports. Removing
+//! entries from the end can only be detected against a head hash kept
+//! elsewhere, so `verify` returns the current one.
+//!
+//! Appends take a transaction-scoped advisory lock so that the chain stays
+//! linear across processes; pass the connection of an open transaction to
+//! `append` to commit the entry together with the change it describes.
+
+use actix_web::{HttpMessage, HttpRequest};
+use chrono::{DateTime, SecondsFormat, Utc};
+use diesel::pg::PgConnection;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::audit_logs;
+use crate::models::AuditLog;
+
+/// `prev_hash` of the first entry in the chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Advisory lock serializing appends to the chain
+const CHAIN_LOCK_KEY: i64 = 0x6175_6469_745f_6c67;
+/// Entries read per query while verifying
+const VERIFY_PAGE_SIZE: i64 = 1000;
+
+/// Who made a change, and from where
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditContext {
+    pub actor: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub correlation_id: Option<String>,
+}
+
+impl AuditContext {
+    /// Actor, client IP, user agent and correlation ID of a request
+    pub fn from_request(req: &HttpRequest) -> Self {
+        // Before borrowing the extensions: connection info is cached in them
+        let ip_address = crate::handlers::helpers::get_client_ip(req);
+        let extensions = req.extensions();
+        Self {
+            actor: extensions.get::<Uuid>().copied(),
+            ip_address: Some(ip_address),
+            user_agent: req
+                .headers()
+                .get("User-Agent")
+                .and_then(|ua| ua.to_str().ok())
+                .map(str::to_string),
+            correlation_id: extensions.get::<String>().cloned(),
+        }
+    }
+
+    /// Attribute the change to `actor`, as authenticated by the handler
+    pub fn with_actor(mut self, actor: Uuid) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Changes made by the system itself, such as background workers
+    pub fn system() -> Self {
+        Self::default()
+    }
+}
+
+/// Request extension set by handlers that wrote their own audit entry, so
+/// that the audit middleware doesn't add a generic one
+#[derive(Debug, Clone, Copy)]
+pub struct Audited;
+
+/// Mark `req` as audited by its handler
+pub fn mark_audited(req: &HttpRequest) {
+    req.extensions_mut().insert(Audited);
+}
+
+/// A change to record
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub details: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        action: impl Into<String>,
+        resource_type: impl Into<String>,
+        resource_id: Option<Uuid>,
+    ) -> Self {
+        Self {
+            action: action.into(),
+            resource_type: resource_type.into(),
+            resource_id,
+            before: None,
+            after: None,
+            details: None,
+        }
+    }
+
+    /// State of the entity before and after the change; `None` for a side
+    /// that doesn't exist, as for creation or deletion
+    pub fn with_change(mut self, before: Option<Value>, after: Option<Value>) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// `details` with the field-level diff of `before` and `after` under `changes`
+    fn details_with_diff(&self) -> Option<Value> {
+        let changes = match (&self.before, &self.after) {
+            (None, None) => None,
+            (before, after) => Some(diff(
+                before.as_ref().unwrap_or(&Value::Null),
+                after.as_ref().unwrap_or(&Value::Null),
+            )),
+        };
+        match (self.details.clone(), changes) {
+            (details, None) => details,
+            (Some(Value::Object(mut details)), Some(changes)) => {
+                details.insert("changes".to_string(), changes);
+                Some(Value::Object(details))
+            }
+            (Some(other), Some(changes)) => Some(json!({ "info": other, "changes": changes })),
+            (None, Some(changes)) => Some(json!({ "changes": changes })),
+        }
+    }
+}
+
+/// Fields that differ between `before` and `after`, keyed by dotted path,
+/// each as `{"before": .., "after": ..}`; objects are compared field by field
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let mut changes = Map::new();
+    diff_into(&mut changes, String::new(), before, after);
+    Value::Object(changes)
+}
+
+fn diff_into(changes: &mut Map<String, Value>, path: String, before: &Value, after: &Value) {
+    // A created or removed object is diffed field by field against nothing
+    let empty = Value::Object(Map::new());
+    let before = if before.is_null() && after.is_object() {
+        &empty
+    } else {
+        before
+    };
+    let after = if after.is_null() && before.is_object() {
+        &empty
+    } else {
+        after
+    };
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_into(
+                    changes,
+                    child,
+                    before.get(key).unwrap_or(&Value::Null),
+                    after.get(key).unwrap_or(&Value::Null),
+                );
+            }
+        }
+        (before, after) if before != after => {
+            let path = if path.is_empty() {
+                "$".to_string()
+            } else {
+                path
+            };
+            changes.insert(path, json!({ "before": before, "after": after }));
+        }
+        _ => {}
+    }
+}
+
+/// Canonical JSON serialization; keys are written in sorted order whether or
+/// not serde_json preserves insertion order
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::String(key.clone()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(canonical_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// Hash of `entry` chained to `entry.prev_hash`
+///
+/// `user_id` is left out: it references `users` and is cleared when the user
+/// is deleted, while `actor_id` keeps the actor for the chain.
+pub fn entry_hash(entry: &AuditLog) -> String {
+    let payload = json!({
+        "id": entry.id,
+        "sequence": entry.sequence,
+        "prev_hash": entry.prev_hash,
+        "actor_id": entry.actor_id,
+        "action": entry.action,
+        "resource_type": entry.resource_type,
+        "resource_id": entry.resource_id,
+        "details": entry.details,
+        "old_values": entry.old_values,
+        "new_values": entry.new_values,
+        "ip_address": entry.ip_address.map(|ip| ip.to_string()),
+        "user_agent": entry.user_agent,
+        "correlation_id": entry.correlation_id,
+        "created_at": entry.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+    });
+    hex::encode(Sha256::digest(canonical_json(&payload).as_bytes()))
+}
+
+/// Postgres stores timestamps to the microsecond; truncate before hashing so
+/// that the stored entry hashes the same
+fn to_micros(at: DateTime<Utc>) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(at.timestamp_micros()).unwrap_or(at)
+}
+
+/// Append `event` to the chain on `conn`
+///
+/// Runs in a (nested) transaction holding the chain lock until the outermost
+/// transaction ends.
+pub fn append(
+    conn: &mut PgConnection,
+    ctx: &AuditContext,
+    event: AuditEvent,
+) -> AppResult<AuditLog> {
+    conn.transaction(|tx| {
+        diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+            .bind::<diesel::sql_types::BigInt, _>(CHAIN_LOCK_KEY)
+            .execute(tx)?;
+        let last = audit_logs::table
+            .filter(audit_logs::sequence.is_not_null())
+            .order(audit_logs::sequence.desc())
+            .select((audit_logs::sequence, audit_logs::entry_hash))
+            .first::<(Option<i64>, Option<String>)>(tx)
+            .optional()?;
+        let (sequence, prev_hash) = match last {
+            Some((Some(sequence), Some(hash))) => (sequence + 1, hash),
+            _ => (1, GENESIS_HASH.to_string()),
+        };
+
+        let mut entry = AuditLog {
+            id: Uuid::new_v4(),
+            user_id: ctx.actor,
+            action: event.action.clone(),
+            resource_type: event.resource_type.clone(),
+            resource_id: event.resource_id,
+            details: event.details_with_diff(),
+            old_values: event.before.clone(),
+            new_values: event.after.clone(),
+            ip_address: ctx
+                .ip_address
+                .as_deref()
+                .and_then(|ip| ip.parse::<ipnetwork::IpNetwork>().ok()),
+            user_agent: ctx.user_agent.clone(),
+            created_at: to_micros(Utc::now()),
+            sequence: Some(sequence),
+            actor_id: ctx.actor,
+            correlation_id: ctx.correlation_id.clone(),
+            prev_hash: Some(prev_hash),
+            entry_hash: None,
+        };
+        entry.entry_hash = Some(entry_hash(&entry));
+
+        diesel::insert_into(audit_logs::table)
+            .values(&entry)
+            .execute(tx)?;
+        Ok(entry)
+    })
+    .map_err(AppError::Database)
+}
+
+/// Where and why the chain breaks
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChainBreak {
+    pub id: Uuid,
+    pub sequence: Option<i64>,
+    pub reason: String,
+}
+
+/// Result of checking the chain
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChainVerification {
+    pub valid: bool,
+    /// Chained entries checked
+    pub checked: u64,
+    pub first_break: Option<ChainBreak>,
+    /// Hash of the last entry; compare with a previously recorded head to
+    /// detect entries removed from the end
+    pub head_hash: Option<String>,
+}
+
+/// Running state of a verification, fed entries in sequence order
+#[derive(Debug, Clone)]
+pub struct ChainVerifier {
+    next_sequence: i64,
+    prev_hash: String,
+    checked: u64,
+    first_break: Option<ChainBreak>,
+}
+
+impl Default for ChainVerifier {
+    fn default() -> Self {
+        Self {
+            next_sequence: 1,
+            prev_hash: GENESIS_HASH.to_string(),
+            checked: 0,
+            first_break: None,
+        }
+    }
+}
+
+impl ChainVerifier {
+    /// Check the next entry; returns false once the chain is broken
+    pub fn check(&mut self, entry: &AuditLog) -> bool {
+        if self.first_break.is_some() {
+            return false;
+        }
+        self.checked += 1;
+        let reason = if entry.sequence != Some(self.next_sequence) {
+            Some(format!(
+                "expected sequence {}, found {:?}; entries were removed or reordered",
+                self.next_sequence, entry.sequence
+            ))
+        } else if entry.prev_hash.as_deref() != Some(self.prev_hash.as_str()) {
+            Some("prev_hash doesn't match the previous entry".to_string())
+        } else if entry.entry_hash.as_deref() != Some(entry_hash(entry).as_str()) {
+            Some("entry_hash doesn't match the entry's contents; it was modified".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            self.first_break = Some(ChainBreak {
+                id: entry.id,
+                sequence: entry.sequence,
+                reason,
+            });
+            return false;
+        }
+        self.next_sequence += 1;
+        self.prev_hash = entry.entry_hash.clone().unwrap_or_default();
+        true
+    }
+
+    pub fn finish(self) -> ChainVerification {
+        ChainVerification {
+            valid: self.first_break.is_none(),
+            checked: self.checked,
+            head_hash: (self.next_sequence > 1).then_some(self.prev_hash),
+            first_break: self.first_break,
+        }
+    }
+}
+
+/// Filters for listing entries
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<Uuid>,
+    pub correlation_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// The audit trail in the database
+#[derive(Clone)]
+pub struct AuditTrail {
+    db: Arc<Database>,
+}
+
+impl AuditTrail {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Append `event` in its own transaction
+    pub fn record(&self, ctx: &AuditContext, event: AuditEvent) -> AppResult<AuditLog> {
+        let mut conn = self.db.get_connection()?;
+        append(&mut conn, ctx, event)
+    }
+
+    /// Entries matching `query`, newest first
+    pub fn list(&self, query: &AuditQuery) -> AppResult<Vec<AuditLog>> {
+        let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+        let page = query.page.unwrap_or(1).max(1);
+        let mut conn = self.db.get_connection()?;
+        let mut select = audit_logs::table.into_boxed();
+        if let Some(actor_id) = query.actor_id {
+            select = select.filter(audit_logs::actor_id.eq(actor_id));
+        }
+        if let Some(ref action) = query.action {
+            select = select.filter(audit_logs::action.eq(action.clone()));
+        }
+        if let Some(ref resource_type) = query.resource_type {
+            select = select.filter(audit_logs::resource_type.eq(resource_type.clone()));
+        }
+        if let Some(resource_id) = query.resource_id {
+            select = select.filter(audit_logs::resource_id.eq(resource_id));
+        }
+        if let Some(ref correlation_id) = query.correlation_id {
+            select = select.filter(audit_logs::correlation_id.eq(correlation_id.clone()));
+        }
+        if let Some(from) = query.from {
+            select = select.filter(audit_logs::created_at.ge(from));
+        }
+        if let Some(to) = query.to {
+            select = select.filter(audit_logs::created_at.lt(to));
+        }
+        select
+            .order((audit_logs::created_at.desc(), audit_logs::sequence.desc()))
+            .limit(per_page)
+            .offset((page - 1) * per_page)
+            .load::<AuditLog>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Recompute the chain from the first entry. Entries written before the
+    /// chain existed have no sequence and are not checked.
+    pub fn verify(&self) -> AppResult<ChainVerification> {
+        let mut conn = self.db.get_connection()?;
+        let mut verifier = ChainVerifier::default();
+        let mut after = 0i64;
+        loop {
+            let page = audit_logs::table
+                .filter(audit_logs::sequence.gt(after))
+                .order(audit_logs::sequence.asc())
+                .limit(VERIFY_PAGE_SIZE)
+                .load::<AuditLog>(&mut conn)
+                .map_err(AppError::Database)?;
+            let Some(last) = page.last().and_then(|entry| entry.sequence) else {
+                break;
+            };
+            if !page.iter().all(|entry| verifier.check(entry)) {
+                break;
+            }
+            after = last;
+        }
+        Ok(verifier.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(events: usize) -> Vec<AuditLog> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=events as i64)
+            .map(|sequence| {
+                let mut entry = AuditLog {
+                    id: Uuid::new_v4(),
+                    user_id: None,
+                    action: "approve".to_string(),
+                    resource_type: "reconciliation_match".to_string(),
+                    resource_id: Some(Uuid::new_v4()),
+                    details: Some(json!({ "changes": { "status": { "before": "pending", "after": "approved" } } })),
+                    old_values: Some(json!({ "status": "pending" })),
+                    new_values: Some(json!({ "status": "approved" })),
+                    ip_address: "10.0.0.1".parse().ok(),
+                    user_agent: Some("test".to_string()),
+                    created_at: to_micros(Utc::now()),
+                    sequence: Some(sequence),
+                    actor_id: Some(Uuid::new_v4()),
+                    correlation_id: Some("corr".to_string()),
+                    prev_hash: Some(prev_hash.clone()),
+                    entry_hash: None,
+                };
+                entry.entry_hash = Some(entry_hash(&entry));
+                prev_hash = entry.entry_hash.clone().unwrap_or_default();
+                entry
+            })
+            .collect()
+    }
+
+    fn verify(entries: &[AuditLog]) -> ChainVerification {
+        let mut verifier = ChainVerifier::default();
+        for entry in entries {
+            verifier.check(entry);
+        }
+        verifier.finish()
+    }
+
+    #[test]
+    fn hash_ignores_key_order_and_user_id() {
+        let entry = chain(1).remove(0);
+        let mut reordered = entry.clone();
+        reordered.details = serde_json::from_str(
+            r#"{"changes":{"status":{"after":"approved","before":"pending"}}}"#,
+        )
+        .ok();
+        reordered.user_id = None;
+        assert_eq!(entry_hash(&entry), entry_hash(&reordered));
+
+        let mut edited = entry.clone();
+        edited.new_values = Some(json!({ "status": "rejected" }));
+        assert_ne!(entry_hash(&entry), entry_hash(&edited));
+    }
+
+    #[test]
+    fn verifies_chain_and_finds_first_break() {
+        let entries = chain(4);
+        let result = verify(&entries);
+        assert!(result.valid);
+        assert_eq!(result.checked, 4);
+        assert_eq!(result.head_hash, entries[3].entry_hash);
+
+        let mut modified = entries.clone();
+        modified[1].action = "reject".to_string();
+        let result = verify(&modified);
+        assert!(!result.valid);
+        assert_eq!(result.first_break.map(|b| b.sequence), Some(Some(2)));
+
+        let mut removed = entries.clone();
+        removed.remove(2);
+        let result = verify(&removed);
+        assert_eq!(result.first_break.map(|b| b.sequence), Some(Some(4)));
+
+        // Re-hashing a modified entry still breaks the link to the next one
+        let mut rehashed = entries;
+        rehashed[0].action = "reject".to_string();
+        rehashed[0].entry_hash = Some(entry_hash(&rehashed[0]));
+        let result = verify(&rehashed);
+        assert_eq!(result.first_break.map(|b| b.sequence), Some(Some(2)));
+    }
+
+    #[test]
+    fn diffs_nested_fields() {
+        let before =
+            json!({ "name": "Q1", "settings": { "threshold": 0.8, "fields": ["a"] }, "old": 1 });
+        let after =
+            json!({ "name": "Q1", "settings": { "threshold": 0.9, "fields": ["a"] }, "new": 2 });
+        assert_eq!(
+            diff(&before, &after),
+            json!({
+                "new": { "before": null, "after": 2 },
+                "old": { "before": 1, "after": null },
+                "settings.threshold": { "before": 0.8, "after": 0.9 },
+            })
+        );
+        assert_eq!(
+            diff(&Value::Null, &json!("created")),
+            json!({ "$": { "before": null, "after": "created" } })
+        );
+
+        let event = AuditEvent::new("update", "rule", None)
+            .with_change(Some(json!({ "a": 1 })), Some(json!({ "a": 2 })))
+            .with_details(json!({ "job_id": "j" }));
+        assert_eq!(
+            event.details_with_diff(),
+            Some(json!({ "job_id": "j", "changes": { "a": { "before": 1, "after": 2 } } }))
+        );
+    }
+}
@@ -0,0 +1,685 @@
+//! Personal and service API keys
+//!
+//! A key authenticates as its user wherever a JWT is accepted: a personal key
+//! as the user who created it, a service key as the service account an admin
+//! created it for. What it can reach is narrowed twice. Its scopes
+//! (`ingestion:write`, `reconciliation:read`, `*`) name the route groups it
+//! may call, `write` covering every method and `read` only safe ones. When
+//! it lists projects, it can act only in those, whatever roles its user holds
+//! elsewhere. The project restriction is applied by the project role lookups
+//! in `utils::authorization`, which see the [`ProjectGrant`] of the request
+//! being handled through [`with_grant`] and deny access without one.
+//!
+//! Only a SHA-256 of a key is stored; the key itself is returned once, when
+//! it is created. Revoked and expired keys are refused.
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{api_keys, users};
+use crate::models::{ApiKey, NewApiKey, User};
+use crate::services::audit::{self, AuditContext, AuditEvent};
+
+/// Start of every key, telling keys apart from JWTs in `Authorization`
+pub const KEY_PREFIX: &str = "rk_";
+/// Random characters after the prefix
+const SECRET_LENGTH: usize = 40;
+/// Characters of a key kept in `key_prefix` to identify it in listings
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+/// `last_used_at` is only rewritten once it is older than this
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+/// Route groups no key can call, whatever its scopes
+const KEYLESS_RESOURCES: &[&str] = &["auth", "api-keys"];
+/// User statuses that can't authenticate
+const DISABLED_STATUSES: &[&str] = &["inactive", "suspended"];
+/// Role (stored in the status field) of service accounts, the only users
+/// service keys can be issued for
+pub const SERVICE_ACCOUNT_STATUS: &str = "service";
+
+/// Whose identity a key carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyKind {
+    /// Acts as the user who created it
+    #[default]
+    Personal,
+    /// Acts as a service account; created by admins
+    Service,
+}
+
+impl ApiKeyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyKind::Personal => "personal",
+            ApiKeyKind::Service => "service",
+        }
+    }
+}
+
+impl FromStr for ApiKeyKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "personal" => Ok(ApiKeyKind::Personal),
+            "service" => Ok(ApiKeyKind::Service),
+            _ => Err(AppError::Validation(format!("Invalid API key kind: {}", s))),
+        }
+    }
+}
+
+/// Access a request needs to a route group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeAccess {
+    /// GET, HEAD and OPTIONS
+    Read,
+    /// Any method
+    Write,
+}
+
+impl ScopeAccess {
+    pub fn for_method(method: &str) -> Self {
+        match method {
+            "GET" | "HEAD" | "OPTIONS" => ScopeAccess::Read,
+            _ => ScopeAccess::Write,
+        }
+    }
+}
+
+/// What a key may call: `*`, or `<resource>:read` or `<resource>:write`
+/// where the resource is a route group such as `ingestion`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    All,
+    Resource {
+        resource: String,
+        access: ScopeAccess,
+    },
+}
+
+impl ApiKeyScope {
+    pub fn allows(&self, resource: &str, access: ScopeAccess) -> bool {
+        if KEYLESS_RESOURCES.contains(&resource) {
+            return false;
+        }
+        match self {
+            ApiKeyScope::All => true,
+            ApiKeyScope::Resource {
+                resource: granted,
+                access: granted_access,
+            } => {
+                granted == resource
+                    && (*granted_access == ScopeAccess::Write || access == ScopeAccess::Read)
+            }
+        }
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(ApiKeyScope::All);
+        }
+        let invalid = || {
+            AppError::Validation(format!(
+                "Invalid scope '{}': expected '*', '<resource>:read' or '<resource>:write'",
+                s
+            ))
+        };
+        let (resource, access) = s.split_once(':').ok_or_else(invalid)?;
+        let access = match access {
+            "read" => ScopeAccess::Read,
+            "write" => ScopeAccess::Write,
+            _ => return Err(invalid()),
+        };
+        if resource.is_empty()
+            || !resource
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c == '-' || c == '_')
+        {
+            return Err(invalid());
+        }
+        if KEYLESS_RESOURCES.contains(&resource) {
+            return Err(AppError::Validation(format!(
+                "API keys can't be granted access to {}",
+                resource
+            )));
+        }
+        Ok(ApiKeyScope::Resource {
+            resource: resource.to_string(),
+            access,
+        })
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiKeyScope::All => write!(f, "*"),
+            ApiKeyScope::Resource { resource, access } => match access {
+                ScopeAccess::Read => write!(f, "{}:read", resource),
+                ScopeAccess::Write => write!(f, "{}:write", resource),
+            },
+        }
+    }
+}
+
+/// Route group of a request path: the segment after `/api/`, `/api/v1/` or
+/// `/api/v2/`
+pub fn request_resource(path: &str) -> &str {
+    let rest = path
+        .strip_prefix("/api/")
+        .unwrap_or(path.trim_start_matches('/'));
+    let rest = rest
+        .strip_prefix("v1/")
+        .or_else(|| rest.strip_prefix("v2/"))
+        .unwrap_or(rest);
+    rest.split('/').next().unwrap_or_default()
+}
+
+/// What a request authenticated by an API key may do
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyGrant {
+    pub key_id: Uuid,
+    pub user_id: Uuid,
+    pub kind: ApiKeyKind,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Only projects the key can act in; `None` for all of its user's
+    pub project_ids: Option<Vec<Uuid>>,
+}
+
+impl ApiKeyGrant {
+    /// Check the key's scopes cover a call to `path` with `method`
+    pub fn check_request(&self, path: &str, method: &str) -> AppResult<()> {
+        let resource = request_resource(path);
+        let access = ScopeAccess::for_method(method);
+        if self
+            .scopes
+            .iter()
+            .any(|scope| scope.allows(resource, access))
+        {
+            return Ok(());
+        }
+        let needed = match access {
+            ScopeAccess::Read => "read",
+            ScopeAccess::Write => "write",
+        };
+        Err(AppError::Forbidden(format!(
+            "API key lacks the {}:{} scope",
+            resource, needed
+        )))
+    }
+
+    pub fn allows_project(&self, project_id: Uuid) -> bool {
+        self.project_ids
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&project_id))
+    }
+}
+
+/// Projects a request, or work done on its behalf, may act in
+///
+/// Authentication puts one in the request extensions: [`Unrestricted`] once a
+/// JWT is verified, [`Key`] once an API key is.
+///
+/// [`Unrestricted`]: ProjectGrant::Unrestricted
+/// [`Key`]: ProjectGrant::Key
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectGrant {
+    /// Only the user's project roles decide; for JWTs
+    Unrestricted,
+    /// Also limited to the projects of the key that authenticated the request
+    Key(ApiKeyGrant),
+}
+
+impl ProjectGrant {
+    pub fn allows_project(&self, project_id: Uuid) -> bool {
+        match self {
+            ProjectGrant::Unrestricted => true,
+            ProjectGrant::Key(grant) => grant.allows_project(project_id),
+        }
+    }
+
+    /// Projects the grant is limited to; `None` when it isn't
+    pub fn project_restriction(&self) -> Option<&[Uuid]> {
+        match self {
+            ProjectGrant::Unrestricted => None,
+            ProjectGrant::Key(grant) => grant.project_ids.as_deref(),
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Grant of the request being handled
+    static CURRENT_GRANT: ProjectGrant;
+}
+
+/// Handle a request under the grant its authentication settled on, so that
+/// project checks made while handling it honour an API key's restriction
+pub async fn with_grant<F: Future>(grant: ProjectGrant, fut: F) -> F::Output {
+    CURRENT_GRANT.scope(grant, fut).await
+}
+
+/// Grant of the request being handled; `None` outside of one
+///
+/// Task-locals don't follow work onto other tasks (`tokio::spawn`,
+/// `web::block`), so there the grant can't be known and project checks
+/// deny access rather than lift a key's restriction.
+pub fn current_grant() -> Option<ProjectGrant> {
+    CURRENT_GRANT.try_with(ProjectGrant::clone).ok()
+}
+
+/// Whether a bearer token is an API key rather than a JWT
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_PREFIX)
+}
+
+/// Hash under which a key is stored
+pub fn hash_key(key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn generate_key() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", KEY_PREFIX, secret)
+}
+
+/// Scopes stored for a key
+pub fn stored_scopes(key: &ApiKey) -> AppResult<Vec<ApiKeyScope>> {
+    let scopes: Vec<String> = serde_json::from_value(key.permissions.clone())
+        .map_err(|e| AppError::Internal(format!("Invalid scopes on API key {}: {}", key.id, e)))?;
+    scopes.iter().map(|scope| scope.parse()).collect()
+}
+
+/// Request for a new key
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ApiKeyKind,
+    /// Service account a service key acts as; personal keys act as their
+    /// creator
+    pub user_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub project_ids: Option<Vec<Uuid>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl CreateApiKey {
+    fn validate(&self) -> AppResult<Vec<ApiKeyScope>> {
+        let name = self.name.trim();
+        if name.is_empty() || name.len() > 255 {
+            return Err(AppError::Validation(
+                "name must be between 1 and 255 characters".to_string(),
+            ));
+        }
+        if self.scopes.is_empty() {
+            return Err(AppError::Validation(
+                "At least one scope is required".to_string(),
+            ));
+        }
+        if matches!(&self.project_ids, Some(ids) if ids.is_empty()) {
+            return Err(AppError::Validation(
+                "project_ids must list at least one project, or be left out for all".to_string(),
+            ));
+        }
+        if matches!(self.expires_at, Some(at) if at <= Utc::now()) {
+            return Err(AppError::Validation(
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+        self.scopes.iter().map(|scope| scope.parse()).collect()
+    }
+}
+
+/// A key as created; `key` is returned only this once
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Issues, lists, revokes and authenticates API keys
+pub struct ApiKeyService {
+    db: Database,
+}
+
+impl ApiKeyService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Issue a key for `creator`, or as an admin a service key for the
+    /// service account in the request
+    pub fn create(
+        &self,
+        ctx: &AuditContext,
+        creator: Uuid,
+        request: CreateApiKey,
+    ) -> AppResult<CreatedApiKey> {
+        let scopes = request.validate()?;
+        let user_id = match request.kind {
+            ApiKeyKind::Personal => {
+                if request.user_id.is_some_and(|id| id != creator) {
+                    return Err(AppError::Validation(
+                        "Personal keys can only be created for yourself".to_string(),
+                    ));
+                }
+                creator
+            }
+            ApiKeyKind::Service => {
+                crate::utils::check_admin_permission(&self.db, creator)?;
+                request.user_id.ok_or_else(|| {
+                    AppError::Validation(
+                        "Service keys require the service account's user_id".to_string(),
+                    )
+                })?
+            }
+        };
+
+        if let Some(project_ids) = &request.project_ids {
+            // A key can only be narrowed to projects its user can already see
+            if let Some(accessible) = crate::utils::accessible_project_ids(&self.db, user_id)? {
+                if let Some(missing) = project_ids.iter().find(|id| !accessible.contains(id)) {
+                    return Err(AppError::Validation(format!(
+                        "The key's user has no access to project {}",
+                        missing
+                    )));
+                }
+            }
+        }
+
+        let key = generate_key();
+        let new_key = NewApiKey {
+            user_id,
+            name: request.name.trim().to_string(),
+            key_hash: hash_key(&key),
+            key_prefix: key.chars().take(DISPLAY_PREFIX_LENGTH).collect(),
+            permissions: json!(scopes.iter().map(ToString::to_string).collect::<Vec<_>>()),
+            expires_at: request.expires_at,
+            is_active: true,
+            kind: request.kind.as_str().to_string(),
+            project_ids: request.project_ids.as_ref().map(|ids| json!(ids)),
+            created_by: Some(creator),
+        };
+
+        let mut conn = self.db.get_connection()?;
+        let api_key = conn.transaction::<_, AppError, _>(|tx| {
+            let status = users::table
+                .find(user_id)
+                .select(users::status)
+                .first::<String>(tx)
+                .optional()?
+                .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+            if DISABLED_STATUSES.contains(&status.as_str()) {
+                return Err(AppError::Validation(format!(
+                    "User {} is {}",
+                    user_id, status
+                )));
+            }
+            if request.kind == ApiKeyKind::Service && status != SERVICE_ACCOUNT_STATUS {
+                return Err(AppError::Validation(format!(
+                    "User {} is not a service account",
+                    user_id
+                )));
+            }
+
+            let api_key = diesel::insert_into(api_keys::table)
+                .values(&new_key)
+                .get_result::<ApiKey>(tx)?;
+            audit::append(
+                tx,
+                ctx,
+                AuditEvent::new("api_key.create", "api_key", Some(api_key.id)).with_details(
+                    json!({
+                        "name": api_key.name,
+                        "kind": api_key.kind,
+                        "user_id": api_key.user_id,
+                        "key_prefix": api_key.key_prefix,
+                        "scopes": api_key.permissions,
+                        "project_ids": api_key.project_ids,
+                        "expires_at": api_key.expires_at,
+                    }),
+                ),
+            )?;
+            Ok(api_key)
+        })?;
+
+        Ok(CreatedApiKey { key, api_key })
+    }
+
+    /// Keys a user owns or created; every key for `None`
+    pub fn list(&self, user_id: Option<Uuid>) -> AppResult<Vec<ApiKey>> {
+        let mut conn = self.db.get_connection()?;
+        let mut query = api_keys::table.into_boxed();
+        if let Some(user_id) = user_id {
+            query = query.filter(
+                api_keys::user_id
+                    .eq(user_id)
+                    .or(api_keys::created_by.eq(user_id)),
+            );
+        }
+        query
+            .order(api_keys::created_at.desc())
+            .load::<ApiKey>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    pub fn get(&self, id: Uuid) -> AppResult<ApiKey> {
+        let mut conn = self.db.get_connection()?;
+        api_keys::table
+            .find(id)
+            .first::<ApiKey>(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("API key {} not found", id)))
+    }
+
+    /// Revoke a key; its user, its creator and admins can
+    pub fn revoke(&self, ctx: &AuditContext, id: Uuid, actor: Uuid) -> AppResult<ApiKey> {
+        let key = self.get(id)?;
+        if key.user_id != actor && key.created_by != Some(actor) {
+            crate::utils::check_admin_permission(&self.db, actor)?;
+        }
+
+        let mut conn = self.db.get_connection()?;
+        conn.transaction::<_, AppError, _>(|tx| {
+            let now = Utc::now();
+            let revoked = diesel::update(
+                api_keys::table
+                    .find(id)
+                    .filter(api_keys::revoked_at.is_null()),
+            )
+            .set((
+                api_keys::is_active.eq(false),
+                api_keys::revoked_at.eq(now),
+                api_keys::revoked_by.eq(actor),
+                api_keys::updated_at.eq(now),
+            ))
+            .get_result::<ApiKey>(tx)
+            .optional()?
+            .ok_or_else(|| AppError::Conflict(format!("API key {} is already revoked", id)))?;
+            audit::append(
+                tx,
+                ctx,
+                AuditEvent::new("api_key.revoke", "api_key", Some(id)).with_details(json!({
+                    "user_id": revoked.user_id,
+                    "key_prefix": revoked.key_prefix,
+                })),
+            )?;
+            Ok(revoked)
+        })
+    }
+
+    /// Look up a presented key, refusing revoked and expired ones and those
+    /// of disabled users, and note its use
+    pub fn authenticate(&self, key: &str, ip: Option<&str>) -> AppResult<(ApiKeyGrant, User)> {
+        let mut conn = self.db.get_connection()?;
+        let api_key = api_keys::table
+            .filter(api_keys::key_hash.eq(hash_key(key)))
+            .first::<ApiKey>(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+        let user = users::table
+            .find(api_key.user_id)
+            .first::<User>(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::Unauthorized("API key's user no longer exists".to_string()))?;
+
+        let now = Utc::now();
+        if api_key.revoked_at.is_some() || !api_key.is_active {
+            return Err(AppError::Unauthorized(
+                "API key has been revoked".to_string(),
+            ));
+        }
+        if api_key.expires_at.is_some_and(|at| at <= now) {
+            return Err(AppError::Unauthorized("API key has expired".to_string()));
+        }
+        if DISABLED_STATUSES.contains(&user.status.as_str()) {
+            return Err(AppError::Unauthorized(
+                "API key's user is disabled".to_string(),
+            ));
+        }
+
+        let stale = api_key
+            .last_used_at
+            .is_none_or(|at| now - at >= Duration::seconds(LAST_USED_RESOLUTION_SECS));
+        if stale || api_key.last_used_ip.as_deref() != ip {
+            if let Err(e) = diesel::update(api_keys::table.find(api_key.id))
+                .set((
+                    api_keys::last_used_at.eq(now),
+                    api_keys::last_used_ip.eq(ip.map(|ip| ip.chars().take(45).collect::<String>())),
+                ))
+                .execute(&mut conn)
+            {
+                log::warn!("Failed to record use of API key {}: {}", api_key.id, e);
+            }
+        }
+
+        let project_ids = match &api_key.project_ids {
+            Some(ids) => Some(serde_json::from_value(ids.clone()).map_err(|e| {
+                AppError::Internal(format!("Invalid projects on API key {}: {}", api_key.id, e))
+            })?),
+            None => None,
+        };
+        let grant = ApiKeyGrant {
+            key_id: api_key.id,
+            user_id: api_key.user_id,
+            kind: api_key.kind.parse()?,
+            scopes: stored_scopes(&api_key)?,
+            project_ids,
+        };
+        Ok((grant, user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(scopes: &[&str], project_ids: Option<Vec<Uuid>>) -> ApiKeyGrant {
+        ApiKeyGrant {
+            key_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            kind: ApiKeyKind::Service,
+            scopes: scopes
+                .iter()
+                .map(|s| s.parse().unwrap_or_else(|e| panic!("{}: {:?}", s, e)))
+                .collect(),
+            project_ids,
+        }
+    }
+
+    #[test]
+    fn test_scopes_cover_route_groups_by_method() {
+        let erp = grant(&["ingestion:write", "reconciliation:read"], None);
+
+        assert!(erp.check_request("/api/v1/ingestion/jobs", "POST").is_ok());
+        assert!(erp.check_request("/api/ingestion/jobs/1", "GET").is_ok());
+        assert!(erp
+            .check_request("/api/v1/reconciliation/jobs", "GET")
+            .is_ok());
+        assert!(erp
+            .check_request("/api/v1/reconciliation/jobs", "POST")
+            .is_err());
+        assert!(erp.check_request("/api/v1/projects", "GET").is_err());
+
+        let all = grant(&["*"], None);
+        assert!(all.check_request("/api/v2/users", "DELETE").is_ok());
+        assert!(all.check_request("/api/v1/auth/refresh", "POST").is_err());
+        assert!(all.check_request("/api/api-keys", "POST").is_err());
+    }
+
+    #[test]
+    fn test_scope_parsing() {
+        assert_eq!("*".parse::<ApiKeyScope>().ok(), Some(ApiKeyScope::All));
+        for scope in ["ingestion:write", "cashflow:read"] {
+            let parsed = scope
+                .parse::<ApiKeyScope>()
+                .unwrap_or_else(|e| panic!("{}: {:?}", scope, e));
+            assert_eq!(parsed.to_string(), scope);
+        }
+        for scope in [
+            "ingestion",
+            "ingestion:delete",
+            ":read",
+            "Ingestion:read",
+            "auth:read",
+        ] {
+            assert!(scope.parse::<ApiKeyScope>().is_err(), "{}", scope);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_restriction_applies_within_grant() {
+        let allowed = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let restricted = ProjectGrant::Key(grant(&["*"], Some(vec![allowed])));
+
+        // Outside of a request there is no grant; for JWTs only roles decide
+        assert_eq!(current_grant(), None);
+        assert!(
+            with_grant(ProjectGrant::Unrestricted, async {
+                current_grant().is_some_and(|grant| grant.allows_project(other))
+            })
+            .await
+        );
+
+        let current = with_grant(restricted.clone(), async { current_grant() }).await;
+        assert_eq!(current.as_ref(), Some(&restricted));
+        assert!(restricted.allows_project(allowed));
+        assert!(!restricted.allows_project(other));
+        assert_eq!(restricted.project_restriction(), Some(&[allowed][..]));
+
+        // A spawned task doesn't inherit the request's grant, so it has none
+        let spawned = with_grant(restricted, async {
+            tokio::spawn(async { current_grant() }).await
+        })
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(spawned, None);
+
+        let unscoped = ProjectGrant::Key(grant(&["*"], None));
+        assert!(unscoped.allows_project(other));
+        assert_eq!(unscoped.project_restriction(), None);
+    }
+}
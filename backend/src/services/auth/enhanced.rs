@@ -272,15 +272,30 @@ impl EnhancedAuthService {
         crate::services::auth::roles::RoleManager::get_user_permissions(user_role)
     }
 
-    /// Generate API key
+    /// Issue a personal API key for a user, with every scope the user's
+    /// roles allow; see `ApiKeyService` for narrower and service keys
     pub async fn generate_api_key(
         &self,
-        _user_id: uuid::Uuid,
-        _description: &str,
-        _db: &Database,
+        user_id: uuid::Uuid,
+        description: &str,
+        db: &Database,
     ) -> AppResult<String> {
-        // Generate a secure API key
-        PasswordManager::generate_reset_token()
+        use crate::services::audit::AuditContext;
+        use crate::services::auth::api_keys::{ApiKeyService, CreateApiKey};
+
+        let created = ApiKeyService::new(db.clone()).create(
+            &AuditContext::system().with_actor(user_id),
+            user_id,
+            CreateApiKey {
+                name: description.to_string(),
+                kind: Default::default(),
+                user_id: None,
+                scopes: vec!["*".to_string()],
+                project_ids: None,
+                expires_at: None,
+            },
+        )?;
+        Ok(created.key)
     }
 
     /// Validate password strength
@@ -1,4 +1,8 @@
 //! User roles and role-based access control
+//!
+//! `UserRole` is a user's system-wide role; `ProjectRole` is the role a user
+//! holds in one project, through `project_members` or a team granted a role
+//! in the project, and decides which `ProjectAction`s they may take there.
 
 use crate::errors::AppError;
 use serde::{Deserialize, Serialize};
@@ -124,3 +128,137 @@ impl RoleManager {
         }
     }
 }
+
+/// Role of a user within one project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectRole {
+    Owner,
+    Approver,
+    Reviewer,
+    Preparer,
+    Viewer,
+}
+
+impl ProjectRole {
+    pub const ALL: [ProjectRole; 5] = [
+        ProjectRole::Owner,
+        ProjectRole::Approver,
+        ProjectRole::Reviewer,
+        ProjectRole::Preparer,
+        ProjectRole::Viewer,
+    ];
+
+    /// Whether this role may take `action`
+    ///
+    /// Preparing and reviewing are held by different roles, so that the
+    /// person who prepared a reconciliation is not the one who reviews it;
+    /// only owners hold both.
+    pub fn allows(self, action: ProjectAction) -> bool {
+        match self {
+            ProjectRole::Owner => true,
+            ProjectRole::Approver => matches!(
+                action,
+                ProjectAction::View | ProjectAction::Review | ProjectAction::Approve
+            ),
+            ProjectRole::Reviewer => matches!(action, ProjectAction::View | ProjectAction::Review),
+            ProjectRole::Preparer => matches!(action, ProjectAction::View | ProjectAction::Prepare),
+            ProjectRole::Viewer => action == ProjectAction::View,
+        }
+    }
+}
+
+impl std::str::FromStr for ProjectRole {
+    type Err = AppError;
+
+    /// Parse a role; `editor` and `member` are older names for preparer
+    ///
+    /// Stored roles are constrained to the canonical names, so only request
+    /// input still uses the older ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "owner" => Ok(ProjectRole::Owner),
+            "approver" => Ok(ProjectRole::Approver),
+            "reviewer" => Ok(ProjectRole::Reviewer),
+            "preparer" | "editor" | "member" => Ok(ProjectRole::Preparer),
+            "viewer" => Ok(ProjectRole::Viewer),
+            _ => Err(AppError::Validation(format!(
+                "Invalid project role: {}; expected owner, approver, reviewer, preparer or viewer",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectRole::Owner => write!(f, "owner"),
+            ProjectRole::Approver => write!(f, "approver"),
+            ProjectRole::Reviewer => write!(f, "reviewer"),
+            ProjectRole::Preparer => write!(f, "preparer"),
+            ProjectRole::Viewer => write!(f, "viewer"),
+        }
+    }
+}
+
+/// What a request does within a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectAction {
+    /// Read the project's data
+    View,
+    /// Ingest data and create, change, run or delete jobs, rules and records
+    Prepare,
+    /// Accept or reject matches, resolve discrepancies and decide cases
+    Review,
+    /// Sign off reviewed work
+    Approve,
+    /// Change members, roles and settings
+    Manage,
+}
+
+impl std::fmt::Display for ProjectAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectAction::View => write!(f, "view"),
+            ProjectAction::Prepare => write!(f, "prepare"),
+            ProjectAction::Review => write!(f, "review"),
+            ProjectAction::Approve => write!(f, "approve"),
+            ProjectAction::Manage => write!(f, "manage"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_roles_separate_preparing_from_reviewing() {
+        use ProjectAction::*;
+        let allowed = |role: ProjectRole| -> Vec<ProjectAction> {
+            [View, Prepare, Review, Approve, Manage]
+                .into_iter()
+                .filter(|action| role.allows(*action))
+                .collect()
+        };
+        assert_eq!(
+            allowed(ProjectRole::Owner),
+            vec![View, Prepare, Review, Approve, Manage]
+        );
+        assert_eq!(allowed(ProjectRole::Approver), vec![View, Review, Approve]);
+        assert_eq!(allowed(ProjectRole::Reviewer), vec![View, Review]);
+        assert_eq!(allowed(ProjectRole::Preparer), vec![View, Prepare]);
+        assert_eq!(allowed(ProjectRole::Viewer), vec![View]);
+
+        for role in ProjectRole::ALL {
+            assert_eq!(role.to_string().parse::<ProjectRole>().ok(), Some(role));
+        }
+        assert_eq!(
+            "Member".parse::<ProjectRole>().ok(),
+            Some(ProjectRole::Preparer)
+        );
+        assert!("admin".parse::<ProjectRole>().is_err());
+    }
+}
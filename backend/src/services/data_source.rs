@@ -11,8 +11,11 @@ use uuid::Uuid;
 
 use crate::database::Database;
 use crate::errors::{AppError, AppResult};
-use crate::models::schema::data_sources;
-use crate::models::{DataSource, NewDataSource, UpdateDataSource};
+use crate::models::schema::{data_source_mappings, data_source_statements, data_sources};
+use crate::models::{
+    DataSource, DataSourceMapping, DataSourceStatement, NewDataSource, NewDataSourceMapping, UpdateDataSource,
+};
+use crate::services::parsing::{SourceSchema, TransformSpec};
 use super::data_source_config::{CreateDataSourceConfig, UpdateDataSourceConfig};
 
 /// Data source service
@@ -164,6 +167,93 @@ impl DataSourceService {
         Ok(data_source)
     }
 
+    /// Get the bank statement balances read into a data source, in file order
+    pub async fn get_statements(&self, data_source_id: Uuid) -> AppResult<Vec<DataSourceStatement>> {
+        let mut conn = self.db.get_connection()?;
+
+        let statements = data_source_statements::table
+            .filter(data_source_statements::data_source_id.eq(data_source_id))
+            .order(data_source_statements::sequence.asc())
+            .load::<DataSourceStatement>(&mut conn)
+            .map_err(AppError::Database)?;
+
+        Ok(statements)
+    }
+
+    /// Save a transformation spec as the data source's next mapping version
+    pub async fn save_mapping(
+        &self,
+        data_source_id: Uuid,
+        spec: &TransformSpec,
+        created_by: Option<Uuid>,
+    ) -> AppResult<DataSourceMapping> {
+        spec.compile()
+            .map_err(|e| AppError::Validation(format!("Invalid mapping: {}", e)))?;
+        let spec = serde_json::to_value(spec).map_err(|e| AppError::Internal(e.to_string()))?;
+        let mut conn = self.db.get_connection()?;
+
+        conn.transaction(|conn| {
+            // Lock the data source so concurrent saves don't pick the same version
+            data_sources::table
+                .find(data_source_id)
+                .select(data_sources::id)
+                .for_update()
+                .first::<Uuid>(conn)?;
+            let latest: Option<i32> = data_source_mappings::table
+                .filter(data_source_mappings::data_source_id.eq(data_source_id))
+                .select(diesel::dsl::max(data_source_mappings::version))
+                .first(conn)?;
+            diesel::insert_into(data_source_mappings::table)
+                .values(&NewDataSourceMapping {
+                    data_source_id,
+                    version: latest.unwrap_or(0) + 1,
+                    spec,
+                    created_by,
+                })
+                .get_result::<DataSourceMapping>(conn)
+        })
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => AppError::NotFound("Data source not found".to_string()),
+            e => AppError::Database(e),
+        })
+    }
+
+    /// Get every saved mapping of a data source, newest first
+    pub async fn get_mappings(&self, data_source_id: Uuid) -> AppResult<Vec<DataSourceMapping>> {
+        let mut conn = self.db.get_connection()?;
+
+        let mappings = data_source_mappings::table
+            .filter(data_source_mappings::data_source_id.eq(data_source_id))
+            .order(data_source_mappings::version.desc())
+            .load::<DataSourceMapping>(&mut conn)
+            .map_err(AppError::Database)?;
+
+        Ok(mappings)
+    }
+
+    /// Get one mapping version of a data source, or the latest one
+    pub async fn get_mapping(
+        &self,
+        data_source_id: Uuid,
+        version: Option<i32>,
+    ) -> AppResult<Option<DataSourceMapping>> {
+        let mut conn = self.db.get_connection()?;
+
+        let mut query = data_source_mappings::table
+            .filter(data_source_mappings::data_source_id.eq(data_source_id))
+            .into_boxed();
+        if let Some(version) = version {
+            query = query.filter(data_source_mappings::version.eq(version));
+        }
+        let mapping = query
+            .order(data_source_mappings::version.desc())
+            .first::<DataSourceMapping>(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?;
+
+        Ok(mapping)
+    }
+
     /// Update a data source
     pub async fn update_data_source(
         &self,
@@ -329,18 +419,43 @@ impl DataSourceService {
         }
 
         // Check source type
-        if !["csv", "json", "xlsx", "txt"].contains(&data_source.source_type.as_str()) {
+        if ![
+            "csv", "json", "xlsx", "ods", "txt", "camt", "mt940", "bai2", "ofx",
+        ]
+        .contains(&data_source.source_type.as_str())
+        {
             validation.is_valid = false;
             validation
                 .errors
                 .push("Unsupported source type".to_string());
         }
 
-        // Check schema
-        if let Some(schema) = &data_source.schema {
-            if schema.is_null() {
-                validation.warnings.push("Schema is null".to_string());
-            }
+        // Check schema; ingestion infers it from the first file
+        match &data_source.schema {
+            None | Some(serde_json::Value::Null) => validation
+                .warnings
+                .push("No schema yet; it is inferred when the first file is ingested".to_string()),
+            Some(schema) => match serde_json::from_value::<SourceSchema>(schema.clone()) {
+                Ok(schema) if schema.columns.is_empty() => {
+                    validation.warnings.push("Schema has no columns".to_string());
+                }
+                Ok(schema) => {
+                    let untyped: Vec<&str> = schema
+                        .columns
+                        .iter()
+                        .filter(|column| column.column_type.is_none())
+                        .map(|column| column.name.as_str())
+                        .collect();
+                    if !untyped.is_empty() {
+                        validation
+                            .warnings
+                            .push(format!("Columns with no values to infer a type from: {}", untyped.join(", ")));
+                    }
+                }
+                Err(_) => validation.warnings.push(
+                    "Schema was not inferred from a file and will be replaced by the next ingestion".to_string(),
+                ),
+            },
         }
 
         Ok(validation)
@@ -0,0 +1,348 @@
+//! Email service for sending authentication and notification emails
+//!
+//! - `templates`: HTML templates from `email-templates/`, with variables and locales
+//! - `transport`: SMTP, `.eml` file and log transports
+//! - `outbox`: persisted outbox delivered with retries and bounce tracking
+//!
+//! With an outbox (`with_outbox`), email is written to the database and sent
+//! by the outbox worker; without one it is sent directly, through the
+//! resilience manager when there is one.
+
+pub mod outbox;
+pub mod templates;
+pub mod transport;
+
+pub use outbox::{EmailOutbox, OutboxStatus, OutboxWorker};
+pub use templates::{RenderedEmail, TemplateStore};
+pub use transport::{MailTransport, OutgoingEmail, SendError};
+
+use crate::database::Database;
+use crate::errors::AppResult;
+use crate::models::NewEmailOutboxEntry;
+use crate::services::resilience::ResilienceManager;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+/// Email template types
+#[derive(Debug, Clone)]
+pub enum EmailTemplate {
+    PasswordReset { token: String, user_name: String },
+    EmailVerification { token: String, user_name: String },
+    Welcome { user_name: String },
+    Notification { message: String },
+}
+
+/// Outcome of a finished reconciliation job, for the completion email
+#[derive(Debug, Clone)]
+pub struct JobCompletedEmail {
+    pub job_id: uuid::Uuid,
+    pub job_name: String,
+    pub project_id: uuid::Uuid,
+    pub status: String,
+    pub matched_records: i64,
+    pub unmatched_records: i64,
+}
+
+/// Email service
+pub struct EmailService {
+    smtp_host: String,
+    smtp_port: u16,
+    smtp_user: String,
+    from_email: String,
+    /// Base URL of the web app, for links in emails
+    app_url: String,
+    transport: MailTransport,
+    templates: TemplateStore,
+    outbox: Option<EmailOutbox>,
+    resilience: Option<Arc<ResilienceManager>>,
+}
+
+impl std::fmt::Debug for EmailService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailService")
+            .field("smtp_host", &self.smtp_host)
+            .field("smtp_port", &self.smtp_port)
+            .field("smtp_user", &self.smtp_user)
+            .field("from_email", &self.from_email)
+            .field("transport", &self.transport)
+            .field("outbox", &self.outbox.is_some())
+            .finish()
+    }
+}
+
+impl Default for EmailService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmailService {
+    /// Create a new email service
+    pub fn new() -> Self {
+        let smtp_host = env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let smtp_port = env::var("SMTP_PORT")
+            .unwrap_or_else(|_| "587".to_string())
+            .parse()
+            .unwrap_or(587);
+        let smtp_user = env::var("SMTP_USER").unwrap_or_else(|_| "".to_string());
+        let smtp_password =
+            crate::services::secrets::SecretsService::get_smtp_password().unwrap_or_else(|_| "".to_string());
+        let transport = MailTransport::from_env(&smtp_host, smtp_port, &smtp_user, &smtp_password)
+            .unwrap_or_else(|e| {
+                log::error!("Email transport is misconfigured, email will only be logged: {}", e);
+                MailTransport::Log
+            });
+        Self {
+            smtp_host,
+            smtp_port,
+            smtp_user,
+            from_email: env::var("SMTP_FROM").unwrap_or_else(|_| "noreply@reconciliation.com".to_string()),
+            app_url: env::var("APP_URL")
+                .unwrap_or_else(|_| "http://localhost:3000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            transport,
+            templates: TemplateStore::from_env(),
+            outbox: None,
+            resilience: None,
+        }
+    }
+
+    /// Create email service with resilience manager
+    pub fn new_with_resilience(resilience: Arc<ResilienceManager>) -> Self {
+        Self {
+            resilience: Some(resilience),
+            ..Self::new()
+        }
+    }
+
+    /// Queue email in the database outbox instead of sending it inline
+    pub fn with_outbox(mut self, db: Arc<Database>) -> Self {
+        self.outbox = Some(EmailOutbox::new(db));
+        self
+    }
+
+    /// Use `transport` instead of the one configured by the environment
+    pub fn with_transport(mut self, transport: MailTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Load templates from `templates` instead of `EMAIL_TEMPLATES_DIR`
+    pub fn with_templates(mut self, templates: TemplateStore) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Worker delivering this service's outbox, if it has one
+    pub fn outbox_worker(&self) -> Option<OutboxWorker> {
+        self.outbox
+            .clone()
+            .map(|outbox| OutboxWorker::new(outbox, self.transport.clone(), self.from_email.clone()))
+    }
+
+    /// Send password reset email
+    pub async fn send_password_reset(
+        &self,
+        to: &str,
+        token: &str,
+        user_name: &str,
+        locale: Option<&str>,
+    ) -> AppResult<()> {
+        let variables = HashMap::from([
+            ("user_name", user_name.to_string()),
+            ("user_email", to.to_string()),
+            ("reset_link", format!("{}/reset-password?token={}", self.app_url, token)),
+        ]);
+        self.send_template(to, "password-reset", locale, &variables).await
+    }
+
+    /// Send email verification
+    pub async fn send_email_verification(
+        &self,
+        to: &str,
+        token: &str,
+        user_name: &str,
+        locale: Option<&str>,
+    ) -> AppResult<()> {
+        let variables = HashMap::from([
+            ("user_name", user_name.to_string()),
+            ("user_email", to.to_string()),
+            ("verification_link", format!("{}/verify-email?token={}", self.app_url, token)),
+        ]);
+        self.send_template(to, "email-verification", locale, &variables).await
+    }
+
+    /// Send welcome email, with a link to verify the address
+    pub async fn send_welcome_email(
+        &self,
+        to: &str,
+        user_name: &str,
+        verification_token: &str,
+        locale: Option<&str>,
+    ) -> AppResult<()> {
+        let variables = HashMap::from([
+            ("user_name", user_name.to_string()),
+            ("user_email", to.to_string()),
+            (
+                "verification_link",
+                format!("{}/verify-email?token={}", self.app_url, verification_token),
+            ),
+        ]);
+        self.send_template(to, "welcome-email", locale, &variables).await
+    }
+
+    /// Tell a user that their reconciliation job finished
+    pub async fn send_job_completed(
+        &self,
+        to: &str,
+        user_name: &str,
+        job: &JobCompletedEmail,
+        locale: Option<&str>,
+    ) -> AppResult<()> {
+        let variables = HashMap::from([
+            ("user_name", user_name.to_string()),
+            ("user_email", to.to_string()),
+            ("job_name", job.job_name.clone()),
+            ("job_status", job.status.clone()),
+            ("matched_records", job.matched_records.to_string()),
+            ("unmatched_records", job.unmatched_records.to_string()),
+            (
+                "job_link",
+                format!("{}/projects/{}/reconciliation/{}", self.app_url, job.project_id, job.job_id),
+            ),
+        ]);
+        self.send_template(to, "job-completed", locale, &variables).await
+    }
+
+    /// Render template `name` in the recipient's locale and send it
+    pub async fn send_template(
+        &self,
+        to: &str,
+        name: &str,
+        locale: Option<&str>,
+        variables: &HashMap<&str, String>,
+    ) -> AppResult<()> {
+        let rendered = self.templates.render(name, locale, variables)?;
+        let email = OutgoingEmail {
+            to: to.to_string(),
+            subject: rendered.subject,
+            text: rendered.text,
+            html: Some(rendered.html),
+            message_id: None,
+        };
+        self.deliver(email, Some(name), rendered.locale, None).await
+    }
+
+    /// Send generic email with resilience (circuit breaker and retry)
+    pub async fn send_email(&self, to: &str, subject: &str, body: &str) -> AppResult<()> {
+        self.send_email_with_correlation(to, subject, body, None)
+            .await
+    }
+
+    /// Send email with correlation ID for tracing
+    pub async fn send_email_with_correlation(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+        correlation_id: Option<String>,
+    ) -> AppResult<()> {
+        let email = OutgoingEmail {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            text: body.to_string(),
+            html: None,
+            message_id: None,
+        };
+        self.deliver(email, None, None, correlation_id).await
+    }
+
+    /// Queue `email` in the outbox, or send it now when there is none
+    async fn deliver(
+        &self,
+        email: OutgoingEmail,
+        template: Option<&str>,
+        locale: Option<String>,
+        correlation_id: Option<String>,
+    ) -> AppResult<()> {
+        let corr_id = correlation_id.as_deref().unwrap_or("unknown");
+        log::debug!(
+            "[{}] Sending email to {} with subject: {}",
+            corr_id,
+            email.to,
+            email.subject
+        );
+
+        if let Some(ref outbox) = self.outbox {
+            outbox
+                .enqueue(NewEmailOutboxEntry {
+                    recipient: email.to,
+                    subject: email.subject,
+                    html_body: email.html,
+                    text_body: email.text,
+                    template: template.map(str::to_string),
+                    locale,
+                    status: OutboxStatus::Pending.as_str().to_string(),
+                    max_attempts: outbox::DEFAULT_MAX_ATTEMPTS,
+                    correlation_id,
+                })
+                .await?;
+            return Ok(());
+        }
+
+        // If resilience manager is available, use it for SMTP operations
+        if let Some(ref resilience) = self.resilience {
+            return resilience
+                .execute_api_with_correlation(
+                    || async { self.send_email_internal(&email).await },
+                    correlation_id,
+                )
+                .await;
+        }
+
+        self.send_email_internal(&email).await
+    }
+
+    /// Internal email sending implementation
+    async fn send_email_internal(&self, email: &OutgoingEmail) -> AppResult<()> {
+        self.transport.send(&self.from_email, email).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_email_service_creation() {
+        let service = EmailService::new();
+        assert!(!service.smtp_host.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_rendered_template_through_file_transport() {
+        let outbox = tempfile::tempdir().unwrap_or_else(|e| panic!("tempdir: {}", e));
+        let service = EmailService::new()
+            .with_transport(MailTransport::File(outbox.path().to_path_buf()))
+            .with_templates(TemplateStore::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../email-templates")));
+
+        service
+            .send_password_reset("ann@example.com", "tok123", "Ann", Some("en-GB"))
+            .await
+            .unwrap_or_else(|e| panic!("send: {}", e));
+
+        let files: Vec<_> = std::fs::read_dir(outbox.path())
+            .unwrap_or_else(|e| panic!("read_dir: {}", e))
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(files.len(), 1);
+        let eml = std::fs::read_to_string(files[0].path()).unwrap_or_else(|e| panic!("read: {}", e));
+        assert!(eml.contains("To: ann@example.com"));
+        assert!(eml.contains("Subject: Password Reset"));
+        // The body is quoted-printable, which escapes the `=` in `token=`
+        assert!(eml.contains("tok123"));
+    }
+}
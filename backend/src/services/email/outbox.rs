@@ -0,0 +1,328 @@
+//! Persisted email outbox
+//!
+//! Emails are written to the `email_outbox` table and delivered by an
+//! `OutboxWorker`, so that an SMTP outage or a restart delays mail instead of
+//! losing it. Workers claim one due row at a time with
+//! `SELECT ... FOR UPDATE SKIP LOCKED` and hold it for `LOCK_DURATION`, which
+//! outlasts any single SMTP exchange; a row whose worker died is claimed
+//! again once the lock runs out, or failed if it has no attempts left.
+//! Transient failures are retried with exponential backoff until
+//! `max_attempts`, and other permanent failures fail the email at once.
+//! Only a rejection of the recipient's address during delivery marks the
+//! email bounced, and later email to a bounced address is suppressed.
+
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::database::transaction::with_transaction;
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::email_outbox;
+use crate::models::{EmailOutboxEntry, NewEmailOutboxEntry};
+
+use super::transport::{MailTransport, OutgoingEmail, SendError};
+
+/// Delivery attempts before an email is given up on
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 6;
+/// How long a claimed email stays locked to its worker; well over the
+/// transport's `SMTP_TIMEOUT` per command of one delivery
+pub const LOCK_DURATION: Duration = Duration::from_secs(300);
+/// How often an idle worker looks for due email
+pub const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Delay before the first retry; doubled for each further attempt
+pub const RETRY_BASE_DELAY_SECONDS: i64 = 60;
+/// Upper bound on the delay between retries
+pub const RETRY_MAX_DELAY_SECONDS: i64 = 6 * 3600;
+
+/// State of an outbox email
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    /// Waiting for `next_attempt_at`
+    Pending,
+    /// Claimed by a worker until `locked_until`
+    Sending,
+    Sent,
+    /// Out of attempts
+    Failed,
+    /// Rejected by the recipient's server
+    Bounced,
+    /// Not sent because the address bounced before
+    Suppressed,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Sending => "sending",
+            OutboxStatus::Sent => "sent",
+            OutboxStatus::Failed => "failed",
+            OutboxStatus::Bounced => "bounced",
+            OutboxStatus::Suppressed => "suppressed",
+        }
+    }
+}
+
+/// Delay before retrying an email that failed on its `attempt`-th try
+pub fn retry_delay(attempt: i32) -> chrono::Duration {
+    let doublings = attempt.saturating_sub(1).clamp(0, 20) as u32;
+    let seconds = RETRY_BASE_DELAY_SECONDS
+        .saturating_mul(1_i64 << doublings)
+        .min(RETRY_MAX_DELAY_SECONDS);
+    chrono::Duration::seconds(seconds)
+}
+
+/// `Message-ID` of an outbox email: its id at the sender's domain
+pub fn message_id(entry_id: Uuid, from: &str) -> String {
+    let domain = from
+        .rsplit('@')
+        .next()
+        .map(|domain| domain.trim_end_matches('>').trim())
+        .filter(|domain| !domain.is_empty() && *domain != from)
+        .unwrap_or("localhost");
+    format!("{}@{}", entry_id, domain)
+}
+
+/// Handle on the outbox table
+#[derive(Clone)]
+pub struct EmailOutbox {
+    db: Arc<Database>,
+}
+
+impl EmailOutbox {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Store an email for delivery; suppressed when the address has bounced.
+    /// Addresses are stored lowercased so that bounces match regardless of case.
+    pub async fn enqueue(&self, mut email: NewEmailOutboxEntry) -> AppResult<EmailOutboxEntry> {
+        email.recipient = email.recipient.trim().to_lowercase();
+        let mut conn = self.db.get_connection()?;
+        let bounced = email_outbox::table
+            .filter(email_outbox::recipient.eq(&email.recipient))
+            .filter(email_outbox::status.eq(OutboxStatus::Bounced.as_str()))
+            .count()
+            .get_result::<i64>(&mut conn)
+            .map_err(AppError::Database)?;
+        if bounced > 0 {
+            log::warn!("Not emailing {}: earlier email to it bounced", email.recipient);
+            email.status = OutboxStatus::Suppressed.as_str().to_string();
+        }
+        diesel::insert_into(email_outbox::table)
+            .values(&email)
+            .get_result::<EmailOutboxEntry>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Claim the next due email, counting an attempt against it
+    ///
+    /// An email whose worker died mid-delivery after its last attempt is
+    /// failed here rather than sent again.
+    pub async fn claim(&self) -> AppResult<Option<EmailOutboxEntry>> {
+        with_transaction(self.db.get_pool(), move |tx| {
+            let now = Utc::now();
+            diesel::update(
+                email_outbox::table
+                    .filter(email_outbox::status.eq(OutboxStatus::Sending.as_str()))
+                    .filter(email_outbox::locked_until.lt(now))
+                    .filter(email_outbox::attempts.ge(email_outbox::max_attempts)),
+            )
+            .set((
+                email_outbox::status.eq(OutboxStatus::Failed.as_str()),
+                email_outbox::last_error.eq(Some("Worker lock expired on the last attempt".to_string())),
+                email_outbox::locked_until.eq(None::<chrono::DateTime<Utc>>),
+                email_outbox::updated_at.eq(now),
+            ))
+            .execute(tx)
+            .map_err(AppError::Database)?;
+
+            let id = email_outbox::table
+                .filter(
+                    email_outbox::status
+                        .eq(OutboxStatus::Pending.as_str())
+                        .and(email_outbox::next_attempt_at.le(now)),
+                )
+                .or_filter(
+                    email_outbox::status
+                        .eq(OutboxStatus::Sending.as_str())
+                        .and(email_outbox::locked_until.lt(now))
+                        .and(email_outbox::attempts.lt(email_outbox::max_attempts)),
+                )
+                .order(email_outbox::next_attempt_at.asc())
+                .select(email_outbox::id)
+                .for_update()
+                .skip_locked()
+                .first::<Uuid>(tx)
+                .optional()
+                .map_err(AppError::Database)?;
+            let Some(id) = id else {
+                return Ok(None);
+            };
+            let locked_until = now + chrono::Duration::seconds(LOCK_DURATION.as_secs() as i64);
+            diesel::update(email_outbox::table.filter(email_outbox::id.eq(id)))
+                .set((
+                    email_outbox::status.eq(OutboxStatus::Sending.as_str()),
+                    email_outbox::attempts.eq(email_outbox::attempts + 1),
+                    email_outbox::locked_until.eq(Some(locked_until)),
+                    email_outbox::updated_at.eq(now),
+                ))
+                .get_result::<EmailOutboxEntry>(tx)
+                .map(Some)
+                .map_err(AppError::Database)
+        })
+        .await
+    }
+
+    /// Record a delivered email
+    pub fn mark_sent(&self, entry_id: Uuid, message_id: &str) -> AppResult<()> {
+        let now = Utc::now();
+        let mut conn = self.db.get_connection()?;
+        diesel::update(email_outbox::table.filter(email_outbox::id.eq(entry_id)))
+            .set((
+                email_outbox::status.eq(OutboxStatus::Sent.as_str()),
+                email_outbox::message_id.eq(Some(message_id.to_string())),
+                email_outbox::sent_at.eq(Some(now)),
+                email_outbox::locked_until.eq(None::<chrono::DateTime<Utc>>),
+                email_outbox::last_error.eq(None::<String>),
+                email_outbox::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// Record a failed delivery attempt; returns the email's new status
+    ///
+    /// A transient failure is retried after `retry_delay`, or fails the email
+    /// once it has used `max_attempts`; a permanent one fails it at once, and
+    /// a rejected recipient is a bounce.
+    pub fn mark_failed(&self, entry: &EmailOutboxEntry, error: &SendError) -> AppResult<OutboxStatus> {
+        let now = Utc::now();
+        let mut conn = self.db.get_connection()?;
+        let target = email_outbox::table.filter(email_outbox::id.eq(entry.id));
+        let status = match error {
+            SendError::Rejected(reason) => {
+                diesel::update(target)
+                    .set((
+                        email_outbox::status.eq(OutboxStatus::Bounced.as_str()),
+                        email_outbox::bounced_at.eq(Some(now)),
+                        email_outbox::bounce_reason.eq(Some(reason.clone())),
+                        email_outbox::last_error.eq(Some(error.to_string())),
+                        email_outbox::locked_until.eq(None::<chrono::DateTime<Utc>>),
+                        email_outbox::updated_at.eq(now),
+                    ))
+                    .execute(&mut conn)
+                    .map_err(AppError::Database)?;
+                return Ok(OutboxStatus::Bounced);
+            }
+            SendError::Permanent(_) => OutboxStatus::Failed,
+            SendError::Transient(_) if entry.attempts >= entry.max_attempts => OutboxStatus::Failed,
+            SendError::Transient(_) => OutboxStatus::Pending,
+        };
+        diesel::update(target)
+            .set((
+                email_outbox::status.eq(status.as_str()),
+                email_outbox::next_attempt_at.eq(now + retry_delay(entry.attempts)),
+                email_outbox::last_error.eq(Some(error.to_string())),
+                email_outbox::locked_until.eq(None::<chrono::DateTime<Utc>>),
+                email_outbox::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(status)
+    }
+}
+
+/// Delivers due outbox email in the background
+pub struct OutboxWorker {
+    outbox: EmailOutbox,
+    transport: MailTransport,
+    from: String,
+}
+
+impl OutboxWorker {
+    pub fn new(outbox: EmailOutbox, transport: MailTransport, from: String) -> Self {
+        Self {
+            outbox,
+            transport,
+            from,
+        }
+    }
+
+    /// Poll the outbox for as long as the process runs
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(self) {
+        log::info!("Email outbox worker started ({:?})", self.transport);
+        loop {
+            match self.outbox.claim().await {
+                Ok(Some(entry)) => {
+                    self.deliver(entry).await;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to claim outbox email: {}", e),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn deliver(&self, entry: EmailOutboxEntry) {
+        let message_id = message_id(entry.id, &self.from);
+        let email = OutgoingEmail {
+            to: entry.recipient.clone(),
+            subject: entry.subject.clone(),
+            text: entry.text_body.clone(),
+            html: entry.html_body.clone(),
+            message_id: Some(message_id.clone()),
+        };
+        let released = match self.transport.send(&self.from, &email).await {
+            Ok(()) => {
+                log::info!("Sent email {} to {}", entry.id, entry.recipient);
+                self.outbox.mark_sent(entry.id, &message_id)
+            }
+            Err(error) => self.outbox.mark_failed(&entry, &error).map(|status| {
+                log::warn!(
+                    "Email {} to {} failed on attempt {} ({}): {}",
+                    entry.id,
+                    entry.recipient,
+                    entry.attempts,
+                    status.as_str(),
+                    error
+                );
+            }),
+        };
+        if let Err(e) = released {
+            log::error!("Failed to update outbox email {}: {}", entry.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off_exponentially_up_to_cap() {
+        assert_eq!(retry_delay(1).num_seconds(), RETRY_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay(2).num_seconds(), RETRY_BASE_DELAY_SECONDS * 2);
+        assert_eq!(retry_delay(0).num_seconds(), RETRY_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay(30).num_seconds(), RETRY_MAX_DELAY_SECONDS);
+    }
+
+    #[test]
+    fn message_id_uses_sender_domain() {
+        let id = Uuid::nil();
+        assert_eq!(
+            message_id(id, "Reconciliation <noreply@example.com>"),
+            format!("{}@example.com", id)
+        );
+        assert_eq!(message_id(id, "noreply"), format!("{}@localhost", id));
+    }
+}
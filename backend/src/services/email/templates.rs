@@ -0,0 +1,254 @@
+//! HTML email templates
+//!
+//! Templates are HTML files in the repository's `email-templates/` directory
+//! (`EMAIL_TEMPLATES_DIR` overrides the location), named `<name>.html` for the
+//! default locale and `<name>.<locale>.html` for translations, for example
+//! `password-reset.fr.html`. A locale such as `fr-CA` falls back to `fr` and
+//! then to the default. `{{variable}}` placeholders are replaced with
+//! HTML-escaped values; a placeholder without a value is an error rather
+//! than a blank in a sent email. The rendered `<title>` is the subject, and a
+//! plain-text part is derived from the body.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::errors::{AppError, AppResult};
+
+/// An email rendered from a template
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    /// Locale of the template file used; `None` for the default
+    pub locale: Option<String>,
+}
+
+/// Templates loaded from a directory
+#[derive(Debug, Clone)]
+pub struct TemplateStore {
+    dir: PathBuf,
+}
+
+impl TemplateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("EMAIL_TEMPLATES_DIR").unwrap_or_else(|_| "../email-templates".to_string()))
+    }
+
+    /// Render template `name` in `locale`, or the closest locale there is
+    pub fn render(
+        &self,
+        name: &str,
+        locale: Option<&str>,
+        variables: &HashMap<&str, String>,
+    ) -> AppResult<RenderedEmail> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(AppError::Validation(format!("Invalid email template name: {}", name)));
+        }
+        let (source, locale) = self.load(name, locale)?;
+        let html = render_template(&source, variables)
+            .map_err(|e| AppError::Internal(format!("Email template {}: {}", name, e)))?;
+        let subject = extract_title(&html)
+            .ok_or_else(|| AppError::Internal(format!("Email template {} has no <title>", name)))?;
+        let text = html_to_text(&html);
+        Ok(RenderedEmail {
+            subject,
+            html,
+            text,
+            locale,
+        })
+    }
+
+    fn load(&self, name: &str, locale: Option<&str>) -> AppResult<(String, Option<String>)> {
+        for candidate in locale_candidates(locale) {
+            let path = self.dir.join(format!("{}.{}.html", name, candidate));
+            if let Ok(source) = std::fs::read_to_string(&path) {
+                return Ok((source, Some(candidate)));
+            }
+        }
+        let path = self.dir.join(format!("{}.html", name));
+        let source = std::fs::read_to_string(&path).map_err(|e| {
+            AppError::Internal(format!("Email template {} not found at {}: {}", name, path.display(), e))
+        })?;
+        Ok((source, None))
+    }
+}
+
+/// Locales to try for `locale`, most specific first: `fr-ca`, then `fr`
+fn locale_candidates(locale: Option<&str>) -> Vec<String> {
+    let Some(locale) = locale else { return Vec::new() };
+    let locale = locale.trim().to_lowercase().replace('_', "-");
+    if locale.is_empty() || !locale.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Vec::new();
+    }
+    let mut candidates = vec![locale.clone()];
+    if let Some((language, _)) = locale.split_once('-') {
+        candidates.push(language.to_string());
+    }
+    candidates
+}
+
+/// Replace every `{{ name }}` in `source` with its escaped value
+pub fn render_template(source: &str, variables: &HashMap<&str, String>) -> Result<String, String> {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| "unclosed {{".to_string())?;
+        let name = after[..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| format!("no value for {{{{{}}}}}", name))?;
+        output.push_str(&escape_html(value));
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&copy;", "©")
+        .replace("&amp;", "&")
+}
+
+fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title>")?;
+    let title = unescape_html(html[start..end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+/// Plain-text version of an HTML body: the text of `<body>`, one block per line
+fn html_to_text(html: &str) -> String {
+    let lower = html.to_lowercase();
+    let body = match (lower.find("<body"), lower.rfind("</body>")) {
+        (Some(open), Some(close)) => match lower[open..].find('>') {
+            Some(offset) if open + offset < close => &html[open + offset + 1..close],
+            _ => html,
+        },
+        _ => html,
+    };
+
+    let mut text = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        let tag_name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        rest = &rest[start + end + 1..];
+        // Skip the contents of style and script blocks
+        if !tag.starts_with('/') && (tag_name == "style" || tag_name == "script") {
+            let close = format!("</{}>", tag_name);
+            rest = match rest.to_lowercase().find(&close) {
+                Some(offset) => &rest[offset + close.len()..],
+                None => "",
+            };
+            continue;
+        }
+        if matches!(
+            tag_name.as_str(),
+            "br" | "p" | "div" | "tr" | "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table"
+        ) {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    let text = unescape_html(&text);
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(name, value)| (*name, value.to_string())).collect()
+    }
+
+    #[test]
+    fn renders_escaped_variables_subject_and_text() {
+        let source = "<html><head><title>Reset for {{ user_name }}</title><style>p { color: red; }</style></head>\
+                      <body><p>Hello {{user_name}},</p><p><a href=\"{{link}}\">Reset</a></p></body></html>";
+        let html = render_template(source, &variables(&[("user_name", "Ann & <Bob>"), ("link", "https://x/?a=1&b=2")]))
+            .unwrap_or_else(|e| panic!("render: {}", e));
+        assert!(html.contains("Hello Ann &amp; &lt;Bob&gt;,"));
+        assert!(html.contains("href=\"https://x/?a=1&amp;b=2\""));
+        assert_eq!(extract_title(&html).as_deref(), Some("Reset for Ann & <Bob>"));
+        assert_eq!(html_to_text(&html), "Hello Ann & <Bob>,\n\nReset");
+
+        assert!(render_template("Hi {{name}}", &HashMap::new()).is_err());
+        assert!(render_template("Hi {{name", &variables(&[("name", "x")])).is_err());
+    }
+
+    #[test]
+    fn falls_back_from_region_to_language_to_default() {
+        let dir = tempfile::tempdir().unwrap_or_else(|e| panic!("tempdir: {}", e));
+        let write = |name: &str, title: &str| {
+            std::fs::write(
+                dir.path().join(name),
+                format!("<html><head><title>{}</title></head><body>{{{{n}}}}</body></html>", title),
+            )
+            .unwrap_or_else(|e| panic!("write: {}", e));
+        };
+        write("notice.html", "Notice");
+        write("notice.fr.html", "Avis");
+        let store = TemplateStore::new(dir.path());
+        let vars = variables(&[("n", "1")]);
+        let render = |locale: Option<&str>| {
+            store
+                .render("notice", locale, &vars)
+                .unwrap_or_else(|e| panic!("render: {}", e))
+        };
+
+        let french = render(Some("fr_CA"));
+        assert_eq!(french.subject, "Avis");
+        assert_eq!(french.locale.as_deref(), Some("fr"));
+        assert_eq!(render(Some("de")).subject, "Notice");
+        assert_eq!(render(None).locale, None);
+        assert!(store.render("../notice", None, &vars).is_err());
+        assert!(store.render("missing", None, &vars).is_err());
+    }
+}
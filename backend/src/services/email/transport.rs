@@ -0,0 +1,266 @@
+//! Email transports
+//!
+//! `EMAIL_TRANSPORT` selects how mail leaves the process:
+//! - `smtp` (default): lettre SMTP to `SMTP_HOST:SMTP_PORT`, with `SMTP_TLS`
+//!   `starttls` (default), `tls` or `none`, authenticating as `SMTP_USER` when set
+//! - `file`: each message is written as an `.eml` file to `EMAIL_FILE_DIR`
+//!   (default `tmp/emails`), for development and for tests that assert on
+//!   sent mail without a server
+//! - `log`: messages are only logged
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{FileTransport, Message, SmtpTransport, Transport};
+
+use crate::errors::{AppError, AppResult};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Why a delivery failed, which decides whether it is retried and whether
+/// the recipient counts as bounced
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendError {
+    /// The server or network may accept it later (4xx, connection errors)
+    Transient(String),
+    /// Retrying won't help, but the recipient isn't at fault: SMTP
+    /// authentication or policy errors, a bad from address, an unbuildable message
+    Permanent(String),
+    /// The recipient's address is invalid or its mailbox doesn't exist
+    /// (`5.1.x`); a bounce
+    Rejected(String),
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendError::Transient(message) => write!(f, "transient: {}", message),
+            SendError::Permanent(message) => write!(f, "permanent: {}", message),
+            SendError::Rejected(message) => write!(f, "rejected: {}", message),
+        }
+    }
+}
+
+impl From<SendError> for AppError {
+    fn from(error: SendError) -> Self {
+        AppError::Internal(format!("Email delivery failed ({})", error))
+    }
+}
+
+/// An email ready to send
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    /// `Message-ID` header, without angle brackets
+    pub message_id: Option<String>,
+}
+
+/// Where mail is delivered
+#[derive(Clone)]
+pub enum MailTransport {
+    Smtp(SmtpTransport),
+    File(PathBuf),
+    Log,
+}
+
+impl std::fmt::Debug for MailTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailTransport::Smtp(_) => write!(f, "MailTransport::Smtp"),
+            MailTransport::File(dir) => write!(f, "MailTransport::File({})", dir.display()),
+            MailTransport::Log => write!(f, "MailTransport::Log"),
+        }
+    }
+}
+
+impl MailTransport {
+    pub fn from_env(host: &str, port: u16, user: &str, password: &str) -> AppResult<Self> {
+        match std::env::var("EMAIL_TRANSPORT")
+            .unwrap_or_else(|_| "smtp".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "smtp" => {
+                let tls = std::env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+                Self::smtp(host, port, &tls, user, password)
+            }
+            "file" => Ok(MailTransport::File(PathBuf::from(
+                std::env::var("EMAIL_FILE_DIR").unwrap_or_else(|_| "tmp/emails".to_string()),
+            ))),
+            "log" => Ok(MailTransport::Log),
+            other => Err(AppError::Validation(format!(
+                "Unknown EMAIL_TRANSPORT {}; expected smtp, file or log",
+                other
+            ))),
+        }
+    }
+
+    pub fn smtp(host: &str, port: u16, tls: &str, user: &str, password: &str) -> AppResult<Self> {
+        let builder = match tls.to_lowercase().as_str() {
+            "starttls" => SmtpTransport::starttls_relay(host),
+            "tls" => SmtpTransport::relay(host),
+            "none" => Ok(SmtpTransport::builder_dangerous(host)),
+            other => {
+                return Err(AppError::Validation(format!(
+                    "Unknown SMTP_TLS {}; expected starttls, tls or none",
+                    other
+                )))
+            }
+        }
+        .map_err(|e| AppError::Internal(format!("Failed to create SMTP transport: {}", e)))?
+        .port(port)
+        .timeout(Some(SMTP_TIMEOUT));
+        let builder = if user.is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(user.to_string(), password.to_string()))
+        };
+        Ok(MailTransport::Smtp(builder.build()))
+    }
+
+    /// Deliver `email` from `from`
+    pub async fn send(&self, from: &str, email: &OutgoingEmail) -> Result<(), SendError> {
+        let message = build_message(from, email)?;
+        match self {
+            MailTransport::Log => {
+                log::info!("Email to {} with subject: {} (log transport)", email.to, email.subject);
+                Ok(())
+            }
+            MailTransport::File(dir) => {
+                let dir = dir.clone();
+                run_blocking(move || {
+                    std::fs::create_dir_all(&dir)
+                        .map_err(|e| SendError::Transient(format!("Failed to create {}: {}", dir.display(), e)))?;
+                    FileTransport::new(&dir)
+                        .send(&message)
+                        .map(|_| ())
+                        .map_err(|e| SendError::Transient(e.to_string()))
+                })
+                .await
+            }
+            MailTransport::Smtp(transport) => {
+                let transport = transport.clone();
+                run_blocking(move || {
+                    transport.send(&message).map(|_| ()).map_err(|e| {
+                        let message = e.to_string();
+                        if !e.is_permanent() {
+                            SendError::Transient(message)
+                        } else if is_recipient_rejection(&message) {
+                            SendError::Rejected(message)
+                        } else {
+                            SendError::Permanent(message)
+                        }
+                    })
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// lettre's transports block, so they run off the async workers
+async fn run_blocking<F>(send: F) -> Result<(), SendError>
+where
+    F: FnOnce() -> Result<(), SendError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(send)
+        .await
+        .map_err(|e| SendError::Transient(format!("Email task failed: {}", e)))?
+}
+
+/// Whether an SMTP reply carries an enhanced status code of the `5.1.x`
+/// (addressing) class, e.g. `550 5.1.1 User unknown`, as opposed to
+/// `535 5.7.8` for bad credentials or `554 5.7.1` for policy
+fn is_recipient_rejection(reply: &str) -> bool {
+    reply.split(|c: char| c.is_whitespace() || c == ':').any(|token| {
+        token
+            .strip_prefix("5.1.")
+            .is_some_and(|detail| !detail.is_empty() && detail.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Multipart text/HTML message, or plain text when there is no HTML
+pub fn build_message(from: &str, email: &OutgoingEmail) -> Result<Message, SendError> {
+    let from: Mailbox = from
+        .parse()
+        .map_err(|e| SendError::Permanent(format!("Invalid from address {}: {}", from, e)))?;
+    let to: Mailbox = email
+        .to
+        .parse()
+        .map_err(|e| SendError::Rejected(format!("Invalid recipient {}: {}", email.to, e)))?;
+    let builder = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(email.subject.clone())
+        .message_id(email.message_id.as_ref().map(|id| format!("<{}>", id)));
+    let message = match &email.html {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(email.text.clone(), html.clone())),
+        None => builder.singlepart(
+            SinglePart::builder()
+                .header(ContentType::TEXT_PLAIN)
+                .body(email.text.clone()),
+        ),
+    };
+    message.map_err(|e| SendError::Permanent(format!("Failed to build email: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_transport_writes_multipart_eml() {
+        let dir = tempfile::tempdir().unwrap_or_else(|e| panic!("tempdir: {}", e));
+        let transport = MailTransport::File(dir.path().to_path_buf());
+        let email = OutgoingEmail {
+            to: "ann@example.com".to_string(),
+            subject: "Job finished".to_string(),
+            text: "Your job finished.".to_string(),
+            html: Some("<p>Your job finished.</p>".to_string()),
+            message_id: Some("1234@example.com".to_string()),
+        };
+        transport
+            .send("Reconciliation <noreply@example.com>", &email)
+            .await
+            .unwrap_or_else(|e| panic!("send: {}", e));
+
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap_or_else(|e| panic!("read_dir: {}", e))
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(files.len(), 1);
+        let eml = std::fs::read_to_string(files[0].path()).unwrap_or_else(|e| panic!("read: {}", e));
+        assert!(eml.contains("To: ann@example.com"));
+        assert!(eml.contains("Subject: Job finished"));
+        assert!(eml.contains("Message-ID: <1234@example.com>"));
+        assert!(eml.contains("multipart/alternative"));
+        assert!(eml.contains("<p>Your job finished.</p>"));
+
+        let bad = OutgoingEmail {
+            to: "not an address".to_string(),
+            ..email
+        };
+        assert!(matches!(
+            transport.send("noreply@example.com", &bad).await,
+            Err(SendError::Rejected(_))
+        ));
+        assert!(matches!(
+            transport.send("not a sender", &email).await,
+            Err(SendError::Permanent(_))
+        ));
+    }
+
+    #[test]
+    fn only_addressing_errors_are_recipient_rejections() {
+        assert!(is_recipient_rejection("permanent error (550): 5.1.1 User unknown"));
+        assert!(is_recipient_rejection("permanent error (553): 5.1.3 Bad recipient address syntax"));
+        assert!(!is_recipient_rejection("permanent error (535): 5.7.8 Authentication credentials invalid"));
+        assert!(!is_recipient_rejection("permanent error (554): 5.7.1 Relay access denied"));
+        assert!(!is_recipient_rejection("permanent error (550): No such user"));
+    }
+}
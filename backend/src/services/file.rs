@@ -3,7 +3,8 @@
 use crate::database::Database;
 use crate::errors::{AppError, AppResult};
 use crate::models::schema;
-use crate::models::{NewUploadedFile, UploadedFile};
+use crate::models::{NewIngestionJob, NewUploadedFile, UploadedFile};
+use crate::services::ingestion::IngestionService;
 use crate::services::resilience::ResilienceManager;
 use actix_multipart::Multipart;
 use actix_web::http::header::CONTENT_DISPOSITION;
@@ -32,6 +33,9 @@ pub struct ProcessingResult {
     pub record_count: Option<usize>,
     pub processing_time: f64,
     pub errors: Vec<String>,
+    /// Ingestion job holding the per-row errors
+    #[serde(default)]
+    pub ingestion_job_id: Option<Uuid>,
 }
 
 /// File service
@@ -215,11 +219,22 @@ impl FileService {
         // Per-file and total upload constraints
         let max_per_file_bytes: i64 = 10 * 1024 * 1024; // 10MB cap per file
         let max_total_bytes: i64 = 100 * 1024 * 1024; // 100MB cap per request
-        let allowed_types: [&str; 4] = [
+        let allowed_types: [&str; 15] = [
             "text/csv",
             "application/vnd.ms-excel",
             "application/octet-stream",
             "application/csv",
+            "text/tab-separated-values",
+            "application/json",
+            "application/x-ndjson",
+            "application/zip",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.oasis.opendocument.spreadsheet",
+            "application/xml",
+            "text/xml",
+            "text/plain",
+            "application/x-ofx",
+            "application/vnd.intu.qfx",
         ];
 
         // Prepare upload directory with enhanced error handling (Tier 2: Important)
@@ -496,7 +511,7 @@ impl FileService {
         // Then delete physical file (best-effort cleanup)
         // If this fails, we log but don't fail - DB record already deleted
         // A cleanup job can handle orphaned files later
-        let file_path = PathBuf::from(&self.upload_path).join(&file_info.file_path);
+        let file_path = self.stored_path(&file_info);
         if file_path.exists() {
             if let Err(e) = fs::remove_file(&file_path).await {
                 // Log warning but don't fail - DB record already deleted
@@ -525,15 +540,67 @@ impl FileService {
     }
 
     /// Process a file
-    pub async fn process_file(&self, _file_id: Uuid) -> AppResult<ProcessingResult> {
-        // Placeholder implementation
+    ///
+    /// Creates an ingestion job for the uploaded file and parses it into
+    /// reconciliation records, returning once the file has been read.
+    pub async fn process_file(&self, file_id: Uuid) -> AppResult<ProcessingResult> {
+        let started = std::time::Instant::now();
+        let (path, uploaded_file) = self.get_file_for_download(file_id).await?;
+
+        let ingestion = IngestionService::new(Arc::new(self.db.clone()));
+        let job = ingestion
+            .create_job(NewIngestionJob {
+                project_id: uploaded_file.project_id,
+                job_name: uploaded_file.original_filename.clone(),
+                source_type: "file".to_string(),
+                source_config: serde_json::json!({
+                    "file_id": uploaded_file.id,
+                    "filename": uploaded_file.original_filename,
+                }),
+                status: "uploaded".to_string(),
+                progress: 0,
+                metadata: serde_json::json!({}),
+                created_by: uploaded_file.uploaded_by,
+            })
+            .await?;
+
+        let outcome = ingestion.ingest_file(job.id, path).await;
+        self.set_file_status(file_id, if outcome.is_ok() { "processed" } else { "failed" })?;
+        let summary = outcome?;
+
         Ok(ProcessingResult {
-            record_count: Some(0),
-            processing_time: 0.0,
-            errors: vec![],
+            record_count: Some(summary.imported_records),
+            processing_time: started.elapsed().as_secs_f64(),
+            errors: summary.errors,
+            ingestion_job_id: Some(job.id),
         })
     }
 
+    fn set_file_status(&self, file_id: Uuid, new_status: &str) -> AppResult<()> {
+        use crate::models::schema::uploaded_files::dsl::*;
+
+        let mut conn = self.db.get_connection()?;
+        diesel::update(uploaded_files.find(file_id))
+            .set(status.eq(new_status))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// Where an uploaded file is on disk
+    ///
+    /// Records store paths as `uploads/<project>/<file>` while files are
+    /// written under `upload_path/<project>/<file>`; both are tried.
+    fn stored_path(&self, file: &UploadedFile) -> PathBuf {
+        let recorded = PathBuf::from(&self.upload_path).join(&file.file_path);
+        if recorded.exists() {
+            return recorded;
+        }
+        PathBuf::from(&self.upload_path)
+            .join(file.project_id.to_string())
+            .join(&file.filename)
+    }
+
     /// Get file for download
     /// 
     /// Returns the file path and metadata for downloading.
@@ -571,8 +638,8 @@ impl FileService {
             }
         };
 
-        let resolved_path = PathBuf::from(&self.upload_path).join(&uploaded_file.file_path);
-        
+        let resolved_path = self.stored_path(&uploaded_file);
+
         if !resolved_path.exists() {
             return Err(AppError::NotFound(format!(
                 "Physical file not found at path: {}",
@@ -1,18 +1,137 @@
 //! Ingestion service module
+//!
+//! Besides job bookkeeping this runs file ingestion: an uploaded file is
+//! parsed row by row, each row's columns are mapped onto a
+//! `reconciliation_records` row, and rows that fail go to `ingestion_errors`.
+//! Both are written in batches, with the job's progress and counts updated
+//! after every batch. A job whose `source_config` names a `data_source_id`
+//! also updates that data source, including the opening and closing balances
+//! of a bank statement file.
+//!
+//! Before a row's columns are mapped it goes through the job's transformation
+//! spec: one given inline as `source_config.transform`, or else the data
+//! source's saved mapping (the latest, or `source_config.mapping_version`).
+//! The same pipeline previews a spec against the first rows of a file.
+//!
+//! Every file is also profiled into a column schema. The first ingestion into
+//! a data source saves it as the source's schema; later ones are compared
+//! with it and, depending on the drift policy, warn about or fail on added,
+//! removed or retyped columns.
+//!
+//! Duplicates are caught at two levels. A file whose SHA-256 hash matches a
+//! file already ingested into the project is rejected unless
+//! `source_config.allow_duplicate_file` is set. With
+//! `source_config.duplicates` configured, each record is keyed by a natural
+//! key or a fingerprint, and records repeating an earlier row of the file or
+//! an already ingested record are quarantined for review instead of inserted.
+//!
+//! Once ingested, a job's records can be validated with the record rules of
+//! `validation::business_rules`.
 
 use chrono::Utc;
 use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::database::Database;
 use crate::errors::{AppError, AppResult};
-use crate::models::schema::{ingestion_errors, ingestion_jobs, ingestion_results};
+use crate::models::schema::{
+    data_source_statements, data_sources, ingestion_errors, ingestion_jobs, ingestion_results,
+    quarantined_records, reconciliation_records,
+};
 use crate::models::{
-    IngestionError, IngestionJob, IngestionResult, NewIngestionError, NewIngestionJob,
-    NewIngestionResult, UpdateIngestionJob,
+    IngestionError, IngestionJob, IngestionResult, NewDataSourceStatement, NewIngestionError,
+    NewIngestionJob, NewIngestionResult, NewQuarantinedRecord, NewReconciliationRecord, QuarantinedRecord,
+    ReconciliationRecord, UpdateIngestionJob,
+};
+use crate::services::data_source::DataSourceService;
+use crate::services::workflow::{WorkflowEngine, WorkflowEvent};
+use crate::services::validation::business_rules::{record_row, record_text, RecordCheck, RecordRuleSpec, Severity};
+use crate::services::parsing::{
+    self, ColumnMapping, DriftAction, DriftPolicy, DuplicateScope, DuplicateSpec, MappedRecord, ParseOptions,
+    ParseSummary, RowResult, SchemaChange, SchemaProfiler, SourceSchema, TransformSpec, Transformer,
 };
 
+/// Rows (records plus errors) written per batch while ingesting a file
+const INGEST_BATCH_SIZE: usize = 1_000;
+/// Row errors quoted in the summary returned to the caller
+const SUMMARY_ERROR_LIMIT: usize = 20;
+/// Longest `external_id` the records table accepts
+const MAX_EXTERNAL_ID_LENGTH: usize = 255;
+/// Longest statement id or account number the statements table accepts
+const MAX_STATEMENT_TEXT_LENGTH: usize = 255;
+/// Most rows a transformation preview reads
+pub const MAX_PREVIEW_ROWS: usize = 500;
+/// Records loaded per page while validating a job
+const VALIDATION_PAGE_SIZE: i64 = 1_000;
+/// Findings kept per validation rule; the count goes on past it
+const MAX_FINDINGS_PER_RULE: usize = 10_000;
+/// How long a processing job goes without writing a batch before it's taken
+/// to have been abandoned (its replica crashed) and may be processed again
+pub const STALE_PROCESSING_MINUTES: i64 = 30;
+
+/// Outcome of ingesting one file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileIngestionSummary {
+    pub job_id: Uuid,
+    /// Data rows read, including the ones that failed
+    pub total_rows: usize,
+    pub imported_records: usize,
+    pub error_count: usize,
+    /// Rows skipped by the transformation's filters
+    pub filtered_rows: usize,
+    /// Rows quarantined as duplicates instead of imported
+    pub duplicate_rows: usize,
+    /// Hex SHA-256 of the file
+    pub file_hash: String,
+    /// Saved mapping version applied, if the spec came from the data source
+    pub mapping_version: Option<i32>,
+    pub parse: ParseSummary,
+    pub schema: SourceSchema,
+    /// Differences from the data source's schema that didn't fail the job
+    pub schema_changes: Vec<SchemaChange>,
+    /// The first few row errors, for display
+    pub errors: Vec<String>,
+}
+
+/// One record that failed a validation rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordFinding {
+    pub record_id: Uuid,
+    /// File row the record was ingested from
+    pub row: Option<u64>,
+    pub external_id: Option<String>,
+    pub message: String,
+}
+
+/// The records that failed one validation rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleFindings {
+    pub rule: String,
+    pub severity: Severity,
+    /// Records that failed, including any past the kept findings
+    pub total: usize,
+    pub findings: Vec<RecordFinding>,
+}
+
+/// Outcome of validating a job's records
+#[derive(Debug, Clone, Serialize)]
+pub struct JobValidation {
+    pub job_id: Uuid,
+    /// No record failed a rule of error severity
+    pub valid: bool,
+    pub records_checked: usize,
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub rules: Vec<RuleFindings>,
+}
+
 /// Ingestion service
 pub struct IngestionService {
     db: Arc<Database>,
@@ -106,6 +225,97 @@ impl IngestionService {
         Ok((items, total))
     }
 
+    /// A job's quarantined duplicates, optionally only those with `status`
+    pub async fn get_quarantine(
+        &self,
+        job_id: Uuid,
+        status: Option<&str>,
+        page: i64,
+        per_page: i64,
+    ) -> AppResult<(Vec<QuarantinedRecord>, i64)> {
+        let mut conn = self.db.get_connection()?;
+        let offset = (page - 1) * per_page;
+        let filtered = || {
+            let mut query = quarantined_records::table
+                .filter(quarantined_records::ingestion_job_id.eq(job_id))
+                .into_boxed();
+            if let Some(status) = status {
+                query = query.filter(quarantined_records::status.eq(status.to_string()));
+            }
+            query
+        };
+
+        let total: i64 = filtered().count().get_result(&mut conn).map_err(AppError::Database)?;
+
+        let items = filtered()
+            .order(quarantined_records::record_index.asc())
+            .limit(per_page)
+            .offset(offset)
+            .load::<QuarantinedRecord>(&mut conn)
+            .map_err(AppError::Database)?;
+
+        Ok((items, total))
+    }
+
+    pub async fn get_quarantined_record(&self, id: Uuid) -> AppResult<QuarantinedRecord> {
+        let mut conn = self.db.get_connection()?;
+        quarantined_records::table
+            .find(id)
+            .first::<QuarantinedRecord>(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::NotFound(format!("Quarantined record {} not found", id)))
+    }
+
+    /// Review a quarantined duplicate: release it into the job's records, or
+    /// discard it. Either way it stays in quarantine with the decision.
+    pub async fn resolve_quarantined_record(
+        &self,
+        id: Uuid,
+        release: bool,
+        reviewed_by: Uuid,
+    ) -> AppResult<QuarantinedRecord> {
+        let mut conn = self.db.get_connection()?;
+        conn.transaction(|conn| {
+            let quarantined = quarantined_records::table
+                .find(id)
+                .for_update()
+                .first::<QuarantinedRecord>(conn)
+                .optional()?
+                .ok_or_else(|| AppError::NotFound(format!("Quarantined record {} not found", id)))?;
+            if quarantined.status != "pending" {
+                return Err(AppError::Conflict(format!(
+                    "Quarantined record {} was already {}",
+                    id, quarantined.status
+                )));
+            }
+
+            if release {
+                let mut record: NewReconciliationRecord = serde_json::from_value(quarantined.record_data.clone())
+                    .map_err(|e| AppError::Internal(format!("Quarantined record {} is unreadable: {}", id, e)))?;
+                if let Some(trail) = record.audit_trail.as_object_mut() {
+                    trail.insert("released_from_quarantine".to_string(), serde_json::json!(id));
+                    trail.insert("released_by".to_string(), serde_json::json!(reviewed_by));
+                }
+                diesel::insert_into(reconciliation_records::table)
+                    .values(&record)
+                    .execute(conn)?;
+                diesel::update(ingestion_jobs::table.find(quarantined.ingestion_job_id))
+                    .set(ingestion_jobs::processed_records.eq(ingestion_jobs::processed_records + 1))
+                    .execute(conn)?;
+            }
+
+            diesel::update(quarantined_records::table.find(id))
+                .set((
+                    quarantined_records::status.eq(if release { "released" } else { "discarded" }),
+                    quarantined_records::reviewed_by.eq(Some(reviewed_by)),
+                    quarantined_records::reviewed_at.eq(Some(Utc::now())),
+                ))
+                .get_result::<QuarantinedRecord>(conn)
+                .map_err(AppError::Database)
+        })
+    }
+
     pub async fn create_result(&self, new_result: NewIngestionResult) -> AppResult<IngestionResult> {
         let mut conn = self.db.get_connection()?;
         diesel::insert_into(ingestion_results::table)
@@ -131,6 +341,1120 @@ impl IngestionService {
         };
         self.update_job(job_id, update).await
     }
+
+    /// Parse the file at `path` into reconciliation records for the job
+    ///
+    /// Parse options and the column mapping come from the job's
+    /// `source_config`. Records and errors from an earlier run of the same job
+    /// are replaced. The job ends up "completed", or "failed" with the error
+    /// message if the file can't be read at all.
+    pub async fn ingest_file(&self, job_id: Uuid, path: PathBuf) -> AppResult<FileIngestionSummary> {
+        let job = self.get_job(job_id).await?;
+        let options = parse_options(&job)?;
+        let data_source_id = match job.source_config.get("data_source_id") {
+            Some(value) => Some(self.check_data_source(&job, value)?),
+            None => None,
+        };
+        let (spec, mapping_version) = self.resolve_spec(&job, data_source_id).await?;
+        let mapper = RowMapper::new(&spec)?;
+        let schema_check = self.schema_check(&job, data_source_id)?;
+        let duplicates = duplicate_spec(&job)?;
+        let project_id = job.project_id;
+        let db = Arc::clone(&self.db);
+        let outcome = tokio::task::spawn_blocking(move || {
+            FileIngestion::new(&db, &job, mapper, data_source_id, mapping_version, schema_check, duplicates)
+                .run(&path, &options)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?;
+
+        match &outcome {
+            Ok(summary) => WorkflowEngine::spawn_dispatch(
+                Arc::clone(&self.db),
+                WorkflowEvent::IngestionCompleted {
+                    ingestion_job_id: job_id,
+                    project_id,
+                    total_rows: summary.total_rows,
+                    imported_records: summary.imported_records,
+                    error_count: summary.error_count,
+                    duplicate_rows: summary.duplicate_rows,
+                },
+            ),
+            Err(e) => {
+                let update = UpdateIngestionJob {
+                    status: Some("failed".to_string()),
+                    completed_at: Some(Some(Utc::now())),
+                    error_message: Some(e.to_string()),
+                    ..Default::default()
+                };
+                if let Err(update_error) = self.update_job(job_id, update).await {
+                    log::error!("Failed to mark ingestion job {} as failed: {}", job_id, update_error);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// The transformation spec a job runs, and its saved version if it has one
+    ///
+    /// An inline `source_config.transform` wins over the data source's saved
+    /// mappings; `source_config.column_mapping` replaces the spec's columns.
+    pub async fn resolve_spec(
+        &self,
+        job: &IngestionJob,
+        data_source_id: Option<Uuid>,
+    ) -> AppResult<(TransformSpec, Option<i32>)> {
+        let (mut spec, version) = match (job.source_config.get("transform"), data_source_id) {
+            (Some(value), _) => (
+                serde_json::from_value(value.clone())
+                    .map_err(|e| AppError::Validation(format!("Invalid transformation spec: {}", e)))?,
+                None,
+            ),
+            (None, Some(data_source_id)) => {
+                let pinned = match job.source_config.get("mapping_version") {
+                    Some(value) => Some(
+                        value
+                            .as_i64()
+                            .and_then(|version| i32::try_from(version).ok())
+                            .ok_or_else(|| AppError::Validation("mapping_version must be a number".to_string()))?,
+                    ),
+                    None => None,
+                };
+                let saved = DataSourceService::new(self.db.as_ref().clone())
+                    .get_mapping(data_source_id, pinned)
+                    .await?;
+                match (saved, pinned) {
+                    (Some(mapping), _) => (
+                        serde_json::from_value(mapping.spec).map_err(|e| {
+                            AppError::Internal(format!("Saved mapping {} is unreadable: {}", mapping.version, e))
+                        })?,
+                        Some(mapping.version),
+                    ),
+                    (None, Some(version)) => {
+                        return Err(AppError::NotFound(format!("Mapping version {} not found", version)));
+                    }
+                    (None, None) => (TransformSpec::default(), None),
+                }
+            }
+            (None, None) => (TransformSpec::default(), None),
+        };
+        if let Some(value) = job.source_config.get("column_mapping") {
+            spec.columns = serde_json::from_value(value.clone())
+                .map_err(|e| AppError::Validation(format!("Invalid column mapping: {}", e)))?;
+        }
+        Ok((spec, version))
+    }
+
+    /// Run `spec` over the first `limit` rows of the file at `path`
+    ///
+    /// Nothing is written: each row comes back as read, as transformed and as
+    /// mapped, or with the reason it was filtered out or failed.
+    pub async fn preview_file(
+        &self,
+        job: &IngestionJob,
+        path: PathBuf,
+        spec: &TransformSpec,
+        limit: usize,
+    ) -> AppResult<TransformPreview> {
+        let options = parse_options(job)?;
+        let mut mapper = RowMapper::new(spec)?;
+        let limit = limit.clamp(1, MAX_PREVIEW_ROWS);
+
+        tokio::task::spawn_blocking(move || {
+            let mut rows = Vec::with_capacity(limit);
+            let bytes_read = AtomicU64::new(0);
+            let parsed = parsing::parse_file(&path, &options, &bytes_read, &mut |row| {
+                rows.push(PreviewRow::new(row, &mut mapper));
+                if rows.len() >= limit {
+                    // Stop reading; the rows are all that's wanted
+                    return Err(AppError::Internal(PREVIEW_DONE.to_string()));
+                }
+                Ok(())
+            });
+            match parsed {
+                Err(AppError::Internal(message)) if message == PREVIEW_DONE => {}
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+            Ok(TransformPreview {
+                filtered_rows: rows.iter().filter(|row| row.filtered).count(),
+                error_count: rows.iter().filter(|row| row.error.is_some()).count(),
+                column_mapping: mapper.mapping,
+                rows,
+            })
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+    }
+
+    /// The schema a job's file is compared with, and how differences count
+    ///
+    /// The baseline is the data source's saved schema. The policy is the job's
+    /// `source_config.schema_policy`, else the one saved with the schema, and
+    /// is saved along with a new schema. `source_config.accept_schema` makes
+    /// the file's schema the new baseline.
+    fn schema_check(&self, job: &IngestionJob, data_source_id: Option<Uuid>) -> AppResult<SchemaCheck> {
+        let baseline = match data_source_id {
+            Some(data_source_id) => {
+                let mut conn = self.db.get_connection()?;
+                let schema: Option<serde_json::Value> = data_sources::table
+                    .find(data_source_id)
+                    .select(data_sources::schema)
+                    .first(&mut conn)
+                    .map_err(AppError::Database)?;
+                // Schemas written by hand rather than inferred are replaced
+                schema.and_then(|schema| serde_json::from_value::<SourceSchema>(schema).ok())
+            }
+            None => None,
+        };
+        let policy = match job.source_config.get("schema_policy") {
+            Some(value) => Some(
+                serde_json::from_value(value.clone())
+                    .map_err(|e| AppError::Validation(format!("Invalid schema policy: {}", e)))?,
+            ),
+            None => baseline.as_ref().and_then(|schema| schema.policy),
+        };
+        let accept = job
+            .source_config
+            .get("accept_schema")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        Ok(SchemaCheck {
+            baseline,
+            policy,
+            accept,
+        })
+    }
+
+    /// Run record rules over every record the job ingested
+    ///
+    /// A summary of the outcome is kept in the job's `metadata.validation`.
+    pub async fn validate_job(&self, job: &IngestionJob, specs: &[RecordRuleSpec]) -> AppResult<JobValidation> {
+        let today = Utc::now().date_naive();
+        let mut rules = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let references = match &spec.check {
+                RecordCheck::Reference { .. } => Some(self.reference_values(job, &spec.check)?),
+                _ => None,
+            };
+            rules.push(spec.build(today, references).map_err(AppError::Validation)?);
+        }
+        let mut groups: Vec<RuleFindings> = rules
+            .iter()
+            .map(|rule| RuleFindings {
+                rule: rule.name().to_string(),
+                severity: rule.severity(),
+                total: 0,
+                findings: Vec::new(),
+            })
+            .collect();
+
+        let mut records_checked = 0;
+        let mut after = None;
+        loop {
+            let page = self.record_page(&[job.id], after)?;
+            for record in &page {
+                records_checked += 1;
+                for (rule, group) in rules.iter_mut().zip(groups.iter_mut()) {
+                    let Some(message) = rule.check(record) else { continue };
+                    group.total += 1;
+                    if group.findings.len() < MAX_FINDINGS_PER_RULE {
+                        group.findings.push(RecordFinding {
+                            record_id: record.id,
+                            row: record_row(record),
+                            external_id: record.external_id.clone(),
+                            message,
+                        });
+                    }
+                }
+            }
+            if (page.len() as i64) < VALIDATION_PAGE_SIZE {
+                break;
+            }
+            after = page.last().map(|record| record.id);
+        }
+
+        let count = |severity| {
+            groups
+                .iter()
+                .filter(|group| group.severity == severity)
+                .map(|group| group.total)
+                .sum::<usize>()
+        };
+        let validation = JobValidation {
+            job_id: job.id,
+            valid: count(Severity::Error) == 0,
+            records_checked,
+            error_count: count(Severity::Error),
+            warning_count: count(Severity::Warning),
+            rules: groups,
+        };
+
+        let mut metadata = job.metadata.clone();
+        if let Some(object) = metadata.as_object_mut() {
+            object.insert(
+                "validation".to_string(),
+                serde_json::json!({
+                    "valid": validation.valid,
+                    "records_checked": validation.records_checked,
+                    "error_count": validation.error_count,
+                    "warning_count": validation.warning_count,
+                    "rules": validation.rules.iter().map(|group| serde_json::json!({
+                        "rule": group.rule,
+                        "severity": group.severity,
+                        "total": group.total,
+                    })).collect::<Vec<_>>(),
+                    "validated_at": Utc::now(),
+                }),
+            );
+        }
+        let mut conn = self.db.get_connection()?;
+        diesel::update(ingestion_jobs::table.find(job.id))
+            .set(ingestion_jobs::metadata.eq(metadata))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+
+        Ok(validation)
+    }
+
+    /// Up to a page of the records of `job_ids` with ids after `after`, in id order
+    fn record_page(&self, job_ids: &[Uuid], after: Option<Uuid>) -> AppResult<Vec<ReconciliationRecord>> {
+        let mut conn = self.db.get_connection()?;
+        let mut query = reconciliation_records::table
+            .filter(reconciliation_records::ingestion_job_id.eq_any(job_ids))
+            .into_boxed();
+        if let Some(after) = after {
+            query = query.filter(reconciliation_records::id.gt(after));
+        }
+        query
+            .order(reconciliation_records::id.asc())
+            .limit(VALIDATION_PAGE_SIZE)
+            .select(ReconciliationRecord::as_select())
+            .load(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Values a reference rule may point at: the target field of the records
+    /// of another job, or of every job that fed a data source, in the same project
+    fn reference_values(&self, job: &IngestionJob, check: &RecordCheck) -> AppResult<HashSet<String>> {
+        let RecordCheck::Reference {
+            job_id,
+            data_source_id,
+            target_field,
+            ..
+        } = check
+        else {
+            return Ok(HashSet::new());
+        };
+        let mut conn = self.db.get_connection()?;
+        let mut query = ingestion_jobs::table
+            .filter(ingestion_jobs::project_id.eq(job.project_id))
+            .select(ingestion_jobs::id)
+            .into_boxed();
+        query = match (job_id, data_source_id) {
+            (Some(job_id), _) => query.filter(ingestion_jobs::id.eq(*job_id)),
+            (None, Some(data_source_id)) => query.filter(
+                ingestion_jobs::source_config
+                    .retrieve_as_text("data_source_id")
+                    .eq(data_source_id.to_string()),
+            ),
+            (None, None) => {
+                return Err(AppError::Validation(
+                    "A reference rule needs a job_id or data_source_id to refer to".to_string(),
+                ));
+            }
+        };
+        let job_ids: Vec<Uuid> = query.load(&mut conn).map_err(AppError::Database)?;
+        if job_ids.is_empty() {
+            return Err(AppError::NotFound("No records in this project to refer to".to_string()));
+        }
+
+        let mut values = HashSet::new();
+        let mut after = None;
+        loop {
+            let page = self.record_page(&job_ids, after)?;
+            values.extend(page.iter().filter_map(|record| record_text(record, target_field)));
+            if (page.len() as i64) < VALIDATION_PAGE_SIZE {
+                break;
+            }
+            after = page.last().map(|record| record.id);
+        }
+        Ok(values)
+    }
+
+    /// The data source a job feeds, which must be in the job's project
+    fn check_data_source(&self, job: &IngestionJob, value: &serde_json::Value) -> AppResult<Uuid> {
+        let id = value
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| AppError::Validation("data_source_id must be a UUID".to_string()))?;
+        let mut conn = self.db.get_connection()?;
+        let project_id: Uuid = data_sources::table
+            .find(id)
+            .filter(data_sources::is_active.eq(true))
+            .select(data_sources::project_id)
+            .first(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::NotFound(format!("Data source {} not found", id)))?;
+        if project_id != job.project_id {
+            return Err(AppError::Validation(
+                "Data source belongs to a different project than the ingestion job".to_string(),
+            ));
+        }
+        Ok(id)
+    }
+}
+
+/// Error that ends a preview's parse once enough rows are read
+const PREVIEW_DONE: &str = "preview complete";
+
+/// A job's parse options, read from its `source_config`
+fn parse_options(job: &IngestionJob) -> AppResult<ParseOptions> {
+    serde_json::from_value(job.source_config.clone())
+        .map_err(|e| AppError::Validation(format!("Invalid parse options: {}", e)))
+}
+
+/// A job's record-level duplicate detection, from `source_config.duplicates`
+fn duplicate_spec(job: &IngestionJob) -> AppResult<DuplicateSpec> {
+    let Some(value) = job.source_config.get("duplicates") else {
+        return Ok(DuplicateSpec::default());
+    };
+    let spec: DuplicateSpec = serde_json::from_value(value.clone())
+        .map_err(|e| AppError::Validation(format!("Invalid duplicate detection settings: {}", e)))?;
+    spec.validate()
+        .map_err(|e| AppError::Validation(format!("Invalid duplicate detection settings: {}", e)))?;
+    Ok(spec)
+}
+
+/// Hex SHA-256 of a file's contents
+fn hash_file(path: &Path) -> AppResult<String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// What the transformation and column mapping make of one parsed row
+enum RowOutcome {
+    /// A filter step skipped the row
+    Filtered,
+    /// `fields` is the row as read for transform errors, as transformed for
+    /// mapping errors
+    Failed {
+        error_type: &'static str,
+        message: String,
+        fields: Map<String, Value>,
+    },
+    Mapped {
+        record: MappedRecord,
+        fields: Map<String, Value>,
+    },
+}
+
+/// Runs a compiled transformation spec and then the column mapping over rows
+struct RowMapper {
+    transformer: Transformer,
+    /// Columns as the spec gives them; completed from the first row's fields
+    configured: ColumnMapping,
+    mapping: Option<ColumnMapping>,
+}
+
+impl RowMapper {
+    fn new(spec: &TransformSpec) -> AppResult<Self> {
+        let transformer = spec
+            .compile()
+            .map_err(|e| AppError::Validation(format!("Invalid transformation spec: {}", e)))?;
+        Ok(Self {
+            transformer,
+            configured: spec.columns.clone(),
+            mapping: None,
+        })
+    }
+
+    fn map(&mut self, fields: Map<String, Value>) -> RowOutcome {
+        let fields = if self.transformer.is_empty() {
+            fields
+        } else {
+            let mut transformed = fields.clone();
+            match self.transformer.apply(&mut transformed) {
+                Ok(true) => transformed,
+                Ok(false) => return RowOutcome::Filtered,
+                Err(message) => {
+                    return RowOutcome::Failed {
+                        error_type: "transform",
+                        message,
+                        fields,
+                    }
+                }
+            }
+        };
+
+        let configured = &self.configured;
+        let mapping = self
+            .mapping
+            .get_or_insert_with(|| configured.infer(fields.keys().map(String::as_str)));
+        let message = match mapping.map_row(&fields) {
+            Ok(record)
+                if record
+                    .external_id
+                    .as_ref()
+                    .is_some_and(|id| id.chars().count() > MAX_EXTERNAL_ID_LENGTH) =>
+            {
+                format!("External id is longer than {} characters", MAX_EXTERNAL_ID_LENGTH)
+            }
+            Ok(record) => return RowOutcome::Mapped { record, fields },
+            Err(message) => message,
+        };
+        RowOutcome::Failed {
+            error_type: "mapping",
+            message,
+            fields,
+        }
+    }
+}
+
+/// The first rows of a file run through a transformation spec
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformPreview {
+    pub rows: Vec<PreviewRow>,
+    pub filtered_rows: usize,
+    pub error_count: usize,
+    /// Column mapping after inference from the first transformed row
+    pub column_mapping: Option<ColumnMapping>,
+}
+
+/// One row of a transformation preview
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewRow {
+    pub index: usize,
+    /// Fields as read from the file
+    pub source: Option<Value>,
+    /// Fields after the transformation steps
+    pub fields: Option<Value>,
+    /// Record columns the row would be imported with
+    pub record: Option<Value>,
+    pub filtered: bool,
+    pub error: Option<String>,
+}
+
+impl PreviewRow {
+    fn new(row: RowResult, mapper: &mut RowMapper) -> Self {
+        let mut preview = PreviewRow {
+            index: 0,
+            source: None,
+            fields: None,
+            record: None,
+            filtered: false,
+            error: None,
+        };
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => {
+                preview.index = error.index;
+                preview.source = error.raw;
+                preview.error = Some(error.message);
+                return preview;
+            }
+        };
+        preview.index = row.index;
+        preview.source = Some(Value::Object(row.fields.clone()));
+        match mapper.map(row.fields) {
+            RowOutcome::Filtered => preview.filtered = true,
+            RowOutcome::Failed { message, fields, .. } => {
+                preview.fields = Some(Value::Object(fields));
+                preview.error = Some(message);
+            }
+            RowOutcome::Mapped { record, fields } => {
+                preview.fields = Some(Value::Object(fields));
+                preview.record = Some(serde_json::json!({
+                    "external_id": record.external_id,
+                    "amount": record.amount,
+                    "transaction_date": record.transaction_date,
+                    "description": record.description,
+                }));
+            }
+        }
+        preview
+    }
+}
+
+/// How a file's inferred schema is checked against its data source's
+struct SchemaCheck {
+    baseline: Option<SourceSchema>,
+    /// Everything warns when no policy is set
+    policy: Option<DriftPolicy>,
+    /// Save the file's schema over the baseline instead of comparing
+    accept: bool,
+}
+
+/// State of one file ingestion, run on a blocking thread
+struct FileIngestion<'a> {
+    db: &'a Database,
+    job: &'a IngestionJob,
+    mapper: RowMapper,
+    data_source_id: Option<Uuid>,
+    mapping_version: Option<i32>,
+    schema_check: SchemaCheck,
+    profiler: SchemaProfiler,
+    duplicates: DuplicateSpec,
+    /// Jobs whose records a duplicate may repeat; `None` for the whole project
+    duplicate_jobs: Option<Vec<Uuid>>,
+    /// Row each duplicate key was first read from
+    seen_keys: HashMap<String, usize>,
+    file_hash: String,
+    records: Vec<NewReconciliationRecord>,
+    /// File rows of the buffered records
+    record_rows: Vec<usize>,
+    errors: Vec<NewIngestionError>,
+    quarantined: Vec<NewQuarantinedRecord>,
+    total_bytes: u64,
+    total_rows: usize,
+    imported_records: usize,
+    error_count: usize,
+    filtered_rows: usize,
+    duplicate_rows: usize,
+    error_samples: Vec<String>,
+}
+
+impl<'a> FileIngestion<'a> {
+    fn new(
+        db: &'a Database,
+        job: &'a IngestionJob,
+        mapper: RowMapper,
+        data_source_id: Option<Uuid>,
+        mapping_version: Option<i32>,
+        schema_check: SchemaCheck,
+        duplicates: DuplicateSpec,
+    ) -> Self {
+        Self {
+            db,
+            job,
+            mapper,
+            data_source_id,
+            mapping_version,
+            schema_check,
+            profiler: SchemaProfiler::new(),
+            duplicates,
+            duplicate_jobs: None,
+            seen_keys: HashMap::new(),
+            file_hash: String::new(),
+            records: Vec::with_capacity(INGEST_BATCH_SIZE),
+            record_rows: Vec::with_capacity(INGEST_BATCH_SIZE),
+            errors: Vec::new(),
+            quarantined: Vec::new(),
+            total_bytes: 0,
+            total_rows: 0,
+            imported_records: 0,
+            error_count: 0,
+            filtered_rows: 0,
+            duplicate_rows: 0,
+            error_samples: Vec::new(),
+        }
+    }
+
+    fn run(mut self, path: &Path, options: &ParseOptions) -> AppResult<FileIngestionSummary> {
+        self.total_bytes = std::fs::metadata(path)
+            .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?
+            .len();
+        self.file_hash = hash_file(path)?;
+        self.check_duplicate_file()?;
+        self.duplicate_jobs = self.duplicate_jobs()?;
+        self.start()?;
+
+        let bytes_read = AtomicU64::new(0);
+        let parse = parsing::parse_file(path, options, &bytes_read, &mut |row| {
+            self.push(row);
+            if self.records.len() + self.errors.len() + self.quarantined.len() >= INGEST_BATCH_SIZE {
+                self.flush(bytes_read.load(Ordering::Relaxed))?;
+            }
+            Ok(())
+        })?;
+        self.flush(self.total_bytes)?;
+
+        let schema = self.profiler.finish();
+        let schema_changes = match &self.schema_check.baseline {
+            Some(baseline) if !self.schema_check.accept => {
+                let policy = self.schema_check.policy.unwrap_or_default();
+                parsing::schema::compare_schemas(baseline, &schema, &policy)
+            }
+            _ => Vec::new(),
+        };
+        let failures: Vec<String> = schema_changes
+            .iter()
+            .filter(|change| change.action == DriftAction::Fail)
+            .map(ToString::to_string)
+            .collect();
+        if !failures.is_empty() {
+            self.discard()?;
+            return Err(AppError::Validation(format!(
+                "File doesn't match the data source's schema: {}",
+                failures.join("; ")
+            )));
+        }
+        self.finish(&parse, &schema, &schema_changes)?;
+
+        Ok(FileIngestionSummary {
+            job_id: self.job.id,
+            total_rows: self.total_rows,
+            imported_records: self.imported_records,
+            error_count: self.error_count,
+            filtered_rows: self.filtered_rows,
+            duplicate_rows: self.duplicate_rows,
+            file_hash: self.file_hash,
+            mapping_version: self.mapping_version,
+            parse,
+            schema,
+            schema_changes,
+            errors: self.error_samples,
+        })
+    }
+
+    /// Refuse a file already ingested into the project, by this data source or
+    /// another, unless the job allows it
+    fn check_duplicate_file(&self) -> AppResult<()> {
+        let allowed = self
+            .job
+            .source_config
+            .get("allow_duplicate_file")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if allowed {
+            return Ok(());
+        }
+
+        let mut conn = self.db.get_connection()?;
+        let earlier_job: Option<Uuid> = ingestion_jobs::table
+            .filter(ingestion_jobs::project_id.eq(self.job.project_id))
+            .filter(ingestion_jobs::id.ne(self.job.id))
+            .filter(ingestion_jobs::status.eq("completed"))
+            .filter(ingestion_jobs::metadata.retrieve_as_text("file_hash").eq(&self.file_hash))
+            .select(ingestion_jobs::id)
+            .first(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?;
+        if let Some(earlier_job) = earlier_job {
+            return Err(AppError::Conflict(format!(
+                "This file was already ingested by job {}; set allow_duplicate_file to ingest it again",
+                earlier_job
+            )));
+        }
+
+        let mut query = data_sources::table
+            .filter(data_sources::project_id.eq(self.job.project_id))
+            .filter(data_sources::is_active.eq(true))
+            .filter(data_sources::file_hash.eq(&self.file_hash))
+            .select(data_sources::id)
+            .into_boxed();
+        if let Some(data_source_id) = self.data_source_id {
+            query = query.filter(data_sources::id.ne(data_source_id));
+        }
+        let other_source: Option<Uuid> = query.first(&mut conn).optional().map_err(AppError::Database)?;
+        if let Some(other_source) = other_source {
+            return Err(AppError::Conflict(format!(
+                "This file is already the file of data source {}; set allow_duplicate_file to ingest it again",
+                other_source
+            )));
+        }
+        Ok(())
+    }
+
+    /// Earlier jobs whose records this job's records are checked against, for
+    /// the data source scope; the project scope checks every job's records
+    fn duplicate_jobs(&self) -> AppResult<Option<Vec<Uuid>>> {
+        let data_source_id = match (self.duplicates.scope, self.data_source_id) {
+            (DuplicateScope::Job, _) => return Ok(Some(Vec::new())),
+            (DuplicateScope::DataSource, Some(data_source_id)) => data_source_id,
+            // Without a data source to narrow to, the whole project is checked
+            (DuplicateScope::DataSource, None) | (DuplicateScope::Project, _) => return Ok(None),
+        };
+        let mut conn = self.db.get_connection()?;
+        let jobs = ingestion_jobs::table
+            .filter(ingestion_jobs::project_id.eq(self.job.project_id))
+            .filter(ingestion_jobs::id.ne(self.job.id))
+            .filter(
+                ingestion_jobs::source_config
+                    .retrieve_as_text("data_source_id")
+                    .eq(data_source_id.to_string()),
+            )
+            .select(ingestion_jobs::id)
+            .load(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(Some(jobs))
+    }
+
+    /// Clear what an earlier run left behind and mark the job as processing
+    fn start(&self) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        conn.transaction(|conn| {
+            diesel::delete(
+                reconciliation_records::table.filter(reconciliation_records::ingestion_job_id.eq(self.job.id)),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                quarantined_records::table.filter(quarantined_records::ingestion_job_id.eq(self.job.id)),
+            )
+            .execute(conn)?;
+            diesel::delete(ingestion_errors::table.filter(ingestion_errors::job_id.eq(self.job.id)))
+                .execute(conn)?;
+            diesel::update(ingestion_jobs::table.find(self.job.id))
+                .set(&UpdateIngestionJob {
+                    status: Some("processing".to_string()),
+                    progress: Some(0),
+                    total_records: Some(None),
+                    processed_records: Some(0),
+                    error_count: Some(0),
+                    started_at: Some(Some(Utc::now())),
+                    completed_at: Some(None),
+                    error_message: None,
+                })
+                .execute(conn)?;
+            Ok::<_, diesel::result::Error>(())
+        })
+        .map_err(AppError::Database)
+    }
+
+    fn push(&mut self, row: RowResult) {
+        self.total_rows += 1;
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => {
+                self.push_error("parse", error.index, error.message, error.raw);
+                return;
+            }
+        };
+        self.profiler.observe(&row.fields);
+
+        let (mapped, fields) = match self.mapper.map(row.fields) {
+            RowOutcome::Mapped { record, fields } => (record, fields),
+            RowOutcome::Filtered => {
+                self.filtered_rows += 1;
+                return;
+            }
+            RowOutcome::Failed {
+                error_type,
+                message,
+                fields,
+            } => {
+                self.push_error(error_type, row.index, message, Some(Value::Object(fields)));
+                return;
+            }
+        };
+
+        let dedup_key = self.duplicates.key(&mapped, &fields);
+        let mut audit_trail = serde_json::json!({ "ingested_from_row": row.index });
+        if let (Some(key), Some(trail)) = (&dedup_key, audit_trail.as_object_mut()) {
+            trail.insert("dedup_key".to_string(), Value::String(key.clone()));
+        }
+        let record = NewReconciliationRecord {
+            project_id: self.job.project_id,
+            ingestion_job_id: self.job.id,
+            external_id: mapped.external_id,
+            status: "pending".to_string(),
+            amount: mapped.amount,
+            transaction_date: mapped.transaction_date,
+            description: mapped.description,
+            source_data: Value::Object(fields),
+            matching_results: serde_json::json!({}),
+            confidence: None,
+            audit_trail,
+        };
+
+        if let Some(key) = dedup_key {
+            if let Some(&first_row) = self.seen_keys.get(&key) {
+                self.quarantine(record, row.index, key, "in_file", None, Some(first_row));
+                return;
+            }
+            self.seen_keys.insert(key, row.index);
+        }
+        self.records.push(record);
+        self.record_rows.push(row.index);
+    }
+
+    fn quarantine(
+        &mut self,
+        record: NewReconciliationRecord,
+        index: usize,
+        dedup_key: String,
+        reason: &str,
+        duplicate_of_record_id: Option<Uuid>,
+        duplicate_of_row: Option<usize>,
+    ) {
+        let record_data = match serde_json::to_value(&record) {
+            Ok(record_data) => record_data,
+            Err(e) => {
+                self.push_error("duplicate", index, format!("Duplicate record couldn't be quarantined: {}", e), None);
+                return;
+            }
+        };
+        self.duplicate_rows += 1;
+        self.quarantined.push(NewQuarantinedRecord {
+            project_id: self.job.project_id,
+            ingestion_job_id: self.job.id,
+            record_index: i32::try_from(index).ok(),
+            dedup_key,
+            reason: reason.to_string(),
+            duplicate_of_record_id,
+            duplicate_of_row: duplicate_of_row.and_then(|row| i32::try_from(row).ok()),
+            record_data,
+        });
+    }
+
+    /// Move buffered records that repeat a record already ingested to quarantine
+    fn quarantine_existing(&mut self, conn: &mut PgConnection) -> AppResult<()> {
+        if self.duplicates.scope == DuplicateScope::Job {
+            return Ok(());
+        }
+        let keys: Vec<String> = self.records.iter().filter_map(record_dedup_key).collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let dedup_key = reconciliation_records::audit_trail.retrieve_as_text("dedup_key");
+        let mut query = reconciliation_records::table
+            .filter(reconciliation_records::project_id.eq(self.job.project_id))
+            .filter(reconciliation_records::ingestion_job_id.ne(self.job.id))
+            .filter(dedup_key.eq_any(&keys))
+            .select((dedup_key, reconciliation_records::id))
+            .into_boxed();
+        if let Some(jobs) = &self.duplicate_jobs {
+            query = query.filter(reconciliation_records::ingestion_job_id.eq_any(jobs.clone()));
+        }
+        let existing: HashMap<String, Uuid> = query
+            .load::<(String, Uuid)>(conn)
+            .map_err(AppError::Database)?
+            .into_iter()
+            .collect();
+        if existing.is_empty() {
+            return Ok(());
+        }
+
+        let records = std::mem::take(&mut self.records);
+        let rows = std::mem::take(&mut self.record_rows);
+        for (record, row) in records.into_iter().zip(rows) {
+            match record_dedup_key(&record).and_then(|key| existing.get(&key).map(|id| (key, *id))) {
+                Some((key, id)) => self.quarantine(record, row, key, "existing_record", Some(id), None),
+                None => {
+                    self.records.push(record);
+                    self.record_rows.push(row);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_error(&mut self, error_type: &str, index: usize, message: String, raw: Option<serde_json::Value>) {
+        if self.error_samples.len() < SUMMARY_ERROR_LIMIT {
+            self.error_samples.push(format!("Row {}: {}", index, message));
+        }
+        self.errors.push(NewIngestionError {
+            job_id: self.job.id,
+            error_type: error_type.to_string(),
+            error_message: message,
+            record_data: raw,
+            record_index: i32::try_from(index).ok(),
+            stack_trace: None,
+        });
+    }
+
+    /// Remove the records already written, when the file is rejected after parsing
+    fn discard(&mut self) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        conn.transaction(|conn| {
+            diesel::delete(
+                reconciliation_records::table.filter(reconciliation_records::ingestion_job_id.eq(self.job.id)),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                quarantined_records::table.filter(quarantined_records::ingestion_job_id.eq(self.job.id)),
+            )
+            .execute(conn)?;
+            Ok::<_, diesel::result::Error>(())
+        })
+        .map_err(AppError::Database)?;
+        self.imported_records = 0;
+        self.duplicate_rows = 0;
+        Ok(())
+    }
+
+    /// Write the buffered records and errors and report progress
+    fn flush(&mut self, bytes_read: u64) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        self.quarantine_existing(&mut conn)?;
+        let records = std::mem::take(&mut self.records);
+        self.record_rows.clear();
+        let errors = std::mem::take(&mut self.errors);
+        let quarantined = std::mem::take(&mut self.quarantined);
+        let imported = self.imported_records + records.len();
+        let error_count = self.error_count + errors.len();
+        // Progress follows the bytes read; 100 is left for the final update
+        let progress = if self.total_bytes == 0 {
+            0
+        } else {
+            (bytes_read.saturating_mul(100) / self.total_bytes).min(99) as i32
+        };
+
+        conn.transaction(|conn| {
+            if !records.is_empty() {
+                diesel::insert_into(reconciliation_records::table)
+                    .values(&records)
+                    .execute(conn)?;
+            }
+            if !quarantined.is_empty() {
+                diesel::insert_into(quarantined_records::table)
+                    .values(&quarantined)
+                    .execute(conn)?;
+            }
+            if !errors.is_empty() {
+                diesel::insert_into(ingestion_errors::table)
+                    .values(&errors)
+                    .execute(conn)?;
+            }
+            // `updated_at` marks the run as alive; see `STALE_PROCESSING_MINUTES`
+            diesel::update(ingestion_jobs::table.find(self.job.id))
+                .set((
+                    &UpdateIngestionJob {
+                        progress: Some(progress),
+                        processed_records: Some(count_to_i32(imported)),
+                        error_count: Some(count_to_i32(error_count)),
+                        ..Default::default()
+                    },
+                    ingestion_jobs::updated_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+            Ok::<_, diesel::result::Error>(())
+        })
+        .map_err(AppError::Database)?;
+
+        self.imported_records = imported;
+        self.error_count = error_count;
+        Ok(())
+    }
+
+    /// Mark the job completed and record what was detected about the file,
+    /// on the job and on its data source
+    fn finish(&self, parse: &ParseSummary, schema: &SourceSchema, schema_changes: &[SchemaChange]) -> AppResult<()> {
+        let mut metadata = self.job.metadata.clone();
+        if let Some(object) = metadata.as_object_mut() {
+            object.insert(
+                "parse".to_string(),
+                serde_json::to_value(parse).map_err(|e| AppError::Internal(e.to_string()))?,
+            );
+            object.insert(
+                "column_mapping".to_string(),
+                serde_json::to_value(&self.mapper.mapping).map_err(|e| AppError::Internal(e.to_string()))?,
+            );
+            object.insert("filtered_rows".to_string(), serde_json::json!(self.filtered_rows));
+            object.insert("mapping_version".to_string(), serde_json::json!(self.mapping_version));
+            object.insert("file_hash".to_string(), serde_json::json!(self.file_hash));
+            object.insert("duplicate_rows".to_string(), serde_json::json!(self.duplicate_rows));
+            object.insert(
+                "schema".to_string(),
+                serde_json::to_value(schema).map_err(|e| AppError::Internal(e.to_string()))?,
+            );
+            object.insert(
+                "schema_changes".to_string(),
+                serde_json::to_value(schema_changes).map_err(|e| AppError::Internal(e.to_string()))?,
+            );
+        }
+
+        let statements = self.statement_rows(parse);
+        // The first file into a data source, or one accepted, sets its schema
+        let source_schema = match &self.schema_check.baseline {
+            Some(_) if !self.schema_check.accept => None,
+            _ => {
+                let saved = SourceSchema {
+                    policy: self.schema_check.policy,
+                    ..schema.clone()
+                };
+                Some(serde_json::to_value(saved).map_err(|e| AppError::Internal(e.to_string()))?)
+            }
+        };
+        let mut conn = self.db.get_connection()?;
+        conn.transaction(|conn| {
+            diesel::update(ingestion_jobs::table.find(self.job.id))
+                .set((
+                    &UpdateIngestionJob {
+                        status: Some("completed".to_string()),
+                        progress: Some(100),
+                        total_records: Some(Some(count_to_i32(self.total_rows))),
+                        completed_at: Some(Some(Utc::now())),
+                        ..Default::default()
+                    },
+                    ingestion_jobs::metadata.eq(metadata),
+                ))
+                .execute(conn)?;
+
+            let Some(data_source_id) = self.data_source_id else { return Ok(()) };
+            // Balances from an earlier ingestion of the source are superseded
+            diesel::delete(
+                data_source_statements::table.filter(data_source_statements::data_source_id.eq(data_source_id)),
+            )
+            .execute(conn)?;
+            if !statements.is_empty() {
+                diesel::insert_into(data_source_statements::table)
+                    .values(&statements)
+                    .execute(conn)?;
+            }
+            diesel::update(data_sources::table.find(data_source_id))
+                .set((
+                    data_sources::record_count.eq(Some(count_to_i32(self.imported_records))),
+                    data_sources::file_hash.eq(Some(&self.file_hash)),
+                    data_sources::status.eq("processed"),
+                    data_sources::processed_at.eq(Some(Utc::now())),
+                    data_sources::updated_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+            if let Some(source_schema) = source_schema {
+                diesel::update(data_sources::table.find(data_source_id))
+                    .set(data_sources::schema.eq(Some(source_schema)))
+                    .execute(conn)?;
+            }
+            Ok::<_, diesel::result::Error>(())
+        })
+        .map_err(AppError::Database)
+    }
+
+    /// Balances of the statements in the file, for the job's data source
+    fn statement_rows(&self, parse: &ParseSummary) -> Vec<NewDataSourceStatement> {
+        let Some(data_source_id) = self.data_source_id else { return Vec::new() };
+        let limit = |text: &Option<String>| {
+            text.as_ref()
+                .map(|text| text.chars().take(MAX_STATEMENT_TEXT_LENGTH).collect::<String>())
+        };
+        parse
+            .statements
+            .iter()
+            .enumerate()
+            .map(|(sequence, statement)| NewDataSourceStatement {
+                data_source_id,
+                ingestion_job_id: self.job.id,
+                sequence: count_to_i32(sequence + 1),
+                format: parse.format.as_str().to_string(),
+                statement_id: limit(&statement.statement_id),
+                account: limit(&statement.account),
+                currency: statement
+                    .currency
+                    .clone()
+                    .filter(|currency| currency.chars().count() == 3),
+                opening_balance: statement.opening_balance.as_ref().map(|balance| balance.amount.clone()),
+                opening_date: statement.opening_balance.as_ref().and_then(|balance| balance.date),
+                closing_balance: statement.closing_balance.as_ref().map(|balance| balance.amount.clone()),
+                closing_date: statement.closing_balance.as_ref().and_then(|balance| balance.date),
+                entry_count: count_to_i32(statement.entry_count),
+            })
+            .collect()
+    }
+}
+
+/// Duplicate key a buffered record was given, from its audit trail
+fn record_dedup_key(record: &NewReconciliationRecord) -> Option<String> {
+    record.audit_trail.get("dedup_key").and_then(Value::as_str).map(str::to_string)
+}
+
+fn count_to_i32(count: usize) -> i32 {
+    i32::try_from(count).unwrap_or(i32::MAX)
 }
 
 impl Default for UpdateIngestionJob {
@@ -26,6 +26,7 @@ pub mod workflow;
 pub mod cashflow;
 pub mod adjudication;
 pub mod ingestion;
+pub mod parsing;
 pub mod visualization;
 pub mod data_source;
 pub mod data_source_config;
@@ -63,6 +64,7 @@ pub mod ai;
 pub mod security;
 pub mod security_monitor;
 pub mod security_event_logging;
+pub mod audit;
 pub mod compliance_reporting;
 pub mod secrets;
 pub mod secret_manager;
@@ -0,0 +1,242 @@
+//! BAI2 cash management balance reporting files
+//!
+//! Records are comma-separated lines ending in `/`, with `88` lines
+//! continuing the record before them. A group (`02`) carries the as-of date,
+//! each account (`03`) its balance summaries and each `16` record one
+//! transaction. Amounts have no decimal point: the currency's minor units are
+//! implied.
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+
+use crate::errors::{AppError, AppResult};
+
+use super::statement::{clean_text, parse_yymmdd, Balance, StatementEntry, StatementInfo, StatementSink};
+
+/// Currencies without minor units; everything else is taken as two decimals
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &["JPY", "KRW", "CLP", "ISK", "VND", "XAF", "XOF", "HUF"];
+/// Balance type codes for the opening and closing ledger balances
+const OPENING_LEDGER: &str = "010";
+const CLOSING_LEDGER: &str = "015";
+
+/// One logical record with its continuations joined on
+struct Record<'a> {
+    code: &'a str,
+    fields: Vec<&'a str>,
+    /// Text after the fixed fields of a `16` record, which may hold commas
+    raw: String,
+}
+
+fn records(text: &str) -> Vec<Record<'_>> {
+    let mut records: Vec<Record<'_>> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let body = line.strip_suffix('/').unwrap_or(line);
+        let (code, rest) = body.split_once(',').unwrap_or((body, ""));
+        if code == "88" {
+            if let Some(record) = records.last_mut() {
+                record.fields.extend(rest.split(','));
+                record.raw.push(',');
+                record.raw.push_str(rest);
+            }
+            continue;
+        }
+        records.push(Record {
+            code,
+            fields: rest.split(',').collect(),
+            raw: rest.to_string(),
+        });
+    }
+    records
+}
+
+/// Currency state while walking the file: the group's, overridden per account
+#[derive(Default)]
+struct Context {
+    as_of: Option<NaiveDate>,
+    group_currency: Option<String>,
+    currency: Option<String>,
+}
+
+pub fn parse_bai2(text: &str, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    let records = records(text);
+    if records.first().map(|record| record.code) != Some("01") {
+        return Err(AppError::Validation("File is not a BAI2 file: no 01 header record".to_string()));
+    }
+
+    let mut context = Context::default();
+    for record in &records {
+        match record.code {
+            "02" => {
+                context.as_of = record.fields.get(3).and_then(|date| parse_yymmdd(date));
+                context.group_currency = record.fields.get(5).and_then(|c| clean_text(c));
+            }
+            "03" => {
+                context.currency = record
+                    .fields
+                    .get(1)
+                    .and_then(|c| clean_text(c))
+                    .or_else(|| context.group_currency.clone());
+                let statement = read_account(record, &context);
+                sink.begin(statement);
+            }
+            "16" => match read_transaction(record, &context) {
+                Some(entry) => sink.entry(entry)?,
+                None => sink.error("Unreadable BAI2 transaction", &format!("16,{}", record.raw))?,
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Signed amount in the currency's major units
+fn bai_amount(text: &str, currency: Option<&str>) -> Option<BigDecimal> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let scale = match currency {
+        Some(currency) if ZERO_DECIMAL_CURRENCIES.contains(&currency) => 0,
+        _ => 2,
+    };
+    let amount = BigDecimal::new(digits.parse().ok()?, scale);
+    Some(if negative { -amount } else { amount })
+}
+
+/// Skip the extra fields a funds type brings, returning the value date of type `V`
+fn skip_funds_type<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Option<NaiveDate>> {
+    match fields.next()?.trim() {
+        "S" => {
+            fields.nth(2)?;
+            Some(None)
+        }
+        "V" => {
+            let date = fields.next().and_then(parse_yymmdd);
+            fields.next()?;
+            Some(date)
+        }
+        "D" => {
+            let count: usize = fields.next()?.trim().parse().ok()?;
+            for _ in 0..count * 2 {
+                fields.next()?;
+            }
+            Some(None)
+        }
+        _ => Some(None),
+    }
+}
+
+/// `03,account,currency,type,amount,count,funds[,type,amount,count,funds]...`
+fn read_account(record: &Record<'_>, context: &Context) -> StatementInfo {
+    let mut statement = StatementInfo {
+        account: record.fields.first().and_then(|account| clean_text(account)),
+        currency: context.currency.clone(),
+        ..Default::default()
+    };
+    let mut fields = record.fields.iter().skip(2).copied();
+    while let Some(type_code) = fields.next() {
+        let Some(amount) = fields.next() else { break };
+        // Item count, then the funds type and its extra fields
+        if fields.next().is_none() || skip_funds_type(&mut fields).is_none() {
+            break;
+        }
+        let Some(amount) = bai_amount(amount, context.currency.as_deref()) else { continue };
+        let balance = Balance {
+            amount,
+            date: context.as_of,
+        };
+        match type_code.trim() {
+            OPENING_LEDGER => statement.opening_balance = Some(balance),
+            CLOSING_LEDGER => statement.closing_balance = Some(balance),
+            _ => {}
+        }
+    }
+    statement
+}
+
+/// `16,type,amount,funds[,extra...],bank ref,customer ref,text`
+fn read_transaction(record: &Record<'_>, context: &Context) -> Option<StatementEntry> {
+    let mut fields = record.raw.split(',');
+    let type_code = fields.next()?.trim().to_string();
+    let amount = bai_amount(fields.next()?, context.currency.as_deref())?;
+    let value_date = skip_funds_type(&mut fields)?;
+    let bank_reference = fields.next().and_then(clean_text);
+    let customer_reference = fields.next().and_then(clean_text);
+    let text = fields.collect::<Vec<_>>().join(",");
+
+    // Detail type codes 100-399 are credits and 400-699 debits
+    let code: u16 = type_code.parse().ok()?;
+    let debit = (400..700).contains(&code);
+    Some(StatementEntry {
+        reference: bank_reference,
+        booking_date: context.as_of,
+        value_date: value_date.or(context.as_of),
+        amount: if debit { -amount } else { amount },
+        customer_reference,
+        description: clean_text(&text),
+        transaction_code: Some(type_code),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use super::super::RowResult;
+
+    const BAI2: &str = "01,BANKUS,ACME,260302,0600,1,,,2/
+02,ACME,BANKUS,1,260301,2400,USD,2/
+03,123456789,USD,010,100000,,Z,015,-2550,,Z,040,5000,3,V,260302,0000/
+16,165,250000,Z,BR-1,INV-9,ACH CREDIT ACME, INC/
+16,475,127550,S,127550,0,0,BR-2,CHK 1001,CHECK PAID/
+88,CLEARED VIA LOCKBOX
+16,XYZ,1,Z,BR-3,,BROKEN/
+49,-2550,3/
+98,-2550,1,5/
+99,-2550,1,7/";
+
+    #[test]
+    fn reads_balances_and_signed_transactions() {
+        let mut rows = Vec::new();
+        let mut on_row = |row: RowResult| {
+            rows.push(row);
+            Ok(())
+        };
+        let mut sink = StatementSink::new(&mut on_row);
+        parse_bai2(BAI2, &mut sink).unwrap_or_else(|e| panic!("{}", e));
+        let statements = sink.into_statements();
+
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("123456789"));
+        let opening = statement.opening_balance.as_ref().unwrap_or_else(|| panic!("no opening balance"));
+        assert_eq!(opening.amount, BigDecimal::from_str("1000.00").unwrap_or_else(|e| panic!("{}", e)));
+        assert_eq!(opening.date, NaiveDate::from_ymd_opt(2026, 3, 1));
+        let closing = statement.closing_balance.as_ref().unwrap_or_else(|| panic!("no closing balance"));
+        assert_eq!(closing.amount, BigDecimal::from_str("-25.50").unwrap_or_else(|e| panic!("{}", e)));
+
+        let credit = &rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message)).fields;
+        assert_eq!(credit["amount"], 2500.0);
+        assert_eq!(credit["reference"], "BR-1");
+        assert_eq!(credit["customer_reference"], "INV-9");
+        assert_eq!(credit["description"], "ACH CREDIT ACME, INC");
+        assert_eq!(credit["booking_date"], "2026-03-01");
+
+        let debit = &rows[1].as_ref().unwrap_or_else(|e| panic!("{}", e.message)).fields;
+        assert_eq!(debit["amount"], -1275.5);
+        assert_eq!(debit["reference"], "BR-2");
+        assert_eq!(debit["description"], "CHECK PAID,CLEARED VIA LOCKBOX");
+        assert_eq!(debit["currency"], "USD");
+
+        assert!(rows[2].is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! ISO 20022 bank-to-customer statements: camt.052, camt.053 and camt.054
+//!
+//! Statements (`Stmt`), reports (`Rpt`) and notifications (`Ntfctn`) share
+//! one layout: account, balances, then entries (`Ntry`). An entry that books
+//! a batch lists each transaction under `NtryDtls/TxDtls`; when those carry
+//! their own amounts they become separate rows, so a batch booking can match
+//! the individual ledger lines.
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDate;
+
+use crate::errors::{AppError, AppResult};
+
+use super::statement::{clean_text, parse_decimal, parse_iso_date, Balance, StatementEntry, StatementInfo, StatementSink};
+use super::xml::{Element, XmlEvent, XmlReader};
+
+/// End-to-end id sent when the payer didn't give one
+const NOT_PROVIDED: &str = "NOTPROVIDED";
+
+pub fn parse_camt(text: &str, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    let mut reader = XmlReader::new(text);
+    let mut in_message = false;
+    while let Some(event) = reader.next() {
+        match event? {
+            XmlEvent::Start { name, .. } if name.starts_with("BkToCstmr") => in_message = true,
+            XmlEvent::Start { name: "Stmt" | "Rpt" | "Ntfctn", .. } if in_message => {
+                read_statement(&mut reader, sink)?;
+            }
+            _ => {}
+        }
+    }
+    if !in_message {
+        return Err(AppError::Validation(
+            "XML file is not an ISO 20022 camt.052, camt.053 or camt.054 message".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Read the statement just opened, one child element at a time
+fn read_statement(reader: &mut XmlReader<'_>, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    sink.begin(StatementInfo::default());
+    // A closing balance is only a fallback opening balance when no opening one is given
+    let mut previous_closing = None;
+    while let Some(event) = reader.next() {
+        let (name, attributes) = match event? {
+            XmlEvent::Start { name, attributes } => (name, attributes),
+            XmlEvent::End { .. } => break,
+            XmlEvent::Text(_) => continue,
+        };
+        let element = reader.read_element(name, &attributes)?;
+        let statement = sink.current();
+        match name {
+            "Id" => statement.statement_id = clean_text(&element.text),
+            "Acct" => {
+                statement.account = element
+                    .text_at(&["Id", "IBAN"])
+                    .or_else(|| element.text_at(&["Id", "Othr", "Id"]))
+                    .map(str::to_string);
+                statement.currency = element.text_at(&["Ccy"]).map(str::to_string);
+            }
+            "Bal" => {
+                let code = element
+                    .text_at(&["Tp", "CdOrPrtry", "Cd"])
+                    .or_else(|| element.text_at(&["Tp", "CdOrPrtry", "Prtry"]));
+                let Some(balance) = read_balance(&element) else { continue };
+                if statement.currency.is_none() {
+                    statement.currency = element
+                        .find(&["Amt"])
+                        .and_then(|amount| amount.attribute("Ccy"))
+                        .map(str::to_string);
+                }
+                match code {
+                    Some("OPBD") => statement.opening_balance = Some(balance),
+                    Some("PRCD") => previous_closing = Some(balance),
+                    Some("CLBD") => statement.closing_balance = Some(balance),
+                    _ => {}
+                }
+            }
+            "Ntry" => read_entry(&element, sink)?,
+            _ => {}
+        }
+    }
+    let statement = sink.current();
+    if statement.opening_balance.is_none() {
+        statement.opening_balance = previous_closing;
+    }
+    Ok(())
+}
+
+fn read_balance(element: &Element) -> Option<Balance> {
+    Some(Balance {
+        amount: signed_amount(element)?,
+        date: date_of(element, "Dt"),
+    })
+}
+
+/// `Amt`, negated when `CdtDbtInd` says debit
+fn signed_amount(element: &Element) -> Option<BigDecimal> {
+    let amount = element
+        .text_at(&["Amt"])
+        .or_else(|| element.text_at(&["AmtDtls", "TxAmt", "Amt"]))
+        .and_then(parse_decimal)?;
+    Some(match element.text_at(&["CdtDbtInd"]) {
+        Some("DBIT") => -amount,
+        _ => amount,
+    })
+}
+
+/// A date element holding either `Dt` or `DtTm`
+fn date_of(element: &Element, name: &str) -> Option<NaiveDate> {
+    let date = element.child(name)?;
+    date.text_at(&["Dt"])
+        .or_else(|| date.text_at(&["DtTm"]))
+        .or_else(|| Some(date.text.trim()))
+        .and_then(parse_iso_date)
+}
+
+fn read_entry(entry: &Element, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    let Some(amount) = signed_amount(entry) else {
+        let reference = entry.text_at(&["AcctSvcrRef"]).or_else(|| entry.text_at(&["NtryRef"]));
+        return sink.error(
+            "Statement entry has no readable amount",
+            reference.unwrap_or("entry without reference"),
+        );
+    };
+    let base = StatementEntry {
+        reference: entry
+            .text_at(&["AcctSvcrRef"])
+            .or_else(|| entry.text_at(&["NtryRef"]))
+            .map(str::to_string),
+        booking_date: date_of(entry, "BookgDt"),
+        value_date: date_of(entry, "ValDt"),
+        currency: entry
+            .find(&["Amt"])
+            .and_then(|amount| amount.attribute("Ccy"))
+            .map(str::to_string),
+        amount,
+        description: entry.text_at(&["AddtlNtryInf"]).and_then(clean_text),
+        transaction_code: transaction_code(entry),
+        status: entry
+            .text_at(&["Sts", "Cd"])
+            .or_else(|| entry.text_at(&["Sts"]))
+            .map(str::to_string),
+        ..Default::default()
+    };
+
+    let transactions: Vec<&Element> = entry
+        .children_named("NtryDtls")
+        .flat_map(|details| details.children_named("TxDtls"))
+        .collect();
+    let split = transactions.len() > 1 && transactions.iter().all(|tx| signed_amount(tx).is_some());
+    if !split {
+        let mut row = base;
+        if let Some(transaction) = transactions.first() {
+            apply_transaction(&mut row, transaction, entry);
+        }
+        return sink.entry(row);
+    }
+    for transaction in transactions {
+        let mut row = base.clone();
+        if let Some(amount) = signed_amount(transaction) {
+            row.amount = match transaction.text_at(&["CdtDbtInd"]) {
+                // Without an indicator the transaction goes the way of its entry
+                None if base.amount.is_negative() => -amount.abs(),
+                _ => amount,
+            };
+        }
+        apply_transaction(&mut row, transaction, entry);
+        sink.entry(row)?;
+    }
+    Ok(())
+}
+
+/// Fill references, counterparty and remittance from a `TxDtls`
+fn apply_transaction(row: &mut StatementEntry, transaction: &Element, entry: &Element) {
+    if let Some(reference) = transaction.text_at(&["Refs", "AcctSvcrRef"]) {
+        row.reference = Some(reference.to_string());
+    }
+    row.end_to_end_id = transaction
+        .text_at(&["Refs", "EndToEndId"])
+        .filter(|id| *id != NOT_PROVIDED)
+        .map(str::to_string);
+    row.customer_reference = transaction
+        .text_at(&["Refs", "InstrId"])
+        .or_else(|| transaction.text_at(&["Refs", "PmtInfId"]))
+        .map(str::to_string);
+
+    // The counterparty is the payer of a credit and the payee of a debit
+    let debit = transaction
+        .text_at(&["CdtDbtInd"])
+        .or_else(|| entry.text_at(&["CdtDbtInd"]))
+        == Some("DBIT");
+    let (party, account) = if debit { ("Cdtr", "CdtrAcct") } else { ("Dbtr", "DbtrAcct") };
+    if let Some(parties) = transaction.child("RltdPties") {
+        row.counterparty = parties
+            .text_at(&[party, "Nm"])
+            .or_else(|| parties.text_at(&[party, "Pty", "Nm"]))
+            .and_then(clean_text);
+        row.counterparty_account = parties
+            .text_at(&[account, "Id", "IBAN"])
+            .or_else(|| parties.text_at(&[account, "Id", "Othr", "Id"]))
+            .map(str::to_string);
+    }
+
+    if let Some(remittance) = transaction.child("RmtInf") {
+        let unstructured: Vec<&str> = remittance
+            .children_named("Ustrd")
+            .map(|line| line.text.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        row.remittance = if unstructured.is_empty() {
+            remittance
+                .children_named("Strd")
+                .find_map(|structured| structured.text_at(&["CdtrRefInf", "Ref"]))
+                .map(str::to_string)
+        } else {
+            clean_text(&unstructured.join(" "))
+        };
+    }
+    if row.description.is_none() {
+        row.description = transaction.text_at(&["AddtlTxInf"]).and_then(clean_text);
+    }
+}
+
+/// ISO bank transaction code as domain/family/sub-family, or the proprietary code
+fn transaction_code(entry: &Element) -> Option<String> {
+    let code = entry.child("BkTxCd")?;
+    match (
+        code.text_at(&["Domn", "Cd"]),
+        code.text_at(&["Domn", "Fmly", "Cd"]),
+        code.text_at(&["Domn", "Fmly", "SubFmlyCd"]),
+    ) {
+        (Some(domain), Some(family), Some(sub_family)) => Some(format!("{}/{}/{}", domain, family, sub_family)),
+        _ => code.text_at(&["Prtry", "Cd"]).map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use super::super::RowResult;
+
+    const CAMT_053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG-1</MsgId></GrpHdr>
+    <Stmt>
+      <Id>STMT-2026-03</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Bal><Tp><CdOrPrtry><Cd>PRCD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">900.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2026-03-01</Dt></Dt></Bal>
+      <Bal><Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">1037.50</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2026-03-02</Dt></Dt></Bal>
+      <Ntry>
+        <Amt Ccy="EUR">150.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2026-03-02</Dt></BookgDt><ValDt><DtTm>2026-03-03T00:00:00+01:00</DtTm></ValDt>
+        <AcctSvcrRef>BANK-1</AcctSvcrRef>
+        <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>RCDT</Cd><SubFmlyCd>ESCT</SubFmlyCd></Fmly></Domn></BkTxCd>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>INV-2026-0042</EndToEndId></Refs>
+          <RltdPties><Dbtr><Pty><Nm>Acme  GmbH</Nm></Pty></Dbtr><DbtrAcct><Id><IBAN>FR7630006000011234567890189</IBAN></Id></DbtrAcct></RltdPties>
+          <RmtInf><Ustrd>Invoice 42</Ustrd><Ustrd>March</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">12.50</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts>
+        <BookgDt><Dt>2026-03-02</Dt></BookgDt>
+        <NtryDtls>
+          <TxDtls><Amt Ccy="EUR">10.00</Amt><Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs><RltdPties><Cdtr><Nm>Fee A</Nm></Cdtr></RltdPties></TxDtls>
+          <TxDtls><Amt Ccy="EUR">2.50</Amt><RmtInf><Strd><CdtrRefInf><Ref>RF18539007547034</Ref></CdtrRefInf></Strd></RmtInf></TxDtls>
+        </NtryDtls>
+        <AddtlNtryInf>Bank charges</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn reads_balances_entries_and_batch_details() {
+        let mut rows = Vec::new();
+        let mut on_row = |row: RowResult| {
+            rows.push(row.unwrap_or_else(|e| panic!("{}", e.message)));
+            Ok(())
+        };
+        let mut sink = StatementSink::new(&mut on_row);
+        parse_camt(CAMT_053, &mut sink).unwrap_or_else(|e| panic!("{}", e));
+        let statements = sink.into_statements();
+
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.statement_id.as_deref(), Some("STMT-2026-03"));
+        assert_eq!(statement.account.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(statement.entry_count, 3);
+        let opening = statement.opening_balance.as_ref().unwrap_or_else(|| panic!("no opening balance"));
+        assert_eq!(opening.amount, BigDecimal::from_str("900.00").unwrap_or_else(|e| panic!("{}", e)));
+        assert_eq!(opening.date, NaiveDate::from_ymd_opt(2026, 3, 1));
+        assert!(statement.closing_balance.is_some());
+
+        let credit = &rows[0].fields;
+        assert_eq!(credit["reference"], "BANK-1");
+        assert_eq!(credit["amount"], 150.0);
+        assert_eq!(credit["value_date"], "2026-03-03");
+        assert_eq!(credit["counterparty"], "Acme GmbH");
+        assert_eq!(credit["counterparty_account"], "FR7630006000011234567890189");
+        assert_eq!(credit["end_to_end_id"], "INV-2026-0042");
+        assert_eq!(credit["description"], "Invoice 42 March");
+        assert_eq!(credit["transaction_code"], "PMNT/RCDT/ESCT");
+        assert_eq!(credit["status"], "BOOK");
+
+        assert_eq!(rows[1].fields["amount"], -10.0);
+        assert_eq!(rows[1].fields["counterparty"], "Fee A");
+        assert!(rows[1].fields["end_to_end_id"].is_null());
+        assert_eq!(rows[1].fields["description"], "Bank charges");
+        assert_eq!(rows[2].fields["amount"], -2.5);
+        assert_eq!(rows[2].fields["remittance"], "RF18539007547034");
+
+        let mut on_row = |_: RowResult| Ok(());
+        let mut sink = StatementSink::new(&mut on_row);
+        assert!(parse_camt("<Document><Other/></Document>", &mut sink).is_err());
+    }
+}
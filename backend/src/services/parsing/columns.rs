@@ -0,0 +1,416 @@
+//! Mapping parsed fields onto record columns
+//!
+//! Reconciliation records carry four typed columns: `external_id`, `amount`,
+//! `transaction_date` and `description`. Which field of a file feeds each one
+//! is inferred from the header names unless the ingestion job names the fields
+//! itself. Amounts and dates are read in the formats bank and ERP exports use.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::spreadsheet::excel_serial_date;
+
+/// Header names recognised for each column, in order of preference
+const AMOUNT_NAMES: &[&str] = &[
+    "amount", "transaction_amount", "amt", "value", "net_amount", "total", "sum", "betrag", "montant",
+    "importe",
+];
+const DEBIT_NAMES: &[&str] = &["debit", "debit_amount", "withdrawal", "withdrawals", "paid_out", "money_out"];
+const CREDIT_NAMES: &[&str] = &["credit", "credit_amount", "deposit", "deposits", "paid_in", "money_in"];
+const DATE_NAMES: &[&str] = &[
+    "transaction_date", "date", "txn_date", "trans_date", "posting_date", "booking_date", "value_date",
+    "posted", "datum",
+];
+const DESCRIPTION_NAMES: &[&str] = &[
+    "description", "desc", "narrative", "memo", "details", "particulars", "payee", "text",
+    "verwendungszweck",
+];
+const EXTERNAL_ID_NAMES: &[&str] = &[
+    "external_id", "transaction_id", "txn_id", "reference", "ref", "reference_number", "document_number",
+    "invoice_number", "id",
+];
+
+/// Date formats tried in order; day-first before month-first
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d", "%Y/%m/%d", "%Y%m%d", "%d.%m.%Y", "%d/%m/%Y", "%d-%m-%Y", "%m/%d/%Y", "%d.%m.%y",
+    "%d/%m/%y", "%d %b %Y", "%d-%b-%Y", "%d-%b-%y", "%b %d, %Y", "%B %d, %Y", "%d %B %Y",
+];
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%d/%m/%Y %H:%M", "%d.%m.%Y %H:%M"];
+
+/// Which source field feeds each record column
+///
+/// Set on an ingestion job as `source_config.column_mapping`; any column left
+/// out is inferred from the headers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    #[serde(default)]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub amount: Option<String>,
+    /// Money-out field, used with `credit` when there is no signed amount
+    #[serde(default)]
+    pub debit: Option<String>,
+    /// Money-in field, used with `debit` when there is no signed amount
+    #[serde(default)]
+    pub credit: Option<String>,
+    #[serde(default)]
+    pub transaction_date: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// chrono format for dates, when the built-in formats guess wrong
+    #[serde(default)]
+    pub date_format: Option<String>,
+    /// Decimal separator for text amounts ("," for most of Europe); guessed when absent
+    #[serde(default)]
+    pub decimal_separator: Option<char>,
+}
+
+/// The typed columns of one record
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MappedRecord {
+    pub external_id: Option<String>,
+    pub amount: Option<f64>,
+    pub transaction_date: Option<NaiveDate>,
+    pub description: Option<String>,
+}
+
+/// Header reduced to lowercase words joined by underscores
+fn normalize_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn find_header(headers: &[&str], names: &[&str]) -> Option<String> {
+    names.iter().find_map(|wanted| {
+        headers
+            .iter()
+            .find(|header| normalize_name(header) == *wanted)
+            .map(|header| header.to_string())
+    })
+}
+
+impl ColumnMapping {
+    /// Complete this mapping from the headers for any column it leaves unset
+    pub fn infer<'a>(&self, headers: impl IntoIterator<Item = &'a str>) -> Self {
+        let headers: Vec<&str> = headers.into_iter().collect();
+        let amount = self.amount.clone().or_else(|| find_header(&headers, AMOUNT_NAMES));
+        // Split debit/credit columns only matter when there's no signed amount
+        let (debit, credit) = if amount.is_none() {
+            (
+                self.debit.clone().or_else(|| find_header(&headers, DEBIT_NAMES)),
+                self.credit.clone().or_else(|| find_header(&headers, CREDIT_NAMES)),
+            )
+        } else {
+            (self.debit.clone(), self.credit.clone())
+        };
+        Self {
+            external_id: self.external_id.clone().or_else(|| find_header(&headers, EXTERNAL_ID_NAMES)),
+            amount,
+            debit,
+            credit,
+            transaction_date: self.transaction_date.clone().or_else(|| find_header(&headers, DATE_NAMES)),
+            description: self.description.clone().or_else(|| find_header(&headers, DESCRIPTION_NAMES)),
+            date_format: self.date_format.clone(),
+            decimal_separator: self.decimal_separator,
+        }
+    }
+
+    /// Read the typed columns from a row
+    ///
+    /// Missing or empty fields leave a column empty; a value that is present
+    /// but can't be read as an amount or date is an error.
+    pub fn map_row(&self, fields: &Map<String, Value>) -> Result<MappedRecord, String> {
+        let field = |name: &Option<String>| {
+            name.as_ref()
+                .and_then(|name| fields.get(name))
+                .filter(|value| !is_blank(value))
+        };
+
+        let amount_of = |kind: &str, column: &Option<String>| match field(column) {
+            Some(value) => amount_value(value, self.decimal_separator)
+                .map(Some)
+                .ok_or_else(|| invalid(kind, column, value)),
+            None => Ok(None),
+        };
+        let amount = match amount_of("amount", &self.amount)? {
+            Some(amount) => Some(amount),
+            None => {
+                let debit = amount_of("debit", &self.debit)?;
+                let credit = amount_of("credit", &self.credit)?;
+                match (debit, credit) {
+                    (None, None) => None,
+                    (debit, credit) => Some(credit.unwrap_or(0.0) - debit.unwrap_or(0.0).abs()),
+                }
+            }
+        };
+
+        let transaction_date = match field(&self.transaction_date) {
+            Some(value) => Some(
+                date_value(value, self.date_format.as_deref())
+                    .ok_or_else(|| invalid("date", &self.transaction_date, value))?,
+            ),
+            None => None,
+        };
+
+        Ok(MappedRecord {
+            external_id: field(&self.external_id).and_then(value_text),
+            amount,
+            transaction_date,
+            description: field(&self.description).and_then(value_text),
+        })
+    }
+}
+
+pub(super) fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+pub(super) fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+fn invalid(kind: &str, column: &Option<String>, value: &Value) -> String {
+    format!(
+        "Invalid {} in column '{}': {}",
+        kind,
+        column.as_deref().unwrap_or_default(),
+        value
+    )
+}
+
+pub(super) fn amount_value(value: &Value, decimal_separator: Option<char>) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => parse_amount_with(text, decimal_separator),
+        _ => None,
+    }
+}
+
+/// Dates may arrive as text or, from spreadsheets, as Excel serial numbers
+pub(super) fn date_value(value: &Value, format: Option<&str>) -> Option<NaiveDate> {
+    let serial = match value {
+        Value::Number(number) => number.as_f64(),
+        // Five digits is a serial number; eight would be yyyymmdd
+        Value::String(text) if text.trim().len() == 5 && text.trim().bytes().all(|b| b.is_ascii_digit()) => {
+            text.trim().parse().ok()
+        }
+        _ => None,
+    };
+    match serial {
+        Some(serial) if serial < 1_000_000.0 => excel_serial_date(serial, false).map(|datetime| datetime.date()),
+        _ => parse_date(&value_text(value)?, format),
+    }
+}
+
+/// Read an amount as written in bank and ERP exports
+///
+/// Accepts currency symbols and codes, thousands separators (`,` `.` `'` or
+/// spaces), comma decimals, parentheses or a trailing minus for negatives and
+/// trailing `CR`/`DR` markers.
+pub fn parse_amount(text: &str) -> Option<f64> {
+    parse_amount_with(text, None)
+}
+
+/// `parse_amount` with a known decimal separator instead of a guessed one
+pub fn parse_amount_with(text: &str, decimal_separator: Option<char>) -> Option<f64> {
+    let mut text = text.trim().to_string();
+    let mut negative = false;
+
+    let upper = text.to_ascii_uppercase();
+    if upper.ends_with("DR") {
+        negative = true;
+        text.truncate(text.len() - 2);
+    } else if upper.ends_with("CR") {
+        text.truncate(text.len() - 2);
+    }
+    let text = text.trim();
+    let text = if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        negative = !negative;
+        inner
+    } else {
+        text
+    };
+
+    // Keep digits, separators and signs; drops currency symbols, codes and spaces
+    let mut cleaned: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+'))
+        .collect();
+    if cleaned.ends_with('-') {
+        negative = !negative;
+        cleaned.pop();
+    }
+    if let Some(rest) = cleaned.strip_prefix('-') {
+        negative = !negative;
+        cleaned = rest.to_string();
+    } else if let Some(rest) = cleaned.strip_prefix('+') {
+        cleaned = rest.to_string();
+    }
+    if !cleaned.chars().any(|c| c.is_ascii_digit()) || cleaned.contains(['-', '+']) {
+        return None;
+    }
+
+    let normalized = match decimal_separator {
+        Some(decimal) => cleaned
+            .chars()
+            .filter_map(|c| match c {
+                '0'..='9' => Some(c),
+                c if c == decimal => Some('.'),
+                _ => None,
+            })
+            .collect(),
+        None => normalize_separators(&cleaned)?,
+    };
+    let value: f64 = normalized.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Rewrite a digit string with mixed separators as `1234.56`
+fn normalize_separators(digits: &str) -> Option<String> {
+    let commas = digits.matches(',').count();
+    let dots = digits.matches('.').count();
+    let decimal = match (commas, dots) {
+        (0, 0) => None,
+        // Both present: whichever comes last is the decimal point
+        (_, _) if commas > 0 && dots > 0 => {
+            let last_comma = digits.rfind(',')?;
+            let last_dot = digits.rfind('.')?;
+            Some(if last_comma > last_dot { ',' } else { '.' })
+        }
+        // A lone separator followed by exactly three digits is a thousands separator
+        (1, 0) | (0, 1) => {
+            let separator = if commas == 1 { ',' } else { '.' };
+            let (whole, fraction) = digits.split_once(separator)?;
+            if fraction.len() == 3 && !whole.is_empty() && whole != "0" {
+                None
+            } else {
+                Some(separator)
+            }
+        }
+        // Repeated separators are thousands separators
+        _ => None,
+    };
+
+    let mut out = String::with_capacity(digits.len());
+    for c in digits.chars() {
+        match c {
+            '0'..='9' => out.push(c),
+            c if Some(c) == decimal => out.push('.'),
+            _ => {}
+        }
+    }
+    Some(out)
+}
+
+/// Read a date in `format`, or in any of the common formats when none is given
+pub fn parse_date(text: &str, format: Option<&str>) -> Option<NaiveDate> {
+    let text = text.trim();
+    if let Some(format) = format {
+        return NaiveDate::parse_from_str(text, format)
+            .ok()
+            .or_else(|| NaiveDateTime::parse_from_str(text, format).ok().map(|dt| dt.date()));
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Some(datetime.date_naive());
+    }
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        .or_else(|| {
+            DATETIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+                .map(|datetime| datetime.date())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn parses_amount_formats() {
+        assert_eq!(parse_amount("1,234.56"), Some(1234.56));
+        assert_eq!(parse_amount("1.234,56"), Some(1234.56));
+        assert_eq!(parse_amount("-12,50"), Some(-12.5));
+        assert_eq!(parse_amount("(1,000.00)"), Some(-1000.0));
+        assert_eq!(parse_amount("$ 1 234"), Some(1234.0));
+        assert_eq!(parse_amount("EUR 1'234.50"), Some(1234.5));
+        assert_eq!(parse_amount("1,234"), Some(1234.0));
+        assert_eq!(parse_amount("0,125"), Some(0.125));
+        assert_eq!(parse_amount("45.00-"), Some(-45.0));
+        assert_eq!(parse_amount("150.00 DR"), Some(-150.0));
+        assert_eq!(parse_amount("n/a"), None);
+        assert_eq!(parse_amount("1-2"), None);
+        assert_eq!(parse_amount_with("1,234", Some(',')), Some(1.234));
+        assert_eq!(parse_amount_with("1.234.567", Some(',')), Some(1234567.0));
+    }
+
+    #[test]
+    fn parses_date_formats() {
+        assert_eq!(parse_date("2026-02-01", None), date(2026, 2, 1));
+        assert_eq!(parse_date("01.02.2026", None), date(2026, 2, 1));
+        assert_eq!(parse_date("01/02/2026", None), date(2026, 2, 1));
+        assert_eq!(parse_date("12/31/2026", None), date(2026, 12, 31));
+        assert_eq!(parse_date("1 Feb 2026", None), date(2026, 2, 1));
+        assert_eq!(parse_date("2026-02-01T10:00:00Z", None), date(2026, 2, 1));
+        assert_eq!(parse_date("02/01/2026", Some("%m/%d/%Y")), date(2026, 2, 1));
+        assert_eq!(parse_date("soon", None), None);
+        assert_eq!(date_value(&json!(45323), None), date(2024, 2, 1));
+        assert_eq!(date_value(&json!("20260201"), None), date(2026, 2, 1));
+    }
+
+    #[test]
+    fn infers_columns_and_maps_rows() {
+        let mapping = ColumnMapping::default().infer(["Booking Date", "Paid Out", "Paid In", "Memo", "Ref"]);
+        assert_eq!(mapping.transaction_date.as_deref(), Some("Booking Date"));
+        assert_eq!(mapping.debit.as_deref(), Some("Paid Out"));
+        assert_eq!(mapping.credit.as_deref(), Some("Paid In"));
+        assert_eq!(mapping.external_id.as_deref(), Some("Ref"));
+
+        let fields = json!({"Booking Date": "03/02/2026", "Paid Out": "19.99", "Paid In": null, "Memo": "Coffee", "Ref": "T-1"});
+        let record = mapping
+            .map_row(fields.as_object().unwrap_or_else(|| panic!("not an object")))
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(record.amount, Some(-19.99));
+        assert_eq!(record.transaction_date, date(2026, 2, 3));
+        assert_eq!(record.description.as_deref(), Some("Coffee"));
+        assert_eq!(record.external_id.as_deref(), Some("T-1"));
+    }
+
+    #[test]
+    fn explicit_mapping_wins_and_bad_values_are_errors() {
+        let explicit = ColumnMapping {
+            amount: Some("Value EUR".to_string()),
+            ..Default::default()
+        };
+        let mapping = explicit.infer(["amount", "Value EUR", "date"]);
+        assert_eq!(mapping.amount.as_deref(), Some("Value EUR"));
+
+        let fields = json!({"amount": "1", "Value EUR": "abc", "date": "2026-01-01"});
+        let error = mapping
+            .map_row(fields.as_object().unwrap_or_else(|| panic!("not an object")))
+            .err()
+            .unwrap_or_else(|| panic!("expected an error"));
+        assert!(error.contains("Value EUR"));
+    }
+}
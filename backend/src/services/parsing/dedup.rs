@@ -0,0 +1,213 @@
+//! Duplicate keys for ingested records
+//!
+//! A `DuplicateSpec` reduces each mapped record to a key. `Key` mode hashes a
+//! natural key, the values of the configured fields; `Fingerprint` mode
+//! hashes the amount, date, normalized description and external id, so that
+//! the same transaction is recognised when its reference or the spacing and
+//! case of its description differ between exports. Records whose key was
+//! already seen, in the same file or in an earlier ingestion within the
+//! spec's scope, are duplicates.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use super::columns::{value_text, MappedRecord};
+
+/// Separates key parts so that ("ab", "c") and ("a", "bc") differ
+const PART_SEPARATOR: char = '\u{1f}';
+
+/// How records are compared for duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateMode {
+    /// No record-level detection
+    #[default]
+    Off,
+    /// Exact values of the configured fields
+    Key,
+    /// Amount, date, normalized description and external id
+    Fingerprint,
+}
+
+/// Which earlier records a record is compared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateScope {
+    /// Records of every ingestion in the project
+    #[default]
+    Project,
+    /// Records ingested into the same data source
+    DataSource,
+    /// Only the other rows of the same file
+    Job,
+}
+
+/// Record-level duplicate detection, from a job's `source_config.duplicates`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateSpec {
+    #[serde(default)]
+    pub mode: DuplicateMode,
+    /// Natural key fields: record columns (`external_id`, `amount`,
+    /// `transaction_date`, `description`) or fields of the transformed row
+    #[serde(default = "default_key_fields")]
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub scope: DuplicateScope,
+}
+
+fn default_key_fields() -> Vec<String> {
+    vec!["external_id".to_string()]
+}
+
+impl Default for DuplicateSpec {
+    fn default() -> Self {
+        Self {
+            mode: DuplicateMode::Off,
+            fields: default_key_fields(),
+            scope: DuplicateScope::Project,
+        }
+    }
+}
+
+impl DuplicateSpec {
+    pub fn is_enabled(&self) -> bool {
+        self.mode != DuplicateMode::Off
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode == DuplicateMode::Key && self.fields.iter().all(|field| field.trim().is_empty()) {
+            return Err("a duplicate key needs at least one field".to_string());
+        }
+        Ok(())
+    }
+
+    /// Hex SHA-256 key of a record, or `None` when there is nothing to
+    /// compare it by (every key field empty, or no amount and no date)
+    pub fn key(&self, record: &MappedRecord, fields: &Map<String, Value>) -> Option<String> {
+        let parts = match self.mode {
+            DuplicateMode::Off => return None,
+            DuplicateMode::Key => {
+                let parts: Vec<String> = self
+                    .fields
+                    .iter()
+                    .map(|field| key_part(field, record, fields).unwrap_or_default())
+                    .collect();
+                if parts.iter().all(String::is_empty) {
+                    return None;
+                }
+                parts
+            }
+            DuplicateMode::Fingerprint => {
+                if record.amount.is_none() && record.transaction_date.is_none() {
+                    return None;
+                }
+                vec![
+                    record.amount.map(|amount| format!("{:.2}", amount)).unwrap_or_default(),
+                    record.transaction_date.map(|date| date.to_string()).unwrap_or_default(),
+                    record.description.as_deref().map(normalize_text).unwrap_or_default(),
+                    record.external_id.as_deref().map(normalize_text).unwrap_or_default(),
+                ]
+            }
+        };
+
+        let mut hasher = Sha256::new();
+        for part in &parts {
+            hasher.update(part.as_bytes());
+            hasher.update(PART_SEPARATOR.to_string().as_bytes());
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Value of one natural key field, as text
+fn key_part(field: &str, record: &MappedRecord, fields: &Map<String, Value>) -> Option<String> {
+    match field {
+        "external_id" => record.external_id.as_ref().map(|id| id.trim().to_string()),
+        "amount" => record.amount.map(|amount| format!("{:.2}", amount)),
+        "transaction_date" => record.transaction_date.map(|date| date.to_string()),
+        "description" => record.description.as_ref().map(|text| text.trim().to_string()),
+        _ => fields.get(field).and_then(value_text),
+    }
+}
+
+/// Lowercase letters and digits, with every other run of characters as one space
+fn normalize_text(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    fn record(external_id: Option<&str>, amount: Option<f64>, description: Option<&str>) -> MappedRecord {
+        MappedRecord {
+            external_id: external_id.map(str::to_string),
+            amount,
+            transaction_date: NaiveDate::from_ymd_opt(2026, 3, 1),
+            description: description.map(str::to_string),
+        }
+    }
+
+    fn fields(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => panic!("fields must be an object"),
+        }
+    }
+
+    #[test]
+    fn natural_key_uses_record_columns_and_row_fields() {
+        let spec: DuplicateSpec = serde_json::from_value(json!({"mode": "key", "fields": ["external_id", "account"]}))
+            .unwrap_or_else(|e| panic!("spec: {}", e));
+        assert_eq!(spec.scope, DuplicateScope::Project);
+
+        let first = spec.key(&record(Some("INV-1"), Some(10.0), None), &fields(json!({"account": "001"})));
+        let same = spec.key(&record(Some(" INV-1 "), Some(99.0), None), &fields(json!({"account": "001"})));
+        let other = spec.key(&record(Some("INV-1"), Some(10.0), None), &fields(json!({"account": "002"})));
+        assert!(first.is_some());
+        assert_eq!(first, same);
+        assert_ne!(first, other);
+        assert_eq!(first.as_ref().map(String::len), Some(64));
+
+        // Nothing to compare by
+        assert_eq!(spec.key(&record(None, Some(10.0), None), &fields(json!({}))), None);
+        assert!(DuplicateSpec {
+            mode: DuplicateMode::Key,
+            fields: Vec::new(),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert_eq!(DuplicateSpec::default().key(&record(Some("INV-1"), None, None), &fields(json!({}))), None);
+    }
+
+    #[test]
+    fn fingerprint_ignores_case_spacing_and_rounding() {
+        let spec = DuplicateSpec {
+            mode: DuplicateMode::Fingerprint,
+            ..Default::default()
+        };
+        let empty = Map::new();
+        let first = spec.key(&record(None, Some(12.5), Some("ACME Corp. - Invoice 42")), &empty);
+        let same = spec.key(&record(None, Some(12.500_001), Some("acme corp invoice  42")), &empty);
+        let other_amount = spec.key(&record(None, Some(12.51), Some("ACME Corp. - Invoice 42")), &empty);
+        let other_text = spec.key(&record(None, Some(12.5), Some("ACME Corp. - Invoice 43")), &empty);
+        assert!(first.is_some());
+        assert_eq!(first, same);
+        assert_ne!(first, other_amount);
+        assert_ne!(first, other_text);
+
+        let undated = MappedRecord {
+            transaction_date: None,
+            ..record(Some("INV-1"), None, Some("fee"))
+        };
+        assert_eq!(spec.key(&undated, &empty), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! CSV, TSV and other delimiter-separated files
+
+use std::io::Read;
+
+use serde_json::{Map, Value};
+
+use crate::errors::{AppError, AppResult};
+
+use super::{ParsedRow, RowError, RowResult};
+
+/// Delimiters tried when a file doesn't say which one it uses
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+/// Lines of the sample looked at when detecting the delimiter
+const DETECTION_LINES: usize = 50;
+
+/// Pick the delimiter that splits the sample into the most consistent columns
+///
+/// Each candidate is scored by how many sampled lines have the same number of
+/// fields as the header (more than one field), then by that field count.
+/// Falls back to a comma.
+pub fn detect_delimiter(sample: &str) -> u8 {
+    let mut best = (b',', 0usize, 0usize);
+    for delimiter in CANDIDATE_DELIMITERS {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(sample.as_bytes());
+        let widths: Vec<usize> = reader
+            .records()
+            .take(DETECTION_LINES)
+            .filter_map(Result::ok)
+            .map(|record| record.len())
+            .collect();
+        let Some(&header_width) = widths.first() else { continue };
+        if header_width < 2 {
+            continue;
+        }
+        let consistent = widths.iter().filter(|w| **w == header_width).count();
+        if (consistent, header_width) > (best.1, best.2) {
+            best = (delimiter, consistent, header_width);
+        }
+    }
+    best.0
+}
+
+/// Header names made usable as keys: blanks get a positional name and
+/// repeated names a numeric suffix
+pub fn normalize_headers<'a>(raw: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = std::collections::HashMap::new();
+    raw.into_iter()
+        .enumerate()
+        .map(|(position, name)| {
+            let name = name.trim();
+            let base = if name.is_empty() {
+                format!("column_{}", position + 1)
+            } else {
+                name.to_string()
+            };
+            let count = seen.entry(base.clone()).or_insert(0usize);
+            *count += 1;
+            if *count == 1 {
+                base
+            } else {
+                format!("{}_{}", base, count)
+            }
+        })
+        .collect()
+}
+
+/// Read a delimited file with a header row, calling `on_row` for every data row
+///
+/// Rows with the wrong number of fields are reported as row errors; I/O errors
+/// end the parse.
+pub fn parse_delimited<R: Read>(
+    reader: R,
+    delimiter: u8,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<()> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(true)
+        .flexible(true)
+        .trim(csv::Trim::Headers)
+        .from_reader(reader);
+    let headers = normalize_headers(
+        reader
+            .headers()
+            .map_err(|e| AppError::Validation(format!("Failed to read header row: {}", e)))?
+            .iter(),
+    );
+    if headers.is_empty() {
+        return Err(AppError::Validation("File has no header row".to_string()));
+    }
+
+    for (position, record) in reader.records().enumerate() {
+        let index = position + 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) if e.is_io_error() => {
+                return Err(AppError::Internal(format!("Failed to read file: {}", e)));
+            }
+            Err(e) => {
+                on_row(Err(RowError {
+                    index,
+                    message: e.to_string(),
+                    raw: None,
+                }))?;
+                continue;
+            }
+        };
+        // Blank lines carry no data
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let fields: Map<String, Value> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(name, value)| (name.clone(), cell_value(value)))
+            .collect();
+        if record.len() != headers.len() {
+            on_row(Err(RowError {
+                index,
+                message: format!("Expected {} fields, found {}", headers.len(), record.len()),
+                raw: Some(Value::Object(fields)),
+            }))?;
+            continue;
+        }
+        on_row(Ok(ParsedRow { index, fields }))?;
+    }
+    Ok(())
+}
+
+/// Empty cells are null; everything else stays text for the column mapping to interpret
+fn cell_value(value: &str) -> Value {
+    let value = value.trim();
+    if value.is_empty() {
+        Value::Null
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, delimiter: u8) -> Vec<RowResult> {
+        let mut rows = Vec::new();
+        parse_delimited(text.as_bytes(), delimiter, &mut |row| {
+            rows.push(row);
+            Ok(())
+        })
+        .unwrap_or_else(|e| panic!("parse failed: {}", e));
+        rows
+    }
+
+    #[test]
+    fn detects_common_delimiters() {
+        assert_eq!(detect_delimiter("a,b,c\n1,2,3\n"), b',');
+        assert_eq!(detect_delimiter("date;amount;text\n01.02.2026;1,50;\"x;y\"\n"), b';');
+        assert_eq!(detect_delimiter("a\tb\n1\t2\n"), b'\t');
+        assert_eq!(detect_delimiter("a|b|c\n1|2|3\n"), b'|');
+        assert_eq!(detect_delimiter("single column\nvalue\n"), b',');
+    }
+
+    #[test]
+    fn reports_ragged_rows_and_skips_blank_lines() {
+        let rows = parse("ref,amount,,ref\nA,1.00,x,B\n\nC,2.00\n", b',');
+        assert_eq!(rows.len(), 2);
+        let first = rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(first.index, 1);
+        assert_eq!(first.fields["ref"], "A");
+        assert_eq!(first.fields["column_3"], "x");
+        assert_eq!(first.fields["ref_2"], "B");
+        let error = rows[1].as_ref().err().unwrap_or_else(|| panic!("expected a row error"));
+        assert_eq!(error.index, 2);
+        assert!(error.message.contains("Expected 4 fields"));
+    }
+}
@@ -0,0 +1,218 @@
+//! Text encoding detection and transcoding to UTF-8
+//!
+//! Uploaded files come from spreadsheets and bank portals in whatever encoding
+//! the exporting system used. A byte-order mark is trusted when present;
+//! otherwise UTF-16 is recognised by its NUL bytes, valid UTF-8 is taken as
+//! UTF-8, and anything else is read as Windows-1252 (a superset of Latin-1).
+
+use std::io::{self, Read};
+
+/// Bytes inspected when guessing an encoding
+pub const SNIFF_BYTES: usize = 64 * 1024;
+
+/// Encodings an uploaded text file can be read as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+            TextEncoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(TextEncoding::Utf8),
+            "utf-16le" | "utf-16" => Some(TextEncoding::Utf16Le),
+            "utf-16be" => Some(TextEncoding::Utf16Be),
+            "windows-1252" | "cp1252" | "latin-1" | "latin1" | "iso-8859-1" => {
+                Some(TextEncoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Guess the encoding of a file from its first bytes
+///
+/// Returns the encoding and the length of the byte-order mark to skip.
+pub fn detect_encoding(sample: &[u8]) -> (TextEncoding, usize) {
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return (TextEncoding::Utf8, 3);
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return (TextEncoding::Utf16Le, 2);
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return (TextEncoding::Utf16Be, 2);
+    }
+
+    // ASCII text in UTF-16 has a NUL in every other byte
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        let nul_odd = sample.chunks_exact(2).filter(|p| p[1] == 0 && p[0] != 0).count();
+        let nul_even = sample.chunks_exact(2).filter(|p| p[0] == 0 && p[1] != 0).count();
+        if nul_odd * 2 > pairs {
+            return (TextEncoding::Utf16Le, 0);
+        }
+        if nul_even * 2 > pairs {
+            return (TextEncoding::Utf16Be, 0);
+        }
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => (TextEncoding::Utf8, 0),
+        // The sample may end part-way through a character
+        Err(e) if e.error_len().is_none() => (TextEncoding::Utf8, 0),
+        Err(_) => (TextEncoding::Windows1252, 0),
+    }
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F; the rest match Latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+];
+
+fn windows_1252_char(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+/// Reader that turns another reader's bytes in `encoding` into UTF-8
+///
+/// Invalid sequences become U+FFFD rather than failing the whole file.
+pub struct DecodingReader<R> {
+    inner: R,
+    encoding: TextEncoding,
+    /// Undecoded input carried over between reads (half a UTF-16 unit or surrogate pair)
+    pending: Vec<u8>,
+    /// Decoded UTF-8 not yet handed out
+    output: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(inner: R, encoding: TextEncoding) -> Self {
+        Self {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            output: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = [0u8; 8192];
+        let read = self.inner.read(&mut chunk)?;
+        if read == 0 {
+            self.eof = true;
+        }
+        self.pending.extend_from_slice(&chunk[..read]);
+
+        let decoded = match self.encoding {
+            // `read` passes UTF-8 straight through, so this is only a fallback
+            TextEncoding::Utf8 => String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned(),
+            TextEncoding::Windows1252 => {
+                let text: String = self.pending.iter().map(|b| windows_1252_char(*b)).collect();
+                self.pending.clear();
+                text
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let little_endian = self.encoding == TextEncoding::Utf16Le;
+                let mut units: Vec<u16> = self
+                    .pending
+                    .chunks_exact(2)
+                    .map(|p| {
+                        if little_endian {
+                            u16::from_le_bytes([p[0], p[1]])
+                        } else {
+                            u16::from_be_bytes([p[0], p[1]])
+                        }
+                    })
+                    .collect();
+                let mut consumed = units.len() * 2;
+                // Keep a trailing high surrogate until its pair arrives
+                if !self.eof && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+                    units.pop();
+                    consumed -= 2;
+                }
+                self.pending.drain(..consumed);
+                if self.eof {
+                    // A dangling odd byte can't be decoded
+                    self.pending.clear();
+                }
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+        };
+        self.output = decoded.into_bytes();
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.encoding == TextEncoding::Utf8 {
+            return self.inner.read(buf);
+        }
+        while self.position >= self.output.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let available = &self.output[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], encoding: TextEncoding) -> String {
+        let mut text = String::new();
+        DecodingReader::new(bytes, encoding)
+            .read_to_string(&mut text)
+            .unwrap_or_else(|e| panic!("decode failed: {}", e));
+        text
+    }
+
+    #[test]
+    fn detects_bom_utf16_and_legacy_encodings() {
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFa,b"), (TextEncoding::Utf8, 3));
+        assert_eq!(detect_encoding(b"\xFF\xFEa\0"), (TextEncoding::Utf16Le, 2));
+        assert_eq!(detect_encoding(b"a\0,\0b\0"), (TextEncoding::Utf16Le, 0));
+        assert_eq!(detect_encoding("Café,10".as_bytes()), (TextEncoding::Utf8, 0));
+        assert_eq!(detect_encoding(b"Caf\xE9,10"), (TextEncoding::Windows1252, 0));
+    }
+
+    #[test]
+    fn transcodes_to_utf8() {
+        assert_eq!(decode(b"Caf\xE9 \x80", TextEncoding::Windows1252), "Café €");
+        let utf16: Vec<u8> = "Zoë 😀".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode(&utf16, TextEncoding::Utf16Le), "Zoë 😀");
+    }
+}
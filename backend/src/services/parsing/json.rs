@@ -0,0 +1,151 @@
+//! JSON array and newline-delimited JSON files
+//!
+//! A JSON file holds an array of objects, read one element at a time so the
+//! whole array is never in memory. NDJSON holds one object per line.
+
+use std::fmt;
+use std::io::{BufRead, Read};
+
+use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+
+use crate::errors::{AppError, AppResult};
+
+use super::{ParsedRow, RowError, RowResult};
+
+fn object_row(index: usize, value: Value) -> RowResult {
+    match value {
+        Value::Object(fields) => Ok(ParsedRow { index, fields }),
+        other => Err(RowError {
+            index,
+            message: "Expected a JSON object".to_string(),
+            raw: Some(other),
+        }),
+    }
+}
+
+/// Read a JSON array of objects, calling `on_row` for every element
+pub fn parse_json_array<R: Read>(
+    reader: R,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<()> {
+    let mut deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(reader));
+    let mut callback_error = None;
+    let outcome = deserializer.deserialize_seq(ArrayVisitor {
+        on_row,
+        callback_error: &mut callback_error,
+    });
+    if let Some(error) = callback_error {
+        return Err(error);
+    }
+    outcome.map_err(|e| AppError::Validation(format!("Invalid JSON file: {}", e)))?;
+    deserializer
+        .end()
+        .map_err(|e| AppError::Validation(format!("Unexpected data after JSON array: {}", e)))
+}
+
+/// Hands each array element to the row callback as it is deserialized
+struct ArrayVisitor<'a> {
+    on_row: &'a mut dyn FnMut(RowResult) -> AppResult<()>,
+    /// Set when the callback fails, so the failure isn't reported as bad JSON
+    callback_error: &'a mut Option<AppError>,
+}
+
+impl<'de> Visitor<'de> for ArrayVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        while let Some(value) = seq.next_element_seed(ElementSeed)? {
+            index += 1;
+            if let Err(e) = (self.on_row)(object_row(index, value)) {
+                *self.callback_error = Some(e);
+                return Err(serde::de::Error::custom("row handler failed"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes one array element as a `Value`
+struct ElementSeed;
+
+impl<'de> DeserializeSeed<'de> for ElementSeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        serde::Deserialize::deserialize(deserializer)
+    }
+}
+
+/// Read newline-delimited JSON, calling `on_row` for every non-blank line
+pub fn parse_ndjson<R: BufRead>(
+    reader: R,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<()> {
+    let mut index = 0;
+    for line in reader.lines() {
+        let line = line.map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        index += 1;
+        let row = match serde_json::from_str::<Value>(line) {
+            Ok(value) => object_row(index, value),
+            Err(e) => Err(RowError {
+                index,
+                message: format!("Invalid JSON: {}", e),
+                raw: Some(Value::String(line.to_string())),
+            }),
+        };
+        on_row(row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(parse: impl FnOnce(&mut dyn FnMut(RowResult) -> AppResult<()>) -> AppResult<()>) -> Vec<RowResult> {
+        let mut rows = Vec::new();
+        parse(&mut |row| {
+            rows.push(row);
+            Ok(())
+        })
+        .unwrap_or_else(|e| panic!("parse failed: {}", e));
+        rows
+    }
+
+    #[test]
+    fn streams_array_elements_and_flags_non_objects() {
+        let text = r#"[{"ref": "A", "amount": 1.5}, 42, {"ref": "B"}]"#;
+        let rows = collect(|on_row| parse_json_array(text.as_bytes(), on_row));
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+        let third = rows[2].as_ref().unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(third.index, 3);
+        assert_eq!(third.fields["ref"], "B");
+    }
+
+    #[test]
+    fn ndjson_reports_bad_lines_and_continues() {
+        let text = "{\"ref\": \"A\"}\n\nnot json\n{\"ref\": \"C\"}\n";
+        let rows = collect(|on_row| parse_ndjson(text.as_bytes(), on_row));
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].is_err());
+        assert!(rows[2].is_ok());
+    }
+
+    #[test]
+    fn rejects_non_array_documents() {
+        let result = parse_json_array(r#"{"ref": "A"}"#.as_bytes(), &mut |_| Ok(()));
+        assert!(result.is_err());
+    }
+}
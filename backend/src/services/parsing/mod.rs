@@ -0,0 +1,377 @@
+//! Parsers that turn uploaded files into rows of named fields
+//!
+//! - `encoding` - Encoding detection and transcoding to UTF-8
+//! - `delimited` - CSV, TSV and other delimiter-separated files
+//! - `json` - JSON arrays and newline-delimited JSON
+//! - `spreadsheet` - Sheet selection and header detection for workbooks
+//! - `xlsx` / `ods` - Excel and OpenDocument workbook readers
+//! - `statement` - Bank statement entries and balances, shared by:
+//!   - `camt` - ISO 20022 camt.052/053/054 XML
+//!   - `mt940` - SWIFT MT940
+//!   - `bai2` - BAI2 balance reporting
+//!   - `ofx` - OFX/QFX downloads
+//! - `xml` - Pull reader used by the XML-based formats
+//! - `transform` - Saved per-source steps run over each row before mapping
+//! - `columns` - Mapping parsed fields onto record columns
+//! - `schema` - Column types and value profiles, and drift between files
+//! - `dedup` - Natural keys and fingerprints for duplicate records
+//!
+//! Parsers stream: each row is handed to a callback as soon as it is read, so
+//! a file is never held in memory whole. Rows that can't be parsed are passed
+//! to the same callback as `RowError`s and parsing carries on.
+
+pub mod bai2;
+pub mod camt;
+pub mod columns;
+pub mod dedup;
+pub mod delimited;
+pub mod encoding;
+pub mod json;
+pub mod mt940;
+pub mod ods;
+pub mod ofx;
+pub mod schema;
+pub mod spreadsheet;
+pub mod statement;
+pub mod transform;
+pub mod xlsx;
+pub mod xml;
+
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::errors::{AppError, AppResult};
+
+pub use columns::{ColumnMapping, MappedRecord};
+pub use dedup::{DuplicateScope, DuplicateSpec};
+pub use encoding::{detect_encoding, DecodingReader, TextEncoding, SNIFF_BYTES};
+pub use schema::{DriftAction, DriftPolicy, SchemaChange, SchemaProfiler, SourceSchema};
+pub use statement::{Balance, StatementInfo};
+pub use transform::{TransformSpec, Transformer};
+
+/// One row read from a file
+#[derive(Debug, Clone)]
+pub struct ParsedRow {
+    /// 1-based position of the row: among the data rows of a text file, or
+    /// the sheet row number of a spreadsheet
+    pub index: usize,
+    pub fields: Map<String, Value>,
+}
+
+/// A row that couldn't be read
+#[derive(Debug, Clone)]
+pub struct RowError {
+    pub index: usize,
+    pub message: String,
+    /// Whatever could be recovered of the row, for the error report
+    pub raw: Option<Value>,
+}
+
+pub type RowResult = Result<ParsedRow, RowError>;
+
+/// Start of every zip archive, and so of XLSX and ODS workbooks
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+/// Start of OLE compound files such as legacy .xls workbooks
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+
+/// File formats the parsers understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Csv,
+    Tsv,
+    Json,
+    Ndjson,
+    Xlsx,
+    Ods,
+    Camt,
+    Mt940,
+    Bai2,
+    Ofx,
+}
+
+impl FileFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Csv => "csv",
+            FileFormat::Tsv => "tsv",
+            FileFormat::Json => "json",
+            FileFormat::Ndjson => "ndjson",
+            FileFormat::Xlsx => "xlsx",
+            FileFormat::Ods => "ods",
+            FileFormat::Camt => "camt",
+            FileFormat::Mt940 => "mt940",
+            FileFormat::Bai2 => "bai2",
+            FileFormat::Ofx => "ofx",
+        }
+    }
+
+    /// Whether rows come from `statement` rather than a table
+    pub fn is_statement(&self) -> bool {
+        matches!(self, FileFormat::Camt | FileFormat::Mt940 | FileFormat::Bai2 | FileFormat::Ofx)
+    }
+
+    /// Format implied by a file name's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(FileFormat::Csv),
+            "tsv" | "tab" => Some(FileFormat::Tsv),
+            "json" => Some(FileFormat::Json),
+            "ndjson" | "jsonl" => Some(FileFormat::Ndjson),
+            "xlsx" | "xlsm" => Some(FileFormat::Xlsx),
+            "ods" => Some(FileFormat::Ods),
+            "camt" => Some(FileFormat::Camt),
+            "sta" | "mt940" | "940" => Some(FileFormat::Mt940),
+            "bai" | "bai2" => Some(FileFormat::Bai2),
+            "ofx" | "qfx" => Some(FileFormat::Ofx),
+            _ => None,
+        }
+    }
+
+    /// Format guessed from the start of the decoded text
+    pub fn sniff(sample: &str) -> Self {
+        let start = sample.trim_start();
+        if start.starts_with("OFXHEADER") || start.contains("<OFX>") {
+            return FileFormat::Ofx;
+        }
+        // MT940 may start with a SWIFT block header such as "{1:F01..."
+        if start.starts_with(":20:") || start.starts_with("{1:") {
+            return FileFormat::Mt940;
+        }
+        if start.starts_with("01,") {
+            return FileFormat::Bai2;
+        }
+        match start.chars().next() {
+            Some('[') => FileFormat::Json,
+            Some('{') => FileFormat::Ndjson,
+            Some('<') => FileFormat::Camt,
+            _ => FileFormat::Csv,
+        }
+    }
+}
+
+/// Overrides for what would otherwise be detected from the file
+///
+/// Read from an ingestion job's `source_config`; every key is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParseOptions {
+    #[serde(default)]
+    pub format: Option<FileFormat>,
+    /// Single-character field delimiter for CSV files
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// Encoding name such as "utf-8", "utf-16le" or "windows-1252"
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Workbook sheet to read, by name or 1-based position
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// Sheet row number of the header, when detection picks the wrong row
+    #[serde(default)]
+    pub header_row: Option<usize>,
+}
+
+/// What a parse found out about the file
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseSummary {
+    pub format: FileFormat,
+    /// Text encoding; workbooks are always UTF-8 XML
+    pub encoding: Option<&'static str>,
+    pub delimiter: Option<char>,
+    pub sheet: Option<String>,
+    pub header_row: Option<usize>,
+    /// Accounts and balances of a bank statement file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub statements: Vec<StatementInfo>,
+}
+
+/// Reader that counts the bytes taken from the file, for progress reporting
+struct CountingReader<'a, R> {
+    inner: R,
+    count: &'a AtomicU64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Parse the file at `path`, calling `on_row` for every row
+///
+/// Format, encoding and delimiter are detected unless `options` sets them.
+/// `bytes_read` is advanced as the file is consumed. An error from `on_row`
+/// stops the parse and is returned.
+pub fn parse_file(
+    path: &Path,
+    options: &ParseOptions,
+    bytes_read: &AtomicU64,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<ParseSummary> {
+    let mut file = File::open(path)
+        .map_err(|e| AppError::Internal(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut sample = Vec::with_capacity(SNIFF_BYTES);
+    (&mut file)
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut sample)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    if sample.starts_with(OLE_MAGIC) {
+        return Err(AppError::Validation(
+            "Legacy .xls workbooks aren't supported; save the file as .xlsx".to_string(),
+        ));
+    }
+    let format = options.format.or_else(|| FileFormat::from_path(path));
+    if matches!(format, Some(FileFormat::Xlsx | FileFormat::Ods))
+        || (format.is_none() && sample.starts_with(ZIP_MAGIC))
+    {
+        return parse_workbook(file, format, options, bytes_read, on_row);
+    }
+
+    let (detected, bom_len) = detect_encoding(&sample);
+    let encoding = match options.encoding.as_deref() {
+        Some(name) => TextEncoding::parse(name)
+            .ok_or_else(|| AppError::Validation(format!("Unsupported encoding: {}", name)))?,
+        None => detected,
+    };
+    // A byte-order mark only belongs to the encoding it announced
+    let skip = if encoding == detected { bom_len } else { 0 };
+
+    let text_sample = decode_sample(&sample[skip..], encoding);
+    let format = format.unwrap_or_else(|| FileFormat::sniff(&text_sample));
+
+    let mut source = Cursor::new(sample);
+    source.set_position(skip as u64);
+    bytes_read.store(skip as u64, Ordering::Relaxed);
+    let reader = DecodingReader::new(
+        CountingReader {
+            inner: source.chain(file),
+            count: bytes_read,
+        },
+        encoding,
+    );
+
+    let delimiter = match format {
+        FileFormat::Csv => Some(match options.delimiter {
+            Some(delimiter) => u8::try_from(delimiter)
+                .ok()
+                .filter(u8::is_ascii)
+                .ok_or_else(|| AppError::Validation(format!("Unsupported delimiter: {:?}", delimiter)))?,
+            None => delimited::detect_delimiter(&text_sample),
+        }),
+        FileFormat::Tsv => Some(b'\t'),
+        _ => None,
+    };
+
+    let mut statements = Vec::new();
+    match (format, delimiter) {
+        (FileFormat::Json, _) => json::parse_json_array(reader, on_row)?,
+        (FileFormat::Ndjson, _) => json::parse_ndjson(BufReader::new(reader), on_row)?,
+        (format, _) if format.is_statement() => {
+            statements = statement::parse_statement(reader, format, on_row)?;
+        }
+        (_, delimiter) => delimited::parse_delimited(reader, delimiter.unwrap_or(b','), on_row)?,
+    }
+
+    Ok(ParseSummary {
+        format,
+        encoding: Some(encoding.as_str()),
+        delimiter: delimiter.map(char::from),
+        sheet: None,
+        header_row: None,
+        statements,
+    })
+}
+
+/// Parse an XLSX or ODS workbook; `format` is sniffed from the archive when unknown
+fn parse_workbook(
+    mut file: File,
+    format: Option<FileFormat>,
+    options: &ParseOptions,
+    bytes_read: &AtomicU64,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<ParseSummary> {
+    file.rewind()
+        .map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+    let format = match format {
+        Some(format) => format,
+        None => {
+            let mut archive = zip::ZipArchive::new(&mut file)
+                .map_err(|e| AppError::Validation(format!("File is not a readable workbook: {}", e)))?;
+            spreadsheet::workbook_format(&mut archive).ok_or_else(|| {
+                AppError::Validation("Zip archive is not an XLSX or ODS workbook".to_string())
+            })?
+        }
+    };
+    file.rewind()
+        .map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+    let (sheet, header_row) = spreadsheet::parse_spreadsheet(file, format, options, bytes_read, on_row)?;
+    Ok(ParseSummary {
+        format,
+        encoding: None,
+        delimiter: None,
+        sheet: Some(sheet),
+        header_row: Some(header_row),
+        statements: Vec::new(),
+    })
+}
+
+/// Decode the sniffed bytes for format and delimiter detection
+fn decode_sample(bytes: &[u8], encoding: TextEncoding) -> String {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    // Reading from a slice can't fail
+    let _ = DecodingReader::new(bytes, encoding).read_to_end(&mut decoded);
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_comes_from_extension_then_content() {
+        assert_eq!(FileFormat::from_path(Path::new("bank.CSV")), Some(FileFormat::Csv));
+        assert_eq!(FileFormat::from_path(Path::new("export.jsonl")), Some(FileFormat::Ndjson));
+        assert_eq!(FileFormat::from_path(Path::new("statement.txt")), None);
+        assert_eq!(FileFormat::from_path(Path::new("Q1.xlsx")), Some(FileFormat::Xlsx));
+        assert_eq!(FileFormat::sniff("  [{\"a\": 1}]"), FileFormat::Json);
+        assert_eq!(FileFormat::sniff("{\"a\": 1}\n{\"a\": 2}"), FileFormat::Ndjson);
+        assert_eq!(FileFormat::sniff("a;b\n1;2"), FileFormat::Csv);
+        assert_eq!(FileFormat::from_path(Path::new("day.sta")), Some(FileFormat::Mt940));
+        assert_eq!(FileFormat::sniff("\r\n:20:STARTUMS\r\n:25:1/2"), FileFormat::Mt940);
+        assert_eq!(FileFormat::sniff("01,BANK,ACME,260302,0600,1,,,2/"), FileFormat::Bai2);
+        assert_eq!(FileFormat::sniff("OFXHEADER:100\nDATA:OFXSGML"), FileFormat::Ofx);
+        assert_eq!(FileFormat::sniff("<?xml version=\"1.0\"?><Document>"), FileFormat::Camt);
+    }
+
+    #[test]
+    fn parses_a_latin1_semicolon_file_with_progress() {
+        let path = std::env::temp_dir().join(format!("parse-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"Datum;Betrag;Text\n01.02.2026;-12,50;Caf\xE9\n")
+            .unwrap_or_else(|e| panic!("write failed: {}", e));
+
+        let bytes_read = AtomicU64::new(0);
+        let mut rows = Vec::new();
+        let summary = parse_file(&path, &ParseOptions::default(), &bytes_read, &mut |row| {
+            rows.push(row);
+            Ok(())
+        });
+        let _ = std::fs::remove_file(&path);
+        let summary = summary.unwrap_or_else(|e| panic!("parse failed: {}", e));
+
+        assert_eq!(summary.format, FileFormat::Csv);
+        assert_eq!(summary.encoding, Some("windows-1252"));
+        assert_eq!(summary.delimiter, Some(';'));
+        assert_eq!(bytes_read.load(Ordering::Relaxed), 41);
+        let row = rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(row.fields["Text"], "Café");
+    }
+}
@@ -0,0 +1,361 @@
+//! SWIFT MT940 customer statements
+//!
+//! A file holds one or more messages of tagged fields (`:20:`, `:25:`,
+//! `:60F:` ...), optionally wrapped in SWIFT `{1:...}{4:...-}` blocks. Each
+//! `:61:` statement line is an entry; the `:86:` field after it carries the
+//! details, either as free text, in the German `?20`-subfield layout used by
+//! DATEV/DFÜ exports, or in the `/EREF/.../REMI/...` layout of Dutch and
+//! Belgian banks.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::errors::{AppError, AppResult};
+
+use super::statement::{clean_text, parse_decimal, parse_yymmdd, Balance, StatementEntry, StatementInfo, StatementSink};
+
+/// Codes of the slash-delimited `:86:` layout that this parser reads
+const SLASH_CODES: &[&str] = &[
+    "EREF", "REMI", "NAME", "IBAN", "BIC", "CNTP", "ORDP", "BENM", "TRCD", "PURP", "MARF", "CSID", "RTRN",
+    "ATOS", "ID",
+];
+
+/// Split the file into `(tag, value)` fields, continuation lines joined on
+fn fields(text: &str) -> Vec<(&str, String)> {
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some((tag, value)) = field_start(line) {
+            fields.push((tag, value.to_string()));
+        } else if line.starts_with('-') || line.starts_with('{') || line.starts_with('}') {
+            // End of a message or a SWIFT block header
+            if let Some(start) = line.find(":20:") {
+                if let Some((tag, value)) = field_start(&line[start..]) {
+                    fields.push((tag, value.to_string()));
+                }
+            }
+        } else if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+    fields
+}
+
+/// `(tag, rest)` when the line opens a field such as `:60F:`
+fn field_start(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let tag = &rest[..end];
+    let bytes = tag.as_bytes();
+    let valid = (2..=3).contains(&bytes.len())
+        && bytes[..2].iter().all(u8::is_ascii_digit)
+        && bytes[2..].iter().all(u8::is_ascii_uppercase);
+    valid.then(|| (tag, &rest[end + 1..]))
+}
+
+pub fn parse_mt940(text: &str, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    let fields = fields(text);
+    if !fields.iter().any(|(tag, _)| *tag == "61" || tag.starts_with("60")) {
+        return Err(AppError::Validation("File is not an MT940 statement".to_string()));
+    }
+
+    let mut pending: Option<StatementEntry> = None;
+    for (tag, value) in fields {
+        if tag != "86" {
+            if let Some(entry) = pending.take() {
+                sink.entry(entry)?;
+            }
+        }
+        match tag {
+            "20" => sink.begin(StatementInfo {
+                statement_id: clean_text(&value),
+                ..Default::default()
+            }),
+            // Statement number is a better id than the message reference
+            "28C" | "28" => sink.current().statement_id = clean_text(&value),
+            "25" => sink.current().account = clean_text(&value),
+            "60F" | "60M" => {
+                let Some((balance, currency)) = parse_balance(&value) else {
+                    sink.error("Unreadable opening balance", &value)?;
+                    continue;
+                };
+                let statement = sink.current();
+                statement.currency = Some(currency);
+                statement.opening_balance.get_or_insert(balance);
+            }
+            "62F" | "62M" => match parse_balance(&value) {
+                Some((balance, _)) => sink.current().closing_balance = Some(balance),
+                None => sink.error("Unreadable closing balance", &value)?,
+            },
+            "61" => match parse_statement_line(&value) {
+                Some(entry) => pending = Some(entry),
+                None => sink.error("Unreadable statement line", &value)?,
+            },
+            "86" => {
+                if let Some(entry) = pending.as_mut() {
+                    apply_details(entry, &value);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(entry) = pending {
+        sink.entry(entry)?;
+    }
+    Ok(())
+}
+
+/// `C260301EUR1234,56` into a signed balance and its currency
+fn parse_balance(value: &str) -> Option<(Balance, String)> {
+    let value = value.trim();
+    let sign = value.get(..1)?;
+    let date = parse_yymmdd(value.get(1..7)?)?;
+    let currency = value.get(7..10)?.to_string();
+    let amount = parse_decimal(value.get(10..)?)?;
+    let amount = match sign {
+        "D" => -amount,
+        "C" => amount,
+        _ => return None,
+    };
+    Some((Balance { amount, date: Some(date) }, currency))
+}
+
+/// A `:61:` line: value date, optional booking date, mark, amount, type and references
+fn parse_statement_line(value: &str) -> Option<StatementEntry> {
+    let (line, supplementary) = match value.split_once('\n') {
+        Some((line, rest)) => (line.trim(), clean_text(rest)),
+        None => (value.trim(), None),
+    };
+    let value_date = parse_yymmdd(line.get(..6)?)?;
+    let mut rest = &line[6..];
+
+    let booking_date = match rest.get(..4).filter(|date| date.bytes().all(|b| b.is_ascii_digit())) {
+        Some(date) => {
+            rest = &rest[4..];
+            booking_date(value_date, date)
+        }
+        None => None,
+    };
+
+    let (negative, mark_len) = if rest.starts_with("RC") {
+        (true, 2)
+    } else if rest.starts_with("RD") {
+        (false, 2)
+    } else if rest.starts_with('C') {
+        (false, 1)
+    } else if rest.starts_with('D') {
+        (true, 1)
+    } else {
+        return None;
+    };
+    rest = &rest[mark_len..];
+    // Third letter of the currency code, when the bank fills it in
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_len = rest
+        .find(|c: char| !c.is_ascii_digit() && c != ',')
+        .unwrap_or(rest.len());
+    let amount = parse_decimal(&rest[..amount_len])?;
+    rest = &rest[amount_len..];
+
+    let transaction_code = rest.get(..4).map(str::to_string);
+    rest = rest.get(4..).unwrap_or_default();
+    let (customer_reference, bank_reference) = match rest.split_once("//") {
+        Some((customer, bank)) => (customer, Some(bank)),
+        None => (rest, None),
+    };
+
+    Some(StatementEntry {
+        reference: bank_reference.and_then(clean_text),
+        booking_date: booking_date.or(Some(value_date)),
+        value_date: Some(value_date),
+        amount: if negative { -amount } else { amount },
+        customer_reference: clean_text(customer_reference).filter(|reference| reference != "NONREF"),
+        description: supplementary,
+        transaction_code,
+        ..Default::default()
+    })
+}
+
+/// Booking date from MMDD, in the year that puts it nearest the value date
+fn booking_date(value_date: NaiveDate, month_day: &str) -> Option<NaiveDate> {
+    let month = month_day.get(..2)?.parse().ok()?;
+    let day = month_day.get(2..)?.parse().ok()?;
+    [value_date.year(), value_date.year() - 1, value_date.year() + 1]
+        .into_iter()
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|date| (*date - value_date).num_days().abs())
+}
+
+/// Fill counterparty, references and remittance from a `:86:` field
+fn apply_details(entry: &mut StatementEntry, value: &str) {
+    // Lines are wrapped at a fixed width, often mid-word
+    let joined: String = value.lines().map(|line| line.trim_end_matches('\r')).collect();
+    let text = joined.trim();
+    if text.len() > 3 && text.as_bytes()[3] == b'?' && text[..3].bytes().all(|b| b.is_ascii_digit()) {
+        apply_subfields(entry, text);
+    } else if SLASH_CODES.iter().any(|code| text.contains(&format!("/{}/", code))) {
+        apply_slash_codes(entry, text);
+    } else if let Some(text) = clean_text(text) {
+        entry.remittance = Some(text);
+    }
+}
+
+/// The German `:86:` layout: a business code then `?NN` subfields
+fn apply_subfields(entry: &mut StatementEntry, text: &str) {
+    let mut purpose = String::new();
+    let mut name = String::new();
+    for part in text.split('?').skip(1) {
+        let (Some(code), Some(value)) = (part.get(..2), part.get(2..)) else { continue };
+        match code {
+            "00" if entry.description.is_none() => entry.description = clean_text(value),
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61" | "62" | "63" => {
+                purpose.push_str(value)
+            }
+            "31" => entry.counterparty_account = clean_text(value),
+            "32" | "33" => name.push_str(value),
+            _ => {}
+        }
+    }
+    entry.counterparty = clean_text(&name);
+    entry.transaction_code = entry.transaction_code.take().or_else(|| text.get(..3).map(str::to_string));
+
+    // SEPA purposes are tagged: EREF+ end-to-end id, SVWZ+ remittance text
+    let tags = ["EREF+", "KREF+", "MREF+", "CRED+", "DEBT+", "ABWA+", "ABWE+", "SVWZ+"];
+    let mut found: Vec<(usize, &str)> = tags
+        .iter()
+        .filter_map(|tag| purpose.find(tag).map(|position| (position, *tag)))
+        .collect();
+    if found.is_empty() {
+        entry.remittance = clean_text(&purpose);
+        return;
+    }
+    found.sort_unstable();
+    for (i, (position, tag)) in found.iter().enumerate() {
+        let end = found.get(i + 1).map(|(next, _)| *next).unwrap_or(purpose.len());
+        let value = &purpose[position + tag.len()..end];
+        match *tag {
+            "EREF+" => entry.end_to_end_id = clean_text(value).filter(|id| id != "NOTPROVIDED"),
+            "SVWZ+" => entry.remittance = clean_text(value),
+            _ => {}
+        }
+    }
+}
+
+/// The `/CODE/value` layout
+fn apply_slash_codes(entry: &mut StatementEntry, text: &str) {
+    let mut found: Vec<(usize, &str)> = SLASH_CODES
+        .iter()
+        .flat_map(|code| {
+            let marker = format!("/{}/", code);
+            text.match_indices(&marker)
+                .map(|(position, _)| (position, *code))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    found.sort_unstable();
+    // A code's value runs to the next code, so `/` inside values survives
+    found.dedup_by_key(|(position, _)| *position);
+    for (i, (position, code)) in found.iter().enumerate() {
+        let end = found.get(i + 1).map(|(next, _)| *next).unwrap_or(text.len());
+        let start = (position + code.len() + 2).min(end);
+        let value = &text[start..end];
+        match *code {
+            "EREF" => entry.end_to_end_id = clean_text(value).filter(|id| id != "NOTPROVIDED"),
+            "REMI" => {
+                // Structured remittance is "CUR/RF.../ref"; keep the reference
+                let value = value.strip_prefix("USTD//").unwrap_or(value);
+                let value = value.strip_prefix("STRD/CUR/").unwrap_or(value);
+                entry.remittance = clean_text(value.trim_end_matches('/'));
+            }
+            "NAME" => entry.counterparty = clean_text(value.trim_end_matches('/')),
+            "IBAN" => entry.counterparty_account = clean_text(value.trim_end_matches('/')),
+            // Counterparty as account/BIC/name/place
+            "CNTP" | "ORDP" | "BENM" => {
+                let mut parts = value.split('/');
+                if let Some(account) = parts.next().and_then(clean_text) {
+                    entry.counterparty_account = Some(account);
+                }
+                if let Some(name) = parts.nth(1).and_then(clean_text) {
+                    entry.counterparty = Some(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    if entry.counterparty.is_none() && entry.remittance.is_none() {
+        entry.remittance = clean_text(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    use super::super::RowResult;
+
+    const MT940: &str = "{1:F01BANKDEFFAXXX0000000000}{2:O940}{4:\r
+:20:STARTUMS\r
+:25:37040044/0532013000\r
+:28C:00042/001\r
+:60F:C251231EUR1000,00\r
+:61:2512310102DR150,00NTRFNONREF//B-7001\r
+:86:106?00SEPA-UEBERWEISUNG?20EREF+INV-77 SVWZ+Rechnung 7?21 7 Dezember?32ACME\r
+?33 GMBH?31DE02120300000000202051\r
+:61:260102C2500,NMSCKD-9//B-7002\r
+:86:/EREF/E2E-1/CNTP/NL91ABNA0417164300/ABNANL2A/Jansen BV/Amsterdam/REMI/USTD//Order 12\r
+/\r
+:62F:C260102EUR3350,00\r
+-}";
+
+    #[test]
+    fn reads_statement_lines_and_both_detail_layouts() {
+        let mut rows = Vec::new();
+        let mut on_row = |row: RowResult| {
+            rows.push(row.unwrap_or_else(|e| panic!("{}", e.message)));
+            Ok(())
+        };
+        let mut sink = StatementSink::new(&mut on_row);
+        parse_mt940(MT940, &mut sink).unwrap_or_else(|e| panic!("{}", e));
+        let statements = sink.into_statements();
+
+        let statement = &statements[0];
+        assert_eq!(statement.statement_id.as_deref(), Some("00042/001"));
+        assert_eq!(statement.account.as_deref(), Some("37040044/0532013000"));
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        let closing = statement.closing_balance.as_ref().unwrap_or_else(|| panic!("no closing balance"));
+        assert_eq!(closing.amount, BigDecimal::from_str("3350").unwrap_or_else(|e| panic!("{}", e)));
+
+        let debit = &rows[0].fields;
+        assert_eq!(debit["amount"], -150.0);
+        assert_eq!(debit["value_date"], "2025-12-31");
+        assert_eq!(debit["booking_date"], "2026-01-02");
+        assert_eq!(debit["reference"], "B-7001");
+        assert!(debit["customer_reference"].is_null());
+        assert_eq!(debit["end_to_end_id"], "INV-77");
+        assert_eq!(debit["remittance"], "Rechnung 7 7 Dezember");
+        assert_eq!(debit["counterparty"], "ACME GMBH");
+        assert_eq!(debit["counterparty_account"], "DE02120300000000202051");
+        assert_eq!(debit["currency"], "EUR");
+
+        let credit = &rows[1].fields;
+        assert_eq!(credit["amount"], 2500.0);
+        assert_eq!(credit["customer_reference"], "KD-9");
+        assert_eq!(credit["end_to_end_id"], "E2E-1");
+        assert_eq!(credit["counterparty"], "Jansen BV");
+        assert_eq!(credit["counterparty_account"], "NL91ABNA0417164300");
+        assert_eq!(credit["remittance"], "Order 12");
+    }
+
+    #[test]
+    fn field_start_ignores_non_ascii_tags() {
+        assert_eq!(field_start(":60F:C251231EUR1,00"), Some(("60F", "C251231EUR1,00")));
+        assert_eq!(field_start(":2é:Überweisung"), None);
+        assert_eq!(field_start(":é1:x"), None);
+        assert_eq!(field_start(":6:x"), None);
+    }
+}
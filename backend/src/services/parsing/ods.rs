@@ -0,0 +1,231 @@
+//! OpenDocument spreadsheets (.ods)
+//!
+//! Every sheet is in `content.xml`. Cells carry their typed value in
+//! attributes and runs of identical rows or cells are stored once with a
+//! repeat count, which for trailing blanks can run to a million.
+
+use std::io::{Read, Seek};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use zip::ZipArchive;
+
+use crate::errors::{AppError, AppResult};
+
+use super::spreadsheet::{choose_sheet, read_entry, Cell, CellRange, Sheet, SheetRow, MAX_COLUMNS};
+use super::xml::{XmlEvent, XmlReader};
+
+/// Copies made of a repeated row that has values
+const MAX_ROW_REPEAT: usize = 10_000;
+
+fn repeat_count(event: &XmlEvent<'_>, attribute: &str) -> usize {
+    event
+        .attribute(attribute)
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Typed value of a `<table:table-cell>` from its attributes, or `None` for text cells
+fn typed_value(event: &XmlEvent<'_>) -> Option<Cell> {
+    match event.attribute("value-type")? {
+        "float" | "currency" | "percentage" => event
+            .attribute("value")
+            .and_then(|value| value.parse().ok())
+            .map(Cell::Number),
+        "date" => event.attribute("date-value").and_then(parse_date_value).map(Cell::Date),
+        "boolean" => event.attribute("boolean-value").map(|value| Cell::Bool(value == "true")),
+        _ => None,
+    }
+}
+
+fn parse_date_value(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|d| d.and_time(NaiveTime::MIN)))
+}
+
+/// Display text of the cell just opened, up to its end tag
+fn read_cell_text(reader: &mut XmlReader<'_>) -> AppResult<String> {
+    let mut text = String::new();
+    let mut paragraphs = 0usize;
+    let mut depth = 0usize;
+    // Depths of the open paragraph and of an open comment, whose text isn't the cell's
+    let mut paragraph_depth = None;
+    let mut annotation_depth = None;
+    for event in reader.by_ref() {
+        let event = event?;
+        match &event {
+            XmlEvent::Start { name, .. } => {
+                depth += 1;
+                match *name {
+                    _ if annotation_depth.is_some() => {}
+                    "annotation" => annotation_depth = Some(depth),
+                    "p" | "h" => {
+                        if paragraphs > 0 {
+                            text.push('\n');
+                        }
+                        paragraphs += 1;
+                        paragraph_depth = Some(depth);
+                    }
+                    "s" => text.push_str(&" ".repeat(repeat_count(&event, "c"))),
+                    "tab" => text.push('\t'),
+                    "line-break" => text.push('\n'),
+                    _ => {}
+                }
+            }
+            XmlEvent::End { .. } if depth == 0 => break,
+            XmlEvent::End { .. } => {
+                if annotation_depth == Some(depth) {
+                    annotation_depth = None;
+                }
+                if paragraph_depth == Some(depth) {
+                    paragraph_depth = None;
+                }
+                depth -= 1;
+            }
+            XmlEvent::Text(part) if paragraph_depth.is_some() && annotation_depth.is_none() => {
+                text.push_str(part)
+            }
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(text)
+}
+
+/// All sheets of the workbook's content
+fn load_sheets(xml: &str) -> AppResult<Vec<Sheet>> {
+    let mut sheets = Vec::new();
+    let mut reader = XmlReader::new(xml);
+    let mut current: Option<Sheet> = None;
+    let mut row_number = 0usize;
+    let mut row_repeat = 1usize;
+    let mut cells: Vec<Cell> = Vec::new();
+    // Blank cells are only written out once a later cell in the row has a value
+    let mut pending_blanks = 0usize;
+
+    while let Some(event) = reader.next() {
+        let event = event?;
+        match &event {
+            XmlEvent::Start { name: "table", .. } => {
+                current = Some(Sheet {
+                    name: event.attribute("name").unwrap_or_default().to_string(),
+                    ..Default::default()
+                });
+                row_number = 0;
+            }
+            XmlEvent::End { name: "table" } => sheets.extend(current.take()),
+            XmlEvent::Start { name: "table-row", .. } => {
+                row_repeat = repeat_count(&event, "number-rows-repeated");
+                cells.clear();
+                pending_blanks = 0;
+            }
+            XmlEvent::Start {
+                name: name @ ("table-cell" | "covered-table-cell"),
+                ..
+            } => {
+                let repeat = repeat_count(&event, "number-columns-repeated");
+                let columns_spanned = repeat_count(&event, "number-columns-spanned");
+                let rows_spanned = repeat_count(&event, "number-rows-spanned");
+                let typed = typed_value(&event);
+                let text = read_cell_text(&mut reader)?;
+                let cell = match typed {
+                    Some(cell) => cell,
+                    None if text.trim().is_empty() => Cell::Empty,
+                    None => Cell::Text(text),
+                };
+
+                let column = cells.len() + pending_blanks;
+                if *name == "table-cell" && (columns_spanned > 1 || rows_spanned > 1) {
+                    if let Some(sheet) = current.as_mut() {
+                        sheet.merged.push(CellRange {
+                            first_row: row_number + 1,
+                            first_column: column,
+                            last_row: row_number + rows_spanned,
+                            last_column: column + columns_spanned - 1,
+                        });
+                    }
+                }
+                if cell.is_empty() {
+                    pending_blanks = pending_blanks.saturating_add(repeat);
+                } else if column < MAX_COLUMNS {
+                    cells.resize(column, Cell::Empty);
+                    cells.resize(column + repeat.min(MAX_COLUMNS - column), cell);
+                    pending_blanks = 0;
+                }
+            }
+            XmlEvent::End { name: "table-row" } => {
+                if let Some(sheet) = current.as_mut() {
+                    if cells.iter().any(|cell| !cell.is_empty()) {
+                        for copy in 0..row_repeat.min(MAX_ROW_REPEAT) {
+                            sheet.rows.push(SheetRow {
+                                number: row_number + copy + 1,
+                                cells: cells.clone(),
+                            });
+                        }
+                    }
+                }
+                row_number = row_number.saturating_add(row_repeat);
+            }
+            _ => {}
+        }
+    }
+    Ok(sheets)
+}
+
+/// Read one sheet of an ODS workbook
+///
+/// `wanted` picks the sheet by name or position; otherwise the first sheet
+/// with data is read.
+pub fn read_sheet<R: Read + Seek>(archive: &mut ZipArchive<R>, wanted: Option<&str>) -> AppResult<Sheet> {
+    let content = read_entry(archive, "content.xml")?
+        .ok_or_else(|| AppError::Validation("Workbook has no content.xml".to_string()))?;
+    let mut sheets = load_sheets(&content)?;
+    let names: Vec<String> = sheets.iter().map(|sheet| sheet.name.clone()).collect();
+    let index = match choose_sheet(&names, wanted)? {
+        Some(index) => index,
+        None => sheets.iter().position(|sheet| !sheet.rows.is_empty()).unwrap_or(0),
+    };
+    if index >= sheets.len() {
+        return Err(AppError::Validation("Workbook has no sheets".to_string()));
+    }
+    Ok(sheets.swap_remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_repeats_and_reads_typed_cells() {
+        let xml = r#"<office:document-content><office:body><office:spreadsheet>
+            <table:table table:name="Empty"><table:table-row table:number-rows-repeated="1048576"><table:table-cell table:number-columns-repeated="1024"/></table:table-row></table:table>
+            <table:table table:name="Ledger">
+              <table:table-row>
+                <table:table-cell table:number-columns-spanned="2" office:value-type="string"><text:p>Posted</text:p></table:table-cell>
+                <table:covered-table-cell/>
+                <table:table-cell office:value-type="string"><text:p>Memo<text:s text:c="2"/>line</text:p><office:annotation><text:p>note</text:p></office:annotation></table:table-cell>
+              </table:table-row>
+              <table:table-row table:number-rows-repeated="2">
+                <table:table-cell office:value-type="date" office:date-value="2026-01-31"/>
+                <table:table-cell office:value-type="currency" office:value="-1234.5"><text:p>-1.234,50 €</text:p></table:table-cell>
+                <table:table-cell table:number-columns-repeated="16000"/>
+              </table:table-row>
+              <table:table-row table:number-rows-repeated="1048570"><table:table-cell table:number-columns-repeated="1024"/></table:table-row>
+            </table:table>
+        </office:spreadsheet></office:body></office:document-content>"#;
+
+        let sheets = load_sheets(xml).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(sheets.len(), 2);
+        assert!(sheets[0].rows.is_empty());
+        let ledger = &sheets[1];
+        assert_eq!(ledger.rows.len(), 3);
+        assert_eq!(ledger.rows[0].cells[2], Cell::Text("Memo  line".to_string()));
+        assert_eq!(ledger.rows[2].number, 3);
+        assert_eq!(ledger.rows[2].cells.len(), 2);
+        assert_eq!(ledger.rows[2].cells[1], Cell::Number(-1234.5));
+        assert_eq!(
+            ledger.merged,
+            vec![CellRange { first_row: 1, first_column: 0, last_row: 1, last_column: 1 }]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! OFX and QFX statement downloads
+//!
+//! OFX 1.x is SGML where leaf elements have no closing tag
+//! (`<TRNAMT>-12.50`); OFX 2.x is the same vocabulary as XML. Both are read
+//! by one tag scanner that treats any tag followed by text as a leaf. OFX
+//! reports only the closing (ledger) balance, so the opening balance is
+//! worked back from it and the listed transactions.
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+
+use crate::errors::{AppError, AppResult};
+
+use super::statement::{clean_text, parse_decimal, Balance, StatementEntry, StatementInfo, StatementSink};
+use super::xml::unescape;
+
+/// A tag or a value, as scanned from the document
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    /// Text after an opening tag, i.e. the value of a leaf element
+    Value(&'a str, String),
+}
+
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = match text.find("<OFX>") {
+        Some(start) => &text[start..],
+        None => text,
+    };
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else { break };
+        let tag = &rest[start + 1..start + length];
+        rest = &rest[start + length + 1..];
+        // Processing instructions, comments and the SGML header carry no data
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim()));
+            continue;
+        }
+        let name = tag.trim().trim_end_matches('/');
+        let value_end = rest.find('<').unwrap_or(rest.len());
+        let value = rest[..value_end].trim();
+        if value.is_empty() {
+            tokens.push(Token::Open(name));
+        } else {
+            tokens.push(Token::Value(name, unescape(value).into_owned()));
+        }
+    }
+    tokens
+}
+
+/// `YYYYMMDD[HHMMSS[.XXX]][[offset:TZ]]`; only the date is kept
+fn parse_ofx_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim().get(..8)?, "%Y%m%d").ok()
+}
+
+/// State of the statement and transaction being read
+#[derive(Default)]
+struct Reader {
+    entry: Option<StatementEntry>,
+    /// The entry's `TRNAMT` couldn't be read
+    invalid_amount: bool,
+    /// The entry's tags as read, for the error report
+    raw: String,
+    /// Aggregates open around the current token, innermost last
+    path: Vec<String>,
+    start_date: Option<NaiveDate>,
+    ledger_amount: Option<BigDecimal>,
+    ledger_date: Option<NaiveDate>,
+    /// Sum of the statement's transactions
+    total: BigDecimal,
+}
+
+impl Reader {
+    fn inside(&self, name: &str) -> bool {
+        self.path.iter().any(|open| open == name)
+    }
+
+    fn value(&mut self, name: &str, value: String, sink: &mut StatementSink<'_>) {
+        let counterparty_account = self.inside("BANKACCTTO") || self.inside("CCACCTTO");
+        if let Some(entry) = self.entry.as_mut() {
+            self.raw.push_str(&format!("<{}>{}", name, value));
+            match name {
+                "TRNTYPE" => entry.transaction_code = Some(value),
+                "DTPOSTED" => entry.booking_date = parse_ofx_date(&value),
+                "DTAVAIL" => entry.value_date = parse_ofx_date(&value),
+                "TRNAMT" => match parse_decimal(&value) {
+                    Some(amount) => entry.amount = amount,
+                    None => self.invalid_amount = true,
+                },
+                "FITID" => entry.reference = Some(value),
+                "CHECKNUM" => entry.customer_reference = Some(value),
+                "REFNUM" => {
+                    entry.customer_reference.get_or_insert(value);
+                }
+                "NAME" => entry.counterparty = clean_text(&value),
+                "ACCTID" if counterparty_account => entry.counterparty_account = Some(value),
+                "MEMO" => entry.remittance = clean_text(&value),
+                "CURSYM" => entry.currency = Some(value),
+                _ => {}
+            }
+            return;
+        }
+
+        match name {
+            "CURDEF" => sink.current().currency = Some(value),
+            "ACCTID" if self.inside("BANKACCTFROM") || self.inside("CCACCTFROM") => {
+                sink.current().account = Some(value)
+            }
+            "DTSTART" => self.start_date = parse_ofx_date(&value),
+            "BALAMT" if self.inside("LEDGERBAL") => self.ledger_amount = parse_decimal(&value),
+            "DTASOF" if self.inside("LEDGERBAL") => self.ledger_date = parse_ofx_date(&value),
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, name: &str, sink: &mut StatementSink<'_>) {
+        match name {
+            "STMTRS" | "CCSTMTRS" => {
+                sink.begin(StatementInfo::default());
+                self.start_date = None;
+                self.ledger_amount = None;
+                self.ledger_date = None;
+                self.total = BigDecimal::from(0);
+            }
+            "STMTTRN" => {
+                self.entry = Some(StatementEntry::default());
+                self.invalid_amount = false;
+                self.raw.clear();
+            }
+            _ => {}
+        }
+        self.path.push(name.to_string());
+    }
+
+    fn close(&mut self, name: &str, sink: &mut StatementSink<'_>) -> AppResult<()> {
+        // SGML leaves were never pushed; anything still open inside the aggregate ends with it
+        let Some(position) = self.path.iter().rposition(|open| open == name) else { return Ok(()) };
+        self.path.truncate(position);
+        match name {
+            "STMTTRN" => {
+                let Some(entry) = self.entry.take() else { return Ok(()) };
+                if self.invalid_amount {
+                    return sink.error("Transaction amount is not a number", &self.raw);
+                }
+                self.total += &entry.amount;
+                sink.entry(entry)?;
+            }
+            "STMTRS" | "CCSTMTRS" => {
+                if let Some(amount) = self.ledger_amount.take() {
+                    let statement = sink.current();
+                    statement.opening_balance = Some(Balance {
+                        amount: &amount - &self.total,
+                        date: self.start_date,
+                    });
+                    statement.closing_balance = Some(Balance {
+                        amount,
+                        date: self.ledger_date,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_ofx(text: &str, sink: &mut StatementSink<'_>) -> AppResult<()> {
+    if !text.contains("<OFX>") {
+        return Err(AppError::Validation("File is not an OFX document".to_string()));
+    }
+    let mut reader = Reader::default();
+    for token in tokens(text) {
+        match token {
+            Token::Open(name) => reader.open(name, sink),
+            Token::Close(name) => reader.close(name, sink)?,
+            Token::Value(name, value) => reader.value(name, value, sink),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use super::super::RowResult;
+
+    const OFX_SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><TRNUID>1<STMTRS>
+<CURDEF>USD
+<BANKACCTFROM><BANKID>121000248<ACCTID>000123456<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST><DTSTART>20260301<DTEND>20260331
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20260305120000[-8:PST]<TRNAMT>-45.10<FITID>2026030501<NAME>Coffee &amp; Co<MEMO>POS 4411</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20260310<TRNAMT>1200.00<FITID>2026031001<CHECKNUM>1001</STMTTRN>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20260311<TRNAMT>n/a<FITID>2026031101</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>2154.90<DTASOF>20260331</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>";
+
+    const OFX_XML: &str = r#"<?xml version="1.0"?><?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>EUR</CURDEF>
+<CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM>
+<BANKTRANLIST><STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20260302</DTPOSTED><TRNAMT>-9.99</TRNAMT><FITID>X1</FITID><MEMO></MEMO></STMTTRN></BANKTRANLIST>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+    fn parse(text: &str) -> (Vec<RowResult>, Vec<StatementInfo>) {
+        let mut rows = Vec::new();
+        let mut on_row = |row: RowResult| {
+            rows.push(row);
+            Ok(())
+        };
+        let mut sink = StatementSink::new(&mut on_row);
+        parse_ofx(text, &mut sink).unwrap_or_else(|e| panic!("{}", e));
+        let statements = sink.into_statements();
+        (rows, statements)
+    }
+
+    #[test]
+    fn reads_sgml_transactions_and_derives_the_opening_balance() {
+        let (rows, statements) = parse(OFX_SGML);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("000123456"));
+        assert_eq!(statement.entry_count, 2);
+        let closing = statement.closing_balance.as_ref().unwrap_or_else(|| panic!("no closing balance"));
+        assert_eq!(closing.date, NaiveDate::from_ymd_opt(2026, 3, 31));
+        let opening = statement.opening_balance.as_ref().unwrap_or_else(|| panic!("no opening balance"));
+        assert_eq!(opening.amount, BigDecimal::from_str("1000.00").unwrap_or_else(|e| panic!("{}", e)));
+        assert_eq!(opening.date, NaiveDate::from_ymd_opt(2026, 3, 1));
+
+        let debit = &rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message)).fields;
+        assert_eq!(debit["amount"], -45.1);
+        assert_eq!(debit["booking_date"], "2026-03-05");
+        assert_eq!(debit["reference"], "2026030501");
+        assert_eq!(debit["counterparty"], "Coffee & Co");
+        assert_eq!(debit["description"], "POS 4411");
+        assert_eq!(debit["currency"], "USD");
+        let credit = &rows[1].as_ref().unwrap_or_else(|e| panic!("{}", e.message)).fields;
+        assert_eq!(credit["customer_reference"], "1001");
+        assert!(rows[2].is_err());
+    }
+
+    #[test]
+    fn reads_xml_credit_card_statements() {
+        let (rows, statements) = parse(OFX_XML);
+        assert_eq!(statements[0].account.as_deref(), Some("4111"));
+        assert!(statements[0].closing_balance.is_none());
+        let row = &rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message)).fields;
+        assert_eq!(row["amount"], -9.99);
+        assert_eq!(row["currency"], "EUR");
+        assert!(row["remittance"].is_null());
+    }
+}
@@ -0,0 +1,504 @@
+//! Column schemas inferred from parsed rows, and drift between them
+//!
+//! A `SchemaProfiler` watches every row of a file and infers each column's
+//! type, whether it has empty values and a small profile of the values. The
+//! first ingestion into a data source saves the result as the source's
+//! schema; later files are profiled the same way and compared with it, and
+//! the `DriftPolicy` decides which differences only warn and which fail the
+//! ingestion.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use super::columns::{is_blank, parse_amount, parse_date, value_text};
+
+/// Distinct values counted per column before counting stops
+const DISTINCT_LIMIT: usize = 1_000;
+/// Example values kept per column
+const SAMPLE_LIMIT: usize = 3;
+/// Longest example value kept, in characters
+const SAMPLE_LENGTH: usize = 64;
+/// Currency symbols allowed around numbers
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥', '₹', '₣', '₩'];
+
+/// Type of a column's values, from narrowest to widest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Boolean,
+    Integer,
+    Decimal,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    /// Narrowest type that holds values of both types
+    fn join(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Integer, ColumnType::Decimal) | (ColumnType::Decimal, ColumnType::Integer) => {
+                ColumnType::Decimal
+            }
+            _ => ColumnType::Text,
+        }
+    }
+
+    /// Whether values of `other` fit in a column of this type
+    fn accepts(self, other: Self) -> bool {
+        self.join(other) == self
+    }
+
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(_) => Some(ColumnType::Boolean),
+            Value::Number(number) if number.is_i64() || number.is_u64() => Some(ColumnType::Integer),
+            Value::Number(_) => Some(ColumnType::Decimal),
+            Value::String(text) => Some(Self::of_text(text.trim())),
+            Value::Null => None,
+            // Nested arrays and objects are kept as text
+            _ => Some(ColumnType::Text),
+        }
+    }
+
+    fn of_text(text: &str) -> Self {
+        if text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false") {
+            return ColumnType::Boolean;
+        }
+        // A leading zero marks a code such as an account or zip, not a number
+        let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+        if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") && !digits.starts_with("0,") {
+            return ColumnType::Text;
+        }
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            return ColumnType::Integer;
+        }
+        if looks_numeric(text) && parse_amount(text).is_some() {
+            return ColumnType::Decimal;
+        }
+        if parse_date(text, None).is_some() {
+            return ColumnType::Date;
+        }
+        ColumnType::Text
+    }
+}
+
+/// Whether text is an amount, possibly with a currency and sign markers,
+/// rather than text that merely contains digits
+fn looks_numeric(text: &str) -> bool {
+    let upper = text.to_ascii_uppercase();
+    let mut text = upper.trim();
+    for marker in ["CR", "DR"] {
+        text = text.strip_suffix(marker).unwrap_or(text).trim_end();
+    }
+    // An ISO currency code before or after the amount
+    let is_code = |code: &str| code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase());
+    if text.get(..3).is_some_and(is_code) {
+        // "INV-001" is a reference, "USD -1.00" an amount
+        if text[3..].starts_with(['-', '+']) {
+            return false;
+        }
+        text = text[3..].trim_start();
+    } else if text.len() > 3 && text.get(text.len() - 3..).is_some_and(is_code) {
+        text = text[..text.len() - 3].trim_end();
+    }
+    text.chars().any(|c| c.is_ascii_digit())
+        && text.chars().all(|c| {
+            c.is_ascii_digit() || matches!(c, '.' | ',' | '\'' | ' ' | '-' | '+' | '(' | ')') || CURRENCY_SYMBOLS.contains(&c)
+        })
+}
+
+/// What was seen of one column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// `None` while every value seen was empty
+    #[serde(rename = "type")]
+    pub column_type: Option<ColumnType>,
+    /// Whether some rows had the column empty or missing
+    pub nullable: bool,
+    pub null_count: usize,
+    /// Distinct non-empty values, counted up to a limit
+    pub distinct_count: usize,
+    /// The distinct count stopped at its limit
+    #[serde(default)]
+    pub distinct_capped: bool,
+    /// Smallest and largest value, for numbers and dates
+    #[serde(default)]
+    pub min: Option<Value>,
+    #[serde(default)]
+    pub max: Option<Value>,
+    /// Longest value, in characters
+    #[serde(default)]
+    pub max_length: usize,
+    #[serde(default)]
+    pub samples: Vec<String>,
+}
+
+/// Inferred schema of a data source's files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceSchema {
+    /// Columns in the order they were first seen
+    pub columns: Vec<ColumnProfile>,
+    /// Rows the schema was inferred from
+    #[serde(default)]
+    pub row_count: usize,
+    /// How later files that differ from this schema are treated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<DriftPolicy>,
+}
+
+impl SourceSchema {
+    pub fn column(&self, name: &str) -> Option<&ColumnProfile> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// Running state for one column
+#[derive(Debug, Default)]
+struct ColumnState {
+    column_type: Option<ColumnType>,
+    non_null: usize,
+    distinct: HashSet<String>,
+    distinct_capped: bool,
+    min_number: Option<f64>,
+    max_number: Option<f64>,
+    min_date: Option<chrono::NaiveDate>,
+    max_date: Option<chrono::NaiveDate>,
+    max_length: usize,
+    samples: Vec<String>,
+}
+
+/// Builds a `SourceSchema` from rows as they are read
+#[derive(Debug, Default)]
+pub struct SchemaProfiler {
+    names: Vec<String>,
+    columns: HashMap<String, ColumnState>,
+    rows: usize,
+}
+
+impl SchemaProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, fields: &Map<String, Value>) {
+        self.rows += 1;
+        for (name, value) in fields {
+            if !self.columns.contains_key(name) {
+                self.names.push(name.clone());
+            }
+            // Rows where the column is empty or missing count as nulls
+            let state = self.columns.entry(name.clone()).or_default();
+            if is_blank(value) {
+                continue;
+            }
+            let Some(value_type) = ColumnType::of(value) else { continue };
+            state.column_type = Some(match state.column_type {
+                Some(column_type) => column_type.join(value_type),
+                None => value_type,
+            });
+            state.non_null += 1;
+
+            let text = value_text(value).unwrap_or_else(|| value.to_string());
+            state.max_length = state.max_length.max(text.chars().count());
+            match value_type {
+                ColumnType::Integer | ColumnType::Decimal => {
+                    let number = value.as_f64().or_else(|| parse_amount(&text));
+                    if let Some(number) = number {
+                        state.min_number = Some(state.min_number.map_or(number, |min| min.min(number)));
+                        state.max_number = Some(state.max_number.map_or(number, |max| max.max(number)));
+                    }
+                }
+                ColumnType::Date => {
+                    if let Some(date) = parse_date(&text, None) {
+                        state.min_date = Some(state.min_date.map_or(date, |min| min.min(date)));
+                        state.max_date = Some(state.max_date.map_or(date, |max| max.max(date)));
+                    }
+                }
+                _ => {}
+            }
+            if state.samples.len() < SAMPLE_LIMIT && !state.distinct.contains(&text) {
+                state.samples.push(text.chars().take(SAMPLE_LENGTH).collect());
+            }
+            if state.distinct.len() < DISTINCT_LIMIT {
+                state.distinct.insert(text);
+            } else if !state.distinct.contains(&text) {
+                state.distinct_capped = true;
+            }
+        }
+    }
+
+    /// Rows observed so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn finish(&self) -> SourceSchema {
+        let columns = self
+            .names
+            .iter()
+            .filter_map(|name| {
+                let state = self.columns.get(name)?;
+                let (min, max) = match state.column_type {
+                    Some(ColumnType::Integer | ColumnType::Decimal) => (
+                        state.min_number.map(number_value),
+                        state.max_number.map(number_value),
+                    ),
+                    Some(ColumnType::Date) => (
+                        state.min_date.map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+                        state.max_date.map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+                    ),
+                    _ => (None, None),
+                };
+                let null_count = self.rows.saturating_sub(state.non_null);
+                Some(ColumnProfile {
+                    name: name.clone(),
+                    column_type: state.column_type,
+                    nullable: null_count > 0,
+                    null_count,
+                    distinct_count: state.distinct.len(),
+                    distinct_capped: state.distinct_capped,
+                    min,
+                    max,
+                    max_length: state.max_length,
+                    samples: state.samples.clone(),
+                })
+            })
+            .collect();
+        SourceSchema {
+            columns,
+            row_count: self.rows,
+            policy: None,
+        }
+    }
+}
+
+fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Value::from(number as i64)
+    } else {
+        Value::from(number)
+    }
+}
+
+/// What to do about one kind of difference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftAction {
+    Ignore,
+    #[default]
+    Warn,
+    Fail,
+}
+
+/// How each kind of schema difference is treated; everything warns by default
+///
+/// Written either per kind (`{"removed": "fail"}`) or as one action for all
+/// kinds (`"fail"`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DriftPolicy {
+    pub added: DriftAction,
+    pub removed: DriftAction,
+    pub retyped: DriftAction,
+    /// A column that never used to be empty now has empty values
+    pub nullable: DriftAction,
+}
+
+impl<'de> Deserialize<'de> for DriftPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct PerKind {
+            #[serde(default)]
+            added: DriftAction,
+            #[serde(default)]
+            removed: DriftAction,
+            #[serde(default)]
+            retyped: DriftAction,
+            #[serde(default)]
+            nullable: DriftAction,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            All(DriftAction),
+            PerKind(PerKind),
+        }
+
+        Ok(match Written::deserialize(deserializer)? {
+            Written::All(action) => DriftPolicy {
+                added: action,
+                removed: action,
+                retyped: action,
+                nullable: action,
+            },
+            Written::PerKind(policy) => DriftPolicy {
+                added: policy.added,
+                removed: policy.removed,
+                retyped: policy.retyped,
+                nullable: policy.nullable,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Retyped,
+    Nullable,
+}
+
+/// One difference between a file and its data source's schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaChange {
+    pub kind: ChangeKind,
+    pub column: String,
+    pub expected: Option<ColumnType>,
+    pub actual: Option<ColumnType>,
+    pub action: DriftAction,
+}
+
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let type_name = |column_type: Option<ColumnType>| match column_type {
+            Some(column_type) => format!("{:?}", column_type).to_lowercase(),
+            None => "empty".to_string(),
+        };
+        match self.kind {
+            ChangeKind::Added => write!(f, "column '{}' was added", self.column),
+            ChangeKind::Removed => write!(f, "column '{}' was removed", self.column),
+            ChangeKind::Retyped => write!(
+                f,
+                "column '{}' changed from {} to {}",
+                self.column,
+                type_name(self.expected),
+                type_name(self.actual)
+            ),
+            ChangeKind::Nullable => write!(f, "column '{}' now has empty values", self.column),
+        }
+    }
+}
+
+/// Differences of `actual` from `expected` that the policy doesn't ignore
+///
+/// Values that fit the expected type don't count as a change, so integers in
+/// a decimal column or anything in a text column are fine.
+pub fn compare_schemas(expected: &SourceSchema, actual: &SourceSchema, policy: &DriftPolicy) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    let mut change = |kind, column: &str, expected, actual| {
+        let action = match kind {
+            ChangeKind::Added => policy.added,
+            ChangeKind::Removed => policy.removed,
+            ChangeKind::Retyped => policy.retyped,
+            ChangeKind::Nullable => policy.nullable,
+        };
+        if action != DriftAction::Ignore {
+            changes.push(SchemaChange {
+                kind,
+                column: column.to_string(),
+                expected,
+                actual,
+                action,
+            });
+        }
+    };
+
+    for column in &expected.columns {
+        let Some(found) = actual.column(&column.name) else {
+            change(ChangeKind::Removed, &column.name, column.column_type, None);
+            continue;
+        };
+        if let (Some(expected_type), Some(actual_type)) = (column.column_type, found.column_type) {
+            if !expected_type.accepts(actual_type) {
+                change(ChangeKind::Retyped, &column.name, Some(expected_type), Some(actual_type));
+            }
+        }
+        if !column.nullable && found.nullable {
+            change(ChangeKind::Nullable, &column.name, column.column_type, found.column_type);
+        }
+    }
+    for column in &actual.columns {
+        if expected.column(&column.name).is_none() {
+            change(ChangeKind::Added, &column.name, None, column.column_type);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn profile(rows: Value) -> SourceSchema {
+        let mut profiler = SchemaProfiler::new();
+        for row in rows.as_array().unwrap_or_else(|| panic!("rows must be an array")) {
+            match row {
+                Value::Object(fields) => profiler.observe(fields),
+                _ => panic!("row must be an object"),
+            }
+        }
+        profiler.finish()
+    }
+
+    #[test]
+    fn infers_types_nullability_and_profiles() {
+        let schema = profile(json!([
+            {"id": "INV-001", "account": "00123", "amount": "1.234,50 EUR", "qty": 3, "date": "2026-03-01", "paid": "true", "note": ""},
+            {"id": "INV-002", "account": "00124", "amount": "-12.00", "qty": 4.5, "date": "2026-02-15", "paid": "false", "note": "late"},
+            {"id": "INV-002", "account": "00125", "amount": "(7.25)", "qty": 1, "date": "2026-03-09", "paid": "true"}
+        ]));
+        let column = |name: &str| schema.column(name).unwrap_or_else(|| panic!("no column {}", name));
+
+        assert_eq!(schema.row_count, 3);
+        assert_eq!(column("id").column_type, Some(ColumnType::Text));
+        assert_eq!(column("id").distinct_count, 2);
+        assert_eq!(column("account").column_type, Some(ColumnType::Text));
+        assert_eq!(column("amount").column_type, Some(ColumnType::Decimal));
+        assert_eq!(column("amount").min, Some(json!(-12)));
+        assert_eq!(column("amount").max, Some(json!(1234.5)));
+        assert_eq!(column("qty").column_type, Some(ColumnType::Decimal));
+        assert_eq!(column("date").column_type, Some(ColumnType::Date));
+        assert_eq!(column("date").min, Some(json!("2026-02-15")));
+        assert_eq!(column("paid").column_type, Some(ColumnType::Boolean));
+        assert!(!column("amount").nullable);
+        assert!(column("note").nullable);
+        assert_eq!(column("note").null_count, 2);
+        assert_eq!(column("id").samples, vec!["INV-001", "INV-002"]);
+    }
+
+    #[test]
+    fn reports_drift_by_policy() {
+        let expected = profile(json!([{"id": "A1", "amount": "10.50", "date": "2026-03-01", "ref": "x"}]));
+        let actual = profile(json!([
+            {"id": "A2", "amount": "11", "date": "01/03/2026 or so", "memo": "new"},
+            {"id": "", "amount": "12", "date": "n/a", "memo": "new"}
+        ]));
+        let policy: DriftPolicy = serde_json::from_value(json!({"removed": "fail", "nullable": "ignore"}))
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let changes = compare_schemas(&expected, &actual, &policy);
+        let kinds: Vec<_> = changes.iter().map(|change| (change.kind, change.column.as_str(), change.action)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ChangeKind::Retyped, "date", DriftAction::Warn),
+                (ChangeKind::Removed, "ref", DriftAction::Fail),
+                (ChangeKind::Added, "memo", DriftAction::Warn),
+            ]
+        );
+        assert_eq!(changes[0].to_string(), "column 'date' changed from date to text");
+
+        let strict: DriftPolicy = serde_json::from_value(json!("fail")).unwrap_or_else(|e| panic!("{}", e));
+        assert!(compare_schemas(&expected, &actual, &strict)
+            .iter()
+            .any(|change| change.kind == ChangeKind::Nullable && change.action == DriftAction::Fail));
+        assert!(compare_schemas(&expected, &expected, &strict).is_empty());
+    }
+}
@@ -0,0 +1,371 @@
+//! Spreadsheet workbooks (XLSX and ODS)
+//!
+//! Both formats are zip archives of XML. A sheet is loaded whole as a grid of
+//! typed cells, its header row is located (skipping title rows above it and
+//! folding a merged band row into the names below it), and every row after
+//! the header is handed on as a `ParsedRow` numbered by its sheet row.
+
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde_json::{Map, Value};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::errors::{AppError, AppResult};
+
+use super::delimited::normalize_headers;
+use super::{ods, xlsx, FileFormat, ParseOptions, ParsedRow, RowResult};
+
+/// Largest archive entry read, guarding against decompression bombs
+const MAX_ENTRY_BYTES: u64 = 512 * 1024 * 1024;
+/// Rows at the top of a sheet searched for the header row
+const HEADER_SEARCH_ROWS: usize = 20;
+/// Columns beyond this are ignored
+pub const MAX_COLUMNS: usize = 16_384;
+
+/// A typed cell value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(NaiveDateTime),
+}
+
+impl Cell {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Cell::Empty => true,
+            Cell::Text(text) => text.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        match self {
+            Cell::Text(text) => Some(text.trim()),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Cell::Empty => Value::Null,
+            Cell::Text(text) if text.trim().is_empty() => Value::Null,
+            Cell::Text(text) => Value::String(text.trim().to_string()),
+            Cell::Bool(flag) => Value::Bool(*flag),
+            // Whole numbers stay integers so ids don't turn into "1234.0"
+            Cell::Number(number) if number.fract() == 0.0 && number.abs() < 9.0e15 => {
+                Value::from(*number as i64)
+            }
+            Cell::Number(number) => serde_json::Number::from_f64(*number)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Cell::Date(datetime) if datetime.time() == chrono::NaiveTime::MIN => {
+                Value::String(datetime.date().format("%Y-%m-%d").to_string())
+            }
+            Cell::Date(datetime) => Value::String(datetime.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        }
+    }
+}
+
+/// A row with at least one value, numbered as in the sheet (1-based)
+#[derive(Debug, Clone)]
+pub struct SheetRow {
+    pub number: usize,
+    pub cells: Vec<Cell>,
+}
+
+/// A merged block of cells, by 1-based row and 0-based column, inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellRange {
+    pub first_row: usize,
+    pub first_column: usize,
+    pub last_row: usize,
+    pub last_column: usize,
+}
+
+impl CellRange {
+    fn contains(&self, row: usize, column: usize) -> bool {
+        (self.first_row..=self.last_row).contains(&row) && (self.first_column..=self.last_column).contains(&column)
+    }
+}
+
+/// One sheet of a workbook; empty rows are left out
+#[derive(Debug, Clone, Default)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<SheetRow>,
+    pub merged: Vec<CellRange>,
+}
+
+/// Which sheet to read: a name, or a 1-based position
+///
+/// Without a choice the first sheet with any data is read.
+pub fn choose_sheet(names: &[String], wanted: Option<&str>) -> AppResult<Option<usize>> {
+    let Some(wanted) = wanted else { return Ok(None) };
+    if let Some(position) = names.iter().position(|name| name.eq_ignore_ascii_case(wanted.trim())) {
+        return Ok(Some(position));
+    }
+    match wanted.trim().parse::<usize>() {
+        Ok(number) if (1..=names.len()).contains(&number) => Ok(Some(number - 1)),
+        _ => Err(AppError::Validation(format!(
+            "Sheet '{}' not found; the workbook has {}",
+            wanted,
+            names.join(", ")
+        ))),
+    }
+}
+
+/// Column index of an A1-style reference ("C7" -> 2), with its row number
+pub fn parse_cell_reference(reference: &str) -> Option<(usize, usize)> {
+    let letters_end = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(letters_end);
+    if letters.is_empty() {
+        return None;
+    }
+    let mut column = 0usize;
+    for c in letters.chars() {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+        column = column * 26 + usize::from(c.to_ascii_uppercase() as u8 - b'A') + 1;
+    }
+    Some((column - 1, digits.parse().ok()?))
+}
+
+/// Date and time of an Excel serial number
+///
+/// Day 1 is 1900-01-01 in the default system (counting the non-existent
+/// 1900-02-29 Excel inherited from Lotus) and 1904-01-02 in the 1904 system.
+pub fn excel_serial_date(serial: f64, date1904: bool) -> Option<NaiveDateTime> {
+    if !(0.0..2_958_466.0).contains(&serial) {
+        return None;
+    }
+    let epoch = if date1904 {
+        NaiveDate::from_ymd_opt(1904, 1, 1)?
+    } else if serial < 61.0 {
+        NaiveDate::from_ymd_opt(1899, 12, 31)?
+    } else {
+        NaiveDate::from_ymd_opt(1899, 12, 30)?
+    };
+    let days = serial.trunc() as i64;
+    let seconds = ((serial - serial.trunc()) * 86_400.0).round() as i64;
+    epoch
+        .and_hms_opt(0, 0, 0)?
+        .checked_add_signed(Duration::days(days) + Duration::seconds(seconds))
+}
+
+/// Read an archive entry as text, or `None` if the archive doesn't have it
+pub fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> AppResult<Option<String>> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(AppError::Validation(format!("Unreadable workbook entry {}: {}", name, e))),
+    };
+    let mut text = String::new();
+    entry
+        .take(MAX_ENTRY_BYTES + 1)
+        .read_to_string(&mut text)
+        .map_err(|e| AppError::Validation(format!("Unreadable workbook entry {}: {}", name, e)))?;
+    if text.len() as u64 > MAX_ENTRY_BYTES {
+        return Err(AppError::Validation(format!("Workbook entry {} is too large", name)));
+    }
+    Ok(Some(text))
+}
+
+/// Whether a zip archive is an XLSX or ODS workbook
+pub fn workbook_format<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<FileFormat> {
+    if archive.by_name("xl/workbook.xml").is_ok() {
+        return Some(FileFormat::Xlsx);
+    }
+    let mimetype = read_entry(archive, "mimetype").ok().flatten()?;
+    mimetype
+        .trim()
+        .starts_with("application/vnd.oasis.opendocument.spreadsheet")
+        .then_some(FileFormat::Ods)
+}
+
+/// Index of the header among `rows`
+///
+/// The header is the first row of text only that fills at least half as
+/// many columns as the widest of the first rows; title and account lines
+/// above a table don't qualify. Falls back to the first row.
+pub fn detect_header_row(rows: &[SheetRow]) -> usize {
+    let filled = |row: &SheetRow| row.cells.iter().filter(|cell| !cell.is_empty()).count();
+    let width = rows.iter().take(HEADER_SEARCH_ROWS * 2).map(filled).max().unwrap_or(0);
+    rows.iter()
+        .take(HEADER_SEARCH_ROWS)
+        .position(|row| {
+            let count = filled(row);
+            let text = row.cells.iter().filter(|cell| cell.text().is_some_and(|t| !t.is_empty())).count();
+            count >= 2.min(width) && count == text && count * 2 >= width
+        })
+        .unwrap_or(0)
+}
+
+/// Cell text at `column` of `row`, spreading merged blocks over every cell they cover
+fn header_text(sheet: &Sheet, row: &SheetRow, column: usize) -> String {
+    let own = row.cells.get(column).and_then(Cell::text).unwrap_or_default();
+    if !own.is_empty() {
+        return own.to_string();
+    }
+    sheet
+        .merged
+        .iter()
+        .find(|range| range.contains(row.number, column))
+        .and_then(|range| {
+            let origin = sheet.rows.iter().find(|r| r.number == range.first_row)?;
+            origin.cells.get(range.first_column).and_then(Cell::text)
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Column names from the header row, folding in a merged band row directly above it
+fn header_names(sheet: &Sheet, header_index: usize) -> Vec<String> {
+    let header = &sheet.rows[header_index];
+    let band = header_index
+        .checked_sub(1)
+        .map(|i| &sheet.rows[i])
+        .filter(|row| row.number + 1 == header.number)
+        .filter(|row| {
+            sheet
+                .merged
+                .iter()
+                .any(|range| range.first_row == row.number && range.last_column > range.first_column)
+        });
+    let width = header
+        .cells
+        .len()
+        .max(band.map(|row| row.cells.len()).unwrap_or(0));
+
+    let names: Vec<String> = (0..width).map(|column| header_text(sheet, header, column)).collect();
+    let bands: Vec<String> = (0..width)
+        .map(|column| band.map(|row| header_text(sheet, row, column)).unwrap_or_default())
+        .collect();
+    let combined: Vec<String> = names
+        .iter()
+        .zip(&bands)
+        .map(|(name, band)| {
+            let repeated = names.iter().filter(|other| *other == name).count() > 1;
+            match (name.is_empty(), band.is_empty()) {
+                (true, _) => band.clone(),
+                (false, false) if repeated => format!("{} {}", band, name),
+                _ => name.clone(),
+            }
+        })
+        .collect();
+    normalize_headers(combined.iter().map(String::as_str))
+}
+
+/// Read the chosen sheet of a workbook, calling `on_row` for every data row
+///
+/// Returns the sheet name and the sheet row number of the header.
+pub fn parse_spreadsheet(
+    file: File,
+    format: FileFormat,
+    options: &ParseOptions,
+    bytes_read: &AtomicU64,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<(String, usize)> {
+    let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| AppError::Validation(format!("File is not a readable workbook: {}", e)))?;
+    let sheet = match format {
+        FileFormat::Ods => ods::read_sheet(&mut archive, options.sheet.as_deref())?,
+        _ => xlsx::read_sheet(&mut archive, options.sheet.as_deref())?,
+    };
+    if sheet.rows.is_empty() {
+        return Err(AppError::Validation(format!("Sheet '{}' is empty", sheet.name)));
+    }
+
+    let header_index = match options.header_row {
+        Some(number) => sheet
+            .rows
+            .iter()
+            .position(|row| row.number == number)
+            .ok_or_else(|| AppError::Validation(format!("Header row {} is empty", number)))?,
+        None => detect_header_row(&sheet.rows),
+    };
+    let headers = header_names(&sheet, header_index);
+    let header_number = sheet.rows[header_index].number;
+
+    let data_rows = &sheet.rows[header_index + 1..];
+    for (position, row) in data_rows.iter().enumerate() {
+        let mut fields = Map::new();
+        for (column, cell) in row.cells.iter().enumerate() {
+            let name = match headers.get(column) {
+                Some(name) => name.clone(),
+                None if cell.is_empty() => continue,
+                None => format!("column_{}", column + 1),
+            };
+            fields.insert(name, cell.to_value());
+        }
+        for name in headers.iter().skip(row.cells.len()) {
+            fields.insert(name.clone(), Value::Null);
+        }
+        // Progress is spread over the rows, the whole file having been read already
+        bytes_read.store(
+            total_bytes * (position as u64 + 1) / data_rows.len() as u64,
+            Ordering::Relaxed,
+        );
+        on_row(Ok(ParsedRow {
+            index: row.number,
+            fields,
+        }))?;
+    }
+    Ok((sheet.name, header_number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow {
+            number,
+            cells: cells
+                .iter()
+                .map(|text| if text.is_empty() { Cell::Empty } else { Cell::Text(text.to_string()) })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn converts_excel_serial_dates() {
+        let date = |serial, date1904| excel_serial_date(serial, date1904).map(|d| d.to_string());
+        assert_eq!(date(45323.0, false).as_deref(), Some("2024-02-01 00:00:00"));
+        assert_eq!(date(45323.5, false).as_deref(), Some("2024-02-01 12:00:00"));
+        assert_eq!(date(1.0, false).as_deref(), Some("1900-01-01 00:00:00"));
+        assert_eq!(date(43861.0, true).as_deref(), Some("2024-02-01 00:00:00"));
+        assert_eq!(parse_cell_reference("AB12"), Some((27, 12)));
+    }
+
+    #[test]
+    fn finds_header_below_title_and_folds_merged_band() {
+        let sheet = Sheet {
+            name: "Statement".to_string(),
+            rows: vec![
+                row(1, &["ACME Bank statement"]),
+                row(3, &["", "Booking", "", "Value", ""]),
+                row(4, &["Ref", "Date", "Amount", "Date", "Amount"]),
+                row(5, &["T-1", "2026-01-02", "10", "2026-01-03", "10"]),
+            ],
+            merged: vec![
+                CellRange { first_row: 3, first_column: 1, last_row: 3, last_column: 2 },
+                CellRange { first_row: 3, first_column: 3, last_row: 3, last_column: 4 },
+            ],
+        };
+        let header = detect_header_row(&sheet.rows);
+        assert_eq!(header, 2);
+        assert_eq!(
+            header_names(&sheet, header),
+            vec!["Ref", "Booking Date", "Booking Amount", "Value Date", "Value Amount"]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Bank statements: the entries and balances shared by the statement formats
+//!
+//! camt, MT940, BAI2 and OFX files are all read into `StatementEntry`s, which
+//! become rows with the same field names whatever the format, so the default
+//! column mapping picks up reference, amount, booking date and description.
+//! Opening and closing balances are collected per statement and returned with
+//! the parse summary.
+
+use std::io::Read;
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::{AppError, AppResult};
+
+use super::{bai2, camt, mt940, ofx, FileFormat, ParsedRow, RowError, RowResult};
+
+/// Field names of a statement row, in column order
+pub const STATEMENT_FIELDS: &[&str] = &[
+    "statement_id",
+    "account",
+    "reference",
+    "booking_date",
+    "value_date",
+    "amount",
+    "currency",
+    "counterparty",
+    "counterparty_account",
+    "end_to_end_id",
+    "customer_reference",
+    "remittance",
+    "description",
+    "transaction_code",
+    "status",
+];
+
+/// A balance reported by the bank; negative when overdrawn
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Balance {
+    pub amount: BigDecimal,
+    pub date: Option<NaiveDate>,
+}
+
+/// One statement (or notification) for one account
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatementInfo {
+    pub statement_id: Option<String>,
+    pub account: Option<String>,
+    pub currency: Option<String>,
+    pub opening_balance: Option<Balance>,
+    pub closing_balance: Option<Balance>,
+    pub entry_count: usize,
+}
+
+/// One booked movement on an account
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatementEntry {
+    /// The bank's reference for the entry
+    pub reference: Option<String>,
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    /// Credits are positive, debits negative
+    pub amount: BigDecimal,
+    /// Defaults to the statement's currency
+    pub currency: Option<String>,
+    pub counterparty: Option<String>,
+    pub counterparty_account: Option<String>,
+    pub end_to_end_id: Option<String>,
+    /// The account holder's own reference, e.g. a cheque number
+    pub customer_reference: Option<String>,
+    pub remittance: Option<String>,
+    /// Free text from the bank when there's no remittance information
+    pub description: Option<String>,
+    pub transaction_code: Option<String>,
+    pub status: Option<String>,
+}
+
+/// Collects statements and hands their entries on as rows
+pub struct StatementSink<'a> {
+    on_row: &'a mut dyn FnMut(RowResult) -> AppResult<()>,
+    index: usize,
+    statements: Vec<StatementInfo>,
+}
+
+impl<'a> StatementSink<'a> {
+    pub fn new(on_row: &'a mut dyn FnMut(RowResult) -> AppResult<()>) -> Self {
+        Self {
+            on_row,
+            index: 0,
+            statements: Vec::new(),
+        }
+    }
+
+    /// Start a statement; entries pushed from now on belong to it
+    pub fn begin(&mut self, statement: StatementInfo) {
+        self.statements.push(statement);
+    }
+
+    /// The statement being read, started implicitly if the file skipped its header
+    pub fn current(&mut self) -> &mut StatementInfo {
+        if self.statements.is_empty() {
+            self.statements.push(StatementInfo::default());
+        }
+        let last = self.statements.len() - 1;
+        &mut self.statements[last]
+    }
+
+    pub fn entry(&mut self, entry: StatementEntry) -> AppResult<()> {
+        self.index += 1;
+        let statement = self.current();
+        statement.entry_count += 1;
+        let fields = entry_fields(entry, statement);
+        (self.on_row)(Ok(ParsedRow {
+            index: self.index,
+            fields,
+        }))
+    }
+
+    /// Report an entry that couldn't be read, with its source text
+    pub fn error(&mut self, message: impl Into<String>, raw: &str) -> AppResult<()> {
+        self.index += 1;
+        (self.on_row)(Err(RowError {
+            index: self.index,
+            message: message.into(),
+            raw: Some(Value::String(raw.to_string())),
+        }))
+    }
+
+    pub fn into_statements(self) -> Vec<StatementInfo> {
+        self.statements
+    }
+}
+
+fn entry_fields(entry: StatementEntry, statement: &StatementInfo) -> Map<String, Value> {
+    let text = |value: Option<String>| value.map(Value::String).unwrap_or(Value::Null);
+    let date = |value: Option<NaiveDate>| {
+        value
+            .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+            .unwrap_or(Value::Null)
+    };
+    let currency = entry.currency.or_else(|| statement.currency.clone());
+    let values = [
+        text(statement.statement_id.clone()),
+        text(statement.account.clone()),
+        text(entry.reference),
+        date(entry.booking_date),
+        date(entry.value_date),
+        entry.amount.to_f64().map(Value::from).unwrap_or(Value::Null),
+        text(currency),
+        text(entry.counterparty),
+        text(entry.counterparty_account),
+        text(entry.end_to_end_id),
+        text(entry.customer_reference),
+        text(entry.remittance.clone()),
+        text(entry.remittance.or(entry.description)),
+        text(entry.transaction_code),
+        text(entry.status),
+    ];
+    STATEMENT_FIELDS
+        .iter()
+        .map(|name| name.to_string())
+        .zip(values)
+        .collect()
+}
+
+/// Read a whole statement file and pass its entries to `on_row`
+pub fn parse_statement<R: Read>(
+    mut reader: R,
+    format: FileFormat,
+    on_row: &mut dyn FnMut(RowResult) -> AppResult<()>,
+) -> AppResult<Vec<StatementInfo>> {
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| AppError::Validation(format!("Failed to read statement: {}", e)))?;
+    let mut sink = StatementSink::new(on_row);
+    match format {
+        FileFormat::Camt => camt::parse_camt(&text, &mut sink)?,
+        FileFormat::Mt940 => mt940::parse_mt940(&text, &mut sink)?,
+        FileFormat::Bai2 => bai2::parse_bai2(&text, &mut sink)?,
+        FileFormat::Ofx => ofx::parse_ofx(&text, &mut sink)?,
+        other => {
+            return Err(AppError::Internal(format!("{} is not a statement format", other.as_str())));
+        }
+    }
+    Ok(sink.into_statements())
+}
+
+/// Decimal amount with a "." or "," separator and an optional sign
+pub fn parse_decimal(text: &str) -> Option<BigDecimal> {
+    let mut text = text.trim().replace(',', ".");
+    if text.ends_with('.') {
+        text.push('0');
+    }
+    if text.starts_with('.') {
+        text.insert(0, '0');
+    }
+    let digits = text.trim_start_matches(['+', '-']);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    BigDecimal::from_str(text.trim_start_matches('+')).ok()
+}
+
+/// Date from the first ten characters of an ISO date or date-time
+pub fn parse_iso_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Date from six digits, YYMMDD
+pub fn parse_yymmdd(text: &str) -> Option<NaiveDate> {
+    if text.len() != 6 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    NaiveDate::parse_from_str(&format!("20{}", text), "%Y%m%d").ok()
+}
+
+/// Text with runs of whitespace collapsed, or `None` if there is none
+pub fn clean_text(text: &str) -> Option<String> {
+    let cleaned = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_become_rows_the_default_mapping_reads() {
+        let mut rows = Vec::new();
+        let mut on_row = |row: RowResult| {
+            rows.push(row);
+            Ok(())
+        };
+        let mut sink = StatementSink::new(&mut on_row);
+        sink.begin(StatementInfo {
+            account: Some("DE89370400440532013000".to_string()),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        });
+        sink.entry(StatementEntry {
+            reference: Some("B-1".to_string()),
+            booking_date: NaiveDate::from_ymd_opt(2026, 3, 2),
+            amount: BigDecimal::from_str("-12.50").unwrap_or_else(|e| panic!("{}", e)),
+            description: Some("Card payment".to_string()),
+            ..Default::default()
+        })
+        .unwrap_or_else(|e| panic!("{}", e));
+        let statements = sink.into_statements();
+        assert_eq!(statements[0].entry_count, 1);
+
+        let row = rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(row.fields["currency"], "EUR");
+        assert_eq!(row.fields["amount"], -12.5);
+        let mapping = super::super::ColumnMapping::default().infer(row.fields.keys().map(String::as_str));
+        let mapped = mapping.map_row(&row.fields).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(mapped.external_id.as_deref(), Some("B-1"));
+        assert_eq!(mapped.transaction_date, NaiveDate::from_ymd_opt(2026, 3, 2));
+        assert_eq!(mapped.description.as_deref(), Some("Card payment"));
+
+        assert_eq!(parse_decimal("1234,"), BigDecimal::from_str("1234").ok());
+        assert_eq!(parse_decimal("-0.5"), BigDecimal::from_str("-0.5").ok());
+        assert_eq!(parse_decimal("12a"), None);
+    }
+}
@@ -0,0 +1,549 @@
+//! Declarative transformation of parsed rows
+//!
+//! A `TransformSpec` is an ordered list of steps run over each row's fields
+//! before the column mapping reads the record columns: renames, type
+//! coercion, sign flips, regex extraction, computed fields and filters.
+//! Specs are saved per data source in numbered versions and compiled into a
+//! `Transformer` once per ingestion, so regexes are checked up front.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::columns::{amount_value, date_value, is_blank, parse_amount_with, value_text, ColumnMapping};
+
+/// A saved mapping: transformation steps and the record column mapping
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransformSpec {
+    #[serde(default)]
+    pub steps: Vec<TransformStep>,
+    /// Which fields feed the record columns once the steps have run
+    #[serde(default)]
+    pub columns: ColumnMapping,
+}
+
+/// One transformation, written as `{"op": "rename", ...}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformStep {
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Read a field as a type; text amounts and dates are normalized
+    Coerce {
+        field: String,
+        #[serde(rename = "type")]
+        to: ValueType,
+        /// chrono format for dates
+        #[serde(default)]
+        format: Option<String>,
+        #[serde(default)]
+        decimal_separator: Option<char>,
+        #[serde(default)]
+        thousands_separator: Option<char>,
+    },
+    /// Negate an amount, for every row or only where `when` holds
+    FlipSign {
+        field: String,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+    /// Copy a regex capture of one field into another
+    Extract {
+        field: String,
+        pattern: String,
+        target: String,
+        /// Capture group by number or name; the first group if there is one
+        #[serde(default)]
+        group: Option<CaptureGroup>,
+    },
+    Compute {
+        target: String,
+        expression: Expression,
+    },
+    /// Skip rows where the condition doesn't hold
+    KeepIf {
+        when: Condition,
+    },
+    /// Skip rows where the condition holds
+    DropIf {
+        when: Condition,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    String,
+    Integer,
+    Decimal,
+    Date,
+    Boolean,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CaptureGroup {
+    Index(usize),
+    Name(String),
+}
+
+/// A computed value, written as `{"template": "..."}` and so on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expression {
+    /// Text with `{field}` placeholders
+    Template(String),
+    /// The first of the fields that isn't empty
+    Coalesce(Vec<String>),
+    /// Sum of amounts; an operand written `-name` is subtracted and empty or
+    /// missing fields count as zero. Operands that aren't fields are read as
+    /// numbers.
+    Sum(Vec<String>),
+    /// Product of amounts; empty if any operand is empty or missing
+    Product(Vec<String>),
+}
+
+/// A test of one field's value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub field: String,
+    #[serde(default)]
+    pub op: ConditionOp,
+    /// Compared value; a list for `in` and `not_in`, a regex for `matches`
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOp {
+    #[default]
+    Equals,
+    NotEquals,
+    In,
+    NotIn,
+    Contains,
+    Matches,
+    IsEmpty,
+    NotEmpty,
+    GreaterThan,
+    LessThan,
+}
+
+/// A condition ready to evaluate
+#[derive(Debug, Clone)]
+struct Test {
+    condition: Condition,
+    regex: Option<Regex>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Rename { from: String, to: String },
+    Coerce { field: String, to: ValueType, format: Option<String>, decimal: Option<char>, thousands: Option<char> },
+    FlipSign { field: String, when: Option<Test> },
+    Extract { field: String, regex: Regex, target: String, group: Option<CaptureGroup> },
+    Compute { target: String, expression: Expression },
+    Keep(Test),
+    Drop(Test),
+}
+
+/// A compiled `TransformSpec`
+#[derive(Debug, Clone)]
+pub struct Transformer {
+    steps: Vec<Step>,
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn compile_condition(condition: &Condition) -> Result<Test, String> {
+    let regex = match condition.op {
+        ConditionOp::Matches => {
+            let pattern = condition
+                .value
+                .as_str()
+                .ok_or_else(|| format!("Condition on '{}' needs a pattern", condition.field))?;
+            Some(compile_regex(pattern)?)
+        }
+        ConditionOp::In | ConditionOp::NotIn if !condition.value.is_array() => {
+            return Err(format!("Condition on '{}' needs a list of values", condition.field));
+        }
+        _ => None,
+    };
+    Ok(Test {
+        condition: condition.clone(),
+        regex,
+    })
+}
+
+impl TransformSpec {
+    /// Check the spec and prepare it to run
+    pub fn compile(&self) -> Result<Transformer, String> {
+        let steps = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                Self::compile_step(step).map_err(|message| format!("Step {}: {}", i + 1, message))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Transformer { steps })
+    }
+
+    fn compile_step(step: &TransformStep) -> Result<Step, String> {
+        Ok(match step {
+            TransformStep::Rename { from, to } => {
+                if to.trim().is_empty() {
+                    return Err(format!("Renaming '{}' needs a new name", from));
+                }
+                Step::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                }
+            }
+            TransformStep::Coerce {
+                field,
+                to,
+                format,
+                decimal_separator,
+                thousands_separator,
+            } => {
+                if decimal_separator.is_some() && decimal_separator == thousands_separator {
+                    return Err(format!("Field '{}' uses the same decimal and thousands separator", field));
+                }
+                // A known thousands separator implies the other decimal point
+                let decimal = decimal_separator.or(match thousands_separator {
+                    Some('.') => Some(','),
+                    Some(_) => Some('.'),
+                    None => None,
+                });
+                Step::Coerce {
+                    field: field.clone(),
+                    to: *to,
+                    format: format.clone(),
+                    decimal,
+                    thousands: *thousands_separator,
+                }
+            }
+            TransformStep::FlipSign { field, when } => Step::FlipSign {
+                field: field.clone(),
+                when: when.as_ref().map(compile_condition).transpose()?,
+            },
+            TransformStep::Extract {
+                field,
+                pattern,
+                target,
+                group,
+            } => {
+                let regex = compile_regex(pattern)?;
+                if let Some(CaptureGroup::Name(name)) = group {
+                    if !regex.capture_names().any(|capture| capture == Some(name.as_str())) {
+                        return Err(format!("Pattern '{}' has no group named '{}'", pattern, name));
+                    }
+                }
+                Step::Extract {
+                    field: field.clone(),
+                    regex,
+                    target: target.clone(),
+                    group: group.clone(),
+                }
+            }
+            TransformStep::Compute { target, expression } => Step::Compute {
+                target: target.clone(),
+                expression: expression.clone(),
+            },
+            TransformStep::KeepIf { when } => Step::Keep(compile_condition(when)?),
+            TransformStep::DropIf { when } => Step::Drop(compile_condition(when)?),
+        })
+    }
+}
+
+impl Transformer {
+    /// Whether the spec had no steps, so rows pass through unchanged
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Run the steps over a row; `Ok(false)` means a filter skipped it
+    pub fn apply(&self, fields: &mut Map<String, Value>) -> Result<bool, String> {
+        for step in &self.steps {
+            match step {
+                Step::Rename { from, to } => {
+                    if let Some(value) = fields.remove(from) {
+                        fields.insert(to.clone(), value);
+                    }
+                }
+                Step::Coerce {
+                    field,
+                    to,
+                    format,
+                    decimal,
+                    thousands,
+                } => {
+                    let Some(value) = fields.get(field) else { continue };
+                    let coerced = coerce(value, *to, format.as_deref(), *decimal, *thousands)
+                        .ok_or_else(|| format!("Can't read {} as {:?} in field '{}'", value, to, field))?;
+                    fields.insert(field.clone(), coerced);
+                }
+                Step::FlipSign { field, when } => {
+                    if when.as_ref().is_some_and(|test| !test.holds(fields)) {
+                        continue;
+                    }
+                    let Some(value) = fields.get(field).filter(|value| !is_blank(value)) else { continue };
+                    let amount = amount_value(value, None)
+                        .ok_or_else(|| format!("Can't flip the sign of {} in field '{}'", value, field))?;
+                    fields.insert(field.clone(), number(-amount));
+                }
+                Step::Extract {
+                    field,
+                    regex,
+                    target,
+                    group,
+                } => {
+                    let text = fields.get(field).and_then(value_text).unwrap_or_default();
+                    let captured = regex.captures(&text).and_then(|captures| {
+                        let capture = match group {
+                            Some(CaptureGroup::Index(index)) => captures.get(*index),
+                            Some(CaptureGroup::Name(name)) => captures.name(name),
+                            None => captures.get(1).or_else(|| captures.get(0)),
+                        };
+                        capture.map(|capture| capture.as_str().to_string())
+                    });
+                    match captured {
+                        Some(text) => {
+                            fields.insert(target.clone(), Value::String(text));
+                        }
+                        None => {
+                            fields.entry(target.clone()).or_insert(Value::Null);
+                        }
+                    }
+                }
+                Step::Compute { target, expression } => {
+                    let value = evaluate(expression, fields)?;
+                    fields.insert(target.clone(), value);
+                }
+                Step::Keep(test) => {
+                    if !test.holds(fields) {
+                        return Ok(false);
+                    }
+                }
+                Step::Drop(test) => {
+                    if test.holds(fields) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+/// `value` as `to`, or `None` if it can't be read that way; blanks become null
+fn coerce(
+    value: &Value,
+    to: ValueType,
+    format: Option<&str>,
+    decimal: Option<char>,
+    thousands: Option<char>,
+) -> Option<Value> {
+    if is_blank(value) {
+        return Some(Value::Null);
+    }
+    match to {
+        ValueType::String => value_text(value).map(Value::String),
+        ValueType::Decimal | ValueType::Integer => {
+            let amount = match value {
+                Value::String(text) => {
+                    let text: String = text.chars().filter(|c| Some(*c) != thousands).collect();
+                    parse_amount_with(&text, decimal)?
+                }
+                other => amount_value(other, decimal)?,
+            };
+            match to {
+                ValueType::Integer if amount.fract() != 0.0 => None,
+                _ => Some(number(amount)),
+            }
+        }
+        ValueType::Date => date_value(value, format).map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+        ValueType::Boolean => {
+            let text = value_text(value)?.to_lowercase();
+            match text.as_str() {
+                "true" | "yes" | "y" | "1" => Some(Value::Bool(true)),
+                "false" | "no" | "n" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn evaluate(expression: &Expression, fields: &Map<String, Value>) -> Result<Value, String> {
+    // A field's amount, a literal number, or `None` when the field is empty or missing
+    let operand = |name: &str| -> Result<Option<f64>, String> {
+        match fields.get(name) {
+            Some(value) if is_blank(value) => Ok(None),
+            Some(value) => amount_value(value, None)
+                .map(Some)
+                .ok_or_else(|| format!("Field '{}' is not a number: {}", name, value)),
+            None => Ok(name.trim().parse().ok()),
+        }
+    };
+    Ok(match expression {
+        Expression::Template(template) => Value::String(fill_template(template, fields)),
+        Expression::Coalesce(names) => names
+            .iter()
+            .filter_map(|name| fields.get(name))
+            .find(|value| !is_blank(value))
+            .cloned()
+            .unwrap_or(Value::Null),
+        Expression::Sum(operands) => {
+            let mut total = 0.0;
+            for name in operands {
+                let literal = name.trim().parse::<f64>().is_ok();
+                let (sign, name) = match name.strip_prefix('-').filter(|_| !literal) {
+                    Some(rest) => (-1.0, rest),
+                    None => (1.0, name.as_str()),
+                };
+                total += sign * operand(name)?.unwrap_or(0.0);
+            }
+            number(total)
+        }
+        Expression::Product(operands) => {
+            let mut product = Some(1.0);
+            for name in operands {
+                product = match (product, operand(name)?) {
+                    (Some(product), Some(value)) => Some(product * value),
+                    _ => None,
+                };
+            }
+            product.map(number).unwrap_or(Value::Null)
+        }
+    })
+}
+
+/// Replace each `{name}` with the field's text; unknown names become empty
+fn fill_template(template: &str, fields: &Map<String, Value>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else { break };
+        out.push_str(&rest[..start]);
+        let name = &rest[start + 1..start + length];
+        out.push_str(&fields.get(name).and_then(value_text).unwrap_or_default());
+        rest = &rest[start + length + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Test {
+    fn holds(&self, fields: &Map<String, Value>) -> bool {
+        let condition = &self.condition;
+        let value = fields.get(&condition.field).filter(|value| !is_blank(value));
+        let text = value.and_then(value_text).unwrap_or_default();
+        let same = |expected: &Value| match (value, expected) {
+            (Some(value), Value::Number(expected)) => {
+                amount_value(value, None).zip(expected.as_f64()).is_some_and(|(a, b)| a == b)
+            }
+            (_, Value::Null) => value.is_none(),
+            (_, expected) => value_text(expected).is_some_and(|expected| expected.eq_ignore_ascii_case(&text)),
+        };
+        let compare = |expected: &Value| {
+            let actual = value.and_then(|value| amount_value(value, None));
+            let expected = expected.as_f64().or_else(|| expected.as_str().and_then(|text| text.parse().ok()));
+            actual.zip(expected)
+        };
+        match condition.op {
+            ConditionOp::Equals => same(&condition.value),
+            ConditionOp::NotEquals => !same(&condition.value),
+            ConditionOp::In => condition.value.as_array().is_some_and(|values| values.iter().any(same)),
+            ConditionOp::NotIn => !condition.value.as_array().is_some_and(|values| values.iter().any(same)),
+            ConditionOp::Contains => value_text(&condition.value)
+                .is_some_and(|needle| text.to_lowercase().contains(&needle.to_lowercase())),
+            ConditionOp::Matches => value.is_some() && self.regex.as_ref().is_some_and(|regex| regex.is_match(&text)),
+            ConditionOp::IsEmpty => value.is_none(),
+            ConditionOp::NotEmpty => value.is_some(),
+            ConditionOp::GreaterThan => compare(&condition.value).is_some_and(|(actual, expected)| actual > expected),
+            ConditionOp::LessThan => compare(&condition.value).is_some_and(|(actual, expected)| actual < expected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => panic!("row must be an object"),
+        }
+    }
+
+    #[test]
+    fn runs_steps_in_order_and_filters() {
+        let spec: TransformSpec = serde_json::from_value(json!({
+            "steps": [
+                {"op": "rename", "from": "Buchungstag", "to": "booked"},
+                {"op": "coerce", "field": "booked", "type": "date", "format": "%d.%m.%Y"},
+                {"op": "coerce", "field": "Betrag", "type": "decimal", "thousands_separator": "."},
+                {"op": "flip_sign", "field": "Betrag", "when": {"field": "S/H", "op": "in", "value": ["S", "soll"]}},
+                {"op": "extract", "field": "Text", "pattern": "(?i)inv[- ]?(?P<number>\\d+)", "target": "invoice", "group": "number"},
+                {"op": "compute", "target": "reference", "expression": {"template": "INV-{invoice}"}},
+                {"op": "compute", "target": "net", "expression": {"sum": ["Betrag", "-Fee", "0.5"]}},
+                {"op": "drop_if", "when": {"field": "Text", "op": "matches", "value": "^Saldo"}}
+            ],
+            "columns": {"amount": "Betrag", "transaction_date": "booked", "external_id": "reference"}
+        }))
+        .unwrap_or_else(|e| panic!("{}", e));
+        let transformer = spec.compile().unwrap_or_else(|e| panic!("{}", e));
+
+        let mut fields = row(json!({
+            "Buchungstag": "31.01.2026", "Betrag": "1.234,50", "S/H": "S", "Fee": "2", "Text": "Payment inv 42"
+        }));
+        assert_eq!(transformer.apply(&mut fields), Ok(true));
+        assert_eq!(fields["booked"], "2026-01-31");
+        assert_eq!(fields["Betrag"], -1234.5);
+        assert_eq!(fields["invoice"], "42");
+        assert_eq!(fields["reference"], "INV-42");
+        assert_eq!(fields["net"], -1236.0);
+        assert!(!fields.contains_key("Buchungstag"));
+
+        let mapped = spec.columns.map_row(&fields).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(mapped.amount, Some(-1234.5));
+        assert_eq!(mapped.external_id.as_deref(), Some("INV-42"));
+
+        let mut balance = row(json!({"Betrag": "10", "Text": "Saldo alt"}));
+        assert_eq!(transformer.apply(&mut balance), Ok(false));
+        let mut bad = row(json!({"Buchungstag": "2026-13-45", "Text": "x"}));
+        assert!(transformer.apply(&mut bad).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        let bad_regex: TransformSpec = serde_json::from_value(json!({
+            "steps": [{"op": "keep_if", "when": {"field": "a", "op": "matches", "value": "("}}]
+        }))
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert!(bad_regex.compile().is_err_and(|e| e.starts_with("Step 1:")));
+
+        let bad_group: TransformSpec = serde_json::from_value(json!({
+            "steps": [{"op": "extract", "field": "a", "pattern": "(\\d+)", "target": "b", "group": "id"}]
+        }))
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert!(bad_group.compile().is_err());
+        assert!(serde_json::from_value::<TransformSpec>(json!({"steps": [{"op": "explode"}]})).is_err());
+    }
+}
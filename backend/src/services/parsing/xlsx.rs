@@ -0,0 +1,370 @@
+//! Office Open XML workbooks (.xlsx, .xlsm)
+//!
+//! Cell text lives in a shared string table and dates are plain numbers that
+//! only a cell style marks as dates, so both are loaded before a sheet.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use chrono::NaiveDateTime;
+use zip::ZipArchive;
+
+use crate::errors::{AppError, AppResult};
+
+use super::spreadsheet::{
+    choose_sheet, excel_serial_date, parse_cell_reference, read_entry, Cell, CellRange, Sheet, SheetRow,
+    MAX_COLUMNS,
+};
+use super::xml::{XmlEvent, XmlReader};
+
+/// Built-in number formats that display dates or times
+fn is_builtin_date_format(id: u32) -> bool {
+    matches!(id, 14..=22 | 27..=36 | 45..=47 | 50..=58)
+}
+
+/// Whether a custom number format code displays a date or time
+fn is_date_format_code(code: &str) -> bool {
+    let mut plain = String::new();
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // Quoted literals, escaped characters and [colour]/[$-locale] sections
+            '"' => for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+            },
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            '[' => for c in chars.by_ref() {
+                if c == ']' {
+                    break;
+                }
+            },
+            c => plain.push(c.to_ascii_lowercase()),
+        }
+    }
+    plain.contains(['d', 'm', 'y', 'h', 's'])
+}
+
+/// Workbook-level data every sheet needs
+struct Workbook {
+    /// Sheet names with their archive paths, in tab order
+    sheets: Vec<(String, String)>,
+    date1904: bool,
+    shared_strings: Vec<String>,
+    /// Per cell style index, whether it formats dates
+    date_styles: Vec<bool>,
+}
+
+fn load_workbook<R: Read + Seek>(archive: &mut ZipArchive<R>) -> AppResult<Workbook> {
+    let workbook_xml = read_entry(archive, "xl/workbook.xml")?
+        .ok_or_else(|| AppError::Validation("Workbook has no xl/workbook.xml".to_string()))?;
+    let relations_xml = read_entry(archive, "xl/_rels/workbook.xml.rels")?.unwrap_or_default();
+
+    let mut targets = HashMap::new();
+    for event in XmlReader::new(&relations_xml) {
+        let event = event?;
+        if let XmlEvent::Start { name: "Relationship", .. } = &event {
+            if let (Some(id), Some(target)) = (event.attribute("Id"), event.attribute("Target")) {
+                let path = match target.strip_prefix('/') {
+                    Some(absolute) => absolute.to_string(),
+                    None => format!("xl/{}", target),
+                };
+                targets.insert(id.to_string(), path);
+            }
+        }
+    }
+
+    let mut sheets = Vec::new();
+    let mut date1904 = false;
+    for event in XmlReader::new(&workbook_xml) {
+        let event = event?;
+        match event {
+            XmlEvent::Start { name: "workbookPr", .. } => {
+                date1904 = matches!(event.attribute("date1904"), Some("1" | "true"));
+            }
+            XmlEvent::Start { name: "sheet", .. } => {
+                let name = event.attribute("name").unwrap_or_default().to_string();
+                if let Some(path) = event.attribute("id").and_then(|id| targets.get(id)) {
+                    sheets.push((name, path.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Workbook {
+        sheets,
+        date1904,
+        shared_strings: match read_entry(archive, "xl/sharedStrings.xml")? {
+            Some(xml) => load_shared_strings(&xml)?,
+            None => Vec::new(),
+        },
+        date_styles: match read_entry(archive, "xl/styles.xml")? {
+            Some(xml) => load_date_styles(&xml)?,
+            None => Vec::new(),
+        },
+    })
+}
+
+fn load_shared_strings(xml: &str) -> AppResult<Vec<String>> {
+    let mut strings = Vec::new();
+    let mut reader = XmlReader::new(xml);
+    let mut current: Option<String> = None;
+    let mut phonetic_depth = 0usize;
+    while let Some(event) = reader.next() {
+        match event? {
+            XmlEvent::Start { name: "si", .. } => current = Some(String::new()),
+            // Phonetic guides repeat the text in another script
+            XmlEvent::Start { name: "rPh", .. } => phonetic_depth += 1,
+            XmlEvent::End { name: "rPh" } => phonetic_depth = phonetic_depth.saturating_sub(1),
+            XmlEvent::Start { name: "t", .. } if phonetic_depth == 0 => {
+                let text = reader.read_text()?;
+                if let Some(current) = current.as_mut() {
+                    current.push_str(&text);
+                }
+            }
+            XmlEvent::End { name: "si" } => strings.push(current.take().unwrap_or_default()),
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+fn load_date_styles(xml: &str) -> AppResult<Vec<bool>> {
+    let mut custom_dates = HashMap::new();
+    let mut styles = Vec::new();
+    let mut in_cell_formats = false;
+    for event in XmlReader::new(xml) {
+        let event = event?;
+        match event {
+            XmlEvent::Start { name: "numFmt", .. } => {
+                if let (Some(id), Some(code)) = (event.attribute("numFmtId"), event.attribute("formatCode")) {
+                    if let Ok(id) = id.parse::<u32>() {
+                        custom_dates.insert(id, is_date_format_code(code));
+                    }
+                }
+            }
+            XmlEvent::Start { name: "cellXfs", .. } => in_cell_formats = true,
+            XmlEvent::End { name: "cellXfs" } => in_cell_formats = false,
+            XmlEvent::Start { name: "xf", .. } if in_cell_formats => {
+                let id = event.attribute("numFmtId").and_then(|id| id.parse::<u32>().ok()).unwrap_or(0);
+                styles.push(custom_dates.get(&id).copied().unwrap_or_else(|| is_builtin_date_format(id)));
+            }
+            _ => {}
+        }
+    }
+    Ok(styles)
+}
+
+/// Read one sheet of an XLSX workbook
+///
+/// `wanted` picks the sheet by name or position; otherwise the first sheet
+/// with data is read.
+pub fn read_sheet<R: Read + Seek>(archive: &mut ZipArchive<R>, wanted: Option<&str>) -> AppResult<Sheet> {
+    let workbook = load_workbook(archive)?;
+    let names: Vec<String> = workbook.sheets.iter().map(|(name, _)| name.clone()).collect();
+    if let Some(index) = choose_sheet(&names, wanted)? {
+        let (name, path) = &workbook.sheets[index];
+        return load_sheet(archive, &workbook, name, path);
+    }
+    let mut first = None;
+    for (name, path) in &workbook.sheets {
+        let sheet = load_sheet(archive, &workbook, name, path)?;
+        if !sheet.rows.is_empty() {
+            return Ok(sheet);
+        }
+        first.get_or_insert(sheet);
+    }
+    first.ok_or_else(|| AppError::Validation("Workbook has no sheets".to_string()))
+}
+
+fn load_sheet<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    workbook: &Workbook,
+    name: &str,
+    path: &str,
+) -> AppResult<Sheet> {
+    let xml = read_entry(archive, path)?
+        .ok_or_else(|| AppError::Validation(format!("Sheet '{}' is missing from the workbook", name)))?;
+    let mut sheet = Sheet {
+        name: name.to_string(),
+        ..Default::default()
+    };
+
+    let mut reader = XmlReader::new(&xml);
+    let mut row_number = 0usize;
+    let mut cells: Vec<Cell> = Vec::new();
+    while let Some(event) = reader.next() {
+        let event = event?;
+        match event {
+            XmlEvent::Start { name: "row", .. } => {
+                row_number = event
+                    .attribute("r")
+                    .and_then(|r| r.parse().ok())
+                    .unwrap_or(row_number + 1);
+                cells.clear();
+            }
+            XmlEvent::Start { name: "c", .. } => {
+                let column = event
+                    .attribute("r")
+                    .and_then(parse_cell_reference)
+                    .map(|(column, _)| column)
+                    .unwrap_or(cells.len());
+                let cell_type = event.attribute("t").unwrap_or("n").to_string();
+                let style = event.attribute("s").and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
+                let value = read_cell_value(&mut reader)?;
+                let cell = convert_cell(workbook, &cell_type, style, value);
+                if column < MAX_COLUMNS && !cell.is_empty() {
+                    if cells.len() <= column {
+                        cells.resize(column + 1, Cell::Empty);
+                    }
+                    cells[column] = cell;
+                }
+            }
+            XmlEvent::End { name: "row" } if cells.iter().any(|cell| !cell.is_empty()) => {
+                sheet.rows.push(SheetRow {
+                    number: row_number,
+                    cells: std::mem::take(&mut cells),
+                });
+            }
+            XmlEvent::Start { name: "mergeCell", .. } => {
+                if let Some(range) = event.attribute("ref").and_then(parse_range) {
+                    sheet.merged.push(range);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(sheet)
+}
+
+/// Raw value of the `<c>` element just opened: its `<v>` or inline string text
+fn read_cell_value(reader: &mut XmlReader<'_>) -> AppResult<Option<String>> {
+    let mut value = None;
+    while let Some(event) = reader.next() {
+        match event? {
+            XmlEvent::Start { name: "v", .. } => value = Some(reader.read_text()?),
+            XmlEvent::Start { name: "is", .. } => value = Some(reader.read_text()?),
+            // Formulas are kept as their cached result
+            XmlEvent::Start { name: "f", .. } => {
+                reader.read_text()?;
+            }
+            XmlEvent::End { name: "c" } => break,
+            _ => {}
+        }
+    }
+    Ok(value)
+}
+
+fn convert_cell(workbook: &Workbook, cell_type: &str, style: usize, value: Option<String>) -> Cell {
+    let Some(value) = value else { return Cell::Empty };
+    match cell_type {
+        "s" => value
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| workbook.shared_strings.get(index))
+            .map(|text| Cell::Text(text.clone()))
+            .unwrap_or(Cell::Empty),
+        "str" | "inlineStr" | "e" => Cell::Text(value),
+        "b" => Cell::Bool(value.trim() == "1"),
+        "d" => NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| {
+                chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                    .map(|date| date.and_time(chrono::NaiveTime::MIN))
+            })
+            .map(Cell::Date)
+            .unwrap_or(Cell::Text(value)),
+        _ => match value.trim().parse::<f64>() {
+            Ok(number) if workbook.date_styles.get(style).copied().unwrap_or(false) => {
+                excel_serial_date(number, workbook.date1904)
+                    .map(Cell::Date)
+                    .unwrap_or(Cell::Number(number))
+            }
+            Ok(number) => Cell::Number(number),
+            Err(_) => Cell::Text(value),
+        },
+    }
+}
+
+/// "A1:C2" as a cell range
+fn parse_range(reference: &str) -> Option<CellRange> {
+    let (start, end) = reference.split_once(':')?;
+    let (first_column, first_row) = parse_cell_reference(start)?;
+    let (last_column, last_row) = parse_cell_reference(end)?;
+    Some(CellRange {
+        first_row,
+        first_column,
+        last_row,
+        last_column,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    fn workbook(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap_or_else(|e| panic!("{}", e));
+            writer.write_all(content.as_bytes()).unwrap_or_else(|e| panic!("{}", e));
+        }
+        let bytes = writer.finish().unwrap_or_else(|e| panic!("{}", e)).into_inner();
+        ZipArchive::new(Cursor::new(bytes)).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn date_format_codes() {
+        assert!(is_date_format_code("dd/mm/yyyy"));
+        assert!(is_date_format_code("[$-407]d. mmmm yyyy;@"));
+        assert!(!is_date_format_code("#,##0.00 [$€-407]"));
+        assert!(!is_date_format_code("\"Days: \"0"));
+        assert!(!is_date_format_code("General"));
+    }
+
+    #[test]
+    fn reads_shared_strings_dates_and_merges() {
+        let mut archive = workbook(&[
+            (
+                "xl/workbook.xml",
+                r#"<workbook><sheets><sheet name="Cover" sheetId="1" r:id="rId1"/><sheet name="Data" sheetId="2" r:id="rId2"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Target="/xl/worksheets/sheet2.xml"/></Relationships>"#,
+            ),
+            ("xl/sharedStrings.xml", r#"<sst><si><t>Date</t></si><si><r><t>Amo</t></r><r><t>unt</t></r></si></sst>"#),
+            (
+                "xl/styles.xml",
+                r#"<styleSheet><numFmts><numFmt numFmtId="164" formatCode="dd/mm/yyyy"/></numFmts><cellXfs><xf numFmtId="0"/><xf numFmtId="164"/></cellXfs></styleSheet>"#,
+            ),
+            ("xl/worksheets/sheet1.xml", r#"<worksheet><sheetData/></worksheet>"#),
+            (
+                "xl/worksheets/sheet2.xml",
+                r#"<worksheet><sheetData><row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row><row r="2"><c r="A2" s="1"><v>45323</v></c><c r="C2"><f>1+1</f><v>-12.5</v></c></row></sheetData><mergeCells><mergeCell ref="A1:A2"/></mergeCells></worksheet>"#,
+            ),
+        ]);
+
+        let sheet = read_sheet(&mut archive, None).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(sheet.name, "Data");
+        assert_eq!(sheet.rows[0].cells, vec![Cell::Text("Date".into()), Cell::Text("Amount".into())]);
+        assert_eq!(
+            sheet.rows[1].cells[0],
+            Cell::Date(chrono::NaiveDate::from_ymd_opt(2024, 2, 1).unwrap_or_default().and_time(chrono::NaiveTime::MIN))
+        );
+        assert_eq!(sheet.rows[1].cells[2], Cell::Number(-12.5));
+        assert_eq!(sheet.merged.len(), 1);
+
+        assert!(read_sheet(&mut archive, Some("Missing")).is_err());
+        let cover = read_sheet(&mut archive, Some("1")).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(cover.name, "Cover");
+    }
+}
@@ -0,0 +1,266 @@
+//! Minimal pull reader over `xmlparser` tokens
+//!
+//! Spreadsheet and bank statement formats only need element names, attribute
+//! values and text, so namespace prefixes are dropped and entities are
+//! unescaped. Self-closing elements produce a `Start` followed by an `End`.
+//! Small, repeated structures such as statement entries can be read into an
+//! `Element` tree one at a time.
+
+use std::borrow::Cow;
+
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::errors::{AppError, AppResult};
+
+/// Attribute names (without prefix) and unescaped values
+pub type Attributes<'a> = Vec<(&'a str, Cow<'a, str>)>;
+
+/// A step through an XML document
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlEvent<'a> {
+    Start {
+        name: &'a str,
+        attributes: Attributes<'a>,
+    },
+    End {
+        name: &'a str,
+    },
+    Text(Cow<'a, str>),
+}
+
+impl<'a> XmlEvent<'a> {
+    /// Value of the named attribute on a `Start` event
+    pub fn attribute(&self, wanted: &str) -> Option<&str> {
+        match self {
+            XmlEvent::Start { attributes, .. } => attributes
+                .iter()
+                .find(|(name, _)| *name == wanted)
+                .map(|(_, value)| value.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// An element read whole, with its children
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    /// Text directly inside the element, not in its children
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s Element> + 's {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Descendant reached by following the first child with each name
+    pub fn find(&self, path: &[&str]) -> Option<&Element> {
+        path.iter().try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Trimmed text of the descendant at `path`, if it has any
+    pub fn text_at(&self, path: &[&str]) -> Option<&str> {
+        self.find(path)
+            .map(|element| element.text.trim())
+            .filter(|text| !text.is_empty())
+    }
+
+    pub fn attribute(&self, wanted: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == wanted)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Iterator of `XmlEvent`s over a document held in memory
+pub struct XmlReader<'a> {
+    tokens: Tokenizer<'a>,
+    open: Option<(&'a str, Attributes<'a>)>,
+    /// End event owed for a self-closing element
+    pending_end: Option<&'a str>,
+}
+
+impl<'a> XmlReader<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            tokens: Tokenizer::from(text),
+            open: None,
+            pending_end: None,
+        }
+    }
+
+    /// Text up to the end of the element whose `Start` was just read
+    ///
+    /// Text in nested elements is included.
+    pub fn read_text(&mut self) -> AppResult<String> {
+        let mut text = String::new();
+        let mut depth = 0usize;
+        for event in self.by_ref() {
+            match event? {
+                XmlEvent::Start { .. } => depth += 1,
+                XmlEvent::End { .. } if depth == 0 => break,
+                XmlEvent::End { .. } => depth -= 1,
+                XmlEvent::Text(part) => text.push_str(&part),
+            }
+        }
+        Ok(text)
+    }
+
+    /// The element whose `Start` was just read, up to its end tag
+    pub fn read_element(&mut self, name: &str, attributes: &Attributes<'_>) -> AppResult<Element> {
+        let mut stack = vec![Element {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        }];
+        for event in self.by_ref() {
+            match event? {
+                XmlEvent::Start { name, attributes } => {
+                    let element = Element {
+                        name: name.to_string(),
+                        attributes: attributes
+                            .into_iter()
+                            .map(|(name, value)| (name.to_string(), value.into_owned()))
+                            .collect(),
+                        ..Default::default()
+                    };
+                    stack.push(element);
+                }
+                XmlEvent::End { .. } => {
+                    let Some(element) = stack.pop() else { break };
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                XmlEvent::Text(part) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&part);
+                    }
+                }
+            }
+        }
+        Err(AppError::Validation(format!("Unexpected end of XML inside <{}>", name)))
+    }
+}
+
+impl<'a> Iterator for XmlReader<'a> {
+    type Item = AppResult<XmlEvent<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(name) = self.pending_end.take() {
+            return Some(Ok(XmlEvent::End { name }));
+        }
+        for token in self.tokens.by_ref() {
+            let token = match token {
+                Ok(token) => token,
+                Err(e) => return Some(Err(AppError::Validation(format!("Invalid XML: {}", e)))),
+            };
+            match token {
+                Token::ElementStart { local, .. } => self.open = Some((local.as_str(), Vec::new())),
+                Token::Attribute { local, value, .. } => {
+                    if let Some((_, attributes)) = self.open.as_mut() {
+                        attributes.push((local.as_str(), unescape(value.as_str())));
+                    }
+                }
+                Token::ElementEnd { end, .. } => match end {
+                    ElementEnd::Open | ElementEnd::Empty => {
+                        let (name, attributes) = self.open.take()?;
+                        if matches!(end, ElementEnd::Empty) {
+                            self.pending_end = Some(name);
+                        }
+                        return Some(Ok(XmlEvent::Start { name, attributes }));
+                    }
+                    ElementEnd::Close(_, local) => return Some(Ok(XmlEvent::End { name: local.as_str() })),
+                },
+                Token::Text { text } => return Some(Ok(XmlEvent::Text(unescape(text.as_str())))),
+                Token::Cdata { text, .. } => return Some(Ok(XmlEvent::Text(Cow::Borrowed(text.as_str())))),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Replace the predefined and numeric character entities
+pub fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_elements_attributes_and_text() {
+        let xml = r#"<?xml version="1.0"?><x:row r="2"><x:c t="s"/><v>A &amp; B &#x20AC;</v></x:row>"#;
+        let events: Vec<XmlEvent> = XmlReader::new(xml)
+            .collect::<AppResult<_>>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(events[0].attribute("r"), Some("2"));
+        assert_eq!(events[1].attribute("t"), Some("s"));
+        assert_eq!(events[2], XmlEvent::End { name: "c" });
+        assert_eq!(events[4], XmlEvent::Text(Cow::Owned("A & B €".to_string())));
+        assert_eq!(unescape("a &unknown; b"), "a &unknown; b");
+    }
+
+    #[test]
+    fn reads_an_element_tree() {
+        let xml = r#"<Stmt><Ntry><Amt Ccy="EUR">12.50</Amt><Dtls><Ref>A</Ref><Ref>B</Ref></Dtls></Ntry><Next/></Stmt>"#;
+        let mut reader = XmlReader::new(xml);
+        let Some(Ok(XmlEvent::Start { name, attributes })) = reader.nth(1) else {
+            panic!("expected a start tag")
+        };
+        let entry = reader.read_element(name, &attributes).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(entry.text_at(&["Amt"]), Some("12.50"));
+        assert_eq!(entry.find(&["Amt"]).and_then(|amount| amount.attribute("Ccy")), Some("EUR"));
+        assert_eq!(entry.find(&["Dtls"]).map(|d| d.children_named("Ref").count()), Some(2));
+        assert!(matches!(reader.next(), Some(Ok(XmlEvent::Start { name: "Next", .. }))));
+    }
+}
@@ -1,9 +1,18 @@
 //! Permission-focused facade for project access checks.
+//!
+//! Also manages who holds which role in a project: users directly through
+//! `project_members`, and teams through `project_teams`.
 
-use crate::database::Database;
-use crate::errors::AppResult;
+use chrono::Utc;
+use diesel::prelude::*;
 use uuid::Uuid;
 
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{project_members, project_teams, teams, users};
+use crate::models::{NewProjectMember, NewProjectTeam, ProjectMember, ProjectTeam};
+use crate::services::auth::{ProjectAction, ProjectRole};
+
 pub struct ProjectPermissionService {
     db: Database,
 }
@@ -16,4 +25,175 @@ impl ProjectPermissionService {
     pub fn check_project_permission(&self, user_id: Uuid, project_id: Uuid) -> AppResult<()> {
         crate::utils::check_project_permission(&self.db, user_id, project_id)
     }
+
+    pub fn check_project_action(
+        &self,
+        user_id: Uuid,
+        project_id: Uuid,
+        action: ProjectAction,
+    ) -> AppResult<()> {
+        crate::utils::check_project_action(&self.db, user_id, project_id, action)
+    }
+
+    pub fn project_roles(&self, user_id: Uuid, project_id: Uuid) -> AppResult<Vec<ProjectRole>> {
+        crate::utils::project_roles(&self.db, user_id, project_id)
+    }
+
+    /// Active direct members of a project
+    pub fn list_members(&self, project_id: Uuid) -> AppResult<Vec<ProjectMember>> {
+        let mut conn = self.db.get_connection()?;
+        project_members::table
+            .filter(project_members::project_id.eq(project_id))
+            .filter(project_members::is_active.eq(true))
+            .order(project_members::joined_at.asc())
+            .load::<ProjectMember>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Give a user `role` in a project, adding them or reactivating their
+    /// membership if needed
+    pub fn set_member_role(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+        role: ProjectRole,
+        granted_by: Uuid,
+    ) -> AppResult<ProjectMember> {
+        let mut conn = self.db.get_connection()?;
+        conn.transaction::<_, AppError, _>(|tx| {
+            let user_exists = users::table
+                .find(user_id)
+                .select(users::id)
+                .first::<Uuid>(tx)
+                .optional()?
+                .is_some();
+            if !user_exists {
+                return Err(AppError::NotFound(format!("User {} not found", user_id)));
+            }
+
+            let existing = project_members::table
+                .filter(project_members::project_id.eq(project_id))
+                .filter(project_members::user_id.eq(user_id))
+                .select(project_members::id)
+                .for_update()
+                .first::<Uuid>(tx)
+                .optional()?;
+
+            let member = match existing {
+                Some(member_id) => diesel::update(project_members::table.find(member_id))
+                    .set((
+                        project_members::role.eq(role.to_string()),
+                        project_members::is_active.eq(true),
+                        project_members::updated_at.eq(Utc::now()),
+                    ))
+                    .get_result::<ProjectMember>(tx)?,
+                None => diesel::insert_into(project_members::table)
+                    .values(&NewProjectMember {
+                        project_id,
+                        user_id,
+                        role: role.to_string(),
+                        permissions: serde_json::json!({}),
+                        invited_by: granted_by,
+                        is_active: true,
+                    })
+                    .get_result::<ProjectMember>(tx)?,
+            };
+            Ok(member)
+        })
+    }
+
+    /// Remove a user's direct membership; roles held through teams remain
+    pub fn remove_member(&self, project_id: Uuid, user_id: Uuid) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        let rows = diesel::update(
+            project_members::table
+                .filter(project_members::project_id.eq(project_id))
+                .filter(project_members::user_id.eq(user_id))
+                .filter(project_members::is_active.eq(true)),
+        )
+        .set((
+            project_members::is_active.eq(false),
+            project_members::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+
+        if rows == 0 {
+            return Err(AppError::NotFound(format!(
+                "User {} is not a member of this project",
+                user_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Teams granted a role in a project
+    pub fn list_team_grants(&self, project_id: Uuid) -> AppResult<Vec<ProjectTeam>> {
+        let mut conn = self.db.get_connection()?;
+        project_teams::table
+            .filter(project_teams::project_id.eq(project_id))
+            .order(project_teams::created_at.asc())
+            .load::<ProjectTeam>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Give every member of a team `role` in a project, replacing any role
+    /// the team already had there
+    pub fn grant_team(
+        &self,
+        project_id: Uuid,
+        team_id: Uuid,
+        role: ProjectRole,
+        granted_by: Uuid,
+    ) -> AppResult<ProjectTeam> {
+        let mut conn = self.db.get_connection()?;
+
+        let team_exists = teams::table
+            .find(team_id)
+            .select(teams::id)
+            .first::<Uuid>(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?
+            .is_some();
+        if !team_exists {
+            return Err(AppError::NotFound(format!("Team {} not found", team_id)));
+        }
+
+        diesel::insert_into(project_teams::table)
+            .values(&NewProjectTeam {
+                project_id,
+                team_id,
+                role: role.to_string(),
+                granted_by: Some(granted_by),
+            })
+            .on_conflict((project_teams::project_id, project_teams::team_id))
+            .do_update()
+            .set((
+                project_teams::role.eq(role.to_string()),
+                project_teams::granted_by.eq(Some(granted_by)),
+                project_teams::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<ProjectTeam>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Withdraw a team's role in a project
+    pub fn revoke_team(&self, project_id: Uuid, team_id: Uuid) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        let rows = diesel::delete(
+            project_teams::table
+                .filter(project_teams::project_id.eq(project_id))
+                .filter(project_teams::team_id.eq(team_id)),
+        )
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+
+        if rows == 0 {
+            return Err(AppError::NotFound(format!(
+                "Team {} has no role in this project",
+                team_id
+            )));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,447 @@
+//! Aggregate (split / many-to-one) matching for reconciliation
+//!
+//! One bank line often settles several invoices, and one invoice is sometimes
+//! paid in instalments. After one-to-one matching, the records left unmatched
+//! on both sides are grouped by a key (customer, reference prefix, date
+//! bucket) and, within each group, combinations of records whose amounts net
+//! to a single counterpart within tolerance are linked as one N:1 or 1:N match.
+//!
+//! Combinations of several records on both sides (N:M, e.g. two payments
+//! settling three invoices) are not searched for; such records stay unmatched.
+
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::matching::{parse_amount, parse_date};
+use super::scoring::value_to_match_string;
+use super::types::{AmountTolerance, ReconciliationRecord};
+
+/// Amounts are compared in units of 1/10_000 so the subset search can use integers
+const AMOUNT_SCALE: i64 = 10_000;
+
+/// Search nodes explored per counterpart before giving up on it
+const MAX_SEARCH_NODES: usize = 100_000;
+
+/// Widest date bucket honoured, a century; wider ones are clamped to it
+const MAX_WINDOW_DAYS: i64 = 36_500;
+
+/// Key records must share to be combined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GroupKey {
+    /// Same value of `field` (e.g. customer id)
+    Field { field: String },
+    /// Same first `length` characters of `field` (e.g. reference prefix)
+    Prefix { field: String, length: usize },
+    /// Dates in `field` fall into the same `window_days` wide bucket
+    DateWindow { field: String, window_days: i64 },
+}
+
+impl GroupKey {
+    fn key_for(&self, record: &ReconciliationRecord) -> Option<String> {
+        match self {
+            GroupKey::Field { field } => record
+                .fields
+                .get(field)
+                .map(value_to_match_string)
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty()),
+            GroupKey::Prefix { field, length } => record
+                .fields
+                .get(field)
+                .map(value_to_match_string)
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.chars().take(*length).collect()),
+            GroupKey::DateWindow { field, window_days } => {
+                let date = parse_date(&value_to_match_string(record.fields.get(field)?))?;
+                // Clamped first, so a huge width isn't truncated to a small one
+                let width = i32::try_from((*window_days).clamp(1, MAX_WINDOW_DAYS)).unwrap_or(1);
+                let bucket = date.num_days_from_ce().div_euclid(width);
+                Some(bucket.to_string())
+            }
+        }
+    }
+}
+
+/// Which side may contribute several records to one match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateDirection {
+    /// Several source A records against one source B record
+    ManyToOne,
+    /// One source A record against several source B records
+    OneToMany,
+    /// Try many-to-one first, then one-to-many on what is left
+    #[default]
+    Both,
+}
+
+fn default_max_group_size() -> usize {
+    5
+}
+
+/// Aggregate matching configuration for a reconciliation job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateConfig {
+    /// Field holding the amount on both sides
+    pub amount_field: String,
+    /// All keys must agree for records to be combined; empty puts every record in one group
+    #[serde(default)]
+    pub group_by: Vec<GroupKey>,
+    /// Allowed difference between the combined amount and the counterpart; exact when absent
+    #[serde(default)]
+    pub tolerance: Option<AmountTolerance>,
+    /// Maximum number of records combined into one match
+    #[serde(default = "default_max_group_size")]
+    pub max_group_size: usize,
+    #[serde(default)]
+    pub direction: AggregateDirection,
+}
+
+impl AggregateConfig {
+    fn group_key(&self, record: &ReconciliationRecord) -> Option<String> {
+        let mut parts = Vec::with_capacity(self.group_by.len());
+        for key in &self.group_by {
+            parts.push(key.key_for(record)?);
+        }
+        Some(parts.join("|"))
+    }
+
+    fn amount_units(&self, record: &ReconciliationRecord) -> Option<i128> {
+        let amount = parse_amount(&value_to_match_string(record.fields.get(&self.amount_field)?))?;
+        to_units(&amount)
+    }
+
+    fn allowed_units(&self, target: i128) -> i128 {
+        match &self.tolerance {
+            None => 0,
+            Some(AmountTolerance::Absolute(value)) => to_units(&value.abs()).unwrap_or(0),
+            Some(AmountTolerance::Percentage(percent)) => {
+                let percent = percent.to_f64().unwrap_or(0.0).max(0.0);
+                (target.abs() as f64 * percent / 100.0) as i128
+            }
+        }
+    }
+}
+
+fn to_units(amount: &BigDecimal) -> Option<i128> {
+    (amount * BigDecimal::from(AMOUNT_SCALE)).round(0).to_i128()
+}
+
+fn from_units(units: i128) -> BigDecimal {
+    BigDecimal::from(units) / BigDecimal::from(AMOUNT_SCALE)
+}
+
+/// An N:1 or 1:N link between source A and source B records
+///
+/// One side always holds exactly one record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateMatch {
+    pub group_id: Uuid,
+    /// Group key the records shared
+    pub group_key: String,
+    pub source_record_ids: Vec<Uuid>,
+    pub target_record_ids: Vec<Uuid>,
+    pub source_total: BigDecimal,
+    pub target_total: BigDecimal,
+    /// `source_total - target_total`
+    pub difference: BigDecimal,
+    pub confidence_score: f64,
+}
+
+impl AggregateMatch {
+    pub fn is_many_to_one(&self) -> bool {
+        self.source_record_ids.len() > 1
+    }
+
+    /// Result match type for the links of this group
+    pub fn match_type(&self) -> &'static str {
+        if self.is_many_to_one() {
+            "many_to_one"
+        } else {
+            "one_to_many"
+        }
+    }
+}
+
+/// A record with its parsed amount
+struct Entry<'a> {
+    record: &'a ReconciliationRecord,
+    units: i128,
+}
+
+/// Find aggregate matches among records left unmatched by one-to-one matching
+///
+/// Each record is used in at most one aggregate match.
+pub fn find_aggregate_matches(
+    config: &AggregateConfig,
+    records_a: &[ReconciliationRecord],
+    records_b: &[ReconciliationRecord],
+) -> Vec<AggregateMatch> {
+    let max_size = config.max_group_size.max(2);
+    let groups_a = group_entries(config, records_a);
+    let groups_b = group_entries(config, records_b);
+
+    let mut keys: Vec<&String> = groups_a.keys().filter(|k| groups_b.contains_key(*k)).collect();
+    keys.sort();
+
+    let mut matches = Vec::new();
+    for key in keys {
+        let (group_a, group_b) = (&groups_a[key], &groups_b[key]);
+        let mut used_a: HashSet<Uuid> = HashSet::new();
+        let mut used_b: HashSet<Uuid> = HashSet::new();
+
+        if config.direction != AggregateDirection::OneToMany {
+            for (target, parts) in combine(config, group_b, group_a, &mut used_b, &mut used_a, max_size) {
+                matches.push(build_match(config, key, &parts, &[target]));
+            }
+        }
+        if config.direction != AggregateDirection::ManyToOne {
+            for (target, parts) in combine(config, group_a, group_b, &mut used_a, &mut used_b, max_size) {
+                matches.push(build_match(config, key, &[target], &parts));
+            }
+        }
+    }
+    matches
+}
+
+fn group_entries<'a>(
+    config: &AggregateConfig,
+    records: &'a [ReconciliationRecord],
+) -> HashMap<String, Vec<Entry<'a>>> {
+    let mut groups: HashMap<String, Vec<Entry<'a>>> = HashMap::new();
+    for record in records {
+        if let (Some(key), Some(units)) = (config.group_key(record), config.amount_units(record)) {
+            groups.entry(key).or_default().push(Entry { record, units });
+        }
+    }
+    groups
+}
+
+/// For each unused target, look for 2..=max_size unused parts netting to it
+///
+/// Targets are taken largest first so big settlements get first pick of the
+/// parts. Returns (target, parts) pairs and marks their records used.
+fn combine<'a>(
+    config: &AggregateConfig,
+    targets: &[Entry<'a>],
+    parts: &[Entry<'a>],
+    used_targets: &mut HashSet<Uuid>,
+    used_parts: &mut HashSet<Uuid>,
+    max_size: usize,
+) -> Vec<(&'a ReconciliationRecord, Vec<&'a ReconciliationRecord>)> {
+    let mut order: Vec<&Entry> = targets.iter().collect();
+    order.sort_by(|x, y| y.units.abs().cmp(&x.units.abs()).then(x.record.id.cmp(&y.record.id)));
+
+    let mut found = Vec::new();
+    for target in order {
+        if used_targets.contains(&target.record.id) || target.units == 0 {
+            continue;
+        }
+        // Only same-sign parts can add up towards the target, which keeps the search prunable
+        let mut available: Vec<&Entry> = parts
+            .iter()
+            .filter(|p| !used_parts.contains(&p.record.id))
+            .filter(|p| p.units != 0 && p.units.signum() == target.units.signum())
+            .collect();
+        available.sort_by(|x, y| y.units.abs().cmp(&x.units.abs()).then(x.record.id.cmp(&y.record.id)));
+        let amounts: Vec<i128> = available.iter().map(|p| p.units.abs()).collect();
+
+        let goal = target.units.abs();
+        let allowed = config.allowed_units(target.units);
+        if let Some(chosen) = subset_sum(&amounts, goal, allowed, max_size) {
+            used_targets.insert(target.record.id);
+            let records: Vec<&ReconciliationRecord> = chosen
+                .into_iter()
+                .map(|i| {
+                    used_parts.insert(available[i].record.id);
+                    available[i].record
+                })
+                .collect();
+            found.push((target.record, records));
+        }
+    }
+    found
+}
+
+/// Find between 2 and `max_size` indices of `amounts` (sorted descending,
+/// non-negative) whose sum is within `allowed` of `goal`
+///
+/// Depth-first with pruning on the running sum; the search is capped at
+/// `MAX_SEARCH_NODES` so a single large group cannot stall the job.
+fn subset_sum(amounts: &[i128], goal: i128, allowed: i128, max_size: usize) -> Option<Vec<usize>> {
+    // Suffix sums let a branch be dropped when the rest cannot reach the goal
+    let mut remaining = vec![0i128; amounts.len() + 1];
+    for i in (0..amounts.len()).rev() {
+        remaining[i] = remaining[i + 1] + amounts[i];
+    }
+
+    struct Search<'s> {
+        amounts: &'s [i128],
+        remaining: &'s [i128],
+        low: i128,
+        high: i128,
+        max_size: usize,
+        nodes: usize,
+        chosen: Vec<usize>,
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, start: usize, sum: i128) -> bool {
+            if self.chosen.len() >= 2 && sum >= self.low && sum <= self.high {
+                return true;
+            }
+            if self.chosen.len() == self.max_size || self.nodes >= MAX_SEARCH_NODES {
+                return false;
+            }
+            for i in start..self.amounts.len() {
+                self.nodes += 1;
+                if self.nodes >= MAX_SEARCH_NODES || sum + self.remaining[i] < self.low {
+                    return false;
+                }
+                let next = sum + self.amounts[i];
+                if next > self.high {
+                    continue;
+                }
+                self.chosen.push(i);
+                if self.run(i + 1, next) {
+                    return true;
+                }
+                self.chosen.pop();
+            }
+            false
+        }
+    }
+
+    let mut search = Search {
+        amounts,
+        remaining: &remaining,
+        low: goal - allowed,
+        high: goal + allowed,
+        max_size,
+        nodes: 0,
+        chosen: Vec::new(),
+    };
+    if search.run(0, 0) {
+        Some(search.chosen)
+    } else {
+        None
+    }
+}
+
+fn build_match(
+    config: &AggregateConfig,
+    key: &str,
+    sources: &[&ReconciliationRecord],
+    targets: &[&ReconciliationRecord],
+) -> AggregateMatch {
+    let total = |records: &[&ReconciliationRecord]| -> i128 {
+        records.iter().filter_map(|r| config.amount_units(r)).sum()
+    };
+    let (source_units, target_units) = (total(sources), total(targets));
+    let difference = source_units - target_units;
+    let allowed = config.allowed_units(if sources.len() == 1 { source_units } else { target_units });
+
+    // Same grading as numeric rules: exact nets score 1.0, the tolerance edge 0.9
+    let confidence_score = if difference == 0 || allowed == 0 {
+        1.0
+    } else {
+        1.0 - 0.1 * (difference.abs() as f64 / allowed as f64).clamp(0.0, 1.0)
+    };
+
+    AggregateMatch {
+        group_id: Uuid::new_v4(),
+        group_key: key.to_string(),
+        source_record_ids: sources.iter().map(|r| r.id).collect(),
+        target_record_ids: targets.iter().map(|r| r.id).collect(),
+        source_total: from_units(source_units),
+        target_total: from_units(target_units),
+        difference: from_units(difference),
+        confidence_score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support;
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn record(customer: &str, amount: &str) -> ReconciliationRecord {
+        test_support::record(json!({"customer": customer, "amount": amount}))
+    }
+
+    fn config(direction: AggregateDirection) -> AggregateConfig {
+        AggregateConfig {
+            amount_field: "amount".to_string(),
+            group_by: vec![GroupKey::Field {
+                field: "customer".to_string(),
+            }],
+            tolerance: None,
+            max_group_size: 5,
+            direction,
+        }
+    }
+
+    #[test]
+    fn many_invoices_settled_by_one_payment() {
+        let invoices = vec![
+            record("C1", "100.00"),
+            record("C1", "250.50"),
+            record("C1", "80.00"),
+            record("C2", "49.50"),
+        ];
+        let payments = vec![record("C1", "350.50"), record("C2", "999.00")];
+
+        let matches = find_aggregate_matches(&config(AggregateDirection::Both), &invoices, &payments);
+        assert_eq!(matches.len(), 1);
+        let m = &matches[0];
+        assert_eq!(m.match_type(), "many_to_one");
+        assert_eq!(m.target_record_ids, vec![payments[0].id]);
+        let mut sources = m.source_record_ids.clone();
+        sources.sort();
+        let mut expected = vec![invoices[0].id, invoices[1].id];
+        expected.sort();
+        assert_eq!(sources, expected);
+        assert_eq!(m.difference, BigDecimal::from(0));
+    }
+
+    #[test]
+    fn split_payment_within_tolerance() {
+        let invoices = vec![record("C1", "1000.00")];
+        let payments = vec![record("C1", "600.00"), record("C1", "399.98"), record("C1", "5.00")];
+        let mut cfg = config(AggregateDirection::OneToMany);
+        cfg.tolerance = Some(AmountTolerance::Absolute(BigDecimal::from_str("0.05").unwrap_or_default()));
+
+        let matches = find_aggregate_matches(&cfg, &invoices, &payments);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].match_type(), "one_to_many");
+        assert_eq!(matches[0].target_record_ids.len(), 2);
+        assert!(matches[0].confidence_score < 1.0 && matches[0].confidence_score >= 0.9);
+    }
+
+    #[test]
+    fn huge_date_window_is_clamped_not_truncated() {
+        let key = GroupKey::DateWindow {
+            field: "date".to_string(),
+            window_days: (1i64 << 32) + 1,
+        };
+        let day = |date: &str| key.key_for(&test_support::record(json!({ "date": date })));
+        assert!(day("2024-01-10").is_some());
+        assert_eq!(day("2024-01-10"), day("2024-01-11"));
+    }
+
+    #[test]
+    fn subset_sum_respects_group_size() {
+        let amounts = [40, 30, 20, 10];
+        assert_eq!(subset_sum(&amounts, 100, 0, 4), Some(vec![0, 1, 2, 3]));
+        assert_eq!(subset_sum(&amounts, 100, 0, 3), None);
+        assert_eq!(subset_sum(&amounts, 40, 0, 3), Some(vec![1, 3]));
+    }
+}
@@ -0,0 +1,757 @@
+//! Maker-checker (four-eyes) approval of match decisions
+//!
+//! A project's approval policy, in its settings under `approval_policy`, says
+//! which match decisions need a second approver: every approval, manual
+//! matches, or matches whose records' amounts differ by more than a
+//! threshold (a write-off of the difference). Such a decision isn't applied
+//! when it is made. The result is put in `pending_approval` and the proposed
+//! change is kept in `match_approvals` until a user other than the preparer
+//! approves it, which applies the change, or rejects it with a reason, which
+//! restores the result's previous status. Decisions the policy doesn't cover
+//! are applied at once.
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{
+    match_approvals, projects, reconciliation_records, reconciliation_results,
+};
+use crate::models::{MatchApproval, NewMatchApproval, ReconciliationResult};
+use crate::services::audit::{self, AuditContext, AuditEvent};
+
+/// Status of a result whose change awaits approval
+pub const PENDING_APPROVAL: &str = "pending_approval";
+
+/// Review status of a match
+///
+/// Parsed case-insensitively; any other value is rejected, so a change can't
+/// accept a match under a name the approval policy doesn't recognise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "String")]
+pub enum MatchStatus {
+    Pending,
+    Matched,
+    Unmatched,
+    Approved,
+    Rejected,
+    PendingApproval,
+}
+
+impl MatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStatus::Pending => "pending",
+            MatchStatus::Matched => "matched",
+            MatchStatus::Unmatched => "unmatched",
+            MatchStatus::Approved => "approved",
+            MatchStatus::Rejected => "rejected",
+            MatchStatus::PendingApproval => PENDING_APPROVAL,
+        }
+    }
+
+    /// Whether a match in this status counts as reconciled
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, MatchStatus::Matched | MatchStatus::Approved)
+    }
+
+    /// Status of a stored result; `None` when it has none or an unknown one
+    pub fn of(result: &ReconciliationResult) -> Option<Self> {
+        result.status.as_deref().and_then(|status| status.parse().ok())
+    }
+}
+
+impl FromStr for MatchStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pending" => Ok(MatchStatus::Pending),
+            "matched" => Ok(MatchStatus::Matched),
+            "unmatched" => Ok(MatchStatus::Unmatched),
+            "approved" => Ok(MatchStatus::Approved),
+            "rejected" => Ok(MatchStatus::Rejected),
+            PENDING_APPROVAL => Ok(MatchStatus::PendingApproval),
+            _ => Err(AppError::Validation(format!(
+                "Invalid match status: {}; expected pending, matched, unmatched, approved or rejected",
+                s
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for MatchStatus {
+    type Error = AppError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl std::fmt::Display for MatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which match decisions need a second approver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// Every approval of a match
+    #[serde(default)]
+    pub all_matches: bool,
+    /// Matches linked by hand rather than by the engine
+    #[serde(default = "default_true")]
+    pub manual_matches: bool,
+    /// Matches whose records' amounts differ by more than this
+    #[serde(default)]
+    pub variance_threshold: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            all_matches: false,
+            manual_matches: true,
+            variance_threshold: None,
+        }
+    }
+}
+
+/// Why a decision needs approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalReason {
+    AllMatches,
+    ManualMatch,
+    Variance,
+}
+
+/// What the policy looks at in a decision
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MatchFacts {
+    pub manual: bool,
+    /// Absolute difference between the records' amounts, when both have one
+    pub variance: Option<f64>,
+}
+
+impl ApprovalPolicy {
+    /// Policy in a project's settings; the default when it has none
+    pub fn from_settings(settings: &Value) -> AppResult<Self> {
+        let policy: Self = match settings.get("approval_policy") {
+            Some(value) if !value.is_null() => serde_json::from_value(value.clone())
+                .map_err(|e| AppError::Validation(format!("Invalid approval policy: {}", e)))?,
+            _ => Self::default(),
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
+        match self.variance_threshold {
+            Some(threshold) if !threshold.is_finite() || threshold < 0.0 => {
+                Err(AppError::Validation(
+                    "variance_threshold must be a non-negative number".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Conditions of the policy a decision meets; empty if it needs no approval
+    pub fn reasons(&self, facts: &MatchFacts) -> Vec<ApprovalReason> {
+        let mut reasons = Vec::new();
+        if self.all_matches {
+            reasons.push(ApprovalReason::AllMatches);
+        }
+        if self.manual_matches && facts.manual {
+            reasons.push(ApprovalReason::ManualMatch);
+        }
+        if let (Some(threshold), Some(variance)) = (self.variance_threshold, facts.variance) {
+            if variance > threshold {
+                reasons.push(ApprovalReason::Variance);
+            }
+        }
+        reasons
+    }
+}
+
+/// Status of an approval request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ApprovalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// A change to a match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchProposal {
+    pub status: MatchStatus,
+    /// Record the result is linked to by hand, making it a manual match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_b_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_score: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl MatchProposal {
+    /// A change to `status` alone
+    pub fn new(status: MatchStatus) -> Self {
+        Self {
+            status,
+            record_b_id: None,
+            confidence_score: None,
+            notes: None,
+        }
+    }
+
+    /// Whether the change accepts the match: moving it into an accepted
+    /// status or a more final one (matched to approved), linking it by hand,
+    /// or changing the confidence of a match that is already accepted
+    fn accepts(&self, current: &ReconciliationResult) -> bool {
+        let current = MatchStatus::of(current);
+        let promotes = match self.status {
+            MatchStatus::Approved => current != Some(MatchStatus::Approved),
+            MatchStatus::Matched => !current.is_some_and(|status| status.is_accepted()),
+            _ => false,
+        };
+        let edits_accepted = self.status.is_accepted()
+            && current.is_some_and(|status| status.is_accepted())
+            && self.confidence_score.is_some();
+        self.record_b_id.is_some() || promotes || edits_accepted
+    }
+}
+
+/// What became of a proposed change
+#[derive(Debug, Clone)]
+pub enum Proposed {
+    Applied,
+    PendingApproval(MatchApproval),
+}
+
+/// A second user's decision on an approval request
+#[derive(Debug, Clone)]
+pub enum ApprovalDecision {
+    Approve { notes: Option<String> },
+    Reject { reason: String },
+}
+
+/// Filters for the approval queue
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApprovalQuery {
+    pub project_id: Option<Uuid>,
+    pub job_id: Option<Uuid>,
+    /// Defaults to pending
+    pub status: Option<ApprovalStatus>,
+    /// Leave out requests the viewer prepared, which they can't decide
+    #[serde(default)]
+    pub actionable: bool,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// Approval policy of a project
+pub fn project_policy(conn: &mut PgConnection, project_id: Uuid) -> AppResult<ApprovalPolicy> {
+    let settings = projects::table
+        .find(project_id)
+        .select(projects::settings)
+        .first::<Value>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Project {} not found", project_id)))?;
+    ApprovalPolicy::from_settings(&settings)
+}
+
+/// Store a project's approval policy in its settings
+pub fn set_project_policy(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    policy: &ApprovalPolicy,
+) -> AppResult<()> {
+    policy.validate()?;
+    conn.transaction::<_, AppError, _>(|tx| {
+        let mut settings = projects::table
+            .find(project_id)
+            .select(projects::settings)
+            .for_update()
+            .first::<Value>(tx)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Project {} not found", project_id)))?;
+        if !settings.is_object() {
+            settings = json!({});
+        }
+        settings["approval_policy"] = serde_json::to_value(policy)?;
+        diesel::update(projects::table.find(project_id))
+            .set((
+                projects::settings.eq(settings),
+                projects::updated_at.eq(Utc::now()),
+            ))
+            .execute(tx)?;
+        Ok(())
+    })
+}
+
+/// Absolute difference between two records' amounts
+pub fn variance(
+    conn: &mut PgConnection,
+    record_a_id: Uuid,
+    record_b_id: Option<Uuid>,
+) -> AppResult<Option<f64>> {
+    let Some(record_b_id) = record_b_id else {
+        return Ok(None);
+    };
+    let amounts: Vec<(Uuid, Option<f64>)> = reconciliation_records::table
+        .filter(reconciliation_records::id.eq_any([record_a_id, record_b_id]))
+        .select((reconciliation_records::id, reconciliation_records::amount))
+        .load(conn)?;
+    let amount = |id: Uuid| {
+        amounts
+            .iter()
+            .find(|(record, _)| *record == id)
+            .and_then(|(_, amount)| *amount)
+    };
+    Ok(match (amount(record_a_id), amount(record_b_id)) {
+        (Some(a), Some(b)) => Some((a - b).abs()),
+        _ => None,
+    })
+}
+
+/// Apply `proposal` to `result`, or submit it for approval if the project's
+/// policy requires one
+///
+/// `result` must be locked in the caller's transaction. A result already
+/// awaiting approval can't be changed until the request is decided.
+pub fn propose(
+    tx: &mut PgConnection,
+    ctx: &AuditContext,
+    preparer: Uuid,
+    project_id: Uuid,
+    result: &ReconciliationResult,
+    proposal: MatchProposal,
+) -> AppResult<Proposed> {
+    if result.status.as_deref() == Some(PENDING_APPROVAL) {
+        return Err(AppError::Conflict(format!(
+            "Match {} is awaiting approval",
+            result.id
+        )));
+    }
+
+    if proposal.accepts(result) {
+        let facts = MatchFacts {
+            manual: proposal.record_b_id.is_some() || result.match_type == "manual",
+            variance: variance(
+                tx,
+                result.record_a_id,
+                proposal.record_b_id.or(result.record_b_id),
+            )?,
+        };
+        let reasons = project_policy(tx, project_id)?.reasons(&facts);
+        if !reasons.is_empty() {
+            let approval = submit(
+                tx,
+                ctx,
+                preparer,
+                project_id,
+                result,
+                proposal,
+                &reasons,
+                facts.variance,
+            )?;
+            return Ok(Proposed::PendingApproval(approval));
+        }
+    }
+
+    apply(tx, result.id, &proposal, preparer)?;
+    Ok(Proposed::Applied)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn submit(
+    tx: &mut PgConnection,
+    ctx: &AuditContext,
+    preparer: Uuid,
+    project_id: Uuid,
+    result: &ReconciliationResult,
+    proposal: MatchProposal,
+    reasons: &[ApprovalReason],
+    variance: Option<f64>,
+) -> AppResult<MatchApproval> {
+    diesel::update(reconciliation_results::table.find(result.id))
+        .set((
+            reconciliation_results::status.eq(PENDING_APPROVAL),
+            reconciliation_results::reviewed_by.eq(preparer),
+            reconciliation_results::updated_at.eq(Utc::now()),
+        ))
+        .execute(tx)?;
+
+    let approval = diesel::insert_into(match_approvals::table)
+        .values(&NewMatchApproval {
+            result_id: result.id,
+            job_id: result.job_id,
+            project_id,
+            reasons: serde_json::to_value(reasons)?,
+            prepared_notes: proposal.notes.clone(),
+            proposed: serde_json::to_value(&proposal)?,
+            previous_status: result.status.clone(),
+            variance,
+            prepared_by: preparer,
+        })
+        .get_result::<MatchApproval>(tx)?;
+
+    audit::append(
+        tx,
+        ctx,
+        AuditEvent::new("match.submit", "reconciliation_match", Some(result.id))
+            .with_change(
+                Some(json!({ "status": result.status })),
+                Some(json!({ "status": PENDING_APPROVAL })),
+            )
+            .with_details(json!({
+                "approval_id": approval.id,
+                "job_id": result.job_id,
+                "proposed": approval.proposed,
+                "reasons": approval.reasons,
+                "variance": variance,
+            })),
+    )?;
+    Ok(approval)
+}
+
+/// Write `proposal` to a result, recording who made the final call: the
+/// preparer when no approval was needed, else the approver
+fn apply(
+    conn: &mut PgConnection,
+    result_id: Uuid,
+    proposal: &MatchProposal,
+    reviewed_by: Uuid,
+) -> AppResult<()> {
+    let confidence = proposal
+        .confidence_score
+        .map(|score| {
+            BigDecimal::from_str(&score.to_string())
+                .map_err(|_| AppError::Validation("Invalid confidence score".to_string()))
+        })
+        .transpose()?;
+    let now = Utc::now();
+    let target = reconciliation_results::table.find(result_id);
+
+    diesel::update(target)
+        .set((
+            reconciliation_results::status.eq(proposal.status.as_str()),
+            reconciliation_results::reviewed_by.eq(reviewed_by),
+            reconciliation_results::updated_at.eq(now),
+        ))
+        .execute(conn)?;
+    if let Some(record_b_id) = proposal.record_b_id {
+        diesel::update(target)
+            .set((
+                reconciliation_results::record_b_id.eq(record_b_id),
+                reconciliation_results::match_type.eq("manual"),
+            ))
+            .execute(conn)?;
+    }
+    if let Some(confidence) = confidence {
+        diesel::update(target)
+            .set(reconciliation_results::confidence_score.eq(confidence))
+            .execute(conn)?;
+    }
+    if let Some(ref notes) = proposal.notes {
+        diesel::update(target)
+            .set(reconciliation_results::notes.eq(notes))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Approve or reject a pending request as `approver`, who must not be its
+/// preparer
+pub fn decide(
+    conn: &mut PgConnection,
+    ctx: &AuditContext,
+    approval_id: Uuid,
+    approver: Uuid,
+    decision: ApprovalDecision,
+) -> AppResult<MatchApproval> {
+    conn.transaction::<_, AppError, _>(|tx| {
+        let approval = match_approvals::table
+            .find(approval_id)
+            .for_update()
+            .first::<MatchApproval>(tx)
+            .optional()?
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Approval request {} not found", approval_id))
+            })?;
+        if approval.status != ApprovalStatus::Pending.as_str() {
+            return Err(AppError::Conflict(format!(
+                "Approval request {} is already {}",
+                approval_id, approval.status
+            )));
+        }
+        if approval.prepared_by == approver {
+            return Err(AppError::Forbidden(
+                "A match decision must be approved by someone other than its preparer".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let (status, notes, result_status) = match decision {
+            ApprovalDecision::Approve { notes } => {
+                let proposal: MatchProposal = serde_json::from_value(approval.proposed.clone())?;
+                apply(tx, approval.result_id, &proposal, approver)?;
+                (ApprovalStatus::Approved, notes, proposal.status.to_string())
+            }
+            ApprovalDecision::Reject { reason } => {
+                let reason = reason.trim().to_string();
+                if reason.is_empty() {
+                    return Err(AppError::Validation(
+                        "A reason is required to reject a match decision".to_string(),
+                    ));
+                }
+                let restored = approval
+                    .previous_status
+                    .clone()
+                    .unwrap_or_else(|| "pending".to_string());
+                diesel::update(reconciliation_results::table.find(approval.result_id))
+                    .set((
+                        reconciliation_results::status.eq(&restored),
+                        reconciliation_results::updated_at.eq(now),
+                    ))
+                    .execute(tx)?;
+                (ApprovalStatus::Rejected, Some(reason), restored)
+            }
+        };
+
+        let decided = diesel::update(match_approvals::table.find(approval_id))
+            .set((
+                match_approvals::status.eq(status.as_str()),
+                match_approvals::decided_by.eq(approver),
+                match_approvals::decided_at.eq(now),
+                match_approvals::decision_notes.eq(&notes),
+                match_approvals::updated_at.eq(now),
+            ))
+            .get_result::<MatchApproval>(tx)?;
+
+        audit::append(
+            tx,
+            ctx,
+            AuditEvent::new(
+                format!(
+                    "match.approval.{}",
+                    if status == ApprovalStatus::Approved {
+                        "approve"
+                    } else {
+                        "reject"
+                    }
+                ),
+                "reconciliation_match",
+                Some(approval.result_id),
+            )
+            .with_change(
+                Some(json!({ "status": PENDING_APPROVAL })),
+                Some(json!({ "status": result_status })),
+            )
+            .with_details(json!({
+                "approval_id": approval_id,
+                "prepared_by": approval.prepared_by,
+                "notes": notes,
+            })),
+        )?;
+        Ok(decided)
+    })
+}
+
+/// An approval request
+pub fn get(conn: &mut PgConnection, approval_id: Uuid) -> AppResult<MatchApproval> {
+    match_approvals::table
+        .find(approval_id)
+        .first::<MatchApproval>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Approval request {} not found", approval_id)))
+}
+
+/// Approval requests matching `query`, oldest first, in `projects` (all
+/// projects when `None`), with their total count
+pub fn list(
+    conn: &mut PgConnection,
+    query: &ApprovalQuery,
+    projects: Option<&[Uuid]>,
+    viewer: Uuid,
+) -> AppResult<(Vec<MatchApproval>, i64)> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+    let status = query.status.unwrap_or(ApprovalStatus::Pending);
+
+    let filtered = || {
+        let mut q = match_approvals::table
+            .filter(match_approvals::status.eq(status.as_str()))
+            .into_boxed();
+        if let Some(projects) = projects {
+            q = q.filter(match_approvals::project_id.eq_any(projects.to_vec()));
+        }
+        if let Some(project_id) = query.project_id {
+            q = q.filter(match_approvals::project_id.eq(project_id));
+        }
+        if let Some(job_id) = query.job_id {
+            q = q.filter(match_approvals::job_id.eq(job_id));
+        }
+        if query.actionable {
+            q = q.filter(match_approvals::prepared_by.ne(viewer));
+        }
+        q
+    };
+
+    let total = filtered().count().get_result::<i64>(conn)?;
+    let items = filtered()
+        .order(match_approvals::created_at.asc())
+        .limit(per_page)
+        .offset((page - 1) * per_page)
+        .load::<MatchApproval>(conn)?;
+    Ok((items, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_requires_approval_for_manual_matches_and_large_variances() {
+        let policy = ApprovalPolicy::from_settings(&json!({
+            "approval_policy": { "variance_threshold": 10.0 }
+        }))
+        .unwrap_or_else(|e| panic!("policy: {}", e));
+        assert!(policy.manual_matches);
+        assert!(!policy.all_matches);
+
+        let facts = |manual: bool, variance: Option<f64>| MatchFacts { manual, variance };
+        assert!(policy.reasons(&facts(false, Some(10.0))).is_empty());
+        assert!(policy.reasons(&facts(false, None)).is_empty());
+        assert_eq!(
+            policy.reasons(&facts(false, Some(10.5))),
+            vec![ApprovalReason::Variance]
+        );
+        assert_eq!(
+            policy.reasons(&facts(true, Some(25.0))),
+            vec![ApprovalReason::ManualMatch, ApprovalReason::Variance]
+        );
+
+        let strict = ApprovalPolicy {
+            all_matches: true,
+            manual_matches: false,
+            variance_threshold: None,
+        };
+        assert_eq!(
+            strict.reasons(&facts(true, Some(1e9))),
+            vec![ApprovalReason::AllMatches]
+        );
+
+        assert_eq!(
+            ApprovalPolicy::from_settings(&json!({})).unwrap_or_else(|e| panic!("default: {}", e)),
+            ApprovalPolicy::default()
+        );
+        assert!(ApprovalPolicy::from_settings(
+            &json!({ "approval_policy": { "variance_threshold": -1 } })
+        )
+        .is_err());
+        assert!(ApprovalPolicy::from_settings(
+            &json!({ "approval_policy": { "all_matches": "yes" } })
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn only_accepting_changes_are_subject_to_approval() {
+        let result = ReconciliationResult {
+            id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            record_a_id: Uuid::new_v4(),
+            record_b_id: None,
+            match_type: "unmatched".to_string(),
+            confidence_score: None,
+            match_details: None,
+            status: Some("matched".to_string()),
+            updated_at: None,
+            notes: None,
+            reviewed_by: None,
+            created_at: Utc::now(),
+        };
+        let proposal = |status: MatchStatus, record_b_id: Option<Uuid>| MatchProposal {
+            record_b_id,
+            ..MatchProposal::new(status)
+        };
+
+        assert!(proposal(MatchStatus::Approved, None).accepts(&result));
+        assert!(!proposal(MatchStatus::Matched, None).accepts(&result));
+        assert!(!proposal(MatchStatus::Rejected, None).accepts(&result));
+        assert!(proposal(MatchStatus::Matched, Some(Uuid::new_v4())).accepts(&result));
+
+        let unmatched = ReconciliationResult {
+            status: Some("unmatched".to_string()),
+            ..result.clone()
+        };
+        assert!(proposal(MatchStatus::Matched, None).accepts(&unmatched));
+        assert!(!proposal(MatchStatus::Pending, None).accepts(&unmatched));
+
+        let approved = ReconciliationResult {
+            status: Some("approved".to_string()),
+            ..result
+        };
+        assert!(!proposal(MatchStatus::Approved, None).accepts(&approved));
+        assert!(!proposal(MatchStatus::Rejected, None).accepts(&approved));
+        let rescored = MatchProposal {
+            confidence_score: Some(0.5),
+            ..MatchProposal::new(MatchStatus::Approved)
+        };
+        assert!(rescored.accepts(&approved));
+    }
+
+    #[test]
+    fn match_statuses_parse_case_insensitively_and_reject_unknown_names() {
+        for status in [
+            MatchStatus::Pending,
+            MatchStatus::Matched,
+            MatchStatus::Unmatched,
+            MatchStatus::Approved,
+            MatchStatus::Rejected,
+            MatchStatus::PendingApproval,
+        ] {
+            assert_eq!(status.as_str().parse::<MatchStatus>().ok(), Some(status));
+        }
+        assert_eq!(" Approved ".parse::<MatchStatus>().ok(), Some(MatchStatus::Approved));
+        assert_eq!("APPROVED".parse::<MatchStatus>().ok(), Some(MatchStatus::Approved));
+        assert!("accepted".parse::<MatchStatus>().is_err());
+        assert!("".parse::<MatchStatus>().is_err());
+
+        let proposal: MatchProposal = serde_json::from_value(json!({ "status": "Matched" }))
+            .unwrap_or_else(|e| panic!("proposal: {}", e));
+        assert_eq!(proposal.status, MatchStatus::Matched);
+        assert!(serde_json::from_value::<MatchProposal>(json!({ "status": "ok" })).is_err());
+        assert_eq!(
+            serde_json::to_value(MatchProposal::new(MatchStatus::PendingApproval))
+                .unwrap_or_else(|e| panic!("serialize: {}", e)),
+            json!({ "status": "pending_approval" })
+        );
+    }
+}
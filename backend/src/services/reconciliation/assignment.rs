@@ -0,0 +1,364 @@
+//! Match assignment for reconciliation
+//!
+//! Scoring produces, for every source A record, the source B records it could
+//! match. Picking the best B for each A independently lets one B record be
+//! matched to many A records. The one-to-one modes here resolve all candidates
+//! together so each B record is used at most once, and report which A records
+//! lost their preferred candidate to another record ("contested").
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Components larger than this (on either side) fall back to greedy assignment
+/// because the Hungarian algorithm is O(n³)
+pub const MAX_OPTIMAL_COMPONENT_SIZE: usize = 400;
+
+/// How candidate matches are turned into final matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentMode {
+    /// Best candidate per A record; a B record may be matched more than once
+    #[default]
+    BestMatch,
+    /// Highest-scoring pairs first, skipping pairs whose B record is taken
+    Greedy,
+    /// Maximum total score (Hungarian algorithm per connected component)
+    Optimal,
+}
+
+impl AssignmentMode {
+    pub fn is_one_to_one(&self) -> bool {
+        !matches!(self, AssignmentMode::BestMatch)
+    }
+}
+
+/// A scored candidate pair, identified by positions in source A and source B
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandidatePair {
+    pub a: usize,
+    pub b: usize,
+    pub score: f64,
+}
+
+/// Result of resolving candidate pairs
+#[derive(Debug, Clone, Default)]
+pub struct Assignment {
+    /// Chosen B position per A position
+    pub assigned: Vec<Option<usize>>,
+    /// For A records whose top candidate went to another A record, that record's position
+    pub contested_by: Vec<Option<usize>>,
+}
+
+/// Resolve candidate pairs for `a_count` A records according to `mode`
+pub fn assign(mode: AssignmentMode, a_count: usize, pairs: &[CandidatePair]) -> Assignment {
+    let assigned = match mode {
+        AssignmentMode::BestMatch => best_match(a_count, pairs),
+        AssignmentMode::Greedy => greedy(a_count, pairs),
+        AssignmentMode::Optimal => optimal(a_count, pairs),
+    };
+    // Targets are never taken away from anyone in best-match mode
+    let contested_by = if mode.is_one_to_one() {
+        find_contested(a_count, pairs, &assigned)
+    } else {
+        vec![None; a_count]
+    };
+    Assignment {
+        assigned,
+        contested_by,
+    }
+}
+
+/// Order pairs by score (descending), then by position so results are deterministic
+fn compare_pairs(x: &CandidatePair, y: &CandidatePair) -> std::cmp::Ordering {
+    y.score
+        .total_cmp(&x.score)
+        .then(x.a.cmp(&y.a))
+        .then(x.b.cmp(&y.b))
+}
+
+fn top_candidates(a_count: usize, pairs: &[CandidatePair]) -> Vec<Option<CandidatePair>> {
+    let mut top: Vec<Option<CandidatePair>> = vec![None; a_count];
+    for pair in pairs {
+        let slot = &mut top[pair.a];
+        if slot.is_none_or(|best| compare_pairs(pair, &best).is_lt()) {
+            *slot = Some(*pair);
+        }
+    }
+    top
+}
+
+fn best_match(a_count: usize, pairs: &[CandidatePair]) -> Vec<Option<usize>> {
+    top_candidates(a_count, pairs)
+        .into_iter()
+        .map(|top| top.map(|pair| pair.b))
+        .collect()
+}
+
+fn greedy(a_count: usize, pairs: &[CandidatePair]) -> Vec<Option<usize>> {
+    let mut sorted: Vec<CandidatePair> = pairs.to_vec();
+    sorted.sort_by(compare_pairs);
+
+    let mut assigned = vec![None; a_count];
+    let mut taken: HashMap<usize, usize> = HashMap::new();
+    for pair in sorted {
+        if assigned[pair.a].is_none() && !taken.contains_key(&pair.b) {
+            assigned[pair.a] = Some(pair.b);
+            taken.insert(pair.b, pair.a);
+        }
+    }
+    assigned
+}
+
+fn optimal(a_count: usize, pairs: &[CandidatePair]) -> Vec<Option<usize>> {
+    let mut assigned = vec![None; a_count];
+
+    for component in connected_components(pairs) {
+        let a_nodes: Vec<usize> = unique_sorted(component.iter().map(|p| p.a));
+        let b_nodes: Vec<usize> = unique_sorted(component.iter().map(|p| p.b));
+
+        if a_nodes.len() > MAX_OPTIMAL_COMPONENT_SIZE || b_nodes.len() > MAX_OPTIMAL_COMPONENT_SIZE {
+            log::debug!(
+                "Assignment component of {}x{} records exceeds optimal limit, using greedy",
+                a_nodes.len(),
+                b_nodes.len()
+            );
+            for (a, b) in greedy(a_count, &component).into_iter().enumerate() {
+                if b.is_some() {
+                    assigned[a] = b;
+                }
+            }
+            continue;
+        }
+
+        let a_index: HashMap<usize, usize> = a_nodes.iter().enumerate().map(|(i, a)| (*a, i)).collect();
+        let b_index: HashMap<usize, usize> = b_nodes.iter().enumerate().map(|(j, b)| (*b, j)).collect();
+
+        // Square cost matrix; leaving a record unmatched costs as much as a zero score
+        let size = a_nodes.len().max(b_nodes.len());
+        let mut cost = vec![vec![1.0; size]; size];
+        let mut is_edge = vec![vec![false; size]; size];
+        for pair in &component {
+            let (i, j) = (a_index[&pair.a], b_index[&pair.b]);
+            let pair_cost = 1.0 - pair.score.clamp(0.0, 1.0);
+            if !is_edge[i][j] || pair_cost < cost[i][j] {
+                cost[i][j] = pair_cost;
+                is_edge[i][j] = true;
+            }
+        }
+
+        for (i, j) in hungarian(&cost).into_iter().enumerate() {
+            if i < a_nodes.len() && j < b_nodes.len() && is_edge[i][j] {
+                assigned[a_nodes[i]] = Some(b_nodes[j]);
+            }
+        }
+    }
+
+    assigned
+}
+
+fn unique_sorted(values: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut values: Vec<usize> = values.collect();
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+/// Split pairs into connected components of the bipartite candidate graph
+fn connected_components(pairs: &[CandidatePair]) -> Vec<Vec<CandidatePair>> {
+    // Union-find over A records; two A records are connected when they share a B record
+    let mut parent: HashMap<usize, usize> = HashMap::new();
+    // Iterative, as chains of candidates can be far deeper than the stack
+    fn find(parent: &mut HashMap<usize, usize>, x: usize) -> usize {
+        let mut root = x;
+        loop {
+            let p = *parent.entry(root).or_insert(root);
+            if p == root {
+                break;
+            }
+            root = p;
+        }
+        // Path compression: point everything on the way at the root
+        let mut node = x;
+        while node != root {
+            let next = parent.insert(node, root).unwrap_or(root);
+            node = next;
+        }
+        root
+    }
+
+    let mut owner_of_b: HashMap<usize, usize> = HashMap::new();
+    for pair in pairs {
+        find(&mut parent, pair.a);
+        match owner_of_b.get(&pair.b) {
+            Some(&other) => {
+                let (ra, rb) = (find(&mut parent, pair.a), find(&mut parent, other));
+                if ra != rb {
+                    parent.insert(ra, rb);
+                }
+            }
+            None => {
+                owner_of_b.insert(pair.b, pair.a);
+            }
+        }
+    }
+
+    let mut components: HashMap<usize, Vec<CandidatePair>> = HashMap::new();
+    for pair in pairs {
+        let root = find(&mut parent, pair.a);
+        components.entry(root).or_default().push(*pair);
+    }
+    let mut components: Vec<Vec<CandidatePair>> = components.into_values().collect();
+    components.sort_by_key(|c| c.iter().map(|p| p.a).min().unwrap_or(0));
+    components
+}
+
+/// Minimum-cost assignment on a square matrix (Kuhn-Munkres with potentials)
+///
+/// Returns the column assigned to each row.
+fn hungarian(cost: &[Vec<f64>]) -> Vec<usize> {
+    let n = cost.len();
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut p = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=n {
+                if !used[j] {
+                    let cur = cost[i0 - 1][j - 1] - u[i0] - v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut row_to_col = vec![0; n];
+    for j in 1..=n {
+        if p[j] > 0 {
+            row_to_col[p[j] - 1] = j - 1;
+        }
+    }
+    row_to_col
+}
+
+/// Find A records whose top candidate was assigned to a different A record
+fn find_contested(
+    a_count: usize,
+    pairs: &[CandidatePair],
+    assigned: &[Option<usize>],
+) -> Vec<Option<usize>> {
+    let mut winner_of_b: HashMap<usize, usize> = HashMap::new();
+    for (a, b) in assigned.iter().enumerate() {
+        if let Some(b) = b {
+            winner_of_b.insert(*b, a);
+        }
+    }
+
+    top_candidates(a_count, pairs)
+        .into_iter()
+        .enumerate()
+        .map(|(a, top)| {
+            let top = top?;
+            match winner_of_b.get(&top.b) {
+                Some(&winner) if winner != a => Some(winner),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(a: usize, b: usize, score: f64) -> CandidatePair {
+        CandidatePair { a, b, score }
+    }
+
+    #[test]
+    fn best_match_allows_reuse_of_targets() {
+        let pairs = [pair(0, 0, 0.9), pair(1, 0, 0.95)];
+        let result = assign(AssignmentMode::BestMatch, 2, &pairs);
+        assert_eq!(result.assigned, vec![Some(0), Some(0)]);
+        assert_eq!(result.contested_by, vec![None, None]);
+    }
+
+    #[test]
+    fn greedy_uses_each_target_once_and_flags_losers() {
+        let pairs = [pair(0, 0, 0.9), pair(0, 1, 0.8), pair(1, 0, 0.95)];
+        let result = assign(AssignmentMode::Greedy, 2, &pairs);
+        assert_eq!(result.assigned, vec![Some(1), Some(0)]);
+        assert_eq!(result.contested_by, vec![Some(1), None]);
+    }
+
+    #[test]
+    fn optimal_maximises_total_score() {
+        // Greedy takes (0,0)=0.95 first and leaves record 1 unmatched;
+        // the optimal assignment matches both records.
+        let pairs = [pair(0, 0, 0.95), pair(0, 1, 0.9), pair(1, 0, 0.9)];
+        let greedy_result = assign(AssignmentMode::Greedy, 2, &pairs);
+        assert_eq!(greedy_result.assigned, vec![Some(0), None]);
+
+        let optimal_result = assign(AssignmentMode::Optimal, 2, &pairs);
+        assert_eq!(optimal_result.assigned, vec![Some(1), Some(0)]);
+        assert_eq!(optimal_result.contested_by, vec![Some(1), None]);
+    }
+
+    #[test]
+    fn optimal_handles_independent_components_and_unmatched_records() {
+        let pairs = [pair(0, 0, 0.8), pair(2, 3, 0.7), pair(3, 3, 0.9)];
+        let result = assign(AssignmentMode::Optimal, 4, &pairs);
+        assert_eq!(result.assigned, vec![Some(0), None, None, Some(3)]);
+        assert_eq!(result.contested_by, vec![None, None, Some(3), None]);
+    }
+
+    #[test]
+    fn long_candidate_chains_form_one_component() {
+        // Each record joins the next one's target, so every union hangs the
+        // tree so far under a new root and the records form one long chain
+        let n = 200_000;
+        let pairs: Vec<CandidatePair> = (0..n)
+            .flat_map(|i| [pair(i + 1, i + 1, 0.9), pair(i, i + 1, 0.8)])
+            .collect();
+        let components = connected_components(&pairs);
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), pairs.len());
+    }
+}
@@ -0,0 +1,431 @@
+//! Blocking (candidate pair generation) for reconciliation
+//!
+//! Scoring every record of source A against every record of source B is
+//! O(N×M) and does not finish for large jobs. Blocking indexes source B once
+//! and, for each source A record, only yields the B records that share a
+//! block with it. Only those candidate pairs are scored.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::matching::{build_exact_index, exact_key, parse_amount, parse_date};
+use super::types::ReconciliationRecord;
+
+/// Widest date window honoured, a century; wider ones are clamped to it
+const MAX_DATE_WINDOW_DAYS: i64 = 36_500;
+
+/// Blocking strategy used to build candidate pairs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockingStrategy {
+    /// Records must share the exact value of `field`
+    ExactKey { field: String },
+    /// Dates in `field` must be at most `window_days` apart
+    DateWindow { field: String, window_days: i64 },
+    /// Amounts in `field` must fall into the same or an adjacent bucket of width `bucket_size`
+    AmountBucket { field: String, bucket_size: f64 },
+    /// Source B is sorted by `field`; each A record is compared with the
+    /// `window_size` B records around its own sort position
+    SortedNeighbourhood { field: String, window_size: usize },
+}
+
+/// How the candidate sets of several strategies are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockingMode {
+    /// A pair is a candidate if any strategy puts it in the same block
+    #[default]
+    Union,
+    /// A pair is a candidate only if every strategy puts it in the same block
+    Intersection,
+}
+
+/// Blocking configuration for a reconciliation job
+///
+/// An empty strategy list disables blocking and falls back to comparing
+/// every pair.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockingConfig {
+    #[serde(default)]
+    pub strategies: Vec<BlockingStrategy>,
+    #[serde(default)]
+    pub mode: BlockingMode,
+}
+
+impl BlockingConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.strategies.is_empty()
+    }
+}
+
+/// Statistics on how many comparisons blocking avoided
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockingStats {
+    pub source_a_records: u64,
+    pub source_b_records: u64,
+    /// Pairs a full N×M comparison would have scored
+    pub total_pairs: u64,
+    /// Pairs actually scored after blocking
+    pub candidate_pairs: u64,
+    pub comparisons_saved: u64,
+}
+
+impl BlockingStats {
+    pub fn new(source_a_records: usize, source_b_records: usize) -> Self {
+        let source_a_records = source_a_records as u64;
+        let source_b_records = source_b_records as u64;
+        Self {
+            source_a_records,
+            source_b_records,
+            total_pairs: source_a_records * source_b_records,
+            candidate_pairs: 0,
+            comparisons_saved: 0,
+        }
+    }
+
+    /// Record the candidate count produced for one source A record
+    pub fn record_candidates(&mut self, candidates: usize) {
+        self.candidate_pairs += candidates as u64;
+        self.comparisons_saved += self.source_b_records.saturating_sub(candidates as u64);
+    }
+
+    /// Add the candidate counts of a partial run, e.g. one chunk of the job
+    pub fn absorb(&mut self, partial: &BlockingStats) {
+        self.candidate_pairs += partial.candidate_pairs;
+        self.comparisons_saved += partial.comparisons_saved;
+    }
+
+    /// Fraction of the full comparison space that was skipped (0.0 - 1.0)
+    pub fn reduction_ratio(&self) -> f64 {
+        if self.total_pairs == 0 {
+            0.0
+        } else {
+            self.comparisons_saved as f64 / self.total_pairs as f64
+        }
+    }
+}
+
+/// Per-strategy index over source B
+enum StrategyIndex {
+    Exact {
+        field: String,
+        index: HashMap<String, Vec<usize>>,
+    },
+    Date {
+        field: String,
+        window_days: i64,
+        sorted: Vec<(NaiveDate, usize)>,
+    },
+    Amount {
+        field: String,
+        bucket_size: f64,
+        buckets: HashMap<i64, Vec<usize>>,
+    },
+    Sorted {
+        field: String,
+        window_size: usize,
+        sorted: Vec<(String, usize)>,
+    },
+}
+
+/// Candidate index built once over source B
+///
+/// Candidates are returned as positions into the slice the index was built from.
+pub struct CandidateIndex {
+    len: usize,
+    mode: BlockingMode,
+    strategies: Vec<StrategyIndex>,
+}
+
+impl CandidateIndex {
+    /// Build the index for `records_b` according to `config`
+    pub fn build(config: &BlockingConfig, records_b: &[ReconciliationRecord]) -> Self {
+        let strategies = config
+            .strategies
+            .iter()
+            .map(|strategy| Self::build_strategy(strategy, records_b))
+            .collect();
+
+        Self {
+            len: records_b.len(),
+            mode: config.mode,
+            strategies,
+        }
+    }
+
+    fn build_strategy(strategy: &BlockingStrategy, records_b: &[ReconciliationRecord]) -> StrategyIndex {
+        match strategy {
+            BlockingStrategy::ExactKey { field } => StrategyIndex::Exact {
+                field: field.clone(),
+                index: build_exact_index(records_b, field),
+            },
+            BlockingStrategy::DateWindow { field, window_days } => {
+                let mut sorted: Vec<(NaiveDate, usize)> = records_b
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(pos, r)| r.fields.get(field).and_then(value_as_date).map(|d| (d, pos)))
+                    .collect();
+                sorted.sort_unstable();
+                StrategyIndex::Date {
+                    field: field.clone(),
+                    window_days: (*window_days).clamp(0, MAX_DATE_WINDOW_DAYS),
+                    sorted,
+                }
+            }
+            BlockingStrategy::AmountBucket { field, bucket_size } => {
+                let bucket_size = if *bucket_size > 0.0 { *bucket_size } else { 1.0 };
+                let mut buckets: HashMap<i64, Vec<usize>> = HashMap::new();
+                for (pos, record) in records_b.iter().enumerate() {
+                    if let Some(amount) = record.fields.get(field).and_then(value_as_amount) {
+                        buckets.entry(amount_bucket(amount, bucket_size)).or_default().push(pos);
+                    }
+                }
+                StrategyIndex::Amount {
+                    field: field.clone(),
+                    bucket_size,
+                    buckets,
+                }
+            }
+            BlockingStrategy::SortedNeighbourhood { field, window_size } => {
+                let mut sorted: Vec<(String, usize)> = records_b
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(pos, r)| r.fields.get(field).map(|v| (value_as_sort_key(v), pos)))
+                    .collect();
+                sorted.sort_unstable();
+                StrategyIndex::Sorted {
+                    field: field.clone(),
+                    window_size: (*window_size).max(1),
+                    sorted,
+                }
+            }
+        }
+    }
+
+    /// Number of source B records covered by this index
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Candidate source B positions for a source A record, sorted ascending
+    ///
+    /// Without strategies every B record is a candidate. A record missing the
+    /// field a strategy blocks on gets no candidates from that strategy.
+    pub fn candidates(&self, record_a: &ReconciliationRecord) -> Vec<usize> {
+        if self.strategies.is_empty() {
+            return (0..self.len).collect();
+        }
+
+        let mut combined: Option<Vec<usize>> = None;
+        for strategy in &self.strategies {
+            let mut found = strategy.candidates(record_a);
+            found.sort_unstable();
+            found.dedup();
+
+            combined = Some(match (combined, self.mode) {
+                (None, _) => found,
+                (Some(mut acc), BlockingMode::Union) => {
+                    acc.extend(found);
+                    acc.sort_unstable();
+                    acc.dedup();
+                    acc
+                }
+                (Some(acc), BlockingMode::Intersection) => acc
+                    .into_iter()
+                    .filter(|pos| found.binary_search(pos).is_ok())
+                    .collect(),
+            });
+        }
+        combined.unwrap_or_default()
+    }
+}
+
+impl StrategyIndex {
+    fn candidates(&self, record_a: &ReconciliationRecord) -> Vec<usize> {
+        match self {
+            StrategyIndex::Exact { field, index } => record_a
+                .fields
+                .get(field)
+                .and_then(|v| index.get(&exact_key(v)))
+                .cloned()
+                .unwrap_or_default(),
+            StrategyIndex::Date {
+                field,
+                window_days,
+                sorted,
+            } => {
+                let Some(date) = record_a.fields.get(field).and_then(value_as_date) else {
+                    return Vec::new();
+                };
+                // A window past the calendar's ends runs to the end of the range
+                let window = chrono::Duration::days(*window_days);
+                let from = date.checked_sub_signed(window).unwrap_or(NaiveDate::MIN);
+                let to = date.checked_add_signed(window).unwrap_or(NaiveDate::MAX);
+                let start = sorted.partition_point(|(d, _)| *d < from);
+                let end = sorted.partition_point(|(d, _)| *d <= to);
+                sorted[start..end].iter().map(|(_, pos)| *pos).collect()
+            }
+            StrategyIndex::Amount {
+                field,
+                bucket_size,
+                buckets,
+            } => {
+                let Some(amount) = record_a.fields.get(field).and_then(value_as_amount) else {
+                    return Vec::new();
+                };
+                let bucket = amount_bucket(amount, *bucket_size);
+                (bucket - 1..=bucket + 1)
+                    .filter_map(|b| buckets.get(&b))
+                    .flatten()
+                    .copied()
+                    .collect()
+            }
+            StrategyIndex::Sorted {
+                field,
+                window_size,
+                sorted,
+            } => {
+                let Some(value) = record_a.fields.get(field) else {
+                    return Vec::new();
+                };
+                let key = value_as_sort_key(value);
+                let position = sorted.partition_point(|(k, _)| *k < key);
+                let half = window_size / 2;
+                let start = position.saturating_sub(half);
+                let end = (start + window_size).min(sorted.len());
+                let start = end.saturating_sub(*window_size);
+                sorted[start..end].iter().map(|(_, pos)| *pos).collect()
+            }
+        }
+    }
+}
+
+fn amount_bucket(amount: f64, bucket_size: f64) -> i64 {
+    (amount / bucket_size).floor() as i64
+}
+
+fn value_as_amount(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => parse_amount(s).and_then(|a| a.to_string().parse().ok()),
+        _ => None,
+    }
+}
+
+fn value_as_date(value: &serde_json::Value) -> Option<NaiveDate> {
+    value.as_str().and_then(parse_date)
+}
+
+fn value_as_sort_key(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.trim().to_lowercase(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::record;
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn no_strategies_yields_every_record() {
+        let records_b = vec![record(json!({"ref": "A"})), record(json!({"ref": "B"}))];
+        let index = CandidateIndex::build(&BlockingConfig::default(), &records_b);
+        assert_eq!(index.candidates(&record(json!({"ref": "A"}))), vec![0, 1]);
+    }
+
+    #[test]
+    fn exact_key_blocks_on_shared_value() {
+        let records_b = vec![
+            record(json!({"ref": "INV-1"})),
+            record(json!({"ref": "INV-2"})),
+            record(json!({"ref": "INV-1"})),
+        ];
+        let config = BlockingConfig {
+            strategies: vec![BlockingStrategy::ExactKey { field: "ref".to_string() }],
+            mode: BlockingMode::Union,
+        };
+        let index = CandidateIndex::build(&config, &records_b);
+        assert_eq!(index.candidates(&record(json!({"ref": "INV-1"}))), vec![0, 2]);
+        assert!(index.candidates(&record(json!({"other": "x"}))).is_empty());
+    }
+
+    #[test]
+    fn date_window_and_amount_bucket_intersect() {
+        let records_b = vec![
+            record(json!({"date": "2024-01-10", "amount": 100.0})),
+            record(json!({"date": "2024-01-11", "amount": 950.0})),
+            record(json!({"date": "2024-02-20", "amount": 101.0})),
+        ];
+        let config = BlockingConfig {
+            strategies: vec![
+                BlockingStrategy::DateWindow {
+                    field: "date".to_string(),
+                    window_days: 3,
+                },
+                BlockingStrategy::AmountBucket {
+                    field: "amount".to_string(),
+                    bucket_size: 10.0,
+                },
+            ],
+            mode: BlockingMode::Intersection,
+        };
+        let index = CandidateIndex::build(&config, &records_b);
+        let a = record(json!({"date": "2024-01-12", "amount": "99.50"}));
+        assert_eq!(index.candidates(&a), vec![0]);
+    }
+
+    #[test]
+    fn huge_date_window_covers_every_dated_record() {
+        let records_b = vec![
+            record(json!({"date": "1950-01-01"})),
+            record(json!({"date": "2024-01-10"})),
+            record(json!({"ref": "undated"})),
+        ];
+        let config = BlockingConfig {
+            strategies: vec![BlockingStrategy::DateWindow {
+                field: "date".to_string(),
+                window_days: i64::MAX,
+            }],
+            mode: BlockingMode::Union,
+        };
+        let index = CandidateIndex::build(&config, &records_b);
+        assert_eq!(index.candidates(&record(json!({"date": "2000-06-01"}))), vec![0, 1]);
+    }
+
+    #[test]
+    fn sorted_neighbourhood_limits_window() {
+        let records_b: Vec<_> = ["alpha", "bravo", "charlie", "delta", "echo"]
+            .iter()
+            .map(|name| record(json!({ "payee": name })))
+            .collect();
+        let config = BlockingConfig {
+            strategies: vec![BlockingStrategy::SortedNeighbourhood {
+                field: "payee".to_string(),
+                window_size: 2,
+            }],
+            mode: BlockingMode::Union,
+        };
+        let index = CandidateIndex::build(&config, &records_b);
+        assert_eq!(index.candidates(&record(json!({"payee": "Charly"}))), vec![2, 3]);
+    }
+
+    #[test]
+    fn stats_track_saved_comparisons() {
+        let mut stats = BlockingStats::new(2, 100);
+        stats.record_candidates(5);
+        stats.record_candidates(15);
+        assert_eq!(stats.total_pairs, 200);
+        assert_eq!(stats.candidate_pairs, 20);
+        assert_eq!(stats.comparisons_saved, 180);
+        assert!((stats.reduction_ratio() - 0.9).abs() < f64::EPSILON);
+    }
+}
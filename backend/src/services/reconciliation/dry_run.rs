@@ -0,0 +1,383 @@
+//! Dry runs of matching rules
+//!
+//! Scores either two sample records or a sample of two data sources with the
+//! same blocking, scoring and assignment stages a job uses, without creating
+//! a job or persisting anything, so analysts can tune rules before launching.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::models::DataSource;
+use crate::errors::{AppError, AppResult};
+
+use super::assignment::{assign, AssignmentMode, CandidatePair};
+use super::blocking::{BlockingConfig, BlockingStats, CandidateIndex};
+use super::processing::{
+    convert_db_record_to_service_record, load_data_source, load_record_page,
+    load_sample_records_from_data_source, RECORD_PAGE_SIZE,
+};
+use super::scoring::{RuleScore, ScoringEngine};
+use super::types::{MatchingRule, ReconciliationRecord};
+
+/// Source A records scored when the request does not set `sample_size`
+pub const DEFAULT_SAMPLE_SIZE: usize = 100;
+/// Upper bound on `sample_size`
+pub const MAX_SAMPLE_SIZE: usize = 1_000;
+/// Pairs listed in a report; the statistics still cover the whole sample
+pub const MAX_REPORTED_PAIRS: usize = 200;
+/// Source B records a sample is scored against; larger sources are cut
+/// to their first records in id order
+pub const MAX_SOURCE_B_RECORDS: usize = 50_000;
+
+fn default_confidence_threshold() -> f64 {
+    0.8
+}
+
+/// Rule test request: a rule set plus either two records or two data sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTestRequest {
+    pub matching_rules: Vec<MatchingRule>,
+    #[serde(default = "default_confidence_threshold")]
+    pub confidence_threshold: f64,
+    #[serde(default)]
+    pub blocking: BlockingConfig,
+    #[serde(default)]
+    pub assignment: AssignmentMode,
+    /// Field values of a sample source A record
+    #[serde(default)]
+    pub record_a: Option<serde_json::Value>,
+    /// Field values of a sample source B record
+    #[serde(default)]
+    pub record_b: Option<serde_json::Value>,
+    #[serde(default)]
+    pub source_a_id: Option<Uuid>,
+    #[serde(default)]
+    pub source_b_id: Option<Uuid>,
+    /// Source A records to score against source B
+    #[serde(default)]
+    pub sample_size: Option<usize>,
+}
+
+/// What a rule test runs against
+pub enum RuleTestInput {
+    Records {
+        record_a: Box<ReconciliationRecord>,
+        record_b: Box<ReconciliationRecord>,
+    },
+    DataSources {
+        source_a_id: Uuid,
+        source_b_id: Uuid,
+        sample_size: usize,
+    },
+}
+
+impl RuleTestRequest {
+    /// Validate the request and work out which input mode it uses
+    pub fn input(&self) -> AppResult<RuleTestInput> {
+        if self.matching_rules.is_empty() {
+            return Err(AppError::Validation(
+                "matching_rules must contain at least one rule".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.confidence_threshold) {
+            return Err(AppError::Validation(
+                "confidence_threshold must be between 0 and 1".to_string(),
+            ));
+        }
+
+        match (&self.record_a, &self.record_b, self.source_a_id, self.source_b_id) {
+            (Some(a), Some(b), None, None) => Ok(RuleTestInput::Records {
+                record_a: Box::new(sample_record(a, "record_a")?),
+                record_b: Box::new(sample_record(b, "record_b")?),
+            }),
+            (None, None, Some(source_a_id), Some(source_b_id)) => {
+                let sample_size = self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE);
+                if sample_size == 0 || sample_size > MAX_SAMPLE_SIZE {
+                    return Err(AppError::Validation(format!(
+                        "sample_size must be between 1 and {}",
+                        MAX_SAMPLE_SIZE
+                    )));
+                }
+                Ok(RuleTestInput::DataSources {
+                    source_a_id,
+                    source_b_id,
+                    sample_size,
+                })
+            }
+            _ => Err(AppError::Validation(
+                "Provide either record_a and record_b, or source_a_id and source_b_id".to_string(),
+            )),
+        }
+    }
+}
+
+/// Build a record from sample field values; an `id` field is used as its id when it is a UUID
+fn sample_record(value: &serde_json::Value, name: &str) -> AppResult<ReconciliationRecord> {
+    let fields = value
+        .as_object()
+        .ok_or_else(|| AppError::Validation(format!("{} must be an object of field values", name)))?;
+    let id = fields
+        .get("id")
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    Ok(ReconciliationRecord {
+        id,
+        source_id: name.to_string(),
+        fields: fields.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        metadata: HashMap::new(),
+    })
+}
+
+/// Score of the best candidate found for one source A record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairTestResult {
+    pub record_a_id: Uuid,
+    pub record_b_id: Uuid,
+    pub confidence_score: f64,
+    /// The pair clears the threshold and no required rule failed
+    pub passed: bool,
+    /// The pair would be linked after assignment
+    pub would_match: bool,
+    pub vetoed_by: Option<String>,
+    pub rule_scores: Vec<RuleScore>,
+}
+
+/// Outcome of a rule test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTestReport {
+    pub records_a: usize,
+    pub records_b: usize,
+    pub pairs_scored: u64,
+    pub pairs_passed: u64,
+    pub matched_records: usize,
+    /// Share of source A records that would be matched
+    pub projected_match_rate: f64,
+    /// Mean confidence of the matches that would be made
+    pub average_confidence: Option<f64>,
+    pub blocking_stats: BlockingStats,
+    /// Best candidate per source A record, including near misses
+    pub pairs: Vec<PairTestResult>,
+    /// Set when `pairs` was cut to `MAX_REPORTED_PAIRS`
+    pub truncated: bool,
+    /// Set when source B was cut to `MAX_SOURCE_B_RECORDS`, so the projected
+    /// match rate may be low
+    pub source_b_truncated: bool,
+}
+
+/// Load the input records and run the rule test; nothing is written
+///
+/// Scoring runs on the blocking pool, off the request's worker thread.
+pub async fn test_rules(db: &Database, request: &RuleTestRequest) -> AppResult<RuleTestReport> {
+    let (records_a, records_b, source_b_truncated) = match request.input()? {
+        RuleTestInput::Records { record_a, record_b } => (vec![*record_a], vec![*record_b], false),
+        RuleTestInput::DataSources {
+            source_a_id,
+            source_b_id,
+            sample_size,
+        } => {
+            let source_a = load_data_source(db, source_a_id)?;
+            let source_b = load_data_source(db, source_b_id)?;
+            let records_a: Vec<ReconciliationRecord> =
+                load_sample_records_from_data_source(db, &source_a, sample_size as i64)
+                    .await?
+                    .iter()
+                    .map(convert_db_record_to_service_record)
+                    .collect();
+            let (records_b, truncated) = load_source_b(db, &source_b).await?;
+            (records_a, records_b, truncated)
+        }
+    };
+
+    let request = request.clone();
+    let mut report = tokio::task::spawn_blocking(move || evaluate(&request, &records_a, &records_b))
+        .await
+        .map_err(|e| AppError::Internal(format!("Rule test failed: {}", e)))?;
+    report.source_b_truncated = source_b_truncated;
+    Ok(report)
+}
+
+/// Up to `MAX_SOURCE_B_RECORDS` records of source B, a page at a time, and
+/// whether there were more
+async fn load_source_b(
+    db: &Database,
+    data_source: &DataSource,
+) -> AppResult<(Vec<ReconciliationRecord>, bool)> {
+    let mut records = Vec::new();
+    let mut after = None;
+    loop {
+        let remaining = MAX_SOURCE_B_RECORDS + 1 - records.len();
+        let limit = RECORD_PAGE_SIZE.min(remaining as i64);
+        let page = load_record_page(db, data_source, after, limit).await?;
+        records.extend(page.iter().map(convert_db_record_to_service_record));
+        match page.last() {
+            Some(last) if page.len() as i64 == limit && records.len() <= MAX_SOURCE_B_RECORDS => {
+                after = Some(last.id)
+            }
+            _ => break,
+        }
+    }
+    let truncated = records.len() > MAX_SOURCE_B_RECORDS;
+    records.truncate(MAX_SOURCE_B_RECORDS);
+    Ok((records, truncated))
+}
+
+/// Run blocking, scoring and assignment over in-memory records
+pub fn evaluate(
+    request: &RuleTestRequest,
+    records_a: &[ReconciliationRecord],
+    records_b: &[ReconciliationRecord],
+) -> RuleTestReport {
+    let mut engine = ScoringEngine::new(&request.matching_rules);
+    if engine.needs_corpus() {
+        engine.fit_corpus(records_a.iter().chain(records_b.iter()));
+    }
+    let index = CandidateIndex::build(&request.blocking, records_b);
+    let mut blocking_stats = BlockingStats::new(records_a.len(), records_b.len());
+
+    let mut best: Vec<Option<PairTestResult>> = Vec::with_capacity(records_a.len());
+    let mut candidate_pairs = Vec::new();
+    let mut pairs_passed = 0u64;
+
+    for (a, record_a) in records_a.iter().enumerate() {
+        let candidates = index.candidates(record_a);
+        blocking_stats.record_candidates(candidates.len());
+
+        let mut best_for_a: Option<PairTestResult> = None;
+        for b in candidates {
+            let record_b = &records_b[b];
+            let score = engine.score(record_a, record_b);
+            let passed = score.vetoed_by.is_none() && score.confidence_score >= request.confidence_threshold;
+            if passed {
+                pairs_passed += 1;
+                candidate_pairs.push(CandidatePair {
+                    a,
+                    b,
+                    score: score.confidence_score,
+                });
+            }
+            // Prefer passing pairs, then higher scores
+            let better = best_for_a.as_ref().is_none_or(|current| {
+                (passed, score.confidence_score) > (current.passed, current.confidence_score)
+            });
+            if better {
+                best_for_a = Some(PairTestResult {
+                    record_a_id: record_a.id,
+                    record_b_id: record_b.id,
+                    confidence_score: score.confidence_score,
+                    passed,
+                    would_match: false,
+                    vetoed_by: score.vetoed_by,
+                    rule_scores: score.rule_scores,
+                });
+            }
+        }
+        best.push(best_for_a);
+    }
+
+    let assignment = assign(request.assignment, records_a.len(), &candidate_pairs);
+    let mut matched_scores = Vec::new();
+    for (a, assigned) in assignment.assigned.iter().enumerate() {
+        let Some(b) = assigned else { continue };
+        if let Some(pair) = candidate_pairs.iter().find(|p| p.a == a && p.b == *b) {
+            matched_scores.push(pair.score);
+        }
+        if let Some(result) = best[a].as_mut() {
+            // Assignment may have moved the record to a lower-ranked candidate
+            result.would_match = result.record_b_id == records_b[*b].id;
+        }
+    }
+
+    let matched_records = matched_scores.len();
+    let mut pairs: Vec<PairTestResult> = best.into_iter().flatten().collect();
+    let truncated = pairs.len() > MAX_REPORTED_PAIRS;
+    pairs.truncate(MAX_REPORTED_PAIRS);
+
+    RuleTestReport {
+        records_a: records_a.len(),
+        records_b: records_b.len(),
+        pairs_scored: blocking_stats.candidate_pairs,
+        pairs_passed,
+        matched_records,
+        projected_match_rate: if records_a.is_empty() {
+            0.0
+        } else {
+            matched_records as f64 / records_a.len() as f64
+        },
+        average_confidence: if matched_scores.is_empty() {
+            None
+        } else {
+            Some(matched_scores.iter().sum::<f64>() / matched_scores.len() as f64)
+        },
+        blocking_stats,
+        pairs,
+        truncated,
+        source_b_truncated: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::{MatchingRuleType, RuleOptions};
+    use serde_json::json;
+
+    fn request(body: serde_json::Value) -> RuleTestRequest {
+        serde_json::from_value(body).unwrap_or_else(|e| panic!("invalid test request: {}", e))
+    }
+
+    fn rule(field: &str, rule_type: MatchingRuleType) -> MatchingRule {
+        MatchingRule {
+            field: field.to_string(),
+            rule_type,
+            weight: 1.0,
+            threshold: 0.8,
+            required: false,
+            options: RuleOptions::default(),
+        }
+    }
+
+    #[test]
+    fn rejects_ambiguous_input() {
+        let mut req = request(json!({
+            "matching_rules": [],
+            "record_a": {"reference": "INV-1"},
+        }));
+        assert!(req.input().is_err());
+
+        req.matching_rules = vec![rule("reference", MatchingRuleType::Exact)];
+        assert!(req.input().is_err());
+
+        req.record_b = Some(json!({"reference": "INV-1"}));
+        assert!(matches!(req.input(), Ok(RuleTestInput::Records { .. })));
+    }
+
+    #[test]
+    fn projects_match_rate_from_sample() {
+        let mut req = request(json!({"matching_rules": [], "confidence_threshold": 0.9}));
+        req.matching_rules = vec![
+            rule("reference", MatchingRuleType::Exact),
+            rule("amount", MatchingRuleType::NumericRange),
+        ];
+        let record = |reference: &str, amount: &str| {
+            sample_record(&json!({"reference": reference, "amount": amount}), "test")
+                .unwrap_or_else(|e| panic!("{}", e))
+        };
+        let records_a = vec![record("INV-1", "10.00"), record("INV-2", "20.00"), record("INV-3", "30.00")];
+        let records_b = vec![record("INV-1", "10.00"), record("INV-2", "25.00")];
+
+        let report = evaluate(&req, &records_a, &records_b);
+        assert_eq!(report.pairs_scored, 6);
+        assert_eq!(report.matched_records, 1);
+        assert!((report.projected_match_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.pairs.len(), 3);
+        assert!(report.pairs[0].would_match);
+        // INV-2 is a near miss: reference matches, amount does not
+        assert!(!report.pairs[1].passed);
+        assert!((report.pairs[1].confidence_score - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,879 @@
+//! Exports of a reconciliation job's results
+//!
+//! A job's results are joined with the records they link and laid out as
+//! sheets: matched pairs, source A records left unmatched, source B records
+//! nothing matched, and one row per field difference of a match. XLSX writes
+//! each sheet as a worksheet. Parquet, for data lake loads, and CSV write one
+//! flat table with a `section` column, each side's source fields as a JSON
+//! column and the differences as JSON. JSON dumps the result rows as stored.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use diesel::prelude::*;
+use parquet::basic::{Compression, ConvertedType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type as SchemaType;
+use rust_xlsxwriter::{Format, Workbook};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{reconciliation_jobs, reconciliation_records, reconciliation_results};
+use crate::models::{DataSource, ReconciliationJob, ReconciliationRecord, ReconciliationResult};
+
+use super::processing::{load_data_source, record_page};
+use super::types::JobSettings;
+
+/// Formats `export_job_results` writes
+pub const EXPORT_FORMATS: &[&str] = &["csv", "json", "xlsx", "parquet"];
+/// Results, or source B records, loaded per query
+const PAGE_SIZE: i64 = 1_000;
+/// Rows per Parquet row group
+const PARQUET_ROW_GROUP_SIZE: usize = 100_000;
+/// Data rows an XLSX worksheet holds below its header
+const XLSX_MAX_ROWS: usize = 1_048_575;
+/// Days from 0001-01-01 to the Unix epoch, for Parquet dates
+const UNIX_EPOCH_DAYS: i32 = 719_163;
+
+/// Export the results of a job to `path` as csv, json, xlsx or parquet
+///
+/// Runs on the blocking pool. Results are read a page at a time and written
+/// as they are read, so a job's results are never all in memory; XLSX reads
+/// them twice, first to learn the source field columns.
+pub async fn export_job_results(db: &Database, job_id: Uuid, path: &Path, format: &str) -> AppResult<()> {
+    if !EXPORT_FORMATS.contains(&format) {
+        return Err(AppError::Validation(format!("Unsupported export format: {}", format)));
+    }
+    let db = db.clone();
+    let path = path.to_path_buf();
+    let format = format.to_string();
+    tokio::task::spawn_blocking(move || {
+        let pages = || JobPages::new(&db, job_id);
+        match format.as_str() {
+            "json" => write_json(pages()?, &path),
+            "csv" => write_csv(pages()?, &path),
+            "xlsx" => write_xlsx(pages, &path),
+            _ => write_parquet(pages()?, &path),
+        }
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Export task failed: {}", e)))?
+}
+
+/// A page of a job's export: results with the records they link, or source
+/// B records that no result matched
+#[derive(Default)]
+struct JobExport {
+    results: Vec<ReconciliationResult>,
+    records: HashMap<Uuid, ReconciliationRecord>,
+    /// Source B records that no result matched
+    unmatched_b: Vec<ReconciliationRecord>,
+}
+
+/// Reads a job's export a page at a time: its results in creation order,
+/// then the unmatched records of its source B
+struct JobPages<'a> {
+    db: &'a Database,
+    job_id: Uuid,
+    source_b: Option<DataSource>,
+    stage: PageStage,
+    after_result: Option<(DateTime<Utc>, Uuid)>,
+    after_record: Option<Uuid>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PageStage {
+    Results,
+    UnmatchedB,
+    Done,
+}
+
+impl<'a> JobPages<'a> {
+    fn new(db: &'a Database, job_id: Uuid) -> AppResult<Self> {
+        let job = {
+            let mut conn = db.get_connection()?;
+            reconciliation_jobs::table
+                .find(job_id)
+                .first::<ReconciliationJob>(&mut conn)
+                .optional()
+                .map_err(AppError::Database)?
+                .ok_or_else(|| AppError::NotFound(format!("Reconciliation job {} not found", job_id)))?
+        };
+        let settings: JobSettings = job
+            .settings
+            .clone()
+            .and_then(|settings| serde_json::from_value(settings).ok())
+            .unwrap_or_default();
+        let source_b = settings
+            .source_b_id
+            .map(|source_b_id| load_data_source(db, source_b_id))
+            .transpose()?;
+        Ok(Self {
+            db,
+            job_id,
+            source_b,
+            stage: PageStage::Results,
+            after_result: None,
+            after_record: None,
+        })
+    }
+
+    fn result_page(&mut self) -> AppResult<Option<JobExport>> {
+        let mut conn = self.db.get_connection()?;
+        let mut query = reconciliation_results::table
+            .filter(reconciliation_results::job_id.eq(self.job_id))
+            .into_boxed();
+        if let Some((created_at, id)) = self.after_result {
+            query = query.filter(
+                reconciliation_results::created_at.gt(created_at).or(reconciliation_results::created_at
+                    .eq(created_at)
+                    .and(reconciliation_results::id.gt(id))),
+            );
+        }
+        let results = query
+            .order((reconciliation_results::created_at.asc(), reconciliation_results::id.asc()))
+            .limit(PAGE_SIZE)
+            .load::<ReconciliationResult>(&mut conn)
+            .map_err(AppError::Database)?;
+        let Some(last) = results.last() else {
+            return Ok(None);
+        };
+        self.after_result = Some((last.created_at, last.id));
+
+        let ids: Vec<Uuid> = results
+            .iter()
+            .flat_map(|result| std::iter::once(result.record_a_id).chain(result.record_b_id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let records = reconciliation_records::table
+            .filter(reconciliation_records::id.eq_any(&ids))
+            .select(ReconciliationRecord::as_select())
+            .load(&mut conn)
+            .map_err(AppError::Database)?
+            .into_iter()
+            .map(|record| (record.id, record))
+            .collect();
+        Ok(Some(JobExport {
+            results,
+            records,
+            unmatched_b: Vec::new(),
+        }))
+    }
+
+    fn unmatched_b_page(&mut self) -> AppResult<Option<JobExport>> {
+        let Some(source_b) = &self.source_b else {
+            return Ok(None);
+        };
+        let page = record_page(self.db, source_b, self.after_record, PAGE_SIZE)?;
+        let Some(last) = page.last() else {
+            return Ok(None);
+        };
+        self.after_record = Some(last.id);
+
+        let ids: Vec<Uuid> = page.iter().map(|record| record.id).collect();
+        let mut conn = self.db.get_connection()?;
+        let matched: HashSet<Uuid> = reconciliation_results::table
+            .filter(reconciliation_results::job_id.eq(self.job_id))
+            .filter(reconciliation_results::record_b_id.eq_any(&ids))
+            .select(reconciliation_results::record_b_id)
+            .load::<Option<Uuid>>(&mut conn)
+            .map_err(AppError::Database)?
+            .into_iter()
+            .flatten()
+            .collect();
+        Ok(Some(JobExport {
+            unmatched_b: page
+                .into_iter()
+                .filter(|record| !matched.contains(&record.id))
+                .collect(),
+            ..Default::default()
+        }))
+    }
+}
+
+impl Iterator for JobPages<'_> {
+    type Item = AppResult<JobExport>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let page = match self.stage {
+                PageStage::Results => self.result_page(),
+                PageStage::UnmatchedB => self.unmatched_b_page(),
+                PageStage::Done => return None,
+            };
+            match page {
+                Ok(Some(page)) => return Some(Ok(page)),
+                Ok(None) => {
+                    self.stage = match self.stage {
+                        PageStage::Results => PageStage::UnmatchedB,
+                        _ => PageStage::Done,
+                    }
+                }
+                Err(e) => {
+                    self.stage = PageStage::Done;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl JobExport {
+    fn record(&self, id: Option<Uuid>) -> Option<&ReconciliationRecord> {
+        id.and_then(|id| self.records.get(&id))
+    }
+
+    /// Note the source fields of this page's records on each side
+    fn add_fields(&self, a_fields: &mut SourceFields, b_fields: &mut SourceFields) {
+        a_fields.add(self.results.iter().filter_map(|result| self.record(Some(result.record_a_id))));
+        b_fields.add(
+            self.results
+                .iter()
+                .filter_map(|result| self.record(result.record_b_id))
+                .chain(self.unmatched_b.iter()),
+        );
+    }
+
+    /// Matched, Unmatched A, Unmatched B and Differences, for a workbook,
+    /// with a column per source field named in `a_fields` and `b_fields`
+    fn sheets(&self, a_fields: &[String], b_fields: &[String]) -> Vec<Sheet> {
+        let (matched, unmatched_a): (Vec<&ReconciliationResult>, Vec<&ReconciliationResult>) =
+            self.results.iter().partition(|result| result.record_b_id.is_some());
+
+        let mut matched_sheet = Sheet::new("Matched");
+        matched_sheet.columns.extend(result_columns());
+        matched_sheet.columns.extend(record_columns("a_", a_fields));
+        matched_sheet.columns.extend(record_columns("b_", b_fields));
+        matched_sheet.columns.push(("amount_difference".to_string(), ColumnKind::Number));
+        for result in &matched {
+            let record_a = self.record(Some(result.record_a_id));
+            let record_b = self.record(result.record_b_id);
+            let mut row = result_cells(result);
+            row.extend(record_cells(record_a, a_fields));
+            row.extend(record_cells(record_b, b_fields));
+            row.push(match (record_a.and_then(|r| r.amount), record_b.and_then(|r| r.amount)) {
+                (Some(a), Some(b)) => Cell::Number(a - b),
+                _ => Cell::Empty,
+            });
+            matched_sheet.rows.push(row);
+        }
+
+        let mut unmatched_a_sheet = Sheet::new("Unmatched A");
+        unmatched_a_sheet.columns.extend(result_columns());
+        unmatched_a_sheet.columns.extend(record_columns("a_", a_fields));
+        for result in &unmatched_a {
+            let mut row = result_cells(result);
+            row.extend(record_cells(self.record(Some(result.record_a_id)), a_fields));
+            unmatched_a_sheet.rows.push(row);
+        }
+
+        let mut unmatched_b_sheet = Sheet::new("Unmatched B");
+        unmatched_b_sheet.columns.extend(record_columns("b_", b_fields));
+        for record in &self.unmatched_b {
+            unmatched_b_sheet.rows.push(record_cells(Some(record), b_fields));
+        }
+
+        let mut differences_sheet = Sheet::new("Differences");
+        differences_sheet.columns.extend(
+            [
+                ("result_id", ColumnKind::Text),
+                ("a_external_id", ColumnKind::Text),
+                ("b_external_id", ColumnKind::Text),
+                ("field_name", ColumnKind::Text),
+                ("source_value", ColumnKind::Text),
+                ("target_value", ColumnKind::Text),
+                ("difference_type", ColumnKind::Text),
+                ("similarity_score", ColumnKind::Number),
+            ]
+            .map(|(name, kind)| (name.to_string(), kind)),
+        );
+        for result in &matched {
+            let external_id = |id: Option<Uuid>| {
+                self.record(id)
+                    .and_then(|record| record.external_id.clone())
+                    .map_or(Cell::Empty, Cell::Text)
+            };
+            for difference in differences(result).iter() {
+                differences_sheet.rows.push(vec![
+                    Cell::Text(result.id.to_string()),
+                    external_id(Some(result.record_a_id)),
+                    external_id(result.record_b_id),
+                    json_cell(difference.get("field_name")),
+                    json_cell(difference.get("source_value")),
+                    json_cell(difference.get("target_value")),
+                    json_cell(difference.get("difference_type")),
+                    difference
+                        .get("similarity_score")
+                        .and_then(Value::as_f64)
+                        .map_or(Cell::Empty, Cell::Number),
+                ]);
+            }
+        }
+
+        vec![matched_sheet, unmatched_a_sheet, unmatched_b_sheet, differences_sheet]
+    }
+
+    /// Every result and unmatched B record as rows of `flat_columns`, for
+    /// CSV and Parquet
+    fn flat_rows(&self) -> Vec<Vec<Cell>> {
+        let mut rows = Vec::with_capacity(self.results.len() + self.unmatched_b.len());
+        let source_data = |record: Option<&ReconciliationRecord>| {
+            record.map_or(Cell::Empty, |record| Cell::Text(record.source_data.to_string()))
+        };
+        for result in &self.results {
+            let record_a = self.record(Some(result.record_a_id));
+            let record_b = self.record(result.record_b_id);
+            let section = if result.record_b_id.is_some() { "matched" } else { "unmatched_a" };
+            let mut row = vec![Cell::Text(section.to_string())];
+            row.extend(result_cells(result));
+            row.extend(record_cells(record_a, &[]));
+            row.push(source_data(record_a));
+            row.extend(record_cells(record_b, &[]));
+            row.push(source_data(record_b));
+            let differences = differences(result);
+            row.push(if differences.is_empty() {
+                Cell::Empty
+            } else {
+                Cell::Text(Value::Array(differences).to_string())
+            });
+            rows.push(row);
+        }
+        let empty_result = result_columns().len() + record_columns("a_", &[]).len() + 1;
+        for record in &self.unmatched_b {
+            let mut row = vec![Cell::Text("unmatched_b".to_string())];
+            row.extend(vec![Cell::Empty; empty_result]);
+            row.extend(record_cells(Some(record), &[]));
+            row.push(source_data(Some(record)));
+            row.push(Cell::Empty);
+            rows.push(row);
+        }
+        rows
+    }
+}
+
+/// Columns of the single table CSV and Parquet exports hold
+fn flat_columns() -> Vec<(String, ColumnKind)> {
+    let mut columns = vec![("section".to_string(), ColumnKind::Text)];
+    columns.extend(result_columns());
+    columns.extend(record_columns("a_", &[]));
+    columns.push(("a_source_data".to_string(), ColumnKind::Text));
+    columns.extend(record_columns("b_", &[]));
+    columns.push(("b_source_data".to_string(), ColumnKind::Text));
+    columns.push(("differences".to_string(), ColumnKind::Text));
+    columns
+}
+
+/// The per-field differences in a result's match details
+fn differences(result: &ReconciliationResult) -> Vec<Value> {
+    result
+        .match_details
+        .as_ref()
+        .and_then(|details| details.get("differences"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Text,
+    Number,
+    Date,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+}
+
+impl Cell {
+    fn text(&self) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+            Cell::Date(date) => date.to_string(),
+        }
+    }
+}
+
+/// A named table of typed columns
+struct Sheet {
+    name: &'static str,
+    columns: Vec<(String, ColumnKind)>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+fn result_columns() -> Vec<(String, ColumnKind)> {
+    [
+        ("result_id", ColumnKind::Text),
+        ("match_type", ColumnKind::Text),
+        ("confidence_score", ColumnKind::Number),
+        ("status", ColumnKind::Text),
+        ("notes", ColumnKind::Text),
+        ("reviewed_by", ColumnKind::Text),
+    ]
+    .map(|(name, kind)| (name.to_string(), kind))
+    .to_vec()
+}
+
+fn result_cells(result: &ReconciliationResult) -> Vec<Cell> {
+    let text = |text: &Option<String>| text.clone().map_or(Cell::Empty, Cell::Text);
+    vec![
+        Cell::Text(result.id.to_string()),
+        Cell::Text(result.match_type.clone()),
+        result
+            .confidence_score
+            .as_ref()
+            .and_then(|score| score.to_f64())
+            .map_or(Cell::Empty, Cell::Number),
+        text(&result.status),
+        text(&result.notes),
+        result.reviewed_by.map_or(Cell::Empty, |id| Cell::Text(id.to_string())),
+    ]
+}
+
+/// A record's columns, then one per source field, named with `prefix`
+fn record_columns(prefix: &str, fields: &[String]) -> Vec<(String, ColumnKind)> {
+    let mut columns: Vec<(String, ColumnKind)> = [
+        ("record_id", ColumnKind::Text),
+        ("external_id", ColumnKind::Text),
+        ("amount", ColumnKind::Number),
+        ("transaction_date", ColumnKind::Date),
+        ("description", ColumnKind::Text),
+    ]
+    .iter()
+    .map(|(name, kind)| (format!("{}{}", prefix, name), *kind))
+    .collect();
+    columns.extend(fields.iter().map(|field| (format!("{}{}", prefix, field), ColumnKind::Text)));
+    columns
+}
+
+fn record_cells(record: Option<&ReconciliationRecord>, fields: &[String]) -> Vec<Cell> {
+    let Some(record) = record else {
+        return vec![Cell::Empty; 5 + fields.len()];
+    };
+    let mut cells = vec![
+        Cell::Text(record.id.to_string()),
+        record.external_id.clone().map_or(Cell::Empty, Cell::Text),
+        record.amount.map_or(Cell::Empty, Cell::Number),
+        record.transaction_date.map_or(Cell::Empty, Cell::Date),
+        record.description.clone().map_or(Cell::Empty, Cell::Text),
+    ];
+    cells.extend(fields.iter().map(|field| json_cell(record.source_data.get(field))));
+    cells
+}
+
+/// Source field names of records, in the order first seen
+#[derive(Default)]
+struct SourceFields {
+    names: Vec<String>,
+    seen: HashSet<String>,
+}
+
+impl SourceFields {
+    fn add<'a>(&mut self, records: impl Iterator<Item = &'a ReconciliationRecord>) {
+        for record in records {
+            if let Some(object) = record.source_data.as_object() {
+                for name in object.keys() {
+                    if !self.seen.contains(name) {
+                        self.seen.insert(name.clone());
+                        self.names.push(name.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A JSON value as text: strings as they are, anything else serialized
+fn json_cell(value: Option<&Value>) -> Cell {
+    match value {
+        None | Some(Value::Null) => Cell::Empty,
+        Some(Value::String(text)) => Cell::Text(text.clone()),
+        Some(other) => Cell::Text(other.to_string()),
+    }
+}
+
+/// The results as stored, in a JSON array
+fn write_json(pages: impl Iterator<Item = AppResult<JobExport>>, path: &Path) -> AppResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut separator: &[u8] = b"[\n";
+    for page in pages {
+        for result in &page?.results {
+            writer.write_all(separator)?;
+            serde_json::to_writer(&mut writer, result)?;
+            separator = b",\n";
+        }
+    }
+    writer.write_all(if separator == b"[\n" { b"[]\n" } else { b"\n]\n" })?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_csv(pages: impl Iterator<Item = AppResult<JobExport>>, path: &Path) -> AppResult<()> {
+    let csv_error = |e: csv::Error| AppError::Internal(format!("Failed to write CSV export: {}", e));
+    let mut writer = csv::Writer::from_path(path).map_err(csv_error)?;
+    writer
+        .write_record(flat_columns().iter().map(|(name, _)| name))
+        .map_err(csv_error)?;
+    for page in pages {
+        for row in page?.flat_rows() {
+            writer.write_record(row.iter().map(Cell::text)).map_err(csv_error)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the workbook's sheets side by side in constant memory mode, each
+/// worksheet flushing rows to disk as it goes
+fn write_xlsx<I>(pages: impl Fn() -> AppResult<I>, path: &Path) -> AppResult<()>
+where
+    I: Iterator<Item = AppResult<JobExport>>,
+{
+    let xlsx_error = |e: rust_xlsxwriter::XlsxError| AppError::Internal(format!("Failed to write XLSX export: {}", e));
+    let mut a_fields = SourceFields::default();
+    let mut b_fields = SourceFields::default();
+    for page in pages()? {
+        page?.add_fields(&mut a_fields, &mut b_fields);
+    }
+    let (a_fields, b_fields) = (a_fields.names, b_fields.names);
+
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let mut workbook = Workbook::new();
+    let layout = JobExport::default().sheets(&a_fields, &b_fields);
+    for sheet in &layout {
+        let worksheet = workbook.add_worksheet_with_constant_memory();
+        worksheet.set_name(sheet.name).map_err(xlsx_error)?;
+        for (col, (name, _)) in sheet.columns.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, col as u16, name, &header)
+                .map_err(xlsx_error)?;
+        }
+    }
+
+    let mut row_counts = vec![0usize; layout.len()];
+    for page in pages()? {
+        for (index, sheet) in page?.sheets(&a_fields, &b_fields).into_iter().enumerate() {
+            let worksheet = workbook.worksheet_from_index(index).map_err(xlsx_error)?;
+            for row in sheet.rows {
+                row_counts[index] += 1;
+                if row_counts[index] > XLSX_MAX_ROWS {
+                    return Err(AppError::Validation(format!(
+                        "{} has more than the {} rows an XLSX worksheet holds; export Parquet instead",
+                        sheet.name, XLSX_MAX_ROWS
+                    )));
+                }
+                let row_number = row_counts[index] as u32;
+                for (col, cell) in row.iter().enumerate() {
+                    let col = col as u16;
+                    match cell {
+                        Cell::Empty => continue,
+                        Cell::Text(text) => worksheet.write_string(row_number, col, text),
+                        Cell::Number(number) => worksheet.write_number(row_number, col, *number),
+                        Cell::Date(value) => worksheet.write_datetime_with_format(row_number, col, value, &date),
+                    }
+                    .map_err(xlsx_error)?;
+                }
+            }
+        }
+    }
+
+    for (index, sheet) in layout.iter().enumerate() {
+        if sheet.columns.is_empty() {
+            continue;
+        }
+        let worksheet = workbook.worksheet_from_index(index).map_err(xlsx_error)?;
+        let last_col = (sheet.columns.len() - 1) as u16;
+        worksheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+        worksheet
+            .autofilter(0, 0, row_counts[index] as u32, last_col)
+            .map_err(xlsx_error)?;
+    }
+    workbook.save(path).map_err(xlsx_error)
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> AppError {
+    AppError::Internal(format!("Failed to write Parquet export: {}", e))
+}
+
+/// Write rows in row groups of `PARQUET_ROW_GROUP_SIZE`, holding at most
+/// one group in memory
+fn write_parquet(pages: impl Iterator<Item = AppResult<JobExport>>, path: &Path) -> AppResult<()> {
+    let columns = flat_columns();
+    let fields = columns
+        .iter()
+        .map(|(name, kind)| {
+            let (physical, converted) = match kind {
+                ColumnKind::Text => (PhysicalType::BYTE_ARRAY, ConvertedType::UTF8),
+                ColumnKind::Number => (PhysicalType::DOUBLE, ConvertedType::NONE),
+                ColumnKind::Date => (PhysicalType::INT32, ConvertedType::DATE),
+            };
+            SchemaType::primitive_type_builder(name, physical)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_converted_type(converted)
+                .build()
+                .map(Arc::new)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(parquet_error)?;
+    let schema = SchemaType::group_type_builder("reconciliation_results")
+        .with_fields(fields)
+        .build()
+        .map_err(parquet_error)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let file = File::create(path)?;
+    let mut writer =
+        SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties)).map_err(parquet_error)?;
+    let mut rows = Vec::with_capacity(PARQUET_ROW_GROUP_SIZE);
+    for page in pages {
+        rows.extend(page?.flat_rows());
+        if rows.len() >= PARQUET_ROW_GROUP_SIZE {
+            write_row_group(&mut writer, &columns, &rows)?;
+            rows.clear();
+        }
+    }
+    if !rows.is_empty() {
+        write_row_group(&mut writer, &columns, &rows)?;
+    }
+    writer.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+fn write_row_group(
+    writer: &mut SerializedFileWriter<File>,
+    columns: &[(String, ColumnKind)],
+    rows: &[Vec<Cell>],
+) -> AppResult<()> {
+    let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+    let mut col = 0;
+    while let Some(mut column) = row_group.next_column().map_err(parquet_error)? {
+        let cells = rows.iter().map(|row| row.get(col).unwrap_or(&Cell::Empty));
+        let mut levels = Vec::with_capacity(rows.len());
+        match columns[col].1 {
+            ColumnKind::Text => {
+                let mut values = Vec::new();
+                for cell in cells {
+                    match cell {
+                        Cell::Empty => levels.push(0),
+                        other => {
+                            values.push(ByteArray::from(other.text().into_bytes()));
+                            levels.push(1);
+                        }
+                    }
+                }
+                column.typed::<ByteArrayType>().write_batch(&values, Some(&levels), None)
+            }
+            ColumnKind::Number => {
+                let mut values = Vec::new();
+                for cell in cells {
+                    match cell {
+                        Cell::Number(number) => {
+                            values.push(*number);
+                            levels.push(1);
+                        }
+                        _ => levels.push(0),
+                    }
+                }
+                column.typed::<DoubleType>().write_batch(&values, Some(&levels), None)
+            }
+            ColumnKind::Date => {
+                let mut values = Vec::new();
+                for cell in cells {
+                    match cell {
+                        Cell::Date(date) => {
+                            values.push(date.num_days_from_ce() - UNIX_EPOCH_DAYS);
+                            levels.push(1);
+                        }
+                        _ => levels.push(0),
+                    }
+                }
+                column.typed::<Int32Type>().write_batch(&values, Some(&levels), None)
+            }
+        }
+        .map_err(parquet_error)?;
+        column.close().map_err(parquet_error)?;
+        col += 1;
+    }
+    row_group.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use serde_json::json;
+
+    fn record(external_id: &str, amount: f64, source_data: Value) -> ReconciliationRecord {
+        ReconciliationRecord {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            ingestion_job_id: Uuid::nil(),
+            external_id: Some(external_id.to_string()),
+            status: "pending".to_string(),
+            amount: Some(amount),
+            transaction_date: NaiveDate::from_ymd_opt(2026, 3, 1),
+            description: Some("Invoice, \"March\"".to_string()),
+            source_data,
+            matching_results: json!({}),
+            confidence: None,
+            audit_trail: json!({}),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn result(record_a: &ReconciliationRecord, record_b: Option<&ReconciliationRecord>) -> ReconciliationResult {
+        ReconciliationResult {
+            id: Uuid::new_v4(),
+            job_id: Uuid::nil(),
+            record_a_id: record_a.id,
+            record_b_id: record_b.map(|record| record.id),
+            match_type: if record_b.is_some() { "fuzzy" } else { "unmatched" }.to_string(),
+            confidence_score: record_b.map(|_| BigDecimal::from(1)),
+            match_details: record_b.map(|_| {
+                json!({"differences": [{
+                    "field_name": "amount",
+                    "source_value": 100.0,
+                    "target_value": 99.5,
+                    "difference_type": "Different",
+                    "similarity_score": 0.5
+                }]})
+            }),
+            status: Some(if record_b.is_some() { "matched" } else { "unmatched" }.to_string()),
+            updated_at: None,
+            notes: Some("checked, see email".to_string()),
+            reviewed_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn export() -> JobExport {
+        let a1 = record("A-1", 100.0, json!({"ref": "X1", "branch": "north"}));
+        let a2 = record("A-2", 20.0, json!({"ref": "X2"}));
+        let b1 = record("B-1", 99.5, json!({"memo": "x1"}));
+        let b2 = record("B-2", 5.0, json!({"memo": "fee"}));
+        let results = vec![result(&a1, Some(&b1)), result(&a2, None)];
+        JobExport {
+            results,
+            records: [a1, a2, b1]
+                .into_iter()
+                .map(|record| (record.id, record))
+                .collect(),
+            unmatched_b: vec![b2],
+        }
+    }
+
+    fn pages() -> std::iter::Once<AppResult<JobExport>> {
+        std::iter::once(Ok(export()))
+    }
+
+    fn sheets(export: &JobExport) -> Vec<Sheet> {
+        let mut a_fields = SourceFields::default();
+        let mut b_fields = SourceFields::default();
+        export.add_fields(&mut a_fields, &mut b_fields);
+        export.sheets(&a_fields.names, &b_fields.names)
+    }
+
+    fn column(sheet: &Sheet, name: &str) -> usize {
+        sheet
+            .columns
+            .iter()
+            .position(|(column, _)| column == name)
+            .unwrap_or_else(|| panic!("no column {} in {}", name, sheet.name))
+    }
+
+    #[test]
+    fn sheets_join_records_and_differences() {
+        let sheets = sheets(&export());
+        let names: Vec<&str> = sheets.iter().map(|sheet| sheet.name).collect();
+        assert_eq!(names, ["Matched", "Unmatched A", "Unmatched B", "Differences"]);
+        let [matched, unmatched_a, unmatched_b, differences] = &sheets[..] else {
+            panic!("expected four sheets");
+        };
+
+        assert_eq!(matched.rows.len(), 1);
+        let row = &matched.rows[0];
+        assert_eq!(row[column(matched, "a_external_id")], Cell::Text("A-1".to_string()));
+        assert_eq!(row[column(matched, "b_external_id")], Cell::Text("B-1".to_string()));
+        assert_eq!(row[column(matched, "a_branch")], Cell::Text("north".to_string()));
+        assert_eq!(row[column(matched, "b_memo")], Cell::Text("x1".to_string()));
+        assert_eq!(row[column(matched, "amount_difference")], Cell::Number(0.5));
+        assert_eq!(row.len(), matched.columns.len());
+
+        assert_eq!(unmatched_a.rows.len(), 1);
+        assert_eq!(unmatched_a.rows[0][column(unmatched_a, "a_branch")], Cell::Empty);
+        assert_eq!(unmatched_b.rows.len(), 1);
+        assert_eq!(unmatched_b.rows[0][column(unmatched_b, "b_memo")], Cell::Text("fee".to_string()));
+
+        assert_eq!(differences.rows.len(), 1);
+        let difference = &differences.rows[0];
+        assert_eq!(difference[column(differences, "field_name")], Cell::Text("amount".to_string()));
+        assert_eq!(difference[column(differences, "source_value")], Cell::Text("100.0".to_string()));
+        assert_eq!(difference[column(differences, "similarity_score")], Cell::Number(0.5));
+    }
+
+    #[test]
+    fn writes_json_csv_xlsx_and_parquet() {
+        let dir = tempfile::tempdir().unwrap_or_else(|e| panic!("tempdir: {}", e));
+
+        let json_path = dir.path().join("results.json");
+        write_json(pages().chain(pages()), &json_path).unwrap_or_else(|e| panic!("json: {}", e));
+        let json = std::fs::read_to_string(&json_path).unwrap_or_else(|e| panic!("read: {}", e));
+        let results: Vec<Value> = serde_json::from_str(&json).unwrap_or_else(|e| panic!("json: {}", e));
+        assert_eq!(results.len(), 4);
+
+        let csv_path = dir.path().join("results.csv");
+        write_csv(pages(), &csv_path).unwrap_or_else(|e| panic!("csv: {}", e));
+        let mut reader = csv::Reader::from_path(&csv_path).unwrap_or_else(|e| panic!("csv: {}", e));
+        let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap_or_else(|e| panic!("{}", e))).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == flat_columns().len()));
+        assert_eq!(rows[0].get(0), Some("matched"));
+        assert!(rows[0].iter().any(|field| field == "checked, see email"));
+        assert_eq!(rows[2].get(0), Some("unmatched_b"));
+
+        let xlsx_path = dir.path().join("results.xlsx");
+        write_xlsx(|| Ok(pages()), &xlsx_path).unwrap_or_else(|e| panic!("xlsx: {}", e));
+        assert!(std::fs::metadata(&xlsx_path).map(|meta| meta.len() > 0).unwrap_or(false));
+
+        let parquet_path = dir.path().join("results.parquet");
+        write_parquet(pages(), &parquet_path).unwrap_or_else(|e| panic!("parquet: {}", e));
+        let file = std::fs::File::open(&parquet_path).unwrap_or_else(|e| panic!("open: {}", e));
+        let reader = SerializedFileReader::new(file).unwrap_or_else(|e| panic!("parquet: {}", e));
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 3);
+        assert_eq!(metadata.schema_descr().num_columns(), flat_columns().len());
+    }
+}
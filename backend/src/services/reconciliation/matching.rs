@@ -1,12 +1,21 @@
 //! Matching algorithms for reconciliation
 //!
 //! This module contains all matching algorithm implementations including
-//! exact matching, fuzzy matching, and contains matching.
+//! exact matching, fuzzy matching (edit distance, token, TF-IDF and phonetic),
+//! contains matching, numeric tolerance matching and date window matching.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
 
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use super::normalization::tokenize;
+use super::phonetic::{double_metaphone, soundex};
 use super::types::{
-    DifferenceType, FieldDifference, FuzzyAlgorithmType, MatchingResult, ReconciliationRecord,
+    AmountTolerance, DateWindow, DifferenceType, FieldDifference, FuzzyAlgorithmType,
+    MatchingResult, ReconciliationRecord,
 };
 use crate::models::MatchType;
 
@@ -53,11 +62,47 @@ impl MatchingAlgorithm for ContainsMatchingAlgorithm {
     }
 }
 
+/// Document frequencies of word tokens across a field's values
+///
+/// Used by cosine similarity so that words common to most values (e.g.
+/// "payment", "ltd") weigh less than distinctive ones.
+#[derive(Debug, Clone, Default)]
+pub struct TfIdfCorpus {
+    document_count: usize,
+    document_frequency: HashMap<String, usize>,
+}
+
+impl TfIdfCorpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_document(&mut self, text: &str) {
+        let tokens: HashSet<String> = tokenize(text).into_iter().collect();
+        for token in tokens {
+            *self.document_frequency.entry(token).or_insert(0) += 1;
+        }
+        self.document_count += 1;
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.document_count
+    }
+
+    /// Smoothed inverse document frequency; unseen tokens get the highest weight
+    pub fn idf(&self, token: &str) -> f64 {
+        let df = self.document_frequency.get(token).copied().unwrap_or(0);
+        ((1.0 + self.document_count as f64) / (1.0 + df as f64)).ln() + 1.0
+    }
+}
+
 /// Fuzzy matching algorithm
 #[derive(Debug, Clone)]
 pub struct FuzzyMatchingAlgorithm {
     pub threshold: f64,
     pub algorithm_type: FuzzyAlgorithmType,
+    /// Token statistics for `Cosine`; plain term frequencies when absent
+    pub corpus: Option<Arc<TfIdfCorpus>>,
 }
 
 impl FuzzyMatchingAlgorithm {
@@ -65,9 +110,15 @@ impl FuzzyMatchingAlgorithm {
         Self {
             threshold,
             algorithm_type,
+            corpus: None,
         }
     }
 
+    pub fn with_corpus(mut self, corpus: Arc<TfIdfCorpus>) -> Self {
+        self.corpus = Some(corpus);
+        self
+    }
+
     fn levenshtein_distance(&self, s1: &str, s2: &str) -> usize {
         let s1_chars: Vec<char> = s1.chars().collect();
         let s2_chars: Vec<char> = s2.chars().collect();
@@ -107,57 +158,351 @@ impl FuzzyMatchingAlgorithm {
         matrix[s1_len][s2_len]
     }
 
+    fn levenshtein_similarity(&self, s1: &str, s2: &str) -> f64 {
+        let distance = self.levenshtein_distance(s1, s2);
+        let max_len = s1.len().max(s2.len());
+        if max_len == 0 {
+            1.0
+        } else {
+            1.0 - (distance as f64 / max_len as f64)
+        }
+    }
+
     fn jaro_winkler(&self, s1: &str, s2: &str) -> f64 {
         strsim::jaro_winkler(s1, s2)
     }
+
+    /// |A ∩ B| / |A ∪ B| over word token sets
+    fn jaccard(&self, s1: &str, s2: &str) -> f64 {
+        let a: HashSet<String> = tokenize(s1).into_iter().collect();
+        let b: HashSet<String> = tokenize(s2).into_iter().collect();
+        if a.is_empty() && b.is_empty() {
+            return 1.0;
+        }
+        let intersection = a.intersection(&b).count();
+        let union = a.union(&b).count();
+        intersection as f64 / union as f64
+    }
+
+    /// Cosine of the TF-IDF vectors of the two values
+    fn cosine(&self, s1: &str, s2: &str) -> f64 {
+        let vector = |text: &str| -> HashMap<String, f64> {
+            let mut weights: HashMap<String, f64> = HashMap::new();
+            for token in tokenize(text) {
+                *weights.entry(token).or_insert(0.0) += 1.0;
+            }
+            for (token, weight) in weights.iter_mut() {
+                *weight *= self.corpus.as_ref().map_or(1.0, |c| c.idf(token));
+            }
+            weights
+        };
+        let (a, b) = (vector(s1), vector(s2));
+        if a.is_empty() && b.is_empty() {
+            return 1.0;
+        }
+
+        let dot: f64 = a
+            .iter()
+            .filter_map(|(token, wa)| b.get(token).map(|wb| wa * wb))
+            .sum();
+        let norm = |v: &HashMap<String, f64>| v.values().map(|w| w * w).sum::<f64>().sqrt();
+        let denominator = norm(&a) * norm(&b);
+        if denominator == 0.0 {
+            0.0
+        } else {
+            (dot / denominator).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Share of words whose phonetic codes match a word on the other side
+    ///
+    /// Each word is paired with at most one word on the other side; the
+    /// score is 2·pairs / (words in a + words in b).
+    fn phonetic(&self, s1: &str, s2: &str, encode: fn(&str) -> Vec<String>) -> f64 {
+        let codes_a: Vec<Vec<String>> = tokenize(s1).iter().map(|t| encode(t)).collect();
+        let codes_b: Vec<Vec<String>> = tokenize(s2).iter().map(|t| encode(t)).collect();
+        if codes_a.is_empty() && codes_b.is_empty() {
+            return 1.0;
+        }
+
+        let mut used = vec![false; codes_b.len()];
+        let mut pairs = 0;
+        for a in &codes_a {
+            let found = codes_b.iter().enumerate().find(|(j, b)| {
+                !used[*j] && a.iter().any(|code| !code.is_empty() && b.contains(code))
+            });
+            if let Some((j, _)) = found {
+                used[j] = true;
+                pairs += 1;
+            }
+        }
+        2.0 * pairs as f64 / (codes_a.len() + codes_b.len()) as f64
+    }
+}
+
+fn soundex_codes(token: &str) -> Vec<String> {
+    vec![soundex(token)]
+}
+
+fn metaphone_codes(token: &str) -> Vec<String> {
+    let (primary, alternate) = double_metaphone(token);
+    if alternate == primary {
+        vec![primary]
+    } else {
+        vec![primary, alternate]
+    }
 }
 
 impl MatchingAlgorithm for FuzzyMatchingAlgorithm {
     fn calculate_similarity(&self, value_a: &str, value_b: &str) -> f64 {
         match self.algorithm_type {
-            FuzzyAlgorithmType::Levenshtein => {
-                let distance = self.levenshtein_distance(value_a, value_b);
-                let max_len = value_a.len().max(value_b.len());
-                if max_len == 0 {
-                    1.0
-                } else {
-                    1.0 - (distance as f64 / max_len as f64)
-                }
-            }
+            FuzzyAlgorithmType::Levenshtein => self.levenshtein_similarity(value_a, value_b),
             FuzzyAlgorithmType::JaroWinkler => self.jaro_winkler(value_a, value_b),
-            _ => {
-                // Fallback to Levenshtein
-                let distance = self.levenshtein_distance(value_a, value_b);
-                let max_len = value_a.len().max(value_b.len());
-                if max_len == 0 {
-                    1.0
-                } else {
-                    1.0 - (distance as f64 / max_len as f64)
-                }
+            FuzzyAlgorithmType::Jaccard => self.jaccard(value_a, value_b),
+            FuzzyAlgorithmType::Cosine => self.cosine(value_a, value_b),
+            FuzzyAlgorithmType::Soundex => self.phonetic(value_a, value_b, soundex_codes),
+            FuzzyAlgorithmType::Metaphone => self.phonetic(value_a, value_b, metaphone_codes),
+        }
+    }
+
+    fn get_algorithm_name(&self) -> &str {
+        match self.algorithm_type {
+            FuzzyAlgorithmType::Levenshtein => "levenshtein",
+            FuzzyAlgorithmType::JaroWinkler => "jaro_winkler",
+            FuzzyAlgorithmType::Jaccard => "jaccard",
+            FuzzyAlgorithmType::Cosine => "cosine",
+            FuzzyAlgorithmType::Soundex => "soundex",
+            FuzzyAlgorithmType::Metaphone => "metaphone",
+        }
+    }
+}
+
+/// Numeric matching with an absolute or percentage tolerance
+///
+/// Amounts are parsed as `BigDecimal`, so "1,000.50" and "1000.5" compare
+/// equal. Equal amounts score 1.0; amounts inside the tolerance score
+/// linearly down to 0.9 at the tolerance edge; anything outside scores 0.0.
+#[derive(Debug, Clone)]
+pub struct NumericRangeMatchingAlgorithm {
+    pub tolerance: Option<AmountTolerance>,
+}
+
+impl NumericRangeMatchingAlgorithm {
+    pub fn new(tolerance: Option<AmountTolerance>) -> Self {
+        Self { tolerance }
+    }
+
+    /// Absolute tolerance allowed between `a` and `b`
+    fn allowed_difference(&self, a: &BigDecimal, b: &BigDecimal) -> BigDecimal {
+        match &self.tolerance {
+            None => BigDecimal::zero(),
+            Some(AmountTolerance::Absolute(value)) => value.abs(),
+            Some(AmountTolerance::Percentage(percent)) => {
+                let base = if a.abs() > b.abs() { a.abs() } else { b.abs() };
+                base * percent.abs() / BigDecimal::from(100)
             }
         }
     }
+}
+
+impl MatchingAlgorithm for NumericRangeMatchingAlgorithm {
+    fn calculate_similarity(&self, value_a: &str, value_b: &str) -> f64 {
+        let (Some(a), Some(b)) = (parse_amount(value_a), parse_amount(value_b)) else {
+            return 0.0;
+        };
+        let difference = (&a - &b).abs();
+        if difference.is_zero() {
+            return 1.0;
+        }
+        let allowed = self.allowed_difference(&a, &b);
+        if allowed.is_zero() || difference > allowed {
+            return 0.0;
+        }
+        let ratio = (difference / allowed).to_string().parse::<f64>().unwrap_or(1.0);
+        1.0 - 0.1 * ratio.clamp(0.0, 1.0)
+    }
+
+    fn get_algorithm_name(&self) -> &str {
+        "numeric_range"
+    }
+}
+
+/// Date matching within a ±N day window
+///
+/// With `business_days` set, weekends and the configured holidays are not
+/// counted, so a Friday booking settling on Monday is one day apart. Same-day
+/// dates score 1.0; dates inside the window score linearly down to 0.9 at the
+/// window edge; anything outside scores 0.0.
+#[derive(Debug, Clone)]
+pub struct DateRangeMatchingAlgorithm {
+    pub window: DateWindow,
+    /// `window.holidays`, sorted for range lookups
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl DateRangeMatchingAlgorithm {
+    pub fn new(window: DateWindow) -> Self {
+        let holidays = window.holidays.iter().copied().collect();
+        Self { window, holidays }
+    }
+
+    fn distance_days(&self, a: NaiveDate, b: NaiveDate) -> i64 {
+        if self.window.business_days {
+            business_days_within(a, b, &self.holidays, self.window.days.max(0))
+        } else {
+            (a - b).num_days().abs()
+        }
+    }
+}
+
+impl MatchingAlgorithm for DateRangeMatchingAlgorithm {
+    fn calculate_similarity(&self, value_a: &str, value_b: &str) -> f64 {
+        let (Some(a), Some(b)) = (parse_date(value_a), parse_date(value_b)) else {
+            return 0.0;
+        };
+        let distance = self.distance_days(a, b);
+        if distance == 0 {
+            return 1.0;
+        }
+        let window = self.window.days.max(0);
+        if distance > window {
+            return 0.0;
+        }
+        1.0 - 0.1 * (distance as f64 / window as f64)
+    }
 
     fn get_algorithm_name(&self) -> &str {
-        "fuzzy"
+        "date_range"
+    }
+}
+
+/// Count business days between two dates, skipping weekends and holidays
+///
+/// The result is the number of business days you have to step forward from
+/// the earlier date to reach the later one. Whole weeks are counted at once,
+/// so the cost doesn't grow with the distance between the dates.
+pub fn business_days_between(a: NaiveDate, b: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> i64 {
+    let (start, end) = if a <= b { (a, b) } else { (b, a) };
+    weekdays_after(start, end) - weekday_holidays(start, end, holidays)
+}
+
+/// `business_days_between`, or any count above `limit` once it is certain
+/// to exceed it
+///
+/// Scoring only needs to know whether dates are within a window; this skips
+/// the holiday lookup for pairs that are out of it even if every holiday
+/// counted.
+pub fn business_days_within(
+    a: NaiveDate,
+    b: NaiveDate,
+    holidays: &BTreeSet<NaiveDate>,
+    limit: i64,
+) -> i64 {
+    let (start, end) = if a <= b { (a, b) } else { (b, a) };
+    let weekdays = weekdays_after(start, end);
+    if weekdays - holidays.len() as i64 > limit {
+        return weekdays - holidays.len() as i64;
+    }
+    weekdays - weekday_holidays(start, end, holidays)
+}
+
+/// Weekdays in `(start, end]`
+fn weekdays_after(start: NaiveDate, end: NaiveDate) -> i64 {
+    let span = (end - start).num_days();
+    let mut weekdays = span / 7 * 5;
+    // The days left over after the whole weeks: at most six
+    let mut current = start + chrono::Duration::days(span / 7 * 7);
+    while current < end {
+        current = match current.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+        if !matches!(current.weekday(), Weekday::Sat | Weekday::Sun) {
+            weekdays += 1;
+        }
+    }
+    weekdays
+}
+
+/// Holidays in `(start, end]` that fall on weekdays
+fn weekday_holidays(start: NaiveDate, end: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> i64 {
+    use std::ops::Bound::{Excluded, Included};
+    holidays
+        .range((Excluded(start), Included(end)))
+        .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+        .count() as i64
+}
+
+/// Parse an amount string into a `BigDecimal`
+///
+/// Accepts thousands separators, surrounding currency symbols or codes on
+/// either side of the sign ("-$125.00", "$-125.00"), and accounting-style
+/// negatives such as "(125.00)" or "125.00-".
+pub fn parse_amount(value: &str) -> Option<BigDecimal> {
+    let not_numeric = |c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.'));
+    let trimmed = value.trim();
+    let (mut negative, body) = match trimmed.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, trimmed),
+    };
+    let mut body = body.trim_matches(not_numeric);
+    if let Some(inner) = body.strip_suffix('-') {
+        negative = true;
+        body = inner;
+    }
+    if let Some(inner) = body.strip_prefix('-') {
+        negative = !negative;
+        body = inner.trim_start_matches(not_numeric);
+    } else if let Some(inner) = body.strip_prefix('+') {
+        body = inner.trim_start_matches(not_numeric);
     }
+    let cleaned: String = body
+        .chars()
+        .filter(|c| !matches!(c, ',' | ' ' | '\'' | '_'))
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    let amount = BigDecimal::from_str(&cleaned).ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// Parse a date string in the formats commonly found in bank and ledger exports
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let s = value.trim();
+    ["%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y", "%Y%m%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.date_naive())
+        })
+        .or_else(|| s.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()))
 }
 
 /// Build an exact index for fast lookups
+///
+/// Maps each value of `key_field` to the positions in `records` holding it,
+/// so no record's fields are copied.
 pub fn build_exact_index(
-    records: &[(uuid::Uuid, HashMap<String, serde_json::Value>)],
+    records: &[ReconciliationRecord],
     key_field: &str,
-) -> HashMap<String, Vec<uuid::Uuid>> {
-    let mut index: HashMap<String, Vec<uuid::Uuid>> = HashMap::new();
-    for (id, fields) in records.iter() {
-        if let Some(val) = fields.get(key_field) {
-            let key = val.to_string();
-            index.entry(key).or_default().push(*id);
+) -> HashMap<String, Vec<usize>> {
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (pos, record) in records.iter().enumerate() {
+        if let Some(val) = record.fields.get(key_field) {
+            index.entry(exact_key(val)).or_default().push(pos);
         }
     }
     index
 }
 
+/// Key a value is looked up under in an index from `build_exact_index`
+pub fn exact_key(value: &serde_json::Value) -> String {
+    value.to_string()
+}
+
 /// Match two records using specified algorithm
 pub fn match_records(
     source_record: &ReconciliationRecord,
@@ -239,6 +584,101 @@ mod tests {
         assert_eq!(alg.calculate_similarity("alpha", "beta"), 0.0);
     }
 
+    #[test]
+    fn token_and_phonetic_fuzzy_algorithms() {
+        let jaccard = FuzzyMatchingAlgorithm::new(0.5, FuzzyAlgorithmType::Jaccard);
+        assert!((jaccard.calculate_similarity("Acme Widgets Ltd", "widgets acme") - 2.0 / 3.0).abs() < 1e-9);
+
+        let soundex = FuzzyMatchingAlgorithm::new(0.5, FuzzyAlgorithmType::Soundex);
+        assert_eq!(soundex.calculate_similarity("Smyth & Sons", "SMITH AND SONS"), 0.8);
+
+        let metaphone = FuzzyMatchingAlgorithm::new(0.5, FuzzyAlgorithmType::Metaphone);
+        assert_eq!(metaphone.calculate_similarity("Schmidt", "Smith"), 1.0);
+        assert_eq!(metaphone.get_algorithm_name(), "metaphone");
+    }
+
+    #[test]
+    fn cosine_downweights_common_tokens() {
+        let mut corpus = TfIdfCorpus::new();
+        for doc in ["acme payment", "globex payment", "initech payment", "acme refund"] {
+            corpus.add_document(doc);
+        }
+        let plain = FuzzyMatchingAlgorithm::new(0.5, FuzzyAlgorithmType::Cosine);
+        let weighted = plain.clone().with_corpus(Arc::new(corpus));
+
+        assert!((plain.calculate_similarity("acme payment", "acme payment") - 1.0).abs() < 1e-9);
+        // Sharing only the common word scores lower once IDF is applied
+        let shared_common = |alg: &FuzzyMatchingAlgorithm| alg.calculate_similarity("acme payment", "globex payment");
+        assert!(shared_common(&weighted) < shared_common(&plain));
+    }
+
+    #[test]
+    fn numeric_range_uses_decimal_tolerance() {
+        let exact = NumericRangeMatchingAlgorithm::new(None);
+        assert_eq!(exact.calculate_similarity("1,000.50", "1000.5"), 1.0);
+        assert_eq!(exact.calculate_similarity("1000.50", "1000.51"), 0.0);
+
+        let absolute = NumericRangeMatchingAlgorithm::new(Some(AmountTolerance::Absolute(
+            BigDecimal::from_str("0.05").unwrap_or_default(),
+        )));
+        let within = absolute.calculate_similarity("100.00", "100.05");
+        assert!((0.9..1.0).contains(&within));
+        assert_eq!(absolute.calculate_similarity("100.00", "100.06"), 0.0);
+
+        let percentage = NumericRangeMatchingAlgorithm::new(Some(AmountTolerance::Percentage(
+            BigDecimal::from(1),
+        )));
+        assert!(percentage.calculate_similarity("(200.00)", "-198.50") > 0.9);
+        assert_eq!(percentage.calculate_similarity("200.00", "197.00"), 0.0);
+    }
+
+    #[test]
+    fn parse_amount_handles_signs_around_currency_symbols() {
+        let minus = BigDecimal::from_str("-125.00").ok();
+        for value in [
+            "-$125.00", "$-125.00", "- $125.00", "(125.00)", "125.00-", "USD -125.00", "-€125",
+        ] {
+            assert_eq!(parse_amount(value), minus, "{}", value);
+        }
+        assert_eq!(parse_amount("+$1,250.00"), BigDecimal::from_str("1250").ok());
+        assert_eq!(parse_amount("$"), None);
+    }
+
+    #[test]
+    fn date_range_respects_business_days() {
+        let calendar = DateRangeMatchingAlgorithm::new(DateWindow {
+            days: 2,
+            business_days: false,
+            holidays: vec![],
+        });
+        // Friday -> Monday is three calendar days
+        assert_eq!(calendar.calculate_similarity("2024-03-01", "2024-03-04"), 0.0);
+
+        let business = DateRangeMatchingAlgorithm::new(DateWindow {
+            days: 2,
+            business_days: true,
+            holidays: vec![],
+        });
+        assert!(business.calculate_similarity("2024-03-01", "2024-03-04") > 0.9);
+        assert_eq!(business.calculate_similarity("2024-03-01", "2024-03-01T10:00:00Z"), 1.0);
+
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap_or_default();
+        let holidays = BTreeSet::from([day(2024, 3, 4)]);
+        assert_eq!(business_days_between(day(2024, 3, 1), day(2024, 3, 5), &holidays), 1);
+
+        // Whole weeks are counted directly, in either order
+        let none = BTreeSet::new();
+        assert_eq!(business_days_between(day(2024, 3, 1), day(2024, 3, 29), &none), 20);
+        assert_eq!(business_days_between(day(2024, 3, 29), day(2024, 3, 1), &none), 20);
+        assert_eq!(business_days_between(day(2024, 3, 2), day(2024, 3, 3), &none), 0);
+        assert_eq!(business_days_between(day(2024, 3, 2), day(2024, 3, 4), &none), 1);
+
+        // Far-apart dates score 0.0 without stepping through the span
+        let far = business_days_within(day(1, 1, 1), day(2026, 1, 1), &holidays, 2);
+        assert!(far > 2);
+        assert_eq!(business.calculate_similarity("0001-01-01", "2026-01-01"), 0.0);
+    }
+
     #[test]
     fn fuzzy_levenshtein_similarity_behaves_reasonably() {
         let alg = FuzzyMatchingAlgorithm::new(0.7, FuzzyAlgorithmType::Levenshtein);
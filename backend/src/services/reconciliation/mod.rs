@@ -1,26 +1,64 @@
 //! Reconciliation service module
 //!
 //! This module provides the core reconciliation engine split into focused modules:
-//! - `matching.rs`: Matching algorithms (exact, fuzzy, contains)
+//! - `aggregate.rs`: Split/aggregate (N:1, 1:N) matching of leftover records
+//! - `approvals.rs`: Maker-checker approval of match decisions under a project's policy
+//! - `assignment.rs`: One-to-one resolution of candidate matches (greedy, optimal)
+//! - `blocking.rs`: Candidate pair generation (blocking) before scoring
+//! - `dry_run.rs`: Rule tests that score samples without persisting anything
+//! - `export.rs`: CSV, JSON, XLSX and Parquet exports of a job's results with their records
+//! - `matching.rs`: Matching algorithms (exact, fuzzy, contains, numeric, date)
+//! - `normalization.rs`: Per-rule text normalisation (case, punctuation, legal suffixes)
+//! - `phonetic.rs`: Soundex and Double Metaphone encodings
+//! - `scoring.rs`: Rule-based scoring (per-rule algorithms, weights, vetoes)
 //! - `processing.rs`: Processing logic (chunking, result saving)
 //! - `job_management.rs`: Job lifecycle management
+//! - `queue.rs`: Durable Postgres job queue (leases, retries, dead letters, resumption)
 //! - `types.rs`: Common types and data structures
 
+pub mod aggregate;
+pub mod approvals;
+pub mod assignment;
+pub mod blocking;
+pub mod dry_run;
+pub mod export;
 pub mod job_management;
 pub mod matching;
+pub mod normalization;
+pub mod phonetic;
 pub mod processing;
 pub mod processing_config;
+pub mod queue;
+pub mod scoring;
 pub mod service;
 pub mod types;
 
+#[cfg(test)]
+mod test_support;
+
+pub use aggregate::{
+    find_aggregate_matches, AggregateConfig, AggregateDirection, AggregateMatch, GroupKey,
+};
+pub use approvals::{
+    ApprovalDecision, ApprovalPolicy, ApprovalQuery, ApprovalReason, ApprovalStatus, MatchProposal,
+    MatchStatus,
+};
+pub use assignment::{assign, Assignment, AssignmentMode, CandidatePair};
+pub use blocking::{BlockingConfig, BlockingMode, BlockingStats, BlockingStrategy, CandidateIndex};
+pub use dry_run::{PairTestResult, RuleTestReport, RuleTestRequest};
+pub use export::{export_job_results, EXPORT_FORMATS};
 pub use job_management::{JobHandle, JobProcessor, JobProgress, JobStatus};
 pub use matching::{
-    build_exact_index, match_records, ContainsMatchingAlgorithm, ExactMatchingAlgorithm,
-    FuzzyMatchingAlgorithm, MatchingAlgorithm,
+    build_exact_index, business_days_between, business_days_within, exact_key, match_records,
+    parse_amount, parse_date, ContainsMatchingAlgorithm, DateRangeMatchingAlgorithm, ExactMatchingAlgorithm,
+    FuzzyMatchingAlgorithm, MatchingAlgorithm, NumericRangeMatchingAlgorithm, TfIdfCorpus,
 };
+pub use phonetic::{double_metaphone, soundex};
+pub use queue::{JobQueue, QueueState, QueueWorker};
+pub use scoring::{PairScore, RuleScore, ScoringEngine};
 pub use processing::{
     process_data_sources_chunked, save_reconciliation_results, send_progress, update_job_progress,
-    update_job_status,
+    update_job_status, ProcessingCheckpoint, ProcessingSummary, ResultWriter,
 };
 pub use types::FuzzyAlgorithmType;
 pub use types::*;
@@ -28,7 +66,7 @@ pub use types::*;
 // Re-export for backward compatibility
 use crate::database::Database;
 use crate::errors::AppResult;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use uuid::Uuid;
 
 use crate::models::ReconciliationJob;
@@ -44,153 +82,120 @@ pub struct ReconciliationService {
 
 pub use self::processing::*;
 
-// Re-export for backward compatibility
-impl ReconciliationService {
-    pub fn new(db: Database) -> Self {
-        // Get timeout from environment or use default (2 hours)
-        // Improved error handling with logging for invalid values
-        let timeout_seconds = match std::env::var("RECONCILIATION_JOB_TIMEOUT_SECONDS") {
-            Ok(val) => {
-                match val.parse::<u64>() {
-                    Ok(parsed) if parsed > 0 => {
-                        log::info!("Using RECONCILIATION_JOB_TIMEOUT_SECONDS={} from environment", parsed);
-                        parsed
-                    }
-                    Ok(0) => {
-                        log::warn!(
-                            "RECONCILIATION_JOB_TIMEOUT_SECONDS is 0, using default: {}",
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
+/// Job processor shared by every `ReconciliationService` and the queue
+/// worker of this process, so that in-process job status, start checks and
+/// stops cover jobs whichever of them runs them
+static JOB_PROCESSOR: OnceLock<Arc<JobProcessor>> = OnceLock::new();
+
+/// The process's job processor, created (and its stuck-job monitor started)
+/// on first use
+///
+/// `RECONCILIATION_WORKER_CONCURRENCY` bounds how many jobs it runs at once
+/// (default 5) and `RECONCILIATION_JOB_TIMEOUT_SECONDS` how long one may run.
+pub fn shared_job_processor() -> Arc<JobProcessor> {
+    Arc::clone(JOB_PROCESSOR.get_or_init(|| {
+        let concurrency = std::env::var("RECONCILIATION_WORKER_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(5);
+        let processor = Arc::new(JobProcessor::new_with_timeout(
+            concurrency,
+            100,
+            job_timeout_seconds(),
+        ));
+        start_timeout_monitor(Arc::clone(&processor));
+        processor
+    }))
+}
+
+/// Job timeout from the environment, or the default (2 hours)
+fn job_timeout_seconds() -> u64 {
+    // Improved error handling with logging for invalid values
+    match std::env::var("RECONCILIATION_JOB_TIMEOUT_SECONDS") {
+        Ok(val) => {
+            match val.parse::<u64>() {
+                Ok(parsed) if parsed > 0 => {
+                    log::info!("Using RECONCILIATION_JOB_TIMEOUT_SECONDS={} from environment", parsed);
+                    parsed
+                }
+                Ok(0) => {
+                    log::warn!(
+                        "RECONCILIATION_JOB_TIMEOUT_SECONDS is 0, using default: {}",
                         job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                    }
-                    Ok(parsed) => {
-                        log::warn!(
-                            "RECONCILIATION_JOB_TIMEOUT_SECONDS={} is invalid (must be > 0), using default: {}",
-                            parsed,
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
+                    );
+                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
+                }
+                Ok(parsed) => {
+                    log::warn!(
+                        "RECONCILIATION_JOB_TIMEOUT_SECONDS={} is invalid (must be > 0), using default: {}",
+                        parsed,
                         job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to parse RECONCILIATION_JOB_TIMEOUT_SECONDS='{}': {}. Using default: {}",
-                            val,
-                            e,
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
+                    );
+                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to parse RECONCILIATION_JOB_TIMEOUT_SECONDS='{}': {}. Using default: {}",
+                        val,
+                        e,
                         job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                    }
+                    );
+                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
                 }
             }
-            Err(std::env::VarError::NotPresent) => {
-                log::debug!(
-                    "RECONCILIATION_JOB_TIMEOUT_SECONDS not set, using default: {}",
-                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                );
+        }
+        Err(std::env::VarError::NotPresent) => {
+            log::debug!(
+                "RECONCILIATION_JOB_TIMEOUT_SECONDS not set, using default: {}",
                 job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-            }
-            Err(e) => {
-                log::warn!(
-                    "Error reading RECONCILIATION_JOB_TIMEOUT_SECONDS: {}. Using default: {}",
-                    e,
-                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                );
+            );
+            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
+        }
+        Err(e) => {
+            log::warn!(
+                "Error reading RECONCILIATION_JOB_TIMEOUT_SECONDS: {}. Using default: {}",
+                e,
                 job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-            }
-        };
-        
-        let job_processor = Arc::new(JobProcessor::new_with_timeout(5, 100, timeout_seconds));
-        let service = Self { db, job_processor };
-        
-        // Start background task to monitor for stuck jobs
-        service.start_timeout_monitor();
-        
-        service
+            );
+            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
+        }
     }
+}
 
-    pub fn new_with_ws(db: Database, _ws_server: actix::Addr<crate::websocket::WsServer>) -> Self {
-        // Get timeout from environment or use default (2 hours)
-        // Improved error handling with logging for invalid values
-        let timeout_seconds = match std::env::var("RECONCILIATION_JOB_TIMEOUT_SECONDS") {
-            Ok(val) => {
-                match val.parse::<u64>() {
-                    Ok(parsed) if parsed > 0 => {
-                        log::info!("Using RECONCILIATION_JOB_TIMEOUT_SECONDS={} from environment", parsed);
-                        parsed
-                    }
-                    Ok(0) => {
-                        log::warn!(
-                            "RECONCILIATION_JOB_TIMEOUT_SECONDS is 0, using default: {}",
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
-                        job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                    }
-                    Ok(parsed) => {
-                        log::warn!(
-                            "RECONCILIATION_JOB_TIMEOUT_SECONDS={} is invalid (must be > 0), using default: {}",
-                            parsed,
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
-                        job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to parse RECONCILIATION_JOB_TIMEOUT_SECONDS='{}': {}. Using default: {}",
-                            val,
-                            e,
-                            job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                        );
-                        job_management::DEFAULT_JOB_TIMEOUT_SECONDS
+/// Start background task to periodically check for stuck jobs
+fn start_timeout_monitor(processor: Arc<JobProcessor>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60)); // Check every minute
+        loop {
+            interval.tick().await;
+
+            let stuck_jobs = processor.check_stuck_jobs().await;
+            if !stuck_jobs.is_empty() {
+                log::warn!("Found {} stuck job(s), forcing timeout", stuck_jobs.len());
+                for job_id in stuck_jobs {
+                    if let Err(e) = processor.timeout_job(job_id).await {
+                        log::error!("Failed to timeout stuck job {}: {}", job_id, e);
+                    } else {
+                        log::info!("Successfully timed out stuck job {}", job_id);
                     }
                 }
             }
-            Err(std::env::VarError::NotPresent) => {
-                log::debug!(
-                    "RECONCILIATION_JOB_TIMEOUT_SECONDS not set, using default: {}",
-                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                );
-                job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-            }
-            Err(e) => {
-                log::warn!(
-                    "Error reading RECONCILIATION_JOB_TIMEOUT_SECONDS: {}. Using default: {}",
-                    e,
-                    job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-                );
-                job_management::DEFAULT_JOB_TIMEOUT_SECONDS
-            }
-        };
-        
-        let job_processor = Arc::new(JobProcessor::new_with_timeout(5, 100, timeout_seconds));
-        let service = Self { db, job_processor };
-        
-        // Start background task to monitor for stuck jobs
-        service.start_timeout_monitor();
-        
-        service
+        }
+    });
+}
+
+// Re-export for backward compatibility
+impl ReconciliationService {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            job_processor: shared_job_processor(),
+        }
     }
-    
-    /// Start background task to periodically check for stuck jobs
-    fn start_timeout_monitor(&self) {
-        let processor = Arc::clone(&self.job_processor);
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60)); // Check every minute
-            loop {
-                interval.tick().await;
-                
-                let stuck_jobs = processor.check_stuck_jobs().await;
-                if !stuck_jobs.is_empty() {
-                    log::warn!("Found {} stuck job(s), forcing timeout", stuck_jobs.len());
-                    for job_id in stuck_jobs {
-                        if let Err(e) = processor.timeout_job(job_id).await {
-                            log::error!("Failed to timeout stuck job {}: {}", job_id, e);
-                        } else {
-                            log::info!("Successfully timed out stuck job {}", job_id);
-                        }
-                    }
-                }
-            }
-        });
+
+    pub fn new_with_ws(db: Database, _ws_server: actix::Addr<crate::websocket::WsServer>) -> Self {
+        Self::new(db)
     }
 
     /// Create a new reconciliation job
@@ -200,17 +205,30 @@ impl ReconciliationService {
         request: CreateReconciliationJobRequest,
     ) -> AppResult<ReconciliationJobStatus> {
         // Delegate to service module
-        service::create_reconciliation_job_impl(&self.db, user_id, request).await
+        service::create_reconciliation_job_impl(&self.db, user_id, request, None).await
+    }
+
+    /// Create a new reconciliation job, recording it in the audit chain in
+    /// the same transaction
+    pub async fn create_reconciliation_job_audited(
+        &self,
+        audit: &crate::services::audit::AuditContext,
+        user_id: Uuid,
+        request: CreateReconciliationJobRequest,
+    ) -> AppResult<ReconciliationJobStatus> {
+        service::create_reconciliation_job_impl(&self.db, user_id, request, Some(audit)).await
     }
 }
 
 // Forward specific service methods to service module (avoiding duplicates)
 // Note: get_active_jobs and get_queued_jobs are defined as methods on ReconciliationService,
 // not as standalone functions, so they're not re-exported here
+pub use service::batches::{
+    BatchExecutionMode, BatchJobSummary, BatchSummary, BatchTotals, CreateBatchRequest,
+};
 pub use service::{
     batch_approve_matches,
     cancel_reconciliation_job,
-    export_job_results,
     get_reconciliation_results,
     stop_reconciliation_job,
     update_match,
@@ -226,6 +244,10 @@ impl ReconciliationService {
         crate::services::reconciliation::service::get_queued_jobs(self).await
     }
 
+    pub async fn get_dead_letter_jobs(&self) -> AppResult<Vec<crate::models::ReconciliationQueueEntry>> {
+        crate::services::reconciliation::service::get_dead_letter_jobs(self).await
+    }
+
     pub async fn get_reconciliation_progress(
         &self,
         job_id: Uuid,
@@ -272,7 +294,16 @@ impl ReconciliationService {
     }
 
     pub async fn delete_reconciliation_job(&self, job_id: Uuid) -> AppResult<()> {
-        crate::services::reconciliation::service::jobs::delete_reconciliation_job(self, job_id).await
+        crate::services::reconciliation::service::jobs::delete_reconciliation_job(self, job_id, None).await
+    }
+
+    /// Delete a job, recording it in the audit chain in the same transaction
+    pub async fn delete_reconciliation_job_audited(
+        &self,
+        audit: &crate::services::audit::AuditContext,
+        job_id: Uuid,
+    ) -> AppResult<()> {
+        crate::services::reconciliation::service::jobs::delete_reconciliation_job(self, job_id, Some(audit)).await
     }
 
     pub async fn start_reconciliation_job(&self, job_id: Uuid) -> AppResult<()> {
@@ -283,6 +314,31 @@ impl ReconciliationService {
         service::stop_reconciliation_job(self, job_id).await
     }
 
+    pub async fn create_batch(
+        &self,
+        user_id: Uuid,
+        request: CreateBatchRequest,
+    ) -> AppResult<BatchSummary> {
+        service::batches::create_batch(&self.db, user_id, request).await
+    }
+
+    pub fn get_batch(&self, batch_id: Uuid) -> AppResult<BatchSummary> {
+        service::batches::get_batch(&self.db, batch_id)
+    }
+
+    pub fn list_batches(
+        &self,
+        project_id: Uuid,
+        page: i64,
+        per_page: i64,
+    ) -> AppResult<(Vec<BatchSummary>, i64)> {
+        service::batches::list_batches(&self.db, project_id, page, per_page)
+    }
+
+    pub async fn process_batch(&self, batch_id: Uuid) -> AppResult<BatchSummary> {
+        service::batches::process_batch(self, batch_id).await
+    }
+
     pub async fn get_reconciliation_results(
         &self,
         job_id: Uuid,
@@ -295,28 +351,19 @@ impl ReconciliationService {
 
     pub async fn batch_approve_matches(
         &self,
-        user_id: Uuid,
+        audit: &crate::services::audit::AuditContext,
         resolves: Vec<service::MatchResolve>,
     ) -> AppResult<service::BatchApprovalResult> {
-        service::batch_approve_matches(self, user_id, resolves).await
+        service::batch_approve_matches(self, audit, resolves).await
     }
 
     pub async fn update_match(
         &self,
+        audit: &crate::services::audit::AuditContext,
         user_id: Uuid,
         match_id: Uuid,
-        status: Option<&str>,
-        confidence_score: Option<f64>,
-        reviewed_by: Option<&str>,
+        update: service::MatchUpdate,
     ) -> AppResult<service::UpdatedMatch> {
-        service::update_match(
-            self,
-            user_id,
-            match_id,
-            status,
-            confidence_score,
-            reviewed_by,
-        )
-        .await
+        service::update_match(self, audit, user_id, match_id, update).await
     }
 }
@@ -0,0 +1,112 @@
+//! Text normalisation applied to field values before rules compare them
+//!
+//! Normalisation is configured per rule (`RuleOptions::normalization`) and
+//! runs before any algorithm, so "ACME Ltd." and "Acme" can compare equal
+//! under an exact rule as well as a fuzzy one.
+
+use super::types::TextNormalization;
+
+/// Company-form suffixes removed by `strip_legal_suffixes`, compared without dots
+const LEGAL_SUFFIXES: &[&str] = &[
+    "ab", "ag", "bv", "co", "company", "corp", "corporation", "gmbh", "inc", "incorporated",
+    "kg", "limited", "llc", "llp", "ltd", "nv", "oy", "plc", "pte", "pty", "pvt", "sa", "sarl",
+    "sas", "spa", "srl",
+];
+
+impl TextNormalization {
+    pub fn is_noop(&self) -> bool {
+        !(self.case_insensitive
+            || self.strip_punctuation
+            || self.collapse_whitespace
+            || self.strip_legal_suffixes)
+    }
+
+    /// Normalise `value` according to the enabled options
+    pub fn apply(&self, value: &str) -> String {
+        if self.is_noop() {
+            return value.to_string();
+        }
+
+        let mut text = if self.case_insensitive {
+            value.to_lowercase()
+        } else {
+            value.to_string()
+        };
+        if self.strip_punctuation {
+            // Punctuation separates words ("Smith-Jones" -> "Smith Jones"), except
+            // dots and apostrophes inside abbreviations and names ("L.L.C.", "O'Brien")
+            text = text
+                .chars()
+                .filter(|c| *c != '.' && *c != '\'')
+                .map(|c| if c.is_alphanumeric() || c.is_whitespace() { c } else { ' ' })
+                .collect();
+        }
+        if self.strip_legal_suffixes {
+            text = strip_legal_suffixes(&text);
+        }
+        if self.collapse_whitespace || self.strip_punctuation || self.strip_legal_suffixes {
+            text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        text
+    }
+}
+
+/// Remove trailing company-form words, keeping at least one word
+fn strip_legal_suffixes(text: &str) -> String {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    while words.len() > 1 {
+        let Some(last) = words.last() else { break };
+        let bare: String = last
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        if LEGAL_SUFFIXES.contains(&bare.as_str()) {
+            words.pop();
+            // "Acme Ltd," leaves a dangling separator on the name
+            if let Some(name) = words.last_mut() {
+                *name = name.trim_end_matches([',', '&']);
+            }
+        } else {
+            break;
+        }
+    }
+    words.retain(|w| !w.is_empty());
+    words.join(" ")
+}
+
+/// Lowercase alphanumeric tokens of a value, as used by token-based algorithms
+pub fn tokenize(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_case_punctuation_and_legal_suffixes() {
+        let normalization = TextNormalization {
+            case_insensitive: true,
+            strip_punctuation: true,
+            collapse_whitespace: true,
+            strip_legal_suffixes: true,
+        };
+        assert_eq!(normalization.apply("  ACME   Widgets, Ltd. "), "acme widgets");
+        assert_eq!(normalization.apply("Globex Holdings Pty Ltd"), "globex holdings");
+        assert_eq!(normalization.apply("Smith-Jones L.L.C."), "smith jones");
+        // A name that is only a suffix is kept
+        assert_eq!(normalization.apply("Inc."), "inc");
+    }
+
+    #[test]
+    fn defaults_leave_value_untouched() {
+        let normalization = TextNormalization::default();
+        assert!(normalization.is_noop());
+        assert_eq!(normalization.apply(" Acme Ltd "), " Acme Ltd ");
+    }
+}
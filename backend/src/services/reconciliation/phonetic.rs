@@ -0,0 +1,671 @@
+//! Phonetic encodings for name matching
+//!
+//! Payee names are often typed by hand on one side and by a bank on the
+//! other ("Smyth & Sons" vs "SMITH AND SONS"). Soundex and Double Metaphone
+//! reduce words to codes for how they sound so such spellings compare equal.
+
+/// American Soundex code of a single word, e.g. "Robert" -> "R163"
+///
+/// Returns an empty string when the word has no ASCII letters.
+pub fn soundex(word: &str) -> String {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'B' | 'F' | 'P' | 'V' => Some('1'),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+            'D' | 'T' => Some('3'),
+            'L' => Some('4'),
+            'M' | 'N' => Some('5'),
+            'R' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+
+    let mut code = String::with_capacity(4);
+    code.push(first);
+    let mut last = digit(first);
+    for &c in &letters[1..] {
+        match c {
+            // H and W do not separate letters with the same code
+            'H' | 'W' => continue,
+            _ => {
+                let current = digit(c);
+                if let Some(d) = current {
+                    if current != last {
+                        code.push(d);
+                        if code.len() == 4 {
+                            break;
+                        }
+                    }
+                }
+                last = current;
+            }
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    code
+}
+
+/// Maximum length of Double Metaphone codes
+const METAPHONE_MAX_LENGTH: usize = 4;
+
+/// Double Metaphone primary and alternate codes of a single word
+///
+/// Follows Lawrence Philips' algorithm; the alternate code captures the
+/// common non-English pronunciation (e.g. "Schmidt" -> ("XMT", "SMT")).
+pub fn double_metaphone(word: &str) -> (String, String) {
+    let chars: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(|c| c.to_uppercase())
+        .collect();
+    if chars.is_empty() {
+        return (String::new(), String::new());
+    }
+    let mut encoder = Metaphone::new(chars);
+    encoder.encode();
+    (encoder.primary, encoder.alternate)
+}
+
+struct Metaphone {
+    chars: Vec<char>,
+    len: i64,
+    slavo_germanic: bool,
+    primary: String,
+    alternate: String,
+}
+
+impl Metaphone {
+    fn new(chars: Vec<char>) -> Self {
+        let text: String = chars.iter().collect();
+        let slavo_germanic =
+            text.contains('W') || text.contains('K') || text.contains("CZ") || text.contains("WITZ");
+        Self {
+            len: chars.len() as i64,
+            chars,
+            slavo_germanic,
+            primary: String::new(),
+            alternate: String::new(),
+        }
+    }
+
+    fn at(&self, index: i64) -> char {
+        if index < 0 || index >= self.len {
+            '\0'
+        } else {
+            self.chars[index as usize]
+        }
+    }
+
+    /// Whether the `length` characters at `start` equal one of `options`
+    fn contains(&self, start: i64, length: i64, options: &[&str]) -> bool {
+        if start < 0 || start + length > self.len {
+            return false;
+        }
+        let slice: String = self.chars[start as usize..(start + length) as usize].iter().collect();
+        options.iter().any(|o| *o == slice)
+    }
+
+    fn is_vowel(c: char) -> bool {
+        matches!(c, 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+
+    fn vowel_at(&self, index: i64) -> bool {
+        Self::is_vowel(self.at(index))
+    }
+
+    fn append_primary(&mut self, code: &str) {
+        let room = METAPHONE_MAX_LENGTH.saturating_sub(self.primary.len());
+        self.primary.extend(code.chars().take(room));
+    }
+
+    fn append_alternate(&mut self, code: &str) {
+        let room = METAPHONE_MAX_LENGTH.saturating_sub(self.alternate.len());
+        self.alternate.extend(code.chars().take(room));
+    }
+
+    fn append(&mut self, code: &str) {
+        self.append_both(code, code);
+    }
+
+    fn append_both(&mut self, primary: &str, alternate: &str) {
+        self.append_primary(primary);
+        self.append_alternate(alternate);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.primary.len() >= METAPHONE_MAX_LENGTH && self.alternate.len() >= METAPHONE_MAX_LENGTH
+    }
+
+    /// Skip a doubled letter
+    fn next_unless_double(&self, index: i64, letter: char) -> i64 {
+        if self.at(index + 1) == letter {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn encode(&mut self) {
+        let mut index: i64 = 0;
+        if self.contains(0, 2, &["GN", "KN", "PN", "WR", "PS"]) {
+            index = 1;
+        }
+
+        while !self.is_complete() && index < self.len {
+            index = match self.at(index) {
+                'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                    if index == 0 {
+                        self.append("A");
+                    }
+                    index + 1
+                }
+                'B' => {
+                    self.append("P");
+                    self.next_unless_double(index, 'B')
+                }
+                'Ç' => {
+                    self.append("S");
+                    index + 1
+                }
+                'C' => self.handle_c(index),
+                'D' => self.handle_d(index),
+                'F' => {
+                    self.append("F");
+                    self.next_unless_double(index, 'F')
+                }
+                'G' => self.handle_g(index),
+                'H' => self.handle_h(index),
+                'J' => self.handle_j(index),
+                'K' => {
+                    self.append("K");
+                    self.next_unless_double(index, 'K')
+                }
+                'L' => self.handle_l(index),
+                'M' => {
+                    self.append("M");
+                    if self.condition_m0(index) {
+                        index + 2
+                    } else {
+                        index + 1
+                    }
+                }
+                'N' => {
+                    self.append("N");
+                    self.next_unless_double(index, 'N')
+                }
+                'Ñ' => {
+                    self.append("N");
+                    index + 1
+                }
+                'P' => self.handle_p(index),
+                'Q' => {
+                    self.append("K");
+                    self.next_unless_double(index, 'Q')
+                }
+                'R' => self.handle_r(index),
+                'S' => self.handle_s(index),
+                'T' => self.handle_t(index),
+                'V' => {
+                    self.append("F");
+                    self.next_unless_double(index, 'V')
+                }
+                'W' => self.handle_w(index),
+                'X' => self.handle_x(index),
+                'Z' => self.handle_z(index),
+                _ => index + 1,
+            };
+        }
+    }
+
+    fn handle_c(&mut self, index: i64) -> i64 {
+        if self.condition_c0(index) {
+            self.append("K");
+            index + 2
+        } else if index == 0 && self.contains(index, 6, &["CAESAR"]) {
+            self.append("S");
+            index + 2
+        } else if self.contains(index, 2, &["CH"]) {
+            self.handle_ch(index)
+        } else if self.contains(index, 2, &["CZ"]) && !self.contains(index - 2, 4, &["WICZ"]) {
+            self.append_both("S", "X");
+            index + 2
+        } else if self.contains(index + 1, 3, &["CIA"]) {
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["CC"]) && !(index == 1 && self.at(0) == 'M') {
+            self.handle_cc(index)
+        } else if self.contains(index, 2, &["CK", "CG", "CQ"]) {
+            self.append("K");
+            index + 2
+        } else if self.contains(index, 2, &["CI", "CE", "CY"]) {
+            if self.contains(index, 3, &["CIO", "CIE", "CIA"]) {
+                self.append_both("S", "X");
+            } else {
+                self.append("S");
+            }
+            index + 2
+        } else {
+            self.append("K");
+            if self.contains(index + 1, 1, &["C", "K", "Q"]) && !self.contains(index + 1, 2, &["CE", "CI"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn condition_c0(&self, index: i64) -> bool {
+        if self.contains(index, 4, &["CHIA"]) {
+            return true;
+        }
+        if index <= 1 || self.vowel_at(index - 2) || !self.contains(index - 1, 3, &["ACH"]) {
+            return false;
+        }
+        let c = self.at(index + 2);
+        (c != 'I' && c != 'E') || self.contains(index - 2, 6, &["BACHER", "MACHER"])
+    }
+
+    fn handle_ch(&mut self, index: i64) -> i64 {
+        if index > 0 && self.contains(index, 4, &["CHAE"]) {
+            self.append_both("K", "X");
+        } else if self.condition_ch0(index) || self.condition_ch1(index) {
+            self.append("K");
+        } else if index > 0 {
+            if self.contains(0, 2, &["MC"]) {
+                self.append("K");
+            } else {
+                self.append_both("X", "K");
+            }
+        } else {
+            self.append("X");
+        }
+        index + 2
+    }
+
+    fn condition_ch0(&self, index: i64) -> bool {
+        index == 0
+            && (self.contains(index + 1, 5, &["HARAC", "HARIS"])
+                || self.contains(index + 1, 3, &["HOR", "HYM", "HIA", "HEM"]))
+            && !self.contains(0, 5, &["CHORE"])
+    }
+
+    fn condition_ch1(&self, index: i64) -> bool {
+        self.contains(0, 3, &["SCH"])
+            || self.contains(index - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"])
+            || self.contains(index + 2, 1, &["T", "S"])
+            || ((self.contains(index - 1, 1, &["A", "O", "U", "E"]) || index == 0)
+                && (self.contains(index + 2, 1, &["L", "R", "N", "M", "B", "H", "F", "V", "W"])
+                    || index + 1 == self.len - 1))
+    }
+
+    fn handle_cc(&mut self, index: i64) -> i64 {
+        if self.contains(index + 2, 1, &["I", "E", "H"]) && !self.contains(index + 2, 2, &["HU"]) {
+            if (index == 1 && self.at(index - 1) == 'A') || self.contains(index - 1, 5, &["UCCEE", "UCCES"]) {
+                self.append("KS");
+            } else {
+                self.append("X");
+            }
+            index + 3
+        } else {
+            self.append("K");
+            index + 2
+        }
+    }
+
+    fn handle_d(&mut self, index: i64) -> i64 {
+        if self.contains(index, 2, &["DG"]) {
+            if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+                self.append("J");
+                index + 3
+            } else {
+                self.append("TK");
+                index + 2
+            }
+        } else if self.contains(index, 2, &["DT", "DD"]) {
+            self.append("T");
+            index + 2
+        } else {
+            self.append("T");
+            index + 1
+        }
+    }
+
+    fn handle_g(&mut self, index: i64) -> i64 {
+        if self.at(index + 1) == 'H' {
+            return self.handle_gh(index);
+        }
+        if self.at(index + 1) == 'N' {
+            if index == 1 && self.vowel_at(0) && !self.slavo_germanic {
+                self.append_both("KN", "N");
+            } else if !self.contains(index + 2, 2, &["EY"]) && self.at(index + 1) != 'Y' && !self.slavo_germanic {
+                self.append_both("N", "KN");
+            } else {
+                self.append("KN");
+            }
+            return index + 2;
+        }
+        if self.contains(index + 1, 2, &["LI"]) && !self.slavo_germanic {
+            self.append_both("KL", "L");
+            return index + 2;
+        }
+        if index == 0
+            && (self.at(index + 1) == 'Y'
+                || self.contains(
+                    index + 1,
+                    2,
+                    &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"],
+                ))
+        {
+            self.append_both("K", "J");
+            return index + 2;
+        }
+        if (self.contains(index + 1, 2, &["ER"]) || self.at(index + 1) == 'Y')
+            && !self.contains(0, 6, &["DANGER", "RANGER", "MANGER"])
+            && !self.contains(index - 1, 1, &["E", "I"])
+            && !self.contains(index - 1, 3, &["RGY", "OGY"])
+        {
+            self.append_both("K", "J");
+            return index + 2;
+        }
+        if self.contains(index + 1, 1, &["E", "I", "Y"]) || self.contains(index - 1, 4, &["AGGI", "OGGI"]) {
+            if self.contains(0, 3, &["SCH"]) || self.contains(index + 1, 2, &["ET"]) {
+                self.append("K");
+            } else if self.contains(index + 1, 3, &["IER"]) {
+                self.append("J");
+            } else {
+                self.append_both("J", "K");
+            }
+            return index + 2;
+        }
+        self.append("K");
+        self.next_unless_double(index, 'G')
+    }
+
+    fn handle_gh(&mut self, index: i64) -> i64 {
+        if index > 0 && !self.vowel_at(index - 1) {
+            self.append("K");
+        } else if index == 0 {
+            if self.at(index + 2) == 'I' {
+                self.append("J");
+            } else {
+                self.append("K");
+            }
+        } else if (index > 1 && self.contains(index - 2, 1, &["B", "H", "D"]))
+            || (index > 2 && self.contains(index - 3, 1, &["B", "H", "D"]))
+            || (index > 3 && self.contains(index - 4, 1, &["B", "H"]))
+        {
+            // Silent, as in "bough" or "daughter"
+        } else if index > 2 && self.at(index - 1) == 'U' && self.contains(index - 3, 1, &["C", "G", "L", "R", "T"]) {
+            self.append("F");
+        } else if index > 0 && self.at(index - 1) != 'I' {
+            self.append("K");
+        }
+        index + 2
+    }
+
+    fn handle_h(&mut self, index: i64) -> i64 {
+        if (index == 0 || self.vowel_at(index - 1)) && self.vowel_at(index + 1) {
+            self.append("H");
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_j(&mut self, index: i64) -> i64 {
+        if self.contains(index, 4, &["JOSE"]) || self.contains(0, 4, &["SAN "]) {
+            if (index == 0 && self.at(index + 4) == ' ') || self.len == 4 || self.contains(0, 4, &["SAN "]) {
+                self.append("H");
+            } else {
+                self.append_both("J", "H");
+            }
+            return index + 1;
+        }
+        if index == 0 {
+            self.append_both("J", "A");
+        } else if self.vowel_at(index - 1)
+            && !self.slavo_germanic
+            && (self.at(index + 1) == 'A' || self.at(index + 1) == 'O')
+        {
+            self.append_both("J", "H");
+        } else if index == self.len - 1 {
+            self.append_primary("J");
+        } else if !self.contains(index + 1, 1, &["L", "T", "K", "S", "N", "M", "B", "Z"])
+            && !self.contains(index - 1, 1, &["S", "K", "L"])
+        {
+            self.append("J");
+        }
+        self.next_unless_double(index, 'J')
+    }
+
+    fn handle_l(&mut self, index: i64) -> i64 {
+        if self.at(index + 1) == 'L' {
+            if self.condition_l0(index) {
+                self.append_primary("L");
+            } else {
+                self.append("L");
+            }
+            index + 2
+        } else {
+            self.append("L");
+            index + 1
+        }
+    }
+
+    fn condition_l0(&self, index: i64) -> bool {
+        if index == self.len - 3 && self.contains(index - 1, 4, &["ILLO", "ILLA", "ALLE"]) {
+            return true;
+        }
+        (self.contains(self.len - 2, 2, &["AS", "OS"]) || self.contains(self.len - 1, 1, &["A", "O"]))
+            && self.contains(index - 1, 4, &["ALLE"])
+    }
+
+    fn condition_m0(&self, index: i64) -> bool {
+        self.at(index + 1) == 'M'
+            || (self.contains(index - 1, 3, &["UMB"])
+                && (index + 1 == self.len - 1 || self.contains(index + 2, 2, &["ER"])))
+    }
+
+    fn handle_p(&mut self, index: i64) -> i64 {
+        if self.at(index + 1) == 'H' {
+            self.append("F");
+            index + 2
+        } else {
+            self.append("P");
+            if self.contains(index + 1, 1, &["P", "B"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_r(&mut self, index: i64) -> i64 {
+        if index == self.len - 1
+            && !self.slavo_germanic
+            && self.contains(index - 2, 2, &["IE"])
+            && !self.contains(index - 4, 2, &["ME", "MA"])
+        {
+            self.append_alternate("R");
+        } else {
+            self.append("R");
+        }
+        self.next_unless_double(index, 'R')
+    }
+
+    fn handle_s(&mut self, index: i64) -> i64 {
+        if self.contains(index - 1, 3, &["ISL", "YSL"]) {
+            index + 1
+        } else if index == 0 && self.contains(index, 5, &["SUGAR"]) {
+            self.append_both("X", "S");
+            index + 1
+        } else if self.contains(index, 2, &["SH"]) {
+            if self.contains(index + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+                self.append("S");
+            } else {
+                self.append("X");
+            }
+            index + 2
+        } else if self.contains(index, 3, &["SIO", "SIA"]) || self.contains(index, 4, &["SIAN"]) {
+            if self.slavo_germanic {
+                self.append("S");
+            } else {
+                self.append_both("S", "X");
+            }
+            index + 3
+        } else if (index == 0 && self.contains(index + 1, 1, &["M", "N", "L", "W"]))
+            || self.contains(index + 1, 1, &["Z"])
+        {
+            self.append_both("S", "X");
+            self.next_unless_double(index, 'Z')
+        } else if self.contains(index, 2, &["SC"]) {
+            self.handle_sc(index)
+        } else {
+            if index == self.len - 1 && self.contains(index - 2, 2, &["AI", "OI"]) {
+                self.append_alternate("S");
+            } else {
+                self.append("S");
+            }
+            if self.contains(index + 1, 1, &["S", "Z"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_sc(&mut self, index: i64) -> i64 {
+        if self.at(index + 2) == 'H' {
+            if self.contains(index + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                if self.contains(index + 3, 2, &["ER", "EN"]) {
+                    self.append_both("X", "SK");
+                } else {
+                    self.append("SK");
+                }
+            } else if index == 0 && !self.vowel_at(3) && self.at(3) != 'W' {
+                self.append_both("X", "S");
+            } else {
+                self.append("X");
+            }
+        } else if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+            self.append("S");
+        } else {
+            self.append("SK");
+        }
+        index + 3
+    }
+
+    fn handle_t(&mut self, index: i64) -> i64 {
+        if self.contains(index, 4, &["TION"]) || self.contains(index, 3, &["TIA", "TCH"]) {
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["TH"]) || self.contains(index, 3, &["TTH"]) {
+            if self.contains(index + 2, 2, &["OM", "AM"]) || self.contains(0, 3, &["SCH"]) {
+                self.append("T");
+            } else {
+                self.append_both("0", "T");
+            }
+            index + 2
+        } else {
+            self.append("T");
+            if self.contains(index + 1, 1, &["T", "D"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_w(&mut self, index: i64) -> i64 {
+        if self.contains(index, 2, &["WR"]) {
+            self.append("R");
+            return index + 2;
+        }
+        if index == 0 && (self.vowel_at(index + 1) || self.contains(index, 2, &["WH"])) {
+            if self.vowel_at(index + 1) {
+                self.append_both("A", "F");
+            } else {
+                self.append("A");
+            }
+            index + 1
+        } else if (index == self.len - 1 && self.vowel_at(index - 1))
+            || self.contains(index - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+            || self.contains(0, 3, &["SCH"])
+        {
+            self.append_alternate("F");
+            index + 1
+        } else if self.contains(index, 4, &["WICZ", "WITZ"]) {
+            self.append_both("TS", "FX");
+            index + 4
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_x(&mut self, index: i64) -> i64 {
+        if index == 0 {
+            self.append("S");
+            return index + 1;
+        }
+        let silent_french = index == self.len - 1
+            && (self.contains(index - 3, 3, &["IAU", "EAU"]) || self.contains(index - 2, 2, &["AU", "OU"]));
+        if !silent_french {
+            self.append("KS");
+        }
+        if self.contains(index + 1, 1, &["C", "X"]) {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_z(&mut self, index: i64) -> i64 {
+        if self.at(index + 1) == 'H' {
+            self.append("J");
+            return index + 2;
+        }
+        if self.contains(index + 1, 2, &["ZO", "ZI", "ZA"])
+            || (self.slavo_germanic && index > 0 && self.at(index - 1) != 'T')
+        {
+            self.append_both("S", "TS");
+        } else {
+            self.append("S");
+        }
+        self.next_unless_double(index, 'Z')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soundex_codes() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Lee"), "L000");
+        assert_eq!(soundex("123"), "");
+    }
+
+    #[test]
+    fn double_metaphone_codes() {
+        assert_eq!(double_metaphone("Smith"), ("SM0".to_string(), "XMT".to_string()));
+        assert_eq!(double_metaphone("Schmidt"), ("XMT".to_string(), "SMT".to_string()));
+        assert_eq!(double_metaphone("Thomas").0, "TMS");
+        assert_eq!(double_metaphone("Knight").0, "NT");
+        assert_eq!(double_metaphone("Philips").0, "FLPS");
+    }
+}
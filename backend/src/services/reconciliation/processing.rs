@@ -3,6 +3,7 @@
 //! This module contains the core processing logic for reconciliation jobs
 //! including chunk processing, result saving, and batch operations.
 
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -13,16 +14,52 @@ use crate::models::schema::reconciliation_results;
 use crate::models::{DataSource, NewReconciliationResult, ReconciliationRecord as DbReconciliationRecord};
 use diesel::RunQueryDsl;
 
+use super::aggregate::{find_aggregate_matches, AggregateConfig};
+use super::assignment::{assign, CandidatePair};
+use super::blocking::{BlockingStats, CandidateIndex};
 use super::job_management::{JobProgress, JobStatus};
-use super::matching::{ExactMatchingAlgorithm, FuzzyMatchingAlgorithm, MatchingAlgorithm};
-use super::types::{FuzzyAlgorithmType, MatchingResult, MatchingRuleType, ReconciliationRecord};
+use super::scoring::{RuleScore, ScoringEngine};
+use super::types::{MatchingResult, ReconciliationRecord};
 use super::processing_config::{ChunkedProcessingConfig, ChunkProcessingConfig};
 use crate::models::ReconciliationResult as ReconciliationResultType;
 
+/// Source records read from Postgres per query
+pub const RECORD_PAGE_SIZE: i64 = 10_000;
+/// Result rows inserted per statement
+pub const RESULT_WRITE_BATCH_SIZE: usize = 1_000;
+/// Source B records a job is matched against; source B is held in memory
+/// for blocking, so a job over a larger source fails before it starts
+pub const MAX_JOB_SOURCE_B_RECORDS: usize = 1_000_000;
+
+/// Counts of a finished reconciliation run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessingSummary {
+    pub total_records: usize,
+    pub matched_records: usize,
+    pub unmatched_records: usize,
+    pub results_written: usize,
+}
+
+/// How far a run got: every record of source A up to `after_record_id` has
+/// its result rows written
+///
+/// Only runs that write results page by page (best match without aggregation)
+/// produce checkpoints; one-to-one assignment and aggregation need the whole
+/// of source A and start over.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessingCheckpoint {
+    pub after_record_id: Uuid,
+    pub processed_records: usize,
+    pub matched_records: usize,
+    pub results_written: usize,
+}
+
 /// Process reconciliation job in chunks with timeout protection
+///
+/// Result rows are written to `reconciliation_results` as they are produced.
 pub async fn process_data_sources_chunked(
     config: ChunkedProcessingConfig,
-) -> AppResult<Vec<ReconciliationResultType>> {
+) -> AppResult<ProcessingSummary> {
     // Wrap processing in timeout to prevent stuck jobs
     let timeout_duration = std::time::Duration::from_secs(7200); // 2 hours default
     let job_id = config.job_id; // Extract before move
@@ -39,17 +76,48 @@ pub async fn process_data_sources_chunked(
 }
 
 /// Internal processing function (without timeout wrapper)
+///
+/// Source A is streamed from Postgres a page at a time and result rows are
+/// written in batches as each page completes, so memory does not grow with
+/// source A. Source B is held as match records because blocking needs random
+/// access to it, which caps it at `MAX_JOB_SOURCE_B_RECORDS`. One-to-one modes keep only compact candidate pairs for the
+/// global assignment and rescore the chosen pairs in a second pass;
+/// aggregation holds back unmatched rows until split matching has run.
+///
+/// Runs that write results page by page report a `ProcessingCheckpoint` after
+/// each page and can resume from one, discarding rows written after it.
 async fn process_data_sources_chunked_internal(
     config: ChunkedProcessingConfig,
-) -> AppResult<Vec<ReconciliationResultType>> {
-    let mut results = Vec::new();
-
-    // Load actual records from data sources
-    let records_a = load_records_from_data_source(&config.db, &config.source_a).await?;
-    let records_b = load_records_from_data_source(&config.db, &config.source_b).await?;
+) -> AppResult<ProcessingSummary> {
+    let db = &config.db;
+    let job_id = config.job_id;
+    let total_records = count_records_in_data_source(db, &config.source_a)?;
+    let one_to_one = config.assignment.is_one_to_one();
+    let checkpointed = !one_to_one && config.aggregation.is_none();
+    let resume_from = config.resume_from.clone().filter(|_| checkpointed);
+    clear_results(db, job_id, resume_from.as_ref().map(|c| c.after_record_id))?;
+    if let Some(checkpoint) = &resume_from {
+        log::info!(
+            "Job {}: resuming after record {} ({} records already processed)",
+            job_id,
+            checkpoint.after_record_id,
+            checkpoint.processed_records
+        );
+    }
 
-    let total_records = records_a.len(); // We iterate through records_a
-    let total_chunks = total_records.div_ceil(config.chunk_size);
+    // Index source B once so each A record is only scored against the B
+    // records that share a block with it
+    let source_b_records = count_records_in_data_source(db, &config.source_b)?;
+    if source_b_records > MAX_JOB_SOURCE_B_RECORDS {
+        return Err(AppError::Validation(format!(
+            "Source B of job {} has {} records; at most {} can be matched against, \
+             so split the source and reconcile the parts in a batch",
+            job_id, source_b_records, MAX_JOB_SOURCE_B_RECORDS
+        )));
+    }
+    let records_b = load_match_records_from_data_source(db, &config.source_b).await?;
+    let candidate_index = CandidateIndex::build(&config.blocking, &records_b);
+    let mut blocking_stats = BlockingStats::new(total_records, records_b.len());
 
     // Update total records in job status
     {
@@ -57,78 +125,180 @@ async fn process_data_sources_chunked_internal(
         status_guard.total_records = Some(total_records as i32);
     }
 
-    let exact_algorithm = ExactMatchingAlgorithm;
-    let fuzzy_algorithm =
-        FuzzyMatchingAlgorithm::new(config.confidence_threshold, FuzzyAlgorithmType::Levenshtein);
+    let mut scoring_engine = ScoringEngine::new(&config.matching_rules);
+    if scoring_engine.needs_corpus() {
+        update_job_status(&config.status, "processing", 0, "Building token statistics").await;
+        let mut fit = scoring_engine.start_corpus();
+        let mut pages = RecordPages::new(&config.source_a);
+        while let Some(page) = pages.next_page(db).await? {
+            for record in &page {
+                scoring_engine.add_to_corpus(&mut fit, &convert_db_record_to_service_record(record));
+            }
+        }
+        for record in &records_b {
+            scoring_engine.add_to_corpus(&mut fit, record);
+        }
+        scoring_engine.finish_corpus(fit);
+    }
 
-    for chunk_index in 0..total_chunks {
-        let start_record = chunk_index * config.chunk_size;
-        let end_record = ((chunk_index + 1) * config.chunk_size).min(total_records);
-        
-        let records_a_chunk = &records_a[start_record..end_record];
+    let context = Arc::new(MatchContext {
+        records_b,
+        candidate_index,
+        scoring_engine,
+    });
+    let chunk_template = ChunkProcessingConfig {
+        job_id,
+        confidence_threshold: config.confidence_threshold,
+        assignment: config.assignment,
+        start_record: 0,
+        end_record: 0,
+    };
+    let mut progress = ScoringProgress::new(total_records);
+    let mut sink = ResultSink::new(job_id, config.aggregation.is_some());
+    if let Some(checkpoint) = &resume_from {
+        progress.processed_records = checkpoint.processed_records;
+        sink.matched_records = checkpoint.matched_records;
+        sink.writer.written = checkpoint.results_written;
+    }
+    // One-to-one modes: A ids in scoring (= id) order and their candidates
+    let mut a_ids: Vec<Uuid> = Vec::new();
+    let mut pairs: Vec<CandidatePair> = Vec::new();
 
-        // Update current phase
-        let phase = format!("Processing chunk {}/{}", chunk_index + 1, total_chunks);
-        update_job_status(
+    let mut pages = RecordPages::new(&config.source_a);
+    pages.after = resume_from.as_ref().map(|c| c.after_record_id);
+    while let Some(page) = pages.next_page(db).await? {
+        let inputs = Arc::new(ChunkInputs {
+            records_a: page,
+            context: Arc::clone(&context),
+        });
+        let candidates_by_a = score_chunks(
+            Arc::clone(&inputs),
+            &chunk_template,
+            config.chunk_size,
+            config.parallelism,
+            &mut blocking_stats,
+            &mut progress,
             &config.status,
-            "processing",
-            ((chunk_index as f64 / total_chunks as f64) * 80.0) as i32,
-            &phase,
-        )
-        .await;
-
-        // Process chunk
-        let chunk_config = ChunkProcessingConfig {
-            source_a: config.source_a.clone(),
-            source_b: config.source_b.clone(),
-            matching_rules: config.matching_rules.clone(),
-            job_id: config.job_id,
-            confidence_threshold: config.confidence_threshold,
-            start_record,
-            end_record,
-        };
-        let chunk_results = process_chunk(
-            chunk_config,
-            records_a_chunk,
-            &records_b,
-            &exact_algorithm,
-            &fuzzy_algorithm,
         )
         .await?;
 
-        results.extend(chunk_results);
+        if one_to_one {
+            for (record_a, candidates) in inputs.records_a.iter().zip(&candidates_by_a) {
+                let a = a_ids.len();
+                a_ids.push(record_a.id);
+                pairs.extend(candidates.iter().map(|c| CandidatePair {
+                    a,
+                    b: c.b,
+                    score: c.result.confidence_score,
+                }));
+            }
+        } else {
+            for (record_a, candidates) in inputs.records_a.iter().zip(candidates_by_a) {
+                let row = result_row(job_id, record_a.id, candidates.into_iter().next(), None);
+                sink.push(db, record_a, row)?;
+            }
+        }
 
-        // Update progress
-        let progress = ((end_record as f64 / total_records as f64) * 80.0) as i32;
-        let processed_records = end_record as i32;
-        
-        let matched_records = results.iter().filter(|r| r.record_b_id.is_some()).count() as i32;
-        let unmatched_records = results.iter().filter(|r| r.record_b_id.is_none()).count() as i32;
+        if let (true, Some(sender), Some(last)) =
+            (checkpointed, &config.checkpoint_sender, inputs.records_a.last())
+        {
+            sink.writer.flush(db)?;
+            // The receiver only persists checkpoints; a closed channel just means no resumption
+            let _ = sender.send(ProcessingCheckpoint {
+                after_record_id: last.id,
+                processed_records: progress.processed_records,
+                matched_records: sink.matched_records,
+                results_written: sink.writer.written,
+            });
+        }
+    }
 
-        update_job_progress(
-            &config.status,
-            progress,
-            processed_records,
-            matched_records,
-            unmatched_records,
-        )
-        .await;
+    if one_to_one {
+        update_job_status(&config.status, "processing", 82, "Assigning matches").await;
+        // Optimal assignment is O(n³) per component; keep it off the async workers
+        let (mode, records) = (config.assignment, a_ids.len());
+        let assignment = tokio::task::spawn_blocking(move || assign(mode, records, &pairs))
+            .await
+            .map_err(|e| AppError::Internal(format!("Match assignment task failed: {}", e)))?;
 
-        // Update heartbeat to indicate job is still alive
-        {
-            let mut status_guard = config.status.write().await;
-            status_guard.heartbeat();
+        // Second pass: rescore only the assigned pair of each record for its details
+        update_job_status(&config.status, "processing", 85, "Writing matches").await;
+        let mut pages = RecordPages::new(&config.source_a);
+        while let Some(page) = pages.next_page(db).await? {
+            for record_a in &page {
+                // Records added since the first pass were never scored
+                let Ok(a) = a_ids.binary_search(&record_a.id) else {
+                    continue;
+                };
+                // Point at the winning record rather than its position in the job
+                let contested_by = assignment.contested_by[a].map(|winner| a_ids[winner]);
+                let chosen = assignment.assigned[a].and_then(|b| {
+                    let service_record_a = convert_db_record_to_service_record(record_a);
+                    context
+                        .scoring_engine
+                        .match_pair(&service_record_a, &context.records_b[b], config.confidence_threshold)
+                        .map(|(result, rule_scores)| ScoredCandidate {
+                            b,
+                            result,
+                            rule_scores,
+                        })
+                });
+                let row = result_row(job_id, record_a.id, chosen, contested_by);
+                sink.push(db, record_a, row)?;
+            }
         }
+    }
 
-        // Small delay to prevent overwhelming the system
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    if let Some(aggregation) = &config.aggregation {
+        update_job_status(&config.status, "processing", 88, "Matching split payments").await;
+        let (unmatched_a, mut rows): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut sink.unmatched).into_iter().unzip();
+        // Scoring is done, so source B can usually be taken rather than copied
+        let records_b = match Arc::try_unwrap(context) {
+            Ok(context) => context.records_b,
+            Err(context) => context.records_b.clone(),
+        };
+        let unmatched_b: Vec<ReconciliationRecord> = records_b
+            .into_iter()
+            .filter(|record| !sink.matched_b.contains(&record.id))
+            .collect();
+        sink.matched_records +=
+            apply_aggregate_matches(job_id, aggregation, &unmatched_a, &unmatched_b, &mut rows);
+        for row in rows {
+            sink.writer.push(db, row)?;
+        }
     }
+    sink.writer.flush(db)?;
 
-    Ok(results)
+    let summary = ProcessingSummary {
+        total_records: progress.processed_records,
+        matched_records: sink.matched_records,
+        unmatched_records: progress.processed_records.saturating_sub(sink.matched_records),
+        results_written: sink.writer.written,
+    };
+    update_job_progress(
+        &config.status,
+        90,
+        summary.total_records as i32,
+        summary.matched_records as i32,
+        summary.unmatched_records as i32,
+    )
+    .await;
+
+    log::info!(
+        "Job {}: blocking scored {} of {} pairs ({} comparisons saved, {:.1}% reduction)",
+        job_id,
+        blocking_stats.candidate_pairs,
+        blocking_stats.total_pairs,
+        blocking_stats.comparisons_saved,
+        blocking_stats.reduction_ratio() * 100.0
+    );
+
+    Ok(summary)
 }
 
 /// Convert database reconciliation record to service reconciliation record
-fn convert_db_record_to_service_record(db_record: &DbReconciliationRecord) -> ReconciliationRecord {
+pub(crate) fn convert_db_record_to_service_record(db_record: &DbReconciliationRecord) -> ReconciliationRecord {
     // Extract fields from source_data JSON
     let fields = if let serde_json::Value::Object(map) = &db_record.source_data {
         map.iter()
@@ -155,131 +325,476 @@ fn convert_db_record_to_service_record(db_record: &DbReconciliationRecord) -> Re
     }
 }
 
+/// Maximum candidates kept per A record for one-to-one assignment
+const MAX_CANDIDATES_PER_RECORD: usize = 10;
+
+/// A source B record that cleared the threshold for a source A record
+struct ScoredCandidate {
+    /// Position of the B record in source B
+    b: usize,
+    result: MatchingResult,
+    rule_scores: Vec<RuleScore>,
+}
+
+/// Upper bound on chunks scored at the same time for one job
+pub const MAX_PARALLELISM: usize = 64;
+
+/// Degree of parallelism for a job: the requested value, or one worker per CPU core
+pub fn resolve_parallelism(requested: Option<usize>) -> usize {
+    requested
+        .unwrap_or_else(num_cpus::get)
+        .clamp(1, MAX_PARALLELISM)
+}
+
+/// Source B and the scoring setup, shared by every page and chunk of a job
+struct MatchContext {
+    records_b: Vec<ReconciliationRecord>,
+    candidate_index: CandidateIndex,
+    scoring_engine: ScoringEngine,
+}
+
+/// One page of source A, shared by the chunks it is split into
+struct ChunkInputs {
+    records_a: Vec<DbReconciliationRecord>,
+    context: Arc<MatchContext>,
+}
+
+/// Candidates and blocking counts of one scored chunk
+struct ChunkOutput {
+    candidates: Vec<Vec<ScoredCandidate>>,
+    blocking_stats: BlockingStats,
+}
+
+/// Running counts reported to `JobStatus` while source A is scored
+struct ScoringProgress {
+    total_records: usize,
+    processed_records: usize,
+    matched_records: i32,
+    unmatched_records: i32,
+}
+
+impl ScoringProgress {
+    fn new(total_records: usize) -> Self {
+        Self {
+            total_records,
+            processed_records: 0,
+            matched_records: 0,
+            unmatched_records: 0,
+        }
+    }
+
+    /// Scoring covers 0-80% of a job
+    fn percent(&self) -> i32 {
+        let done = self.processed_records as f64 / self.total_records.max(1) as f64;
+        (done.min(1.0) * 80.0) as i32
+    }
+}
+
+/// Score every chunk of a page of source A, at most `parallelism` chunks at a time.
+///
+/// Chunks run on the blocking thread pool so CPU-bound scoring doesn't stall
+/// the async runtime. Outputs are put back in chunk order, so the candidates
+/// returned (and hence the final matches) don't depend on which chunk
+/// finished first. Progress, blocking stats and the heartbeat are updated as
+/// each chunk completes.
+async fn score_chunks(
+    inputs: Arc<ChunkInputs>,
+    chunk_template: &ChunkProcessingConfig,
+    chunk_size: usize,
+    parallelism: usize,
+    blocking_stats: &mut BlockingStats,
+    progress: &mut ScoringProgress,
+    status: &Arc<RwLock<JobStatus>>,
+) -> AppResult<Vec<Vec<ScoredCandidate>>> {
+    let page_records = inputs.records_a.len();
+    let total_chunks = page_records.div_ceil(chunk_size.max(1));
+    let parallelism = parallelism.max(1);
+
+    let mut outputs: Vec<Option<Vec<Vec<ScoredCandidate>>>> =
+        (0..total_chunks).map(|_| None).collect();
+    let mut in_flight = tokio::task::JoinSet::new();
+    let mut next_chunk = 0;
+    let mut completed_chunks = 0;
+
+    while completed_chunks < total_chunks {
+        while next_chunk < total_chunks && in_flight.len() < parallelism {
+            let chunk_index = next_chunk;
+            let chunk_config = ChunkProcessingConfig {
+                start_record: chunk_index * chunk_size,
+                end_record: ((chunk_index + 1) * chunk_size).min(page_records),
+                ..chunk_template.clone()
+            };
+            let inputs = Arc::clone(&inputs);
+            in_flight.spawn_blocking(move || (chunk_index, process_chunk(&chunk_config, &inputs)));
+            next_chunk += 1;
+        }
+
+        let Some(joined) = in_flight.join_next().await else {
+            break;
+        };
+        let (chunk_index, output) = joined
+            .map_err(|e| AppError::Internal(format!("Chunk scoring task failed: {}", e)))?;
+
+        blocking_stats.absorb(&output.blocking_stats);
+        for candidates in &output.candidates {
+            if candidates.is_empty() {
+                progress.unmatched_records += 1;
+            } else {
+                progress.matched_records += 1;
+            }
+        }
+        progress.processed_records += output.candidates.len();
+        outputs[chunk_index] = Some(output.candidates);
+        completed_chunks += 1;
+
+        // Counts are provisional until assignment has run
+        let percent = progress.percent();
+        let phase = format!(
+            "Scored {} of {} records",
+            progress.processed_records, progress.total_records
+        );
+        update_job_status(status, "processing", percent, &phase).await;
+        update_job_progress(
+            status,
+            percent,
+            progress.processed_records as i32,
+            progress.matched_records,
+            progress.unmatched_records,
+        )
+        .await;
+        {
+            let mut status_guard = status.write().await;
+            status_guard.blocking_stats = Some(blocking_stats.clone());
+            status_guard.heartbeat();
+        }
+    }
+
+    let mut candidates_by_a = Vec::with_capacity(page_records);
+    for (chunk_index, output) in outputs.into_iter().enumerate() {
+        let candidates = output.ok_or_else(|| {
+            AppError::Internal(format!("Chunk {} produced no output", chunk_index))
+        })?;
+        candidates_by_a.extend(candidates);
+    }
+    Ok(candidates_by_a)
+}
+
 /// Process a single chunk of data
-async fn process_chunk(
-    config: ChunkProcessingConfig,
-    records_a_chunk: &[DbReconciliationRecord],
-    records_b: &[DbReconciliationRecord],
-    exact_algorithm: &dyn MatchingAlgorithm,
-    fuzzy_algorithm: &FuzzyMatchingAlgorithm,
-) -> AppResult<Vec<ReconciliationResultType>> {
-    let mut results = Vec::new();
-    let fields_to_match: Vec<String> = config
-        .matching_rules
-        .iter()
-        .map(|r| r.field.clone())
-        .collect();
+///
+/// Each A record is only scored against the candidates the blocking index
+/// yields for it. Returns the passing candidates per A record, best first,
+/// along with the chunk's blocking counts. Best-match assignment only needs
+/// the top candidate; one-to-one modes keep a few fallbacks in case the top
+/// candidate is taken by another record.
+fn process_chunk(config: &ChunkProcessingConfig, inputs: &ChunkInputs) -> ChunkOutput {
+    let keep = if config.assignment.is_one_to_one() {
+        MAX_CANDIDATES_PER_RECORD
+    } else {
+        1
+    };
+    let context = &inputs.context;
+    let records_a_chunk = &inputs.records_a[config.start_record..config.end_record];
+    let mut blocking_stats = BlockingStats::new(0, context.records_b.len());
+    let mut candidates = Vec::with_capacity(records_a_chunk.len());
 
     for record_a in records_a_chunk {
-        let mut best_match: Option<MatchingResult> = None;
         let service_record_a = convert_db_record_to_service_record(record_a);
+        let positions = context.candidate_index.candidates(&service_record_a);
+        blocking_stats.record_candidates(positions.len());
 
-        for record_b in records_b {
-            let service_record_b = convert_db_record_to_service_record(record_b);
-            
-            // Determine which algorithm to use based on rules
-            // For simplicity, we'll prefer exact and fallback to fuzzy
-            // A more robust implementation would iterate through rules
-            let algorithm: &dyn MatchingAlgorithm = if config
-                .matching_rules
-                .iter()
-                .any(|r| matches!(r.rule_type, MatchingRuleType::Exact))
-            {
-                exact_algorithm
-            } else {
-                fuzzy_algorithm
-            };
+        let mut scored: Vec<ScoredCandidate> = positions
+            .iter()
+            .filter_map(|&pos| {
+                context
+                    .scoring_engine
+                    .match_pair(&service_record_a, &context.records_b[pos], config.confidence_threshold)
+                    .map(|(result, rule_scores)| ScoredCandidate {
+                        b: pos,
+                        result,
+                        rule_scores,
+                    })
+            })
+            .collect();
+        // Stable sort keeps the first-seen candidate on ties
+        scored.sort_by(|x, y| y.result.confidence_score.total_cmp(&x.result.confidence_score));
+        scored.truncate(keep);
+
+        candidates.push(scored);
+    }
+
+    ChunkOutput {
+        candidates,
+        blocking_stats,
+    }
+}
+
+/// Result row for a source A record and its chosen candidate, if any
+///
+/// `contested_by` is the A record that won this record's preferred
+/// candidate in a one-to-one assignment.
+fn result_row(
+    job_id: Uuid,
+    record_a_id: Uuid,
+    chosen: Option<ScoredCandidate>,
+    contested_by: Option<Uuid>,
+) -> ReconciliationResultType {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    if let Some(ScoredCandidate {
+        result: matched_result,
+        rule_scores,
+        ..
+    }) = chosen
+    {
+        let conf_bd = BigDecimal::from_str(&matched_result.confidence_score.to_string())
+            .unwrap_or_else(|_| BigDecimal::from(0));
+
+        let mut details = serde_json::json!({
+            "matching_fields": matched_result.matching_fields,
+            "differences": matched_result.differences,
+            "rule_scores": rule_scores
+        });
+        if let Some(winner) = contested_by {
+            details["contested"] = serde_json::Value::Bool(true);
+            details["contested_by"] = serde_json::json!(winner);
+        }
+
+        ReconciliationResultType {
+            id: Uuid::new_v4(),
+            job_id,
+            record_a_id,
+            record_b_id: Some(matched_result.target_record.id),
+            match_type: matched_result.match_type.to_string(),
+            confidence_score: Some(conf_bd),
+            match_details: Some(details),
+            status: Some("matched".to_string()),
+            updated_at: Some(chrono::Utc::now()),
+            notes: None,
+            reviewed_by: None,
+            created_at: chrono::Utc::now(),
+        }
+    } else {
+        // No match found for record_a, or every candidate went to another record
+        ReconciliationResultType {
+            id: Uuid::new_v4(),
+            job_id,
+            record_a_id,
+            record_b_id: None,
+            match_type: "unmatched".to_string(),
+            confidence_score: None,
+            match_details: contested_by.map(|winner| {
+                serde_json::json!({
+                    "contested": true,
+                    "contested_by": winner
+                })
+            }),
+            status: Some("unmatched".to_string()),
+            updated_at: Some(chrono::Utc::now()),
+            notes: None,
+            reviewed_by: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Link records left unmatched by one-to-one matching through aggregate matches
+///
+/// `rows` holds the unmatched row of each record in `unmatched_a`, in the
+/// same order. Each aggregate match is stored as one row per (A, B) link;
+/// every row carries the whole group under `match_details.aggregate` so the
+/// N:M match can be rebuilt. Returns how many A records were linked.
+fn apply_aggregate_matches(
+    job_id: Uuid,
+    aggregation: &AggregateConfig,
+    unmatched_a: &[ReconciliationRecord],
+    unmatched_b: &[ReconciliationRecord],
+    rows: &mut Vec<ReconciliationResultType>,
+) -> usize {
+    use bigdecimal::BigDecimal;
+    use std::collections::{HashMap, HashSet};
+    use std::str::FromStr;
+
+    let aggregate_matches = find_aggregate_matches(aggregation, unmatched_a, unmatched_b);
+    if aggregate_matches.is_empty() {
+        return 0;
+    }
+
+    let row_of_a: HashMap<Uuid, usize> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, result)| (result.record_a_id, index))
+        .collect();
+    let mut linked_a = HashSet::new();
+    let mut extra_rows = Vec::new();
+
+    for aggregate in &aggregate_matches {
+        let details = serde_json::to_value(aggregate).unwrap_or(serde_json::Value::Null);
+        let conf_bd = BigDecimal::from_str(&aggregate.confidence_score.to_string())
+            .unwrap_or_else(|_| BigDecimal::from(0));
+
+        for record_a_id in &aggregate.source_record_ids {
+            linked_a.insert(*record_a_id);
+            for (link, record_b_id) in aggregate.target_record_ids.iter().enumerate() {
+                // The first link reuses the record's unmatched row, further links get rows of their own
+                let existing = if link == 0 { row_of_a.get(record_a_id) } else { None };
+                let mut match_details = existing
+                    .and_then(|&row| rows[row].match_details.take())
+                    .unwrap_or_else(|| serde_json::json!({}));
+                match_details["aggregate"] = details.clone();
 
-            if let Some(match_result) = super::matching::match_records(
-                &service_record_a,
-                &service_record_b,
-                algorithm,
-                &fields_to_match,
-                config.confidence_threshold,
-            ) {
-                if best_match.as_ref().map_or(true, |current_best| {
-                    match_result.confidence_score > current_best.confidence_score
-                }) {
-                    best_match = Some(match_result);
+                let row = ReconciliationResultType {
+                    id: Uuid::new_v4(),
+                    job_id,
+                    record_a_id: *record_a_id,
+                    record_b_id: Some(*record_b_id),
+                    match_type: aggregate.match_type().to_string(),
+                    confidence_score: Some(conf_bd.clone()),
+                    match_details: Some(match_details),
+                    status: Some("matched".to_string()),
+                    updated_at: Some(chrono::Utc::now()),
+                    notes: None,
+                    reviewed_by: None,
+                    created_at: chrono::Utc::now(),
+                };
+                match existing {
+                    Some(&index) => rows[index] = row,
+                    None => extra_rows.push(row),
                 }
             }
         }
+    }
 
-        if let Some(matched_result) = best_match {
-            use bigdecimal::BigDecimal;
-            use std::str::FromStr;
-            let conf_bd = BigDecimal::from_str(&matched_result.confidence_score.to_string())
-                .unwrap_or_else(|_| BigDecimal::from(0));
-
-            results.push(ReconciliationResultType {
-                id: Uuid::new_v4(),
-                job_id: config.job_id,
-                record_a_id: record_a.id,
-                record_b_id: Some(matched_result.target_record.id),
-                match_type: matched_result.match_type.to_string(),
-                confidence_score: Some(conf_bd),
-                match_details: Some(serde_json::json!({
-                    "matching_fields": matched_result.matching_fields,
-                    "differences": matched_result.differences
-                })),
-                status: Some("matched".to_string()),
-                updated_at: Some(chrono::Utc::now()),
-                notes: None,
-                reviewed_by: None,
-                created_at: chrono::Utc::now(),
-            });
-        } else {
-            // No match found for record_a in the entire source B
-            results.push(ReconciliationResultType {
-                id: Uuid::new_v4(),
-                job_id: config.job_id,
-                record_a_id: record_a.id,
-                record_b_id: None,
-                match_type: "unmatched".to_string(),
-                confidence_score: None,
-                match_details: None,
-                status: Some("unmatched".to_string()),
-                updated_at: Some(chrono::Utc::now()),
-                notes: None,
-                reviewed_by: None,
-                created_at: chrono::Utc::now(),
-            });
+    log::info!(
+        "Job {}: {} aggregate matches linked {} unmatched records",
+        job_id,
+        aggregate_matches.len(),
+        linked_a.len()
+    );
+    rows.extend(extra_rows);
+    linked_a.len()
+}
+
+/// Where finished result rows go
+///
+/// Rows are written through a `ResultWriter`, except unmatched rows when
+/// aggregation is enabled: those wait, with their record, until split
+/// matching has had a chance to link them.
+struct ResultSink {
+    writer: ResultWriter,
+    hold_unmatched: bool,
+    unmatched: Vec<(ReconciliationRecord, ReconciliationResultType)>,
+    matched_b: std::collections::HashSet<Uuid>,
+    matched_records: usize,
+}
+
+impl ResultSink {
+    fn new(job_id: Uuid, hold_unmatched: bool) -> Self {
+        Self {
+            writer: ResultWriter::new(job_id),
+            hold_unmatched,
+            unmatched: Vec::new(),
+            matched_b: std::collections::HashSet::new(),
+            matched_records: 0,
         }
     }
 
-    Ok(results)
+    fn push(
+        &mut self,
+        db: &Database,
+        record_a: &DbReconciliationRecord,
+        row: ReconciliationResultType,
+    ) -> AppResult<()> {
+        if let Some(record_b_id) = row.record_b_id {
+            self.matched_b.insert(record_b_id);
+            self.matched_records += 1;
+        } else if self.hold_unmatched {
+            self.unmatched
+                .push((convert_db_record_to_service_record(record_a), row));
+            return Ok(());
+        }
+        self.writer.push(db, row)
+    }
 }
 
-/// Save reconciliation results to database
-pub async fn save_reconciliation_results(
-    db: &Database,
+/// Buffers result rows and inserts them `RESULT_WRITE_BATCH_SIZE` at a time
+pub struct ResultWriter {
     job_id: Uuid,
-    results: &[ReconciliationResultType],
-) -> AppResult<()> {
-    let mut conn = db.get_connection()?;
+    buffer: Vec<NewReconciliationResult>,
+    pub written: usize,
+}
 
-    for result in results {
-        let new_result = NewReconciliationResult {
+impl ResultWriter {
+    pub fn new(job_id: Uuid) -> Self {
+        Self {
             job_id,
+            buffer: Vec::with_capacity(RESULT_WRITE_BATCH_SIZE),
+            written: 0,
+        }
+    }
+
+    pub fn push(&mut self, db: &Database, result: ReconciliationResultType) -> AppResult<()> {
+        self.buffer.push(NewReconciliationResult {
+            job_id: self.job_id,
             record_a_id: result.record_a_id,
             record_b_id: result.record_b_id,
-            match_type: result.match_type.clone(),
-            confidence_score: result.confidence_score.clone(),
-            match_details: None,
+            match_type: result.match_type,
+            confidence_score: result.confidence_score,
+            match_details: result.match_details,
             status: Some("pending".to_string()),
             notes: None,
             reviewed_by: None,
-        };
+        });
+        if self.buffer.len() >= RESULT_WRITE_BATCH_SIZE {
+            self.flush(db)?;
+        }
+        Ok(())
+    }
 
+    /// Insert any buffered rows
+    pub fn flush(&mut self, db: &Database) -> AppResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut conn = db.get_connection()?;
         diesel::insert_into(reconciliation_results::table)
-            .values(&new_result)
+            .values(&self.buffer)
             .execute(&mut conn)
             .map_err(AppError::Database)?;
+        self.written += self.buffer.len();
+        self.buffer.clear();
+        Ok(())
     }
+}
+
+/// Delete a job's result rows, or only those of records after `after_record_id`
+fn clear_results(db: &Database, job_id: Uuid, after_record_id: Option<Uuid>) -> AppResult<()> {
+    use diesel::ExpressionMethods;
 
+    let mut conn = db.get_connection()?;
+    let mut query = diesel::delete(reconciliation_results::table)
+        .filter(reconciliation_results::job_id.eq(job_id))
+        .into_boxed();
+    if let Some(after) = after_record_id {
+        query = query.filter(reconciliation_results::record_a_id.gt(after));
+    }
+    query.execute(&mut conn).map_err(AppError::Database)?;
     Ok(())
 }
 
+/// Save reconciliation results to database
+pub async fn save_reconciliation_results(
+    db: &Database,
+    job_id: Uuid,
+    results: &[ReconciliationResultType],
+) -> AppResult<()> {
+    let mut writer = ResultWriter::new(job_id);
+    for result in results {
+        writer.push(db, result.clone())?;
+    }
+    writer.flush(db)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,6 +823,121 @@ mod tests {
         assert_eq!(status_guard.matched_records, 600);
         assert_eq!(status_guard.unmatched_records, 150);
     }
+
+    fn db_record(reference: &str, amount: f64) -> DbReconciliationRecord {
+        let now = chrono::Utc::now();
+        DbReconciliationRecord {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            ingestion_job_id: Uuid::nil(),
+            external_id: Some(reference.to_string()),
+            status: "pending".to_string(),
+            amount: Some(amount),
+            transaction_date: None,
+            description: None,
+            source_data: serde_json::json!({ "reference": reference, "amount": amount }),
+            matching_results: serde_json::json!({}),
+            confidence: None,
+            audit_trail: serde_json::json!({}),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn parallel_scoring_keeps_record_order() {
+        use super::super::assignment::AssignmentMode;
+        use super::super::blocking::BlockingConfig;
+        use super::super::types::{MatchingRule, MatchingRuleType, RuleOptions};
+
+        let records_a: Vec<DbReconciliationRecord> = (0..37)
+            .map(|i| db_record(&format!("INV-{:03}", i), f64::from(i)))
+            .collect();
+        // Source B in reverse so positions differ from source A
+        let records_b: Vec<ReconciliationRecord> = (0..37)
+            .rev()
+            .map(|i| convert_db_record_to_service_record(&db_record(&format!("INV-{:03}", i), f64::from(i))))
+            .collect();
+        let rules = vec![MatchingRule {
+            field: "reference".to_string(),
+            rule_type: MatchingRuleType::Fuzzy,
+            weight: 1.0,
+            threshold: 0.5,
+            required: false,
+            options: RuleOptions::default(),
+        }];
+        let inputs = Arc::new(ChunkInputs {
+            records_a,
+            context: Arc::new(MatchContext {
+                candidate_index: CandidateIndex::build(&BlockingConfig::default(), &records_b),
+                records_b,
+                scoring_engine: ScoringEngine::new(&rules),
+            }),
+        });
+        let template = ChunkProcessingConfig {
+            job_id: Uuid::new_v4(),
+            confidence_threshold: 0.5,
+            assignment: AssignmentMode::default(),
+            start_record: 0,
+            end_record: 0,
+        };
+
+        let mut runs = Vec::new();
+        for parallelism in [1, 4] {
+            let status = Arc::new(RwLock::new(JobStatus::new()));
+            let mut stats = BlockingStats::new(37, 37);
+            let mut progress = ScoringProgress::new(37);
+            let candidates = score_chunks(
+                Arc::clone(&inputs),
+                &template,
+                5,
+                parallelism,
+                &mut stats,
+                &mut progress,
+                &status,
+            )
+            .await
+                .unwrap_or_else(|e| panic!("scoring failed: {}", e));
+            assert_eq!(stats.candidate_pairs, 37 * 37);
+            assert_eq!(status.read().await.processed_records, 37);
+            runs.push(
+                candidates
+                    .iter()
+                    .map(|c| c.first().map(|best| best.b))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        assert_eq!(runs[0].len(), 37);
+        assert_eq!(runs[0], runs[1]);
+        // Each A record's best candidate is its own reference in reversed B
+        assert_eq!(runs[0][0], Some(36));
+        assert_eq!(runs[0][36], Some(0));
+    }
+
+    #[test]
+    fn unmatched_row_names_contest_winner() {
+        let winner = Uuid::new_v4();
+        let row = result_row(Uuid::new_v4(), Uuid::new_v4(), None, Some(winner));
+        assert_eq!(row.record_b_id, None);
+        assert_eq!(row.match_type, "unmatched");
+        let details = row.match_details.unwrap_or_else(|| panic!("missing match details"));
+        assert_eq!(details["contested_by"], serde_json::json!(winner));
+
+        let row = result_row(Uuid::new_v4(), Uuid::new_v4(), None, None);
+        assert!(row.match_details.is_none());
+    }
+
+    #[test]
+    fn scoring_progress_caps_at_scoring_share() {
+        let mut progress = ScoringProgress::new(200);
+        progress.processed_records = 50;
+        assert_eq!(progress.percent(), 20);
+        // Records added after the count must not push progress past scoring
+        progress.processed_records = 250;
+        assert_eq!(progress.percent(), 80);
+        assert_eq!(ScoringProgress::new(0).percent(), 0);
+    }
 }
 
 /// Update job status
@@ -358,21 +988,178 @@ pub async fn send_progress(
         unmatched_records: 0,
         current_phase: phase.to_string(),
         estimated_completion: None,
+        blocking_stats: None,
     };
 
     let _ = sender.send(progress_update).await;
 }
 
+/// Data source by id
+pub fn load_data_source(db: &Database, data_source_id: Uuid) -> AppResult<DataSource> {
+    use crate::models::schema::data_sources;
+    use diesel::prelude::*;
+    let mut conn = db.get_connection()?;
+    data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .first::<DataSource>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound(format!("Data source {} not found", data_source_id)))
+}
+
+/// Records of a data source: those ingested by the jobs whose
+/// `source_config` names it, as other sources of the project have their own
+///
+/// A source that no job names, because its records were ingested before jobs
+/// were tagged with their source or by sample onboarding, falls back to the
+/// project's records from untagged jobs, as every source read before.
+fn data_source_records(
+    conn: &mut diesel::PgConnection,
+    data_source: &DataSource,
+) -> AppResult<crate::models::schema::reconciliation_records::BoxedQuery<'static, diesel::pg::Pg>> {
+    use crate::models::schema::{ingestion_jobs, reconciliation_records};
+    use diesel::prelude::*;
+    let project_jobs = || ingestion_jobs::table.filter(ingestion_jobs::project_id.eq(data_source.project_id));
+    let source_id = ingestion_jobs::source_config.retrieve_as_text("data_source_id");
+    let tagged = diesel::select(diesel::dsl::exists(
+        project_jobs().filter(source_id.eq(data_source.id.to_string())),
+    ))
+    .get_result::<bool>(conn)
+    .map_err(AppError::Database)?;
+    let jobs = if tagged {
+        project_jobs()
+            .filter(source_id.eq(data_source.id.to_string()))
+            .select(ingestion_jobs::id)
+            .into_boxed()
+    } else {
+        project_jobs()
+            .filter(source_id.is_null())
+            .select(ingestion_jobs::id)
+            .into_boxed()
+    };
+    Ok(reconciliation_records::table
+        .filter(reconciliation_records::project_id.eq(data_source.project_id))
+        .filter(reconciliation_records::ingestion_job_id.eq_any(jobs))
+        .into_boxed())
+}
+
+/// Number of reconciliation records in a data source
+pub fn count_records_in_data_source(db: &Database, data_source: &DataSource) -> AppResult<usize> {
+    let mut conn = db.get_connection()?;
+    use diesel::prelude::*;
+    let count = data_source_records(&mut conn, data_source)?
+        .count()
+        .get_result::<i64>(&mut conn)
+        .map_err(AppError::Database)?;
+    Ok(usize::try_from(count).unwrap_or(0))
+}
+
+/// Load up to `limit` records of a data source with ids after `after`, in id order
+pub async fn load_record_page(
+    db: &Database,
+    data_source: &DataSource,
+    after: Option<Uuid>,
+    limit: i64,
+) -> AppResult<Vec<DbReconciliationRecord>> {
+    record_page(db, data_source, after, limit)
+}
+
+/// `load_record_page` for callers already on a blocking thread
+pub fn record_page(
+    db: &Database,
+    data_source: &DataSource,
+    after: Option<Uuid>,
+    limit: i64,
+) -> AppResult<Vec<DbReconciliationRecord>> {
+    let mut conn = db.get_connection()?;
+    use crate::models::schema::reconciliation_records::dsl::*;
+    use diesel::prelude::*;
+    let mut query = data_source_records(&mut conn, data_source)?;
+    if let Some(after) = after {
+        query = query.filter(id.gt(after));
+    }
+    let records = query
+        .order(id.asc())
+        .limit(limit)
+        .select(DbReconciliationRecord::as_select())
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    Ok(records)
+}
+
+/// Keyset pagination over the records of a data source, in id order
+struct RecordPages<'a> {
+    data_source: &'a DataSource,
+    after: Option<Uuid>,
+    exhausted: bool,
+}
+
+impl<'a> RecordPages<'a> {
+    fn new(data_source: &'a DataSource) -> Self {
+        Self {
+            data_source,
+            after: None,
+            exhausted: false,
+        }
+    }
+
+    async fn next_page(&mut self, db: &Database) -> AppResult<Option<Vec<DbReconciliationRecord>>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        let page = load_record_page(db, self.data_source, self.after, RECORD_PAGE_SIZE).await?;
+        self.exhausted = (page.len() as i64) < RECORD_PAGE_SIZE;
+        match page.last() {
+            Some(last) => {
+                self.after = Some(last.id);
+                Ok(Some(page))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Load a data source as match records, a page at a time
+///
+/// Only the converted records are kept, not the database rows they came from.
+pub async fn load_match_records_from_data_source(
+    db: &Database,
+    data_source: &DataSource,
+) -> AppResult<Vec<ReconciliationRecord>> {
+    let mut records = Vec::new();
+    let mut pages = RecordPages::new(data_source);
+    while let Some(page) = pages.next_page(db).await? {
+        records.extend(page.iter().map(convert_db_record_to_service_record));
+    }
+    Ok(records)
+}
+
 /// Load reconciliation records from a data source
 pub async fn load_records_from_data_source(
     db: &Database,
     data_source: &DataSource,
+) -> AppResult<Vec<DbReconciliationRecord>> {
+    let mut conn = db.get_connection()?;
+    use diesel::prelude::*;
+    let records = data_source_records(&mut conn, data_source)?
+        .select(DbReconciliationRecord::as_select())
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    Ok(records)
+}
+
+/// Load at most `limit` reconciliation records from a data source, oldest first
+pub async fn load_sample_records_from_data_source(
+    db: &Database,
+    data_source: &DataSource,
+    limit: i64,
 ) -> AppResult<Vec<DbReconciliationRecord>> {
     let mut conn = db.get_connection()?;
     use crate::models::schema::reconciliation_records::dsl::*;
     use diesel::prelude::*;
-    let records = reconciliation_records
-        .filter(project_id.eq(&data_source.project_id))
+    let records = data_source_records(&mut conn, data_source)?
+        .order(created_at.asc())
+        .limit(limit)
         .select(DbReconciliationRecord::as_select())
         .load(&mut conn)
         .map_err(AppError::Database)?;
@@ -8,10 +8,14 @@ use uuid::Uuid;
 
 use crate::database::Database;
 use crate::models::DataSource;
+use super::aggregate::AggregateConfig;
+use super::assignment::AssignmentMode;
+use super::blocking::BlockingConfig;
 use super::job_management::JobStatus;
 use super::types::MatchingRule;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{Sender, UnboundedSender};
 use super::job_management::JobProgress;
+use super::processing::ProcessingCheckpoint;
 
 /// Configuration for chunked processing
 #[derive(Clone)]
@@ -21,20 +25,27 @@ pub struct ChunkedProcessingConfig {
     pub source_a: DataSource,
     pub source_b: DataSource,
     pub matching_rules: Vec<MatchingRule>,
+    pub blocking: BlockingConfig,
+    pub assignment: AssignmentMode,
+    pub aggregation: Option<AggregateConfig>,
     pub confidence_threshold: f64,
     pub chunk_size: usize,
+    /// Chunks scored at the same time, at least 1
+    pub parallelism: usize,
     pub progress_sender: Option<Sender<JobProgress>>,
     pub status: Arc<RwLock<JobStatus>>,
+    /// Checkpoint of an earlier run to continue from
+    pub resume_from: Option<ProcessingCheckpoint>,
+    /// Receives a checkpoint after each page whose results are written
+    pub checkpoint_sender: Option<UnboundedSender<ProcessingCheckpoint>>,
 }
 
 /// Configuration for chunk processing
 #[derive(Debug, Clone)]
 pub struct ChunkProcessingConfig {
-    pub source_a: DataSource,
-    pub source_b: DataSource,
-    pub matching_rules: Vec<MatchingRule>,
     pub job_id: Uuid,
     pub confidence_threshold: f64,
+    pub assignment: AssignmentMode,
     pub start_record: usize,
     pub end_record: usize,
 }
@@ -0,0 +1,631 @@
+//! Durable reconciliation job queue
+//!
+//! Queued and running jobs are tracked in the `reconciliation_job_queue` table
+//! rather than in process memory, so a deploy or crash doesn't lose them and
+//! every backend replica works from the same queue. Workers claim entries with
+//! `SELECT ... FOR UPDATE SKIP LOCKED` and hold them under a lease renewed by
+//! heartbeats; when a worker dies its lease runs out and another worker
+//! claims the entry. Failed runs are retried with exponential backoff until
+//! `max_attempts` is reached, after which the entry is dead-lettered until the
+//! job is started again.
+//!
+//! Runs that write results page by page save a checkpoint after each page,
+//! and the next attempt resumes from it instead of starting over.
+//!
+//! When a job finishes, or fails for good, its creator is emailed if their
+//! notification settings ask for it, and the batches it belongs to move on.
+
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::database::transaction::with_transaction;
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{reconciliation_job_queue, reconciliation_jobs, users};
+use crate::models::{NewReconciliationQueueEntry, ReconciliationQueueEntry};
+use crate::services::email::{EmailService, JobCompletedEmail};
+use crate::services::user::{PreferencesService, PreferencesServiceTrait};
+use crate::services::workflow::{WorkflowEngine, WorkflowEvent};
+
+use super::job_management::JobProcessor;
+use super::processing::ProcessingCheckpoint;
+use super::service::batches::advance_batches;
+use super::service::jobs::execute_reconciliation_job;
+
+/// Runs of a job before it is dead-lettered
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+/// How long a claim stays valid without a heartbeat
+pub const LEASE_DURATION: Duration = Duration::from_secs(120);
+/// How often a worker renews the leases it holds
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// How often an idle worker looks for work
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Delay before the first retry; doubled for each further attempt
+pub const RETRY_BASE_DELAY_SECONDS: i64 = 30;
+/// Upper bound on the delay between retries
+pub const RETRY_MAX_DELAY_SECONDS: i64 = 3600;
+
+/// State of a queue entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueState {
+    /// Waiting to be claimed once `available_at` has passed
+    Queued,
+    /// Claimed by a worker holding a lease
+    Leased,
+    Completed,
+    Cancelled,
+    /// Out of attempts; runs again only when the job is restarted
+    Dead,
+}
+
+impl QueueState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueState::Queued => "queued",
+            QueueState::Leased => "leased",
+            QueueState::Completed => "completed",
+            QueueState::Cancelled => "cancelled",
+            QueueState::Dead => "dead",
+        }
+    }
+}
+
+/// Delay before retrying a run that failed on its `attempt`-th try
+pub fn retry_delay(attempt: i32) -> chrono::Duration {
+    let doublings = attempt.saturating_sub(1).clamp(0, 20) as u32;
+    let seconds = RETRY_BASE_DELAY_SECONDS
+        .saturating_mul(1_i64 << doublings)
+        .min(RETRY_MAX_DELAY_SECONDS);
+    chrono::Duration::seconds(seconds)
+}
+
+fn lease_expiry() -> chrono::DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(LEASE_DURATION.as_secs() as i64)
+}
+
+/// Handle on the job queue for one worker (or for code that only enqueues)
+#[derive(Clone)]
+pub struct JobQueue {
+    db: Database,
+    worker_id: String,
+}
+
+impl JobQueue {
+    pub fn new(db: Database) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "backend".to_string());
+        Self {
+            db,
+            worker_id: format!("{}:{}", host, Uuid::new_v4()),
+        }
+    }
+
+    /// Queue a job to run as soon as a worker is free, marking the job `queued`
+    ///
+    /// A finished, cancelled or dead-lettered entry is reset, with a fresh set
+    /// of attempts. Fails with `Conflict` while a worker holds a live lease.
+    /// The job's status is set in the same transaction, so it can't overwrite
+    /// the status of a run a worker has already claimed.
+    pub async fn enqueue(&self, job_id: Uuid) -> AppResult<ReconciliationQueueEntry> {
+        with_transaction(self.db.get_pool(), |tx| enqueue_in(tx, job_id)).await
+    }
+
+    /// Claim the next runnable entry, if any
+    ///
+    /// Runnable entries are queued ones whose backoff has passed and leased
+    /// ones whose lease expired. An expired entry that has used up its
+    /// attempts is dead-lettered instead of being run again.
+    pub async fn claim(&self) -> AppResult<Option<ReconciliationQueueEntry>> {
+        let worker_id = self.worker_id.clone();
+        let (claimed, dead_lettered) = with_transaction(self.db.get_pool(), move |tx| {
+            let mut dead_lettered = Vec::new();
+            loop {
+                let now = Utc::now();
+                let candidate = reconciliation_job_queue::table
+                    .filter(
+                        reconciliation_job_queue::state
+                            .eq(QueueState::Queued.as_str())
+                            .and(reconciliation_job_queue::available_at.le(now)),
+                    )
+                    .or_filter(
+                        reconciliation_job_queue::state
+                            .eq(QueueState::Leased.as_str())
+                            .and(reconciliation_job_queue::lease_expires_at.lt(now)),
+                    )
+                    .order(reconciliation_job_queue::available_at.asc())
+                    .for_update()
+                    .skip_locked()
+                    .first::<ReconciliationQueueEntry>(tx)
+                    .optional()
+                    .map_err(AppError::Database)?;
+                let Some(entry) = candidate else {
+                    return Ok((None, dead_lettered));
+                };
+
+                let target = reconciliation_job_queue::table.filter(reconciliation_job_queue::id.eq(entry.id));
+                if entry.state == QueueState::Leased.as_str() && entry.attempts >= entry.max_attempts {
+                    log::error!(
+                        "Reconciliation job {} lost its worker on attempt {}; dead-lettering",
+                        entry.job_id,
+                        entry.attempts
+                    );
+                    diesel::update(target)
+                        .set((
+                            reconciliation_job_queue::state.eq(QueueState::Dead.as_str()),
+                            reconciliation_job_queue::leased_by.eq(None::<String>),
+                            reconciliation_job_queue::lease_expires_at.eq(None::<chrono::DateTime<Utc>>),
+                            reconciliation_job_queue::last_error.eq(Some("Worker lease expired".to_string())),
+                            reconciliation_job_queue::updated_at.eq(now),
+                        ))
+                        .execute(tx)
+                        .map_err(AppError::Database)?;
+                    set_job_status(tx, entry.job_id, "failed")?;
+                    dead_lettered.push(entry.job_id);
+                    continue;
+                }
+
+                let claimed = diesel::update(target)
+                    .set((
+                        reconciliation_job_queue::state.eq(QueueState::Leased.as_str()),
+                        reconciliation_job_queue::attempts.eq(reconciliation_job_queue::attempts + 1),
+                        reconciliation_job_queue::leased_by.eq(Some(worker_id.clone())),
+                        reconciliation_job_queue::lease_expires_at.eq(Some(lease_expiry())),
+                        reconciliation_job_queue::heartbeat_at.eq(Some(now)),
+                        reconciliation_job_queue::updated_at.eq(now),
+                    ))
+                    .get_result::<ReconciliationQueueEntry>(tx)
+                    .map_err(AppError::Database)?;
+                return Ok((Some(claimed), dead_lettered));
+            }
+        })
+        .await?;
+        for job_id in dead_lettered {
+            announce_job_finished(&self.db, job_id).await;
+        }
+        Ok(claimed)
+    }
+
+    /// Renew the lease on an entry; `false` when this worker no longer holds it
+    /// (the lease expired and was taken over, or the job was cancelled)
+    pub fn heartbeat(&self, entry_id: Uuid) -> AppResult<bool> {
+        let now = Utc::now();
+        let mut conn = self.db.get_connection()?;
+        let renewed = diesel::update(self.held(entry_id))
+            .set((
+                reconciliation_job_queue::lease_expires_at.eq(Some(lease_expiry())),
+                reconciliation_job_queue::heartbeat_at.eq(Some(now)),
+                reconciliation_job_queue::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(renewed == 1)
+    }
+
+    /// Record how far the current run got
+    pub fn save_checkpoint(&self, entry_id: Uuid, checkpoint: &ProcessingCheckpoint) -> AppResult<()> {
+        let value = serde_json::to_value(checkpoint)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize checkpoint: {}", e)))?;
+        let mut conn = self.db.get_connection()?;
+        diesel::update(self.held(entry_id))
+            .set(reconciliation_job_queue::checkpoint.eq(Some(value)))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// Mark a claimed entry as done
+    pub fn complete(&self, entry_id: Uuid) -> AppResult<()> {
+        let mut conn = self.db.get_connection()?;
+        diesel::update(self.held(entry_id))
+            .set((
+                reconciliation_job_queue::state.eq(QueueState::Completed.as_str()),
+                reconciliation_job_queue::leased_by.eq(None::<String>),
+                reconciliation_job_queue::lease_expires_at.eq(None::<chrono::DateTime<Utc>>),
+                reconciliation_job_queue::checkpoint.eq(None::<serde_json::Value>),
+                reconciliation_job_queue::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// Release a claimed entry after a failed run
+    ///
+    /// The entry is queued again after `retry_delay`, keeping its checkpoint,
+    /// or dead-lettered once it has used `max_attempts`. Returns the new state.
+    pub fn fail(&self, entry: &ReconciliationQueueEntry, error: &str) -> AppResult<QueueState> {
+        let now = Utc::now();
+        let state = if entry.attempts >= entry.max_attempts {
+            QueueState::Dead
+        } else {
+            QueueState::Queued
+        };
+        let mut conn = self.db.get_connection()?;
+        diesel::update(self.held(entry.id))
+            .set((
+                reconciliation_job_queue::state.eq(state.as_str()),
+                reconciliation_job_queue::available_at.eq(now + retry_delay(entry.attempts)),
+                reconciliation_job_queue::leased_by.eq(None::<String>),
+                reconciliation_job_queue::lease_expires_at.eq(None::<chrono::DateTime<Utc>>),
+                reconciliation_job_queue::last_error.eq(Some(error.to_string())),
+                reconciliation_job_queue::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+        Ok(state)
+    }
+
+    /// Take a job out of the queue; a worker running it stops at its next heartbeat.
+    /// Returns `false` when the job was neither queued nor running.
+    pub fn cancel(&self, job_id: Uuid) -> AppResult<bool> {
+        let mut conn = self.db.get_connection()?;
+        let cancelled = diesel::update(
+            reconciliation_job_queue::table
+                .filter(reconciliation_job_queue::job_id.eq(job_id))
+                .filter(reconciliation_job_queue::state.eq_any([
+                    QueueState::Queued.as_str(),
+                    QueueState::Leased.as_str(),
+                ])),
+        )
+        .set((
+            reconciliation_job_queue::state.eq(QueueState::Cancelled.as_str()),
+            reconciliation_job_queue::leased_by.eq(None::<String>),
+            reconciliation_job_queue::lease_expires_at.eq(None::<chrono::DateTime<Utc>>),
+            reconciliation_job_queue::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+        Ok(cancelled > 0)
+    }
+
+    /// Ids of the jobs whose entries are in `state`, oldest first
+    pub fn job_ids(&self, state: QueueState) -> AppResult<Vec<Uuid>> {
+        let mut conn = self.db.get_connection()?;
+        reconciliation_job_queue::table
+            .filter(reconciliation_job_queue::state.eq(state.as_str()))
+            .order(reconciliation_job_queue::created_at.asc())
+            .select(reconciliation_job_queue::job_id)
+            .load::<Uuid>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// Queue entries of dead-lettered jobs, most recent first
+    pub fn dead_letters(&self) -> AppResult<Vec<ReconciliationQueueEntry>> {
+        let mut conn = self.db.get_connection()?;
+        reconciliation_job_queue::table
+            .filter(reconciliation_job_queue::state.eq(QueueState::Dead.as_str()))
+            .order(reconciliation_job_queue::updated_at.desc())
+            .load::<ReconciliationQueueEntry>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    /// An entry, as long as it is leased by this worker
+    fn held(
+        &self,
+        entry_id: Uuid,
+    ) -> diesel::dsl::Filter<
+        diesel::dsl::Filter<
+            diesel::dsl::Filter<reconciliation_job_queue::table, diesel::dsl::Eq<reconciliation_job_queue::id, Uuid>>,
+            diesel::dsl::Eq<reconciliation_job_queue::state, &'static str>,
+        >,
+        diesel::dsl::Eq<reconciliation_job_queue::leased_by, String>,
+    > {
+        reconciliation_job_queue::table
+            .filter(reconciliation_job_queue::id.eq(entry_id))
+            .filter(reconciliation_job_queue::state.eq(QueueState::Leased.as_str()))
+            .filter(reconciliation_job_queue::leased_by.eq(self.worker_id.clone()))
+    }
+}
+
+/// `JobQueue::enqueue` within the caller's transaction, so the job is only
+/// queued if everything else the transaction does commits too
+pub fn enqueue_in(tx: &mut diesel::PgConnection, job_id: Uuid) -> AppResult<ReconciliationQueueEntry> {
+    let existing = reconciliation_job_queue::table
+        .filter(reconciliation_job_queue::job_id.eq(job_id))
+        .for_update()
+        .first::<ReconciliationQueueEntry>(tx)
+        .optional()
+        .map_err(AppError::Database)?;
+
+    let Some(entry) = existing else {
+        let entry = diesel::insert_into(reconciliation_job_queue::table)
+            .values(&NewReconciliationQueueEntry {
+                job_id,
+                state: QueueState::Queued.as_str().to_string(),
+                max_attempts: DEFAULT_MAX_ATTEMPTS,
+            })
+            .get_result::<ReconciliationQueueEntry>(tx)
+            .map_err(AppError::Database)?;
+        set_job_status(tx, job_id, "queued")?;
+        return Ok(entry);
+    };
+
+    let lease_live = entry.lease_expires_at.is_some_and(|expiry| expiry > Utc::now());
+    if entry.state == QueueState::Leased.as_str() && lease_live {
+        return Err(AppError::Conflict(format!("Job {} is already running", job_id)));
+    }
+
+    let now = Utc::now();
+    let entry = diesel::update(reconciliation_job_queue::table.filter(reconciliation_job_queue::id.eq(entry.id)))
+        .set((
+            reconciliation_job_queue::state.eq(QueueState::Queued.as_str()),
+            reconciliation_job_queue::attempts.eq(0),
+            reconciliation_job_queue::available_at.eq(now),
+            reconciliation_job_queue::leased_by.eq(None::<String>),
+            reconciliation_job_queue::lease_expires_at.eq(None::<chrono::DateTime<Utc>>),
+            reconciliation_job_queue::last_error.eq(None::<String>),
+            reconciliation_job_queue::checkpoint.eq(None::<serde_json::Value>),
+            reconciliation_job_queue::updated_at.eq(now),
+        ))
+        .get_result::<ReconciliationQueueEntry>(tx)
+        .map_err(AppError::Database)?;
+    set_job_status(tx, job_id, "queued")?;
+    Ok(entry)
+}
+
+fn set_job_status(conn: &mut diesel::PgConnection, job_id: Uuid, status: &str) -> AppResult<()> {
+    diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+        .set((
+            reconciliation_jobs::status.eq(status),
+            reconciliation_jobs::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .map_err(AppError::Database)?;
+    Ok(())
+}
+
+/// Let the workflows and batches waiting on a job know it has finished,
+/// whether it completed, failed for good, lost its worker or was cancelled
+pub async fn announce_job_finished(db: &Database, job_id: Uuid) {
+    match finished_event(db, job_id) {
+        Ok(event) => WorkflowEngine::spawn_dispatch(Arc::new(db.clone()), event),
+        Err(e) => log::warn!("Failed to dispatch workflows for reconciliation job {}: {}", job_id, e),
+    }
+    if let Err(e) = advance_batches(db, job_id).await {
+        log::error!("Failed to advance batches of reconciliation job {}: {}", job_id, e);
+    }
+}
+
+/// Workflow event for a finished job
+fn finished_event(db: &Database, job_id: Uuid) -> AppResult<WorkflowEvent> {
+    let mut conn = db.get_connection()?;
+    let (project_id, status, matched, unmatched) = reconciliation_jobs::table
+        .find(job_id)
+        .select((
+            reconciliation_jobs::project_id,
+            reconciliation_jobs::status,
+            reconciliation_jobs::matched_records,
+            reconciliation_jobs::unmatched_records,
+        ))
+        .first::<(Uuid, String, Option<i32>, Option<i32>)>(&mut conn)
+        .map_err(AppError::Database)?;
+    Ok(WorkflowEvent::ReconciliationJobFinished {
+        job_id,
+        project_id,
+        status,
+        matched: matched.unwrap_or(0),
+        unmatched: unmatched.unwrap_or(0),
+    })
+}
+
+/// Claims queued jobs and runs them, up to the processor's `max_concurrent_jobs` at a time
+pub struct QueueWorker {
+    queue: JobQueue,
+    db: Database,
+    processor: Arc<JobProcessor>,
+    email: Option<Arc<EmailService>>,
+}
+
+impl QueueWorker {
+    pub fn new(db: Database, processor: Arc<JobProcessor>) -> Self {
+        Self {
+            queue: JobQueue::new(db.clone()),
+            db,
+            processor,
+            email: None,
+        }
+    }
+
+    /// Email job creators when their jobs finish
+    pub fn with_email(mut self, email: Arc<EmailService>) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Poll the queue in the background for as long as the process runs
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(Arc::new(self).run())
+    }
+
+    async fn run(self: Arc<Self>) {
+        log::info!("Reconciliation queue worker {} started", self.queue.worker_id);
+        let mut running = tokio::task::JoinSet::new();
+        loop {
+            while running.try_join_next().is_some() {}
+
+            if running.len() < self.processor.max_concurrent_jobs {
+                match self.queue.claim().await {
+                    Ok(Some(entry)) => {
+                        let worker = Arc::clone(&self);
+                        running.spawn(async move { worker.run_entry(entry).await });
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to claim a reconciliation job: {}", e),
+                }
+            }
+
+            // Wake up early when a running job finishes and frees a slot
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                Some(_) = running.join_next(), if !running.is_empty() => {}
+            }
+        }
+    }
+
+    /// Run one claimed entry, heartbeating its lease and persisting checkpoints
+    async fn run_entry(&self, entry: ReconciliationQueueEntry) {
+        let job_id = entry.job_id;
+        let resume_from: Option<ProcessingCheckpoint> =
+            entry.checkpoint.clone().and_then(|value| {
+                serde_json::from_value(value)
+                    .map_err(|e| {
+                        log::warn!(
+                            "Ignoring unreadable checkpoint of reconciliation job {}; starting over: {}",
+                            job_id,
+                            e
+                        )
+                    })
+                    .ok()
+            });
+        log::info!(
+            "Running reconciliation job {} (attempt {} of {})",
+            job_id,
+            entry.attempts,
+            entry.max_attempts
+        );
+
+        let (checkpoint_sender, mut checkpoints) = mpsc::unbounded_channel();
+        let run = execute_reconciliation_job(
+            &self.db,
+            &self.processor,
+            job_id,
+            resume_from,
+            Some(checkpoint_sender),
+        );
+        tokio::pin!(run);
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+
+        // `None` when the lease was lost; the run is dropped and left to whoever holds it now
+        let outcome = loop {
+            tokio::select! {
+                outcome = &mut run => break Some(outcome),
+                Some(checkpoint) = checkpoints.recv() => {
+                    if let Err(e) = self.queue.save_checkpoint(entry.id, &checkpoint) {
+                        log::warn!("Failed to save checkpoint for job {}: {}", job_id, e);
+                    }
+                }
+                _ = heartbeat.tick() => match self.queue.heartbeat(entry.id) {
+                    Ok(true) => {}
+                    Ok(false) => break None,
+                    Err(e) => log::warn!("Heartbeat for job {} failed: {}", job_id, e),
+                },
+            }
+        };
+        self.processor.complete_job(&job_id).await;
+
+        let Some(outcome) = outcome else {
+            log::warn!("Lease on reconciliation job {} was lost or cancelled; stopped running it", job_id);
+            return;
+        };
+        while let Ok(checkpoint) = checkpoints.try_recv() {
+            if let Err(e) = self.queue.save_checkpoint(entry.id, &checkpoint) {
+                log::warn!("Failed to save checkpoint for job {}: {}", job_id, e);
+            }
+        }
+
+        let released = match outcome {
+            Ok(()) => self.queue.complete(entry.id).map(|()| QueueState::Completed),
+            Err(e) => {
+                log::error!("Reconciliation job {} failed on attempt {}: {}", job_id, entry.attempts, e);
+                self.queue.fail(&entry, &e.to_string()).and_then(|state| {
+                    let job_status = if state == QueueState::Dead { "failed" } else { "queued" };
+                    let mut conn = self.db.get_connection()?;
+                    set_job_status(&mut conn, job_id, job_status)?;
+                    Ok(state)
+                })
+            }
+        };
+        match released {
+            Ok(QueueState::Completed | QueueState::Dead) => {
+                if let Err(e) = self.notify_finished(job_id).await {
+                    log::warn!("Failed to email completion of reconciliation job {}: {}", job_id, e);
+                }
+                announce_job_finished(&self.db, job_id).await;
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to update queue entry for job {}: {}", job_id, e),
+        }
+    }
+
+    /// Email a finished job's creator, if they want completion emails
+    async fn notify_finished(&self, job_id: Uuid) -> AppResult<()> {
+        let Some(email) = &self.email else {
+            return Ok(());
+        };
+        let (job_name, project_id, status, matched, unmatched, created_by) = {
+            let mut conn = self.db.get_connection()?;
+            reconciliation_jobs::table
+                .find(job_id)
+                .select((
+                    reconciliation_jobs::name,
+                    reconciliation_jobs::project_id,
+                    reconciliation_jobs::status,
+                    reconciliation_jobs::matched_records,
+                    reconciliation_jobs::unmatched_records,
+                    reconciliation_jobs::created_by,
+                ))
+                .first::<(String, Uuid, String, Option<i32>, Option<i32>, Uuid)>(&mut conn)
+                .map_err(AppError::Database)?
+        };
+
+        let settings = PreferencesService::new(Arc::new(self.db.clone()))
+            .get_settings(created_by)
+            .await?;
+        if !(settings.notifications.email && settings.notifications.reconciliation_complete) {
+            return Ok(());
+        }
+        let (address, first_name) = {
+            let mut conn = self.db.get_connection()?;
+            users::table
+                .find(created_by)
+                .select((users::email, users::first_name))
+                .first::<(String, Option<String>)>(&mut conn)
+                .map_err(AppError::Database)?
+        };
+
+        let job = JobCompletedEmail {
+            job_id,
+            job_name,
+            project_id,
+            status,
+            matched_records: matched.unwrap_or(0).into(),
+            unmatched_records: unmatched.unwrap_or(0).into(),
+        };
+        let locale = Some(settings.preferences.language.as_str()).filter(|language| !language.is_empty());
+        email
+            .send_job_completed(&address, first_name.as_deref().unwrap_or("there"), &job, locale)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off_exponentially_up_to_cap() {
+        assert_eq!(retry_delay(1).num_seconds(), RETRY_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay(2).num_seconds(), RETRY_BASE_DELAY_SECONDS * 2);
+        assert_eq!(retry_delay(3).num_seconds(), RETRY_BASE_DELAY_SECONDS * 4);
+        assert_eq!(retry_delay(0).num_seconds(), RETRY_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay(40).num_seconds(), RETRY_MAX_DELAY_SECONDS);
+    }
+
+    #[test]
+    fn checkpoint_round_trips_through_json() {
+        let checkpoint = ProcessingCheckpoint {
+            after_record_id: Uuid::new_v4(),
+            processed_records: 20_000,
+            matched_records: 18_500,
+            results_written: 20_000,
+        };
+        let value = serde_json::to_value(&checkpoint).unwrap_or_else(|e| panic!("{}", e));
+        let parsed: ProcessingCheckpoint = serde_json::from_value(value).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(parsed, checkpoint);
+    }
+}
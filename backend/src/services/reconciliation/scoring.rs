@@ -0,0 +1,391 @@
+//! Rule-based match scoring
+//!
+//! Every `MatchingRule` is evaluated with the algorithm for its own rule type
+//! and checked against its own threshold. Rule similarities are combined into
+//! a weighted confidence score, and `required` rules veto the pair outright
+//! when they fail. Values are normalised per rule before comparison.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::matching::{
+    ContainsMatchingAlgorithm, DateRangeMatchingAlgorithm, ExactMatchingAlgorithm,
+    FuzzyMatchingAlgorithm, MatchingAlgorithm, NumericRangeMatchingAlgorithm, TfIdfCorpus,
+};
+use super::types::{
+    DifferenceType, FieldDifference, FuzzyAlgorithmType, MatchingResult, MatchingRule,
+    MatchingRuleType, ReconciliationRecord,
+};
+use crate::models::MatchType;
+
+/// Outcome of evaluating one rule against a record pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleScore {
+    pub field: String,
+    pub rule_type: MatchingRuleType,
+    pub algorithm: String,
+    pub weight: f64,
+    pub threshold: f64,
+    pub similarity: f64,
+    /// Weighted share of the final confidence score
+    pub contribution: f64,
+    pub passed: bool,
+    pub required: bool,
+}
+
+/// Full scoring outcome for a record pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairScore {
+    pub confidence_score: f64,
+    /// Set when a required rule failed; the pair must not be matched
+    pub vetoed_by: Option<String>,
+    pub rule_scores: Vec<RuleScore>,
+    pub matching_fields: Vec<String>,
+    pub differences: Vec<FieldDifference>,
+}
+
+/// A rule paired with the algorithm that evaluates it
+struct CompiledRule {
+    rule: MatchingRule,
+    algorithm: Box<dyn MatchingAlgorithm + Send + Sync>,
+}
+
+impl CompiledRule {
+    /// Render a field value for this rule's algorithm, applying its normalisation
+    fn normalize(&self, value: &serde_json::Value) -> String {
+        let text = value_to_match_string(value);
+        match &self.rule.options.normalization {
+            Some(normalization) => normalization.apply(&text),
+            None => text,
+        }
+    }
+}
+
+/// TF-IDF statistics being collected for the cosine rules of a `ScoringEngine`
+pub struct CorpusFit {
+    corpora: Vec<Option<TfIdfCorpus>>,
+}
+
+/// Scores record pairs against a set of matching rules
+pub struct ScoringEngine {
+    rules: Vec<CompiledRule>,
+}
+
+impl ScoringEngine {
+    pub fn new(rules: &[MatchingRule]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| CompiledRule {
+                rule: rule.clone(),
+                algorithm: algorithm_for_rule(rule),
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether any rule needs corpus statistics (TF-IDF cosine)
+    pub fn needs_corpus(&self) -> bool {
+        self.rules.iter().any(|c| uses_cosine(&c.rule))
+    }
+
+    /// Build TF-IDF statistics for cosine rules from the values of their field
+    pub fn fit_corpus<'r>(&mut self, records: impl IntoIterator<Item = &'r ReconciliationRecord>) {
+        let mut fit = self.start_corpus();
+        for record in records {
+            self.add_to_corpus(&mut fit, record);
+        }
+        self.finish_corpus(fit);
+    }
+
+    /// Empty statistics for `add_to_corpus`, for records that arrive in pages
+    pub fn start_corpus(&self) -> CorpusFit {
+        CorpusFit {
+            corpora: self
+                .rules
+                .iter()
+                .map(|c| uses_cosine(&c.rule).then(TfIdfCorpus::new))
+                .collect(),
+        }
+    }
+
+    pub fn add_to_corpus(&self, fit: &mut CorpusFit, record: &ReconciliationRecord) {
+        for (compiled, corpus) in self.rules.iter().zip(fit.corpora.iter_mut()) {
+            if let (Some(corpus), Some(value)) = (corpus, record.fields.get(&compiled.rule.field)) {
+                corpus.add_document(&compiled.normalize(value));
+            }
+        }
+    }
+
+    /// Switch the cosine rules over to the collected statistics
+    pub fn finish_corpus(&mut self, fit: CorpusFit) {
+        for (compiled, corpus) in self.rules.iter_mut().zip(fit.corpora) {
+            if let Some(corpus) = corpus {
+                let algorithm = FuzzyMatchingAlgorithm::new(
+                    compiled.rule.threshold,
+                    FuzzyAlgorithmType::Cosine,
+                )
+                .with_corpus(Arc::new(corpus));
+                compiled.algorithm = Box::new(algorithm);
+            }
+        }
+    }
+
+    /// Evaluate every rule for the pair and combine the results
+    pub fn score(&self, source: &ReconciliationRecord, target: &ReconciliationRecord) -> PairScore {
+        let mut rule_scores = Vec::with_capacity(self.rules.len());
+        let mut matching_fields = Vec::new();
+        let mut differences = Vec::new();
+        let mut vetoed_by = None;
+        let mut weighted_sum = 0.0;
+        let mut total_weight = 0.0;
+
+        for compiled in &self.rules {
+            let rule = &compiled.rule;
+            let weight = rule.weight.max(0.0);
+            let source_val = source.fields.get(&rule.field);
+            let target_val = target.fields.get(&rule.field);
+
+            let similarity = match (source_val, target_val) {
+                (Some(a), Some(b)) => compiled
+                    .algorithm
+                    .calculate_similarity(&compiled.normalize(a), &compiled.normalize(b)),
+                // Absent on both sides: the rule cannot say anything about the pair
+                (None, None) if !rule.required => continue,
+                _ => 0.0,
+            };
+            let passed = similarity >= rule.threshold;
+
+            weighted_sum += weight * similarity;
+            total_weight += weight;
+
+            if passed {
+                matching_fields.push(rule.field.clone());
+            } else {
+                if rule.required && vetoed_by.is_none() {
+                    vetoed_by = Some(rule.field.clone());
+                }
+                differences.push(FieldDifference {
+                    field_name: rule.field.clone(),
+                    source_value: source_val.cloned().unwrap_or(serde_json::Value::Null),
+                    target_value: target_val.cloned().unwrap_or(serde_json::Value::Null),
+                    difference_type: if source_val.is_none() || target_val.is_none() {
+                        DifferenceType::Missing
+                    } else if similarity > 0.5 {
+                        DifferenceType::Similar
+                    } else {
+                        DifferenceType::Different
+                    },
+                    similarity_score: similarity,
+                });
+            }
+
+            rule_scores.push(RuleScore {
+                field: rule.field.clone(),
+                rule_type: rule.rule_type.clone(),
+                algorithm: compiled.algorithm.get_algorithm_name().to_string(),
+                weight,
+                threshold: rule.threshold,
+                similarity,
+                contribution: weight * similarity,
+                passed,
+                required: rule.required,
+            });
+        }
+
+        let confidence_score = if total_weight > 0.0 {
+            weighted_sum / total_weight
+        } else {
+            0.0
+        };
+        // Report contributions as shares of the final score
+        if total_weight > 0.0 {
+            for score in &mut rule_scores {
+                score.contribution /= total_weight;
+            }
+        }
+
+        PairScore {
+            confidence_score,
+            vetoed_by,
+            rule_scores,
+            matching_fields,
+            differences,
+        }
+    }
+
+    /// Score a pair and turn it into a match if it clears `threshold` and no
+    /// required rule vetoed it
+    pub fn match_pair(
+        &self,
+        source: &ReconciliationRecord,
+        target: &ReconciliationRecord,
+        threshold: f64,
+    ) -> Option<(MatchingResult, Vec<RuleScore>)> {
+        let score = self.score(source, target);
+        if score.vetoed_by.is_some() || score.confidence_score < threshold {
+            return None;
+        }
+
+        let match_type = if score.differences.is_empty() && score.confidence_score >= 1.0 {
+            MatchType::Exact
+        } else {
+            MatchType::Fuzzy
+        };
+
+        Some((
+            MatchingResult {
+                source_record: source.clone(),
+                target_record: target.clone(),
+                confidence_score: score.confidence_score,
+                match_type,
+                matching_fields: score.matching_fields,
+                differences: score.differences,
+            },
+            score.rule_scores,
+        ))
+    }
+}
+
+/// Pick the algorithm that evaluates a rule of the given type
+fn algorithm_for_rule(rule: &MatchingRule) -> Box<dyn MatchingAlgorithm + Send + Sync> {
+    match rule.rule_type {
+        MatchingRuleType::Exact => Box::new(ExactMatchingAlgorithm),
+        MatchingRuleType::Fuzzy => Box::new(FuzzyMatchingAlgorithm::new(
+            rule.threshold,
+            rule.options
+                .algorithm
+                .clone()
+                .unwrap_or(FuzzyAlgorithmType::Levenshtein),
+        )),
+        MatchingRuleType::Contains => Box::new(ContainsMatchingAlgorithm),
+        MatchingRuleType::NumericRange => Box::new(NumericRangeMatchingAlgorithm::new(
+            rule.options.tolerance.clone(),
+        )),
+        MatchingRuleType::DateRange => Box::new(DateRangeMatchingAlgorithm::new(
+            rule.options.date_window.clone().unwrap_or_default(),
+        )),
+    }
+}
+
+fn uses_cosine(rule: &MatchingRule) -> bool {
+    matches!(rule.rule_type, MatchingRuleType::Fuzzy)
+        && matches!(rule.options.algorithm, Some(FuzzyAlgorithmType::Cosine))
+}
+
+/// Render a field value for comparison without JSON string quoting
+pub fn value_to_match_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::record;
+    use super::super::types::{RuleOptions, TextNormalization};
+    use super::*;
+    use serde_json::json;
+
+    fn rule(field: &str, rule_type: MatchingRuleType, weight: f64, threshold: f64) -> MatchingRule {
+        MatchingRule {
+            field: field.to_string(),
+            rule_type,
+            weight,
+            threshold,
+            required: false,
+            options: RuleOptions::default(),
+        }
+    }
+
+    #[test]
+    fn weights_drive_combined_score() {
+        let engine = ScoringEngine::new(&[
+            rule("reference", MatchingRuleType::Exact, 3.0, 1.0),
+            rule("payee", MatchingRuleType::Exact, 1.0, 1.0),
+        ]);
+        let a = record(json!({"reference": "INV-1", "payee": "Acme"}));
+        let b = record(json!({"reference": "INV-1", "payee": "Globex"}));
+
+        let score = engine.score(&a, &b);
+        assert!((score.confidence_score - 0.75).abs() < 1e-9);
+        assert_eq!(score.matching_fields, vec!["reference".to_string()]);
+        assert_eq!(score.differences.len(), 1);
+        assert!((score.rule_scores[0].contribution - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn each_rule_uses_its_own_algorithm() {
+        let engine = ScoringEngine::new(&[
+            rule("reference", MatchingRuleType::Exact, 1.0, 1.0),
+            rule("memo", MatchingRuleType::Contains, 1.0, 0.5),
+        ]);
+        let a = record(json!({"reference": "INV-1", "memo": "Payment INV-1 March"}));
+        let b = record(json!({"reference": "INV-1", "memo": "inv-1"}));
+
+        let score = engine.score(&a, &b);
+        assert_eq!(score.rule_scores[0].algorithm, "exact");
+        assert_eq!(score.rule_scores[1].algorithm, "contains");
+        assert!(score.rule_scores.iter().all(|r| r.passed));
+    }
+
+    #[test]
+    fn required_rule_vetoes_pair() {
+        let mut amount = rule("amount", MatchingRuleType::Exact, 1.0, 1.0);
+        amount.required = true;
+        let engine = ScoringEngine::new(&[
+            amount,
+            rule("payee", MatchingRuleType::Exact, 10.0, 1.0),
+        ]);
+        let a = record(json!({"amount": "100.00", "payee": "Acme"}));
+        let b = record(json!({"amount": "99.00", "payee": "Acme"}));
+
+        let score = engine.score(&a, &b);
+        assert_eq!(score.vetoed_by.as_deref(), Some("amount"));
+        assert!(score.confidence_score > 0.9);
+        assert!(engine.match_pair(&a, &b, 0.5).is_none());
+    }
+
+    #[test]
+    fn rules_pick_algorithm_and_normalization() {
+        let mut payee = rule("payee", MatchingRuleType::Fuzzy, 1.0, 0.9);
+        payee.options.algorithm = Some(FuzzyAlgorithmType::Metaphone);
+        payee.options.normalization = Some(TextNormalization {
+            case_insensitive: true,
+            strip_punctuation: true,
+            collapse_whitespace: true,
+            strip_legal_suffixes: true,
+        });
+        let engine = ScoringEngine::new(&[payee]);
+        let a = record(json!({"payee": "Smyth & Sons Ltd."}));
+        let b = record(json!({"payee": "SMITH SONS"}));
+
+        let score = engine.score(&a, &b);
+        assert_eq!(score.rule_scores[0].algorithm, "metaphone");
+        assert!(score.rule_scores[0].passed);
+    }
+
+    #[test]
+    fn missing_field_counts_as_difference() {
+        let engine = ScoringEngine::new(&[
+            rule("reference", MatchingRuleType::Exact, 1.0, 1.0),
+            rule("payee", MatchingRuleType::Exact, 1.0, 1.0),
+        ]);
+        let a = record(json!({"reference": "INV-1", "payee": "Acme"}));
+        let b = record(json!({"reference": "INV-1"}));
+
+        let matched = engine.match_pair(&a, &b, 0.5);
+        assert!(matched.is_some());
+        if let Some((result, rule_scores)) = matched {
+            assert_eq!(result.match_type, MatchType::Fuzzy);
+            assert!(matches!(result.differences[0].difference_type, DifferenceType::Missing));
+            assert_eq!(rule_scores.len(), 2);
+        }
+    }
+}
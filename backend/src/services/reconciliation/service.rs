@@ -3,25 +3,28 @@
 //! This module contains all the service methods that implement the public API
 //! for reconciliation operations.
 
+pub mod batches;
 pub mod jobs;
 pub mod results;
 
-use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
 use uuid::Uuid;
 
 use crate::database::Database;
 use crate::errors::{AppError, AppResult};
-use crate::models::schema::{reconciliation_jobs, reconciliation_results};
+use crate::models::schema::reconciliation_jobs;
+use crate::services::audit::{AuditContext, AuditEvent};
 use crate::models::{
-    NewReconciliationJob, ReconciliationJob,
+    NewReconciliationJob, ReconciliationJob, ReconciliationQueueEntry,
 };
 use super::types::{
-    CreateReconciliationJobRequest, ReconciliationJobStatus,
+    CreateReconciliationJobRequest, JobSettings, ReconciliationJobStatus,
 };
+use super::queue::{announce_job_finished, JobQueue, QueueState};
 use super::ReconciliationService;
 
 // Re-export types and functions from sub-modules
-pub use results::{BatchApprovalResult, MatchResolve, UpdatedMatch};
+pub use results::{BatchApprovalResult, MatchResolve, MatchUpdate, UpdatedMatch};
 // Note: get_reconciliation_progress is in jobs module, not re-exported here to avoid conflicts
 
 
@@ -70,7 +73,16 @@ pub async fn stop_reconciliation_job(
     service: &ReconciliationService,
     job_id: Uuid,
 ) -> AppResult<()> {
-    service.job_processor.stop_job(job_id).await
+    if !JobQueue::new(service.db.clone()).cancel(job_id)? {
+        return service.job_processor.stop_job(job_id).await;
+    }
+    let mut conn = service.db.get_connection()?;
+    diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+        .set(reconciliation_jobs::status.eq("cancelled"))
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+    announce_job_finished(&service.db, job_id).await;
+    Ok(())
 }
 
 // Delegate to results module
@@ -109,100 +121,52 @@ pub async fn cancel_reconciliation_job(
         }
     }
     
+    // Drop it from the job queue; a worker running it stops at its next heartbeat
+    JobQueue::new(service.db.clone()).cancel(job_id)?;
+
     // Update job status to cancelled
     diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
         .set(reconciliation_jobs::status.eq("cancelled"))
         .execute(&mut conn)
         .map_err(AppError::Database)?;
-    
+
+    announce_job_finished(&service.db, job_id).await;
     Ok(())
 }
 
-/// Get list of currently active reconciliation jobs.
+/// Get list of currently active reconciliation jobs, on any replica.
 pub async fn get_active_jobs(service: &ReconciliationService) -> AppResult<Vec<Uuid>> {
-    Ok(service
-        .job_processor
-        .active_jobs
-        .read()
-        .await
-        .keys()
-        .cloned()
-        .collect())
+    JobQueue::new(service.db.clone()).job_ids(QueueState::Leased)
 }
 
 /// Get list of reconciliation jobs currently queued for processing.
 pub async fn get_queued_jobs(service: &ReconciliationService) -> AppResult<Vec<Uuid>> {
-    Ok(service.job_processor.job_queue.read().await.clone())
+    JobQueue::new(service.db.clone()).job_ids(QueueState::Queued)
 }
 
+/// Get the queue entries of jobs that ran out of attempts.
+pub async fn get_dead_letter_jobs(
+    service: &ReconciliationService,
+) -> AppResult<Vec<ReconciliationQueueEntry>> {
+    JobQueue::new(service.db.clone()).dead_letters()
+}
 
 
 
 
 
 
-/// Export job results to file
-pub async fn export_job_results(
-    db: &Database,
-    job_id: Uuid,
-    path: &std::path::Path,
-    format: &str,
-) -> AppResult<()> {
-    use crate::models::ReconciliationResult;
-    use std::io::Write;
-
-    let mut conn = db.get_connection()?;
-    let results = reconciliation_results::table
-        .filter(reconciliation_results::job_id.eq(job_id))
-        .load::<ReconciliationResult>(&mut conn)
-        .map_err(AppError::Database)?;
-
-    match format {
-        "csv" => {
-            let mut writer = std::fs::File::create(path)?;
-            writeln!(writer, "id,job_id,record_a_id,record_b_id,match_type,confidence_score,status,notes,created_at,updated_at")?;
-            for result in results {
-                writeln!(
-                    writer,
-                    "{},{},{},{},{},{},{},{},{},{}",
-                    result.id,
-                    result.job_id,
-                    result.record_a_id,
-                    result.record_b_id.unwrap_or(Uuid::nil()),
-                    result.match_type,
-                    result
-                        .confidence_score
-                        .map(|c| c.to_string())
-                        .unwrap_or("0.0".to_string()),
-                    result.status.as_deref().unwrap_or("unknown"),
-                    result.notes.as_deref().unwrap_or(""),
-                    result.created_at,
-                    result.updated_at.unwrap_or(result.created_at)
-                )?;
-            }
-        }
-        "json" => {
-            let json = serde_json::to_string_pretty(&results)?;
-            std::fs::write(path, json)?;
-        }
-        _ => {
-            return Err(AppError::Validation(format!(
-                "Unsupported export format: {}",
-                format
-            )))
-        }
-    }
-
-    Ok(())
-}
 
 /// Create reconciliation job implementation
+///
+/// With `audit`, the new job is recorded in the audit chain in the same
+/// transaction.
 pub async fn create_reconciliation_job_impl(
     db: &Database,
     user_id: Uuid,
     request: CreateReconciliationJobRequest,
+    audit: Option<&AuditContext>,
 ) -> AppResult<ReconciliationJobStatus> {
-    let job_id = Uuid::new_v4();
     crate::database::transaction::with_transaction(db.get_pool(), |tx| {
         use crate::models::schema::data_sources;
 
@@ -235,9 +199,10 @@ pub async fn create_reconciliation_job_impl(
             )));
         }
 
-        // 2) Create the job
-        let settings_json =
-            serde_json::to_value(&request.matching_rules).map_err(AppError::Serialization)?;
+        // 2) Create the job; the sources are kept in settings so the job can
+        // be run again later without the original request
+        let settings_json = serde_json::to_value(JobSettings::from_request(&request))
+            .map_err(AppError::Serialization)?;
 
         use bigdecimal::BigDecimal;
         use std::str::FromStr;
@@ -262,11 +227,26 @@ pub async fn create_reconciliation_job_impl(
             processing_time_ms: None,
         };
 
-        diesel::insert_into(reconciliation_jobs::table)
+        let job_id = diesel::insert_into(reconciliation_jobs::table)
             .values(&new_job)
-            .execute(tx)
+            .returning(reconciliation_jobs::id)
+            .get_result::<Uuid>(tx)
             .map_err(AppError::Database)?;
 
+        if let Some(audit) = audit {
+            let job = reconciliation_jobs::table
+                .find(job_id)
+                .select(ReconciliationJob::as_select())
+                .first::<ReconciliationJob>(tx)
+                .map_err(AppError::Database)?;
+            crate::services::audit::append(
+                tx,
+                audit,
+                AuditEvent::new("job.create", "reconciliation_job", Some(job_id))
+                    .with_change(None, serde_json::to_value(&job).ok()),
+            )?;
+        }
+
         Ok(ReconciliationJobStatus {
             id: job_id,
             name: request.name,
@@ -0,0 +1,560 @@
+//! Reconciliation batches
+//!
+//! A batch groups reconciliation jobs that are run and reported on together,
+//! e.g. one job per bank account for a month-end close. Jobs run through the
+//! durable job queue, either one after another in batch order (the next job
+//! is queued when the one before it finishes) or all queued at once and run
+//! as queue workers free up. The batch status and summary are derived from
+//! the status of its jobs.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::transaction::with_transaction;
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{reconciliation_batch_jobs, reconciliation_batches, reconciliation_jobs};
+use crate::models::{
+    NewReconciliationBatch, NewReconciliationBatchJob, ReconciliationBatch, ReconciliationBatchJob,
+    ReconciliationJob,
+};
+use crate::services::reconciliation::queue::{enqueue_in, JobQueue};
+use crate::services::reconciliation::ReconciliationService;
+
+use super::jobs::ensure_job_startable;
+
+/// Largest number of jobs a single batch may hold
+pub const MAX_JOBS_PER_BATCH: usize = 200;
+
+/// How the jobs of a batch are run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchExecutionMode {
+    /// One job at a time, in batch order
+    #[default]
+    Sequential,
+    /// All queued at once, run as queue workers free up
+    Parallel,
+}
+
+impl BatchExecutionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sequential => "sequential",
+            Self::Parallel => "parallel",
+        }
+    }
+
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "sequential" => Ok(Self::Sequential),
+            "parallel" => Ok(Self::Parallel),
+            other => Err(AppError::Validation(format!(
+                "Unknown batch execution mode: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Request to create a batch from existing jobs of one project
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateBatchRequest {
+    pub project_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Jobs in the order they are processed
+    pub job_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub execution_mode: BatchExecutionMode,
+}
+
+/// Per-job line of a batch summary
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchJobSummary {
+    pub job_id: Uuid,
+    pub position: i32,
+    pub name: String,
+    pub status: String,
+    pub progress: i32,
+    pub total_records: Option<i32>,
+    pub matched_records: i32,
+    pub unmatched_records: i32,
+    pub processing_time_ms: Option<i32>,
+}
+
+/// Combined figures over all jobs of a batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchTotals {
+    pub jobs: usize,
+    pub pending_jobs: usize,
+    pub running_jobs: usize,
+    pub completed_jobs: usize,
+    pub failed_jobs: usize,
+    pub total_records: i64,
+    pub matched_records: i64,
+    pub unmatched_records: i64,
+    /// Matched share of all records across completed jobs, 0.0 - 1.0
+    pub match_rate: f64,
+    /// Mean job progress, 0 - 100
+    pub progress: i32,
+}
+
+/// Batch with its derived status and combined summary
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchSummary {
+    #[serde(flatten)]
+    pub batch: ReconciliationBatch,
+    pub totals: BatchTotals,
+    pub jobs: Vec<BatchJobSummary>,
+}
+
+/// Create a batch over existing jobs of a project
+pub async fn create_batch(
+    db: &Database,
+    user_id: Uuid,
+    request: CreateBatchRequest,
+) -> AppResult<BatchSummary> {
+    if request.name.trim().is_empty() {
+        return Err(AppError::Validation("Batch name must not be empty".to_string()));
+    }
+    if request.job_ids.is_empty() {
+        return Err(AppError::Validation("A batch needs at least one job".to_string()));
+    }
+    if request.job_ids.len() > MAX_JOBS_PER_BATCH {
+        return Err(AppError::Validation(format!(
+            "A batch may hold at most {} jobs",
+            MAX_JOBS_PER_BATCH
+        )));
+    }
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = request.job_ids.iter().find(|id| !seen.insert(**id)) {
+        return Err(AppError::Validation(format!(
+            "Job {} appears more than once in the batch",
+            duplicate
+        )));
+    }
+
+    let batch_id = crate::database::transaction::with_transaction(db.get_pool(), |tx| {
+        let found: HashSet<Uuid> = reconciliation_jobs::table
+            .filter(reconciliation_jobs::id.eq_any(&request.job_ids))
+            .filter(reconciliation_jobs::project_id.eq(request.project_id))
+            .select(reconciliation_jobs::id)
+            .load::<Uuid>(tx)
+            .map_err(AppError::Database)?
+            .into_iter()
+            .collect();
+        if let Some(missing) = request.job_ids.iter().find(|id| !found.contains(id)) {
+            return Err(AppError::NotFound(format!(
+                "Reconciliation job {} not found for project {}",
+                missing, request.project_id
+            )));
+        }
+
+        let new_batch = NewReconciliationBatch {
+            project_id: request.project_id,
+            name: request.name.trim().to_string(),
+            description: request.description.clone(),
+            execution_mode: request.execution_mode.as_str().to_string(),
+            status: "pending".to_string(),
+            created_by: user_id,
+        };
+        let batch_id = diesel::insert_into(reconciliation_batches::table)
+            .values(&new_batch)
+            .returning(reconciliation_batches::id)
+            .get_result::<Uuid>(tx)
+            .map_err(AppError::Database)?;
+
+        let memberships: Vec<NewReconciliationBatchJob> = request
+            .job_ids
+            .iter()
+            .zip(0i32..)
+            .map(|(job_id, position)| NewReconciliationBatchJob {
+                batch_id,
+                job_id: *job_id,
+                position,
+            })
+            .collect();
+        diesel::insert_into(reconciliation_batch_jobs::table)
+            .values(&memberships)
+            .execute(tx)
+            .map_err(AppError::Database)?;
+
+        Ok(batch_id)
+    })
+    .await?;
+
+    get_batch(db, batch_id)
+}
+
+/// Batch by id with its summary
+pub fn get_batch(db: &Database, batch_id: Uuid) -> AppResult<BatchSummary> {
+    let mut conn = db.get_connection()?;
+    let batch = reconciliation_batches::table
+        .filter(reconciliation_batches::id.eq(batch_id))
+        .select(ReconciliationBatch::as_select())
+        .first(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound(format!("Batch {} not found", batch_id)))?;
+    let mut summaries = summarize_batches(&mut conn, vec![batch])?;
+    summaries
+        .pop()
+        .ok_or_else(|| AppError::Internal(format!("Batch {} could not be summarised", batch_id)))
+}
+
+/// One page of a project's batches, newest first, with the total count
+pub fn list_batches(
+    db: &Database,
+    project_id: Uuid,
+    page: i64,
+    per_page: i64,
+) -> AppResult<(Vec<BatchSummary>, i64)> {
+    let mut conn = db.get_connection()?;
+    let total = reconciliation_batches::table
+        .filter(reconciliation_batches::project_id.eq(project_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .map_err(AppError::Database)?;
+    let batches = reconciliation_batches::table
+        .filter(reconciliation_batches::project_id.eq(project_id))
+        .order(reconciliation_batches::created_at.desc())
+        .limit(per_page)
+        .offset((page - 1) * per_page)
+        .select(ReconciliationBatch::as_select())
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    Ok((summarize_batches(&mut conn, batches)?, total))
+}
+
+/// Start processing every job of a batch through the job queue.
+///
+/// Fails with `Conflict` when the batch or any of its jobs is already
+/// running. Returns the batch as it stands once processing has started.
+pub async fn process_batch(
+    service: &ReconciliationService,
+    batch_id: Uuid,
+) -> AppResult<BatchSummary> {
+    let summary = get_batch(&service.db, batch_id)?;
+    let job_ids: Vec<Uuid> = summary.jobs.iter().map(|job| job.job_id).collect();
+    for job_id in &job_ids {
+        ensure_job_startable(&service.db, &service.job_processor, *job_id).await?;
+    }
+    let mode = BatchExecutionMode::parse(&summary.batch.execution_mode)?;
+    let queued_now = match mode {
+        BatchExecutionMode::Sequential => &job_ids[..job_ids.len().min(1)],
+        BatchExecutionMode::Parallel => &job_ids[..],
+    };
+
+    // Claimed, reset and queued in one transaction: of two concurrent calls
+    // only one starts the batch, and a job that can't be queued leaves the
+    // batch as it was
+    let started_at = Utc::now();
+    with_transaction(service.db.get_pool(), |tx| {
+        let claimed = diesel::update(
+            reconciliation_batches::table
+                .filter(reconciliation_batches::id.eq(batch_id))
+                .filter(reconciliation_batches::status.ne("running")),
+        )
+        .set((
+            reconciliation_batches::status.eq("running"),
+            reconciliation_batches::started_at.eq(Some(started_at)),
+            reconciliation_batches::completed_at.eq(None::<chrono::DateTime<Utc>>),
+            reconciliation_batches::updated_at.eq(started_at),
+        ))
+        .returning(reconciliation_batches::id)
+        .get_result::<Uuid>(tx)
+        .optional()
+        .map_err(AppError::Database)?;
+        if claimed.is_none() {
+            return Err(AppError::Conflict(format!("Batch {} is already running", batch_id)));
+        }
+        // Jobs queued later must not look finished from an earlier run
+        let later: Vec<Uuid> = job_ids[queued_now.len()..].to_vec();
+        diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq_any(&later)))
+            .set((
+                reconciliation_jobs::status.eq("pending"),
+                reconciliation_jobs::updated_at.eq(started_at),
+            ))
+            .execute(tx)
+            .map_err(AppError::Database)?;
+        for job_id in queued_now {
+            enqueue_in(tx, *job_id)?;
+        }
+        Ok(())
+    })
+    .await?;
+
+    get_batch(&service.db, batch_id)
+}
+
+/// Move the running batches a finished job belongs to along
+///
+/// Called by the queue worker whenever a job finishes for good, so batches
+/// progress whichever replica ran the job and survive restarts. The next job
+/// of a sequential batch is queued; a batch whose jobs have all finished gets
+/// its final status.
+pub async fn advance_batches(db: &Database, job_id: Uuid) -> AppResult<()> {
+    let batches = {
+        let mut conn = db.get_connection()?;
+        let batch_ids = reconciliation_batch_jobs::table
+            .filter(reconciliation_batch_jobs::job_id.eq(job_id))
+            .select(reconciliation_batch_jobs::batch_id)
+            .load::<Uuid>(&mut conn)
+            .map_err(AppError::Database)?;
+        reconciliation_batches::table
+            .filter(reconciliation_batches::id.eq_any(&batch_ids))
+            .filter(reconciliation_batches::status.eq("running"))
+            .select(ReconciliationBatch::as_select())
+            .load(&mut conn)
+            .map_err(AppError::Database)?
+    };
+
+    for batch in batches {
+        if BatchExecutionMode::parse(&batch.execution_mode)? == BatchExecutionMode::Sequential
+            && queue_next_job(db, &get_batch(db, batch.id)?, job_id).await
+        {
+            continue;
+        }
+        let summary = get_batch(db, batch.id)?;
+        let finished = summary
+            .jobs
+            .iter()
+            .all(|job| matches!(job.status.as_str(), "completed" | "failed" | "cancelled"));
+        if finished {
+            finish_batch(db, batch.id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Queue the first job after `finished_job_id` in a sequential batch that
+/// can be queued; whether one was
+async fn queue_next_job(db: &Database, summary: &BatchSummary, finished_job_id: Uuid) -> bool {
+    let Some(position) = summary
+        .jobs
+        .iter()
+        .find(|job| job.job_id == finished_job_id)
+        .map(|job| job.position)
+    else {
+        return false;
+    };
+    let queue = JobQueue::new(db.clone());
+    for job in summary.jobs.iter().filter(|job| job.position > position) {
+        match queue.enqueue(job.job_id).await {
+            Ok(_) => return true,
+            // Already running; the batch moves on when that run finishes
+            Err(AppError::Conflict(_)) => return true,
+            // Fails in the batch so the rest of the batch still runs
+            Err(e) => {
+                log::error!(
+                    "Failed to queue job {} of batch {}: {}",
+                    job.job_id,
+                    summary.batch.id,
+                    e
+                );
+                let failed = db.get_connection().and_then(|mut conn| {
+                    diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job.job_id)))
+                        .set((
+                            reconciliation_jobs::status.eq("failed"),
+                            reconciliation_jobs::updated_at.eq(Utc::now()),
+                        ))
+                        .execute(&mut conn)
+                        .map_err(AppError::Database)
+                });
+                if let Err(e) = failed {
+                    log::error!("Failed to mark job {} failed: {}", job.job_id, e);
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Persist the final status of a batch once all of its jobs have run
+fn finish_batch(db: &Database, batch_id: Uuid) -> AppResult<()> {
+    let summary = get_batch(db, batch_id)?;
+    let statuses: Vec<&str> = summary.jobs.iter().map(|job| job.status.as_str()).collect();
+    let status = derive_batch_status(&statuses);
+    let now = Utc::now();
+    let mut conn = db.get_connection()?;
+    diesel::update(reconciliation_batches::table.filter(reconciliation_batches::id.eq(batch_id)))
+        .set((
+            reconciliation_batches::status.eq(status),
+            reconciliation_batches::completed_at.eq(Some(now)),
+            reconciliation_batches::updated_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+    log::info!(
+        "Batch {} finished as {}: {}/{} jobs completed",
+        batch_id,
+        status,
+        summary.totals.completed_jobs,
+        summary.totals.jobs
+    );
+    Ok(())
+}
+
+/// Attach job summaries and totals to batches, loading all jobs in one query
+fn summarize_batches(
+    conn: &mut PgConnection,
+    batches: Vec<ReconciliationBatch>,
+) -> AppResult<Vec<BatchSummary>> {
+    let batch_ids: Vec<Uuid> = batches.iter().map(|batch| batch.id).collect();
+    let rows = reconciliation_batch_jobs::table
+        .inner_join(reconciliation_jobs::table)
+        .filter(reconciliation_batch_jobs::batch_id.eq_any(&batch_ids))
+        .order(reconciliation_batch_jobs::position.asc())
+        .select((ReconciliationBatchJob::as_select(), ReconciliationJob::as_select()))
+        .load::<(ReconciliationBatchJob, ReconciliationJob)>(conn)
+        .map_err(AppError::Database)?;
+
+    let mut jobs_by_batch: HashMap<Uuid, Vec<BatchJobSummary>> = HashMap::new();
+    for (membership, job) in rows {
+        jobs_by_batch
+            .entry(membership.batch_id)
+            .or_default()
+            .push(BatchJobSummary {
+                job_id: job.id,
+                position: membership.position,
+                name: job.name,
+                status: job.status,
+                progress: job.progress.unwrap_or(0),
+                total_records: job.total_records,
+                matched_records: job.matched_records.unwrap_or(0),
+                unmatched_records: job.unmatched_records.unwrap_or(0),
+                processing_time_ms: job.processing_time_ms,
+            });
+    }
+
+    Ok(batches
+        .into_iter()
+        .map(|mut batch| {
+            let jobs = jobs_by_batch.remove(&batch.id).unwrap_or_default();
+            let totals = batch_totals(&jobs);
+            // A batch that has been started reports the live state of its jobs
+            if batch.status != "pending" {
+                let statuses: Vec<&str> = jobs.iter().map(|job| job.status.as_str()).collect();
+                batch.status = derive_batch_status(&statuses).to_string();
+            }
+            BatchSummary { batch, totals, jobs }
+        })
+        .collect())
+}
+
+/// Batch status from the statuses of its jobs.
+///
+/// `pending` until any job has started, `running` while any job is still to
+/// finish, then `completed`, `completed_with_errors` when some jobs failed or
+/// were cancelled, or `failed` when none completed.
+pub fn derive_batch_status(job_statuses: &[&str]) -> &'static str {
+    let finished = |status: &&str| matches!(*status, "completed" | "failed" | "cancelled");
+    if job_statuses.is_empty() || job_statuses.iter().all(|status| *status == "pending") {
+        return "pending";
+    }
+    if !job_statuses.iter().all(finished) {
+        return "running";
+    }
+    let completed = job_statuses.iter().filter(|status| **status == "completed").count();
+    if completed == job_statuses.len() {
+        "completed"
+    } else if completed > 0 {
+        "completed_with_errors"
+    } else {
+        "failed"
+    }
+}
+
+/// Combined counts over the jobs of a batch
+pub fn batch_totals(jobs: &[BatchJobSummary]) -> BatchTotals {
+    let mut totals = BatchTotals {
+        jobs: jobs.len(),
+        ..BatchTotals::default()
+    };
+    let mut progress_sum = 0i64;
+    for job in jobs {
+        match job.status.as_str() {
+            "completed" => totals.completed_jobs += 1,
+            "failed" | "cancelled" => totals.failed_jobs += 1,
+            "running" => totals.running_jobs += 1,
+            _ => totals.pending_jobs += 1,
+        }
+        // Finished jobs count as done whatever their last recorded progress
+        progress_sum += if matches!(job.status.as_str(), "completed" | "failed" | "cancelled") {
+            100
+        } else {
+            i64::from(job.progress.clamp(0, 100))
+        };
+        if job.status == "completed" {
+            totals.total_records += i64::from(job.total_records.unwrap_or(0));
+            totals.matched_records += i64::from(job.matched_records);
+            totals.unmatched_records += i64::from(job.unmatched_records);
+        }
+    }
+    if !jobs.is_empty() {
+        totals.progress = i32::try_from(progress_sum / jobs.len() as i64).unwrap_or(100);
+    }
+    let compared = totals.matched_records + totals.unmatched_records;
+    if compared > 0 {
+        totals.match_rate = totals.matched_records as f64 / compared as f64;
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(status: &str, progress: i32, matched: i32, unmatched: i32) -> BatchJobSummary {
+        BatchJobSummary {
+            job_id: Uuid::new_v4(),
+            position: 0,
+            name: "Account".to_string(),
+            status: status.to_string(),
+            progress,
+            total_records: Some(matched + unmatched),
+            matched_records: matched,
+            unmatched_records: unmatched,
+            processing_time_ms: None,
+        }
+    }
+
+    #[test]
+    fn batch_status_follows_job_statuses() {
+        assert_eq!(derive_batch_status(&[]), "pending");
+        assert_eq!(derive_batch_status(&["pending", "pending"]), "pending");
+        assert_eq!(derive_batch_status(&["completed", "pending"]), "running");
+        assert_eq!(derive_batch_status(&["completed", "running"]), "running");
+        assert_eq!(derive_batch_status(&["completed", "completed"]), "completed");
+        assert_eq!(derive_batch_status(&["completed", "failed"]), "completed_with_errors");
+        assert_eq!(derive_batch_status(&["failed", "cancelled"]), "failed");
+    }
+
+    #[test]
+    fn totals_combine_completed_jobs() {
+        let jobs = vec![
+            job("completed", 100, 90, 10),
+            job("completed", 100, 60, 40),
+            job("running", 40, 5, 0),
+            job("failed", 10, 0, 0),
+        ];
+        let totals = batch_totals(&jobs);
+
+        assert_eq!(totals.jobs, 4);
+        assert_eq!(totals.completed_jobs, 2);
+        assert_eq!(totals.running_jobs, 1);
+        assert_eq!(totals.failed_jobs, 1);
+        // Counts of unfinished jobs are provisional and left out
+        assert_eq!(totals.total_records, 200);
+        assert_eq!(totals.matched_records, 150);
+        assert_eq!(totals.unmatched_records, 50);
+        assert!((totals.match_rate - 0.75).abs() < 1e-9);
+        assert_eq!(totals.progress, 85);
+    }
+}
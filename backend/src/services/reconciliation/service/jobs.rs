@@ -1,17 +1,25 @@
 //! Reconciliation job management operations
 
+use crate::database::Database;
 use crate::errors::{AppError, AppResult};
 use crate::models::schema::{reconciliation_jobs, reconciliation_results};
 use crate::models::{ReconciliationJob, UpdateReconciliationJob};
+use crate::services::audit::{AuditContext, AuditEvent};
 use crate::services::reconciliation::ReconciliationService;
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::Utc;
-use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
 use std::str::FromStr;
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::services::reconciliation::types::ReconciliationJobStatus;
-use crate::services::reconciliation::job_management::{JobProgress, JobStatus};
+use crate::services::reconciliation::types::{JobSettings, ReconciliationJobStatus};
+use crate::services::reconciliation::job_management::{JobProcessor, JobProgress, JobStatus};
+use crate::services::reconciliation::processing::{
+    load_data_source, process_data_sources_chunked, resolve_parallelism, ProcessingCheckpoint,
+};
+use crate::services::reconciliation::queue::JobQueue;
+use crate::services::reconciliation::processing_config::ChunkedProcessingConfig;
 
 /// Calculate estimated completion time
 fn calculate_estimated_completion(
@@ -92,6 +100,7 @@ pub async fn get_reconciliation_progress(
             unmatched_records: status.unmatched_records,
             current_phase: status.current_phase.clone(),
             estimated_completion: calculate_estimated_completion(service, &status),
+            blocking_stats: status.blocking_stats.clone(),
         })
     } else {
         // Get from database
@@ -118,6 +127,7 @@ pub async fn get_reconciliation_progress(
                     "unknown".to_string()
                 },
                 estimated_completion: None,
+                blocking_stats: None,
             })
         } else {
             Err(AppError::NotFound(format!(
@@ -225,21 +235,45 @@ pub async fn update_reconciliation_job(
 }
 
 /// Delete a reconciliation job and its results
+///
+/// With `audit`, the deleted job is recorded in the audit chain in the same
+/// transaction.
 pub async fn delete_reconciliation_job(
     service: &ReconciliationService,
     job_id: Uuid,
+    audit: Option<&AuditContext>,
 ) -> AppResult<()> {
-    let mut conn = service.db.get_connection()?;
-    diesel::delete(
-        reconciliation_results::table
-            .filter(reconciliation_results::job_id.eq(job_id)),
-    )
-    .execute(&mut conn)
-    .map_err(AppError::Database)?;
-    diesel::delete(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
-        .execute(&mut conn)
+    crate::database::transaction::with_transaction(service.db.get_pool(), |tx| {
+        let before = match audit {
+            Some(_) => reconciliation_jobs::table
+                .find(job_id)
+                .select(ReconciliationJob::as_select())
+                .first::<ReconciliationJob>(tx)
+                .optional()
+                .map_err(AppError::Database)?,
+            None => None,
+        };
+        diesel::delete(
+            reconciliation_results::table
+                .filter(reconciliation_results::job_id.eq(job_id)),
+        )
+        .execute(tx)
         .map_err(AppError::Database)?;
-    Ok(())
+        diesel::delete(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+            .execute(tx)
+            .map_err(AppError::Database)?;
+
+        if let Some(audit) = audit {
+            crate::services::audit::append(
+                tx,
+                audit,
+                AuditEvent::new("job.delete", "reconciliation_job", Some(job_id))
+                    .with_change(before.and_then(|job| serde_json::to_value(job).ok()), None),
+            )?;
+        }
+        Ok(())
+    })
+    .await
 }
 
 /// List reconciliation jobs for a project
@@ -260,10 +294,17 @@ use std::sync::Arc;
 use tokio;
 use log;
 
+/// Confidence threshold for jobs created without one
+const DEFAULT_CONFIDENCE_THRESHOLD: f64 = 0.8;
+
+/// How often a running job's in-memory status is copied to the processor and the job row
+const PROGRESS_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Starts a reconciliation job for processing.
 ///
 /// This function initiates the reconciliation process for the specified job.
-/// The job will be queued and processed asynchronously.
+/// The job is put on the durable job queue and run by a queue worker; its
+/// progress and outcome are recorded on the job row.
 ///
 /// # Arguments
 /// * `service` - Reference to the reconciliation service
@@ -288,33 +329,184 @@ pub async fn start_reconciliation_job(
     service: &ReconciliationService,
     job_id: Uuid,
 ) -> AppResult<()> {
-    // Start the job (returns JobHandle, not a Result)
-    let _job_handle = service.job_processor.start_job(job_id).await;
-    let timeout_duration = service.job_processor.get_timeout_duration();
-    
-    // Spawn a background task to monitor for timeout with enhanced error handling
-    let processor = Arc::clone(&service.job_processor);
-    let job_id_clone = job_id;
-    tokio::spawn(async move {
-        tokio::time::sleep(timeout_duration).await;
-        
-        // Check if job is still active after timeout
-        let stuck_jobs = processor.check_stuck_jobs().await;
-        if stuck_jobs.contains(&job_id_clone) {
-            log::warn!(
-                "Job {} exceeded timeout of {} seconds, forcing timeout",
-                job_id_clone,
-                timeout_duration.as_secs()
-            );
-            if let Err(e) = processor.timeout_job(job_id_clone).await {
-                log::error!(
-                    "Failed to timeout job {}: {}. This may indicate a system issue.",
-                    job_id_clone,
-                    e
-                );
-            }
-        }
-    });
-    
+    ensure_job_startable(&service.db, &service.job_processor, job_id).await?;
+
+    // A queue worker on any replica picks the job up
+    JobQueue::new(service.db.clone()).enqueue(job_id).await?;
+    Ok(())
+}
+
+/// Reject a start request for a job that does not exist or is already running
+pub async fn ensure_job_startable(
+    db: &Database,
+    processor: &JobProcessor,
+    job_id: Uuid,
+) -> AppResult<()> {
+    if processor.get_job_status(&job_id).await.is_some() {
+        return Err(AppError::Conflict(format!("Job {} is already running", job_id)));
+    }
+    let mut conn = db.get_connection()?;
+    let status = reconciliation_jobs::table
+        .filter(reconciliation_jobs::id.eq(job_id))
+        .select(reconciliation_jobs::status)
+        .first::<String>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound(format!("Reconciliation job {} not found", job_id)))?;
+    if status == "running" {
+        return Err(AppError::Conflict(format!("Job {} is already running", job_id)));
+    }
     Ok(())
 }
+
+/// Run a job once: `resume_from` continues an earlier attempt and
+/// `checkpoint_sender` receives progress a later attempt can resume from.
+/// The job row is marked `running` and then `completed`, but not `failed`;
+/// callers decide what a failure means.
+pub(crate) async fn execute_reconciliation_job(
+    db: &Database,
+    processor: &Arc<JobProcessor>,
+    job_id: Uuid,
+    resume_from: Option<ProcessingCheckpoint>,
+    checkpoint_sender: Option<tokio::sync::mpsc::UnboundedSender<ProcessingCheckpoint>>,
+) -> AppResult<()> {
+    let job = {
+        let mut conn = db.get_connection()?;
+        reconciliation_jobs::table
+            .filter(reconciliation_jobs::id.eq(job_id))
+            .first::<ReconciliationJob>(&mut conn)
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::NotFound(format!("Reconciliation job {} not found", job_id)))?
+    };
+    let settings: JobSettings = match job.settings {
+        Some(settings) => serde_json::from_value(settings).map_err(|e| {
+            AppError::Validation(format!("Invalid settings for job {}: {}", job_id, e))
+        })?,
+        None => JobSettings::default(),
+    };
+    let (Some(source_a_id), Some(source_b_id)) = (settings.source_a_id, settings.source_b_id) else {
+        return Err(AppError::Validation(format!(
+            "Job {} has no data sources configured",
+            job_id
+        )));
+    };
+    let source_a = load_data_source(db, source_a_id)?;
+    let source_b = load_data_source(db, source_b_id)?;
+    let confidence_threshold = job
+        .confidence_threshold
+        .as_ref()
+        .and_then(ToPrimitive::to_f64)
+        .unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
+
+    let started_at = Utc::now();
+    {
+        let mut conn = db.get_connection()?;
+        diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+            .set((
+                reconciliation_jobs::status.eq("running"),
+                reconciliation_jobs::started_at.eq(Some(started_at)),
+                reconciliation_jobs::completed_at.eq(None::<chrono::DateTime<Utc>>),
+                reconciliation_jobs::progress.eq(Some(0)),
+                reconciliation_jobs::updated_at.eq(started_at),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::Database)?;
+    }
+    processor.start_job(job_id).await;
+
+    let status = Arc::new(RwLock::new(JobStatus::new()));
+    // Held in a JoinSet so the sync task is aborted even if this future is dropped
+    let mut sync = tokio::task::JoinSet::new();
+    sync.spawn(sync_job_progress(
+        db.clone(),
+        Arc::clone(processor),
+        job_id,
+        Arc::clone(&status),
+    ));
+    let config = ChunkedProcessingConfig {
+        db: db.clone(),
+        job_id,
+        source_a,
+        source_b,
+        matching_rules: settings.matching_rules,
+        blocking: settings.blocking,
+        assignment: settings.assignment,
+        aggregation: settings.aggregation,
+        parallelism: resolve_parallelism(settings.parallelism),
+        confidence_threshold,
+        chunk_size: processor.chunk_size,
+        progress_sender: None,
+        status: Arc::clone(&status),
+        resume_from,
+        checkpoint_sender,
+    };
+    let timer = std::time::Instant::now();
+    let processed = process_data_sources_chunked(config).await;
+    sync.abort_all();
+    let summary = processed?;
+
+    let processing_time_ms = i32::try_from(timer.elapsed().as_millis()).unwrap_or(i32::MAX);
+    let completed_at = Utc::now();
+    let mut conn = db.get_connection()?;
+    diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+        .set((
+            reconciliation_jobs::status.eq("completed"),
+            reconciliation_jobs::completed_at.eq(Some(completed_at)),
+            reconciliation_jobs::progress.eq(Some(100)),
+            reconciliation_jobs::total_records.eq(Some(summary.total_records as i32)),
+            reconciliation_jobs::processed_records.eq(Some(summary.total_records as i32)),
+            reconciliation_jobs::matched_records.eq(Some(summary.matched_records as i32)),
+            reconciliation_jobs::unmatched_records.eq(Some(summary.unmatched_records as i32)),
+            reconciliation_jobs::processing_time_ms.eq(Some(processing_time_ms)),
+            reconciliation_jobs::updated_at.eq(completed_at),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::Database)?;
+
+    log::info!(
+        "Reconciliation job {} completed in {} ms: {} matched, {} unmatched, {} result rows",
+        job_id,
+        processing_time_ms,
+        summary.matched_records,
+        summary.unmatched_records,
+        summary.results_written
+    );
+    Ok(())
+}
+
+/// Periodically publish a running job's status to the processor and the job row
+async fn sync_job_progress(
+    db: Database,
+    processor: Arc<JobProcessor>,
+    job_id: Uuid,
+    status: Arc<RwLock<JobStatus>>,
+) {
+    let mut interval = tokio::time::interval(PROGRESS_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        let snapshot = status.read().await.clone();
+
+        // A stopped or timed-out job is no longer tracked; don't re-register it
+        if let Some(entry) = processor.active_jobs.write().await.get_mut(&job_id) {
+            *entry = snapshot.clone();
+        }
+
+        let persisted = db.get_connection().and_then(|mut conn| {
+            diesel::update(reconciliation_jobs::table.filter(reconciliation_jobs::id.eq(job_id)))
+                .set((
+                    reconciliation_jobs::progress.eq(Some(snapshot.progress)),
+                    reconciliation_jobs::total_records.eq(snapshot.total_records),
+                    reconciliation_jobs::processed_records.eq(Some(snapshot.processed_records)),
+                    reconciliation_jobs::matched_records.eq(Some(snapshot.matched_records)),
+                    reconciliation_jobs::unmatched_records.eq(Some(snapshot.unmatched_records)),
+                    reconciliation_jobs::updated_at.eq(Utc::now()),
+                ))
+                .execute(&mut conn)
+                .map_err(AppError::Database)
+        });
+        if let Err(e) = persisted {
+            log::warn!("Failed to persist progress for job {}: {}", job_id, e);
+        }
+    }
+}
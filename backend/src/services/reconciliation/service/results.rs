@@ -1,13 +1,14 @@
 //! Reconciliation results operations
 
 use crate::errors::{AppError, AppResult};
-use crate::models::schema::reconciliation_results;
+use crate::models::schema::{reconciliation_jobs, reconciliation_records, reconciliation_results};
 use crate::models::ReconciliationResult;
+use crate::services::audit::{AuditContext, AuditEvent};
+use crate::services::reconciliation::approvals::{self, MatchProposal, MatchStatus, Proposed};
 use crate::services::reconciliation::ReconciliationService;
-use bigdecimal::BigDecimal;
 use chrono::Utc;
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-use std::str::FromStr;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use serde_json::json;
 use uuid::Uuid;
 
 use super::super::types::ReconciliationResultDetail;
@@ -25,6 +26,8 @@ pub struct MatchResolve {
 pub struct BatchApprovalResult {
     pub approved: i32,
     pub rejected: i32,
+    /// Approvals submitted for a second approver instead of applied
+    pub pending_approval: i32,
     pub errors: Option<Vec<String>>,
 }
 
@@ -35,6 +38,9 @@ pub struct UpdatedMatch {
     pub status: String,
     pub confidence_score: Option<f64>,
     pub reviewed_by: Option<String>,
+    /// Approval request the change awaits, if the policy required one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_id: Option<Uuid>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
@@ -78,27 +84,31 @@ pub async fn get_reconciliation_results(
 }
 
 /// Batch approve or reject matches within a single transaction
+///
+/// Approvals the project's approval policy covers are submitted for a second
+/// approver instead of being applied (see `approvals`). Each status change is
+/// recorded in the audit trail in the same transaction, attributed to
+/// `audit`'s actor, who is the preparer of any submitted approval.
 pub async fn batch_approve_matches(
     service: &ReconciliationService,
-    _user_id: Uuid,
+    audit: &AuditContext,
     resolves: Vec<MatchResolve>,
 ) -> AppResult<BatchApprovalResult> {
+    let preparer = audit
+        .actor
+        .ok_or_else(|| AppError::Unauthorized("Match decisions need an authenticated user".to_string()))?;
+
     crate::database::transaction::with_transaction(service.db.get_pool(), |tx| {
         let mut approved = 0i32;
         let mut rejected = 0i32;
+        let mut pending_approval = 0i32;
         let mut errors: Vec<String> = Vec::new();
 
         for item in &resolves {
             let action = item.action.to_lowercase();
             let status_val = match action.as_str() {
-                "approve" => {
-                    approved += 1;
-                    "approved"
-                }
-                "reject" => {
-                    rejected += 1;
-                    "rejected"
-                }
+                "approve" => MatchStatus::Approved,
+                "reject" => MatchStatus::Rejected,
                 _ => {
                     errors.push(format!(
                         "Invalid action '{}' for match {}",
@@ -108,25 +118,50 @@ pub async fn batch_approve_matches(
                 }
             };
 
-            let rows = diesel::update(
-                reconciliation_results::table.filter(reconciliation_results::id.eq(item.match_id)),
-            )
-            .set((
-                reconciliation_results::status.eq(status_val),
-                reconciliation_results::updated_at.eq(Utc::now()),
-                reconciliation_results::notes.eq(item.notes.clone()),
-            ))
-            .execute(tx)
-            .map_err(AppError::Database)?;
-
-            if rows == 0 {
+            let Some((current, project_id)) = locked_match(tx, item.match_id)? else {
                 errors.push(format!("Match {} not found", item.match_id));
+                continue;
+            };
+
+            let proposal = MatchProposal {
+                notes: item.notes.clone(),
+                ..MatchProposal::new(status_val)
+            };
+            match approvals::propose(tx, audit, preparer, project_id, &current, proposal) {
+                Ok(Proposed::PendingApproval(_)) => {
+                    pending_approval += 1;
+                    continue;
+                }
+                Ok(Proposed::Applied) => {}
+                Err(AppError::Conflict(message)) => {
+                    errors.push(message);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            crate::services::audit::append(
+                tx,
+                audit,
+                AuditEvent::new(format!("match.{}", action), "reconciliation_match", Some(item.match_id))
+                    .with_change(
+                        Some(json!({ "status": current.status, "notes": current.notes })),
+                        Some(json!({ "status": status_val.as_str(), "notes": item.notes })),
+                    )
+                    .with_details(json!({ "job_id": current.job_id })),
+            )?;
+
+            if status_val == MatchStatus::Approved {
+                approved += 1;
+            } else {
+                rejected += 1;
             }
         }
 
         Ok(BatchApprovalResult {
             approved,
             rejected,
+            pending_approval,
             errors: if errors.is_empty() {
                 None
             } else {
@@ -137,59 +172,128 @@ pub async fn batch_approve_matches(
     .await
 }
 
+/// A match, locked for update, with its job's project
+fn locked_match(
+    conn: &mut diesel::PgConnection,
+    match_id: Uuid,
+) -> AppResult<Option<(ReconciliationResult, Uuid)>> {
+    let current = reconciliation_results::table
+        .find(match_id)
+        .for_update()
+        .first::<ReconciliationResult>(conn)
+        .optional()
+        .map_err(AppError::Database)?;
+    let Some(current) = current else {
+        return Ok(None);
+    };
+    let project_id = reconciliation_jobs::table
+        .find(current.job_id)
+        .select(reconciliation_jobs::project_id)
+        .first::<Uuid>(conn)
+        .map_err(AppError::Database)?;
+    Ok(Some((current, project_id)))
+}
+
+/// Change to an individual match, from the match update endpoint
+#[derive(Debug, Clone, Default)]
+pub struct MatchUpdate {
+    /// Keeps the current status when `None`
+    pub status: Option<MatchStatus>,
+    pub confidence_score: Option<f64>,
+    /// Link the match to this record by hand
+    pub record_b_id: Option<Uuid>,
+    pub notes: Option<String>,
+}
+
 /// Update individual reconciliation match
+///
+/// `user_id` is recorded as the reviewer. Changes that accept the match go
+/// through the project's approval policy like batch approvals do.
 pub async fn update_match(
     service: &ReconciliationService,
-    _user_id: Uuid,
+    audit: &AuditContext,
+    user_id: Uuid,
     match_id: Uuid,
-    status: Option<&str>,
-    confidence_score: Option<f64>,
-    reviewed_by: Option<&str>,
+    update: MatchUpdate,
 ) -> AppResult<UpdatedMatch> {
+    if update
+        .confidence_score
+        .is_some_and(|score| !(0.0..=1.0).contains(&score))
+    {
+        return Err(AppError::Validation(
+            "Confidence score must be between 0 and 1".to_string(),
+        ));
+    }
+    if update.status == Some(MatchStatus::PendingApproval) {
+        return Err(AppError::Validation(format!(
+            "Status {} is set by submitting a match for approval",
+            approvals::PENDING_APPROVAL
+        )));
+    }
+
     let mut conn = service.db.get_connection()?;
+    let (updated_match, approval) = conn.transaction::<_, AppError, _>(|tx| {
+        let (current, project_id) = locked_match(tx, match_id)?
+            .ok_or_else(|| AppError::NotFound("Match not found".to_string()))?;
 
-    // Build the update query dynamically
-    // Convert parameters to proper types
-    let status_val = status.map(Some).unwrap_or(None);
-    let confidence_val = if let Some(confidence) = confidence_score {
-        // Convert f64 to BigDecimal using string conversion
-        Some(
-            BigDecimal::from_str(&confidence.to_string())
-                .map_err(|_| AppError::Validation("Invalid confidence score".to_string()))?,
-        )
-    } else {
-        None
-    };
-    let reviewer_val = if let Some(reviewer) = reviewed_by {
-        Some(
-            Uuid::parse_str(reviewer)
-                .map_err(|_| AppError::Validation("Invalid reviewer UUID".to_string()))?,
-        )
-    } else {
-        None
-    };
+        if let Some(record_b_id) = update.record_b_id {
+            let in_project = reconciliation_records::table
+                .find(record_b_id)
+                .select(reconciliation_records::project_id)
+                .first::<Uuid>(tx)
+                .optional()
+                .map_err(AppError::Database)?
+                == Some(project_id);
+            if !in_project || record_b_id == current.record_a_id {
+                return Err(AppError::Validation(format!(
+                    "Record {} can't be matched with this record",
+                    record_b_id
+                )));
+            }
+        }
 
-    let rows = diesel::update(
-        reconciliation_results::table.filter(reconciliation_results::id.eq(match_id)),
-    )
-    .set((
-        reconciliation_results::status.eq(status_val),
-        reconciliation_results::confidence_score.eq(confidence_val),
-        reconciliation_results::reviewed_by.eq(reviewer_val),
-        reconciliation_results::updated_at.eq(Utc::now()),
-    ))
-    .execute(&mut conn)
-    .map_err(AppError::Database)?;
-
-    if rows == 0 {
-        return Err(AppError::NotFound("Match not found".to_string()));
-    }
+        let proposal = MatchProposal {
+            status: update
+                .status
+                .or_else(|| MatchStatus::of(&current))
+                .unwrap_or(MatchStatus::Pending),
+            record_b_id: update.record_b_id,
+            confidence_score: update.confidence_score,
+            notes: update.notes.clone(),
+        };
+        let approval = match approvals::propose(tx, audit, user_id, project_id, &current, proposal.clone())? {
+            Proposed::Applied => {
+                crate::services::audit::append(
+                    tx,
+                    audit,
+                    AuditEvent::new("match.update", "reconciliation_match", Some(match_id))
+                        .with_change(
+                            Some(json!({
+                                "status": current.status,
+                                "record_b_id": current.record_b_id,
+                                "match_type": current.match_type,
+                                "notes": current.notes,
+                            })),
+                            Some(json!({
+                                "status": proposal.status.as_str(),
+                                "record_b_id": proposal.record_b_id.or(current.record_b_id),
+                                "match_type": if proposal.record_b_id.is_some() { "manual" } else { current.match_type.as_str() },
+                                "notes": proposal.notes.clone().or(current.notes.clone()),
+                            })),
+                        )
+                        .with_details(json!({ "job_id": current.job_id })),
+                )?;
+                None
+            }
+            Proposed::PendingApproval(approval) => Some(approval.id),
+        };
 
-    // Fetch the updated match
-    let updated_match: ReconciliationResult = reconciliation_results::table
-        .find(match_id)
-        .first(&mut conn)
-        .map_err(AppError::Database)?;
+        let updated_match: ReconciliationResult = reconciliation_results::table
+            .find(match_id)
+            .first(tx)
+            .map_err(AppError::Database)?;
+        Ok((updated_match, approval))
+    })?;
 
     Ok(UpdatedMatch {
         id: updated_match.id,
@@ -200,6 +304,7 @@ pub async fn update_match(
             .confidence_score
             .map(|c| c.to_string().parse::<f64>().unwrap_or(0.0)),
         reviewed_by: updated_match.reviewed_by.map(|u| u.to_string()),
+        approval_id: approval,
         updated_at: updated_match.updated_at.unwrap_or(updated_match.created_at),
     })
 }
@@ -0,0 +1,21 @@
+//! Fixtures shared by the reconciliation modules' tests
+
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::types::ReconciliationRecord;
+
+/// A record with the fields of a JSON object
+pub fn record(fields: Value) -> ReconciliationRecord {
+    let fields = fields
+        .as_object()
+        .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    ReconciliationRecord {
+        id: Uuid::new_v4(),
+        source_id: "test".to_string(),
+        fields,
+        metadata: HashMap::new(),
+    }
+}
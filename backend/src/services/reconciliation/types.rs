@@ -1,11 +1,16 @@
 //! Types and data structures for reconciliation service
 
+use bigdecimal::BigDecimal;
 use chrono::DateTime;
+use chrono::NaiveDate;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::aggregate::AggregateConfig;
+use super::assignment::AssignmentMode;
+use super::blocking::BlockingConfig;
 use crate::models::MatchType;
 
 /// Reconciliation record
@@ -52,9 +57,13 @@ pub enum DifferenceType {
 pub enum FuzzyAlgorithmType {
     Levenshtein,
     JaroWinkler,
+    /// Overlap of word token sets
     Jaccard,
+    /// TF-IDF weighted cosine over word tokens; rare words count for more
     Cosine,
+    /// Words compared by Soundex code
     Soundex,
+    /// Words compared by Double Metaphone codes
     Metaphone,
 }
 
@@ -65,6 +74,67 @@ pub struct MatchingRule {
     pub rule_type: MatchingRuleType,
     pub weight: f64,
     pub threshold: f64,
+    /// "Must match" rule: a pair failing this rule's threshold is never matched
+    #[serde(default)]
+    pub required: bool,
+    /// Rule-type specific parameters (tolerances, date windows)
+    #[serde(default)]
+    pub options: RuleOptions,
+}
+
+/// Optional parameters for a matching rule
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleOptions {
+    /// Allowed amount difference for `NumericRange` rules; exact when absent
+    #[serde(default)]
+    pub tolerance: Option<AmountTolerance>,
+    /// Allowed date distance for `DateRange` rules; same day when absent
+    #[serde(default)]
+    pub date_window: Option<DateWindow>,
+    /// Algorithm for `Fuzzy` rules; Levenshtein when absent
+    #[serde(default)]
+    pub algorithm: Option<FuzzyAlgorithmType>,
+    /// Clean-up applied to both values before comparing
+    #[serde(default)]
+    pub normalization: Option<TextNormalization>,
+}
+
+/// Text normalisation options for a matching rule
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextNormalization {
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Drop dots and apostrophes, turn other punctuation into spaces
+    #[serde(default)]
+    pub strip_punctuation: bool,
+    /// Trim and collapse runs of whitespace
+    #[serde(default)]
+    pub collapse_whitespace: bool,
+    /// Remove trailing company forms such as "Ltd", "Inc", "GmbH"
+    #[serde(default)]
+    pub strip_legal_suffixes: bool,
+}
+
+/// Amount tolerance for numeric matching
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum AmountTolerance {
+    /// Maximum absolute difference, e.g. 0.05
+    Absolute(BigDecimal),
+    /// Maximum difference as a percentage of the larger amount, e.g. 1.5
+    Percentage(BigDecimal),
+}
+
+/// Date window for date matching
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateWindow {
+    /// Maximum distance in days (either direction)
+    pub days: i64,
+    /// Count only weekdays that are not in `holidays`
+    #[serde(default)]
+    pub business_days: bool,
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
 }
 
 /// Matching rule types
@@ -86,9 +156,56 @@ pub struct CreateReconciliationJobRequest {
     pub source_a_id: Uuid,
     pub source_b_id: Uuid,
     pub matching_rules: Vec<MatchingRule>,
+    /// Candidate generation before scoring; empty compares every pair
+    #[serde(default)]
+    pub blocking: BlockingConfig,
+    /// How candidates are resolved into matches; defaults to best match per record
+    #[serde(default)]
+    pub assignment: AssignmentMode,
+    /// Split/aggregate matching over records left unmatched; off when absent
+    #[serde(default)]
+    pub aggregation: Option<AggregateConfig>,
+    /// Chunks scored concurrently; defaults to the number of CPU cores
+    #[serde(default)]
+    pub parallelism: Option<usize>,
     pub confidence_threshold: f64,
 }
 
+/// Job configuration persisted in `reconciliation_jobs.settings`
+///
+/// Every key is optional so settings written before a key existed still load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobSettings {
+    #[serde(default)]
+    pub source_a_id: Option<Uuid>,
+    #[serde(default)]
+    pub source_b_id: Option<Uuid>,
+    #[serde(default)]
+    pub matching_rules: Vec<MatchingRule>,
+    #[serde(default)]
+    pub blocking: BlockingConfig,
+    #[serde(default)]
+    pub assignment: AssignmentMode,
+    #[serde(default)]
+    pub aggregation: Option<AggregateConfig>,
+    #[serde(default)]
+    pub parallelism: Option<usize>,
+}
+
+impl JobSettings {
+    pub fn from_request(request: &CreateReconciliationJobRequest) -> Self {
+        Self {
+            source_a_id: Some(request.source_a_id),
+            source_b_id: Some(request.source_b_id),
+            matching_rules: request.matching_rules.clone(),
+            blocking: request.blocking.clone(),
+            assignment: request.assignment,
+            aggregation: request.aggregation.clone(),
+            parallelism: request.parallelism,
+        }
+    }
+}
+
 /// Reconciliation job status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReconciliationJobStatus {
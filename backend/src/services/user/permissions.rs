@@ -101,7 +101,7 @@ impl PermissionService {
 
     /// Check if role is valid
     pub fn is_valid_role(&self, role: &str) -> bool {
-        matches!(role, "user" | "admin" | "manager" | "viewer")
+        matches!(role, "user" | "admin" | "manager" | "viewer" | "service")
     }
 
     /// Get permissions for a role
@@ -1,7 +1,16 @@
 //! Business rules validation
+//!
+//! Besides the per-entity rules this holds the record-level rules run over an
+//! ingestion job's records: built-in checks configured as `RecordRuleSpec`s,
+//! or any other `RecordRule`.
 
 use crate::errors::{AppError, AppResult};
-use std::collections::HashMap;
+use crate::models::ReconciliationRecord;
+use chrono::{Duration, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
 
 pub struct BusinessRulesValidator;
 
@@ -32,7 +41,7 @@ impl BusinessRulesValidator {
     ) -> AppResult<()> {
         if let Some(role) = data.get("role") {
             if let Some(role_str) = role.as_str() {
-                let valid_roles = ["admin", "user", "analyst", "viewer"];
+                let valid_roles = ["admin", "user", "analyst", "viewer", "service"];
                 if !valid_roles.contains(&role_str) {
                     return Err(AppError::Validation(format!(
                         "Invalid role: {}. Valid roles are: {}",
@@ -100,3 +109,437 @@ impl BusinessRulesValidator {
         Ok(())
     }
 }
+
+/// How a failed record rule counts against a job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+/// A check run over each record of an ingestion job
+///
+/// Rules see records one at a time, in a stable order, and may keep state
+/// between them, as the uniqueness check does.
+pub trait RecordRule: Send {
+    /// Name the rule's findings are grouped under
+    fn name(&self) -> &str;
+    fn severity(&self) -> Severity;
+    /// Why the record fails the rule, or `None` if it passes
+    fn check(&mut self, record: &ReconciliationRecord) -> Option<String>;
+}
+
+/// A configured record rule, written as `{"type": "required", ...}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordRuleSpec {
+    /// Name to group findings under; derived from the rule when absent
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub check: RecordCheck,
+}
+
+/// The built-in record checks
+///
+/// Fields are the record columns (`external_id`, `amount`,
+/// `transaction_date`, `description`) or keys of the record's source data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordCheck {
+    Required {
+        fields: Vec<String>,
+    },
+    Pattern {
+        field: String,
+        pattern: String,
+    },
+    Range {
+        field: String,
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+    AllowedValues {
+        field: String,
+        values: Vec<String>,
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    Unique {
+        #[serde(default = "default_unique_field")]
+        field: String,
+    },
+    /// The value must appear in another ingestion job's or data source's records
+    Reference {
+        field: String,
+        #[serde(default)]
+        job_id: Option<Uuid>,
+        #[serde(default)]
+        data_source_id: Option<Uuid>,
+        #[serde(default = "default_unique_field")]
+        target_field: String,
+    },
+    /// Dates must lie in a window; with no bounds set, not before 1900 and
+    /// not in the future
+    DateSanity {
+        #[serde(default = "default_date_field")]
+        field: String,
+        #[serde(default)]
+        not_before: Option<NaiveDate>,
+        #[serde(default)]
+        not_after: Option<NaiveDate>,
+        /// Days after today still accepted
+        #[serde(default)]
+        max_future_days: Option<i64>,
+        /// Days before today still accepted
+        #[serde(default)]
+        max_age_days: Option<i64>,
+    },
+}
+
+fn default_unique_field() -> String {
+    "external_id".to_string()
+}
+
+fn default_date_field() -> String {
+    "transaction_date".to_string()
+}
+
+impl RecordRuleSpec {
+    /// Rules run when a job's validation doesn't configure any
+    pub fn defaults() -> Vec<Self> {
+        let rule = |severity, check| RecordRuleSpec {
+            name: None,
+            severity,
+            check,
+        };
+        vec![
+            rule(
+                Severity::Error,
+                RecordCheck::Required {
+                    fields: vec!["amount".to_string()],
+                },
+            ),
+            rule(
+                Severity::Error,
+                RecordCheck::Unique {
+                    field: default_unique_field(),
+                },
+            ),
+            rule(
+                Severity::Warning,
+                RecordCheck::DateSanity {
+                    field: default_date_field(),
+                    not_before: None,
+                    not_after: None,
+                    max_future_days: None,
+                    max_age_days: None,
+                },
+            ),
+        ]
+    }
+
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.check {
+            RecordCheck::Required { fields } => format!("required:{}", fields.join(",")),
+            RecordCheck::Pattern { field, .. } => format!("pattern:{}", field),
+            RecordCheck::Range { field, .. } => format!("range:{}", field),
+            RecordCheck::AllowedValues { field, .. } => format!("allowed_values:{}", field),
+            RecordCheck::Unique { field } => format!("unique:{}", field),
+            RecordCheck::Reference { field, .. } => format!("reference:{}", field),
+            RecordCheck::DateSanity { field, .. } => format!("date_sanity:{}", field),
+        }
+    }
+
+    /// Build the rule; a reference check needs the values it may refer to
+    pub fn build(
+        &self,
+        today: NaiveDate,
+        reference_values: Option<HashSet<String>>,
+    ) -> Result<Box<dyn RecordRule>, String> {
+        let name = self.display_name();
+        let check = match &self.check {
+            RecordCheck::Required { fields } => {
+                if fields.is_empty() {
+                    return Err(format!("Rule '{}' lists no fields", name));
+                }
+                Check::Required(fields.clone())
+            }
+            RecordCheck::Pattern { field, pattern } => Check::Pattern(
+                field.clone(),
+                Regex::new(pattern).map_err(|e| format!("Rule '{}' has an invalid pattern: {}", name, e))?,
+            ),
+            RecordCheck::Range { field, min, max } => {
+                if min.is_none() && max.is_none() {
+                    return Err(format!("Rule '{}' needs a min or a max", name));
+                }
+                Check::Range(field.clone(), *min, *max)
+            }
+            RecordCheck::AllowedValues {
+                field,
+                values,
+                case_sensitive,
+            } => {
+                let fold = |value: &String| if *case_sensitive { value.clone() } else { value.to_lowercase() };
+                Check::Allowed(field.clone(), values.iter().map(fold).collect(), *case_sensitive)
+            }
+            RecordCheck::Unique { field } => Check::Unique(field.clone(), HashMap::new()),
+            RecordCheck::Reference { field, .. } => Check::Reference(
+                field.clone(),
+                reference_values.ok_or_else(|| format!("Rule '{}' has no values to refer to", name))?,
+            ),
+            RecordCheck::DateSanity {
+                field,
+                not_before,
+                not_after,
+                max_future_days,
+                max_age_days,
+            } => {
+                let unbounded =
+                    not_before.is_none() && not_after.is_none() && max_future_days.is_none() && max_age_days.is_none();
+                let earliest = [*not_before, max_age_days.and_then(|days| today.checked_sub_signed(Duration::days(days)))]
+                    .into_iter()
+                    .flatten()
+                    .max()
+                    .or_else(|| unbounded.then(|| NaiveDate::from_ymd_opt(1900, 1, 1)).flatten());
+                let latest = [*not_after, max_future_days.and_then(|days| today.checked_add_signed(Duration::days(days)))]
+                    .into_iter()
+                    .flatten()
+                    .min()
+                    .or_else(|| unbounded.then_some(today));
+                Check::Dates(field.clone(), earliest, latest)
+            }
+        };
+        Ok(Box::new(BuiltinRule {
+            name,
+            severity: self.severity,
+            check,
+        }))
+    }
+}
+
+/// A built-in check, ready to run
+enum Check {
+    Required(Vec<String>),
+    Pattern(String, Regex),
+    Range(String, Option<f64>, Option<f64>),
+    /// Field, allowed values (lowercased unless case-sensitive), case-sensitive
+    Allowed(String, HashSet<String>, bool),
+    /// Field and the row each value was first seen in
+    Unique(String, HashMap<String, Option<u64>>),
+    Reference(String, HashSet<String>),
+    Dates(String, Option<NaiveDate>, Option<NaiveDate>),
+}
+
+struct BuiltinRule {
+    name: String,
+    severity: Severity,
+    check: Check,
+}
+
+/// A record column, or else a source data key, as a JSON value
+pub fn record_field(record: &ReconciliationRecord, field: &str) -> Option<serde_json::Value> {
+    let value = match field {
+        "external_id" => record.external_id.clone().map(serde_json::Value::String),
+        "amount" => record.amount.map(serde_json::Value::from),
+        "transaction_date" => record
+            .transaction_date
+            .map(|date| serde_json::Value::String(date.format("%Y-%m-%d").to_string())),
+        "description" => record.description.clone().map(serde_json::Value::String),
+        _ => record.source_data.get(field).cloned(),
+    };
+    value.filter(|value| match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::String(text) => !text.trim().is_empty(),
+        _ => true,
+    })
+}
+
+/// A field as text, for matching against patterns and value lists
+pub fn record_text(record: &ReconciliationRecord, field: &str) -> Option<String> {
+    record_field(record, field).map(|value| match value {
+        serde_json::Value::String(text) => text.trim().to_string(),
+        other => other.to_string(),
+    })
+}
+
+/// The file row a record was ingested from
+pub fn record_row(record: &ReconciliationRecord) -> Option<u64> {
+    record.audit_trail.get("ingested_from_row").and_then(|row| row.as_u64())
+}
+
+impl RecordRule for BuiltinRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&mut self, record: &ReconciliationRecord) -> Option<String> {
+        match &mut self.check {
+            Check::Required(fields) => {
+                let missing: Vec<&str> = fields
+                    .iter()
+                    .filter(|field| record_field(record, field).is_none())
+                    .map(String::as_str)
+                    .collect();
+                (!missing.is_empty()).then(|| format!("Missing {}", missing.join(", ")))
+            }
+            Check::Pattern(field, regex) => {
+                let text = record_text(record, field)?;
+                (!regex.is_match(&text)).then(|| format!("{} '{}' doesn't match {}", field, text, regex.as_str()))
+            }
+            Check::Range(field, min, max) => {
+                let value = record_field(record, field)?;
+                let number = value
+                    .as_f64()
+                    .or_else(|| value.as_str().and_then(|text| text.trim().parse().ok()));
+                let Some(number) = number else {
+                    return Some(format!("{} {} is not a number", field, value));
+                };
+                if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                    let bound = |bound: &Option<f64>| bound.map_or("-".to_string(), |bound| bound.to_string());
+                    return Some(format!("{} {} is outside {}..{}", field, number, bound(min), bound(max)));
+                }
+                None
+            }
+            Check::Allowed(field, values, case_sensitive) => {
+                let text = record_text(record, field)?;
+                let key = if *case_sensitive { text.clone() } else { text.to_lowercase() };
+                (!values.contains(&key)).then(|| format!("{} '{}' is not an allowed value", field, text))
+            }
+            Check::Unique(field, seen) => {
+                let text = record_text(record, field)?;
+                let row = record_row(record);
+                match seen.get(&text) {
+                    Some(first) => Some(match first {
+                        Some(first) => format!("Duplicate {} '{}', first seen in row {}", field, text, first),
+                        None => format!("Duplicate {} '{}'", field, text),
+                    }),
+                    None => {
+                        seen.insert(text, row);
+                        None
+                    }
+                }
+            }
+            Check::Reference(field, values) => {
+                let text = record_text(record, field)?;
+                (!values.contains(&text)).then(|| format!("{} '{}' has no matching record", field, text))
+            }
+            Check::Dates(field, earliest, latest) => {
+                let text = record_text(record, field)?;
+                let Some(date) = crate::services::parsing::columns::parse_date(&text, None) else {
+                    return Some(format!("{} '{}' is not a date", field, text));
+                };
+                if earliest.is_some_and(|earliest| date < earliest) {
+                    return Some(format!("{} {} is too far in the past", field, date));
+                }
+                if latest.is_some_and(|latest| date > latest) {
+                    return Some(format!("{} {} is too far in the future", field, date));
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(row: u64, external_id: Option<&str>, amount: Option<f64>, date: Option<&str>, data: serde_json::Value) -> ReconciliationRecord {
+        ReconciliationRecord {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            ingestion_job_id: Uuid::nil(),
+            external_id: external_id.map(str::to_string),
+            status: "pending".to_string(),
+            amount,
+            transaction_date: date.and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+            description: None,
+            source_data: data,
+            matching_results: json!({}),
+            confidence: None,
+            audit_trail: json!({ "ingested_from_row": row }),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn run(rules: serde_json::Value, records: &[ReconciliationRecord], references: Option<HashSet<String>>) -> Vec<(String, String)> {
+        let specs: Vec<RecordRuleSpec> = serde_json::from_value(rules).unwrap_or_else(|e| panic!("{}", e));
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap_or_else(|| panic!("bad date"));
+        let mut rules: Vec<Box<dyn RecordRule>> = specs
+            .iter()
+            .map(|spec| spec.build(today, references.clone()).unwrap_or_else(|e| panic!("{}", e)))
+            .collect();
+        let mut findings = Vec::new();
+        for record in records {
+            for rule in rules.iter_mut() {
+                if let Some(message) = rule.check(record) {
+                    findings.push((rule.name().to_string(), message));
+                }
+            }
+        }
+        findings
+    }
+
+    #[test]
+    fn record_rules_report_each_failure() {
+        let records = [
+            record(1, Some("INV-1"), Some(120.0), Some("2026-10-01"), json!({"currency": "EUR", "vendor": "V-1"})),
+            record(2, Some("INV-1"), Some(-5.0), Some("2027-01-01"), json!({"currency": "usd", "vendor": "V-9"})),
+            record(3, Some("bad id"), None, Some("1899-12-31"), json!({"currency": "GBP"})),
+        ];
+        let findings = run(
+            json!([
+                {"type": "required", "fields": ["amount", "vendor"]},
+                {"type": "pattern", "field": "external_id", "pattern": "^INV-\\d+$", "severity": "warning"},
+                {"type": "range", "field": "amount", "min": 0},
+                {"type": "allowed_values", "field": "currency", "values": ["EUR", "USD"], "name": "currency"},
+                {"type": "unique"},
+                {"type": "reference", "field": "vendor"},
+                {"type": "date_sanity"}
+            ]),
+            &records,
+            Some(HashSet::from(["V-1".to_string()])),
+        );
+        let expected = [
+            ("range:amount", "amount -5 is outside 0..-"),
+            ("unique:external_id", "Duplicate external_id 'INV-1', first seen in row 1"),
+            ("reference:vendor", "vendor 'V-9' has no matching record"),
+            ("date_sanity:transaction_date", "transaction_date 2027-01-01 is too far in the future"),
+            ("required:amount,vendor", "Missing amount, vendor"),
+            ("pattern:external_id", "external_id 'bad id' doesn't match ^INV-\\d+$"),
+            ("currency", "currency 'GBP' is not an allowed value"),
+            ("date_sanity:transaction_date", "transaction_date 1899-12-31 is too far in the past"),
+        ];
+        let expected: Vec<(String, String)> =
+            expected.iter().map(|(rule, message)| (rule.to_string(), message.to_string())).collect();
+        assert_eq!(findings, expected);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap_or_else(|| panic!("bad date"));
+        let specs: Vec<RecordRuleSpec> = serde_json::from_value(json!([
+            {"type": "pattern", "field": "a", "pattern": "("},
+            {"type": "range", "field": "amount"},
+            {"type": "reference", "field": "vendor", "job_id": Uuid::nil()}
+        ]))
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert!(specs.iter().all(|spec| spec.build(today, None).is_err()));
+        assert!(serde_json::from_value::<RecordRuleSpec>(json!({"type": "telepathy"})).is_err());
+    }
+}
@@ -1,4 +1,20 @@
 //! Workflow service module
+//!
+//! CRUD for workflows, their instances and rules, plus the engine that runs
+//! them:
+//! - `condition` - conditions and templates evaluated against instance state
+//! - `definition` - the step graph stored in `Workflow.definition`, and rule actions
+//! - `engine` - runs instances, on events or on request, and records their transitions
+//! - `webhook` - the hosts and addresses webhook steps may call
+
+pub mod condition;
+pub mod definition;
+pub mod engine;
+pub mod webhook;
+
+pub use condition::Condition;
+pub use definition::{RuleAction, WorkflowDefinition};
+pub use engine::{InstanceStatus, WorkflowEngine, WorkflowEvent};
 
 use diesel::prelude::*;
 use std::sync::Arc;
@@ -8,7 +24,7 @@ use crate::database::Database;
 use crate::errors::{AppError, AppResult};
 use crate::models::schema::{workflow_instances, workflow_rules, workflows};
 use crate::models::{
-    NewWorkflow, NewWorkflowInstance, NewWorkflowRule, UpdateWorkflow, UpdateWorkflowInstance,
+    NewWorkflow, NewWorkflowInstance, NewWorkflowRule, UpdateWorkflow,
     UpdateWorkflowRule, Workflow, WorkflowInstance, WorkflowRule,
 };
 
@@ -22,13 +38,21 @@ impl WorkflowService {
         Self { db }
     }
 
-    pub async fn list_workflows(&self, page: i64, per_page: i64) -> AppResult<(Vec<Workflow>, i64)> {
+    /// Workflows of `project_ids`, or every workflow for `None`
+    pub async fn list_workflows(&self, project_ids: Option<&[Uuid]>, page: i64, per_page: i64) -> AppResult<(Vec<Workflow>, i64)> {
         let mut conn = self.db.get_connection()?;
         let offset = (page - 1) * per_page;
 
-        let total: i64 = workflows::table.count().get_result(&mut conn).map_err(AppError::Database)?;
+        let mut items_query = workflows::table.into_boxed();
+        let mut count_query = workflows::table.into_boxed();
+        if let Some(ids) = project_ids {
+            items_query = items_query.filter(workflows::project_id.eq_any(ids));
+            count_query = count_query.filter(workflows::project_id.eq_any(ids));
+        }
 
-        let items = workflows::table
+        let total: i64 = count_query.count().get_result(&mut conn).map_err(AppError::Database)?;
+
+        let items = items_query
             .order(workflows::created_at.desc())
             .limit(per_page)
             .offset(offset)
@@ -113,14 +137,6 @@ impl WorkflowService {
             })
     }
 
-    pub async fn update_instance(&self, instance_id: Uuid, update: UpdateWorkflowInstance) -> AppResult<WorkflowInstance> {
-        let mut conn = self.db.get_connection()?;
-        diesel::update(workflow_instances::table.find(instance_id))
-            .set(&update)
-            .get_result::<WorkflowInstance>(&mut conn)
-            .map_err(AppError::Database)
-    }
-
     pub async fn list_rules(&self, workflow_id: Uuid) -> AppResult<Vec<WorkflowRule>> {
         let mut conn = self.db.get_connection()?;
         workflow_rules::table
@@ -0,0 +1,340 @@
+//! Conditions over workflow state
+//!
+//! Trigger conditions, transition guards and `WorkflowRule.condition` share
+//! one JSON form, evaluated against a JSON document (an event, or an
+//! instance's state):
+//!
+//! - `{"field": "event.unmatched", "op": "gt", "value": 100}` compares the
+//!   value at a dotted path; ops are `eq`, `ne`, `gt`, `gte`, `lt`, `lte`,
+//!   `in`, `contains` and `exists`
+//! - `{"all": [...]}`, `{"any": [...]}` and `{"not": {...}}` combine them
+//! - `true` and `false` are constant
+//!
+//! Ordering ops compare numbers, or strings with strings; a missing field
+//! satisfies only `ne` and `exists: false`. Strings in templates can refer to
+//! the same paths as `{{event.job_id}}`.
+
+use serde_json::Value;
+
+use crate::errors::{AppError, AppResult};
+
+/// Comparison of a field with a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    Contains,
+    Exists,
+}
+
+impl Op {
+    fn parse(s: &str) -> AppResult<Self> {
+        Ok(match s {
+            "eq" => Op::Eq,
+            "ne" => Op::Ne,
+            "gt" => Op::Gt,
+            "gte" => Op::Gte,
+            "lt" => Op::Lt,
+            "lte" => Op::Lte,
+            "in" => Op::In,
+            "contains" => Op::Contains,
+            "exists" => Op::Exists,
+            _ => return Err(AppError::Validation(format!("Unknown condition op: {}", s))),
+        })
+    }
+}
+
+/// A parsed condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Const(bool),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    Compare { field: String, op: Op, value: Value },
+}
+
+impl Condition {
+    pub fn parse(value: &Value) -> AppResult<Self> {
+        let invalid = |msg: &str| AppError::Validation(format!("Invalid condition: {}", msg));
+        let object = match value {
+            Value::Bool(b) => return Ok(Condition::Const(*b)),
+            Value::Object(object) => object,
+            _ => return Err(invalid("expected an object or a boolean")),
+        };
+
+        let parse_list = |key: &str| -> AppResult<Vec<Condition>> {
+            object
+                .get(key)
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid(&format!("'{}' must be a list", key)))?
+                .iter()
+                .map(Condition::parse)
+                .collect()
+        };
+        if object.contains_key("all") {
+            return Ok(Condition::All(parse_list("all")?));
+        }
+        if object.contains_key("any") {
+            return Ok(Condition::Any(parse_list("any")?));
+        }
+        if let Some(inner) = object.get("not") {
+            return Ok(Condition::Not(Box::new(Condition::parse(inner)?)));
+        }
+
+        let field = object
+            .get("field")
+            .and_then(Value::as_str)
+            .filter(|field| !field.is_empty())
+            .ok_or_else(|| invalid("expected 'all', 'any', 'not' or a 'field'"))?;
+        let op = Op::parse(object.get("op").and_then(Value::as_str).unwrap_or("eq"))?;
+        let value = match (object.get("value"), op) {
+            (Some(value), _) => value.clone(),
+            (None, Op::Exists) => Value::Bool(true),
+            (None, _) => return Err(invalid(&format!("'{}' needs a 'value'", field))),
+        };
+        if op == Op::In && !value.is_array() {
+            return Err(invalid("'in' needs a list 'value'"));
+        }
+        Ok(Condition::Compare {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+
+    pub fn evaluate(&self, document: &Value) -> bool {
+        match self {
+            Condition::Const(b) => *b,
+            Condition::All(conditions) => conditions.iter().all(|c| c.evaluate(document)),
+            Condition::Any(conditions) => conditions.iter().any(|c| c.evaluate(document)),
+            Condition::Not(condition) => !condition.evaluate(document),
+            Condition::Compare { field, op, value } => compare(lookup(document, field), *op, value),
+        }
+    }
+}
+
+/// Parse and evaluate a condition in one go
+pub fn evaluate(condition: &Value, document: &Value) -> AppResult<bool> {
+    Ok(Condition::parse(condition)?.evaluate(document))
+}
+
+/// Value at a dotted path; numeric segments index lists
+pub fn lookup<'a>(document: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(document, |current, segment| match current {
+            Value::Object(object) => object.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn compare(actual: Option<&Value>, op: Op, expected: &Value) -> bool {
+    let Some(actual) = actual.filter(|v| !v.is_null()) else {
+        return match op {
+            Op::Ne => !expected.is_null(),
+            Op::Exists => expected == &Value::Bool(false),
+            Op::Eq => expected.is_null(),
+            _ => false,
+        };
+    };
+    match op {
+        Op::Eq => values_equal(actual, expected),
+        Op::Ne => !values_equal(actual, expected),
+        Op::Gt | Op::Gte | Op::Lt | Op::Lte => {
+            let ordering = match (actual, expected) {
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => match (number(actual), number(expected)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => None,
+                },
+            };
+            ordering.is_some_and(|ordering| match op {
+                Op::Gt => ordering.is_gt(),
+                Op::Gte => ordering.is_ge(),
+                Op::Lt => ordering.is_lt(),
+                _ => ordering.is_le(),
+            })
+        }
+        Op::In => expected
+            .as_array()
+            .is_some_and(|items| items.iter().any(|item| values_equal(actual, item))),
+        Op::Contains => match actual {
+            Value::String(s) => expected.as_str().is_some_and(|needle| s.contains(needle)),
+            Value::Array(items) => items.iter().any(|item| values_equal(item, expected)),
+            _ => false,
+        },
+        Op::Exists => expected != &Value::Bool(false),
+    }
+}
+
+/// Numbers are equal by value, so 100 matches 100.0
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) if a.is_finite() && b.is_finite() => (a - b).abs() < f64::EPSILON,
+        _ => a == b,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64()
+}
+
+/// Fill `{{path}}` placeholders in a string from `document`
+///
+/// A string that is just one placeholder becomes the value itself, so
+/// `"{{event.unmatched}}"` stays a number; elsewhere values are inserted as
+/// text and missing ones as nothing.
+pub fn render(template: &str, document: &Value) -> Value {
+    let trimmed = template.trim();
+    if let Some(path) = trimmed
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .filter(|path| !path.contains("{{") && !path.contains("}}"))
+    {
+        return lookup(document, path.trim())
+            .cloned()
+            .unwrap_or(Value::Null);
+    }
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+        match lookup(document, path) {
+            Some(Value::String(s)) => out.push_str(s),
+            Some(Value::Null) | None => {}
+            Some(value) => out.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Value::String(out)
+}
+
+/// Fill placeholders in every string of a JSON value
+pub fn render_value(value: &Value, document: &Value) -> Value {
+    match value {
+        Value::String(s) => render(s, document),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(item, document))
+                .collect(),
+        ),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), render_value(value, document)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn holds(condition: Value, document: &Value) -> bool {
+        evaluate(&condition, document).unwrap_or_else(|e| panic!("{}: {:?}", condition, e))
+    }
+
+    #[test]
+    fn test_conditions_over_event() {
+        let doc = json!({
+            "event": {"event": "reconciliation_job_finished", "status": "completed", "unmatched": 120},
+            "steps": {"review": {"output": {"approved": true}}},
+            "tags": ["month_end"],
+        });
+
+        assert!(holds(
+            json!({"field": "event.unmatched", "op": "gt", "value": 100}),
+            &doc
+        ));
+        assert!(!holds(
+            json!({"field": "event.unmatched", "op": "gt", "value": 120}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "event.unmatched", "op": "eq", "value": 120.0}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "event.status", "value": "completed"}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "event.status", "op": "in", "value": ["completed", "failed"]}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "tags", "op": "contains", "value": "month_end"}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "steps.review.output.approved", "value": true}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"all": [
+                {"field": "event.event", "value": "reconciliation_job_finished"},
+                {"not": {"field": "event.error", "op": "exists"}},
+                {"any": [false, {"field": "event.unmatched", "op": "gte", "value": 50}]}
+            ]}),
+            &doc
+        ));
+        // Missing fields only satisfy negative checks
+        assert!(!holds(
+            json!({"field": "event.missing", "op": "gt", "value": 0}),
+            &doc
+        ));
+        assert!(holds(
+            json!({"field": "event.missing", "op": "ne", "value": 0}),
+            &doc
+        ));
+    }
+
+    #[test]
+    fn test_invalid_conditions_are_rejected() {
+        for condition in [
+            json!("yes"),
+            json!({"field": "a", "op": "between", "value": 1}),
+            json!({"field": "a", "op": "gt"}),
+            json!({"field": "a", "op": "in", "value": 1}),
+            json!({"all": {"field": "a", "value": 1}}),
+            json!({"op": "eq", "value": 1}),
+        ] {
+            assert!(Condition::parse(&condition).is_err(), "{}", condition);
+        }
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let doc =
+            json!({"event": {"job_id": "j-1", "unmatched": 7}, "workflow": {"name": "Month end"}});
+
+        assert_eq!(render("{{event.unmatched}}", &doc), json!(7));
+        assert_eq!(
+            render(
+                "{{workflow.name}}: {{event.unmatched}} unmatched{{event.none}}",
+                &doc
+            ),
+            json!("Month end: 7 unmatched")
+        );
+        assert_eq!(
+            render_value(&json!({"job": "{{event.job_id}}", "n": 1}), &doc),
+            json!({"job": "j-1", "n": 1})
+        );
+    }
+}
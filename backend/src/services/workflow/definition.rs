@@ -0,0 +1,431 @@
+//! Workflow definitions
+//!
+//! `Workflow.definition` is a state machine over named steps:
+//!
+//! ```json
+//! {
+//!   "trigger": {
+//!     "event": "reconciliation_job_finished",
+//!     "condition": {"field": "event.unmatched", "op": "gt", "value": 100}
+//!   },
+//!   "start": "review",
+//!   "steps": [
+//!     {"id": "review", "type": "approval", "instructions": "Check the unmatched records",
+//!      "next": [{"when": {"field": "steps.review.output.approved", "value": true}, "to": "rerun"}]},
+//!     {"id": "rerun", "type": "reconciliation_job", "job_id": "{{event.job_id}}", "max_attempts": 2,
+//!      "next": [{"to": "end"}]}
+//!   ]
+//! }
+//! ```
+//!
+//! After a step completes, the first transition whose `when` holds against
+//! the instance's state is taken; a transition without `when` always is, and
+//! the target `end` (or no matching transition) completes the instance.
+//! String fields of steps are templates (see `condition::render`).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+use super::condition::Condition;
+use super::webhook::{WebhookPolicy, MAX_WEBHOOK_TIMEOUT_SECS};
+use crate::errors::{AppError, AppResult};
+
+/// Transition target that completes the instance
+pub const END: &str = "end";
+
+/// Events a workflow can be triggered by
+pub const TRIGGER_EVENTS: &[&str] = &["ingestion_completed", "reconciliation_job_finished"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowDefinition {
+    /// Start the workflow on this event; without it, instances are only
+    /// started through the API
+    #[serde(default)]
+    pub trigger: Option<Trigger>,
+    pub start: String,
+    pub steps: Vec<StepDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trigger {
+    pub event: String,
+    /// Evaluated against `{"event": {...}}`
+    #[serde(default)]
+    pub condition: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepDefinition {
+    pub id: String,
+    #[serde(flatten)]
+    pub action: StepAction,
+    #[serde(default)]
+    pub next: Vec<Transition>,
+    /// Runs of the step before the instance fails; retrying it starts over
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+}
+
+fn default_max_attempts() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transition {
+    #[serde(default)]
+    pub when: Option<Value>,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepAction {
+    /// Queue a reconciliation job and wait for it to finish
+    ReconciliationJob { job_id: String },
+    /// Wait for a project approver to approve or reject
+    Approval {
+        #[serde(default)]
+        instructions: Option<String>,
+        /// Step to go to on rejection; without it the instance is rejected
+        #[serde(default)]
+        on_reject: Option<String>,
+    },
+    /// Send an in-app notification to each recipient (user IDs)
+    Notification {
+        recipients: Vec<String>,
+        title: String,
+        message: String,
+        #[serde(default = "default_notification_type")]
+        notification_type: String,
+    },
+    /// Call an HTTP endpoint on an allowed host (see `webhook`); a non-2xx
+    /// response fails the step, and only its status is kept
+    Webhook {
+        url: String,
+        #[serde(default = "default_webhook_method")]
+        method: String,
+        #[serde(default)]
+        headers: serde_json::Map<String, Value>,
+        #[serde(default)]
+        body: Option<Value>,
+        #[serde(default = "default_webhook_timeout")]
+        timeout_secs: u64,
+    },
+}
+
+fn default_notification_type() -> String {
+    "workflow".to_string()
+}
+
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+fn default_webhook_timeout() -> u64 {
+    MAX_WEBHOOK_TIMEOUT_SECS
+}
+
+/// Scheme and host of a webhook URL, when neither is templated
+fn webhook_origin(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let origin = &url[..url.len() - rest.len() + authority_end];
+    (!origin.contains("{{") && authority_end > 0).then_some(origin)
+}
+
+impl StepAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StepAction::ReconciliationJob { .. } => "reconciliation_job",
+            StepAction::Approval { .. } => "approval",
+            StepAction::Notification { .. } => "notification",
+            StepAction::Webhook { .. } => "webhook",
+        }
+    }
+}
+
+impl WorkflowDefinition {
+    /// Parse and validate a stored definition
+    pub fn parse(value: &Value) -> AppResult<Self> {
+        let definition: WorkflowDefinition = serde_json::from_value(value.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid workflow definition: {}", e)))?;
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
+        let invalid = |msg: String| {
+            Err(AppError::Validation(format!(
+                "Invalid workflow definition: {}",
+                msg
+            )))
+        };
+        if self.steps.is_empty() {
+            return invalid("no steps".to_string());
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if step.id.is_empty() || step.id == END {
+                return invalid(format!("'{}' is not a valid step id", step.id));
+            }
+            if !ids.insert(step.id.as_str()) {
+                return invalid(format!("duplicate step '{}'", step.id));
+            }
+        }
+        if !ids.contains(self.start.as_str()) {
+            return invalid(format!("start step '{}' does not exist", self.start));
+        }
+
+        for step in &self.steps {
+            if step.max_attempts == 0 {
+                return invalid(format!(
+                    "step '{}' needs max_attempts of at least 1",
+                    step.id
+                ));
+            }
+            let targets = step
+                .next
+                .iter()
+                .map(|t| t.to.as_str())
+                .chain(match &step.action {
+                    StepAction::Approval { on_reject, .. } => on_reject.as_deref(),
+                    _ => None,
+                });
+            for target in targets {
+                if target != END && !ids.contains(target) {
+                    return invalid(format!(
+                        "step '{}' goes to unknown step '{}'",
+                        step.id, target
+                    ));
+                }
+            }
+            for transition in &step.next {
+                if let Some(when) = &transition.when {
+                    Condition::parse(when)?;
+                }
+            }
+            match &step.action {
+                StepAction::Webhook {
+                    url,
+                    method,
+                    timeout_secs,
+                    ..
+                } => {
+                    // Templates may fill in the path and query, never the host
+                    let Some(origin) = webhook_origin(url) else {
+                        return invalid(format!(
+                            "step '{}' needs an http(s) url with a literal host",
+                            step.id
+                        ));
+                    };
+                    if !(1..=MAX_WEBHOOK_TIMEOUT_SECS).contains(timeout_secs) {
+                        return invalid(format!(
+                            "step '{}' needs a timeout_secs between 1 and {}",
+                            step.id, MAX_WEBHOOK_TIMEOUT_SECS
+                        ));
+                    }
+                    if let Err(e) = WebhookPolicy::from_env().check_url(origin) {
+                        return invalid(format!("step '{}': {}", step.id, e));
+                    }
+                    if reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).is_err() {
+                        return invalid(format!(
+                            "step '{}' has an invalid method '{}'",
+                            step.id, method
+                        ));
+                    }
+                }
+                StepAction::Notification { recipients, .. } if recipients.is_empty() => {
+                    return invalid(format!("step '{}' has no recipients", step.id));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(trigger) = &self.trigger {
+            if !TRIGGER_EVENTS.contains(&trigger.event.as_str()) {
+                return invalid(format!(
+                    "unknown trigger event '{}'; expected one of {}",
+                    trigger.event,
+                    TRIGGER_EVENTS.join(", ")
+                ));
+            }
+            if let Some(condition) = &trigger.condition {
+                Condition::parse(condition)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn step(&self, id: &str) -> AppResult<&StepDefinition> {
+        self.steps
+            .iter()
+            .find(|step| step.id == id)
+            .ok_or_else(|| AppError::Validation(format!("Workflow has no step '{}'", id)))
+    }
+
+    /// Where to go after `step` completes, given the instance's state
+    pub fn next_step(&self, step: &StepDefinition, state: &Value) -> Option<String> {
+        step.next
+            .iter()
+            .find(|transition| {
+                transition
+                    .when
+                    .as_ref()
+                    .is_none_or(|when| Condition::parse(when).is_ok_and(|c| c.evaluate(state)))
+            })
+            .map(|transition| transition.to.clone())
+            .filter(|to| to != END)
+    }
+}
+
+/// What a `WorkflowRule.action` does when its condition holds
+///
+/// Active rules are evaluated against the instance's state after every
+/// completed step, highest priority first; `goto`, `complete` and `fail`
+/// replace the step's own transition, and the first of them wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Merge values into the instance's `vars`
+    Set {
+        values: serde_json::Map<String, Value>,
+    },
+    Goto {
+        step: String,
+    },
+    Complete,
+    Fail {
+        reason: String,
+    },
+}
+
+impl RuleAction {
+    pub fn parse(value: &Value) -> AppResult<Self> {
+        serde_json::from_value(value.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid rule action: {}", e)))
+    }
+
+    /// Whether this action decides where the instance goes next
+    pub fn is_transition(&self) -> bool {
+        !matches!(self, RuleAction::Set { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition() -> Value {
+        json!({
+            "trigger": {
+                "event": "reconciliation_job_finished",
+                "condition": {"field": "event.unmatched", "op": "gt", "value": 100}
+            },
+            "start": "review",
+            "steps": [
+                {"id": "review", "type": "approval", "on_reject": "notify",
+                 "next": [{"when": {"field": "steps.review.output.approved", "value": true}, "to": "rerun"},
+                          {"to": "end"}]},
+                {"id": "rerun", "type": "reconciliation_job", "job_id": "{{event.job_id}}", "max_attempts": 2,
+                 "next": [{"to": "notify"}]},
+                {"id": "notify", "type": "notification", "recipients": ["{{instance.started_by}}"],
+                 "title": "Done", "message": "{{workflow.name}} finished"}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_parse_and_walk_definition() {
+        let definition =
+            WorkflowDefinition::parse(&definition()).unwrap_or_else(|e| panic!("{:?}", e));
+        let review = definition
+            .step("review")
+            .unwrap_or_else(|e| panic!("{:?}", e));
+        assert_eq!(review.action.kind(), "approval");
+        assert_eq!(review.max_attempts, 1);
+
+        let approved = json!({"steps": {"review": {"output": {"approved": true}}}});
+        assert_eq!(
+            definition.next_step(review, &approved),
+            Some("rerun".to_string())
+        );
+        assert_eq!(definition.next_step(review, &json!({})), None);
+
+        let notify = definition
+            .step("notify")
+            .unwrap_or_else(|e| panic!("{:?}", e));
+        assert_eq!(definition.next_step(notify, &json!({})), None);
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        let mut missing_start = definition();
+        missing_start["start"] = json!("nope");
+        let mut unknown_target = definition();
+        unknown_target["steps"][1]["next"] = json!([{"to": "nope"}]);
+        let mut duplicate = definition();
+        duplicate["steps"][2]["id"] = json!("review");
+        let mut bad_trigger = definition();
+        bad_trigger["trigger"]["event"] = json!("file_uploaded");
+        let mut bad_guard = definition();
+        bad_guard["steps"][0]["next"][0]["when"] = json!({"field": "x", "op": "near", "value": 1});
+        let mut bad_type = definition();
+        bad_type["steps"][2]["type"] = json!("sms");
+        let mut templated_host = definition();
+        templated_host["steps"][2] = json!({"id": "notify", "type": "webhook", "url": "{{input.url}}"});
+        let mut long_timeout = definition();
+        long_timeout["steps"][2] = json!({"id": "notify", "type": "webhook",
+            "url": "https://hooks.example.com/{{event.job_id}}", "timeout_secs": 600});
+
+        for value in [
+            missing_start,
+            unknown_target,
+            duplicate,
+            bad_trigger,
+            bad_guard,
+            bad_type,
+            templated_host,
+            long_timeout,
+        ] {
+            assert!(WorkflowDefinition::parse(&value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_webhook_origin_must_be_literal() {
+        assert_eq!(
+            webhook_origin("https://hooks.example.com/jobs/{{event.job_id}}?x=1"),
+            Some("https://hooks.example.com")
+        );
+        assert_eq!(
+            webhook_origin("http://hooks.example.com:8080"),
+            Some("http://hooks.example.com:8080")
+        );
+        assert_eq!(webhook_origin("{{input.url}}"), None);
+        assert_eq!(webhook_origin("https://{{input.host}}/x"), None);
+        assert_eq!(webhook_origin("https://hooks.example.com{{input.path}}"), None);
+        assert_eq!(webhook_origin("https:///x"), None);
+        assert_eq!(webhook_origin("file:///etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_rule_actions() {
+        assert_eq!(
+            RuleAction::parse(&json!({"type": "goto", "step": "review"})).ok(),
+            Some(RuleAction::Goto {
+                step: "review".to_string()
+            })
+        );
+        assert!(RuleAction::parse(&json!({"type": "complete"})).is_ok_and(|a| a.is_transition()));
+        assert!(
+            RuleAction::parse(&json!({"type": "set", "values": {"escalated": true}}))
+                .is_ok_and(|a| !a.is_transition())
+        );
+        assert!(RuleAction::parse(&json!({"type": "explode"})).is_err());
+    }
+}
@@ -0,0 +1,1250 @@
+//! Workflow execution engine
+//!
+//! Runs a workflow's definition as a state machine over its instances.
+//! Instances move `pending` → `running` → `completed`, pausing in `waiting`
+//! on a reconciliation job or an approval, and ending in `failed`,
+//! `rejected` or `cancelled` otherwise. Every change is written together
+//! with a `workflow_transitions` row, and only when the instance is still in
+//! the status it was read in, so two replicas never run the same instance.
+//!
+//! A failed step that has attempts left waits (`retry:<step>`) until its
+//! `retry_at`, when the retry worker runs it again.
+//!
+//! An instance's `state` is
+//! `{"input": ..., "event": ..., "vars": {...}, "steps": {"<id>": {"status", "attempts", "output", "error", "retry_at"}}}`;
+//! conditions and templates see it with `instance` and `workflow` added.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::condition::{self, Condition};
+use super::definition::{RuleAction, StepAction, StepDefinition, WorkflowDefinition};
+use super::webhook::{WebhookPolicy, MAX_WEBHOOK_TIMEOUT_SECS};
+use crate::database::Database;
+use crate::errors::{AppError, AppResult};
+use crate::models::schema::{
+    reconciliation_jobs, workflow_instances, workflow_rules, workflow_transitions, workflows,
+};
+use crate::models::{
+    NewWorkflowInstance, NewWorkflowTransition, UpdateWorkflowInstance, Workflow, WorkflowInstance,
+    WorkflowRule, WorkflowTransition,
+};
+use crate::services::notification::NotificationService;
+use crate::services::reconciliation::queue::retry_delay;
+use crate::services::reconciliation::ReconciliationService;
+
+/// Steps one run executes before it stops, so a cycle in a definition
+/// can't spin forever; the instance fails and can be retried
+const MAX_STEPS_PER_RUN: usize = 100;
+
+/// How often the retry worker looks for steps due to run again
+const RETRY_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long a running instance goes without a change before it's taken to
+/// have been abandoned (its replica crashed) and may be retried; longer
+/// than a run of webhook steps can take
+const STALE_RUNNING_MINUTES: i64 = 30;
+
+/// Lifecycle of a workflow instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceStatus {
+    Pending,
+    Running,
+    /// Paused until what `waiting_for` names happens
+    Waiting,
+    Completed,
+    /// A step ran out of attempts; can be retried
+    Failed,
+    /// An approval step was rejected with nowhere to go
+    Rejected,
+    Cancelled,
+}
+
+impl InstanceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstanceStatus::Pending => "pending",
+            InstanceStatus::Running => "running",
+            InstanceStatus::Waiting => "waiting",
+            InstanceStatus::Completed => "completed",
+            InstanceStatus::Failed => "failed",
+            InstanceStatus::Rejected => "rejected",
+            InstanceStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            InstanceStatus::Completed | InstanceStatus::Rejected | InstanceStatus::Cancelled
+        )
+    }
+}
+
+impl std::str::FromStr for InstanceStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "pending" => InstanceStatus::Pending,
+            "running" => InstanceStatus::Running,
+            "waiting" => InstanceStatus::Waiting,
+            "completed" => InstanceStatus::Completed,
+            "failed" => InstanceStatus::Failed,
+            "rejected" => InstanceStatus::Rejected,
+            "cancelled" => InstanceStatus::Cancelled,
+            _ => {
+                return Err(AppError::Internal(format!(
+                    "Unknown workflow instance status: {}",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+/// Something that happened elsewhere that workflows can react to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WorkflowEvent {
+    IngestionCompleted {
+        ingestion_job_id: Uuid,
+        project_id: Uuid,
+        total_rows: usize,
+        imported_records: usize,
+        error_count: usize,
+        duplicate_rows: usize,
+    },
+    ReconciliationJobFinished {
+        job_id: Uuid,
+        project_id: Uuid,
+        status: String,
+        matched: i32,
+        unmatched: i32,
+    },
+}
+
+impl WorkflowEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkflowEvent::IngestionCompleted { .. } => "ingestion_completed",
+            WorkflowEvent::ReconciliationJobFinished { .. } => "reconciliation_job_finished",
+        }
+    }
+
+    pub fn project_id(&self) -> Uuid {
+        match self {
+            WorkflowEvent::IngestionCompleted { project_id, .. }
+            | WorkflowEvent::ReconciliationJobFinished { project_id, .. } => *project_id,
+        }
+    }
+
+    /// `waiting_for` of instances this event resumes
+    fn resumes(&self) -> Option<String> {
+        match self {
+            WorkflowEvent::ReconciliationJobFinished { job_id, .. } => {
+                Some(job_waiting_for(*job_id))
+            }
+            WorkflowEvent::IngestionCompleted { .. } => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+fn job_waiting_for(job_id: Uuid) -> String {
+    format!("job:{}", job_id)
+}
+
+fn approval_waiting_for(step: &str) -> String {
+    format!("approval:{}", step)
+}
+
+const RETRY_WAITING_PREFIX: &str = "retry:";
+
+fn retry_waiting_for(step: &str) -> String {
+    format!("{}{}", RETRY_WAITING_PREFIX, step)
+}
+
+/// Result of running one step
+enum StepOutcome {
+    Completed(Value),
+    Waiting(String),
+    /// Wait on a reconciliation job, which is queued once the wait is recorded
+    StartJob(Uuid),
+    Failed(String),
+}
+
+/// A change to an instance, persisted with its transition row
+struct Change {
+    status: InstanceStatus,
+    step: Option<String>,
+    state: Value,
+    waiting_for: Option<String>,
+    error: Option<String>,
+    reason: &'static str,
+    details: Value,
+}
+
+/// Runs workflow instances
+#[derive(Clone)]
+pub struct WorkflowEngine {
+    db: Arc<Database>,
+    webhooks: WebhookPolicy,
+}
+
+impl WorkflowEngine {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            webhooks: WebhookPolicy::from_env(),
+        }
+    }
+
+    /// Dispatch an event in the background, logging failures
+    ///
+    /// For callers that must not wait on, or fail because of, workflows.
+    pub fn spawn_dispatch(db: Arc<Database>, event: WorkflowEvent) {
+        tokio::spawn(async move {
+            let name = event.name();
+            if let Err(e) = WorkflowEngine::new(db).dispatch(event).await {
+                log::error!("Failed to dispatch workflow event {}: {}", name, e);
+            }
+        });
+    }
+
+    /// Run failed steps again once their backoff has passed; every replica
+    /// runs one, and the conditional status update keeps them from running
+    /// the same instance twice
+    pub fn spawn_retry_worker(db: Arc<Database>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let engine = WorkflowEngine::new(db);
+            let mut interval = tokio::time::interval(RETRY_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine.resume_due_retries().await {
+                    log::error!("Failed to resume workflow retries: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Run the steps whose retry is due
+    pub async fn resume_due_retries(&self) -> AppResult<Vec<WorkflowInstance>> {
+        let waiting = {
+            let mut conn = self.db.get_connection()?;
+            workflow_instances::table
+                .filter(workflow_instances::status.eq(InstanceStatus::Waiting.as_str()))
+                .filter(workflow_instances::waiting_for.like(format!("{}%", RETRY_WAITING_PREFIX)))
+                .load::<WorkflowInstance>(&mut conn)
+                .map_err(AppError::Database)?
+        };
+        let now = Utc::now();
+        let mut resumed = Vec::new();
+        for instance in waiting {
+            let step = instance.current_step.clone().unwrap_or_default();
+            let retry_at = instance.state["steps"][&step]["retry_at"]
+                .as_str()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok());
+            if retry_at.is_some_and(|at| at > now) {
+                continue;
+            }
+            let state = instance.state.clone();
+            let instance = match self.apply(
+                &instance,
+                Change {
+                    status: InstanceStatus::Running,
+                    step: Some(step.clone()),
+                    state,
+                    waiting_for: None,
+                    error: None,
+                    reason: "retry_due",
+                    details: json!({ "step": step }),
+                },
+                None,
+            ) {
+                Ok(instance) => instance,
+                // Another replica got to it first
+                Err(AppError::Conflict(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            match self.run(instance, None).await {
+                Ok(instance) => resumed.push(instance),
+                Err(e) => log::error!("Failed to retry workflow step '{}': {}", step, e),
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Resume instances waiting on an event, and start workflows it triggers
+    ///
+    /// A job that a workflow step queued only resumes that workflow; it
+    /// doesn't trigger others, so a workflow that reruns a job on
+    /// `reconciliation_job_finished` doesn't start itself again.
+    pub async fn dispatch(&self, event: WorkflowEvent) -> AppResult<Vec<WorkflowInstance>> {
+        let mut touched = Vec::new();
+
+        if let Some(waiting_for) = event.resumes() {
+            let waiting = {
+                let mut conn = self.db.get_connection()?;
+                workflow_instances::table
+                    .filter(workflow_instances::status.eq(InstanceStatus::Waiting.as_str()))
+                    .filter(workflow_instances::waiting_for.eq(&waiting_for))
+                    .load::<WorkflowInstance>(&mut conn)
+                    .map_err(AppError::Database)?
+            };
+            if !waiting.is_empty() {
+                for instance in waiting {
+                    match self.resume_on_event(instance, &event).await {
+                        Ok(instance) => touched.push(instance),
+                        Err(e) => {
+                            log::error!("Failed to resume workflow on {}: {}", waiting_for, e)
+                        }
+                    }
+                }
+                return Ok(touched);
+            }
+        }
+
+        let candidates = {
+            let mut conn = self.db.get_connection()?;
+            workflows::table
+                .filter(workflows::status.eq("active"))
+                .filter(
+                    workflows::project_id
+                        .is_null()
+                        .or(workflows::project_id.eq(event.project_id())),
+                )
+                .load::<Workflow>(&mut conn)
+                .map_err(AppError::Database)?
+        };
+        let document = json!({ "event": event.to_value() });
+        for workflow in candidates {
+            let definition = match WorkflowDefinition::parse(&workflow.definition) {
+                Ok(definition) => definition,
+                Err(e) => {
+                    log::warn!(
+                        "Skipping workflow {} with an invalid definition: {}",
+                        workflow.id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let Some(trigger) = &definition.trigger else {
+                continue;
+            };
+            if trigger.event != event.name() {
+                continue;
+            }
+            let matches = match &trigger.condition {
+                Some(condition) => Condition::parse(condition).is_ok_and(|c| c.evaluate(&document)),
+                None => true,
+            };
+            if !matches {
+                continue;
+            }
+            match self
+                .create_and_run(&workflow, &definition, None, Value::Null, event.to_value())
+                .await
+            {
+                Ok(instance) => touched.push(instance),
+                Err(e) => log::error!(
+                    "Failed to start workflow {} on {}: {}",
+                    workflow.id,
+                    event.name(),
+                    e
+                ),
+            }
+        }
+        Ok(touched)
+    }
+
+    /// Start an instance of an active workflow and run it until it waits or ends
+    pub async fn start(
+        &self,
+        workflow_id: Uuid,
+        started_by: Option<Uuid>,
+        input: Value,
+    ) -> AppResult<WorkflowInstance> {
+        let workflow = self.workflow(workflow_id)?;
+        if workflow.status != "active" {
+            return Err(AppError::Conflict(format!(
+                "Workflow {} is {}; only active workflows can be started",
+                workflow_id, workflow.status
+            )));
+        }
+        let definition = WorkflowDefinition::parse(&workflow.definition)?;
+        self.create_and_run(&workflow, &definition, started_by, input, Value::Null)
+            .await
+    }
+
+    /// Run a failed instance's failed step again, with a fresh set of attempts
+    ///
+    /// An instance left running by a replica that crashed mid-step can be
+    /// retried the same way once it has gone `STALE_RUNNING_MINUTES`
+    /// without a change.
+    pub async fn retry(&self, instance_id: Uuid, actor: Uuid) -> AppResult<WorkflowInstance> {
+        let instance = self.instance(instance_id)?;
+        let abandoned = instance.status == InstanceStatus::Running.as_str()
+            && instance.updated_at
+                < Utc::now() - chrono::Duration::minutes(STALE_RUNNING_MINUTES);
+        if instance.status != InstanceStatus::Failed.as_str() && !abandoned {
+            return Err(AppError::Conflict(format!(
+                "Only failed or abandoned instances can be retried; instance {} is {}",
+                instance_id, instance.status
+            )));
+        }
+        let step = instance.current_step.clone().ok_or_else(|| {
+            AppError::Conflict(format!("Instance {} has no step to retry", instance_id))
+        })?;
+
+        let mut state = instance.state.clone();
+        let step_state = step_state_mut(&mut state, &step);
+        step_state["status"] = json!("pending");
+        step_state["attempts"] = json!(0);
+        step_state["error"] = Value::Null;
+        let instance = self.apply(
+            &instance,
+            Change {
+                status: InstanceStatus::Running,
+                step: Some(step.clone()),
+                state,
+                waiting_for: None,
+                error: None,
+                reason: "retried",
+                details: json!({ "step": step }),
+            },
+            Some(actor),
+        )?;
+        self.run(instance, Some(actor)).await
+    }
+
+    /// Approve or reject the approval step an instance is waiting on
+    pub async fn decide(
+        &self,
+        instance_id: Uuid,
+        step_id: &str,
+        approver: Uuid,
+        approved: bool,
+        notes: Option<String>,
+    ) -> AppResult<WorkflowInstance> {
+        let instance = self.instance(instance_id)?;
+        if instance.status != InstanceStatus::Waiting.as_str()
+            || instance.waiting_for.as_deref() != Some(approval_waiting_for(step_id).as_str())
+        {
+            return Err(AppError::Conflict(format!(
+                "Instance {} is not waiting for approval of step '{}'",
+                instance_id, step_id
+            )));
+        }
+        let workflow = self.workflow(instance.workflow_id)?;
+        let definition = WorkflowDefinition::parse(&workflow.definition)?;
+        let step = definition.step(step_id)?;
+        let output = json!({
+            "approved": approved,
+            "approver": approver,
+            "notes": notes,
+            "decided_at": Utc::now(),
+        });
+
+        let instance = if approved {
+            self.complete_step(
+                &instance,
+                &workflow,
+                &definition,
+                step,
+                output,
+                Some(approver),
+                "approved",
+            )?
+        } else {
+            let mut state = instance.state.clone();
+            let step_state = step_state_mut(&mut state, step_id);
+            step_state["status"] = json!("rejected");
+            step_state["output"] = output;
+            let on_reject = match &step.action {
+                StepAction::Approval { on_reject, .. } => on_reject.clone(),
+                _ => None,
+            };
+            let change = match on_reject {
+                Some(next) => Change {
+                    status: InstanceStatus::Running,
+                    step: Some(next.clone()),
+                    state,
+                    waiting_for: None,
+                    error: None,
+                    reason: "rejected",
+                    details: json!({ "step": step_id, "next": next }),
+                },
+                None => Change {
+                    status: InstanceStatus::Rejected,
+                    step: Some(step_id.to_string()),
+                    state,
+                    waiting_for: None,
+                    error: None,
+                    reason: "rejected",
+                    details: json!({ "step": step_id }),
+                },
+            };
+            self.apply(&instance, change, Some(approver))?
+        };
+        self.run(instance, Some(approver)).await
+    }
+
+    /// Cancel an instance that hasn't finished
+    pub fn cancel(&self, instance_id: Uuid, actor: Option<Uuid>) -> AppResult<WorkflowInstance> {
+        let instance = self.instance(instance_id)?;
+        if instance.status.parse::<InstanceStatus>()?.is_finished() {
+            return Err(AppError::Conflict(format!(
+                "Instance {} is already {}",
+                instance_id, instance.status
+            )));
+        }
+        let state = instance.state.clone();
+        self.apply(
+            &instance,
+            Change {
+                status: InstanceStatus::Cancelled,
+                step: instance.current_step.clone(),
+                state,
+                waiting_for: None,
+                error: None,
+                reason: "cancelled",
+                details: json!({}),
+            },
+            actor,
+        )
+    }
+
+    /// An instance's transitions, oldest first
+    pub fn transitions(&self, instance_id: Uuid) -> AppResult<Vec<WorkflowTransition>> {
+        let mut conn = self.db.get_connection()?;
+        workflow_transitions::table
+            .filter(workflow_transitions::instance_id.eq(instance_id))
+            .order(workflow_transitions::created_at.asc())
+            .load::<WorkflowTransition>(&mut conn)
+            .map_err(AppError::Database)
+    }
+
+    async fn create_and_run(
+        &self,
+        workflow: &Workflow,
+        definition: &WorkflowDefinition,
+        started_by: Option<Uuid>,
+        input: Value,
+        event: Value,
+    ) -> AppResult<WorkflowInstance> {
+        let state = json!({ "input": input, "event": event, "vars": {}, "steps": {} });
+        let instance = {
+            let mut conn = self.db.get_connection()?;
+            diesel::insert_into(workflow_instances::table)
+                .values(&NewWorkflowInstance {
+                    workflow_id: workflow.id,
+                    status: InstanceStatus::Pending.as_str().to_string(),
+                    current_step: None,
+                    state: state.clone(),
+                    started_by,
+                    started_at: Some(Utc::now()),
+                })
+                .get_result::<WorkflowInstance>(&mut conn)
+                .map_err(AppError::Database)?
+        };
+        let instance = self.apply(
+            &instance,
+            Change {
+                status: InstanceStatus::Running,
+                step: Some(definition.start.clone()),
+                state,
+                waiting_for: None,
+                error: None,
+                reason: "started",
+                details: json!({ "trigger": event.get("event") }),
+            },
+            started_by,
+        )?;
+        self.run(instance, started_by).await
+    }
+
+    /// Continue an instance waiting on the job an event reports finished
+    async fn resume_on_event(
+        &self,
+        instance: WorkflowInstance,
+        event: &WorkflowEvent,
+    ) -> AppResult<WorkflowInstance> {
+        let workflow = self.workflow(instance.workflow_id)?;
+        let definition = WorkflowDefinition::parse(&workflow.definition)?;
+        let step_id = instance.current_step.clone().unwrap_or_default();
+        let step = definition.step(&step_id)?;
+
+        let instance = match event {
+            WorkflowEvent::ReconciliationJobFinished { job_id, status, .. }
+                if status != "completed" =>
+            {
+                let error = format!("Reconciliation job {} finished as {}", job_id, status);
+                self.fail_step(&instance, step, error, None)?
+            }
+            _ => self.complete_step(
+                &instance,
+                &workflow,
+                &definition,
+                step,
+                event.to_value(),
+                None,
+                "resumed",
+            )?,
+        };
+        self.run(instance, None).await
+    }
+
+    /// Run an instance's current step, and the ones after it, until it
+    /// waits or ends
+    async fn run(
+        &self,
+        mut instance: WorkflowInstance,
+        actor: Option<Uuid>,
+    ) -> AppResult<WorkflowInstance> {
+        let workflow = self.workflow(instance.workflow_id)?;
+        let definition = match WorkflowDefinition::parse(&workflow.definition) {
+            Ok(definition) => definition,
+            Err(e) => return self.fail(&instance, e.to_string(), actor),
+        };
+
+        for _ in 0..MAX_STEPS_PER_RUN {
+            if instance.status != InstanceStatus::Running.as_str() {
+                return Ok(instance);
+            }
+            let step_id = instance.current_step.clone().unwrap_or_default();
+            let step = match definition.step(&step_id) {
+                Ok(step) => step,
+                Err(e) => return self.fail(&instance, e.to_string(), actor),
+            };
+
+            let mut state = instance.state.clone();
+            let step_state = step_state_mut(&mut state, &step.id);
+            let attempts = step_state["attempts"].as_u64().unwrap_or(0) + 1;
+            step_state["attempts"] = json!(attempts);
+            step_state["status"] = json!("running");
+            instance.state = state;
+
+            let outcome = self.execute(&instance, &workflow, step).await;
+            instance = match outcome {
+                StepOutcome::Completed(output) => self.complete_step(
+                    &instance,
+                    &workflow,
+                    &definition,
+                    step,
+                    output,
+                    actor,
+                    "step_completed",
+                )?,
+                StepOutcome::Waiting(waiting_for) => {
+                    self.wait(&instance, step, waiting_for, actor)?
+                }
+                StepOutcome::StartJob(job_id) => {
+                    // Record the wait first, so the job's finish event
+                    // always finds the instance waiting on it
+                    let waiting = self.wait(&instance, step, job_waiting_for(job_id), actor)?;
+                    let service = ReconciliationService::new(self.db.as_ref().clone());
+                    match service.start_reconciliation_job(job_id).await {
+                        Ok(()) => waiting,
+                        Err(e) => self.fail_step(&waiting, step, e.to_string(), actor)?,
+                    }
+                }
+                StepOutcome::Failed(error) => self.fail_step(&instance, step, error, actor)?,
+            };
+        }
+
+        if instance.status == InstanceStatus::Running.as_str() {
+            let error = format!("Stopped after {} steps in one run", MAX_STEPS_PER_RUN);
+            return self.fail(&instance, error, actor);
+        }
+        Ok(instance)
+    }
+
+    /// Pause an instance on its current step until `waiting_for` happens
+    fn wait(
+        &self,
+        instance: &WorkflowInstance,
+        step: &StepDefinition,
+        waiting_for: String,
+        actor: Option<Uuid>,
+    ) -> AppResult<WorkflowInstance> {
+        let mut state = instance.state.clone();
+        step_state_mut(&mut state, &step.id)["status"] = json!("waiting");
+        self.apply(
+            instance,
+            Change {
+                status: InstanceStatus::Waiting,
+                step: Some(step.id.clone()),
+                state,
+                waiting_for: Some(waiting_for.clone()),
+                error: None,
+                reason: "waiting",
+                details: json!({ "step": step.id, "waiting_for": waiting_for }),
+            },
+            actor,
+        )
+    }
+
+    /// Record a step's failure; while it has attempts left it waits out a
+    /// backoff and runs again
+    fn fail_step(
+        &self,
+        instance: &WorkflowInstance,
+        step: &StepDefinition,
+        error: String,
+        actor: Option<Uuid>,
+    ) -> AppResult<WorkflowInstance> {
+        let mut state = instance.state.clone();
+        let step_state = step_state_mut(&mut state, &step.id);
+        let attempts = step_state["attempts"].as_u64().unwrap_or(1);
+        step_state["status"] = json!("failed");
+        step_state["error"] = json!(error);
+
+        let retrying = attempts < u64::from(step.max_attempts);
+        let retry_at = Utc::now() + retry_delay(i32::try_from(attempts).unwrap_or(i32::MAX));
+        step_state["retry_at"] = if retrying { json!(retry_at) } else { Value::Null };
+        self.apply(
+            instance,
+            Change {
+                status: if retrying {
+                    InstanceStatus::Waiting
+                } else {
+                    InstanceStatus::Failed
+                },
+                step: Some(step.id.clone()),
+                state,
+                waiting_for: retrying.then(|| retry_waiting_for(&step.id)),
+                error: (!retrying).then(|| format!("Step '{}' failed: {}", step.id, error)),
+                reason: if retrying {
+                    "step_retrying"
+                } else {
+                    "step_failed"
+                },
+                details: json!({
+                    "step": step.id,
+                    "attempts": attempts,
+                    "error": error,
+                    "retry_at": retrying.then_some(retry_at),
+                }),
+            },
+            actor,
+        )
+    }
+
+    /// Record a step's output, apply the workflow's rules and move on
+    #[allow(clippy::too_many_arguments)]
+    fn complete_step(
+        &self,
+        instance: &WorkflowInstance,
+        workflow: &Workflow,
+        definition: &WorkflowDefinition,
+        step: &StepDefinition,
+        output: Value,
+        actor: Option<Uuid>,
+        reason: &'static str,
+    ) -> AppResult<WorkflowInstance> {
+        let mut state = instance.state.clone();
+        let step_state = step_state_mut(&mut state, &step.id);
+        step_state["status"] = json!("completed");
+        step_state["output"] = output;
+        step_state["error"] = Value::Null;
+
+        let rules = self.rules(workflow.id)?;
+        let (decision, fired) = apply_rules(&rules, &mut state, |state| {
+            context(state, instance, workflow)
+        });
+        let document = context(&state, instance, workflow);
+
+        let (status, next, error) = match decision {
+            Some(RuleAction::Goto { step: target }) => {
+                if definition.step(&target).is_ok() {
+                    (InstanceStatus::Running, Some(target), None)
+                } else {
+                    let error = format!("Rule goes to unknown step '{}'", target);
+                    (InstanceStatus::Failed, Some(step.id.clone()), Some(error))
+                }
+            }
+            Some(RuleAction::Complete) => (InstanceStatus::Completed, Some(step.id.clone()), None),
+            Some(RuleAction::Fail { reason }) => {
+                (InstanceStatus::Failed, Some(step.id.clone()), Some(reason))
+            }
+            Some(RuleAction::Set { .. }) | None => match definition.next_step(step, &document) {
+                Some(next) => (InstanceStatus::Running, Some(next), None),
+                None => (InstanceStatus::Completed, Some(step.id.clone()), None),
+            },
+        };
+        self.apply(
+            instance,
+            Change {
+                status,
+                step: next,
+                state,
+                waiting_for: None,
+                error,
+                reason,
+                details: json!({ "step": step.id, "rules": fired }),
+            },
+            actor,
+        )
+    }
+
+    fn fail(
+        &self,
+        instance: &WorkflowInstance,
+        error: String,
+        actor: Option<Uuid>,
+    ) -> AppResult<WorkflowInstance> {
+        let state = instance.state.clone();
+        self.apply(
+            instance,
+            Change {
+                status: InstanceStatus::Failed,
+                step: instance.current_step.clone(),
+                state,
+                waiting_for: None,
+                error: Some(error.clone()),
+                reason: "failed",
+                details: json!({ "error": error }),
+            },
+            actor,
+        )
+    }
+
+    async fn execute(
+        &self,
+        instance: &WorkflowInstance,
+        workflow: &Workflow,
+        step: &StepDefinition,
+    ) -> StepOutcome {
+        let document = context(&instance.state, instance, workflow);
+        let result = match &step.action {
+            StepAction::ReconciliationJob { job_id } => self
+                .workflow_job(workflow, &condition::render(job_id, &document))
+                .map(StepOutcome::StartJob),
+            StepAction::Approval { .. } => Ok(StepOutcome::Waiting(approval_waiting_for(&step.id))),
+            StepAction::Notification {
+                recipients,
+                title,
+                message,
+                notification_type,
+            } => self
+                .notify(
+                    instance,
+                    step,
+                    &document,
+                    recipients,
+                    title,
+                    message,
+                    notification_type,
+                )
+                .await
+                .map(StepOutcome::Completed),
+            StepAction::Webhook {
+                url,
+                method,
+                headers,
+                body,
+                timeout_secs,
+            } => {
+                self.call_webhook(
+                    &document,
+                    url,
+                    method,
+                    headers,
+                    body.as_ref(),
+                    *timeout_secs,
+                )
+                .await
+            }
+        };
+        result.unwrap_or_else(|e| StepOutcome::Failed(e.to_string()))
+    }
+
+    /// The reconciliation job a step names, which must be in the workflow's project
+    fn workflow_job(&self, workflow: &Workflow, job_id: &Value) -> AppResult<Uuid> {
+        let job_id = job_id
+            .as_str()
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or_else(|| {
+                AppError::Validation(format!("'{}' is not a reconciliation job ID", job_id))
+            })?;
+        let project_id = {
+            let mut conn = self.db.get_connection()?;
+            reconciliation_jobs::table
+                .find(job_id)
+                .select(reconciliation_jobs::project_id)
+                .first::<Uuid>(&mut conn)
+                .optional()?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Reconciliation job {} not found", job_id))
+                })?
+        };
+        if workflow.project_id.is_some_and(|p| p != project_id) {
+            return Err(AppError::Forbidden(format!(
+                "Reconciliation job {} is not in the workflow's project",
+                job_id
+            )));
+        }
+        Ok(job_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        instance: &WorkflowInstance,
+        step: &StepDefinition,
+        document: &Value,
+        recipients: &[String],
+        title: &str,
+        message: &str,
+        notification_type: &str,
+    ) -> AppResult<Value> {
+        let title = render_text(title, document);
+        let message = render_text(message, document);
+        let metadata = json!({
+            "workflow_id": instance.workflow_id,
+            "instance_id": instance.id,
+            "step": step.id,
+        });
+
+        let notifications = NotificationService::new(Arc::clone(&self.db));
+        let mut notified = Vec::new();
+        for recipient in recipients {
+            let rendered = render_text(recipient, document);
+            let user_id = Uuid::parse_str(rendered.trim())
+                .map_err(|_| AppError::Validation(format!("'{}' is not a user ID", rendered)))?;
+            notifications
+                .create_notification(
+                    user_id,
+                    title.clone(),
+                    message.clone(),
+                    notification_type.to_string(),
+                    Some(metadata.clone()),
+                )
+                .await?;
+            notified.push(user_id);
+        }
+        Ok(json!({ "notified": notified }))
+    }
+
+    /// Call a webhook the policy allows, connecting only to the addresses
+    /// it checked; only the response status is kept, as instance state is
+    /// readable by anyone who can view the workflow
+    async fn call_webhook(
+        &self,
+        document: &Value,
+        url: &str,
+        method: &str,
+        headers: &serde_json::Map<String, Value>,
+        body: Option<&Value>,
+        timeout_secs: u64,
+    ) -> AppResult<StepOutcome> {
+        let url = render_text(url, document);
+        let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| AppError::Validation(format!("Invalid webhook method: {}", method)))?;
+        let target = self.webhooks.resolve(&url).await?;
+        let client = reqwest::Client::builder()
+            .resolve_to_addrs(&target.host, &target.addrs)
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(
+                timeout_secs.clamp(1, MAX_WEBHOOK_TIMEOUT_SECS),
+            ))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build webhook client: {}", e)))?;
+        let mut request = client.request(method, target.url);
+        for (name, value) in headers {
+            let value = match condition::render_value(value, document) {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            request = request.header(name.as_str(), value);
+        }
+        if let Some(body) = body {
+            request = request.json(&condition::render_value(body, document));
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                return Ok(StepOutcome::Failed(format!(
+                    "Webhook request failed: {}",
+                    e
+                )))
+            }
+        };
+        let status = response.status();
+        if !status.is_success() {
+            return Ok(StepOutcome::Failed(format!("Webhook returned {}", status)));
+        }
+        Ok(StepOutcome::Completed(json!({ "status": status.as_u16() })))
+    }
+
+    /// Write a change if the instance is still in the status it was read in
+    fn apply(
+        &self,
+        instance: &WorkflowInstance,
+        change: Change,
+        actor: Option<Uuid>,
+    ) -> AppResult<WorkflowInstance> {
+        let mut conn = self.db.get_connection()?;
+        conn.transaction::<_, AppError, _>(|tx| {
+            // Failed instances can still be retried, so they aren't finished
+            let finished = change.status.is_finished();
+            let update = UpdateWorkflowInstance {
+                status: Some(change.status.as_str().to_string()),
+                current_step: change.step.clone(),
+                state: Some(change.state),
+                completed_at: finished.then(Utc::now),
+                error_message: Some(change.error.clone()),
+                waiting_for: Some(change.waiting_for),
+            };
+            let updated = diesel::update(
+                workflow_instances::table
+                    .filter(workflow_instances::id.eq(instance.id))
+                    .filter(workflow_instances::status.eq(&instance.status)),
+            )
+            .set((&update, workflow_instances::updated_at.eq(Utc::now())))
+            .get_result::<WorkflowInstance>(tx)
+            .optional()?
+            .ok_or_else(|| {
+                AppError::Conflict(format!(
+                    "Workflow instance {} was changed concurrently",
+                    instance.id
+                ))
+            })?;
+
+            diesel::insert_into(workflow_transitions::table)
+                .values(&NewWorkflowTransition {
+                    instance_id: instance.id,
+                    from_step: instance.current_step.clone(),
+                    to_step: change.step,
+                    from_status: instance.status.clone(),
+                    to_status: change.status.as_str().to_string(),
+                    reason: change.reason.to_string(),
+                    details: change.details,
+                    actor_id: actor,
+                })
+                .execute(tx)?;
+            Ok(updated)
+        })
+    }
+
+    fn workflow(&self, workflow_id: Uuid) -> AppResult<Workflow> {
+        let mut conn = self.db.get_connection()?;
+        workflows::table
+            .find(workflow_id)
+            .first::<Workflow>(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Workflow {} not found", workflow_id)))
+    }
+
+    fn instance(&self, instance_id: Uuid) -> AppResult<WorkflowInstance> {
+        let mut conn = self.db.get_connection()?;
+        workflow_instances::table
+            .find(instance_id)
+            .first::<WorkflowInstance>(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Instance {} not found", instance_id)))
+    }
+
+    fn rules(&self, workflow_id: Uuid) -> AppResult<Vec<WorkflowRule>> {
+        let mut conn = self.db.get_connection()?;
+        workflow_rules::table
+            .filter(workflow_rules::workflow_id.eq(workflow_id))
+            .filter(workflow_rules::is_active.eq(true))
+            .order((
+                workflow_rules::priority.desc(),
+                workflow_rules::created_at.asc(),
+            ))
+            .load::<WorkflowRule>(&mut conn)
+            .map_err(AppError::Database)
+    }
+}
+
+/// State as conditions and templates see it
+fn context(state: &Value, instance: &WorkflowInstance, workflow: &Workflow) -> Value {
+    let mut document = state.clone();
+    if !document.is_object() {
+        document = json!({});
+    }
+    document["instance"] = json!({
+        "id": instance.id,
+        "workflow_id": instance.workflow_id,
+        "started_by": instance.started_by,
+    });
+    document["workflow"] = json!({
+        "id": workflow.id,
+        "name": workflow.name,
+        "project_id": workflow.project_id,
+    });
+    document
+}
+
+/// `state.steps.<id>`, created if missing
+fn step_state_mut<'a>(state: &'a mut Value, step_id: &str) -> &'a mut Value {
+    if !state.is_object() {
+        *state = json!({});
+    }
+    if !state["steps"].is_object() {
+        state["steps"] = json!({});
+    }
+    let step_state = &mut state["steps"][step_id];
+    if !step_state.is_object() {
+        *step_state = json!({ "status": "pending", "attempts": 0, "output": null, "error": null });
+    }
+    step_state
+}
+
+/// Evaluate rules in order against the state, merging `set` actions into
+/// `vars`; returns the first transition action and the IDs of the rules that
+/// fired. Invalid rules are skipped.
+fn apply_rules(
+    rules: &[WorkflowRule],
+    state: &mut Value,
+    context: impl Fn(&Value) -> Value,
+) -> (Option<RuleAction>, Vec<Uuid>) {
+    let mut fired = Vec::new();
+    for rule in rules {
+        let holds = match Condition::parse(&rule.condition) {
+            Ok(condition) => condition.evaluate(&context(state)),
+            Err(e) => {
+                log::warn!(
+                    "Skipping workflow rule {} with an invalid condition: {}",
+                    rule.id,
+                    e
+                );
+                continue;
+            }
+        };
+        if !holds {
+            continue;
+        }
+        let action = match RuleAction::parse(&rule.action) {
+            Ok(action) => action,
+            Err(e) => {
+                log::warn!(
+                    "Skipping workflow rule {} with an invalid action: {}",
+                    rule.id,
+                    e
+                );
+                continue;
+            }
+        };
+        fired.push(rule.id);
+        match action {
+            RuleAction::Set { values } => {
+                if !state["vars"].is_object() {
+                    state["vars"] = json!({});
+                }
+                if let Some(vars) = state["vars"].as_object_mut() {
+                    vars.extend(values);
+                }
+            }
+            action => return (Some(action), fired),
+        }
+    }
+    (None, fired)
+}
+
+fn render_text(template: &str, document: &Value) -> String {
+    match condition::render(template, document) {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(priority: i32, condition: Value, action: Value) -> WorkflowRule {
+        WorkflowRule {
+            id: Uuid::new_v4(),
+            workflow_id: Uuid::new_v4(),
+            name: format!("rule {}", priority),
+            condition,
+            action,
+            priority,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_rules_set_vars_then_first_transition_wins() {
+        let rules = vec![
+            rule(
+                30,
+                json!({"field": "event.unmatched", "op": "gt", "value": 100}),
+                json!({"type": "set", "values": {"escalate": true}}),
+            ),
+            rule(
+                20,
+                json!({"field": "vars.escalate", "value": true}),
+                json!({"type": "goto", "step": "escalate"}),
+            ),
+            rule(10, json!(true), json!({"type": "complete"})),
+            rule(
+                5,
+                json!({"field": "x", "op": "nope", "value": 1}),
+                json!({"type": "complete"}),
+            ),
+        ];
+        let mut state = json!({"event": {"unmatched": 250}, "vars": {}, "steps": {}});
+
+        let (decision, fired) = apply_rules(&rules, &mut state, Value::clone);
+        assert_eq!(
+            decision,
+            Some(RuleAction::Goto {
+                step: "escalate".to_string()
+            })
+        );
+        assert_eq!(fired, vec![rules[0].id, rules[1].id]);
+        assert_eq!(state["vars"]["escalate"], json!(true));
+
+        let mut quiet = json!({"event": {"unmatched": 3}, "vars": {}});
+        let (decision, fired) = apply_rules(&rules, &mut quiet, Value::clone);
+        assert_eq!(decision, Some(RuleAction::Complete));
+        assert_eq!(fired, vec![rules[2].id]);
+    }
+
+    #[test]
+    fn test_events_serialize_with_their_name() {
+        let job_id = Uuid::new_v4();
+        let event = WorkflowEvent::ReconciliationJobFinished {
+            job_id,
+            project_id: Uuid::new_v4(),
+            status: "completed".to_string(),
+            matched: 10,
+            unmatched: 120,
+        };
+        let value = event.to_value();
+        assert_eq!(value["event"], json!(event.name()));
+        assert_eq!(value["unmatched"], json!(120));
+        assert_eq!(event.resumes(), Some(format!("job:{}", job_id)));
+        assert!(condition::evaluate(
+            &json!({"field": "event.unmatched", "op": "gt", "value": 100}),
+            &json!({"event": value})
+        )
+        .unwrap_or_else(|e| panic!("{:?}", e)));
+    }
+
+    #[test]
+    fn test_step_state_is_created_on_demand() {
+        let mut state = json!({"input": null});
+        step_state_mut(&mut state, "review")["attempts"] = json!(1);
+        assert_eq!(state["steps"]["review"]["attempts"], json!(1));
+        assert_eq!(state["steps"]["review"]["status"], json!("pending"));
+    }
+}
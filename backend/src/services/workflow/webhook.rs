@@ -0,0 +1,224 @@
+//! Where webhook steps may send requests
+//!
+//! A webhook URL is a template that Manage users write and whose values can
+//! come from instance input, so the server must not be usable to reach
+//! internal services. Hosts must be in `WORKFLOW_WEBHOOK_ALLOWED_HOSTS`
+//! (comma-separated; `*.example.com` covers subdomains), and every address a
+//! host resolves to must be public. Without the variable, webhook steps fail.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::errors::{AppError, AppResult};
+
+/// Longest a webhook step may wait for a response; steps run inside the
+/// request that started or resumed the instance
+pub const MAX_WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Hosts webhook steps may call
+#[derive(Debug, Clone, Default)]
+pub struct WebhookPolicy {
+    allowed_hosts: Vec<String>,
+}
+
+/// A URL that passed the policy, with the addresses to connect to
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub url: url::Url,
+    pub host: String,
+    pub addrs: Vec<SocketAddr>,
+}
+
+impl WebhookPolicy {
+    pub fn new(allowed_hosts: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            allowed_hosts: allowed_hosts
+                .into_iter()
+                .map(|host| host.trim().trim_end_matches('.').to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("WORKFLOW_WEBHOOK_ALLOWED_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::to_string),
+        )
+    }
+
+    /// Check what can be checked of a URL without resolving it
+    pub fn check_url(&self, url: &str) -> AppResult<(url::Url, String)> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| AppError::Validation(format!("Invalid webhook URL '{}': {}", url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(AppError::Validation(format!(
+                "Webhook URL '{}' is not http(s)",
+                url
+            )));
+        }
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err(AppError::Validation(
+                "Webhook URLs can't carry credentials".to_string(),
+            ));
+        }
+        let host = match parsed.host() {
+            Some(url::Host::Domain(domain)) => domain.trim_end_matches('.').to_lowercase(),
+            Some(url::Host::Ipv4(ip)) => ip.to_string(),
+            Some(url::Host::Ipv6(ip)) => ip.to_string(),
+            None => {
+                return Err(AppError::Validation(format!(
+                    "Webhook URL '{}' has no host",
+                    url
+                )))
+            }
+        };
+        if !self.allows_host(&host) {
+            return Err(AppError::Forbidden(format!(
+                "Webhook host '{}' is not in WORKFLOW_WEBHOOK_ALLOWED_HOSTS",
+                host
+            )));
+        }
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if !is_public(ip) {
+                return Err(AppError::Forbidden(format!(
+                    "Webhook host {} is not a public address",
+                    ip
+                )));
+            }
+        }
+        Ok((parsed, host))
+    }
+
+    /// Check a URL and resolve its host, refusing it unless every address
+    /// is public
+    pub async fn resolve(&self, url: &str) -> AppResult<WebhookTarget> {
+        let (url, host) = self.check_url(url)?;
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| AppError::Validation(format!("Can't resolve webhook host '{}': {}", host, e)))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(AppError::Validation(format!(
+                "Webhook host '{}' has no addresses",
+                host
+            )));
+        }
+        if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+            return Err(AppError::Forbidden(format!(
+                "Webhook host '{}' resolves to {}, which is not a public address",
+                host,
+                addr.ip()
+            )));
+        }
+        Ok(WebhookTarget { url, host, addrs })
+    }
+
+    fn allows_host(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|allowed| {
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.')),
+                None => host == allowed,
+            }
+        })
+    }
+}
+
+/// Whether an address is reachable on the public internet, rather than
+/// loopback, private, link-local (cloud metadata) or otherwise special
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_v4(v4),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Shared address space (carrier-grade NAT)
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking, and reserved for future use
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local
+        || (first & 0xfe00) == 0xfc00
+        // Link-local
+        || (first & 0xffc0) == 0xfe80
+        // Documentation
+        || first == 0x2001 && ip.segments()[1] == 0x0db8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> WebhookPolicy {
+        WebhookPolicy::new(["hooks.example.com".to_string(), "*.partner.io".to_string()])
+    }
+
+    #[test]
+    fn test_only_allowed_hosts_pass() {
+        let policy = policy();
+        assert!(policy.check_url("https://hooks.example.com/x").is_ok());
+        assert!(policy.check_url("https://HOOKS.example.com./x").is_ok());
+        assert!(policy.check_url("https://api.partner.io/x").is_ok());
+        assert!(policy.check_url("https://partner.io/x").is_err());
+        assert!(policy.check_url("https://evilpartner.io/x").is_err());
+        assert!(policy.check_url("https://other.example.com/x").is_err());
+        assert!(policy.check_url("ftp://hooks.example.com/x").is_err());
+        assert!(policy.check_url("https://user:pw@hooks.example.com/x").is_err());
+        assert!(WebhookPolicy::default()
+            .check_url("https://hooks.example.com/x")
+            .is_err());
+    }
+
+    #[test]
+    fn test_internal_addresses_are_refused() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            let ip: IpAddr = ip.parse().unwrap_or_else(|e| panic!("{}: {}", ip, e));
+            assert!(!is_public(ip), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            let ip: IpAddr = ip.parse().unwrap_or_else(|e| panic!("{}: {}", ip, e));
+            assert!(is_public(ip), "{}", ip);
+        }
+
+        // Even when allowed by name, an internal literal address is refused
+        let policy = WebhookPolicy::new(["169.254.169.254".to_string()]);
+        assert!(policy.check_url("http://169.254.169.254/latest").is_err());
+    }
+}
@@ -11,46 +11,200 @@ use uuid::Uuid;
 // Import models and schema
 use crate::models::schema::projects;
 use crate::models::schema::users;
-use crate::models::{Project, User};
+use crate::models::schema::{project_members, project_teams, team_members, teams};
+use crate::models::User;
+use crate::services::auth::{api_keys, ProjectAction, ProjectRole};
+
+/// Roles a user holds in a project
+///
+/// Admins and the project's owner hold `Owner`; everyone else holds the roles
+/// of their active `project_members` rows and of each active team they are an
+/// active member of that is granted a role in the project. An empty list
+/// means no access, including when the project doesn't exist, is outside
+/// the projects the request's API key is restricted to, or is checked outside
+/// of a request (see `api_keys::current_grant`).
+pub fn project_roles(
+    db: &Database,
+    user_id: Uuid,
+    project_id: Uuid,
+) -> AppResult<Vec<ProjectRole>> {
+    let Some(grant) = api_keys::current_grant() else {
+        log::warn!(
+            "Refusing access to project {} checked outside of a request",
+            project_id
+        );
+        return Ok(Vec::new());
+    };
+    if !grant.allows_project(project_id) {
+        return Ok(Vec::new());
+    }
 
-/// Check if a user has permission to access a project
-pub fn check_project_permission(db: &Database, user_id: Uuid, project_id: Uuid) -> AppResult<()> {
     let mut conn = db.get_connection()?;
 
-    // Get the project
-    let project = projects::table
+    let owner_id = projects::table
         .filter(projects::id.eq(project_id))
-        .first::<Project>(&mut conn)
+        .select(projects::owner_id)
+        .first::<Uuid>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?;
+    let Some(owner_id) = owner_id else {
+        return Ok(Vec::new());
+    };
+
+    let status = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::status)
+        .first::<String>(&mut conn)
         .optional()
         .map_err(AppError::Database)?;
+    // Role is stored in the status field
+    if status.as_deref() == Some("admin") || owner_id == user_id {
+        return Ok(vec![ProjectRole::Owner]);
+    }
+
+    let mut stored: Vec<String> = project_members::table
+        .filter(project_members::project_id.eq(project_id))
+        .filter(project_members::user_id.eq(user_id))
+        .filter(project_members::is_active.eq(true))
+        .select(project_members::role)
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
 
-    if let Some(p) = project {
-        // Get the user to check their role
-        let user = users::table
-            .filter(users::id.eq(user_id))
-            .first::<User>(&mut conn)
-            .optional()
+    let team_ids: Vec<Uuid> = team_members::table
+        .inner_join(teams::table)
+        .filter(team_members::user_id.eq(user_id))
+        .filter(team_members::is_active.eq(true))
+        .filter(teams::is_active.eq(true))
+        .select(team_members::team_id)
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    if !team_ids.is_empty() {
+        let team_roles: Vec<String> = project_teams::table
+            .filter(project_teams::project_id.eq(project_id))
+            .filter(project_teams::team_id.eq_any(&team_ids))
+            .select(project_teams::role)
+            .load(&mut conn)
             .map_err(AppError::Database)?;
+        stored.extend(team_roles);
+    }
 
-        if let Some(u) = user {
-            // Check if user owns the project or is an admin (role stored in status field)
-            if u.status == "admin" || p.owner_id == user_id {
-                return Ok(());
-            }
+    let mut roles = Vec::new();
+    for role in stored {
+        match role.parse::<ProjectRole>() {
+            Ok(role) if !roles.contains(&role) => roles.push(role),
+            Ok(_) => {}
+            Err(_) => log::warn!(
+                "Ignoring unknown role '{}' of user {} in project {}",
+                role,
+                user_id,
+                project_id
+            ),
         }
     }
+    Ok(roles)
+}
+
+/// Check that a user holds a role in a project that allows `action`
+pub fn check_project_action(
+    db: &Database,
+    user_id: Uuid,
+    project_id: Uuid,
+    action: ProjectAction,
+) -> AppResult<()> {
+    let roles = project_roles(db, user_id, project_id)?;
+    if roles.iter().any(|role| role.allows(action)) {
+        return Ok(());
+    }
 
     // Record unauthorized access attempt
     crate::middleware::security::UNAUTHORIZED_ACCESS_ATTEMPTS
         .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    Err(AppError::Forbidden(
-        "Access denied to this project".to_string(),
-    ))
+    if roles.is_empty() {
+        Err(AppError::Forbidden(
+            "Access denied to this project".to_string(),
+        ))
+    } else {
+        Err(AppError::Forbidden(format!(
+            "Your role in this project does not allow you to {}",
+            action
+        )))
+    }
+}
+
+/// Check if a user has permission to access a project
+pub fn check_project_permission(db: &Database, user_id: Uuid, project_id: Uuid) -> AppResult<()> {
+    check_project_action(db, user_id, project_id, ProjectAction::View)
 }
 
 /// Check if a user can manage reconciliation jobs for a project
 pub fn check_job_permission(db: &Database, user_id: Uuid, project_id: Uuid) -> AppResult<()> {
-    check_project_permission(db, user_id, project_id)
+    check_project_action(db, user_id, project_id, ProjectAction::Prepare)
+}
+
+/// Projects a user holds any role in; `None` for admins, who can see all
+///
+/// Narrowed to the projects the request's API key is restricted to, if any.
+pub fn accessible_project_ids(db: &Database, user_id: Uuid) -> AppResult<Option<Vec<Uuid>>> {
+    let Some(grant) = api_keys::current_grant() else {
+        log::warn!("Refusing project access checked outside of a request");
+        return Ok(Some(Vec::new()));
+    };
+    let ids = user_project_ids(db, user_id)?;
+    Ok(match (grant.project_restriction(), ids) {
+        (None, ids) => ids,
+        (Some(restricted), None) => Some(restricted.to_vec()),
+        (Some(restricted), Some(ids)) => Some(
+            ids.into_iter()
+                .filter(|id| restricted.contains(id))
+                .collect(),
+        ),
+    })
+}
+
+fn user_project_ids(db: &Database, user_id: Uuid) -> AppResult<Option<Vec<Uuid>>> {
+    let mut conn = db.get_connection()?;
+
+    let status = users::table
+        .filter(users::id.eq(user_id))
+        .select(users::status)
+        .first::<String>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?;
+    if status.as_deref() == Some("admin") {
+        return Ok(None);
+    }
+
+    let mut ids: Vec<Uuid> = projects::table
+        .filter(projects::owner_id.eq(user_id))
+        .select(projects::id)
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    let member_of: Vec<Uuid> = project_members::table
+        .filter(project_members::user_id.eq(user_id))
+        .filter(project_members::is_active.eq(true))
+        .select(project_members::project_id)
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+    let through_teams: Vec<Uuid> = project_teams::table
+        .inner_join(teams::table)
+        .filter(teams::is_active.eq(true))
+        .filter(
+            project_teams::team_id.eq_any(
+                team_members::table
+                    .filter(team_members::user_id.eq(user_id))
+                    .filter(team_members::is_active.eq(true))
+                    .select(team_members::team_id),
+            ),
+        )
+        .select(project_teams::project_id)
+        .load(&mut conn)
+        .map_err(AppError::Database)?;
+
+    ids.extend(member_of);
+    ids.extend(through_teams);
+    ids.sort();
+    ids.dedup();
+    Ok(Some(ids))
 }
 
 /// Check if a user is an admin
@@ -90,8 +244,34 @@ pub fn get_project_id_from_job(db: &Database, job_id: Uuid) -> AppResult<Uuid> {
     Ok(project_id)
 }
 
+/// Get project_id from a reconciliation match (result) id
+pub fn get_project_id_from_match(db: &Database, match_id: Uuid) -> AppResult<Uuid> {
+    use crate::models::schema::{reconciliation_jobs, reconciliation_results};
+
+    let mut conn = db.get_connection()?;
+
+    reconciliation_results::table
+        .inner_join(reconciliation_jobs::table)
+        .filter(reconciliation_results::id.eq(match_id))
+        .select(reconciliation_jobs::project_id)
+        .first::<Uuid>(&mut conn)
+        .optional()
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound("Match not found".to_string()))
+}
+
 /// Check authorization for a reconciliation job by job_id
 pub fn check_job_access(db: &Database, user_id: Uuid, job_id: Uuid) -> AppResult<()> {
+    check_job_action(db, user_id, job_id, ProjectAction::View)
+}
+
+/// Check that a user may take `action` on a reconciliation job's project
+pub fn check_job_action(
+    db: &Database,
+    user_id: Uuid,
+    job_id: Uuid,
+    action: ProjectAction,
+) -> AppResult<()> {
     let project_id = get_project_id_from_job(db, job_id)?;
-    check_project_permission(db, user_id, project_id)
+    check_project_action(db, user_id, project_id, action)
 }
@@ -15,7 +15,9 @@ pub mod string;
 pub mod tiered_error_handling;
 
 pub use authorization::{
-    check_admin_permission, check_job_access, check_job_permission, check_project_permission,
+    accessible_project_ids, check_admin_permission, check_job_access, check_job_action,
+    check_job_permission, check_project_action, check_project_permission,
+    get_project_id_from_match, project_roles,
 };
 pub use error_handling::{AppError, AppResult, OptionExt, ResultExt};
 
@@ -329,12 +329,16 @@ mod reconciliation_critical_flows {
                 rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
                 weight: 1.0,
                 threshold: 0.8,
+                required: false,
+                options: Default::default(),
             },
             reconciliation_backend::services::reconciliation::types::MatchingRule {
                 field: "amount".to_string(),
                 rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
                 weight: 0.8,
                 threshold: 0.7,
+                required: false,
+                options: Default::default(),
             },
         ];
 
@@ -345,6 +349,10 @@ mod reconciliation_critical_flows {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.75,
         };
 
@@ -414,6 +422,10 @@ mod reconciliation_critical_flows {
             source_a_id: Uuid::new_v4(), // Non-existent source
             source_b_id: Uuid::new_v4(), // Non-existent source
             matching_rules: vec![],
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.75,
         };
 
@@ -494,8 +506,14 @@ mod e2e_critical_flow {
                     rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
                     weight: 1.0,
                     threshold: 0.8,
+                    required: false,
+                    options: Default::default(),
                 },
             ],
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.75,
         };
 
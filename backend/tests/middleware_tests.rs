@@ -1700,3 +1700,81 @@ async fn test_log_level_variants() {
     assert!(matches!(LogLevel::Warn, LogLevel::Warn));
     assert!(matches!(LogLevel::Error, LogLevel::Error));
 }
+
+/// Test Combined Security Middleware - JWT user outside production keeps their project roles
+#[tokio::test]
+async fn test_combined_security_jwt_user_passes_project_role_check() {
+    use reconciliation_backend::config::Config;
+    use reconciliation_backend::middleware::{CombinedSecurityMiddleware, ZeroTrustConfig};
+    use reconciliation_backend::services::project::ProjectService;
+    use reconciliation_backend::services::project_models::CreateProjectRequest;
+    use reconciliation_backend::services::user::{CreateUserRequest, UserService};
+    use reconciliation_backend::test_utils_export::database::setup_test_database;
+    use reconciliation_backend::utils::authorization::check_project_permission;
+
+    let (db, _) = setup_test_database().await;
+    let db = Arc::new(db);
+    let auth_service = AuthService::new("test_secret".to_string(), 3600);
+    let user = UserService::new(db.clone(), auth_service.clone())
+        .create_user(CreateUserRequest {
+            email: format!("jwt_{}@example.com", uuid::Uuid::new_v4()),
+            password: "TestPassword123!".to_string(),
+            first_name: "Jwt".to_string(),
+            last_name: "User".to_string(),
+            role: Some("user".to_string()),
+        })
+        .await
+        .unwrap();
+    let project = ProjectService::new((*db).clone())
+        .create_project(CreateProjectRequest {
+            name: "JWT Grant Project".to_string(),
+            description: None,
+            owner_id: user.id,
+            status: None,
+            settings: None,
+        })
+        .await
+        .unwrap();
+    let user = UserService::new(db.clone(), auth_service.clone())
+        .get_user_by_id_raw(user.id)
+        .await
+        .unwrap();
+    let token = auth_service.generate_token(&user).unwrap();
+
+    let zero_trust_config = ZeroTrustConfig {
+        require_identity_verification: false,
+        require_mtls: false,
+        enforce_least_privilege: false,
+        network_segmentation: false,
+    };
+    let (user_id, project_id) = (user.id, project.id);
+    let app = test::init_service(
+        App::new()
+            .wrap(CombinedSecurityMiddleware::new(
+                SecurityHeadersConfig::default(),
+                zero_trust_config,
+                Arc::new(Config::from_env().expect("Failed to load config")),
+            ))
+            .route(
+                "/api/v2/projects/check",
+                web::get().to(move || {
+                    let db = db.clone();
+                    async move {
+                        match check_project_permission(&db, user_id, project_id) {
+                            Ok(()) => HttpResponse::Ok().finish(),
+                            Err(_) => HttpResponse::Forbidden().finish(),
+                        }
+                    }
+                }),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v2/projects/check")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+}
@@ -72,3 +72,109 @@ async fn test_get_reconciliation_job_no_auth() {
     assert!(resp.status().is_client_error());
 }
 
+
+#[tokio::test]
+async fn test_update_and_delete_job_rule_are_audited_under_the_rule() {
+    use actix_web::HttpMessage;
+    use diesel::prelude::*;
+    use reconciliation_backend::models::schema::reconciliation_jobs;
+    use reconciliation_backend::models::NewReconciliationJob;
+    use reconciliation_backend::services::audit::{AuditQuery, AuditTrail};
+    use reconciliation_backend::services::auth::{api_keys, AuthService, ProjectGrant};
+    use reconciliation_backend::services::project::ProjectService;
+    use reconciliation_backend::services::project_models::CreateProjectRequest;
+    use reconciliation_backend::services::user::{CreateUserRequest, UserService};
+
+    let (db, _) = setup_test_database().await;
+    let db = Arc::new(db);
+    let auth_service = AuthService::new("test_secret".to_string(), 3600);
+    let user = UserService::new(db.clone(), auth_service)
+        .create_user(CreateUserRequest {
+            email: format!("rules_{}@example.com", Uuid::new_v4()),
+            password: "TestPassword123!".to_string(),
+            first_name: "Rule".to_string(),
+            last_name: "Editor".to_string(),
+            role: Some("user".to_string()),
+        })
+        .await
+        .unwrap();
+    let project = ProjectService::new((*db).clone())
+        .create_project(CreateProjectRequest {
+            name: "Rule Audit Project".to_string(),
+            description: None,
+            owner_id: user.id,
+            status: None,
+            settings: None,
+        })
+        .await
+        .unwrap();
+    let mut conn = db.get_connection().unwrap_or_else(|e| panic!("{:?}", e));
+    let job_id: Uuid = diesel::insert_into(reconciliation_jobs::table)
+        .values(&NewReconciliationJob {
+            project_id: project.id,
+            name: "Rule audit job".to_string(),
+            description: None,
+            status: "pending".to_string(),
+            started_at: None,
+            completed_at: None,
+            created_by: user.id,
+            settings: Some(json!({
+                "matching_rules": [
+                    { "field": "amount", "rule_type": "exact", "weight": 1.0, "threshold": 0.8 }
+                ]
+            })),
+            confidence_threshold: None,
+            progress: None,
+            total_records: None,
+            processed_records: None,
+            matched_records: None,
+            unmatched_records: None,
+            processing_time_ms: None,
+        })
+        .returning(reconciliation_jobs::id)
+        .get_result(&mut conn)
+        .unwrap_or_else(|e| panic!("{:?}", e));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new((*db).clone()))
+            .service(web::scope("/reconciliation").configure(reconciliation::configure_routes))
+    ).await;
+    let rule_uri = format!("/reconciliation/rules/{}-0", job_id);
+
+    // Edit the rule, then delete it, as its owner
+    let req = test::TestRequest::put()
+        .uri(&rule_uri)
+        .set_json(json!({ "threshold": 0.9 }))
+        .to_request();
+    req.extensions_mut().insert(user.id);
+    let resp = api_keys::with_grant(ProjectGrant::Unrestricted, test::call_service(&app, req)).await;
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["threshold"], json!(0.9));
+    let rule_id = body["data"]["id"]
+        .as_str()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .expect("an edited rule keeps an id");
+
+    let req = test::TestRequest::delete().uri(&rule_uri).to_request();
+    req.extensions_mut().insert(user.id);
+    let resp = api_keys::with_grant(ProjectGrant::Unrestricted, test::call_service(&app, req)).await;
+    assert_eq!(resp.status(), 204);
+
+    // Both changes are chained under the rule's id
+    let trail = AuditTrail::new(db.clone());
+    let entries = trail
+        .list(&AuditQuery {
+            resource_type: Some("matching_rule".to_string()),
+            resource_id: Some(rule_id),
+            ..Default::default()
+        })
+        .unwrap_or_else(|e| panic!("{:?}", e));
+    let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(actions, vec!["rule.delete", "rule.update"]);
+    assert!(entries.iter().all(|entry| entry.sequence.is_some() && entry.actor_id == Some(user.id)));
+    assert_eq!(entries[0].new_values, None);
+    assert_eq!(entries[1].new_values.as_ref().map(|rule| &rule["threshold"]), Some(&json!(0.9)));
+    assert!(trail.verify().unwrap_or_else(|e| panic!("{:?}", e)).valid);
+}
@@ -64,6 +64,8 @@ mod reconciliation_job_tests {
             rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let job_request = CreateReconciliationJobRequest {
@@ -73,6 +75,10 @@ mod reconciliation_job_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -128,6 +134,8 @@ mod reconciliation_job_tests {
             rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 1.0,
+            required: false,
+            options: Default::default(),
         }];
 
         let job_request = CreateReconciliationJobRequest {
@@ -137,6 +145,10 @@ mod reconciliation_job_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 1.0,
         };
 
@@ -211,6 +223,8 @@ mod reconciliation_job_tests {
             rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let job_request = CreateReconciliationJobRequest {
@@ -220,6 +234,10 @@ mod reconciliation_job_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -433,6 +451,8 @@ mod reconciliation_job_tests {
             rule_type: reconciliation_backend::services::reconciliation::types::MatchingRuleType::Fuzzy,
             weight: 1.0,
             threshold: 0.75,
+            required: false,
+            options: Default::default(),
         }];
 
         let job_request = CreateReconciliationJobRequest {
@@ -442,6 +462,10 @@ mod reconciliation_job_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.75,
         };
 
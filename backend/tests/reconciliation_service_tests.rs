@@ -73,6 +73,8 @@ mod reconciliation_service_tests {
             rule_type: MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let request = CreateReconciliationJobRequest {
@@ -82,6 +84,10 @@ mod reconciliation_service_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -272,6 +278,8 @@ mod reconciliation_service_tests {
             rule_type: MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let create_request = CreateReconciliationJobRequest {
@@ -281,6 +289,10 @@ mod reconciliation_service_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -321,6 +333,8 @@ mod reconciliation_service_tests {
             rule_type: MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let create_request = CreateReconciliationJobRequest {
@@ -330,6 +344,10 @@ mod reconciliation_service_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -514,6 +532,8 @@ mod reconciliation_service_tests {
             rule_type: MatchingRuleType::Exact,
             weight: 1.0,
             threshold: 0.8,
+            required: false,
+            options: Default::default(),
         }];
 
         let create_request = CreateReconciliationJobRequest {
@@ -523,6 +543,10 @@ mod reconciliation_service_tests {
             source_a_id,
             source_b_id,
             matching_rules,
+            blocking: Default::default(),
+            assignment: Default::default(),
+            aggregation: None,
+            parallelism: None,
             confidence_threshold: 0.8,
         };
 
@@ -644,5 +668,184 @@ mod reconciliation_service_tests {
         let result = reconciliation_service.get_active_jobs().await;
         assert!(result.is_ok());
     }
-}
 
+    #[tokio::test]
+    async fn test_data_sources_of_one_project_load_their_own_records() {
+        use diesel::prelude::*;
+        use reconciliation_backend::models::schema::{data_sources, ingestion_jobs, reconciliation_records};
+        use reconciliation_backend::models::{DataSource, NewDataSource, NewIngestionJob, NewReconciliationRecord};
+        use reconciliation_backend::services::reconciliation::processing::{
+            count_records_in_data_source, load_match_records_from_data_source, load_records_from_data_source,
+        };
+
+        let (db, _) = setup_test_database().await;
+        let db_arc = Arc::new(db);
+        let auth_service = AuthService::new("test_secret".to_string(), 3600);
+        let (user_id, project_id, _, _) = setup_test_fixtures(db_arc.clone(), auth_service).await;
+        let mut conn = db_arc.get_connection().unwrap_or_else(|e| panic!("{:?}", e));
+
+        // Two sources in the project, each fed by its own ingestion job
+        let mut sources = Vec::new();
+        for (name, count) in [("Bank", 3), ("Ledger", 2)] {
+            let source: DataSource = diesel::insert_into(data_sources::table)
+                .values(&NewDataSource {
+                    project_id,
+                    name: name.to_string(),
+                    description: None,
+                    source_type: "csv".to_string(),
+                    connection_config: None,
+                    file_path: None,
+                    file_size: None,
+                    file_hash: None,
+                    record_count: None,
+                    schema: None,
+                    status: "uploaded".to_string(),
+                    uploaded_at: None,
+                    processed_at: None,
+                    is_active: true,
+                })
+                .returning(DataSource::as_returning())
+                .get_result(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            let job_id: Uuid = diesel::insert_into(ingestion_jobs::table)
+                .values(&NewIngestionJob {
+                    project_id,
+                    job_name: format!("{} import", name),
+                    source_type: "csv".to_string(),
+                    source_config: serde_json::json!({"data_source_id": source.id}),
+                    status: "completed".to_string(),
+                    progress: 100,
+                    metadata: serde_json::json!({}),
+                    created_by: user_id,
+                })
+                .returning(ingestion_jobs::id)
+                .get_result(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            let records: Vec<NewReconciliationRecord> = (0..count)
+                .map(|i| NewReconciliationRecord {
+                    project_id,
+                    ingestion_job_id: job_id,
+                    external_id: Some(format!("{}-{}", name, i)),
+                    status: "pending".to_string(),
+                    amount: Some(100.0),
+                    transaction_date: None,
+                    description: None,
+                    source_data: serde_json::json!({"source": name}),
+                    matching_results: serde_json::json!({}),
+                    confidence: None,
+                    audit_trail: serde_json::json!([]),
+                })
+                .collect();
+            diesel::insert_into(reconciliation_records::table)
+                .values(&records)
+                .execute(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            sources.push((name, count, source));
+        }
+
+        for (name, count, source) in &sources {
+            let counted =
+                count_records_in_data_source(&db_arc, source).unwrap_or_else(|e| panic!("{:?}", e));
+            assert_eq!(counted, *count, "{}", name);
+
+            let paged = load_match_records_from_data_source(&db_arc, source)
+                .await
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            assert_eq!(paged.len(), *count, "{}", name);
+
+            let loaded = load_records_from_data_source(&db_arc, source)
+                .await
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            assert_eq!(loaded.len(), *count, "{}", name);
+            assert!(
+                loaded.iter().all(|record| record.source_data["source"] == *name),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_source_no_job_names_reads_untagged_records() {
+        use diesel::prelude::*;
+        use reconciliation_backend::models::schema::{data_sources, ingestion_jobs, reconciliation_records};
+        use reconciliation_backend::models::{DataSource, NewDataSource, NewIngestionJob, NewReconciliationRecord};
+        use reconciliation_backend::services::reconciliation::processing::count_records_in_data_source;
+
+        let (db, _) = setup_test_database().await;
+        let db_arc = Arc::new(db);
+        let auth_service = AuthService::new("test_secret".to_string(), 3600);
+        let (user_id, project_id, _, _) = setup_test_fixtures(db_arc.clone(), auth_service).await;
+        let mut conn = db_arc.get_connection().unwrap_or_else(|e| panic!("{:?}", e));
+
+        let mut sources = Vec::new();
+        for name in ["Legacy", "Tagged"] {
+            let source: DataSource = diesel::insert_into(data_sources::table)
+                .values(&NewDataSource {
+                    project_id,
+                    name: name.to_string(),
+                    description: None,
+                    source_type: "csv".to_string(),
+                    connection_config: None,
+                    file_path: None,
+                    file_size: None,
+                    file_hash: None,
+                    record_count: None,
+                    schema: None,
+                    status: "uploaded".to_string(),
+                    uploaded_at: None,
+                    processed_at: None,
+                    is_active: true,
+                })
+                .returning(DataSource::as_returning())
+                .get_result(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            sources.push(source);
+        }
+
+        // An import from before jobs named their source, and one that names "Tagged"
+        for (source_config, count) in [
+            (serde_json::json!({"file_id": Uuid::new_v4()}), 4),
+            (serde_json::json!({"data_source_id": sources[1].id}), 2),
+        ] {
+            let job_id: Uuid = diesel::insert_into(ingestion_jobs::table)
+                .values(&NewIngestionJob {
+                    project_id,
+                    job_name: "import".to_string(),
+                    source_type: "csv".to_string(),
+                    source_config,
+                    status: "completed".to_string(),
+                    progress: 100,
+                    metadata: serde_json::json!({}),
+                    created_by: user_id,
+                })
+                .returning(ingestion_jobs::id)
+                .get_result(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+            let records: Vec<NewReconciliationRecord> = (0..count)
+                .map(|i| NewReconciliationRecord {
+                    project_id,
+                    ingestion_job_id: job_id,
+                    external_id: Some(format!("{}-{}", job_id, i)),
+                    status: "pending".to_string(),
+                    amount: Some(100.0),
+                    transaction_date: None,
+                    description: None,
+                    source_data: serde_json::json!({}),
+                    matching_results: serde_json::json!({}),
+                    confidence: None,
+                    audit_trail: serde_json::json!([]),
+                })
+                .collect();
+            diesel::insert_into(reconciliation_records::table)
+                .values(&records)
+                .execute(&mut conn)
+                .unwrap_or_else(|e| panic!("{:?}", e));
+        }
+
+        let legacy = count_records_in_data_source(&db_arc, &sources[0]).unwrap_or_else(|e| panic!("{:?}", e));
+        assert_eq!(legacy, 4);
+        let tagged = count_records_in_data_source(&db_arc, &sources[1]).unwrap_or_else(|e| panic!("{:?}", e));
+        assert_eq!(tagged, 2);
+    }
+}